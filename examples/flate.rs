@@ -67,7 +67,8 @@ fn main() {
         Box::new(io::stdin())
     } else {
         Box::new(
-            fs::File::open(input_filename).expect(&format!("Can't open file: {}", input_filename)),
+            fs::File::open(input_filename)
+                .unwrap_or_else(|_| panic!("Can't open file: {}", input_filename)),
         )
     };
     let mut input = io::BufReader::new(input);
@@ -80,7 +81,7 @@ fn main() {
     } else {
         Box::new(
             fs::File::create(output_filename)
-                .expect(&format!("Can't create file: {}", output_filename)),
+                .unwrap_or_else(|_| panic!("Can't create file: {}", output_filename)),
         )
     };
     let mut output = io::BufWriter::new(output);
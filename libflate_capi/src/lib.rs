@@ -0,0 +1,799 @@
+//! A zlib compatible C API on top of [libflate](https://docs.rs/libflate).
+//!
+//! This crate is built as a shared (`cdylib`) and a static (`staticlib`) library
+//! exporting a subset of the zlib API:
+//!
+//! - Utility functions: `compress`, `compress2`, `uncompress` and `zlibVersion`
+//! - Streaming compression: `deflateInit_`, `deflate` and `deflateEnd`
+//! - Streaming decompression: `inflateInit_`, `inflate` and `inflateEnd`
+//!
+//! The `deflateInit` and `inflateInit` macros of the standard `zlib.h` expand to
+//! `deflateInit_` and `inflateInit_` respectively, so existing C code can be linked
+//! against this library without modification.
+//!
+//! # NOTICE
+//!
+//! - Compression levels `1` to `9` are all mapped to the default compression of libflate,
+//!   and level `0` produces non-compressed blocks.
+//! - `Z_PARTIAL_FLUSH` and `Z_FULL_FLUSH` behave as `Z_SYNC_FLUSH`. `Z_BLOCK` and `Z_TREES` are not supported by `deflate`.
+//! - The `zalloc`, `zfree` and `opaque` fields of `z_stream` are ignored; the Rust global allocator is always used.
+#![warn(missing_docs)]
+#![allow(non_camel_case_types, non_snake_case)]
+use libflate::checksum::Adler32;
+use libflate::push::{Decoder, Encoder, Flush, Format, Status};
+use libflate::zlib::EncodeOptions;
+use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
+use std::{mem, ptr, slice};
+
+/// No error.
+pub const Z_OK: c_int = 0;
+/// The end of the stream has been reached.
+pub const Z_STREAM_END: c_int = 1;
+/// A preset dictionary is needed (never returned by this library).
+pub const Z_NEED_DICT: c_int = 2;
+/// A file system error (never returned by this library).
+pub const Z_ERRNO: c_int = -1;
+/// The stream state is inconsistent or a parameter is invalid.
+pub const Z_STREAM_ERROR: c_int = -2;
+/// The input data is corrupted or incomplete.
+pub const Z_DATA_ERROR: c_int = -3;
+/// Not enough memory (never returned by this library).
+pub const Z_MEM_ERROR: c_int = -4;
+/// No progress is possible or the output buffer is too small.
+pub const Z_BUF_ERROR: c_int = -5;
+/// The zlib version expected by the caller is incompatible.
+pub const Z_VERSION_ERROR: c_int = -6;
+
+/// Data may be buffered internally.
+pub const Z_NO_FLUSH: c_int = 0;
+/// Treated as `Z_SYNC_FLUSH`.
+pub const Z_PARTIAL_FLUSH: c_int = 1;
+/// All pending output is flushed and aligned to a byte boundary.
+pub const Z_SYNC_FLUSH: c_int = 2;
+/// Treated as `Z_SYNC_FLUSH`.
+pub const Z_FULL_FLUSH: c_int = 3;
+/// The stream is terminated.
+pub const Z_FINISH: c_int = 4;
+
+/// The default compression level.
+pub const Z_DEFAULT_COMPRESSION: c_int = -1;
+
+const ZLIB_VERSION: &[u8] = b"1.2.11\0";
+const DATA_ERROR_MESSAGE: &[u8] = b"invalid compressed data\0";
+
+/// The allocation function type of zlib (ignored by this library).
+pub type alloc_func = Option<unsafe extern "C" fn(*mut c_void, c_uint, c_uint) -> *mut c_void>;
+
+/// The deallocation function type of zlib (ignored by this library).
+pub type free_func = Option<unsafe extern "C" fn(*mut c_void, *mut c_void)>;
+
+/// The stream structure which has the same layout as `z_stream` of zlib.
+#[repr(C)]
+#[derive(Debug)]
+pub struct z_stream {
+    /// Next input byte.
+    pub next_in: *const u8,
+    /// Number of bytes available at `next_in`.
+    pub avail_in: c_uint,
+    /// Total number of input bytes read so far.
+    pub total_in: c_ulong,
+
+    /// Next output byte will go here.
+    pub next_out: *mut u8,
+    /// Remaining free space at `next_out`.
+    pub avail_out: c_uint,
+    /// Total number of bytes output so far.
+    pub total_out: c_ulong,
+
+    /// Last error message, NULL if no error.
+    pub msg: *const c_char,
+    /// Internal state (not visible to applications).
+    pub state: *mut c_void,
+
+    /// Used to allocate the internal state (ignored).
+    pub zalloc: alloc_func,
+    /// Used to free the internal state (ignored).
+    pub zfree: free_func,
+    /// Private data object passed to `zalloc` and `zfree` (ignored).
+    pub opaque: *mut c_void,
+
+    /// Best guess about the data type (always `0`).
+    pub data_type: c_int,
+    /// Adler-32 value of the uncompressed data (only updated by `deflate`).
+    pub adler: c_ulong,
+    /// Reserved for future use.
+    pub reserved: c_ulong,
+}
+
+#[derive(Debug)]
+enum State {
+    // Boxed so that the size of the enum doesn't depend on the (quite different) sizes of the coders.
+    Deflate(Box<DeflateState>),
+    Inflate(Box<InflateState>),
+}
+
+#[derive(Debug)]
+struct DeflateState {
+    encoder: Encoder,
+    adler32: Adler32,
+}
+
+#[derive(Debug)]
+struct InflateState {
+    decoder: Decoder,
+
+    // A decoded byte which could not be written because the output buffer was full.
+    pending: Option<u8>,
+    failed: bool,
+}
+impl InflateState {
+    fn new() -> Self {
+        InflateState {
+            decoder: Decoder::new(Format::Zlib),
+            pending: None,
+            failed: false,
+        }
+    }
+
+    /// Returns the pair of the consumed and the produced byte counts.
+    ///
+    /// If `output` is full, a single byte is decoded ahead in order to detect the end of the stream.
+    fn decode(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ()> {
+        if self.failed {
+            return Err(());
+        }
+        let mut produced = 0;
+        if let Some(b) = self.pending {
+            if output.is_empty() {
+                return Ok((0, 0));
+            }
+            output[0] = b;
+            self.pending = None;
+            produced = 1;
+        }
+
+        let mut lookahead = [0; 1];
+        let full = produced == output.len();
+        let result = if full {
+            self.decoder.decode(input, &mut lookahead)
+        } else {
+            self.decoder.decode(input, &mut output[produced..])
+        };
+        let progress = result.map_err(|_| self.failed = true)?;
+        if full {
+            if progress.produced > 0 {
+                self.pending = Some(lookahead[0]);
+            }
+        } else {
+            produced += progress.produced;
+        }
+        Ok((progress.consumed, produced))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.pending.is_none() && self.decoder.is_finished()
+    }
+}
+
+fn encode_options(level: c_int) -> Option<EncodeOptions<libflate::lz77::DefaultLz77Encoder>> {
+    match level {
+        0 => Some(EncodeOptions::new().no_compression()),
+        -1..=9 => Some(EncodeOptions::new()),
+        _ => None,
+    }
+}
+
+fn flush_mode(flush: c_int) -> Option<Flush> {
+    match flush {
+        Z_NO_FLUSH => Some(Flush::None),
+        Z_PARTIAL_FLUSH | Z_SYNC_FLUSH | Z_FULL_FLUSH => Some(Flush::Sync),
+        Z_FINISH => Some(Flush::Finish),
+        _ => None,
+    }
+}
+
+unsafe fn make_slice<'a>(p: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(p, len)
+    }
+}
+
+unsafe fn make_slice_mut<'a>(p: *mut u8, len: usize) -> &'a mut [u8] {
+    if len == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(p, len)
+    }
+}
+
+unsafe fn init_stream(
+    strm: *mut z_stream,
+    version: *const c_char,
+    stream_size: c_int,
+    state: impl FnOnce() -> Option<State>,
+) -> c_int {
+    if version.is_null()
+        || *version as u8 != ZLIB_VERSION[0]
+        || stream_size as usize != mem::size_of::<z_stream>()
+    {
+        return Z_VERSION_ERROR;
+    }
+    let strm = match strm.as_mut() {
+        Some(strm) => strm,
+        None => return Z_STREAM_ERROR,
+    };
+    let state = match state() {
+        Some(state) => state,
+        None => return Z_STREAM_ERROR,
+    };
+    strm.total_in = 0;
+    strm.total_out = 0;
+    strm.msg = ptr::null();
+    strm.data_type = 0;
+    strm.adler = 1;
+    strm.state = Box::into_raw(Box::new(state)) as *mut c_void;
+    Z_OK
+}
+
+unsafe fn end_stream(strm: *mut z_stream, is_deflate: bool) -> c_int {
+    let strm = match strm.as_mut() {
+        Some(strm) if !strm.state.is_null() => strm,
+        _ => return Z_STREAM_ERROR,
+    };
+    let state = strm.state as *mut State;
+    match (&*state, is_deflate) {
+        (State::Deflate(_), true) | (State::Inflate(_), false) => {}
+        _ => return Z_STREAM_ERROR,
+    }
+    drop(Box::from_raw(state));
+    strm.state = ptr::null_mut();
+    Z_OK
+}
+
+unsafe fn stream_buffers<'a>(strm: &z_stream) -> Option<(&'a [u8], &'a mut [u8])> {
+    if (strm.next_in.is_null() && strm.avail_in != 0)
+        || (strm.next_out.is_null() && strm.avail_out != 0)
+    {
+        return None;
+    }
+    Some((
+        make_slice(strm.next_in, strm.avail_in as usize),
+        make_slice_mut(strm.next_out, strm.avail_out as usize),
+    ))
+}
+
+unsafe fn advance(strm: &mut z_stream, consumed: usize, produced: usize) {
+    if consumed > 0 {
+        strm.next_in = strm.next_in.add(consumed);
+        strm.avail_in -= consumed as c_uint;
+        strm.total_in += consumed as c_ulong;
+    }
+    if produced > 0 {
+        strm.next_out = strm.next_out.add(produced);
+        strm.avail_out -= produced as c_uint;
+        strm.total_out += produced as c_ulong;
+    }
+}
+
+/// Returns the zlib version string with which this library is compatible.
+#[no_mangle]
+pub extern "C" fn zlibVersion() -> *const c_char {
+    ZLIB_VERSION.as_ptr() as *const c_char
+}
+
+/// Initializes `strm` for compression (the implementation of the `deflateInit` macro).
+///
+/// # Safety
+///
+/// `strm` must point to a valid `z_stream` and `version` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn deflateInit_(
+    strm: *mut z_stream,
+    level: c_int,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    init_stream(strm, version, stream_size, || {
+        let options = encode_options(level)?;
+        let encoder = Encoder::zlib(options).ok()?;
+        Some(State::Deflate(Box::new(DeflateState {
+            encoder,
+            adler32: Adler32::new(),
+        })))
+    })
+}
+
+/// Compresses as much data as possible from `next_in` to `next_out`.
+///
+/// # Safety
+///
+/// `strm` must have been initialized by `deflateInit_`, and its buffer fields must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn deflate(strm: *mut z_stream, flush: c_int) -> c_int {
+    let strm = match strm.as_mut() {
+        Some(strm) if !strm.state.is_null() => strm,
+        _ => return Z_STREAM_ERROR,
+    };
+    let state = match &mut *(strm.state as *mut State) {
+        State::Deflate(state) => state,
+        State::Inflate(_) => return Z_STREAM_ERROR,
+    };
+    let (flush, (input, output)) = match (flush_mode(flush), stream_buffers(strm)) {
+        (Some(flush), Some(buffers)) => (flush, buffers),
+        _ => return Z_STREAM_ERROR,
+    };
+    if state.encoder.is_finished() && flush != Flush::Finish {
+        return Z_STREAM_ERROR;
+    }
+    if output.is_empty() {
+        return Z_BUF_ERROR;
+    }
+
+    let progress = match state.encoder.encode(input, output, flush) {
+        Ok(progress) => progress,
+        Err(_) => return Z_STREAM_ERROR,
+    };
+    state.adler32.update(&input[..progress.consumed]);
+    strm.adler = c_ulong::from(state.adler32.value());
+    advance(strm, progress.consumed, progress.produced);
+
+    if progress.status == Status::StreamEnd {
+        Z_STREAM_END
+    } else if progress.consumed == 0 && progress.produced == 0 {
+        Z_BUF_ERROR
+    } else {
+        Z_OK
+    }
+}
+
+/// Frees the state of a compression stream.
+///
+/// # Safety
+///
+/// `strm` must have been initialized by `deflateInit_`.
+#[no_mangle]
+pub unsafe extern "C" fn deflateEnd(strm: *mut z_stream) -> c_int {
+    end_stream(strm, true)
+}
+
+/// Initializes `strm` for decompression (the implementation of the `inflateInit` macro).
+///
+/// # Safety
+///
+/// `strm` must point to a valid `z_stream` and `version` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn inflateInit_(
+    strm: *mut z_stream,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    init_stream(strm, version, stream_size, || {
+        Some(State::Inflate(Box::new(InflateState::new())))
+    })
+}
+
+/// Decompresses as much data as possible from `next_in` to `next_out`.
+///
+/// The `flush` parameter is ignored.
+///
+/// # Safety
+///
+/// `strm` must have been initialized by `inflateInit_`, and its buffer fields must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn inflate(strm: *mut z_stream, _flush: c_int) -> c_int {
+    let strm = match strm.as_mut() {
+        Some(strm) if !strm.state.is_null() => strm,
+        _ => return Z_STREAM_ERROR,
+    };
+    let state = match &mut *(strm.state as *mut State) {
+        State::Inflate(state) => state,
+        State::Deflate(_) => return Z_STREAM_ERROR,
+    };
+    let (input, output) = match stream_buffers(strm) {
+        Some(buffers) => buffers,
+        None => return Z_STREAM_ERROR,
+    };
+    if state.is_finished() {
+        return Z_STREAM_END;
+    }
+
+    let (consumed, produced) = match state.decode(input, output) {
+        Ok(progress) => progress,
+        Err(()) => {
+            strm.msg = DATA_ERROR_MESSAGE.as_ptr() as *const c_char;
+            return Z_DATA_ERROR;
+        }
+    };
+    advance(strm, consumed, produced);
+
+    if state.is_finished() {
+        Z_STREAM_END
+    } else if consumed == 0 && produced == 0 {
+        Z_BUF_ERROR
+    } else {
+        Z_OK
+    }
+}
+
+/// Frees the state of a decompression stream.
+///
+/// # Safety
+///
+/// `strm` must have been initialized by `inflateInit_`.
+#[no_mangle]
+pub unsafe extern "C" fn inflateEnd(strm: *mut z_stream) -> c_int {
+    end_stream(strm, false)
+}
+
+/// Compresses `source` into `dest` with the default compression level.
+///
+/// # Safety
+///
+/// See [`compress2`].
+#[no_mangle]
+pub unsafe extern "C" fn compress(
+    dest: *mut u8,
+    destLen: *mut c_ulong,
+    source: *const u8,
+    sourceLen: c_ulong,
+) -> c_int {
+    compress2(dest, destLen, source, sourceLen, Z_DEFAULT_COMPRESSION)
+}
+
+/// Compresses `source` into `dest`.
+///
+/// On entry, `*destLen` is the size of `dest`. On exit, it is the size of the compressed data.
+///
+/// Returns `Z_BUF_ERROR` if `dest` is too small.
+///
+/// # Safety
+///
+/// `dest` must be valid for writes of `*destLen` bytes and `source` must be valid for reads of `sourceLen` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn compress2(
+    dest: *mut u8,
+    destLen: *mut c_ulong,
+    source: *const u8,
+    sourceLen: c_ulong,
+    level: c_int,
+) -> c_int {
+    if destLen.is_null()
+        || (dest.is_null() && *destLen != 0)
+        || (source.is_null() && sourceLen != 0)
+    {
+        return Z_STREAM_ERROR;
+    }
+    let mut encoder = match encode_options(level).map(Encoder::zlib) {
+        Some(Ok(encoder)) => encoder,
+        _ => return Z_STREAM_ERROR,
+    };
+    let input = make_slice(source, sourceLen as usize);
+    let output = make_slice_mut(dest, *destLen as usize);
+
+    let mut consumed = 0;
+    let mut produced = 0;
+    loop {
+        let progress =
+            match encoder.encode(&input[consumed..], &mut output[produced..], Flush::Finish) {
+                Ok(progress) => progress,
+                Err(_) => return Z_STREAM_ERROR,
+            };
+        consumed += progress.consumed;
+        produced += progress.produced;
+        if progress.status == Status::StreamEnd {
+            break;
+        }
+        if produced == output.len() {
+            return Z_BUF_ERROR;
+        }
+    }
+    *destLen = produced as c_ulong;
+    Z_OK
+}
+
+/// Decompresses `source` into `dest`.
+///
+/// On entry, `*destLen` is the size of `dest`. On exit, it is the size of the decompressed data.
+///
+/// Returns `Z_BUF_ERROR` if `dest` is too small, and `Z_DATA_ERROR` if `source` is corrupted or incomplete.
+///
+/// # Safety
+///
+/// `dest` must be valid for writes of `*destLen` bytes and `source` must be valid for reads of `sourceLen` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn uncompress(
+    dest: *mut u8,
+    destLen: *mut c_ulong,
+    source: *const u8,
+    sourceLen: c_ulong,
+) -> c_int {
+    if destLen.is_null()
+        || (dest.is_null() && *destLen != 0)
+        || (source.is_null() && sourceLen != 0)
+    {
+        return Z_STREAM_ERROR;
+    }
+    let input = make_slice(source, sourceLen as usize);
+    let output = make_slice_mut(dest, *destLen as usize);
+
+    let mut state = InflateState::new();
+    let mut consumed = 0;
+    let mut produced = 0;
+    loop {
+        let (c, p) = match state.decode(&input[consumed..], &mut output[produced..]) {
+            Ok(progress) => progress,
+            Err(()) => return Z_DATA_ERROR,
+        };
+        consumed += c;
+        produced += p;
+        if state.is_finished() {
+            break;
+        }
+        if state.pending.is_some() {
+            return Z_BUF_ERROR;
+        }
+        if c == 0 && p == 0 {
+            return Z_DATA_ERROR;
+        }
+    }
+    *destLen = produced as c_ulong;
+    Z_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn new_stream() -> z_stream {
+        z_stream {
+            next_in: ptr::null(),
+            avail_in: 0,
+            total_in: 0,
+            next_out: ptr::null_mut(),
+            avail_out: 0,
+            total_out: 0,
+            msg: ptr::null(),
+            state: ptr::null_mut(),
+            zalloc: None,
+            zfree: None,
+            opaque: ptr::null_mut(),
+            data_type: 0,
+            adler: 0,
+            reserved: 0,
+        }
+    }
+
+    fn version() -> *const c_char {
+        zlibVersion()
+    }
+
+    fn stream_size() -> c_int {
+        mem::size_of::<z_stream>() as c_int
+    }
+
+    fn plain_data() -> Vec<u8> {
+        (0..100_000).map(|i| (i % 13 + i / 1000) as u8).collect()
+    }
+
+    #[test]
+    fn compress_and_uncompress_works() {
+        let plain = plain_data();
+        for &level in &[Z_DEFAULT_COMPRESSION, 0, 9] {
+            let mut compressed = vec![0; plain.len() * 2];
+            let mut compressed_len = compressed.len() as c_ulong;
+            let ret = unsafe {
+                compress2(
+                    compressed.as_mut_ptr(),
+                    &mut compressed_len,
+                    plain.as_ptr(),
+                    plain.len() as c_ulong,
+                    level,
+                )
+            };
+            assert_eq!(ret, Z_OK);
+            compressed.truncate(compressed_len as usize);
+
+            let mut decoded = Vec::new();
+            libflate::zlib::Decoder::new(&compressed[..])
+                .unwrap()
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, plain);
+
+            // Exactly sized output buffer.
+            let mut uncompressed = vec![0; plain.len()];
+            let mut uncompressed_len = uncompressed.len() as c_ulong;
+            let ret = unsafe {
+                uncompress(
+                    uncompressed.as_mut_ptr(),
+                    &mut uncompressed_len,
+                    compressed.as_ptr(),
+                    compressed_len,
+                )
+            };
+            assert_eq!(ret, Z_OK);
+            assert_eq!(uncompressed_len as usize, plain.len());
+            assert_eq!(uncompressed, plain);
+        }
+    }
+
+    #[test]
+    fn too_small_buffers_are_reported() {
+        let plain = plain_data();
+        let mut compressed = vec![0; 10];
+        let mut compressed_len = compressed.len() as c_ulong;
+        let ret = unsafe {
+            compress(
+                compressed.as_mut_ptr(),
+                &mut compressed_len,
+                plain.as_ptr(),
+                plain.len() as c_ulong,
+            )
+        };
+        assert_eq!(ret, Z_BUF_ERROR);
+
+        let mut compressed = vec![0; plain.len()];
+        let mut compressed_len = compressed.len() as c_ulong;
+        let ret = unsafe {
+            compress(
+                compressed.as_mut_ptr(),
+                &mut compressed_len,
+                plain.as_ptr(),
+                plain.len() as c_ulong,
+            )
+        };
+        assert_eq!(ret, Z_OK);
+
+        let mut uncompressed = vec![0; plain.len() - 1];
+        let mut uncompressed_len = uncompressed.len() as c_ulong;
+        let ret = unsafe {
+            uncompress(
+                uncompressed.as_mut_ptr(),
+                &mut uncompressed_len,
+                compressed.as_ptr(),
+                compressed_len,
+            )
+        };
+        assert_eq!(ret, Z_BUF_ERROR);
+    }
+
+    #[test]
+    fn corrupted_data_is_reported() {
+        let mut uncompressed = vec![0; 100];
+        let mut uncompressed_len = uncompressed.len() as c_ulong;
+        let corrupted = [120, 156, 255, 255, 255];
+        let ret = unsafe {
+            uncompress(
+                uncompressed.as_mut_ptr(),
+                &mut uncompressed_len,
+                corrupted.as_ptr(),
+                corrupted.len() as c_ulong,
+            )
+        };
+        assert_eq!(ret, Z_DATA_ERROR);
+
+        // Truncated
+        let truncated = [120, 156, 243, 72, 205, 201, 201];
+        let ret = unsafe {
+            uncompress(
+                uncompressed.as_mut_ptr(),
+                &mut uncompressed_len,
+                truncated.as_ptr(),
+                truncated.len() as c_ulong,
+            )
+        };
+        assert_eq!(ret, Z_DATA_ERROR);
+    }
+
+    #[test]
+    fn streaming_works() {
+        let plain = plain_data();
+
+        // Compression with small buffers
+        let mut strm = new_stream();
+        assert_eq!(
+            unsafe { deflateInit_(&mut strm, 6, version(), stream_size()) },
+            Z_OK
+        );
+        let mut compressed = Vec::new();
+        let mut buf = [0; 100];
+        for (i, chunk) in plain.chunks(777).enumerate() {
+            let flush = if i % 10 == 0 {
+                Z_SYNC_FLUSH
+            } else {
+                Z_NO_FLUSH
+            };
+            strm.next_in = chunk.as_ptr();
+            strm.avail_in = chunk.len() as c_uint;
+            loop {
+                strm.next_out = buf.as_mut_ptr();
+                strm.avail_out = buf.len() as c_uint;
+                let ret = unsafe { deflate(&mut strm, flush) };
+                assert!(ret == Z_OK || ret == Z_BUF_ERROR, "ret={}", ret);
+                compressed.extend_from_slice(&buf[..buf.len() - strm.avail_out as usize]);
+                if strm.avail_out != 0 {
+                    break;
+                }
+            }
+            assert_eq!(strm.avail_in, 0);
+        }
+        loop {
+            strm.next_out = buf.as_mut_ptr();
+            strm.avail_out = buf.len() as c_uint;
+            let ret = unsafe { deflate(&mut strm, Z_FINISH) };
+            compressed.extend_from_slice(&buf[..buf.len() - strm.avail_out as usize]);
+            if ret == Z_STREAM_END {
+                break;
+            }
+            assert_eq!(ret, Z_OK);
+        }
+        assert_eq!(strm.total_in as usize, plain.len());
+        assert_eq!(strm.total_out as usize, compressed.len());
+        let mut adler32 = Adler32::new();
+        adler32.update(&plain);
+        assert_eq!(strm.adler, c_ulong::from(adler32.value()));
+        assert_eq!(unsafe { deflateEnd(&mut strm) }, Z_OK);
+
+        // Decompression with small buffers
+        let mut strm = new_stream();
+        assert_eq!(
+            unsafe { inflateInit_(&mut strm, version(), stream_size()) },
+            Z_OK
+        );
+        let mut decompressed = Vec::new();
+        let mut ret = Z_OK;
+        for chunk in compressed.chunks(33) {
+            strm.next_in = chunk.as_ptr();
+            strm.avail_in = chunk.len() as c_uint;
+            loop {
+                strm.next_out = buf.as_mut_ptr();
+                strm.avail_out = buf.len() as c_uint;
+                ret = unsafe { inflate(&mut strm, Z_NO_FLUSH) };
+                assert!(ret == Z_OK || ret == Z_STREAM_END, "ret={}", ret);
+                decompressed.extend_from_slice(&buf[..buf.len() - strm.avail_out as usize]);
+                if strm.avail_out != 0 || ret == Z_STREAM_END {
+                    break;
+                }
+            }
+        }
+        assert_eq!(ret, Z_STREAM_END);
+        assert_eq!(decompressed, plain);
+        assert_eq!(strm.total_in as usize, compressed.len());
+        assert_eq!(unsafe { inflateEnd(&mut strm) }, Z_OK);
+    }
+
+    #[test]
+    fn invalid_usages_are_rejected() {
+        let mut strm = new_stream();
+        assert_eq!(
+            unsafe { deflateInit_(&mut strm, 10, version(), stream_size()) },
+            Z_STREAM_ERROR
+        );
+        assert_eq!(
+            unsafe { deflateInit_(&mut strm, 6, version(), stream_size() - 1) },
+            Z_VERSION_ERROR
+        );
+        assert_eq!(unsafe { deflate(&mut strm, Z_NO_FLUSH) }, Z_STREAM_ERROR);
+
+        assert_eq!(
+            unsafe { inflateInit_(&mut strm, version(), stream_size()) },
+            Z_OK
+        );
+        assert_eq!(unsafe { deflate(&mut strm, Z_NO_FLUSH) }, Z_STREAM_ERROR);
+        assert_eq!(unsafe { deflateEnd(&mut strm) }, Z_STREAM_ERROR);
+
+        let garbage = [0xFF; 8];
+        let mut buf = [0; 8];
+        strm.next_in = garbage.as_ptr();
+        strm.avail_in = garbage.len() as c_uint;
+        strm.next_out = buf.as_mut_ptr();
+        strm.avail_out = buf.len() as c_uint;
+        assert_eq!(unsafe { inflate(&mut strm, Z_NO_FLUSH) }, Z_DATA_ERROR);
+        assert!(!strm.msg.is_null());
+        assert_eq!(unsafe { inflateEnd(&mut strm) }, Z_OK);
+        assert!(strm.state.is_null());
+    }
+}
@@ -7,17 +7,82 @@ use hashbrown::HashMap;
 #[cfg(not(feature = "no_std"))]
 use std::{cmp, collections::HashMap};
 
+#[cfg(feature = "serde")]
+use core::convert::TryFrom;
+
 use super::Code;
 use super::Lz77Encode;
 use super::Sink;
 
 /// A [`Lz77Encode`] implementation used by default.
-#[derive(Debug)]
+///
+/// The input is buffered until `8 * window_size` bytes are accumulated (or `flush()` is called),
+/// and then the whole buffer is encoded at once and cleared, keeping its capacity for the following input.
+/// No history is carried over to the next buffer (matches never span across two of them),
+/// so the buffered data are never moved within the buffer, and a ring buffer window would bring no benefit.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedDefaultLz77Encoder"))]
 pub struct DefaultLz77Encoder {
     window_size: u16,
     max_length: u16,
+    #[cfg_attr(feature = "serde", serde(default = "default_min_length"))]
+    min_length: u16,
+    #[cfg_attr(
+        all(feature = "serde", feature = "allocator_api"),
+        serde(serialize_with = "serialize_buffer")
+    )]
+    buf: super::Buffer,
+}
+
+#[cfg(feature = "serde")]
+fn default_min_length() -> u16 {
+    super::MIN_LENGTH
+}
+
+#[cfg(all(feature = "serde", feature = "allocator_api"))]
+fn serialize_buffer<S>(buf: &super::Buffer, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serde::Serialize::serialize(&buf[..], serializer)
+}
+
+/// A [`DefaultLz77Encoder`] just deserialized, whose parameters are validated before use.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedDefaultLz77Encoder {
+    window_size: u16,
+    max_length: u16,
+    #[serde(default = "default_min_length")]
+    min_length: u16,
     buf: Vec<u8>,
 }
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedDefaultLz77Encoder> for DefaultLz77Encoder {
+    type Error = &'static str;
+    fn try_from(encoder: UncheckedDefaultLz77Encoder) -> Result<Self, Self::Error> {
+        // The parameters must be the ones `DefaultLz77EncoderBuilder::build()` may produce.
+        let min_length = cmp::min(
+            cmp::max(encoder.min_length, super::MIN_LENGTH),
+            encoder.max_length,
+        );
+        if encoder.window_size > super::MAX_WINDOW_SIZE
+            || encoder.max_length > super::MAX_LENGTH
+            || encoder.min_length != min_length
+        {
+            return Err("The parameters of the LZ77 encoder are invalid");
+        }
+        let mut buf = super::global_buffer();
+        buf.extend_from_slice(&encoder.buf);
+        Ok(DefaultLz77Encoder {
+            window_size: encoder.window_size,
+            max_length: encoder.max_length,
+            min_length: encoder.min_length,
+            buf,
+        })
+    }
+}
 
 impl DefaultLz77Encoder {
     /// Makes a new encoder instance.
@@ -58,6 +123,33 @@ impl DefaultLz77Encoder {
             .window_size(cmp::min(size, super::MAX_WINDOW_SIZE))
             .build()
     }
+
+    /// Makes the buffer of this encoder (which holds the input data not encoded yet) allocated by `allocator`.
+    ///
+    /// The data buffered so far are moved to the new buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use libflate_lz77::{Code, DefaultLz77Encoder, Lz77Encode};
+    ///
+    /// let mut lz77 = DefaultLz77Encoder::new();
+    /// lz77.set_allocator(&std::alloc::System);
+    ///
+    /// let mut codes: Vec<Code> = Vec::new();
+    /// lz77.encode(b"aaaaa", &mut codes);
+    /// lz77.flush(&mut codes);
+    /// assert_eq!(
+    ///     codes,
+    ///     [Code::Literal(b'a'), Code::Pointer { length: 4, backward_distance: 1 }]
+    /// );
+    /// ```
+    #[cfg(feature = "allocator_api")]
+    pub fn set_allocator(&mut self, allocator: super::DynAllocator) {
+        let mut buf = Vec::new_in(allocator);
+        buf.extend_from_slice(&self.buf);
+        self.buf = buf;
+    }
 }
 
 impl Default for DefaultLz77Encoder {
@@ -88,13 +180,12 @@ impl Lz77Encode for DefaultLz77Encoder {
             let matched = prefix_table.insert(key, i as u32);
             if let Some(j) = matched.map(|j| j as usize) {
                 let distance = i - j;
-                if distance <= self.window_size as usize {
-                    let length = 3 + longest_common_prefix(
-                        &self.buf,
-                        i + 3,
-                        j + 3,
-                        self.max_length as usize,
-                    );
+                let length = if distance <= self.window_size as usize {
+                    3 + longest_common_prefix(&self.buf, i + 3, j + 3, self.max_length as usize)
+                } else {
+                    0
+                };
+                if length >= self.min_length {
                     sink.consume(Code::Pointer {
                         length,
                         backward_distance: distance as u16,
@@ -120,6 +211,19 @@ impl Lz77Encode for DefaultLz77Encoder {
     fn window_size(&self) -> u16 {
         self.window_size
     }
+
+    /// Returns the capacity of the buffer which holds the input data not encoded yet.
+    ///
+    /// The buffer is encoded when its length reaches `8 * window_size` bytes,
+    /// so it is at most `8 * window_size` bytes plus the length of the largest `buf` passed to `encode()`.
+    ///
+    /// While encoding the buffer, a temporary prefix table is allocated additionally.
+    /// The table takes about 24 bytes per buffered byte if the buffer is shorter than [`MAX_WINDOW_SIZE`](super::MAX_WINDOW_SIZE),
+    /// otherwise 1.5 MiB plus 8 bytes per buffered byte in the worst case.
+    /// It is released before `encode()` or `flush()` returns, and so not included in the returned value.
+    fn memory_usage(&self) -> usize {
+        self.buf.capacity()
+    }
 }
 
 #[inline]
@@ -212,6 +316,7 @@ impl LargePrefixTable {
 pub struct DefaultLz77EncoderBuilder {
     window_size: u16,
     max_length: u16,
+    min_length: u16,
 }
 
 impl DefaultLz77EncoderBuilder {
@@ -220,6 +325,7 @@ impl DefaultLz77EncoderBuilder {
         DefaultLz77EncoderBuilder {
             window_size: super::MAX_WINDOW_SIZE,
             max_length: super::MAX_LENGTH,
+            min_length: super::MIN_LENGTH,
         }
     }
 
@@ -248,12 +354,27 @@ impl DefaultLz77EncoderBuilder {
         }
     }
 
+    /// Set the minimum length of a pointer command this encoder will emit.
+    ///
+    /// Shorter matches are emitted as literals instead.
+    /// Raising the minimum (e.g., to `6`) favors Huffman coding over string matching,
+    /// which compresses better data consisting of small values with somewhat random distribution,
+    /// such as filtered image data.
+    /// The value is clamped to the range from [`MIN_LENGTH`](super::MIN_LENGTH) to the maximum length.
+    pub fn min_length(self, min_length: u16) -> Self {
+        DefaultLz77EncoderBuilder {
+            min_length: cmp::max(min_length, super::MIN_LENGTH),
+            ..self
+        }
+    }
+
     /// Build the encoder with the builder state's parameters.
     pub fn build(self) -> DefaultLz77Encoder {
         DefaultLz77Encoder {
             window_size: self.window_size,
             max_length: self.max_length,
-            buf: Vec::new(),
+            min_length: cmp::min(self.min_length, self.max_length),
+            buf: super::global_buffer(),
         }
     }
 }
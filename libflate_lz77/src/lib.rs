@@ -3,6 +3,7 @@
 //! LZ77 is a compression algorithm used in [DEFLATE](https://tools.ietf.org/html/rfc1951).
 #![warn(missing_docs)]
 #![cfg_attr(feature = "no_std", no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
 #[cfg(feature = "no_std")]
 extern crate alloc;
@@ -12,12 +13,16 @@ pub use self::default::{DefaultLz77Encoder, DefaultLz77EncoderBuilder};
 use alloc::vec::Vec;
 #[cfg(feature = "no_std")]
 use core2::io;
+#[cfg(not(feature = "allocator_api"))]
 use rle_decode_fast::rle_decode;
 #[cfg(not(feature = "no_std"))]
 use std::io;
 
 mod default;
 
+/// Minimum length of sharable bytes in a pointer.
+pub const MIN_LENGTH: u16 = 3;
+
 /// Maximum length of sharable bytes in a pointer.
 pub const MAX_LENGTH: u16 = 258;
 
@@ -29,6 +34,7 @@ pub const MAX_WINDOW_SIZE: u16 = MAX_DISTANCE;
 
 /// A LZ77 encoded data.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Code {
     /// Literal byte.
     Literal(u8),
@@ -66,7 +72,7 @@ pub trait Sink {
     /// Consumes a LZ77 encoded `Code`.
     fn consume(&mut self, code: Code);
 }
-impl<'a, T> Sink for &'a mut T
+impl<T> Sink for &mut T
 where
     T: Sink,
 {
@@ -74,6 +80,7 @@ where
         (*self).consume(code);
     }
 }
+#[cfg(not(feature = "allocator_api"))]
 impl<T> Sink for Vec<T>
 where
     T: From<Code>,
@@ -82,6 +89,16 @@ where
         self.push(T::from(code));
     }
 }
+#[cfg(feature = "allocator_api")]
+impl<T, A> Sink for Vec<T, A>
+where
+    T: From<Code>,
+    A: core::alloc::Allocator,
+{
+    fn consume(&mut self, code: Code) {
+        self.push(T::from(code));
+    }
+}
 
 /// The [`Lz77Encode`] trait defines the interface of LZ77 encoding algorithm.
 pub trait Lz77Encode {
@@ -108,10 +125,18 @@ pub trait Lz77Encode {
     fn window_size(&self) -> u16 {
         MAX_WINDOW_SIZE
     }
+
+    /// Returns the number of bytes currently allocated on the heap by the encoder.
+    ///
+    /// If the implementation is omitted, `0` will be returned.
+    fn memory_usage(&self) -> usize {
+        0
+    }
 }
 
 /// A no compression implementation of [`Lz77Encode`] trait.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NoCompressionLz77Encoder;
 impl NoCompressionLz77Encoder {
     /// Makes a new encoder instance.
@@ -151,11 +176,64 @@ impl Lz77Encode for NoCompressionLz77Encoder {
     }
 }
 
+/// An allocator which backs the buffer (including the sliding window) of a [`Lz77Decoder`]
+/// or a [`DefaultLz77Encoder`] (available only if the `allocator_api` feature is enabled).
+///
+/// Because the allocator is type-erased, it does not appear in the types of the decoders and the encoders.
+/// The `'static` lifetime can be obtained by using a `static` allocator or by leaking a `Box`.
+#[cfg(feature = "allocator_api")]
+pub type DynAllocator = &'static (dyn core::alloc::Allocator + Sync);
+
+#[cfg(feature = "allocator_api")]
+type Buffer = Vec<u8, DynAllocator>;
+#[cfg(not(feature = "allocator_api"))]
+type Buffer = Vec<u8>;
+
+/// Returns an empty buffer allocated by the global allocator.
+fn global_buffer() -> Buffer {
+    #[cfg(all(feature = "allocator_api", feature = "no_std"))]
+    {
+        Vec::new_in(&alloc::alloc::Global)
+    }
+    #[cfg(all(feature = "allocator_api", not(feature = "no_std")))]
+    {
+        Vec::new_in(&std::alloc::Global)
+    }
+    #[cfg(not(feature = "allocator_api"))]
+    {
+        Vec::new()
+    }
+}
+
 /// LZ77 decoder.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Lz77Decoder {
-    buffer: Vec<u8>,
+    buffer: Buffer,
     offset: usize,
+    window_size: u16,
+}
+
+#[cfg(not(feature = "allocator_api"))]
+impl Default for Lz77Decoder {
+    fn default() -> Self {
+        Lz77Decoder {
+            buffer: Vec::new(),
+            offset: 0,
+            window_size: MAX_WINDOW_SIZE,
+        }
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl Default for Lz77Decoder {
+    fn default() -> Self {
+        #[cfg(feature = "no_std")]
+        use alloc::alloc::Global;
+        #[cfg(not(feature = "no_std"))]
+        use std::alloc::Global;
+
+        Self::new_in(&Global)
+    }
 }
 
 impl Lz77Decoder {
@@ -164,20 +242,75 @@ impl Lz77Decoder {
         Self::default()
     }
 
+    /// Makes a new [`Lz77Decoder`] instance whose buffer (including the sliding window) is allocated by `allocator`.
+    ///
+    /// # Examples
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use libflate_lz77::{Code, Lz77Decoder};
+    ///
+    /// let mut decoder = Lz77Decoder::new_in(&std::alloc::System);
+    /// decoder.decode(Code::Literal(b'a')).unwrap();
+    /// decoder.decode(Code::Pointer { length: 3, backward_distance: 1 }).unwrap();
+    /// assert_eq!(decoder.buffer(), b"aaaa");
+    /// ```
+    #[cfg(feature = "allocator_api")]
+    pub fn new_in(allocator: DynAllocator) -> Self {
+        Lz77Decoder {
+            buffer: Vec::new_in(allocator),
+            offset: 0,
+            window_size: MAX_WINDOW_SIZE,
+        }
+    }
+
+    /// Sets the size of the sliding window.
+    ///
+    /// Only the last `window_size` bytes are kept as the window,
+    /// so the memory used by the buffer shrinks accordingly,
+    /// and a pointer whose backward distance exceeds `window_size` is rejected as invalid data.
+    /// The value is clamped to the range `1..=MAX_WINDOW_SIZE`.
+    ///
+    /// The default value is [`MAX_WINDOW_SIZE`].
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate_lz77::{Code, Lz77Decoder};
+    ///
+    /// let mut decoder = Lz77Decoder::new();
+    /// decoder.set_window_size(2);
+    /// decoder.decode(Code::Literal(b'a')).unwrap();
+    /// decoder.decode(Code::Literal(b'b')).unwrap();
+    /// decoder.decode(Code::Literal(b'c')).unwrap();
+    /// decoder.decode(Code::Pointer { length: 2, backward_distance: 2 }).unwrap();
+    /// assert!(decoder.decode(Code::Pointer { length: 3, backward_distance: 3 }).is_err());
+    /// ```
+    pub fn set_window_size(&mut self, window_size: u16) {
+        self.window_size = window_size.clamp(1, MAX_WINDOW_SIZE);
+    }
+
+    /// Returns the size of the sliding window.
+    pub fn window_size(&self) -> u16 {
+        self.window_size
+    }
+
     /// Decodes a [`Code`].
     ///
     /// The decoded bytes are appended to the buffer of [`Lz77Decoder`].
+    /// If the buffer can not be grown, an error is returned (see [`Lz77Decoder::extend_from_reader`]).
     #[inline]
     pub fn decode(&mut self, code: Code) -> io::Result<()> {
         match code {
             Code::Literal(b) => {
+                self.try_reserve(1)?;
                 self.buffer.push(b);
             }
             Code::Pointer {
                 length,
                 backward_distance,
             } => {
-                if self.buffer.len() < backward_distance as usize {
+                if self.buffer.len() < backward_distance as usize
+                    || self.window_size < backward_distance
+                {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidData,
                         #[cfg(not(feature = "no_std"))]
@@ -190,6 +323,7 @@ impl Lz77Decoder {
                         "Too long backword reference",
                     ));
                 }
+                self.try_reserve(usize::from(length))?;
                 rle_decode(
                     &mut self.buffer,
                     usize::from(backward_distance),
@@ -200,15 +334,84 @@ impl Lz77Decoder {
         Ok(())
     }
 
+    /// Appends `literals` to the buffer of [`Lz77Decoder`].
+    ///
+    /// This is equivalent to (but faster than) decoding each byte as [`Code::Literal`].
+    #[inline]
+    pub fn decode_literals(&mut self, literals: &[u8]) -> io::Result<()> {
+        self.try_reserve(literals.len())?;
+        self.buffer.extend_from_slice(literals);
+        Ok(())
+    }
+
     /// Appends the bytes read from `reader` to the buffer of [`Lz77Decoder`].
-    pub fn extend_from_reader<R: io::Read>(&mut self, mut reader: R) -> io::Result<usize> {
-        reader.read_to_end(&mut self.buffer)
+    ///
+    /// If the buffer can not be grown, an error of `io::ErrorKind::OutOfMemory`
+    /// (`io::ErrorKind::Other` if the `no_std` feature is enabled) is returned instead of aborting.
+    pub fn extend_from_reader<R: io::Read>(&mut self, reader: R) -> io::Result<usize> {
+        self.extend_from_reader_up_to(reader, usize::MAX)
+    }
+
+    /// Same as [`Lz77Decoder::extend_from_reader`] except that at most `max_len` bytes are read.
+    ///
+    /// The buffer is grown by at most `max_len` bytes,
+    /// so this should be preferred if the length of the data is known in advance.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate_lz77::Lz77Decoder;
+    ///
+    /// let mut decoder = Lz77Decoder::new();
+    /// assert_eq!(decoder.extend_from_reader_up_to(&b"Hello World!"[..], 5).unwrap(), 5);
+    /// assert_eq!(decoder.buffer(), b"Hello");
+    /// assert!(decoder.memory_usage() < 8 * 1024);
+    /// ```
+    pub fn extend_from_reader_up_to<R: io::Read>(
+        &mut self,
+        mut reader: R,
+        max_len: usize,
+    ) -> io::Result<usize> {
+        const CHUNK_SIZE: usize = 8 * 1024;
+
+        let mut total = 0;
+        while total < max_len {
+            let chunk_size = core::cmp::min(CHUNK_SIZE, max_len - total);
+            self.try_reserve(chunk_size)?;
+            let old_len = self.buffer.len();
+            self.buffer.resize(old_len + chunk_size, 0);
+            let result = reader.read(&mut self.buffer[old_len..]);
+            let size = *result.as_ref().unwrap_or(&0);
+            self.buffer.truncate(old_len + size);
+            match result {
+                Ok(0) => break,
+                Ok(_) => total += size,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(total)
     }
 
     /// Appends the given bytes to the buffer of [`Lz77Decoder`].
+    ///
+    /// The bytes are regarded as already read (i.e., they are only used as the sliding window).
+    ///
+    /// This aborts the process if the buffer can not be grown.
+    /// Use [`Lz77Decoder::try_extend_from_slice`] to handle the failure.
     pub fn extend_from_slice(&mut self, buf: &[u8]) {
+        let buf = self.skip_overwritten_window(buf);
         self.buffer.extend_from_slice(buf);
         self.offset += buf.len();
+        self.truncate_old_buffer();
+    }
+
+    /// Same as [`Lz77Decoder::extend_from_slice`] except that
+    /// this returns an error if the buffer can not be grown.
+    pub fn try_extend_from_slice(&mut self, buf: &[u8]) -> io::Result<()> {
+        let buf = self.skip_overwritten_window(buf);
+        self.try_reserve(buf.len())?;
+        self.extend_from_slice(buf);
+        Ok(())
     }
 
     /// Clears the buffer of [`Lz77Decoder`].
@@ -217,16 +420,60 @@ impl Lz77Decoder {
         self.offset = 0;
     }
 
+    /// Returns the number of bytes currently allocated on the heap by [`Lz77Decoder`].
+    ///
+    /// The buffer keeps (at least) the last [`Lz77Decoder::window_size`] bytes as the sliding window,
+    /// and grows by the decoded data which have not been read yet.
+    /// Once the decoded data have been read, the buffer is shrunk to the window size
+    /// provided that it exceeds four times the window size.
+    pub fn memory_usage(&self) -> usize {
+        self.buffer.capacity()
+    }
+
     /// Returns the buffer of [`Lz77Decoder`].
     #[inline]
     pub fn buffer(&self) -> &[u8] {
         &self.buffer[self.offset..]
     }
 
+    /// Marks the first `amt` bytes of [`Lz77Decoder::buffer`] as read.
+    ///
+    /// `amt` is clamped to the length of the buffer.
+    pub fn consume(&mut self, amt: usize) {
+        self.offset += core::cmp::min(amt, self.buffer.len() - self.offset);
+        self.truncate_old_buffer();
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> io::Result<()> {
+        self.buffer.try_reserve(additional).map_err(|_| {
+            io::Error::new(
+                #[cfg(not(feature = "no_std"))]
+                io::ErrorKind::OutOfMemory,
+                #[cfg(feature = "no_std")]
+                io::ErrorKind::Other,
+                "Failed to grow the buffer of the LZ77 decoder",
+            )
+        })
+    }
+
+    /// If all the decoded data have been read and `buf` covers the whole window,
+    /// clears the buffer and returns only the last `window_size` bytes of `buf`
+    /// (the rest would be discarded from the window anyway).
+    fn skip_overwritten_window<'a>(&mut self, buf: &'a [u8]) -> &'a [u8] {
+        let window_size = usize::from(self.window_size);
+        if self.buffer().is_empty() && buf.len() >= window_size {
+            self.clear();
+            &buf[buf.len() - window_size..]
+        } else {
+            buf
+        }
+    }
+
     fn truncate_old_buffer(&mut self) {
-        if self.buffer().is_empty() && self.buffer.len() > MAX_DISTANCE as usize * 4 {
+        let window_size = usize::from(self.window_size);
+        if self.buffer().is_empty() && self.buffer.len() > window_size * 4 {
             let old_len = self.buffer.len();
-            let new_len = MAX_DISTANCE as usize;
+            let new_len = window_size;
             {
                 // isolation to please borrow checker
                 let (dst, src) = self.buffer.split_at_mut(old_len - new_len);
@@ -238,12 +485,23 @@ impl Lz77Decoder {
     }
 }
 
+/// The same as `rle_decode_fast::rle_decode` except that `buffer` may use a custom allocator.
+#[cfg(feature = "allocator_api")]
+fn rle_decode(buffer: &mut Buffer, lookbehind_length: usize, fill_length: usize) {
+    let start = buffer.len() - lookbehind_length;
+    let mut remaining = fill_length;
+    while remaining > 0 {
+        let size = core::cmp::min(remaining, buffer.len() - start);
+        buffer.extend_from_within(start..start + size);
+        remaining -= size;
+    }
+}
+
 impl io::Read for Lz77Decoder {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let copy_size = core::cmp::min(buf.len(), self.buffer.len() - self.offset);
         buf[..copy_size].copy_from_slice(&self.buffer[self.offset..][..copy_size]);
-        self.offset += copy_size;
-        self.truncate_old_buffer();
+        self.consume(copy_size);
         Ok(copy_size)
     }
 }
@@ -277,4 +535,65 @@ mod tests {
         assert_eq!(decoded, b"hello world!");
         assert!(decoder.buffer().is_empty());
     }
+
+    #[test]
+    fn min_length_works() {
+        let input = b"abcd-abcd-abcdefgh-abcdefgh";
+        for min_length in [MIN_LENGTH, 6, 9] {
+            let mut codes = Vec::new();
+            let mut encoder = DefaultLz77EncoderBuilder::new()
+                .min_length(min_length)
+                .build();
+            encoder.encode(input, &mut codes);
+            encoder.flush(&mut codes);
+            assert!(codes.iter().all(|code| match *code {
+                Code::Pointer { length, .. } => length >= min_length,
+                Code::Literal(_) => true,
+            }));
+
+            let mut decoder = Lz77Decoder::new();
+            for code in codes {
+                decoder.decode(code).unwrap();
+            }
+            assert_eq!(decoder.buffer(), input);
+        }
+    }
+
+    #[test]
+    fn buffer_growth_is_bounded_by_requested_length() {
+        let data = [7; 10_000];
+        let mut decoder = Lz77Decoder::new();
+        decoder.set_window_size(1024);
+
+        assert_eq!(
+            decoder.extend_from_reader_up_to(&data[..], 100).unwrap(),
+            100
+        );
+        assert_eq!(decoder.buffer(), &data[..100]);
+        assert!(decoder.memory_usage() < 1024, "{}", decoder.memory_usage());
+
+        // Reaches EOF before `max_len`
+        assert_eq!(
+            decoder.extend_from_reader_up_to(&data[..50], 1000).unwrap(),
+            50
+        );
+        assert_eq!(decoder.buffer().len(), 150);
+
+        // Only the last `window_size` bytes are kept as the window
+        decoder.consume(150);
+        decoder.try_extend_from_slice(&data).unwrap();
+        assert!(decoder.buffer().is_empty());
+        assert!(
+            decoder.memory_usage() <= 2 * 1024,
+            "{}",
+            decoder.memory_usage()
+        );
+        decoder
+            .decode(Code::Pointer {
+                length: 3,
+                backward_distance: 1024,
+            })
+            .unwrap();
+        assert_eq!(decoder.buffer(), [7; 3]);
+    }
 }
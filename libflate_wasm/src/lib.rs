@@ -0,0 +1,276 @@
+//! JavaScript bindings of [libflate](https://docs.rs/libflate) for WebAssembly.
+//!
+//! This crate exports the following API via [wasm-bindgen](https://docs.rs/wasm-bindgen):
+//!
+//! - One-shot functions: `compress(data, format)` and `decompress(data, format, max_output)`
+//! - Streaming classes: `Compressor` and `Decompressor`, which take input chunks by `push(chunk)`
+//!   and return the output produced so far
+//!
+//! `format` is one of `"deflate"`, `"zlib"` and `"gzip"`, and `decompress` also accepts `"auto"`
+//! (see [`libflate::any`]). All data are passed as `Uint8Array`s, and errors are thrown as JavaScript `Error`s.
+//!
+//! Since everything is built on the slice-in/slice-out API of [`libflate::push`],
+//! no blocking I/O is involved.
+#![warn(missing_docs)]
+use libflate::push::{Decoder, Encoder, Flush, Format, Status};
+use libflate::{any, deflate, gzip, zlib};
+use std::{cmp, fmt};
+use wasm_bindgen::prelude::*;
+
+/// The maximum number of the bytes decompressed at a time by `decompress` with `max_output`.
+const DECOMPRESS_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The error type of the internal functions, which is independent of the `no_std` feature of libflate.
+type Result<T> = std::result::Result<T, String>;
+
+/// Compresses `data` into a stream of `format`.
+#[wasm_bindgen]
+pub fn compress(data: &[u8], format: &str) -> std::result::Result<Vec<u8>, JsError> {
+    compress_impl(data, format).map_err(to_js_error)
+}
+
+/// Decompresses `data`, which is a stream of `format` (or of an automatically detected format if `"auto"`).
+///
+/// If the decompressed data exceed `max_output` bytes, an error is thrown
+/// (as soon as the limit is exceeded, so that a small malicious input can not exhaust the memory).
+/// `max_output` may be omitted (`undefined`) if `data` is trusted.
+///
+/// For GZIP, only the first member is decompressed.
+#[wasm_bindgen]
+pub fn decompress(
+    data: &[u8],
+    format: &str,
+    max_output: Option<usize>,
+) -> std::result::Result<Vec<u8>, JsError> {
+    decompress_impl(data, format, max_output).map_err(to_js_error)
+}
+
+/// Streaming compressor.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Compressor {
+    encoder: Encoder,
+}
+#[wasm_bindgen]
+impl Compressor {
+    /// Makes a new compressor which produces a stream of `format`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(format: &str) -> std::result::Result<Compressor, JsError> {
+        parse_format(format)
+            .map(|format| Compressor {
+                encoder: Encoder::new(format),
+            })
+            .map_err(to_js_error)
+    }
+
+    /// Compresses `chunk`, and returns the compressed data produced so far (which may be empty).
+    pub fn push(&mut self, chunk: &[u8]) -> std::result::Result<Vec<u8>, JsError> {
+        self.encode(chunk, Flush::None).map_err(to_js_error)
+    }
+
+    /// Returns all the compressed data of the chunks pushed so far (`Z_SYNC_FLUSH`).
+    pub fn flush(&mut self) -> std::result::Result<Vec<u8>, JsError> {
+        self.encode(&[], Flush::Sync).map_err(to_js_error)
+    }
+
+    /// Terminates the stream, and returns the rest of the compressed data.
+    pub fn finish(&mut self) -> std::result::Result<Vec<u8>, JsError> {
+        self.encode(&[], Flush::Finish).map_err(to_js_error)
+    }
+
+    fn encode(&mut self, chunk: &[u8], flush: Flush) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.encoder
+            .encode_to_vec(chunk, &mut output, flush)
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+}
+
+/// Streaming decompressor.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct Decompressor {
+    decoder: Decoder,
+}
+#[wasm_bindgen]
+impl Decompressor {
+    /// Makes a new decompressor of a stream of `format` (`"auto"` is not accepted).
+    #[wasm_bindgen(constructor)]
+    pub fn new(format: &str) -> std::result::Result<Decompressor, JsError> {
+        parse_format(format)
+            .map(|format| Decompressor {
+                decoder: Decoder::new(format),
+            })
+            .map_err(to_js_error)
+    }
+
+    /// Decompresses `chunk`, and returns the decompressed data produced so far (which may be empty).
+    ///
+    /// The bytes following the end of the stream are ignored.
+    pub fn push(&mut self, chunk: &[u8]) -> std::result::Result<Vec<u8>, JsError> {
+        self.decode(chunk).map_err(to_js_error)
+    }
+
+    /// Returns `true` if the end of the stream has been reached.
+    #[wasm_bindgen(getter, js_name = isFinished)]
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Throws an error if the end of the stream has not been reached (i.e., the stream is truncated).
+    pub fn finish(&self) -> std::result::Result<(), JsError> {
+        self.check_finished().map_err(to_js_error)
+    }
+
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.decoder
+            .decode_to_vec(chunk, &mut output)
+            .map_err(|e| e.to_string())?;
+        Ok(output)
+    }
+
+    fn check_finished(&self) -> Result<()> {
+        if self.decoder.is_finished() {
+            Ok(())
+        } else {
+            Err("The compressed stream is truncated".to_owned())
+        }
+    }
+}
+
+fn compress_impl(data: &[u8], format: &str) -> Result<Vec<u8>> {
+    match parse_format(format)? {
+        Format::Deflate => deflate::compress_to_vec(data),
+        Format::Zlib => zlib::compress_to_vec(data),
+        Format::Gzip => gzip::compress_to_vec(data),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn decompress_impl(data: &[u8], format: &str, max_output: Option<usize>) -> Result<Vec<u8>> {
+    let format = if format == "auto" {
+        any::detect_format(data)
+    } else {
+        parse_format(format)?
+    };
+    let max_output = match max_output {
+        Some(max_output) => max_output,
+        None => {
+            return match format {
+                Format::Deflate => deflate::decompress_to_vec(data),
+                Format::Zlib => zlib::decompress_to_vec(data),
+                Format::Gzip => gzip::decompress_to_vec(data),
+            }
+            .map_err(|e| e.to_string());
+        }
+    };
+
+    // The output is grown by at most `DECOMPRESS_CHUNK_SIZE` bytes at a time (and one byte past the limit to detect the excess)
+    let mut decoder = Decoder::new(format);
+    let mut input = data;
+    let mut output = Vec::new();
+    loop {
+        let len = output.len();
+        let size = cmp::min(DECOMPRESS_CHUNK_SIZE, max_output.saturating_add(1) - len);
+        output.resize(len + size, 0);
+        let progress = decoder
+            .decode(input, &mut output[len..])
+            .map_err(|e| e.to_string())?;
+        output.truncate(len + progress.produced);
+        input = &input[progress.consumed..];
+        if output.len() > max_output {
+            return Err(format!(
+                "The size of the decompressed data exceeds the limit ({} bytes)",
+                max_output
+            ));
+        }
+        if progress.status == Status::StreamEnd {
+            return Ok(output);
+        }
+        if progress.produced < size {
+            // The input has been exhausted before the end of the stream
+            return Err("The compressed stream is truncated".to_owned());
+        }
+    }
+}
+
+fn parse_format(format: &str) -> Result<Format> {
+    match format {
+        "deflate" => Ok(Format::Deflate),
+        "zlib" => Ok(Format::Zlib),
+        "gzip" => Ok(Format::Gzip),
+        _ => Err(format!("unknown format: {:?}", format)),
+    }
+}
+
+fn to_js_error<E: fmt::Display>(e: E) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    // Only the error-free paths of the exported functions can be called on non-wasm targets,
+    // because `JsError` is made by a JavaScript call.
+    use super::*;
+
+    #[test]
+    fn one_shot_functions_work() {
+        for format in &["deflate", "zlib", "gzip"] {
+            let encoded = compress(b"Hello World!", format).unwrap();
+            assert_eq!(decompress(&encoded, format, None).unwrap(), b"Hello World!");
+            assert_eq!(decompress(&encoded, "auto", None).unwrap(), b"Hello World!");
+            assert_eq!(
+                decompress(&encoded, format, Some(12)).unwrap(),
+                b"Hello World!"
+            );
+        }
+        assert!(compress_impl(b"Hello World!", "bzip2").is_err());
+        assert!(decompress_impl(b"garbage", "gzip", None).is_err());
+        assert!(decompress_impl(b"garbage", "gzip", Some(100)).is_err());
+    }
+
+    #[test]
+    fn output_size_is_limited() {
+        let plain = vec![0; 1_000_000];
+        for format in &["deflate", "zlib", "gzip"] {
+            let encoded = compress(&plain, format).unwrap();
+            for &max in &[0, 999_999] {
+                let e = decompress_impl(&encoded, format, Some(max)).unwrap_err();
+                assert!(e.contains("exceeds the limit"), "{}", e);
+            }
+            for &max in &[1_000_000, usize::MAX] {
+                assert_eq!(decompress_impl(&encoded, format, Some(max)).unwrap(), plain);
+            }
+            let truncated = &encoded[..encoded.len() / 2];
+            assert!(decompress_impl(truncated, format, Some(usize::MAX)).is_err());
+        }
+    }
+
+    #[test]
+    fn streaming_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for format in &["deflate", "zlib", "gzip"] {
+            let mut compressor = Compressor::new(format).unwrap();
+            let mut encoded = Vec::new();
+            for chunk in plain.chunks(10_000) {
+                encoded.extend(compressor.push(chunk).unwrap());
+            }
+            encoded.extend(compressor.flush().unwrap());
+            encoded.extend(compressor.finish().unwrap());
+
+            let mut decompressor = Decompressor::new(format).unwrap();
+            let mut decoded = Vec::new();
+            for chunk in encoded.chunks(1000) {
+                assert!(decompressor.check_finished().is_err());
+                decoded.extend(decompressor.push(chunk).unwrap());
+            }
+            assert!(decompressor.is_finished());
+            decompressor.finish().unwrap();
+            assert_eq!(decoded, plain);
+        }
+    }
+}
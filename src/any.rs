@@ -0,0 +1,537 @@
+//! A decoder which automatically detects the format (GZIP, ZLIB or raw DEFLATE) of its input.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::{Read, Write};
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::{Read, Write};
+//! use libflate::any::Decoder;
+//! use libflate::push::Format;
+//! use libflate::{deflate, gzip, zlib};
+//!
+//! let mut gzip_encoder = gzip::Encoder::new(Vec::new()).unwrap();
+//! gzip_encoder.write_all(b"Hello World!").unwrap();
+//! let gzip_data = gzip_encoder.finish().into_result().unwrap();
+//!
+//! let mut zlib_encoder = zlib::Encoder::new(Vec::new()).unwrap();
+//! zlib_encoder.write_all(b"Hello World!").unwrap();
+//! let zlib_data = zlib_encoder.finish().into_result().unwrap();
+//!
+//! let mut deflate_encoder = deflate::Encoder::new(Vec::new());
+//! deflate_encoder.write_all(b"Hello World!").unwrap();
+//! let deflate_data = deflate_encoder.finish().into_result().unwrap();
+//!
+//! for (data, format) in &[(gzip_data, Format::Gzip),
+//!                         (zlib_data, Format::Zlib),
+//!                         (deflate_data, Format::Deflate)] {
+//!     let mut decoder = Decoder::new(&data[..]).unwrap();
+//!     assert_eq!(decoder.format(), *format);
+//!
+//!     let mut buf = Vec::new();
+//!     decoder.read_to_end(&mut buf).unwrap();
+//!     assert_eq!(buf, b"Hello World!");
+//! }
+//! ```
+use crate::deflate;
+use crate::error::{Error, ErrorKind};
+use crate::gzip;
+use crate::push::Format;
+use crate::util::{self, PrefixedReader};
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use core2::io::{self, BufRead, Read};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, BufRead, Read};
+
+/// Guesses the format of a compressed stream from its first two bytes.
+///
+/// GZIP is recognized by its magic number and ZLIB by a valid CMF/FLG header pair.
+/// Any other input (including one shorter than two bytes) is regarded as raw DEFLATE.
+///
+/// Note that a raw DEFLATE stream can accidentally look like a ZLIB header (about one in five hundred chance).
+///
+/// # Examples
+/// ```
+/// use libflate::any::detect_format;
+/// use libflate::push::Format;
+///
+/// assert_eq!(detect_format(&[31, 139, 8, 0]), Format::Gzip);
+/// assert_eq!(detect_format(&[120, 156, 243, 72]), Format::Zlib);
+/// assert_eq!(detect_format(&[243, 72, 205, 201]), Format::Deflate);
+/// ```
+pub fn detect_format(prefix: &[u8]) -> Format {
+    match *prefix {
+        [0x1F, 0x8B, ..] => Format::Gzip,
+        [cmf, flg, ..] if is_zlib_header(cmf, flg) => Format::Zlib,
+        _ => Format::Deflate,
+    }
+}
+
+pub(crate) fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    let compression_method = cmf & 0b1111;
+    let window_size_bits = cmf >> 4;
+    compression_method == 8
+        && window_size_bits <= 7
+        && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0
+}
+
+/// The format of a compressed stream and the metadata of its header, identified by [`sniff`].
+#[derive(Debug, Clone)]
+pub struct FormatInfo {
+    format: Format,
+    header_size: Option<usize>,
+    gzip_header: Option<gzip::Header>,
+    zlib_header: Option<zlib::Header>,
+}
+impl FormatInfo {
+    /// Returns the identified format.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the number of the bytes of the header (or the header and the preset dictionary ID of ZLIB),
+    /// which is `0` for raw DEFLATE.
+    ///
+    /// Returns `None` if the header is longer than the sniffed bytes.
+    pub fn header_size(&self) -> Option<usize> {
+        self.header_size
+    }
+
+    /// Returns the GZIP header, or `None` if the stream is not GZIP or its header is longer than the sniffed bytes.
+    pub fn gzip_header(&self) -> Option<&gzip::Header> {
+        self.gzip_header.as_ref()
+    }
+
+    /// Returns the ZLIB header, or `None` if the stream is not ZLIB.
+    pub fn zlib_header(&self) -> Option<&zlib::Header> {
+        self.zlib_header.as_ref()
+    }
+}
+
+/// Identifies the format of a compressed stream from its first bytes, and parses its header.
+///
+/// Unlike [`detect_format`], this returns `None` if the bytes are not the start of any supported stream
+/// (a GZIP stream with a malformed header, or something which can't even be a raw DEFLATE stream),
+/// or if they are too short (less than two bytes) to tell.
+/// Raw DEFLATE is still only a guess, whose first block header and (for a stored block) length fields are checked.
+///
+/// # Examples
+/// ```
+/// use libflate::any::sniff;
+/// use libflate::push::Format;
+/// use libflate::zlib::Lz77WindowSize;
+///
+/// let info = sniff(&[31, 139, 8, 8, 0, 0, 0, 0, 0, 3, b'a', 0, 3, 0]).unwrap();
+/// assert_eq!(info.format(), Format::Gzip);
+/// assert_eq!(info.header_size(), Some(12));
+/// assert_eq!(info.gzip_header().unwrap().filename().unwrap().to_bytes(), b"a");
+///
+/// let info = sniff(&[120, 156, 243, 72]).unwrap();
+/// assert_eq!(info.format(), Format::Zlib);
+/// assert_eq!(info.zlib_header().unwrap().window_size(), Lz77WindowSize::KB32);
+///
+/// assert_eq!(sniff(&[243, 72, 205, 201]).unwrap().format(), Format::Deflate);
+/// assert!(sniff(&[0xFF, 0xFF]).is_none()); // A reserved block type
+/// ```
+pub fn sniff(prefix: &[u8]) -> Option<FormatInfo> {
+    let mut info = FormatInfo {
+        format: detect_format(prefix),
+        header_size: None,
+        gzip_header: None,
+        zlib_header: None,
+    };
+    match (info.format, prefix) {
+        (_, [] | [_]) => return None,
+        (Format::Gzip, _) => {
+            let mut reader = prefix;
+            match gzip::Header::read_from(&mut reader) {
+                Ok(header) => {
+                    info.header_size = Some(prefix.len() - reader.len());
+                    info.gzip_header = Some(header);
+                }
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+                Err(_) => return None,
+            }
+        }
+        (Format::Zlib, &[cmf, flg, ..]) => {
+            let header_size = if flg & 0b10_0000 != 0 { 6 } else { 2 };
+            if header_size <= prefix.len() {
+                info.header_size = Some(header_size);
+            }
+            let window_size = zlib::Lz77WindowSize::from_u4(cmf >> 4)?;
+            let level = zlib::CompressionLevel::from_u2(flg >> 6);
+            info.zlib_header = Some(zlib::Header::new(window_size, level));
+        }
+        (_, &[first, ..]) => {
+            match (first >> 1) & 0b11 {
+                0b00 if prefix.len() >= 5 => {
+                    // A stored block: `LEN` must be the one's complement of `NLEN`
+                    let len = u16::from_le_bytes([prefix[1], prefix[2]]);
+                    let nlen = u16::from_le_bytes([prefix[3], prefix[4]]);
+                    if len != !nlen {
+                        return None;
+                    }
+                }
+                0b11 => return None,
+                _ => {}
+            }
+            info.header_size = Some(0);
+        }
+    }
+    Some(info)
+}
+
+/// Identifies the format of the stream read by `reader` like [`sniff`], without consuming any bytes.
+///
+/// Only the bytes already buffered (or read by a single `fill_buf` call) are examined,
+/// so the header of a GZIP stream may not be parsed even if it is valid.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::{BufRead, BufReader};
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::{BufRead, BufReader};
+/// use libflate::any::sniff_reader;
+/// use libflate::push::Format;
+///
+/// let mut reader = BufReader::new(&[120, 156, 243, 72][..]);
+/// let info = sniff_reader(&mut reader).unwrap().unwrap();
+/// assert_eq!(info.format(), Format::Zlib);
+/// assert_eq!(reader.fill_buf().unwrap().len(), 4);
+/// ```
+pub fn sniff_reader<R: BufRead>(reader: &mut R) -> io::Result<Option<FormatInfo>> {
+    util::fill_buf(reader).map(sniff)
+}
+
+/// Decoder which detects the format of the input stream and then decodes it.
+///
+/// See [`detect_format`] for the detection rule.
+#[derive(Debug)]
+pub struct Decoder<R> {
+    inner: Inner<R>,
+}
+
+#[derive(Debug)]
+enum Inner<R> {
+    Deflate(deflate::Decoder<PrefixedReader<R>>),
+    Zlib(zlib::Decoder<PrefixedReader<R>>),
+    Gzip(gzip::Decoder<PrefixedReader<R>>),
+}
+
+impl<R> Decoder<R>
+where
+    R: Read,
+{
+    /// Makes a new decoder instance.
+    ///
+    /// The first bytes of `inner` are read to detect the format,
+    /// and then the header of the stream (if any) is read.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::any::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let prefix = util::read_prefix(&mut inner, 2)?;
+        if prefix.len() < 2 {
+            // Even the shortest DEFLATE stream (an empty fixed Huffman block) takes two bytes
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "The stream ended before its format was detected",
+            )
+            .with_value(prefix.len() as u64)
+            .with_expected(2u64)
+            .into());
+        }
+        let format = detect_format(&prefix);
+        let reader = PrefixedReader::new(prefix, inner);
+        let inner = match format {
+            Format::Deflate => Inner::Deflate(deflate::Decoder::new(reader)),
+            Format::Zlib => Inner::Zlib(zlib::Decoder::new(reader)?),
+            Format::Gzip => Inner::Gzip(gzip::Decoder::new(reader)?),
+        };
+        Ok(Decoder { inner })
+    }
+
+    /// Returns the detected format.
+    pub fn format(&self) -> Format {
+        match self.inner {
+            Inner::Deflate(_) => Format::Deflate,
+            Inner::Zlib(_) => Format::Zlib,
+            Inner::Gzip(_) => Format::Gzip,
+        }
+    }
+
+    /// Returns the number of the (compressed) bytes read from the inner stream, including the header and the trailer.
+    pub fn total_in(&self) -> u64 {
+        match self.inner {
+            Inner::Deflate(ref x) => x.total_in(),
+            Inner::Zlib(ref x) => x.total_in(),
+            Inner::Gzip(ref x) => x.total_in(),
+        }
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        match self.inner {
+            Inner::Deflate(ref x) => x.total_out(),
+            Inner::Zlib(ref x) => x.total_out(),
+            Inner::Gzip(ref x) => x.total_out(),
+        }
+    }
+
+    /// Returns the header of the GZIP stream, or `None` if the stream is not GZIP.
+    pub fn gzip_header(&self) -> Option<&gzip::Header> {
+        if let Inner::Gzip(ref x) = self.inner {
+            Some(x.header())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the header of the ZLIB stream, or `None` if the stream is not ZLIB.
+    pub fn zlib_header(&self) -> Option<&zlib::Header> {
+        if let Inner::Zlib(ref x) = self.inner {
+            Some(x.header())
+        } else {
+            None
+        }
+    }
+
+    /// Discards the next `n` decoded bytes without copying them.
+    ///
+    /// See [`deflate::Decoder::skip`] for the details.
+    pub fn skip(&mut self, n: u64) -> io::Result<u64> {
+        util::skip(self, n)
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        match self.inner {
+            Inner::Deflate(ref x) => x.as_inner_ref().as_inner_ref(),
+            Inner::Zlib(ref x) => x.as_inner_ref().as_inner_ref(),
+            Inner::Gzip(ref x) => x.as_inner_ref().as_inner_ref(),
+        }
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        match self.inner {
+            Inner::Deflate(ref mut x) => x.as_inner_mut().as_inner_mut(),
+            Inner::Zlib(ref mut x) => x.as_inner_mut().as_inner_mut(),
+            Inner::Gzip(ref mut x) => x.as_inner_mut().as_inner_mut(),
+        }
+    }
+
+    /// Unwraps this `Decoder`, returning the underlying reader.
+    ///
+    /// Note that the bytes which have been read for the format detection but not decoded yet will be lost.
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            Inner::Deflate(x) => x.into_inner().into_inner(),
+            Inner::Zlib(x) => x.into_inner().into_inner(),
+            Inner::Gzip(x) => x.into_inner().into_inner(),
+        }
+    }
+}
+impl<R> Read for Decoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner {
+            Inner::Deflate(ref mut x) => x.read(buf),
+            Inner::Zlib(ref mut x) => x.read(buf),
+            Inner::Gzip(ref mut x) => x.read(buf),
+        }
+    }
+}
+impl<R> BufRead for Decoder<R>
+where
+    R: Read,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.inner {
+            Inner::Deflate(ref mut x) => x.fill_buf(),
+            Inner::Zlib(ref mut x) => x.fill_buf(),
+            Inner::Gzip(ref mut x) => x.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self.inner {
+            Inner::Deflate(ref mut x) => x.consume(amt),
+            Inner::Zlib(ref mut x) => x.consume(amt),
+            Inner::Gzip(ref mut x) => x.consume(amt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "no_std")]
+    use core2::io::Write;
+    #[cfg(not(feature = "no_std"))]
+    use std::io::Write;
+
+    #[test]
+    fn zlib_header_detection_works() {
+        for header in &[
+            [0x78, 0x01],
+            [0x78, 0x5E],
+            [0x78, 0x9C],
+            [0x78, 0xDA],
+            [0x08, 0x1D],
+        ] {
+            assert_eq!(detect_format(header), Format::Zlib);
+        }
+        assert_eq!(detect_format(&[0x78, 0x9D]), Format::Deflate); // Bad check bits
+        assert_eq!(detect_format(&[0x88, 0x98]), Format::Deflate); // Too large window
+        assert_eq!(detect_format(&[0x1F]), Format::Deflate);
+        assert_eq!(detect_format(&[]), Format::Deflate);
+    }
+
+    #[test]
+    fn sniff_works() {
+        let plain = b"Hello World!";
+        let header = gzip::HeaderBuilder::new()
+            .filename_str("foo.txt")
+            .unwrap()
+            .finish();
+        let options = gzip::EncodeOptions::new().header(header);
+        let mut encoder = gzip::Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(plain).unwrap();
+        let gzip_data = encoder.finish().into_result().unwrap();
+
+        let info = sniff(&gzip_data).unwrap();
+        assert_eq!(info.format(), Format::Gzip);
+        assert_eq!(info.header_size(), Some(18));
+        assert_eq!(
+            info.gzip_header().unwrap().filename().unwrap().to_bytes(),
+            b"foo.txt"
+        );
+        assert!(info.zlib_header().is_none());
+
+        // A truncated header is still identified, but not parsed
+        let info = sniff(&gzip_data[..12]).unwrap();
+        assert_eq!(info.format(), Format::Gzip);
+        assert!(info.header_size().is_none());
+        assert!(info.gzip_header().is_none());
+
+        // An unsupported compression method
+        let mut broken = gzip_data.clone();
+        broken[2] = 7;
+        assert!(sniff(&broken).is_none());
+
+        let zlib_data = zlib::compress_to_vec(plain).unwrap();
+        let info = sniff(&zlib_data).unwrap();
+        assert_eq!(info.format(), Format::Zlib);
+        assert_eq!(info.header_size(), Some(2));
+        assert!(info.zlib_header().is_some());
+        // With a preset dictionary
+        let info = sniff(&[0x78, 0xBB, 0, 0]).unwrap();
+        assert_eq!(info.format(), Format::Zlib);
+        assert!(info.header_size().is_none());
+        assert_eq!(
+            sniff(&[0x78, 0xBB, 0, 0, 0, 1]).unwrap().header_size(),
+            Some(6)
+        );
+
+        let deflate_data = deflate::compress_to_vec(plain).unwrap();
+        let info = sniff(&deflate_data).unwrap();
+        assert_eq!(info.format(), Format::Deflate);
+        assert_eq!(info.header_size(), Some(0));
+        let stored = [0x01, 0x03, 0x00, 0xFC, 0xFF, b'f', b'o', b'o'];
+        assert_eq!(sniff(&stored).unwrap().format(), Format::Deflate);
+        assert!(sniff(&[0x01, 0x03, 0x00, 0xFC, 0xFE]).is_none());
+        assert!(sniff(&[0x07, 0x00]).is_none());
+        assert!(sniff(&[0x03]).is_none());
+        assert!(sniff(&[]).is_none());
+
+        let mut reader = &zlib_data[..];
+        let info = sniff_reader(&mut reader).unwrap().unwrap();
+        assert_eq!(info.format(), Format::Zlib);
+        assert_eq!(reader, &zlib_data[..]);
+    }
+
+    #[test]
+    fn short_deflate_stream_works() {
+        // An empty final block with the fixed Huffman codes
+        let encoded = [0x03, 0x00];
+
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        assert_eq!(decoder.format(), Format::Deflate);
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn headers_are_accessible() {
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(b"foo").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let decoder = Decoder::new(&encoded[..]).unwrap();
+        assert!(decoder.gzip_header().is_some());
+        assert!(decoder.zlib_header().is_none());
+    }
+
+    #[test]
+    fn malformed_input_never_panics() {
+        let plain = (0..10_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let streams = [
+            deflate::compress_to_vec(&plain).unwrap(),
+            zlib::compress_to_vec(&plain).unwrap(),
+            gzip::compress_to_vec(&plain).unwrap(),
+        ];
+
+        // A deterministic xorshift generator
+        let mut seed = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as usize
+        };
+        for i in 0..300 {
+            let mut input = streams[i % streams.len()].clone();
+            for _ in 0..next() % 4 + 1 {
+                let position = next() % input.len();
+                match next() % 3 {
+                    0 => input[position] ^= 1 << (next() % 8),
+                    1 => input[position] = next() as u8,
+                    _ => input.truncate(position + 1),
+                }
+            }
+
+            // Every outcome (success or error) is fine as long as it doesn't panic
+            if let Ok(mut decoder) = Decoder::new(&input[..]) {
+                let _ = decoder.read_to_end(&mut Vec::new());
+            }
+            if let Ok(mut decoder) = gzip::MultiDecoder::new(&input[..]) {
+                let _ = decoder.read_to_end(&mut Vec::new());
+            }
+            let mut decoder = crate::push::Decoder::new(Format::Zlib);
+            let _ = decoder.decode(&input, &mut vec![0; plain.len()]);
+        }
+    }
+}
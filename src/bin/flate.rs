@@ -0,0 +1,226 @@
+//! A gzip-like command line tool built on libflate.
+//!
+//! ```console
+//! $ cargo install libflate --features cli
+//! $ flate foo.txt            # Compresses `foo.txt` into `foo.txt.gz` (and removes `foo.txt`)
+//! $ flate -d foo.txt.gz      # Decompresses `foo.txt.gz` into `foo.txt`
+//! $ flate -c < foo > foo.gz  # Compresses the standard input
+//! ```
+extern crate clap;
+extern crate libflate;
+
+#[cfg(not(feature = "no_std"))]
+use clap::{App, Arg, ArgMatches};
+#[cfg(not(feature = "no_std"))]
+use libflate::codec;
+#[cfg(not(feature = "no_std"))]
+use libflate::copy::Copier;
+#[cfg(not(feature = "no_std"))]
+use libflate::deflate;
+#[cfg(not(feature = "no_std"))]
+use libflate::gzip;
+#[cfg(not(feature = "no_std"))]
+use libflate::lz77::CompressionLevel;
+#[cfg(not(feature = "no_std"))]
+use libflate::options::CompressionOptions;
+#[cfg(not(feature = "no_std"))]
+use libflate::push::Format;
+#[cfg(not(feature = "no_std"))]
+use std::fs;
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::process;
+
+#[cfg(feature = "no_std")]
+fn main() {}
+
+#[cfg(not(feature = "no_std"))]
+fn main() {
+    let matches = App::new("flate")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Compresses or decompresses files in the GZIP, ZLIB or raw DEFLATE format")
+        .arg(
+            Arg::with_name("FILE")
+                .help("Files to be processed (the standard input if omitted or `-`)")
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("DECOMPRESS")
+                .short("d")
+                .long("decompress")
+                .help("Decompresses instead of compressing"),
+        )
+        .arg(
+            Arg::with_name("STDOUT")
+                .short("c")
+                .long("stdout")
+                .help("Writes to the standard output, keeping the input files"),
+        )
+        .arg(
+            Arg::with_name("KEEP")
+                .short("k")
+                .long("keep")
+                .help("Keeps the input files"),
+        )
+        .arg(
+            Arg::with_name("FORCE")
+                .short("f")
+                .long("force")
+                .help("Overwrites the existing output files"),
+        )
+        .arg(
+            Arg::with_name("FORMAT")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["gzip", "zlib", "deflate"])
+                .default_value("gzip"),
+        )
+        .arg(
+            Arg::with_name("LEVEL")
+                .short("l")
+                .long("level")
+                .help("0 (no compression), 1-3 (fast), 4-6 (balanced) or 7-9 (best)")
+                .takes_value(true)
+                .default_value("6"),
+        )
+        .arg(
+            Arg::with_name("THREADS")
+                .short("t")
+                .long("threads")
+                .help("The number of compression threads")
+                .takes_value(true)
+                .default_value("1"),
+        )
+        .get_matches();
+
+    let config = Config::from_matches(&matches).unwrap_or_else(|e| {
+        eprintln!("flate: {}", e);
+        process::exit(2);
+    });
+    let files = matches
+        .values_of("FILE")
+        .map(|files| files.collect::<Vec<_>>())
+        .unwrap_or_else(|| vec!["-"]);
+    let mut failed = false;
+    for file in files {
+        if let Err(e) = config.process(file) {
+            eprintln!("flate: {}: {}", file, e);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+struct Config {
+    decompress: bool,
+    stdout: bool,
+    keep: bool,
+    force: bool,
+    format: Format,
+    options: CompressionOptions,
+}
+#[cfg(not(feature = "no_std"))]
+impl Config {
+    fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let format = match matches.value_of("FORMAT") {
+            Some("zlib") => Format::Zlib,
+            Some("deflate") => Format::Deflate,
+            _ => Format::Gzip,
+        };
+        let level = match matches.value_of("LEVEL").and_then(|x| x.parse::<u8>().ok()) {
+            Some(0) => CompressionLevel::None,
+            Some(1..=3) => CompressionLevel::Fast,
+            Some(4..=6) => CompressionLevel::Balance,
+            Some(7..=9) => CompressionLevel::Best,
+            _ => return Err("the level must be an integer from 0 to 9".to_owned()),
+        };
+        let threads = match matches.value_of("THREADS").and_then(|x| x.parse().ok()) {
+            Some(threads) if threads > 0 => threads,
+            _ => return Err("the number of threads must be a positive integer".to_owned()),
+        };
+        Ok(Config {
+            decompress: matches.is_present("DECOMPRESS"),
+            stdout: matches.is_present("STDOUT"),
+            keep: matches.is_present("KEEP"),
+            force: matches.is_present("FORCE"),
+            format,
+            options: CompressionOptions::new().level(level).threads(threads),
+        })
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self.format {
+            Format::Gzip => ".gz",
+            Format::Zlib => ".zz",
+            Format::Deflate => ".deflate",
+        }
+    }
+
+    fn process(&self, file: &str) -> io::Result<()> {
+        if file == "-" {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            return self.convert(stdin.lock(), stdout.lock());
+        }
+
+        let input = io::BufReader::new(fs::File::open(file)?);
+        if self.stdout {
+            let stdout = io::stdout();
+            return self.convert(input, stdout.lock());
+        }
+
+        let output_file = if self.decompress {
+            file.strip_suffix(self.suffix())
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown suffix (expected `{}`)", self.suffix()),
+                    )
+                })?
+                .to_owned()
+        } else {
+            format!("{}{}", file, self.suffix())
+        };
+        let output = if self.force {
+            fs::File::create(&output_file)
+        } else {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&output_file)
+        }
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", output_file, e)))?;
+
+        if let Err(e) = self.convert(input, io::BufWriter::new(output)) {
+            let _ = fs::remove_file(&output_file);
+            return Err(e);
+        }
+        if !self.keep {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+
+    fn convert<R: Read, W: Write>(&self, mut input: R, mut output: W) -> io::Result<()> {
+        if !self.decompress {
+            let mut encoder = codec::compressor(self.format, output, self.options.clone())?;
+            Copier::new().copy(&mut input, &mut encoder)?;
+            encoder.finish_boxed().into_result()?.flush()
+        } else if self.format == Format::Gzip {
+            // Concatenated GZIP members are decompressed as a whole (like gunzip)
+            let mut decoder = gzip::MultiDecoder::new(input)?;
+            Copier::new().decode(&mut decoder, &mut output)?;
+            output.flush()
+        } else {
+            let options = deflate::DecodeOptions::new();
+            let mut decoder = codec::decompressor(self.format, input, options)?;
+            Copier::new().copy(&mut decoder, &mut output)?;
+            output.flush()
+        }
+    }
+}
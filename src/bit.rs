@@ -1,8 +1,38 @@
+//! Bit-level reader and writer.
+//!
+//! The bits are packed starting from the least significant bit of each byte (LSB-first), like in DEFLATE.
+//! These are useful for inspecting DEFLATE streams at the bit level and for implementing adjacent formats.
+//!
+//! # Examples
+//! ```
+//! use libflate::bit::{BitReader, BitWriter};
+//!
+//! let mut writer = BitWriter::new(Vec::new());
+//! writer.write_bit(true).unwrap();
+//! writer.write_bits(2, 0b10).unwrap();
+//! writer.align_to_byte().unwrap();
+//! writer.write_bits(16, 0xABCD).unwrap();
+//! writer.flush().unwrap();
+//! let buf = writer.into_inner();
+//! assert_eq!(buf, [0b101, 0xCD, 0xAB]);
+//!
+//! let mut reader = BitReader::new(&buf[..]);
+//! assert_eq!(reader.peek_bits(3).unwrap(), 0b101);
+//! assert!(reader.read_bit().unwrap());
+//! assert_eq!(reader.read_bits(2).unwrap(), 0b10);
+//! reader.align_to_byte();
+//! assert_eq!(reader.read_bits(16).unwrap(), 0xABCD);
+//! assert!(reader.read_bit().is_err());
+//! ```
+use crate::util;
+#[cfg(feature = "no_std")]
+use core::cmp;
 #[cfg(feature = "no_std")]
 use core2::io;
 #[cfg(not(feature = "no_std"))]
-use std::io;
+use std::{cmp, io};
 
+/// Writer which writes bits to the inner writer.
 #[derive(Debug)]
 pub struct BitWriter<W> {
     inner: W,
@@ -13,6 +43,7 @@ impl<W> BitWriter<W>
 where
     W: io::Write,
 {
+    /// Makes a new `BitWriter` instance.
     pub fn new(inner: W) -> Self {
         BitWriter {
             inner,
@@ -20,25 +51,47 @@ where
             end: 0,
         }
     }
+    /// Writes a bit.
     #[inline(always)]
     pub fn write_bit(&mut self, bit: bool) -> io::Result<()> {
         self.write_bits(1, bit as u16)
     }
+    /// Writes the lowest `bitwidth` bits of `bits` (starting from the least significant one).
+    ///
+    /// `bitwidth` must be at most 16, and the other bits of `bits` must be zero,
+    /// otherwise an `io::ErrorKind::InvalidInput` error is returned.
     #[inline(always)]
     pub fn write_bits(&mut self, bitwidth: u8, bits: u16) -> io::Result<()> {
-        debug_assert!(bitwidth < 16);
+        check_bitwidth(bitwidth)?;
+        if bitwidth < 16 && bits >> bitwidth != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The bits out of the bit width must be zero",
+            ));
+        }
         debug_assert!(self.end + bitwidth <= 32);
         self.buf |= u32::from(bits) << self.end;
         self.end += bitwidth;
         self.flush_if_needed()
     }
+    /// Pads the written bits with zeros to the next byte boundary (does nothing if they are already aligned).
+    pub fn align_to_byte(&mut self) -> io::Result<()> {
+        self.end = self.end.div_ceil(8) * 8;
+        self.flush_if_needed()
+    }
+
+    /// Writes the pending bits (padded with zeros to a byte boundary) and flushes the inner writer.
     pub fn flush(&mut self) -> io::Result<()> {
+        self.write_pending_bits()?;
+        util::retry_interrupted(|| self.inner.flush())
+    }
+    /// Writes the pending bits (padded with zeros to a byte boundary) without flushing the inner writer.
+    pub(crate) fn write_pending_bits(&mut self) -> io::Result<()> {
         while self.end > 0 {
             self.inner.write_all(&[self.buf as u8])?;
             self.buf >>= 8;
             self.end = self.end.saturating_sub(8);
         }
-        self.inner.flush()?;
         Ok(())
     }
     #[inline(always)]
@@ -52,17 +105,42 @@ where
     }
 }
 impl<W> BitWriter<W> {
+    /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &W {
         &self.inner
     }
+    /// Returns the bits which have not been written to the inner stream yet, and the number of them.
+    pub(crate) fn pending_bits(&self) -> (u32, u8) {
+        (self.buf, self.end)
+    }
+    /// Makes a new instance which will write `pending_bits` (see `pending_bits()`) before anything else.
+    pub(crate) fn with_pending_bits(inner: W, (buf, end): (u32, u8)) -> Self {
+        BitWriter { inner, buf, end }
+    }
+    /// Returns the mutable reference to the inner stream.
     pub fn as_inner_mut(&mut self) -> &mut W {
         &mut self.inner
     }
+    /// Unwraps this `BitWriter`, returning the underlying writer.
+    ///
+    /// Note that the bits which have not been flushed will be lost.
     pub fn into_inner(self) -> W {
         self.inner
     }
 }
 
+#[inline(always)]
+fn check_bitwidth(bitwidth: u8) -> io::Result<()> {
+    if bitwidth > 16 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The bit width must be at most 16",
+        ));
+    }
+    Ok(())
+}
+
+/// Reader which reads bits from the inner reader.
 #[derive(Debug)]
 pub struct BitReader<R> {
     inner: R,
@@ -74,6 +152,7 @@ impl<R> BitReader<R>
 where
     R: io::Read,
 {
+    /// Makes a new `BitReader` instance.
     pub fn new(inner: R) -> Self {
         BitReader {
             inner,
@@ -83,35 +162,92 @@ where
         }
     }
 
+    /// Records `e` unless an error (e.g., `WouldBlock` of the inner reader) has already been recorded.
     #[inline(always)]
-    pub fn set_last_error(&mut self, e: io::Error) {
-        self.last_error = Some(e);
+    pub(crate) fn set_last_error(&mut self, e: io::Error) {
+        if self.last_error.is_none() {
+            self.last_error = Some(e);
+        }
     }
     #[inline(always)]
-    pub fn check_last_error(&mut self) -> io::Result<()> {
+    pub(crate) fn has_last_error(&self) -> bool {
+        self.last_error.is_some()
+    }
+    #[inline(always)]
+    pub(crate) fn check_last_error(&mut self) -> io::Result<()> {
         if let Some(e) = self.last_error.take() {
             Err(e)
         } else {
             Ok(())
         }
     }
+    /// Reads a bit.
     #[inline(always)]
     pub fn read_bit(&mut self) -> io::Result<bool> {
         self.read_bits(1).map(|b| b != 0)
     }
+    /// Reads `bitwidth` (at most 16) bits, the first of which is placed at the least significant bit.
+    ///
+    /// If `bitwidth` exceeds 16, an `io::ErrorKind::InvalidInput` error is returned.
     #[inline(always)]
     pub fn read_bits(&mut self, bitwidth: u8) -> io::Result<u16> {
+        check_bitwidth(bitwidth)?;
         let v = self.read_bits_unchecked(bitwidth);
         self.check_last_error().map(|_| v)
     }
+
+    /// Returns the next `bitwidth` (at most 16) bits without consuming them.
+    ///
+    /// If the stream ends before `bitwidth` bits, an `UnexpectedEof` error is returned
+    /// and the reader can not be used anymore.
+    /// If `bitwidth` exceeds 16, an `io::ErrorKind::InvalidInput` error is returned.
+    #[inline]
+    pub fn peek_bits(&mut self, bitwidth: u8) -> io::Result<u16> {
+        check_bitwidth(bitwidth)?;
+        let v = self.peek_bits_unchecked(bitwidth);
+        self.check_last_error().map(|_| v)
+    }
+
+    /// Skips the next `bitwidth` bits.
+    pub fn skip_bits(&mut self, mut bitwidth: u32) -> io::Result<()> {
+        while bitwidth > 0 {
+            let n = cmp::min(bitwidth, 16) as u8;
+            self.read_bits(n)?;
+            bitwidth -= u32::from(n);
+        }
+        Ok(())
+    }
+
+    /// Skips the bits up to the next byte boundary (does nothing if the reader is already aligned).
+    ///
+    /// After this, the following bytes can be read by [`BitReader::read_bits`]
+    /// (or, if no bits are buffered, directly from the inner reader).
+    pub fn align_to_byte(&mut self) {
+        self.offset = self.offset.div_ceil(8) * 8;
+    }
+
+    /// Returns `true` if the next bit is at a byte boundary.
+    pub fn is_aligned(&self) -> bool {
+        self.offset.is_multiple_of(8)
+    }
+
+    /// Returns `true` if some bits read from the inner reader have not been consumed yet.
+    pub(crate) fn has_buffered_bits(&self) -> bool {
+        self.offset < 32
+    }
+
+    /// Returns the number of the bits read from the inner reader but not consumed yet.
+    pub(crate) fn buffered_bit_count(&self) -> u8 {
+        32 - self.offset
+    }
     #[inline(always)]
-    pub fn read_bits_unchecked(&mut self, bitwidth: u8) -> u16 {
+    pub(crate) fn read_bits_unchecked(&mut self, bitwidth: u8) -> u16 {
         let bits = self.peek_bits_unchecked(bitwidth);
-        self.skip_bits(bitwidth);
+        self.skip_bits_unchecked(bitwidth);
         bits
     }
     #[inline(always)]
-    pub fn peek_bits_unchecked(&mut self, bitwidth: u8) -> u16 {
+    pub(crate) fn peek_bits_unchecked(&mut self, bitwidth: u8) -> u16 {
         debug_assert!(bitwidth <= 16);
         while 32 < self.offset + bitwidth {
             if self.last_error.is_some() {
@@ -123,11 +259,11 @@ where
             }
         }
         debug_assert!(self.offset < 32 || bitwidth == 0);
-        let bits = self.last_read.wrapping_shr(u32::from(self.offset)) as u16;
-        bits & ((1 << bitwidth) - 1)
+        let bits = self.last_read.wrapping_shr(u32::from(self.offset));
+        (bits & ((1 << bitwidth) - 1)) as u16
     }
     #[inline(always)]
-    pub fn skip_bits(&mut self, bitwidth: u8) {
+    pub(crate) fn skip_bits_unchecked(&mut self, bitwidth: u8) {
         debug_assert!(self.last_error.is_some() || 32 - self.offset >= bitwidth);
         self.offset += bitwidth;
     }
@@ -156,15 +292,22 @@ where
     }
 }
 impl<R> BitReader<R> {
-    pub fn reset(&mut self) {
+    pub(crate) fn reset(&mut self) {
         self.offset = 32;
     }
+    /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
         &self.inner
     }
+    /// Returns the mutable reference to the inner stream.
+    ///
+    /// Note that some bytes may have already been read from the stream into the bit buffer.
     pub fn as_inner_mut(&mut self) -> &mut R {
         &mut self.inner
     }
+    /// Unwraps this `BitReader`, returning the underlying reader.
+    ///
+    /// Note that the bits which have been read into the bit buffer but not consumed yet will be lost.
     pub fn into_inner(self) -> R {
         self.inner
     }
@@ -202,16 +345,64 @@ mod tests {
     fn reader_works() {
         let buf = [0b10100101, 0b11010101];
         let mut reader = BitReader::new(&buf[..]);
-        assert_eq!(reader.read_bit().unwrap(), true);
-        assert_eq!(reader.read_bit().unwrap(), false);
+        assert!(reader.read_bit().unwrap());
+        assert!(!reader.read_bit().unwrap());
         assert_eq!(reader.read_bits(8).unwrap(), 0b01101001);
         assert_eq!(reader.peek_bits_unchecked(3), 0b101);
         assert_eq!(reader.peek_bits_unchecked(3), 0b101);
-        reader.skip_bits(1);
+        reader.skip_bits_unchecked(1);
         assert_eq!(reader.peek_bits_unchecked(3), 0b010);
         assert_eq!(
             reader.read_bits(8).map_err(|e| e.kind()),
             Err(io::ErrorKind::UnexpectedEof)
         );
     }
+
+    #[test]
+    fn skip_and_align_work() {
+        let buf = [0b1010_0101, 0b1101_0101, 0xFF, 0x12];
+        let mut reader = BitReader::new(&buf[..]);
+        assert!(reader.is_aligned());
+        reader.skip_bits(3).unwrap();
+        assert!(!reader.is_aligned());
+        assert_eq!(reader.peek_bits(5).unwrap(), 0b10100);
+        reader.align_to_byte();
+        assert!(reader.is_aligned());
+        reader.align_to_byte();
+        assert_eq!(reader.read_bits(8).unwrap(), 0b1101_0101);
+        reader.skip_bits(8).unwrap();
+        assert_eq!(reader.read_bits(8).unwrap(), 0x12);
+        assert!(reader.skip_bits(1).is_err());
+
+        let mut writer = BitWriter::new(Vec::new());
+        writer.align_to_byte().unwrap();
+        writer.write_bits(3, 0b111).unwrap();
+        writer.align_to_byte().unwrap();
+        writer.align_to_byte().unwrap();
+        writer.write_bits(16, 0x1234).unwrap();
+        writer.write_bit(true).unwrap();
+        writer.align_to_byte().unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.into_inner(), [0b111, 0x34, 0x12, 0b1]);
+    }
+
+    #[test]
+    fn invalid_bit_widths_are_rejected() {
+        let buf = [0xFF; 4];
+        let mut reader = BitReader::new(&buf[..]);
+        let e = reader.read_bits(20).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let e = reader.peek_bits(17).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(reader.read_bits(16).unwrap(), 0xFFFF);
+
+        let mut writer = BitWriter::new(Vec::new());
+        let e = writer.write_bits(17, 0).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let e = writer.write_bits(3, 0b1000).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        writer.write_bits(3, 0b111).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.into_inner(), [0b111]);
+    }
 }
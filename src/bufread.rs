@@ -0,0 +1,389 @@
+//! Encoders and decoders which read their input directly from the buffer of a `BufRead`.
+//!
+//! The encoders and decoders of the other modules read their input through `Read`,
+//! in many small pieces (the decoders) or into a private buffer (the [`read::Encoder`](crate::read::Encoder)).
+//! The types in this module instead borrow the input from [`BufRead::fill_buf`]
+//! and [`consume`](BufRead::consume) exactly the bytes they have used.
+//! So the bytes following a compressed stream are left unread in the inner reader
+//! (e.g., `std::io::BufReader`), and no extra reads are issued to fill a private buffer.
+//! Note that the data are still copied into the internal buffers of the push-style coders they are built on.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::Read;
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::Read;
+//! use libflate::bufread::{Decoder, Encoder};
+//! use libflate::push::Format;
+//!
+//! // Encoding
+//! let mut encoder = Encoder::new(&b"Hello World!"[..], Format::Gzip);
+//! let mut encoded_data = Vec::new();
+//! encoder.read_to_end(&mut encoded_data).unwrap();
+//!
+//! // Decoding
+//! encoded_data.extend_from_slice(b"trailing");
+//! let mut decoder = Decoder::new(&encoded_data[..], Format::Gzip);
+//! let mut decoded_data = Vec::new();
+//! decoder.read_to_end(&mut decoded_data).unwrap();
+//!
+//! assert_eq!(decoded_data, b"Hello World!");
+//! assert_eq!(decoder.into_inner(), b"trailing");
+//! ```
+use crate::deflate;
+use crate::error::{Error, ErrorKind};
+use crate::gzip;
+use crate::lz77;
+use crate::push::{self, Flush, Format, Status};
+use crate::util;
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use core2::io::{self, BufRead, Read};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, BufRead, Read};
+
+/// Encoder which compresses the data borrowed from the buffer of the inner reader, and implements `Read` for the compressed data.
+///
+/// This is the same as [`read::Encoder`](crate::read::Encoder) except that no input buffer is allocated.
+#[derive(Debug)]
+pub struct Encoder<R, E = lz77::DefaultLz77Encoder> {
+    inner: R,
+    encoder: push::Encoder<E>,
+}
+impl<R> Encoder<R, lz77::DefaultLz77Encoder>
+where
+    R: BufRead,
+{
+    /// Makes a new encoder instance for `format` with the default options.
+    ///
+    /// `inner` is the reader from which the data to be compressed is read.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::bufread::Encoder;
+    /// use libflate::push::Format;
+    ///
+    /// let mut encoder = Encoder::new(&b"Hello World!"[..], Format::Deflate);
+    /// let mut buf = Vec::new();
+    /// encoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf,
+    ///            [5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237,
+    ///             147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
+    /// ```
+    pub fn new(inner: R, format: Format) -> Self {
+        Self::from_push(inner, push::Encoder::new(format))
+    }
+}
+impl<R, E> Encoder<R, E>
+where
+    R: BufRead,
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new encoder instance that produces a raw DEFLATE stream.
+    pub fn deflate(inner: R, options: deflate::EncodeOptions<E>) -> Self {
+        Self::from_push(inner, push::Encoder::deflate(options))
+    }
+
+    /// Makes a new encoder instance that produces a ZLIB stream.
+    pub fn zlib(inner: R, options: zlib::EncodeOptions<E>) -> io::Result<Self> {
+        push::Encoder::zlib(options).map(|encoder| Self::from_push(inner, encoder))
+    }
+
+    /// Makes a new encoder instance that produces a GZIP stream.
+    pub fn gzip(inner: R, options: gzip::EncodeOptions<E>) -> io::Result<Self> {
+        push::Encoder::gzip(options).map(|encoder| Self::from_push(inner, encoder))
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Encoder`, returning the underlying reader.
+    ///
+    /// The bytes which have not been consumed are left in the reader,
+    /// but note that the consumed ones may not have been written to the compressed stream yet.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the number of the (uncompressed) bytes consumed from the inner stream by this encoder.
+    pub fn total_in(&self) -> u64 {
+        self.encoder.total_in()
+    }
+
+    /// Returns the number of the (compressed) bytes read from this encoder.
+    pub fn total_out(&self) -> u64 {
+        self.encoder.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this encoder (excluding the inner stream).
+    ///
+    /// See [`push::Encoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.encoder.memory_usage()
+    }
+
+    fn from_push(inner: R, encoder: push::Encoder<E>) -> Self {
+        Encoder { inner, encoder }
+    }
+}
+impl<R, E> Read for Encoder<R, E>
+where
+    R: BufRead,
+    E: lz77::Lz77Encode,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let input = util::fill_buf(&mut self.inner)?;
+            let flush = if input.is_empty() {
+                Flush::Finish
+            } else {
+                Flush::None
+            };
+            let progress = self.encoder.encode(input, buf, flush)?;
+            self.inner.consume(progress.consumed);
+            if progress.produced > 0 {
+                return Ok(progress.produced);
+            }
+            if progress.status == Status::StreamEnd {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+/// Decoder which decompresses the data borrowed from the buffer of the inner reader.
+///
+/// Only the bytes belonging to the compressed stream are consumed,
+/// so the inner reader is positioned just after the stream once the decoding has finished.
+#[derive(Debug)]
+pub struct Decoder<R> {
+    inner: R,
+    decoder: push::Decoder,
+}
+impl<R> Decoder<R>
+where
+    R: BufRead,
+{
+    /// Makes a new decoder instance for `format`.
+    ///
+    /// Unlike the decoders of the other modules, this doesn't read the header of the stream until the first `read`.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::bufread::Decoder;
+    /// use libflate::push::Format;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..], Format::Zlib);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn new(inner: R, format: Format) -> Self {
+        Decoder {
+            inner,
+            decoder: push::Decoder::new(format),
+        }
+    }
+
+    /// Returns `true` if the end of the stream has been reached, `false` otherwise.
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Decoder`, returning the underlying reader.
+    ///
+    /// Once the end of the stream has been reached, the reader is positioned just after the stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the number of the (compressed) bytes consumed from the inner stream.
+    pub fn total_in(&self) -> u64 {
+        self.decoder.total_in()
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        self.decoder.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder (excluding the inner stream).
+    ///
+    /// See [`push::Decoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.decoder.memory_usage()
+    }
+}
+impl<R> Read for Decoder<R>
+where
+    R: BufRead,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while !self.decoder.is_finished() {
+            let input = util::fill_buf(&mut self.inner)?;
+            let eof = input.is_empty();
+            let progress = self.decoder.decode(input, buf)?;
+            self.inner.consume(progress.consumed);
+            if progress.produced > 0 {
+                return Ok(progress.produced);
+            }
+            if eof && progress.status != Status::StreamEnd {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "The compressed stream is truncated",
+                )
+                .into());
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::any;
+    #[cfg(feature = "no_std")]
+    use alloc::vec::Vec;
+
+    /// A reader which returns the inner data in chunks of (at most) `chunk_size` bytes.
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk_size: usize,
+    }
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let size = self.fill_buf()?.len().min(buf.len());
+            buf[..size].copy_from_slice(&self.data[..size]);
+            self.consume(size);
+            Ok(size)
+        }
+    }
+    impl BufRead for ChunkedReader<'_> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Ok(&self.data[..self.data.len().min(self.chunk_size)])
+        }
+        fn consume(&mut self, amt: usize) {
+            self.data = &self.data[amt..];
+        }
+    }
+
+    #[test]
+    fn round_trip_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            for &chunk_size in &[1, 100, 100_000] {
+                let inner = ChunkedReader {
+                    data: &plain,
+                    chunk_size,
+                };
+                let mut encoded = Vec::new();
+                Encoder::new(inner, format)
+                    .read_to_end(&mut encoded)
+                    .unwrap();
+
+                let mut decoded = Vec::new();
+                any::Decoder::new(&encoded[..])
+                    .unwrap()
+                    .read_to_end(&mut decoded)
+                    .unwrap();
+                assert_eq!(decoded, plain);
+
+                encoded.extend_from_slice(b"trailing");
+                let inner = ChunkedReader {
+                    data: &encoded,
+                    chunk_size,
+                };
+                let mut decoder = Decoder::new(inner, format);
+                let mut decoded = Vec::new();
+                decoder.read_to_end(&mut decoded).unwrap();
+                assert_eq!(decoded, plain);
+                assert!(decoder.is_finished());
+                assert_eq!(decoder.into_inner().data, b"trailing");
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_stream_is_rejected() {
+        let mut encoded = Vec::new();
+        Encoder::new(&b"Hello World!"[..], Format::Gzip)
+            .read_to_end(&mut encoded)
+            .unwrap();
+        encoded.truncate(encoded.len() - 1);
+
+        let mut decoder = Decoder::new(&encoded[..], Format::Gzip);
+        let mut decoded = Vec::new();
+        let error = decoder.read_to_end(&mut decoded).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+    #[test]
+    fn total_in_and_total_out_work() {
+        use crate::guard::{Guard, Limits};
+
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let mut encoder = Encoder::new(&plain[..], format);
+            let mut encoded = Vec::new();
+            encoder.read_to_end(&mut encoded).unwrap();
+            assert_eq!(encoder.total_in(), plain.len() as u64);
+            assert_eq!(encoder.total_out(), encoded.len() as u64);
+
+            let inner = ChunkedReader {
+                data: &encoded,
+                chunk_size: 100,
+            };
+            let mut decoder = Decoder::new(inner, format);
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoder.total_in(), encoded.len() as u64);
+            assert_eq!(decoder.total_out(), plain.len() as u64);
+
+            // The decoder can be wrapped by `Guard`
+            let decoder = Decoder::new(&encoded[..], format);
+            let mut guard = Guard::new(decoder, Limits::new().max_output_size(1000));
+            let mut decoded = Vec::new();
+            let error = guard.read_to_end(&mut decoded).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(decoded, &plain[..1000]);
+        }
+    }
+}
@@ -1,20 +1,95 @@
+//! Checksum algorithms used by the ZLIB and GZIP formats.
+//!
+//! [`Crc32c`] is not used by these formats,
+//! but is provided for custom container formats built on the [`deflate`](crate::deflate) module.
+//!
+//! These types only depend on `core`, so they are available with the `no_std` feature as well.
+//!
+//! # Hashing
+//!
+//! The checksums implement [`Hasher`], so they can be used in generic hashing code
+//! (e.g., with [`Hash`](core::hash::Hash) based utilities).
+//!
+//! [`Hasher::finish`] returns the current checksum value (zero-extended to `u64`).
+//! Note that [`Hash`](core::hash::Hash) implementations may write extra bytes (e.g., the length of a slice),
+//! so use [`Hasher::write`] to compute the checksum of raw bytes.
+//!
+//! # Examples
+//! ```
+//! use libflate::checksum::{Adler32, Crc32, Crc32c};
+//! use std::hash::Hasher;
+//!
+//! fn checksum<H: Hasher + Default>(chunks: &[&[u8]]) -> u64 {
+//!     let mut hasher = H::default();
+//!     for chunk in chunks {
+//!         hasher.write(chunk);
+//!     }
+//!     hasher.finish()
+//! }
+//!
+//! assert_eq!(checksum::<Adler32>(&[b"ab", b"cde"]), 0x05C801F0);
+//! assert_eq!(checksum::<Crc32>(&[b"ab", b"cde"]), 0x8587D865);
+//! assert_eq!(checksum::<Crc32c>(&[b"ab", b"cde"]), 0xC450D697);
+//! ```
+#[cfg(not(feature = "no_std"))]
+use crate::parallel;
 use adler32::RollingAdler32;
 #[cfg(feature = "no_std")]
-use core::fmt;
+use core::{fmt, hash::Hasher};
+#[cfg(feature = "no_std")]
+use core2::io;
 #[cfg(not(feature = "no_std"))]
-use std::fmt;
+use std::{fmt, hash::Hasher, io};
 
+/// Adler-32 checksum (used by the ZLIB format).
+///
+/// # Examples
+/// ```
+/// use libflate::checksum::Adler32;
+///
+/// let mut adler32 = Adler32::new();
+/// adler32.update(b"abcde");
+/// assert_eq!(adler32.value(), 0x05C801F0);
+/// ```
 pub struct Adler32(RollingAdler32);
 impl Adler32 {
+    /// Makes a new `Adler32` instance.
     pub fn new() -> Self {
         Adler32(RollingAdler32::new())
     }
+
+    /// Returns the current checksum value.
     pub fn value(&self) -> u32 {
         self.0.hash()
     }
+
+    /// Updates the checksum with `buf`.
     pub fn update(&mut self, buf: &[u8]) {
         self.0.update_buffer(buf);
     }
+
+    /// Makes a new `Adler32` instance whose current checksum is `value`.
+    pub(crate) fn from_value(value: u32) -> Self {
+        Adler32(RollingAdler32::from_value(value))
+    }
+
+    /// Updates the checksum as if the `other_len` bytes hashed by `other` were passed to `update()`.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn combine(&mut self, other: &Adler32, other_len: u64) {
+        const BASE: u64 = 65521;
+        let (a, b) = (self.value(), other.value());
+        let rem = other_len % BASE;
+        let s1 = u64::from(a & 0xFFFF);
+        let s2 = rem * s1 % BASE;
+        let s1 = (s1 + u64::from(b & 0xFFFF) + BASE - 1) % BASE;
+        let s2 = (s2 + u64::from(a >> 16) + u64::from(b >> 16) + BASE - rem) % BASE;
+        self.0 = RollingAdler32::from_value(((s2 << 16) | s1) as u32);
+    }
+}
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl fmt::Debug for Adler32 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -22,17 +97,48 @@ impl fmt::Debug for Adler32 {
     }
 }
 
+/// CRC-32 checksum (used by the GZIP format).
+///
+/// # Examples
+/// ```
+/// use libflate::checksum::Crc32;
+///
+/// let mut crc32 = Crc32::new();
+/// crc32.update(b"abcde");
+/// assert_eq!(crc32.value(), 0x8587D865);
+/// ```
 pub struct Crc32(crc32fast::Hasher);
 impl Crc32 {
+    /// Makes a new `Crc32` instance.
     pub fn new() -> Self {
         Crc32(crc32fast::Hasher::new())
     }
+
+    /// Returns the current checksum value.
     pub fn value(&self) -> u32 {
         self.0.clone().finalize()
     }
+
+    /// Updates the checksum with `buf`.
     pub fn update(&mut self, buf: &[u8]) {
         self.0.update(buf);
     }
+
+    /// Makes a new `Crc32` instance whose current checksum is `value`.
+    pub(crate) fn from_value(value: u32) -> Self {
+        Crc32(crc32fast::Hasher::new_with_initial(value))
+    }
+
+    /// Updates the checksum as if the bytes hashed by `other` were passed to `update()`.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn combine(&mut self, other: &Crc32) {
+        self.0.combine(&other.0);
+    }
+}
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl fmt::Debug for Crc32 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -40,9 +146,226 @@ impl fmt::Debug for Crc32 {
     }
 }
 
+/// CRC-32C (Castagnoli) checksum, which is used by, e.g., iSCSI, SCTP and ext4.
+///
+/// This is a table-driven (slicing-by-8) implementation in safe Rust:
+/// the hardware CRC-32C instructions are not used, as this crate does not use `unsafe` code.
+///
+/// # Examples
+/// ```
+/// use libflate::checksum::Crc32c;
+///
+/// let mut crc32c = Crc32c::new();
+/// crc32c.update(b"abcde");
+/// assert_eq!(crc32c.value(), 0xC450D697);
+/// ```
+#[derive(Clone)]
+pub struct Crc32c(u32);
+impl Crc32c {
+    /// Makes a new `Crc32c` instance.
+    pub fn new() -> Self {
+        Crc32c(!0)
+    }
+
+    /// Returns the current checksum value.
+    pub fn value(&self) -> u32 {
+        !self.0
+    }
+
+    /// Updates the checksum with `buf`.
+    pub fn update(&mut self, buf: &[u8]) {
+        let t = &CRC32C_TABLES;
+        let mut crc = self.0;
+        let mut chunks = buf.chunks_exact(8);
+        for c in &mut chunks {
+            let lo = u32::from_le_bytes([c[0], c[1], c[2], c[3]]) ^ crc;
+            let hi = u32::from_le_bytes([c[4], c[5], c[6], c[7]]);
+            crc = t[7][(lo & 0xFF) as usize]
+                ^ t[6][((lo >> 8) & 0xFF) as usize]
+                ^ t[5][((lo >> 16) & 0xFF) as usize]
+                ^ t[4][(lo >> 24) as usize]
+                ^ t[3][(hi & 0xFF) as usize]
+                ^ t[2][((hi >> 8) & 0xFF) as usize]
+                ^ t[1][((hi >> 16) & 0xFF) as usize]
+                ^ t[0][(hi >> 24) as usize];
+        }
+        for &b in chunks.remainder() {
+            crc = t[0][((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8);
+        }
+        self.0 = crc;
+    }
+}
+impl Default for Crc32c {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl fmt::Debug for Crc32c {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Crc32c({:#010X})", self.value())
+    }
+}
+
+/// The reversed polynomial of CRC-32C.
+const CRC32C_POLYNOMIAL: u32 = 0x82F6_3B78;
+
+/// `CRC32C_TABLES[n][b]` is the CRC of the byte `b` followed by `n` zero bytes.
+const CRC32C_TABLES: [[u32; 256]; 8] = crc32c_tables();
+
+const fn crc32c_tables() -> [[u32; 256]; 8] {
+    let mut tables = [[0; 256]; 8];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32C_POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+    let mut n = 1;
+    while n < 8 {
+        let mut i = 0;
+        while i < 256 {
+            let prev = tables[n - 1][i];
+            tables[n][i] = (prev >> 8) ^ tables[0][(prev & 0xFF) as usize];
+            i += 1;
+        }
+        n += 1;
+    }
+    tables
+}
+
+impl Hasher for Adler32 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+    fn finish(&self) -> u64 {
+        u64::from(self.value())
+    }
+}
+impl Hasher for Crc32 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+    fn finish(&self) -> u64 {
+        u64::from(self.value())
+    }
+}
+impl Hasher for Crc32c {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+    fn finish(&self) -> u64 {
+        u64::from(self.value())
+    }
+}
+
+/// The common interface of [`Adler32`] and [`Crc32`].
+pub(crate) trait Checksum32 {
+    fn update(&mut self, buf: &[u8]);
+    fn value(&self) -> u32;
+}
+impl Checksum32 for Adler32 {
+    fn update(&mut self, buf: &[u8]) {
+        Adler32::update(self, buf);
+    }
+    fn value(&self) -> u32 {
+        Adler32::value(self)
+    }
+}
+impl Checksum32 for Crc32 {
+    fn update(&mut self, buf: &[u8]) {
+        Crc32::update(self, buf);
+    }
+    fn value(&self) -> u32 {
+        Crc32::value(self)
+    }
+}
+
+/// The checksum of a ZLIB or GZIP stream, which is computed on a worker thread if pipelined.
+#[derive(Debug)]
+pub(crate) struct StreamChecksum<C> {
+    checksum: C,
+    pipelined: bool,
+    #[cfg(not(feature = "no_std"))]
+    worker: Option<parallel::ChecksumWorker<C>>,
+}
+impl<C> StreamChecksum<C>
+where
+    C: Checksum32 + Default + Send + 'static,
+{
+    pub fn new(checksum: C) -> Self {
+        Self::with_pipelining(checksum, false)
+    }
+
+    /// Makes an instance which updates `checksum` on a worker thread if `pipelined` is `true`.
+    ///
+    /// With the `no_std` feature, `pipelined` is ignored.
+    pub fn with_pipelining(checksum: C, pipelined: bool) -> Self {
+        #[cfg(not(feature = "no_std"))]
+        if pipelined {
+            return StreamChecksum {
+                // Replaced with the result of the worker by `value()`
+                checksum: C::default(),
+                pipelined,
+                worker: Some(parallel::ChecksumWorker::new(checksum)),
+            };
+        }
+        StreamChecksum {
+            checksum,
+            pipelined,
+            #[cfg(not(feature = "no_std"))]
+            worker: None,
+        }
+    }
+
+    /// Restarts the computation with a new checksum (e.g., for the next member of a GZIP stream).
+    pub fn reset(&mut self) {
+        *self = Self::with_pipelining(C::default(), self.pipelined);
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        #[cfg(not(feature = "no_std"))]
+        if let Some(worker) = self.worker.as_mut() {
+            worker.update(buf);
+            return;
+        }
+        self.checksum.update(buf);
+    }
+
+    /// Returns the checksum of all the data passed so far.
+    ///
+    /// If pipelined, this waits for the worker thread, and the subsequent updates are done on the current thread.
+    pub fn value(&mut self) -> io::Result<u32> {
+        #[cfg(not(feature = "no_std"))]
+        if let Some(worker) = self.worker.take() {
+            self.checksum = worker.finish()?;
+        }
+        Ok(self.checksum.value())
+    }
+
+    /// Returns the checksum of all the data passed so far, unless it is being computed on a worker thread.
+    pub fn peek(&self) -> Option<u32> {
+        #[cfg(not(feature = "no_std"))]
+        if self.worker.is_some() {
+            return None;
+        }
+        Some(self.checksum.value())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "no_std")]
+    use alloc::vec::Vec;
 
     #[test]
     fn crc32_works() {
@@ -51,10 +374,65 @@ mod tests {
         assert_eq!(crc32.value(), 0x8587D865);
     }
 
+    #[test]
+    fn crc32c_works() {
+        let mut crc32c = Crc32c::new();
+        crc32c.update(b"123456789");
+        assert_eq!(crc32c.value(), 0xE3069283);
+
+        // Updating in pieces (across the 8-byte chunks) gives the same result
+        let data = (0..=255).cycle().take(256 * 40).collect::<Vec<u8>>();
+        let mut crc32c = Crc32c::new();
+        for chunk in data.chunks(13) {
+            crc32c.update(chunk);
+        }
+        assert_eq!(crc32c.value(), 0xBD846CD7);
+        let mut other = Crc32c::new();
+        other.update(&data);
+        assert_eq!(other.value(), crc32c.value());
+        assert_eq!(Crc32c::default().value(), 0);
+    }
+
     #[test]
     fn adler32_works() {
         let mut adler32 = Adler32::new();
         adler32.update(b"abcde");
         assert_eq!(adler32.value(), 0x05C801F0);
     }
+
+    #[test]
+    fn hasher_works() {
+        use core::hash::Hash;
+
+        let mut crc32 = Crc32::new();
+        b"abcde".hash(&mut crc32);
+        let mut expected = Crc32::new();
+        expected.write_usize(5);
+        expected.update(b"abcde");
+        assert_eq!(crc32.finish(), u64::from(expected.value()));
+
+        let mut adler32 = Adler32::new();
+        adler32.write_u8(b'a');
+        adler32.write(b"bcde");
+        assert_eq!(adler32.finish(), 0x05C801F0);
+    }
+
+    #[test]
+    fn combine_works() {
+        let (a, b) = (&b"Hello "[..], &b"World!"[..]);
+
+        let mut crc32 = Crc32::new();
+        crc32.update(a);
+        let mut other = Crc32::new();
+        other.update(b);
+        crc32.combine(&other);
+        assert_eq!(crc32.value(), 0x1C291CA3);
+
+        let mut adler32 = Adler32::new();
+        adler32.update(a);
+        let mut other = Adler32::new();
+        other.update(b);
+        adler32.combine(&other, b.len() as u64);
+        assert_eq!(adler32.value(), 0x1C49043E);
+    }
 }
@@ -0,0 +1,627 @@
+//! Traits implemented by the encoders and decoders of all the supported formats.
+//!
+//! [`Compress`] and [`Decompress`] allow writing code which is generic over the compressed format.
+//! They are object safe, so the format can also be chosen at runtime by using [`compressor`] and [`decompressor`].
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::{Read, Write};
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::{Read, Write};
+//! use libflate::codec::{self, Compress, Decompress};
+//! use libflate::deflate;
+//! use libflate::options::CompressionOptions;
+//! use libflate::push::Format;
+//!
+//! for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+//!     let mut encoder = codec::compressor(format, Vec::new(), CompressionOptions::new()).unwrap();
+//!     assert_eq!(encoder.format(), format);
+//!     encoder.write_all(b"Hello World!").unwrap();
+//!     let encoded_data = encoder.finish_boxed().into_result().unwrap();
+//!
+//!     let options = deflate::DecodeOptions::new();
+//!     let mut decoder = codec::decompressor(format, &encoded_data[..], options).unwrap();
+//!     let mut buf = Vec::new();
+//!     decoder.read_to_end(&mut buf).unwrap();
+//!     assert_eq!(buf, b"Hello World!");
+//! }
+//! ```
+use crate::any;
+use crate::bufread;
+use crate::deflate;
+use crate::finish::Finish;
+use crate::gzip;
+use crate::http;
+use crate::lz77;
+use crate::non_blocking;
+use crate::options::CompressionOptions;
+use crate::push::{self, Format};
+use crate::read;
+use crate::write;
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use alloc::boxed::Box;
+#[cfg(feature = "no_std")]
+use core2::io::{self, BufRead, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, BufRead, Read, Write};
+
+/// An encoder which compresses the data written to it and writes the result to an inner writer of type `W`.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::Write;
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::Write;
+/// use libflate::codec::Compress;
+/// use libflate::options::CompressionOptions;
+/// use libflate::{gzip, zlib};
+///
+/// fn compress<E: Compress<Vec<u8>>>(data: &[u8]) -> Vec<u8> {
+///     let mut encoder = E::from_options(Vec::new(), CompressionOptions::new()).unwrap();
+///     encoder.write_all(data).unwrap();
+///     encoder.finish().into_result().unwrap()
+/// }
+///
+/// let gzip_data = compress::<gzip::Encoder<_>>(b"Hello World!");
+/// let zlib_data = compress::<zlib::Encoder<_>>(b"Hello World!");
+/// assert_eq!(gzip_data[..2], [31, 139]);
+/// assert_eq!(zlib_data[..2], [120, 156]);
+/// ```
+pub trait Compress<W>: Write {
+    /// Makes a new encoder instance with the given options.
+    ///
+    /// The header of a GZIP stream is the default one (see [`gzip::HeaderBuilder`]).
+    fn from_options(inner: W, options: CompressionOptions) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    /// Returns the format of the stream produced by this encoder.
+    fn format(&self) -> Format;
+
+    /// Writes the trailer of the stream (if any), and returns the inner writer.
+    fn finish(self) -> Finish<W, io::Error>
+    where
+        Self: Sized;
+
+    /// The same as [`Compress::finish`], but callable on a trait object.
+    fn finish_boxed(self: Box<Self>) -> Finish<W, io::Error>;
+}
+
+/// A decoder which decompresses the data read from an inner reader of type `R`.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::Read;
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::Read;
+/// use libflate::codec::Decompress;
+/// use libflate::{deflate, zlib};
+///
+/// fn decompress<'a, D: Decompress<&'a [u8]>>(data: &'a [u8]) -> Vec<u8> {
+///     let mut decoder = D::from_options(data, deflate::DecodeOptions::new()).unwrap();
+///     let mut buf = Vec::new();
+///     decoder.read_to_end(&mut buf).unwrap();
+///     buf
+/// }
+///
+/// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+/// assert_eq!(decompress::<zlib::Decoder<_>>(&encoded_data), b"Hello World!");
+/// ```
+pub trait Decompress<R>: Read {
+    /// Makes a new decoder instance with the given options.
+    ///
+    /// The header of the stream (if any) is read by this function.
+    /// For GZIP, the options are passed to [`gzip::DecodeOptions::deflate_options`].
+    fn from_options(inner: R, options: deflate::DecodeOptions) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    /// Returns the format of the stream decoded by this decoder.
+    fn format(&self) -> Format;
+
+    /// Unwraps this decoder, returning the underlying reader.
+    fn into_inner(self) -> R
+    where
+        Self: Sized;
+
+    /// The same as [`Decompress::into_inner`], but callable on a trait object.
+    fn into_inner_boxed(self: Box<Self>) -> R;
+}
+
+/// The numbers of bytes processed by an encoder or a decoder.
+///
+/// This is implemented by all the encoders and decoders of this crate
+/// (e.g., the ones of the [`deflate`], [`zlib`], [`gzip`], [`push`] and [`non_blocking`] modules),
+/// and is used by [`guard::Guard`](crate::guard::Guard).
+pub trait ByteCount {
+    /// Returns the number of the input bytes
+    /// (uncompressed ones for an encoder, and compressed ones for a decoder).
+    fn total_in(&self) -> u64;
+
+    /// Returns the number of the output bytes
+    /// (compressed ones for an encoder, and decompressed ones for a decoder).
+    fn total_out(&self) -> u64;
+}
+
+/// Makes an encoder for the format chosen at runtime.
+///
+/// See the [module documentation](self) for an example.
+pub fn compressor<'a, W>(
+    format: Format,
+    inner: W,
+    options: CompressionOptions,
+) -> io::Result<Box<dyn Compress<W> + 'a>>
+where
+    W: Write + 'a,
+{
+    Ok(match format {
+        Format::Deflate => Box::new(deflate::Encoder::from_options(inner, options)?),
+        Format::Zlib => Box::new(zlib::Encoder::from_options(inner, options)?),
+        Format::Gzip => Box::new(gzip::Encoder::from_options(inner, options)?),
+    })
+}
+
+/// Makes a decoder for the format chosen at runtime.
+///
+/// See the [module documentation](self) for an example.
+pub fn decompressor<'a, R>(
+    format: Format,
+    inner: R,
+    options: deflate::DecodeOptions,
+) -> io::Result<Box<dyn Decompress<R> + 'a>>
+where
+    R: Read + 'a,
+{
+    Ok(match format {
+        Format::Deflate => Box::new(deflate::Decoder::from_options(inner, options)?),
+        Format::Zlib => Box::new(zlib::Decoder::from_options(inner, options)?),
+        Format::Gzip => Box::new(gzip::Decoder::from_options(inner, options)?),
+    })
+}
+
+impl<W: Write> Compress<W> for deflate::Encoder<W, lz77::DefaultLz77Encoder> {
+    fn from_options(inner: W, options: CompressionOptions) -> io::Result<Self> {
+        Ok(deflate::Encoder::with_options(inner, options.into()))
+    }
+    fn format(&self) -> Format {
+        Format::Deflate
+    }
+    fn finish(self) -> Finish<W, io::Error> {
+        deflate::Encoder::finish(self)
+    }
+    fn finish_boxed(self: Box<Self>) -> Finish<W, io::Error> {
+        deflate::Encoder::finish(*self)
+    }
+}
+
+impl<W: Write> Compress<W> for zlib::Encoder<W, lz77::DefaultLz77Encoder> {
+    fn from_options(inner: W, options: CompressionOptions) -> io::Result<Self> {
+        zlib::Encoder::with_options(inner, options.into())
+    }
+    fn format(&self) -> Format {
+        Format::Zlib
+    }
+    fn finish(self) -> Finish<W, io::Error> {
+        zlib::Encoder::finish(self)
+    }
+    fn finish_boxed(self: Box<Self>) -> Finish<W, io::Error> {
+        zlib::Encoder::finish(*self)
+    }
+}
+
+impl<W: Write> Compress<W> for gzip::Encoder<W, lz77::DefaultLz77Encoder> {
+    fn from_options(inner: W, options: CompressionOptions) -> io::Result<Self> {
+        gzip::Encoder::with_options(inner, options.into())
+    }
+    fn format(&self) -> Format {
+        Format::Gzip
+    }
+    fn finish(self) -> Finish<W, io::Error> {
+        gzip::Encoder::finish(self)
+    }
+    fn finish_boxed(self: Box<Self>) -> Finish<W, io::Error> {
+        gzip::Encoder::finish(*self)
+    }
+}
+
+impl<R: Read> Decompress<R> for deflate::Decoder<R> {
+    fn from_options(inner: R, options: deflate::DecodeOptions) -> io::Result<Self> {
+        Ok(deflate::Decoder::with_options(inner, options))
+    }
+    fn format(&self) -> Format {
+        Format::Deflate
+    }
+    fn into_inner(self) -> R {
+        deflate::Decoder::into_inner(self)
+    }
+    fn into_inner_boxed(self: Box<Self>) -> R {
+        deflate::Decoder::into_inner(*self)
+    }
+}
+
+impl<R: Read> Decompress<R> for zlib::Decoder<R> {
+    fn from_options(inner: R, options: deflate::DecodeOptions) -> io::Result<Self> {
+        zlib::Decoder::with_options(inner, options)
+    }
+    fn format(&self) -> Format {
+        Format::Zlib
+    }
+    fn into_inner(self) -> R {
+        zlib::Decoder::into_inner(self)
+    }
+    fn into_inner_boxed(self: Box<Self>) -> R {
+        zlib::Decoder::into_inner(*self)
+    }
+}
+
+impl<R: Read> Decompress<R> for gzip::Decoder<R> {
+    fn from_options(inner: R, options: deflate::DecodeOptions) -> io::Result<Self> {
+        gzip::Decoder::with_options(inner, gzip::DecodeOptions::new().deflate_options(options))
+    }
+    fn format(&self) -> Format {
+        Format::Gzip
+    }
+    fn into_inner(self) -> R {
+        gzip::Decoder::into_inner(self)
+    }
+    fn into_inner_boxed(self: Box<Self>) -> R {
+        gzip::Decoder::into_inner(*self)
+    }
+}
+
+impl<W, E> ByteCount for deflate::Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        deflate::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        deflate::Encoder::total_out(self)
+    }
+}
+
+impl<W, E> ByteCount for zlib::Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        zlib::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        zlib::Encoder::total_out(self)
+    }
+}
+
+impl<W, E> ByteCount for gzip::Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        gzip::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        gzip::Encoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for deflate::Decoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        deflate::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        deflate::Decoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for zlib::Decoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        zlib::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        zlib::Decoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for gzip::Decoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        gzip::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        gzip::Decoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for gzip::MultiDecoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        gzip::MultiDecoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        gzip::MultiDecoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for any::Decoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        any::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        any::Decoder::total_out(self)
+    }
+}
+
+impl<E> ByteCount for push::Encoder<E>
+where
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        push::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        push::Encoder::total_out(self)
+    }
+}
+
+impl ByteCount for push::Decoder {
+    fn total_in(&self) -> u64 {
+        push::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        push::Decoder::total_out(self)
+    }
+}
+
+impl<R, E> ByteCount for read::Encoder<R, E>
+where
+    R: Read,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        read::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        read::Encoder::total_out(self)
+    }
+}
+
+impl<R, E> ByteCount for bufread::Encoder<R, E>
+where
+    R: BufRead,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        bufread::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        bufread::Encoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for bufread::Decoder<R>
+where
+    R: BufRead,
+{
+    fn total_in(&self) -> u64 {
+        bufread::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        bufread::Decoder::total_out(self)
+    }
+}
+
+impl<W> ByteCount for write::Decoder<W>
+where
+    W: Write,
+{
+    fn total_in(&self) -> u64 {
+        write::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        write::Decoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for non_blocking::deflate::Decoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        non_blocking::deflate::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        non_blocking::deflate::Decoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for non_blocking::zlib::Decoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        non_blocking::zlib::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        non_blocking::zlib::Decoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for non_blocking::gzip::Decoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        non_blocking::gzip::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        non_blocking::gzip::Decoder::total_out(self)
+    }
+}
+
+impl<R> ByteCount for http::DeflateDecoder<R>
+where
+    R: Read,
+{
+    fn total_in(&self) -> u64 {
+        http::DeflateDecoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        http::DeflateDecoder::total_out(self)
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "no_std")))]
+impl<R> ByteCount for crate::tokio::deflate::Decoder<R> {
+    fn total_in(&self) -> u64 {
+        crate::tokio::deflate::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        crate::tokio::deflate::Decoder::total_out(self)
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "no_std")))]
+impl<W, E> ByteCount for crate::tokio::deflate::Encoder<W, E>
+where
+    W: ::tokio::io::AsyncWrite + Unpin,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        crate::tokio::deflate::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        crate::tokio::deflate::Encoder::total_out(self)
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "no_std")))]
+impl<R> ByteCount for crate::tokio::zlib::Decoder<R> {
+    fn total_in(&self) -> u64 {
+        crate::tokio::zlib::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        crate::tokio::zlib::Decoder::total_out(self)
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "no_std")))]
+impl<W, E> ByteCount for crate::tokio::zlib::Encoder<W, E>
+where
+    W: ::tokio::io::AsyncWrite + Unpin,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        crate::tokio::zlib::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        crate::tokio::zlib::Encoder::total_out(self)
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "no_std")))]
+impl<R> ByteCount for crate::tokio::gzip::Decoder<R> {
+    fn total_in(&self) -> u64 {
+        crate::tokio::gzip::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        crate::tokio::gzip::Decoder::total_out(self)
+    }
+}
+
+#[cfg(all(feature = "tokio", not(feature = "no_std")))]
+impl<W, E> ByteCount for crate::tokio::gzip::Encoder<W, E>
+where
+    W: ::tokio::io::AsyncWrite + Unpin,
+    E: lz77::Lz77Encode,
+{
+    fn total_in(&self) -> u64 {
+        crate::tokio::gzip::Encoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        crate::tokio::gzip::Encoder::total_out(self)
+    }
+}
+
+#[cfg(all(feature = "tokio-util", not(feature = "no_std")))]
+impl<S> ByteCount for crate::tokio::stream::Decoder<S> {
+    fn total_in(&self) -> u64 {
+        crate::tokio::stream::Decoder::total_in(self)
+    }
+    fn total_out(&self) -> u64 {
+        crate::tokio::stream::Decoder::total_out(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "no_std")]
+    use alloc::vec::Vec;
+
+    fn compress<E: Compress<Vec<u8>>>(plain: &[u8]) -> Vec<u8> {
+        let mut encoder = E::from_options(Vec::new(), CompressionOptions::new()).unwrap();
+        encoder.write_all(plain).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    fn decompress<'a, D: Decompress<&'a [u8]>>(encoded: &'a [u8]) -> Vec<u8> {
+        let mut decoder = D::from_options(encoded, deflate::DecodeOptions::new()).unwrap();
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert!(decoder.into_inner().is_empty());
+        decoded
+    }
+
+    #[test]
+    fn generic_round_trip_works() {
+        let plain = (0..10_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let encoded = compress::<deflate::Encoder<_>>(&plain);
+        assert_eq!(decompress::<deflate::Decoder<_>>(&encoded), plain);
+        let encoded = compress::<zlib::Encoder<_>>(&plain);
+        assert_eq!(decompress::<zlib::Decoder<_>>(&encoded), plain);
+        let encoded = compress::<gzip::Encoder<_>>(&plain);
+        assert_eq!(decompress::<gzip::Decoder<_>>(&encoded), plain);
+    }
+
+    #[test]
+    fn runtime_selection_works() {
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let mut encoder = compressor(format, Vec::new(), CompressionOptions::new()).unwrap();
+            encoder.write_all(b"Hello World!").unwrap();
+            let mut encoded = encoder.finish_boxed().into_result().unwrap();
+            encoded.extend_from_slice(b"trailing");
+
+            let mut decoder =
+                decompressor(format, &encoded[..], deflate::DecodeOptions::new()).unwrap();
+            assert_eq!(decoder.format(), format);
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, b"Hello World!");
+            assert_eq!(decoder.into_inner_boxed(), b"trailing");
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Piping a whole stream through an encoder or a decoder.
+//!
+//! [`Copier`] is the counterpart of `io::copy` for the encoders and decoders of this crate.
+//! It uses a large buffer which is reused across the copies,
+//! optionally reports the progress, and returns the numbers of the processed bytes on both sides.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::Write;
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::Write;
+//! use libflate::copy;
+//! use libflate::gzip::{Decoder, Encoder};
+//!
+//! let mut encoder = Encoder::new(Vec::new()).unwrap();
+//! let copied = copy::encode(&mut &b"Hello World!"[..], &mut encoder).unwrap();
+//! assert_eq!(copied.total_in, 12);
+//! let encoded_data = encoder.finish().into_result().unwrap();
+//!
+//! let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+//! let mut decoded_data = Vec::new();
+//! let copied = copy::decode(&mut decoder, &mut decoded_data).unwrap();
+//! assert_eq!(copied.total_out, 12);
+//! assert_eq!(decoded_data, b"Hello World!");
+//! ```
+use crate::codec::ByteCount;
+use crate::util::{self, ProgressHook};
+#[cfg(feature = "no_std")]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, Read, Write};
+
+/// The default size of the buffer of [`Copier`].
+pub const DEFAULT_BUFFER_SIZE: usize = 128 * 1024;
+
+/// The numbers of the bytes processed by a copy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Copied {
+    /// The number of the input bytes
+    /// (uncompressed ones for an encoder, and compressed ones for a decoder).
+    pub total_in: u64,
+
+    /// The number of the output bytes
+    /// (compressed ones for an encoder, and decompressed ones for a decoder).
+    pub total_out: u64,
+}
+
+/// Copies whole streams through a reusable buffer.
+#[derive(Debug)]
+pub struct Copier {
+    buf: Vec<u8>,
+    progress: Option<ProgressHook>,
+}
+impl Copier {
+    /// Makes a new `Copier` instance with a buffer of [`DEFAULT_BUFFER_SIZE`] bytes.
+    pub fn new() -> Self {
+        Self::with_buffer_size(DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Makes a new `Copier` instance with a buffer of `size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is `0`.
+    pub fn with_buffer_size(size: usize) -> Self {
+        assert!(size > 0, "The buffer size must be positive");
+        Copier {
+            buf: vec![0; size],
+            progress: None,
+        }
+    }
+
+    /// Registers a callback which is invoked every `interval` input bytes of a copy.
+    ///
+    /// The callback receives the numbers of the input and output bytes of the current copy
+    /// (the same as the fields of [`Copied`]), and is also invoked once when the copy completes.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::copy::Copier;
+    /// use libflate::zlib::Decoder;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    /// let last = Arc::new(Mutex::new(None));
+    /// let mut copier = Copier::new();
+    /// {
+    ///     let last = last.clone();
+    ///     copier.set_progress_callback(1024 * 1024, move |bytes_in, bytes_out| {
+    ///         *last.lock().unwrap() = Some((bytes_in, bytes_out));
+    ///     });
+    /// }
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// copier.decode(&mut decoder, &mut Vec::new()).unwrap();
+    ///
+    /// // The two bytes of the ZLIB header were read by `Decoder::new()` before the copy
+    /// assert_eq!(*last.lock().unwrap(), Some((18, 12)));
+    /// ```
+    pub fn set_progress_callback<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressHook::new(interval, callback));
+    }
+
+    /// Reads all the data from `decoder` and writes them to `writer`.
+    ///
+    /// Returns the numbers of the compressed bytes read by `decoder`
+    /// and the decompressed bytes written to `writer` during this copy.
+    pub fn decode<D, W>(&mut self, decoder: &mut D, writer: &mut W) -> io::Result<Copied>
+    where
+        D: Read + ByteCount + ?Sized,
+        W: Write + ?Sized,
+    {
+        let (start_in, start_out) = (decoder.total_in(), decoder.total_out());
+        self.copy_with(decoder, writer, |decoder, _, _| Copied {
+            total_in: decoder.total_in() - start_in,
+            total_out: decoder.total_out() - start_out,
+        })
+    }
+
+    /// Reads all the data from `reader` and writes them to `encoder`.
+    ///
+    /// Returns the numbers of the uncompressed bytes read from `reader`
+    /// and the compressed bytes written by `encoder` during this copy.
+    ///
+    /// `encoder` is not finished, so the returned `total_out` doesn't include
+    /// the data which are still buffered in the encoder (nor the trailer of the stream).
+    pub fn encode<R, E>(&mut self, reader: &mut R, encoder: &mut E) -> io::Result<Copied>
+    where
+        R: Read + ?Sized,
+        E: Write + ByteCount + ?Sized,
+    {
+        let (start_in, start_out) = (encoder.total_in(), encoder.total_out());
+        self.copy_with(reader, encoder, |_, encoder, _| Copied {
+            total_in: encoder.total_in() - start_in,
+            total_out: encoder.total_out() - start_out,
+        })
+    }
+
+    /// Reads all the data from `reader` and writes them to `writer` as is.
+    ///
+    /// Both fields of the returned value are the number of the copied bytes.
+    pub fn copy<R, W>(&mut self, reader: &mut R, writer: &mut W) -> io::Result<Copied>
+    where
+        R: Read + ?Sized,
+        W: Write + ?Sized,
+    {
+        self.copy_with(reader, writer, |_, _, copied| Copied {
+            total_in: copied,
+            total_out: copied,
+        })
+    }
+
+    /// Copies the data, reporting the progress by the counts which `count` makes
+    /// from the both ends and the number of the bytes copied so far.
+    fn copy_with<R, W, F>(&mut self, reader: &mut R, writer: &mut W, count: F) -> io::Result<Copied>
+    where
+        R: Read + ?Sized,
+        W: Write + ?Sized,
+        F: Fn(&R, &W, u64) -> Copied,
+    {
+        if let Some(progress) = self.progress.as_mut() {
+            progress.restart();
+        }
+        let mut copied = 0;
+        loop {
+            let size = util::retry_interrupted(|| reader.read(&mut self.buf))?;
+            if size == 0 {
+                break;
+            }
+            writer.write_all(&self.buf[..size])?;
+            copied += size as u64;
+            if let Some(progress) = self.progress.as_mut() {
+                let c = count(reader, writer, copied);
+                progress.notify(c.total_in, c.total_out);
+            }
+        }
+        let c = count(reader, writer, copied);
+        if let Some(progress) = self.progress.as_mut() {
+            progress.finish(c.total_in, c.total_out);
+        }
+        Ok(c)
+    }
+}
+impl Default for Copier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads all the data from `decoder` and writes them to `writer` with a default [`Copier`].
+pub fn decode<D, W>(decoder: &mut D, writer: &mut W) -> io::Result<Copied>
+where
+    D: Read + ByteCount + ?Sized,
+    W: Write + ?Sized,
+{
+    Copier::new().decode(decoder, writer)
+}
+
+/// Reads all the data from `reader` and writes them to `encoder` with a default [`Copier`].
+///
+/// See [`Copier::encode`] for the details.
+pub fn encode<R, E>(reader: &mut R, encoder: &mut E) -> io::Result<Copied>
+where
+    R: Read + ?Sized,
+    E: Write + ByteCount + ?Sized,
+{
+    Copier::new().encode(reader, encoder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zlib;
+    #[cfg(feature = "no_std")]
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    #[cfg(not(feature = "no_std"))]
+    use std::sync::Arc;
+
+    #[test]
+    fn copier_works() {
+        let plain = (0..300_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut copier = Copier::with_buffer_size(1000);
+        {
+            let calls = calls.clone();
+            copier.set_progress_callback(100_000, move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+
+        let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+        let copied = copier.encode(&mut &plain[..], &mut encoder).unwrap();
+        assert_eq!(copied.total_in, plain.len() as u64);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut decoder = zlib::Decoder::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        let copied = copier.decode(&mut decoder, &mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+        assert_eq!(
+            copied,
+            Copied {
+                total_in: encoded.len() as u64 - 2,
+                total_out: plain.len() as u64,
+            }
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        let mut copied_data = Vec::new();
+        let copied = copier.copy(&mut &plain[..], &mut copied_data).unwrap();
+        assert_eq!(copied_data, plain);
+        assert_eq!(copied.total_out, plain.len() as u64);
+    }
+}
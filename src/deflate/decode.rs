@@ -1,17 +1,282 @@
 use super::symbol;
 use crate::bit;
+use crate::error::{Error, ErrorKind};
 use crate::lz77;
+use crate::read;
+use crate::util::{self, CancellationCheck, Counter, ProgressHook};
 #[cfg(feature = "no_std")]
-use core2::io::{self, Read};
+use alloc::{borrow::Cow, vec::Vec};
+#[cfg(feature = "no_std")]
+use core::cmp;
+#[cfg(feature = "no_std")]
+use core2::io::{self, BufRead, Read, Seek, SeekFrom};
+#[cfg(not(feature = "no_std"))]
+use std::borrow::Cow;
 #[cfg(not(feature = "no_std"))]
-use std::io::{self, Read};
+use std::{
+    cmp,
+    io::{self, BufRead, Read, Seek, SeekFrom},
+};
+
+/// The maximum number of the consecutive literals which are appended to the decoded data at once.
+const LITERAL_BATCH_SIZE: usize = 64;
+
+/// Options for a DEFLATE decoder.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecodeOptions {
+    strict_huffman_header: bool,
+    window_size: u16,
+    pipelined_checksum: bool,
+    endless: bool,
+    greedy_read: bool,
+}
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            strict_huffman_header: false,
+            window_size: lz77::MAX_WINDOW_SIZE,
+            pipelined_checksum: false,
+            endless: false,
+            greedy_read: false,
+        }
+    }
+}
+impl DecodeOptions {
+    /// Makes a default instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::{Decoder, DecodeOptions};
+    ///
+    /// let options = DecodeOptions::new();
+    /// let decoder = Decoder::with_options(&[][..], options);
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a hardened instance which is suitable for decoding untrusted input.
+    ///
+    /// Currently, this is equivalent to `DecodeOptions::new().strict_huffman_header(true)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::DecodeOptions;
+    ///
+    /// assert_eq!(DecodeOptions::strict(), DecodeOptions::new().strict_huffman_header(true));
+    /// ```
+    pub fn strict() -> Self {
+        Self::new().strict_huffman_header(true)
+    }
+
+    /// Specifies whether the header of a dynamic Huffman block is strictly validated.
+    ///
+    /// If `true`, a header which no conforming encoder produces
+    /// (e.g., HLIT greater than 286 or no code for the end-of-block symbol)
+    /// is rejected with [`ErrorKind::LimitExceeded`] before the rest of the block is read.
+    ///
+    /// The default value is `false`.
+    pub fn strict_huffman_header(mut self, strict: bool) -> Self {
+        self.strict_huffman_header = strict;
+        self
+    }
+
+    /// Returns whether the header of a dynamic Huffman block is strictly validated.
+    pub fn get_strict_huffman_header(&self) -> bool {
+        self.strict_huffman_header
+    }
+
+    /// Specifies the size of the sliding window (in bytes).
+    ///
+    /// This is intended for memory constrained targets which only receive streams
+    /// known to be produced with a small window (e.g., ZLIB streams whose CINFO is 3 or less).
+    /// The decoder keeps only the last `window_size` bytes as the window
+    /// and suspends the decoding of a block once twice that size of data are buffered,
+    /// so the memory usage shrinks proportionally.
+    /// A back-reference beyond the window is rejected as invalid data.
+    ///
+    /// The value is clamped to the range `1..=lz77::MAX_WINDOW_SIZE`.
+    /// The default value is `lz77::MAX_WINDOW_SIZE` (32 KiB), which accepts any valid stream.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, DecodeOptions, EncodeOptions, Encoder};
+    /// use libflate::lz77::DefaultLz77EncoderBuilder;
+    ///
+    /// let lz77 = DefaultLz77EncoderBuilder::new().window_size(2048).build();
+    /// let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::with_lz77(lz77));
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = Decoder::with_options(&encoded_data[..], DecodeOptions::new().window_size(2048));
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn window_size(mut self, window_size: u16) -> Self {
+        self.window_size = window_size.clamp(1, lz77::MAX_WINDOW_SIZE);
+        self
+    }
+
+    /// Returns the size of the sliding window.
+    pub fn get_window_size(&self) -> u16 {
+        self.window_size
+    }
+
+    /// Specifies whether the ZLIB and GZIP decoders compute the checksum of the decoded data on a worker thread.
+    ///
+    /// The decoded data are copied into a ring of buffers which are passed to the worker,
+    /// and the checksum is joined (and verified) at the end of the stream.
+    /// This improves the throughput of a single stream on multi-core machines
+    /// at the cost of the copy and a thread.
+    ///
+    /// This is ignored by the DEFLATE decoder (which has no checksum).
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::Read;
+    /// use libflate::deflate::DecodeOptions;
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    ///
+    /// let options = DecodeOptions::new().pipelined_checksum(true);
+    /// let mut decoder = Decoder::with_options(&encoded_data[..], options).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn pipelined_checksum(mut self, pipelined: bool) -> Self {
+        self.pipelined_checksum = pipelined;
+        self
+    }
+
+    /// Returns whether the ZLIB and GZIP decoders compute the checksum on a worker thread.
+    pub fn get_pipelined_checksum(&self) -> bool {
+        self.pipelined_checksum
+    }
+
+    /// Specifies whether the decoder accepts an endless DEFLATE stream, which has no final block.
+    ///
+    /// Some streaming protocols keep a single DEFLATE stream open during a session,
+    /// and make a sync flush (an empty non-compressed block) after each message instead of ever ending the stream
+    /// (see [`EncodeOptions::endless`](super::EncodeOptions::endless)).
+    /// The decoder returns the data of each message as soon as they are decoded,
+    /// without waiting for the following data regardless of this option.
+    ///
+    /// If `true`, the inner stream ending just after a sync flush (or before the first block)
+    /// is regarded as the end of the DEFLATE stream instead of an `UnexpectedEof` error.
+    /// Ending anywhere else is still an error, and a final block ends the stream as usual.
+    ///
+    /// This is intended for raw DEFLATE streams:
+    /// the ZLIB and GZIP decoders expect their trailer after the DEFLATE stream anyway.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, DecodeOptions, EncodeOptions, Encoder};
+    ///
+    /// let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().endless());
+    /// encoder.write_all(b"Hello ").unwrap();
+    /// encoder.message_boundary().unwrap();
+    /// encoder.write_all(b"World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = Decoder::with_options(&encoded_data[..], DecodeOptions::new().endless(true));
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    ///
+    /// // Without the option, the stream is truncated
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+    /// ```
+    pub fn endless(mut self, endless: bool) -> Self {
+        self.endless = endless;
+        self
+    }
+
+    /// Returns whether the decoder accepts an endless DEFLATE stream.
+    pub fn get_endless(&self) -> bool {
+        self.endless
+    }
+
+    /// Specifies whether `read` keeps decoding until the buffer passed to it is full.
+    ///
+    /// By default, `read` returns as soon as some decoded data are available,
+    /// which may be much less than the size of the buffer (e.g., a single back-reference near a suspension point).
+    /// If `true`, `read` keeps decoding the current block until the buffer is full,
+    /// the inner stream reaches its end, or the block ends.
+    /// Stopping at the end of a block makes each message terminated by a sync flush still returned without
+    /// waiting for the next one.
+    ///
+    /// If an error occurs after some data are decoded into the buffer, the data are returned first,
+    /// and the error is returned by the next call.
+    ///
+    /// The ZLIB and GZIP decoders inherit this option from the DEFLATE decoder which they wrap.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::{self, Decoder, DecodeOptions};
+    ///
+    /// let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    /// let encoded_data = deflate::compress_to_vec(&plain).unwrap();
+    ///
+    /// let mut decoder = Decoder::with_options(&encoded_data[..], DecodeOptions::new().greedy_read(true));
+    /// let mut buf = vec![0; 80_000];
+    /// assert_eq!(decoder.read(&mut buf).unwrap(), buf.len());
+    /// assert_eq!(buf, &plain[..buf.len()]);
+    /// ```
+    pub fn greedy_read(mut self, greedy: bool) -> Self {
+        self.greedy_read = greedy;
+        self
+    }
+
+    /// Returns whether `read` keeps decoding until the buffer passed to it is full.
+    pub fn get_greedy_read(&self) -> bool {
+        self.greedy_read
+    }
+
+    /// The size of decoded data at which the decoding of a block is suspended until the data are read.
+    fn max_buffered_decoded_size(&self) -> usize {
+        usize::from(self.window_size) * 2
+    }
+}
 
 /// DEFLATE decoder.
 #[derive(Debug)]
 pub struct Decoder<R> {
-    bit_reader: bit::BitReader<R>,
+    bit_reader: bit::BitReader<Counter<R>>,
+    decoded_size: u64,
+    progress: Option<ProgressHook>,
+    cancellation: Option<CancellationCheck>,
     lz77_decoder: lz77::Lz77Decoder,
+    block: Option<symbol::Decoder>,
+    non_compressed_remaining: u16,
+    options: DecodeOptions,
     eos: bool,
+
+    // An error which occurred after some data were decoded by a greedy `read`, returned by the next call.
+    deferred_error: Option<io::Error>,
 }
 impl<R> Decoder<R>
 where
@@ -37,55 +302,440 @@ where
     /// assert_eq!(buf, b"Hello World!");
     /// ```
     pub fn new(inner: R) -> Self {
+        Self::with_options(inner, DecodeOptions::default())
+    }
+
+    /// Makes a new decoder instance with the specified options.
+    ///
+    /// `inner` is to be decoded DEFLATE stream.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::{Decoder, DecodeOptions};
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::with_options(&encoded_data[..], DecodeOptions::strict());
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn with_options(inner: R, options: DecodeOptions) -> Self {
+        let mut lz77_decoder = lz77::Lz77Decoder::new();
+        lz77_decoder.set_window_size(options.window_size);
         Decoder {
-            bit_reader: bit::BitReader::new(inner),
-            lz77_decoder: lz77::Lz77Decoder::new(),
+            bit_reader: bit::BitReader::new(Counter::new(inner)),
+            decoded_size: 0,
+            progress: None,
+            cancellation: None,
+            lz77_decoder,
+            block: None,
+            non_compressed_remaining: 0,
+            options,
             eos: false,
+            deferred_error: None,
         }
     }
 
+    /// Makes a new decoder instance whose buffer (the sliding window and the decoded data) is allocated by `allocator`.
+    ///
+    /// This is available only if the `allocator_api` feature (which requires a nightly compiler) is enabled.
+    /// Only this buffer is allocated by `allocator`.
+    /// All the other allocations of the decoder (e.g., the Huffman tables of the current block)
+    /// use the global allocator.
+    ///
+    /// # Examples
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use libflate::deflate::{Decoder, DecodeOptions};
+    /// use std::io::Read;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::with_allocator(&encoded_data[..], DecodeOptions::new(), &std::alloc::System);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    #[cfg(feature = "allocator_api")]
+    pub fn with_allocator(inner: R, options: DecodeOptions, allocator: lz77::DynAllocator) -> Self {
+        let mut this = Self::with_options(inner, options);
+        this.lz77_decoder = lz77::Lz77Decoder::new_in(allocator);
+        this.lz77_decoder.set_window_size(this.options.window_size);
+        this
+    }
+
+    /// Returns the number of the (compressed) bytes read from the inner stream.
+    ///
+    /// This may include a few bytes which have been read into the bit buffer but not decoded yet.
+    pub fn total_in(&self) -> u64 {
+        self.bit_reader.as_inner_ref().count()
+    }
+
+    /// Returns the number of the bits of the inner stream consumed by the decoder.
+    ///
+    /// Unlike [`Decoder::total_in`], this does not include the bits which have been read into the bit buffer
+    /// but not decoded yet, so this is the exact position of the decoding in the DEFLATE stream.
+    /// This is useful for, e.g., indexing tools which record the bit offsets of blocks.
+    ///
+    /// If the decoding has failed, the returned value is unspecified.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// assert_eq!(decoder.total_bits_consumed(), 0);
+    ///
+    /// decoder.read_to_end(&mut Vec::new()).unwrap();
+    /// assert_eq!(decoder.total_bits_consumed(), 106);
+    /// ```
+    pub fn total_bits_consumed(&self) -> u64 {
+        self.total_in() * 8 - u64::from(self.bit_reader.buffered_bit_count())
+    }
+
+    /// Returns the position just after the end of the DEFLATE stream,
+    /// as a pair of the byte offset and the bit offset (`0..8`, from the least significant bit) in the byte.
+    ///
+    /// A DEFLATE stream ends at the end of its final block, which is not necessarily byte-aligned,
+    /// so formats which pack data immediately after the stream need this position.
+    /// Note that the inner stream has been read up to [`Decoder::total_in`],
+    /// which may be after the returned position.
+    ///
+    /// Returns `None` if the end of the stream has not been decoded yet.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// assert_eq!(decoder.end_position(), None);
+    ///
+    /// decoder.read_to_end(&mut Vec::new()).unwrap();
+    /// assert_eq!(decoder.end_position(), Some((13, 2)));
+    /// ```
+    pub fn end_position(&self) -> Option<(u64, u8)> {
+        if !self.eos || self.block.is_some() || self.non_compressed_remaining > 0 {
+            return None;
+        }
+        let bits = self.total_bits_consumed();
+        Some((bits / 8, (bits % 8) as u8))
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(decoder.total_in(), 14);
+    /// assert_eq!(decoder.total_out(), 12);
+    /// ```
+    pub fn total_out(&self) -> u64 {
+        self.decoded_size
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder (excluding the inner stream).
+    ///
+    /// Most of the memory is used by the buffer which holds the sliding window (32 KiB by default)
+    /// and the decoded data which have not been read yet.
+    /// The decoding of a block is suspended once twice the window size (64 KiB) of data are buffered,
+    /// so the buffer does not grow beyond about six times the window size (192 KiB)
+    /// (and the allocated capacity beyond twice that, because the buffer grows geometrically).
+    /// See [`DecodeOptions::window_size`] to reduce it.
+    pub fn memory_usage(&self) -> usize {
+        self.lz77_decoder.memory_usage()
+            + self.block.as_ref().map_or(0, |block| block.memory_usage())
+    }
+
+    /// Registers a callback which is invoked every time `interval` bytes of the DEFLATE stream have been read.
+    ///
+    /// The arguments of the callback are the number of bytes read from the inner stream so far
+    /// and the number of decoded bytes read from this decoder so far.
+    /// The callback is also invoked once when the end of the stream is reached.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::Decoder;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let last = Arc::new(Mutex::new(None));
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// {
+    ///     let last = last.clone();
+    ///     decoder.set_progress_callback(1024 * 1024, move |bytes_in, bytes_out| {
+    ///         *last.lock().unwrap() = Some((bytes_in, bytes_out));
+    ///     });
+    /// }
+    /// decoder.read_to_end(&mut Vec::new()).unwrap();
+    ///
+    /// assert_eq!(*last.lock().unwrap(), Some((14, 12)));
+    /// ```
+    pub fn set_progress_callback<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        let mut progress = ProgressHook::new(interval, callback);
+        progress.notify(self.bit_reader.as_inner_ref().count(), self.decoded_size);
+        self.progress = Some(progress);
+    }
+
+    /// Registers a check which is invoked periodically to tell whether the decoding should be cancelled.
+    ///
+    /// The check is invoked at the beginning of each block and every 64 KiB of decoded data.
+    /// Once it returns `true`, reading from this decoder fails with an error
+    /// whose kind is [`ErrorKind::Cancelled`] (`io::ErrorKind::Other`).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::Decoder;
+    /// use libflate::error::{Error, ErrorKind};
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let cancelled = Arc::new(AtomicBool::new(false));
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// {
+    ///     let cancelled = cancelled.clone();
+    ///     decoder.set_cancellation_check(move || cancelled.load(Ordering::Relaxed));
+    /// }
+    ///
+    /// // e.g., from another thread
+    /// cancelled.store(true, Ordering::Relaxed);
+    ///
+    /// let error = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+    /// # #[cfg(not(feature = "no_std"))]
+    /// assert_eq!(Error::from_io_error(&error).unwrap().kind(), ErrorKind::Cancelled);
+    /// ```
+    pub fn set_cancellation_check<F>(&mut self, is_cancelled: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.cancellation = Some(CancellationCheck::new(is_cancelled));
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
-        self.bit_reader.as_inner_ref()
+        self.bit_reader.as_inner_ref().as_inner_ref()
     }
 
     /// Returns the mutable reference to the inner stream.
     pub fn as_inner_mut(&mut self) -> &mut R {
-        self.bit_reader.as_inner_mut()
+        self.bit_reader.as_inner_mut().as_inner_mut()
     }
 
     /// Unwraps this `Decoder`, returning the underlying reader.
     ///
+    /// If the end of the stream has been reached, the reader is positioned just after the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Cursor;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Cursor;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let decoder = Decoder::new(Cursor::new(&encoded_data));
+    /// assert_eq!(decoder.into_inner().into_inner(), &encoded_data);
+    /// ```
+    pub fn into_inner(self) -> R {
+        self.bit_reader.into_inner().into_inner()
+    }
+
+    /// Returns the data that has been decoded but has not yet been read.
+    ///
+    /// This method is useful to retrieve partial decoded data when the decoding process is failed.
+    pub fn unread_decoded_data(&self) -> &[u8] {
+        self.lz77_decoder.buffer()
+    }
+
+    /// Decodes exactly `buf.len()` bytes into `buf`.
+    ///
+    /// This is intended for the formats which record the uncompressed size outside of the DEFLATE stream
+    /// (e.g., ZIP entries). Unlike `Read::read_exact`, which lets the decoder buffer up to 64 KiB
+    /// of decoded data ahead, this method decodes only the symbols needed to fill `buf`,
+    /// so the inner stream is read no further than necessary (except for a few bytes in the bit buffer,
+    /// see [`Decoder::total_in`]).
+    /// The decoder stays in a consistent state, so the following data can be decoded by further calls.
+    ///
+    /// If the stream ends before `buf` is filled, an `UnexpectedEof` error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{self, Read};
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    ///
+    /// let mut hello = [0; 5];
+    /// decoder.decode_exact(&mut hello).unwrap();
+    /// assert_eq!(&hello, b"Hello");
+    /// assert!(decoder.total_in() < encoded_data.len() as u64);
+    ///
+    /// let mut rest = Vec::new();
+    /// decoder.read_to_end(&mut rest).unwrap();
+    /// assert_eq!(rest, b" World!");
+    ///
+    /// # #[cfg(not(feature = "no_std"))]
+    /// assert_eq!(decoder.decode_exact(&mut [0; 1]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    /// ```
+    pub fn decode_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut offset = 0;
+        while offset < buf.len() {
+            self.fill_decoded_buffer_up_to(buf.len() - offset)?;
+            let size = self.lz77_decoder.read(&mut buf[offset..])?;
+            if size == 0 {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "The DEFLATE stream ended before the requested size of data were decoded",
+                )
+                .with_value(offset as u64)
+                .with_expected(buf.len() as u64)
+                .into());
+            }
+            self.add_decoded_size(size);
+            offset += size;
+        }
+        Ok(())
+    }
+
+    /// Discards the next `n` decoded bytes without copying them.
+    ///
+    /// The data are decoded into the internal buffer of the decoder only, so no output buffer is allocated.
+    /// Returns the number of the discarded bytes, which is less than `n` only if the end of the stream is reached.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// assert_eq!(decoder.skip(6).unwrap(), 6);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"World!");
+    /// assert_eq!(decoder.skip(1).unwrap(), 0);
+    /// ```
+    pub fn skip(&mut self, n: u64) -> io::Result<u64> {
+        util::skip(self, n)
+    }
+
+    /// Returns an iterator which yields the decoded data in chunks of `chunk_size` bytes.
+    ///
+    /// See [`read::Chunks`] for the details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`.
+    ///
     /// # Examples
     /// ```
-    /// #[cfg(feature = "no_std")]
-    /// use core2::io::Cursor;
-    /// #[cfg(not(feature = "no_std"))]
-    /// use std::io::Cursor;
     /// use libflate::deflate::Decoder;
     ///
     /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
-    /// let decoder = Decoder::new(Cursor::new(&encoded_data));
-    /// assert_eq!(decoder.into_inner().into_inner(), &encoded_data);
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut decoded_data = Vec::new();
+    /// for chunk in decoder.chunks(5) {
+    ///     let chunk = chunk.unwrap();
+    ///     assert!(chunk.len() <= 5);
+    ///     decoded_data.extend_from_slice(&chunk);
+    /// }
+    /// assert_eq!(decoded_data, b"Hello World!");
     /// ```
-    pub fn into_inner(self) -> R {
-        self.bit_reader.into_inner()
+    pub fn chunks(&mut self, chunk_size: usize) -> read::Chunks<&mut Self> {
+        read::Chunks::new(self, chunk_size)
     }
 
-    /// Returns the data that has been decoded but has not yet been read.
+    /// Replaces the buffer (the sliding window and the decoded data) of this decoder with `buffer`,
+    /// returning the current one.
     ///
-    /// This method is useful to retrieve partial decoded data when the decoding process is failed.
-    pub fn unread_decoded_data(&self) -> &[u8] {
-        self.lz77_decoder.buffer()
+    /// This is intended to reuse the allocation of `buffer` before starting the decoding.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn replace_buffer(&mut self, mut buffer: lz77::Lz77Decoder) -> lz77::Lz77Decoder {
+        buffer.clear();
+        buffer.set_window_size(self.options.window_size);
+        core::mem::replace(&mut self.lz77_decoder, buffer)
     }
 
     pub(crate) fn reset(&mut self) {
         self.bit_reader.reset();
         self.lz77_decoder.clear();
-        self.eos = false
+        self.block = None;
+        self.non_compressed_remaining = 0;
+        self.eos = false;
+        self.deferred_error = None;
+    }
+
+    /// Seeks the inner stream to `start` (the start of a DEFLATE stream),
+    /// and resets this decoder to decode the stream from the beginning.
+    pub(crate) fn rewind_to(&mut self, start: u64) -> io::Result<()>
+    where
+        R: Seek,
+    {
+        self.as_inner_mut().seek(SeekFrom::Start(start))?;
+        self.reset();
+        let _ = self.bit_reader.check_last_error();
+        self.bit_reader.as_inner_mut().reset_count();
+        self.decoded_size = 0;
+        if let Some(progress) = self.progress.as_mut() {
+            progress.restart();
+        }
+        Ok(())
+    }
+
+    /// Returns the position of the start of the DEFLATE stream in the inner stream.
+    pub(crate) fn stream_start(&mut self) -> io::Result<u64>
+    where
+        R: Seek,
+    {
+        let read = self.total_in();
+        util::stream_start(self.as_inner_mut(), read)
     }
 
-    fn read_non_compressed_block(&mut self) -> io::Result<()> {
+    fn read_non_compressed_block_len(&mut self) -> io::Result<u16> {
         self.bit_reader.reset();
         let mut buf = [0; 2];
         self.bit_reader.as_inner_mut().read_exact(&mut buf)?;
@@ -93,81 +743,414 @@ where
         self.bit_reader.as_inner_mut().read_exact(&mut buf)?;
         let nlen = u16::from_le_bytes(buf);
         if !len != nlen {
-            Err(invalid_data_error!(
-                "LEN={} is not the one's complement of NLEN={}",
-                len,
-                nlen
-            ))
+            Err(Error::new(
+                ErrorKind::InvalidHeader,
+                "NLEN is not the one's complement of LEN",
+            )
+            .with_value(nlen)
+            .with_expected(!len)
+            .into())
         } else {
-            self.lz77_decoder
-                .extend_from_reader(self.bit_reader.as_inner_mut().take(len.into()))
-                .and_then(|used| {
-                    if used != len.into() {
-                        Err(io::Error::new(
-                            io::ErrorKind::UnexpectedEof,
-                            #[cfg(not(feature = "no_std"))]
-                            format!("The reader has incorrect length: expected {len}, read {used}"),
-                            #[cfg(feature = "no_std")]
-                            "The reader has incorrect length",
-                        ))
-                    } else {
-                        Ok(())
-                    }
-                })
+            Ok(len)
+        }
+    }
+    fn read_non_compressed_block(&mut self, limit: usize) -> io::Result<()> {
+        self.non_compressed_remaining = self.read_non_compressed_block_len()?;
+        self.read_non_compressed_data(limit)
+    }
+    fn read_non_compressed_data(&mut self, limit: usize) -> io::Result<()> {
+        let remaining = self.non_compressed_remaining;
+        let len = cmp::min(usize::from(remaining), limit);
+        let used = self
+            .lz77_decoder
+            .extend_from_reader_up_to(self.bit_reader.as_inner_mut(), len)?;
+        if used != len {
+            return Err(short_non_compressed_block_error(used, remaining));
         }
+        self.non_compressed_remaining -= len as u16;
+        Ok(())
     }
-    fn read_compressed_block<H>(&mut self, huffman: &H) -> io::Result<()>
+    fn read_compressed_block<H>(&mut self, huffman: &H, limit: usize) -> io::Result<()>
     where
         H: symbol::HuffmanCodec,
     {
         let symbol_decoder = huffman.load(&mut self.bit_reader)?;
-        loop {
-            let s = symbol_decoder.decode_unchecked(&mut self.bit_reader);
+        self.decode_compressed_block(symbol_decoder, limit)
+    }
+    /// Decodes the symbols of the current block until `limit` bytes of decoded data are buffered
+    /// (it may be exceeded by a back-reference) or the end of the block is reached.
+    fn decode_compressed_block(
+        &mut self,
+        symbol_decoder: symbol::Decoder,
+        limit: usize,
+    ) -> io::Result<()> {
+        self.check_cancellation()?;
+
+        // Runs of literals (which dominate text-like data) are decoded into this buffer
+        // and appended to the LZ77 decoder at once.
+        let mut literals = [0; LITERAL_BATCH_SIZE];
+        while self.lz77_decoder.buffer().len() < limit {
+            let batch = cmp::min(literals.len(), limit - self.lz77_decoder.buffer().len());
+            let (n, s) = symbol_decoder
+                .decode_literals_unchecked(&mut self.bit_reader, &mut literals[..batch]);
+            self.lz77_decoder.decode_literals(&literals[..n])?;
             self.bit_reader.check_last_error()?;
             match s {
-                symbol::Symbol::Code(code) => {
+                None => {}
+                Some(symbol::Symbol::Code(code)) => {
                     self.lz77_decoder.decode(code)?;
                 }
-                symbol::Symbol::EndOfBlock => {
-                    break;
+                Some(symbol::Symbol::EndOfBlock) => {
+                    return Ok(());
                 }
             }
         }
+        self.block = Some(symbol_decoder);
+        Ok(())
+    }
+    /// Decodes the following blocks until some decoded data are buffered or the end of the stream is reached.
+    fn fill_decoded_buffer(&mut self) -> io::Result<()> {
+        self.fill_decoded_buffer_up_to(self.options.max_buffered_decoded_size())
+    }
+    /// Same as [`Decoder::fill_decoded_buffer`], except that the decoding stops once `limit` bytes are buffered.
+    fn fill_decoded_buffer_up_to(&mut self, limit: usize) -> io::Result<()> {
+        while self.lz77_decoder.buffer().is_empty() {
+            if let Some(block) = self.block.take() {
+                self.decode_compressed_block(block, limit)?;
+            } else if self.non_compressed_remaining > 0 {
+                self.read_non_compressed_data(limit)?;
+            } else if self.eos {
+                self.finish_progress();
+                break;
+            } else if let Some(btype) = self.read_next_block_header()? {
+                self.read_block(btype, limit)?;
+            } else {
+                self.eos = true;
+            }
+        }
         Ok(())
     }
+    /// Returns `true` if decoded data can be read without reading the inner stream.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn has_buffered_data(&self) -> bool {
+        !self.lz77_decoder.buffer().is_empty()
+    }
+    fn add_decoded_size(&mut self, size: usize) {
+        self.decoded_size += size as u64;
+        if let Some(progress) = self.progress.as_mut() {
+            progress.notify(self.bit_reader.as_inner_ref().count(), self.decoded_size);
+        }
+    }
+    fn finish_progress(&mut self) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress.finish(self.bit_reader.as_inner_ref().count(), self.decoded_size);
+        }
+    }
+    fn check_cancellation(&self) -> io::Result<()> {
+        self.cancellation
+            .as_ref()
+            .map_or(Ok(()), |cancellation| cancellation.check())
+    }
+    /// Same as [`Decoder::read_block_header`], except that this returns `None`
+    /// if the inner stream of an endless DEFLATE stream ends at a block boundary.
+    fn read_next_block_header(&mut self) -> io::Result<Option<u16>> {
+        if !self.options.endless || self.bit_reader.has_buffered_bits() {
+            return self.read_block_header().map(Some);
+        }
+        let state = self.bit_reader.state();
+        let count = self.bit_reader.as_inner_ref().count();
+        match self.read_block_header() {
+            Err(e)
+                if e.kind() == io::ErrorKind::UnexpectedEof
+                    && self.bit_reader.as_inner_ref().count() == count =>
+            {
+                self.bit_reader.restore_state(state);
+                Ok(None)
+            }
+            result => result.map(Some),
+        }
+    }
+    fn read_block_header(&mut self) -> io::Result<u16> {
+        self.check_cancellation()?;
+        let bfinal = self.bit_reader.read_bit()?;
+        let btype = self.bit_reader.read_bits(2)?;
+        self.eos = bfinal;
+        Ok(btype)
+    }
+    fn read_block(&mut self, btype: u16, limit: usize) -> io::Result<()> {
+        match btype {
+            0b00 => self.read_non_compressed_block(limit),
+            0b01 => self.read_compressed_block(&symbol::FixedHuffmanCodec, limit),
+            0b10 if self.options.strict_huffman_header => {
+                self.read_compressed_block(&symbol::StrictDynamicHuffmanCodec, limit)
+            }
+            0b10 => self.read_compressed_block(&symbol::DynamicHuffmanCodec, limit),
+            0b11 => Err(Error::new(
+                ErrorKind::InvalidHeader,
+                "btype 0x11 of DEFLATE is reserved(error) value",
+            )
+            .into()),
+            _ => unreachable!(),
+        }
+    }
+}
+impl<'a> Decoder<&'a [u8]> {
+    /// Decodes the next chunk of data.
+    ///
+    /// This is a fast path for in-memory decompression.
+    /// The bits are read directly from the input slice, and the contents of a non-compressed (stored) block
+    /// are returned as a slice borrowed from the input (only the last `window_size` bytes of them,
+    /// which may be referred by the following blocks, are copied into the sliding window).
+    /// The data of a compressed block are returned as owned buffers.
+    /// A compressed block is split into chunks of at most twice the [window size](DecodeOptions::window_size)
+    /// plus the length of a match (258 bytes), while a borrowed chunk holds a whole non-compressed block
+    /// (up to 65,535 bytes). So the chunks have no fixed size; it depends on the block structure of the input.
+    ///
+    /// Returns `None` once the end of the stream has been reached. Empty chunks are never returned.
+    ///
+    /// Note that this method must not be mixed with `Read::read`
+    /// unless the data buffered by the latter ([`Decoder::unread_decoded_data`]) have been consumed.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::{Decoder, EncodeOptions, Encoder};
+    /// use std::borrow::Cow;
+    ///
+    /// let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().no_compression());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let chunk = decoder.next_chunk().unwrap().unwrap();
+    /// assert!(matches!(chunk, Cow::Borrowed(b"Hello World!")));
+    /// assert!(decoder.next_chunk().unwrap().is_none());
+    /// ```
+    pub fn next_chunk(&mut self) -> io::Result<Option<Cow<'a, [u8]>>> {
+        let limit = self.options.max_buffered_decoded_size();
+        loop {
+            let buffered = self.lz77_decoder.buffer().len();
+            if buffered > 0 {
+                let mut chunk = Vec::new();
+                util::try_reserve(&mut chunk, buffered)?;
+                chunk.resize(buffered, 0);
+                self.lz77_decoder.read_exact(&mut chunk)?;
+                self.add_decoded_size(chunk.len());
+                return Ok(Some(Cow::Owned(chunk)));
+            } else if let Some(block) = self.block.take() {
+                self.decode_compressed_block(block, limit)?;
+            } else if self.non_compressed_remaining > 0 {
+                self.read_non_compressed_data(limit)?;
+            } else if self.eos {
+                self.finish_progress();
+                return Ok(None);
+            } else {
+                let btype = match self.read_next_block_header()? {
+                    Some(btype) => btype,
+                    None => {
+                        self.eos = true;
+                        continue;
+                    }
+                };
+                if btype != 0b00 {
+                    self.read_block(btype, limit)?;
+                    continue;
+                }
+
+                let len = self.read_non_compressed_block_len()?;
+                let input = *self.bit_reader.as_inner_ref().as_inner_ref();
+                if input.len() < usize::from(len) {
+                    return Err(short_non_compressed_block_error(input.len(), len));
+                }
+                let (chunk, rest) = input.split_at(usize::from(len));
+                *self.bit_reader.as_inner_mut().as_inner_mut() = rest;
+                self.bit_reader.as_inner_mut().add(chunk.len());
+                let window_start = chunk
+                    .len()
+                    .saturating_sub(usize::from(self.options.window_size));
+                self.lz77_decoder
+                    .try_extend_from_slice(&chunk[window_start..])?;
+                if !chunk.is_empty() {
+                    self.add_decoded_size(chunk.len());
+                    return Ok(Some(Cow::Borrowed(chunk)));
+                }
+            }
+        }
+    }
 }
 impl<R> Read for Decoder<R>
 where
     R: Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if !self.lz77_decoder.buffer().is_empty() {
-            self.lz77_decoder.read(buf)
-        } else if self.eos {
-            Ok(0)
-        } else {
-            let bfinal = self.bit_reader.read_bit()?;
-            let btype = self.bit_reader.read_bits(2)?;
-            self.eos = bfinal;
-            match btype {
-                0b00 => {
-                    self.read_non_compressed_block()?;
-                    self.read(buf)
-                }
-                0b01 => {
-                    self.read_compressed_block(&symbol::FixedHuffmanCodec)?;
-                    self.read(buf)
-                }
-                0b10 => {
-                    self.read_compressed_block(&symbol::DynamicHuffmanCodec)?;
-                    self.read(buf)
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = self.deferred_error.take() {
+            return Err(e);
+        }
+        let mut size = 0;
+        loop {
+            if let Err(e) = self.fill_decoded_buffer() {
+                if size == 0 {
+                    return Err(e);
                 }
-                0b11 => Err(invalid_data_error!(
-                    "btype 0x11 of DEFLATE is reserved(error) value"
-                )),
-                _ => unreachable!(),
+                self.deferred_error = Some(e);
+                break;
+            }
+            let n = self.lz77_decoder.read(&mut buf[size..])?;
+            self.add_decoded_size(n);
+            size += n;
+
+            let in_block = self.block.is_some() || self.non_compressed_remaining > 0;
+            if !self.options.greedy_read || n == 0 || size == buf.len() || !in_block {
+                break;
             }
         }
+        Ok(size)
+    }
+
+    /// Fills `bufs` in order.
+    ///
+    /// The buffers following the first non-empty one are filled only with the data which have already been decoded,
+    /// so this method reads the inner stream at most as much as a single `read` does.
+    #[cfg(not(feature = "no_std"))]
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        util::read_vectored(self, bufs, Self::has_buffered_data)
+    }
+}
+
+/// Exposes the buffer of the decoded data, so that they can be processed without copying.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::BufRead;
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::BufRead;
+/// use libflate::deflate::Decoder;
+///
+/// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 228, 226, 2, 0];
+///
+/// let mut decoder = Decoder::new(&encoded_data[..]);
+/// let mut lines = Vec::new();
+/// for line in decoder.lines() {
+///     lines.push(line.unwrap());
+/// }
+/// assert_eq!(lines, ["Hello World!", ""]);
+/// ```
+impl<R> BufRead for Decoder<R>
+where
+    R: Read,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.fill_decoded_buffer()?;
+        Ok(self.lz77_decoder.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = cmp::min(amt, self.lz77_decoder.buffer().len());
+        self.lz77_decoder.consume(amt);
+        self.add_decoded_size(amt);
+    }
+}
+
+impl<R> util::Rewind for Decoder<R>
+where
+    R: Read + Seek,
+{
+    fn position(&self) -> u64 {
+        self.total_out()
+    }
+    fn rewind(&mut self) -> io::Result<()> {
+        let start = self.stream_start()?;
+        self.rewind_to(start)
+    }
+}
+
+/// Seeks in the decoded data.
+///
+/// Seeking forward decodes and discards the data (see [`Decoder::skip`]),
+/// and seeking backward rewinds the inner stream to the start of the DEFLATE stream and decodes it again.
+/// So the cost of a seek is proportional to the distance from the current position (or the start of the stream).
+/// Seeking beyond the end of the stream moves the position to the end.
+///
+/// The start of the stream is derived from the current position of the inner stream and [`Decoder::total_in`],
+/// so the inner stream should not be moved by the other code.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::{Cursor, Read, Seek, SeekFrom};
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::{Cursor, Read, Seek, SeekFrom};
+/// use libflate::deflate::Decoder;
+///
+/// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+/// let mut decoder = Decoder::new(Cursor::new(&encoded_data[..]));
+///
+/// let mut buf = [0; 5];
+/// assert_eq!(decoder.seek(SeekFrom::Start(6)).unwrap(), 6);
+/// decoder.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"World");
+///
+/// assert_eq!(decoder.seek(SeekFrom::End(-12)).unwrap(), 0);
+/// decoder.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"Hello");
+/// ```
+impl<R> Seek for Decoder<R>
+where
+    R: Read + Seek,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        util::seek(self, pos)
+    }
+}
+
+fn short_non_compressed_block_error(used: usize, len: u16) -> io::Error {
+    Error::new(ErrorKind::UnexpectedEof, "The reader has incorrect length")
+        .with_value(used as u64)
+        .with_expected(len)
+        .into()
+}
+
+/// Decompresses the DEFLATE stream `input`.
+///
+/// The capacity of the returned vector is reserved in advance from the size of `input`.
+/// Use [`decompress_to_vec_with_capacity`] if the size of the decompressed data is (roughly) known.
+pub fn decompress_to_vec(input: &[u8]) -> io::Result<Vec<u8>> {
+    decompress_to_vec_with_capacity(input, util::decompressed_capacity_hint(input.len()))
+}
+
+/// Decompresses the DEFLATE stream `input`, reserving `capacity` bytes for the decompressed data in advance.
+///
+/// The vector grows as usual if `capacity` is insufficient.
+///
+/// Unless the `no_std` feature is enabled, the sliding window of the decoder is kept in a thread-local [`CoderPool`](crate::pool::CoderPool),
+/// and reused by the following calls on the same thread.
+///
+/// # Examples
+/// ```
+/// use libflate::deflate;
+///
+/// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+/// let decoded_data = deflate::decompress_to_vec_with_capacity(&encoded_data, 12).unwrap();
+/// assert_eq!(decoded_data, b"Hello World!");
+/// assert_eq!(decoded_data.capacity(), 12);
+/// ```
+pub fn decompress_to_vec_with_capacity(input: &[u8], capacity: usize) -> io::Result<Vec<u8>> {
+    #[cfg(not(feature = "no_std"))]
+    {
+        crate::pool::with_local_pool(|pool| {
+            util::read_to_vec(pool.deflate_decoder(input), capacity)
+        })
+    }
+    #[cfg(feature = "no_std")]
+    {
+        util::read_to_vec(Decoder::new(input), capacity)
     }
 }
 
@@ -191,7 +1174,7 @@ mod tests {
             119, 142, 170, 169, 138, 202, 112, 228, 140, 38,
         ];
         let mut bit_reader = crate::bit::BitReader::new(&input[..]);
-        assert_eq!(bit_reader.read_bit().unwrap(), false); // not final block
+        assert!(!bit_reader.read_bit().unwrap()); // not final block
         assert_eq!(bit_reader.read_bits(2).unwrap(), 0b10); // DynamicHuffmanCodec
         DynamicHuffmanCodec.load(&mut bit_reader).unwrap();
     }
@@ -225,4 +1208,561 @@ mod tests {
         let mut decoder = Decoder::new(&input[..]);
         assert!(io::copy(&mut decoder, &mut io::sink()).is_err());
     }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn memory_usage_is_bounded() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::Write;
+
+        let plain = (0..1_000_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().block_size(64 * 1024));
+        for chunk in plain.chunks(10_000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let encoded = encoder.finish().into_result().unwrap();
+
+        // A single huge block
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(&plain).unwrap();
+        let single_block_encoded = encoder.finish().into_result().unwrap();
+
+        for encoded in [encoded, single_block_encoded] {
+            let mut decoder = Decoder::new(&encoded[..]);
+            let mut buf = [0; 1024];
+            let mut decoded = Vec::new();
+            let mut max_usage = 0;
+            loop {
+                let size = decoder.read(&mut buf).unwrap();
+                if size == 0 {
+                    break;
+                }
+                decoded.extend_from_slice(&buf[..size]);
+                max_usage = max_usage.max(decoder.memory_usage());
+            }
+            assert_eq!(decoded, plain);
+            assert!(max_usage > 0);
+            // The capacity of the buffer is doubled as needed
+            assert!(max_usage <= 2 * 256 * 1024, "{}", max_usage);
+        }
+    }
+
+    #[test]
+    fn literal_runs_work() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::Write;
+
+        // Mostly literals, with some back-references in between
+        let mut seed = 12345u32;
+        let mut plain = Vec::new();
+        for i in 0..50_000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            plain.push((seed >> 24) as u8);
+            if i % 1000 == 0 {
+                plain.extend_from_slice(b"a back-reference follows: a back-reference");
+            }
+        }
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().fixed_huffman_codes(),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoder = Decoder::new(&encoded[..]);
+            let mut decoded = Vec::new();
+            let mut size = 0;
+            while decoded.len() < plain.len() {
+                size = size % 100 + 1;
+                let mut buf = vec![0; cmp::min(size, plain.len() - decoded.len())];
+                decoder.decode_exact(&mut buf).unwrap();
+                decoded.extend_from_slice(&buf);
+            }
+            assert_eq!(decoded, plain);
+
+            // The literals decoded before an error remain available
+            let mut decoder = Decoder::new(&encoded[..encoded.len() / 2]);
+            let mut decoded = Vec::new();
+            assert!(decoder.read_to_end(&mut decoded).is_err());
+            decoded.extend_from_slice(decoder.unread_decoded_data());
+            assert!(decoded.len() >= plain.len() / 2 - 100);
+            assert_eq!(decoded, &plain[..decoded.len()]);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn decode_exact_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::Write;
+
+        let plain = (0..200_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for options in [EncodeOptions::new(), EncodeOptions::new().no_compression()] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoder = Decoder::new(&encoded[..]);
+            let mut decoded = vec![0; 1000];
+            decoder.decode_exact(&mut decoded).unwrap();
+            assert_eq!(decoded, &plain[..1000]);
+            assert!(decoder.unread_decoded_data().len() < 258);
+            let total_in = decoder.total_in();
+
+            // Reading the next byte doesn't need much more input
+            let mut buf = [0; 1];
+            decoder.decode_exact(&mut buf).unwrap();
+            assert_eq!(buf[0], plain[1000]);
+            assert!(decoder.total_in() - total_in <= 8);
+
+            let mut rest = Vec::new();
+            decoder.read_to_end(&mut rest).unwrap();
+            assert_eq!(rest, &plain[1001..]);
+        }
+
+        // Chunks of various sizes
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let mut decoder = Decoder::new(&encoded[..]);
+        let mut decoded = Vec::new();
+        for size in (0..).map(|i| i * 37 % 5000) {
+            let size = size.min(plain.len() - decoded.len());
+            let mut buf = vec![0; size];
+            decoder.decode_exact(&mut buf).unwrap();
+            decoded.extend_from_slice(&buf);
+            if decoded.len() == plain.len() {
+                break;
+            }
+        }
+        assert_eq!(decoded, plain);
+        let error = decoder.decode_exact(&mut [0; 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn small_window_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use crate::lz77::DefaultLz77Encoder;
+        use std::io::Write;
+
+        let plain = (0..200_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for options in [
+            EncodeOptions::with_lz77(DefaultLz77Encoder::with_window_size(2048)),
+            EncodeOptions::new().no_compression(),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let options = DecodeOptions::new().window_size(2048);
+            let mut decoder = Decoder::with_options(&encoded[..], options);
+            let mut buf = [0; 1024];
+            let mut decoded = Vec::new();
+            let mut max_usage = 0;
+            loop {
+                let size = decoder.read(&mut buf).unwrap();
+                if size == 0 {
+                    break;
+                }
+                decoded.extend_from_slice(&buf[..size]);
+                max_usage = max_usage.max(decoder.memory_usage());
+            }
+            assert_eq!(decoded, plain);
+            assert!(max_usage <= 32 * 1024, "{}", max_usage);
+        }
+
+        // Back-references beyond the window are rejected
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        let options = DecodeOptions::new().window_size(1024);
+        let mut decoder = Decoder::with_options(&encoded[..], options);
+        let error = io::copy(&mut decoder, &mut io::sink()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn next_chunk_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::Write;
+
+        let plain = (0..200_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for (options, stored) in [
+            (EncodeOptions::new(), false),
+            (EncodeOptions::new().no_compression(), true),
+            (EncodeOptions::new().fixed_huffman_codes(), false),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options.block_size(10_000));
+            for chunk in plain.chunks(30_000) {
+                encoder.write_all(chunk).unwrap();
+                encoder.flush().unwrap();
+            }
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoder = Decoder::new(&encoded[..]);
+            let mut decoded = Vec::new();
+            let mut borrowed = 0;
+            while let Some(chunk) = decoder.next_chunk().unwrap() {
+                assert!(!chunk.is_empty());
+                if let Cow::Borrowed(chunk) = chunk {
+                    borrowed += chunk.len();
+                }
+                decoded.extend_from_slice(&chunk);
+            }
+            assert_eq!(decoded, plain);
+            assert_eq!(borrowed == plain.len(), stored);
+            assert!(decoder.as_inner_ref().is_empty());
+            assert!(
+                decoder.memory_usage() <= 2 * 256 * 1024,
+                "{}",
+                decoder.memory_usage()
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn next_chunk_keeps_window_of_stored_blocks() {
+        // A stored block ("abc") followed by a fixed Huffman block that refers to it
+        let mut input = vec![0b000, 3, 0, !3, !0];
+        input.extend_from_slice(b"abc");
+        let mut encoded = Vec::new();
+        {
+            use crate::deflate::symbol::{FixedHuffmanCodec, HuffmanCodec, Symbol};
+            use crate::lz77::Code;
+            let symbols = [
+                Symbol::Code(Code::Pointer {
+                    length: 6,
+                    backward_distance: 3,
+                }),
+                Symbol::EndOfBlock,
+            ];
+            let mut writer = crate::bit::BitWriter::new(&mut encoded);
+            writer.write_bit(true).unwrap();
+            writer.write_bits(2, 0b01).unwrap();
+            let codec = FixedHuffmanCodec.build(&symbols).unwrap();
+            for s in &symbols {
+                codec.encode(&mut writer, s).unwrap();
+            }
+            writer.flush().unwrap();
+        }
+        input.extend_from_slice(&encoded);
+
+        let mut decoder = Decoder::new(&input[..]);
+        assert_eq!(
+            decoder.next_chunk().unwrap(),
+            Some(Cow::Borrowed(&b"abc"[..]))
+        );
+        assert_eq!(
+            decoder.next_chunk().unwrap(),
+            Some(Cow::Owned(b"abcabc".to_vec()))
+        );
+        assert_eq!(decoder.next_chunk().unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn small_window_bounds_memory_of_stored_blocks() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::Write;
+
+        let plain = (0..300_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for block_size in [10_000, 65_535] {
+            let options = EncodeOptions::new().no_compression().block_size(block_size);
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            for window_size in [1024, 2048] {
+                let options = DecodeOptions::new().window_size(window_size);
+                let limit = options.max_buffered_decoded_size();
+
+                // The window copied from each borrowed block
+                let mut decoder = Decoder::with_options(&encoded[..], options.clone());
+                let mut decoded = Vec::new();
+                while let Some(chunk) = decoder.next_chunk().unwrap() {
+                    decoded.extend_from_slice(&chunk);
+                    assert!(
+                        decoder.memory_usage() <= limit,
+                        "{}",
+                        decoder.memory_usage()
+                    );
+                }
+                assert_eq!(decoded, plain);
+
+                // The data read into the buffer
+                let mut decoder = Decoder::with_options(&encoded[..], options);
+                let mut buf = [0; 1024];
+                let mut decoded = Vec::new();
+                let mut max_usage = 0;
+                loop {
+                    let size = decoder.read(&mut buf).unwrap();
+                    if size == 0 {
+                        break;
+                    }
+                    decoded.extend_from_slice(&buf[..size]);
+                    max_usage = max_usage.max(decoder.memory_usage());
+                }
+                assert_eq!(decoded, plain);
+                // See the doc of `Decoder::memory_usage`
+                assert!(max_usage <= limit * 6, "{}", max_usage);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn strict_huffman_header_works() {
+        // BFINAL=1, BTYPE=10 and HLIT=30 (i.e., 287 literal/length codes)
+        let input = [0b1111_0101, 0, 0, 0];
+
+        let mut decoder = Decoder::with_options(&input[..], DecodeOptions::strict());
+        let error = io::copy(&mut decoder, &mut io::sink()).unwrap_err();
+        let error = Error::from_io_error(&error).unwrap();
+        assert_eq!(error.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(error.value(), Some(287));
+
+        let mut decoder = Decoder::new(&input[..]);
+        let error = io::copy(&mut decoder, &mut io::sink()).unwrap_err();
+        assert_ne!(
+            Error::from_io_error(&error).map(|e| e.kind()),
+            Some(ErrorKind::LimitExceeded)
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn reserved_distance_code_is_rejected() {
+        // BFINAL=1, BTYPE=01, the length code 257 and the (reserved) distance code 30
+        let input = [0b0000_0011, 0b0011_1110, 0];
+
+        let mut decoder = Decoder::new(&input[..]);
+        let error = io::copy(&mut decoder, &mut io::sink()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            Error::from_io_error(&error).map(|e| e.kind()),
+            Some(ErrorKind::InvalidHuffmanCode)
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn no_bytes_are_read_past_the_end_of_stream() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::Write;
+
+        let plain = (0..100_000)
+            .map(|i| (i * 7 % 253) as u8 ^ (i / 300) as u8)
+            .collect::<Vec<_>>();
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().no_compression(),
+        ] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let mut encoded = encoder.finish().into_result().unwrap();
+            encoded.extend_from_slice(b"trailing");
+
+            let mut decoder = Decoder::new(&encoded[..]);
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, plain);
+            assert_eq!(decoder.into_inner(), b"trailing");
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn skip_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::{Read, Write};
+
+        let plain = (0..200_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for options in [EncodeOptions::new(), EncodeOptions::new().no_compression()] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let encoded_data = encoder.finish().into_result().unwrap();
+
+            let mut decoder = Decoder::new(&encoded_data[..]);
+            let mut buf = [0; 10];
+            assert_eq!(decoder.skip(0).unwrap(), 0);
+            assert_eq!(decoder.skip(123_456).unwrap(), 123_456);
+            decoder.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, plain[123_456..][..10]);
+            assert_eq!(decoder.total_out(), 123_466);
+
+            let rest = plain.len() as u64 - 123_466;
+            assert_eq!(decoder.skip(u64::MAX).unwrap(), rest);
+            assert_eq!(decoder.skip(1).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn endless_stream_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::{Read, Write};
+
+        let messages = [&b"Hello "[..], &[b'a'; 100_000][..], b"World!"];
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().endless());
+        let mut boundaries = Vec::new();
+        for message in &messages {
+            encoder.write_all(message).unwrap();
+            encoder.message_boundary().unwrap();
+            boundaries.push(encoder.as_inner_ref().len());
+        }
+        encoder.write_all(b"!").unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+        assert!(encoded_data.ends_with(&[0, 0, 255, 255]));
+        boundaries.push(encoded_data.len());
+
+        // Each prefix ending at a flush point is a complete endless stream
+        let options = DecodeOptions::new().endless(true);
+        for (i, &boundary) in boundaries.iter().enumerate() {
+            let mut expected = messages[..cmp::min(i + 1, messages.len())].concat();
+            if i == messages.len() {
+                expected.push(b'!');
+            }
+            let mut decoder = Decoder::with_options(&encoded_data[..boundary], options.clone());
+            let mut buf = Vec::new();
+            decoder.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, expected);
+
+            let mut decoder = Decoder::with_options(&encoded_data[..boundary], options.clone());
+            let mut buf = Vec::new();
+            while let Some(chunk) = decoder.next_chunk().unwrap() {
+                buf.extend_from_slice(&chunk);
+            }
+            assert_eq!(buf, expected);
+
+            let mut decoder = Decoder::new(&encoded_data[..boundary]);
+            let e = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        }
+        let mut decoder = Decoder::with_options(&[][..], options.clone());
+        assert_eq!(decoder.read(&mut [0; 1]).unwrap(), 0);
+
+        // The stream ending elsewhere is still truncated
+        let mut decoder = Decoder::with_options(&encoded_data[..boundaries[0] + 1], options);
+        let e = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        // A final block ends an endless stream as usual
+        let mut encoded_data = encoded_data;
+        encoded_data.extend_from_slice(&[3, 0]);
+        encoded_data.extend_from_slice(b"trailer");
+        let mut decoder =
+            Decoder::with_options(&encoded_data[..], DecodeOptions::new().endless(true));
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, [&messages.concat()[..], b"!"].concat());
+        assert_eq!(decoder.into_inner(), b"trailer");
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn greedy_read_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::{Read, Write};
+
+        // Random bytes of 16 symbols, which compress only to about a half
+        let mut seed = 1u32;
+        let plain = (0..300_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 28) as u8
+            })
+            .collect::<Vec<_>>();
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().endless());
+        encoder.write_all(&plain[..200_000]).unwrap();
+        encoder.message_boundary().unwrap();
+        encoder.write_all(&plain[200_000..]).unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+
+        let options = DecodeOptions::new().endless(true).greedy_read(true);
+        let mut decoder = Decoder::with_options(&encoded_data[..], options.clone());
+        let mut decoded = Vec::new();
+        let mut buf = vec![0; 150_000];
+        let mut sizes = Vec::new();
+        loop {
+            let size = decoder.read(&mut buf).unwrap();
+            if size == 0 {
+                break;
+            }
+            sizes.push(size);
+            decoded.extend_from_slice(&buf[..size]);
+        }
+        assert_eq!(decoded, plain);
+        // The first read fills the buffer, and the second one stops at the sync flush
+        assert_eq!(sizes[..2], [150_000, 50_000]);
+
+        // Without the option, a read is limited by the buffered decoded data
+        let mut decoder = Decoder::new(&encoded_data[..]);
+        assert!(decoder.read(&mut buf).unwrap() < buf.len());
+
+        // The data decoded before an error are returned first
+        let truncated = &encoded_data[..encoded_data.len() / 4];
+        let mut decoder = Decoder::with_options(truncated, options);
+        let size = decoder.read(&mut buf).unwrap();
+        assert!(size > 0);
+        assert_eq!(buf[..size], plain[..size]);
+        assert!(decoder.read(&mut buf).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn end_position_works() {
+        use crate::deflate::{EncodeOptions, Encoder};
+        use std::io::{Read, Write};
+        use std::sync::{Arc, Mutex};
+
+        let plain = (0..20_000)
+            .map(|i| (i % 13 * i % 97) as u8)
+            .collect::<Vec<_>>();
+        for options in [
+            EncodeOptions::new().block_size(3000),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().no_compression(),
+        ] {
+            let total_bits = Arc::new(Mutex::new(0));
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            {
+                let total_bits = total_bits.clone();
+                encoder.set_block_callback(move |info| {
+                    *total_bits.lock().unwrap() += info.output_bits
+                });
+            }
+            encoder.write_all(&plain).unwrap();
+            let mut encoded_data = encoder.finish().into_result().unwrap();
+            let total_bits = *total_bits.lock().unwrap();
+            encoded_data.extend_from_slice(b"trailing data");
+
+            let mut decoder = Decoder::new(&encoded_data[..]);
+            assert_eq!(decoder.end_position(), None);
+            let mut buf = vec![0; 1000];
+            decoder.read_exact(&mut buf).unwrap();
+            assert!(decoder.total_bits_consumed() <= decoder.total_in() * 8);
+            decoder.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf.len(), plain.len());
+            assert_eq!(decoder.total_bits_consumed(), total_bits);
+            assert_eq!(
+                decoder.end_position(),
+                Some((total_bits / 8, (total_bits % 8) as u8))
+            );
+        }
+    }
 }
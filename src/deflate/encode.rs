@@ -1,28 +1,110 @@
 use super::symbol;
 use super::BlockType;
 use crate::bit;
-use crate::finish::{Complete, Finish};
+use crate::error::{Error, ErrorKind};
+use crate::finish::{AutoFinish, Complete, Finish};
 use crate::lz77;
+use crate::options::Strategy;
+#[cfg(not(feature = "no_std"))]
+use crate::parallel;
+use crate::util::{self, CancellationCheck, Counter, FinishState, ProgressHook};
 #[cfg(feature = "no_std")]
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec, vec::Vec};
+#[cfg(feature = "serde")]
+use core::convert::TryFrom;
 #[cfg(feature = "no_std")]
-use core::cmp;
+use core::{cmp, mem};
 #[cfg(feature = "no_std")]
-use core2::io;
+use core2::io::{self, Write as _};
 #[cfg(not(feature = "no_std"))]
-use std::{cmp, io};
+use std::{
+    cmp,
+    io::{self, Write as _},
+    mem,
+};
 
 /// The default size of a DEFLATE block.
 pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
 
+/// The default size of the internal buffers of the I/O adapters (e.g., [`read::Encoder`](crate::read::Encoder)).
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
 const MAX_NON_COMPRESSED_BLOCK_SIZE: usize = 0xFFFF;
 
+/// The number of the symbols whose statistics are compared with those of the current block
+/// in the adaptive block size mode.
+const SEGMENT_SYMBOLS: usize = 4096;
+
+/// The minimum number of the symbols of a block ended by the adaptive block size mode.
+const MIN_ADAPTIVE_BLOCK_SYMBOLS: usize = 2 * SEGMENT_SYMBOLS;
+
+/// The estimated cost (in bits) of starting a new block, which is mainly the dynamic Huffman code header.
+const BLOCK_SPLIT_COST_BITS: u64 = 1024;
+
+/// The number of the literal/length codes (286) and the distance codes (30).
+const SPLITTER_ALPHABET_SIZE: usize = 286 + 30;
+
+/// The type of a DEFLATE block (see [`BlockInfo`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    /// Non-compressed block.
+    Stored,
+
+    /// Block compressed with the fixed Huffman codes.
+    Fixed,
+
+    /// Block compressed with dynamic Huffman codes.
+    Dynamic,
+}
+
+/// Where the dynamic Huffman codes of a block came from (see [`BlockInfo`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HuffmanCodesSource {
+    /// The codes were built from the symbols of the block.
+    Built,
+
+    /// The codes of the previous block were reused (see [`EncodeOptions::reuse_huffman_codes`]).
+    Reused,
+
+    /// The codes given by [`EncodeOptions::huffman_tables`] were used.
+    Custom,
+}
+
+/// The information of a block written by an encoder (see [`Encoder::set_block_callback`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockInfo {
+    /// The type of the block.
+    pub kind: BlockKind,
+
+    /// The source of the Huffman codes (`None` unless `kind` is [`BlockKind::Dynamic`]).
+    pub huffman_codes: Option<HuffmanCodesSource>,
+
+    /// The number of the uncompressed bytes in the block.
+    pub input_size: usize,
+
+    /// The number of the bits of the block, including the block header (and the Huffman codes).
+    pub output_bits: u64,
+
+    /// Whether the block is the last one of the stream.
+    pub is_final: bool,
+}
+
 /// Options for a DEFLATE encoder.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EncodeOptions<E = lz77::DefaultLz77Encoder> {
     block_size: usize,
+    buffer_size: usize,
     dynamic_huffman: bool,
+    adaptive_block_size: bool,
+    reuse_huffman_codes: bool,
+    huffman_tables: Option<HuffmanTables>,
+    sync_flush_interval: Option<u64>,
+    line_flush: Option<u64>,
+    endless: bool,
     lz77: Option<E>,
+    #[cfg(not(feature = "no_std"))]
+    threads: usize,
+    pipelined_checksum: bool,
 }
 impl Default for EncodeOptions<lz77::DefaultLz77Encoder> {
     fn default() -> Self {
@@ -42,7 +124,17 @@ impl EncodeOptions<lz77::DefaultLz77Encoder> {
     pub fn new() -> Self {
         EncodeOptions {
             block_size: DEFAULT_BLOCK_SIZE,
+            buffer_size: DEFAULT_BUFFER_SIZE,
             dynamic_huffman: true,
+            adaptive_block_size: false,
+            reuse_huffman_codes: false,
+            huffman_tables: None,
+            sync_flush_interval: None,
+            line_flush: None,
+            endless: false,
+            #[cfg(not(feature = "no_std"))]
+            threads: 1,
+            pipelined_checksum: false,
             lz77: Some(lz77::DefaultLz77Encoder::new()),
         }
     }
@@ -64,7 +156,17 @@ where
     pub fn with_lz77(lz77: E) -> Self {
         EncodeOptions {
             block_size: DEFAULT_BLOCK_SIZE,
+            buffer_size: DEFAULT_BUFFER_SIZE,
             dynamic_huffman: true,
+            adaptive_block_size: false,
+            reuse_huffman_codes: false,
+            huffman_tables: None,
+            sync_flush_interval: None,
+            line_flush: None,
+            endless: false,
+            #[cfg(not(feature = "no_std"))]
+            threads: 1,
+            pipelined_checksum: false,
             lz77: Some(lz77),
         }
     }
@@ -100,6 +202,34 @@ where
         self
     }
 
+    /// Specifies the size of the buffer in which the I/O adapters stage the data around the encoder.
+    ///
+    /// This is the input buffer of [`read::Encoder`](crate::read::Encoder) and
+    /// the output buffer of the `tokio` encoders (if the `tokio` feature is enabled).
+    /// The encoders of this module write to the inner stream directly, so they don't use it.
+    /// Smaller buffers save memory, and larger ones reduce the number of I/O calls.
+    ///
+    /// The value is clamped to at least `1`, and the default value is `DEFAULT_BUFFER_SIZE`.
+    ///
+    /// # Example
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::deflate::EncodeOptions;
+    /// use libflate::read::Encoder;
+    ///
+    /// let options = EncodeOptions::new().buffer_size(256);
+    /// let mut encoder = Encoder::deflate(&b"Hello World!"[..], options);
+    /// let mut encoded_data = Vec::new();
+    /// encoder.read_to_end(&mut encoded_data).unwrap();
+    /// ```
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = cmp::max(size, 1);
+        self
+    }
+
     /// Specifies to compress with fixed huffman codes.
     ///
     /// # Example
@@ -114,6 +244,255 @@ where
         self
     }
 
+    /// Enables the adaptive block size mode.
+    ///
+    /// In this mode, the encoder ends a block where the statistics of the data change
+    /// (e.g., when text is followed by binary data), and keeps growing it while they stay stable.
+    /// The block size specified by [`EncodeOptions::block_size`] becomes the upper limit.
+    /// In addition, a block is encoded with the fixed Huffman codes
+    /// if the header of the dynamic Huffman codes would cost more than it saves, which is typical for small inputs.
+    ///
+    /// This is ignored if fixed Huffman codes are specified or LZ77 compression is disabled.
+    ///
+    /// # Example
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().adaptive_block_size();
+    /// let mut encoder = Encoder::with_options(Vec::new(), options);
+    /// encoder.write_all(b"Hello World!").unwrap();
+    ///
+    /// // The fixed Huffman codes are used because the input is too small to have a dynamic Huffman code header
+    /// assert_eq!(encoder.finish().into_result().unwrap(),
+    ///            [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0]);
+    /// ```
+    pub fn adaptive_block_size(mut self) -> Self {
+        self.adaptive_block_size = true;
+        self
+    }
+
+    /// Enables the reuse of the dynamic Huffman codes of the previous block.
+    ///
+    /// If the codes of the previous block encode the current block almost as well as the optimal ones
+    /// (within about 3% of the estimated lower bound), they and their encoded header are used again,
+    /// which skips the construction of the codes and the header.
+    /// This speeds up the compression of long homogeneous data (e.g., logs) with a slight loss of compression ratio.
+    ///
+    /// This is ignored if fixed Huffman codes are specified or LZ77 compression is disabled.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::deflate::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().block_size(64 * 1024).reuse_huffman_codes();
+    /// let encoder = Encoder::with_options(Vec::new(), options);
+    /// ```
+    pub fn reuse_huffman_codes(mut self) -> Self {
+        self.reuse_huffman_codes = true;
+        self
+    }
+
+    /// Specifies the Huffman code lengths used for all blocks instead of the ones built for each block.
+    ///
+    /// Pre-trained tables speed up the compression of data whose symbol distribution is stable
+    /// (e.g., telemetry records), and may even make it smaller for short blocks.
+    /// If a block contains a symbol which has no code in `tables`,
+    /// or if `tables` make a block larger than the codes built for it,
+    /// the block is encoded with the codes built for it as usual
+    /// (so [`max_compressed_len`] holds for any tables).
+    ///
+    /// This is ignored if fixed Huffman codes are specified or LZ77 compression is disabled.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::deflate::{Decoder, Encoder, EncodeOptions, HuffmanTables};
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    ///
+    /// // Shorter codes for ASCII characters
+    /// let mut literal = vec![8; 128];
+    /// literal.extend_from_slice(&[9; 128]);
+    /// literal.extend_from_slice(&[6; 2]);
+    /// literal.extend_from_slice(&[7; 28]);
+    /// let mut distance = vec![4; 2];
+    /// distance.extend_from_slice(&[5; 28]);
+    /// let tables = HuffmanTables::new(&literal, &distance).unwrap();
+    ///
+    /// let options = EncodeOptions::new().huffman_tables(tables);
+    /// let mut encoder = Encoder::with_options(Vec::new(), options);
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoded_data = Vec::new();
+    /// Decoder::new(&encoded_data[..]).read_to_end(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data, b"Hello World!");
+    /// ```
+    pub fn huffman_tables(mut self, tables: HuffmanTables) -> Self {
+        self.huffman_tables = Some(tables);
+        self
+    }
+
+    /// Makes the encoder emit a sync flush (see [`Encoder::message_boundary`])
+    /// after every `bytes` bytes of uncompressed data.
+    ///
+    /// This bounds the amount of the data buffered inside the encoder,
+    /// which is useful for latency-sensitive streams.
+    /// A write is split at the flush points, so the flushes don't depend on how the data are written.
+    ///
+    /// By default, the encoder is flushed only when requested.
+    ///
+    /// # Example
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().sync_flush_interval(6);
+    /// let mut encoder = Encoder::with_options(Vec::new(), options);
+    /// encoder.write_all(b"Hello World!").unwrap();
+    ///
+    /// // Two sync flushes have been made
+    /// let written = encoder.as_inner_ref();
+    /// assert_eq!(written.windows(4).filter(|x| *x == [0, 0, 255, 255]).count(), 2);
+    /// ```
+    pub fn sync_flush_interval(mut self, bytes: u64) -> Self {
+        self.sync_flush_interval = Some(cmp::max(bytes, 1));
+        self
+    }
+
+    /// Makes the encoder emit a sync flush after each newline (`b'\n'`),
+    /// once at least `min_bytes` bytes of uncompressed data have been written since the last flush.
+    ///
+    /// This makes each line of a log stream decodable as soon as it is written,
+    /// without splitting the writes and flushing around every line.
+    /// `min_bytes` limits the rate of the flushes (each of which costs a few bytes of output and worse compression):
+    /// the lines shorter than that are flushed together with the following ones.
+    /// If it is `0`, every line is flushed.
+    ///
+    /// This can be combined with [`EncodeOptions::sync_flush_interval`], which additionally bounds the length of a line.
+    ///
+    /// By default, the encoder is flushed only when requested.
+    ///
+    /// # Example
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().line_flush(0);
+    /// let mut encoder = Encoder::with_options(Vec::new(), options);
+    /// encoder.write_all(b"foo\nbar\nbaz").unwrap();
+    ///
+    /// // Each of the complete lines has been flushed
+    /// let written = encoder.as_inner_ref();
+    /// assert_eq!(written.windows(4).filter(|x| *x == [0, 0, 255, 255]).count(), 2);
+    /// ```
+    pub fn line_flush(mut self, min_bytes: u64) -> Self {
+        self.line_flush = Some(min_bytes);
+        self
+    }
+
+    /// Makes the encoder produce an endless DEFLATE stream, which has no final block.
+    ///
+    /// Some streaming protocols keep a single DEFLATE stream open during a session,
+    /// and make a sync flush after each message (see [`Encoder::message_boundary`]) instead of ever ending the stream.
+    /// With this option, [`Encoder::finish`] (and [`Encoder::try_finish`]) terminates the stream
+    /// with a sync flush (like [`Encoder::message_boundary`]) instead of the final block,
+    /// so the stream can be continued later (e.g., by [`Encoder::from_state`]).
+    ///
+    /// Such a stream is decoded with [`DecodeOptions::endless`](super::DecodeOptions::endless).
+    /// As the stream is never completed, this option is only meaningful for raw DEFLATE streams
+    /// (the ZLIB and GZIP decoders can not read the trailer which follows an endless stream).
+    ///
+    /// # Example
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::{Encoder, EncodeOptions};
+    ///
+    /// let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().endless());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    /// assert!(encoded_data.ends_with(&[0, 0, 255, 255]));
+    /// ```
+    pub fn endless(mut self) -> Self {
+        self.endless = true;
+        self
+    }
+
+    /// Specifies the number of worker threads used to compress input data.
+    ///
+    /// If `threads` is greater than `1`, the input is compressed in parallel.
+    /// See the [`parallel`](crate::parallel) module for the details.
+    ///
+    /// The default value is `1`.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::deflate::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().threads(4);
+    /// let encoder = Encoder::with_options(Vec::new(), options);
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Specifies whether the ZLIB and GZIP encoders compute the checksum of the input data on a worker thread.
+    ///
+    /// The input data are copied into a ring of buffers which are passed to the worker,
+    /// and the checksum is joined when the encoding is finished.
+    /// This improves the throughput of a single stream on multi-core machines
+    /// at the cost of the copy and a thread.
+    ///
+    /// This is ignored by the DEFLATE encoder (which has no checksum),
+    /// and if the input data are compressed in parallel (see [`EncodeOptions::threads`]).
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::zlib::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().pipelined_checksum(true);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn pipelined_checksum(mut self, pipelined: bool) -> Self {
+        self.pipelined_checksum = pipelined;
+        self
+    }
+
+    pub(crate) fn get_buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn get_lz77(&self) -> Option<&E> {
+        self.lz77.as_ref()
+    }
+    pub(crate) fn get_pipelined_checksum(&self) -> bool {
+        // The parallel encoder computes the checksum on its own worker threads
+        #[cfg(not(feature = "no_std"))]
+        {
+            self.pipelined_checksum && self.threads <= 1
+        }
+        #[cfg(feature = "no_std")]
+        self.pipelined_checksum
+    }
     fn get_block_type(&self) -> BlockType {
         if self.lz77.is_none() {
             BlockType::Raw
@@ -135,8 +514,16 @@ where
 /// DEFLATE encoder.
 #[derive(Debug)]
 pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
-    writer: bit::BitWriter<W>,
+    writer: bit::BitWriter<Counter<W>>,
     block: Block<E>,
+    plain_size: u64,
+    progress: Option<ProgressHook>,
+    block_callback: Option<BlockCallback>,
+    cancellation: Option<CancellationCheck>,
+    finish_state: FinishState,
+    sync_flush: SyncFlushPolicy,
+    #[cfg(not(feature = "no_std"))]
+    parallel: Option<parallel::Encoder>,
 }
 impl<W> Encoder<W, lz77::DefaultLz77Encoder>
 where
@@ -164,6 +551,43 @@ where
     pub fn new(inner: W) -> Self {
         Self::with_options(inner, EncodeOptions::default())
     }
+
+    /// Makes a new encoder instance whose buffers (the LZ77 window and the encoded symbols of the current block)
+    /// are allocated by `allocator`.
+    ///
+    /// This is available only if the `allocator_api` feature (which requires a nightly compiler) is enabled.
+    /// Only these buffers are allocated by `allocator`.
+    /// All the other allocations of the encoder (e.g., the Huffman codes and the temporary tables of the LZ77 encoding),
+    /// including all the ones made on the worker threads if `EncodeOptions::threads` is set,
+    /// use the global allocator.
+    ///
+    /// # Examples
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use libflate::deflate::{Decoder, EncodeOptions, Encoder};
+    /// use std::io::{Read, Write};
+    ///
+    /// let mut encoder = Encoder::with_allocator(Vec::new(), EncodeOptions::new(), &std::alloc::System);
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut buf = Vec::new();
+    /// Decoder::new(&encoded_data[..]).read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    #[cfg(feature = "allocator_api")]
+    pub fn with_allocator(
+        inner: W,
+        mut options: EncodeOptions<lz77::DefaultLz77Encoder>,
+        allocator: lz77::DynAllocator,
+    ) -> Self {
+        if let Some(lz77) = options.lz77.as_mut() {
+            lz77.set_allocator(allocator);
+        }
+        let mut this = Self::with_options(inner, options);
+        this.block.set_allocator(BufferAllocator(allocator));
+        this
+    }
 }
 impl<W, E> Encoder<W, E>
 where
@@ -191,10 +615,90 @@ where
     ///             114, 108, 100, 33]);
     /// ```
     pub fn with_options(inner: W, options: EncodeOptions<E>) -> Self {
+        #[cfg(not(feature = "no_std"))]
+        {
+            Self::with_checksum(inner, options, parallel::ChecksumKind::None)
+        }
+        #[cfg(feature = "no_std")]
+        Encoder {
+            writer: bit::BitWriter::new(Counter::new(inner)),
+            sync_flush: SyncFlushPolicy::new(
+                options.sync_flush_interval,
+                options.line_flush,
+                options.endless,
+            ),
+            block: Block::new(options),
+            plain_size: 0,
+            progress: None,
+            block_callback: None,
+            cancellation: None,
+            finish_state: FinishState::default(),
+        }
+    }
+
+    /// Makes a new encoder instance which computes the `checksum` of the input data
+    /// on the worker threads if multi-threaded compression is enabled.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn with_checksum(
+        inner: W,
+        options: EncodeOptions<E>,
+        checksum: parallel::ChecksumKind,
+    ) -> Self {
+        let parallel = if options.threads > 1 {
+            let chunk_options = parallel::ChunkOptions {
+                block_size: options.block_size,
+                dynamic_huffman: options.dynamic_huffman,
+                adaptive_block_size: options.adaptive_block_size,
+                reuse_huffman_codes: options.reuse_huffman_codes,
+                huffman_tables: options.huffman_tables.clone(),
+                window_size: options.lz77.as_ref().map(|lz77| lz77.window_size()),
+            };
+            Some(parallel::Encoder::new(
+                options.threads,
+                chunk_options,
+                checksum,
+            ))
+        } else {
+            None
+        };
         Encoder {
-            writer: bit::BitWriter::new(inner),
+            writer: bit::BitWriter::new(Counter::new(inner)),
+            sync_flush: SyncFlushPolicy::new(
+                options.sync_flush_interval,
+                options.line_flush,
+                options.endless,
+            ),
             block: Block::new(options),
+            plain_size: 0,
+            progress: None,
+            block_callback: None,
+            cancellation: None,
+            finish_state: FinishState::default(),
+            parallel,
+        }
+    }
+
+    /// Returns `true` if the input data is compressed on the worker threads.
+    pub(crate) fn is_parallel(&self) -> bool {
+        #[cfg(not(feature = "no_std"))]
+        {
+            self.parallel.is_some()
+        }
+        #[cfg(feature = "no_std")]
+        false
+    }
+
+    /// Returns the checksum of the whole input computed on the worker threads.
+    ///
+    /// If multi-threaded compression is disabled (or the encoder has not been finished), this returns `None`.
+    pub(crate) fn parallel_checksum(&self) -> Option<u32> {
+        #[cfg(not(feature = "no_std"))]
+        if let Some(parallel) = self.parallel.as_ref() {
+            if self.finish_state.is_finished() {
+                return parallel.checksum();
+            }
         }
+        None
     }
 
     /// Flushes internal buffer and returns the inner stream.
@@ -215,50 +719,701 @@ where
     ///            147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
     /// ```
     pub fn finish(mut self) -> Finish<W, io::Error> {
-        match self.block.finish(&mut self.writer) {
-            Ok(_) => Finish::new(self.writer.into_inner(), None),
-            Err(e) => Finish::new(self.writer.into_inner(), Some(e)),
+        let result = self.try_finish();
+        Finish::new(self.into_inner(), result.err())
+    }
+
+    /// Writes the final block of the DEFLATE stream, and flushes the inner stream.
+    ///
+    /// Unlike [`Encoder::finish`], this does not consume the encoder,
+    /// which is convenient if the encoder is stored in a struct or used as a trait object.
+    /// This method is idempotent: the second and later calls return the result of the first call
+    /// without writing anything. Writing to the encoder after this call fails.
+    ///
+    /// The only exception is an `Interrupted` or `WouldBlock` error from the final flush of the inner stream
+    /// (e.g., a non-blocking socket), after which the next call retries the flush.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!".as_ref()).unwrap();
+    /// encoder.try_finish().unwrap();
+    /// encoder.try_finish().unwrap();
+    /// assert!(encoder.write_all(b"Hello World!").is_err());
+    ///
+    /// assert_eq!(encoder.into_inner(),
+    ///            [5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237,
+    ///            147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
+    /// ```
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        if let Some(result) = self.finish_state.previous_result() {
+            return result;
         }
+        self.write_end_of_stream()?;
+        let result = util::retry_interrupted(|| self.writer.as_inner_mut().flush());
+        self.finish_state.record_flush(&result);
+        result
     }
 
-    /// Returns the immutable reference to the inner stream.
-    pub fn as_inner_ref(&self) -> &W {
-        self.writer.as_inner_ref()
+    /// Writes the end of the stream (if it has not been written yet) without flushing the inner stream.
+    pub(crate) fn write_end_of_stream(&mut self) -> io::Result<()> {
+        if let Some(result) = self.finish_state.previous_result() {
+            return result;
+        }
+        if self.finish_state.is_finished() {
+            return Ok(());
+        }
+        let result = self.finish_stream();
+        self.finish_state.record_end(&result);
+        if let Some(progress) = self.progress.as_mut() {
+            progress.finish(self.plain_size, self.writer.as_inner_ref().count());
+        }
+        result
     }
 
-    /// Returns the mutable reference to the inner stream.
-    pub fn as_inner_mut(&mut self) -> &mut W {
-        self.writer.as_inner_mut()
+    fn finish_stream(&mut self) -> io::Result<()> {
+        if self.sync_flush.endless {
+            return self.message_boundary();
+        }
+        #[cfg(not(feature = "no_std"))]
+        if let Some(parallel) = self.parallel.as_mut() {
+            return parallel.finish(self.writer.as_inner_mut());
+        }
+        let result = self.block.finish(&mut self.writer);
+        self.report_blocks();
+        result
     }
 
-    /// Unwraps the `Encoder`, returning the inner stream.
-    pub fn into_inner(self) -> W {
-        self.writer.into_inner()
+    /// Returns the current state of this encoder.
+    ///
+    /// The state, together with the data written to the inner stream so far,
+    /// is everything needed to resume the encoding with [`Encoder::from_state`]
+    /// (e.g., in another process after a crash).
+    /// If the `serde` feature is enabled, the state can be serialized.
+    ///
+    /// Note that the data buffered by the inner stream itself (e.g., `BufWriter`) is not a part of the state,
+    /// so the inner stream should be flushed before persisting the state.
+    ///
+    /// # Errors
+    ///
+    /// If multi-threaded compression is enabled, this method returns an `ErrorKind::Unsupported` error.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello ").unwrap();
+    /// let state = encoder.state().unwrap();
+    /// let written = encoder.into_inner();
+    ///
+    /// // Resumes the encoding
+    /// let mut encoder = Encoder::from_state(written, state);
+    /// encoder.write_all(b"World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoded_data = Vec::new();
+    /// Decoder::new(&encoded_data[..]).read_to_end(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data, b"Hello World!");
+    /// ```
+    pub fn state(&self) -> io::Result<EncoderState<E>>
+    where
+        E: Clone,
+    {
+        if self.is_parallel() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "The state of a multi-threaded encoder can not be taken",
+            )
+            .into());
+        }
+        Ok(EncoderState {
+            pending_bits: self.writer.pending_bits(),
+            block: self.block.clone(),
+            sync_flush: self.sync_flush.clone(),
+        })
     }
 
-    pub(crate) fn zlib_sync_flush(&mut self) -> io::Result<()> {
-        self.block.flush(&mut self.writer, false)?;
+    /// Makes an encoder instance which resumes the encoding from `state`.
+    ///
+    /// `inner` should be (or be positioned at the end of) the stream written by the original encoder
+    /// at the time [`Encoder::state`] was called.
+    pub fn from_state(inner: W, state: EncoderState<E>) -> Self {
+        Encoder {
+            writer: bit::BitWriter::with_pending_bits(Counter::new(inner), state.pending_bits),
+            block: Block {
+                log: None,
+                ..state.block
+            },
+            plain_size: 0,
+            progress: None,
+            block_callback: None,
+            cancellation: None,
+            finish_state: FinishState::default(),
+            sync_flush: state.sync_flush,
+            #[cfg(not(feature = "no_std"))]
+            parallel: None,
+        }
+    }
 
-        self.writer.write_bit(false)?;
-        self.writer.write_bits(2, BlockType::Raw as u16)?;
-        self.writer.flush()?;
-        self.writer.as_inner_mut().write_all(&[0, 0, 255, 255])?;
+    /// Drops this (single-threaded) encoder without finishing the stream, and returns its state.
+    pub(super) fn into_state(self) -> EncoderState<E> {
+        self.into_parts().1
+    }
 
-        self.writer.as_inner_mut().flush()
+    /// Unwraps this (single-threaded) encoder without finishing the stream, returning the inner stream and the state.
+    pub(crate) fn into_parts(self) -> (W, EncoderState<E>) {
+        let state = EncoderState {
+            pending_bits: self.writer.pending_bits(),
+            block: self.block,
+            sync_flush: self.sync_flush,
+        };
+        (self.writer.into_inner().into_inner(), state)
     }
-}
-impl<W, E> io::Write for Encoder<W, E>
-where
-    W: io::Write,
-    E: lz77::Lz77Encode,
-{
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.block.write(&mut self.writer, buf)?;
-        Ok(buf.len())
+
+    /// Replaces the buffers of this new (single-threaded) encoder with the ones of `state`,
+    /// which is taken from an encoder made with the same options and then finished.
+    ///
+    /// The buffers are not reused if the state has buffered data or its parameters have been changed
+    /// (e.g., by [`Encoder::set_level`]), so the output is the same as the one of a new encoder.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn reuse_buffers(&mut self, state: EncoderState<E>) {
+        if !state.is_idle() {
+            return;
+        }
+        let mut block = state.block;
+        if block.block_type != self.block.block_type
+            || block.params.dynamic_huffman != self.block.params.dynamic_huffman
+        {
+            return;
+        }
+        block.block_buf.reset_history();
+        self.block = Block {
+            log: self.block.log.take(),
+            ..block
+        };
+    }
+
+    /// Returns the number of the (uncompressed) bytes written to this encoder.
+    pub fn total_in(&self) -> u64 {
+        self.plain_size
+    }
+
+    /// Returns the number of the (compressed) bytes written to the inner stream.
+    ///
+    /// The bits buffered by the encoder are not counted until they are flushed.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(&[0; 1024]).unwrap();
+    /// assert_eq!(encoder.total_in(), 1024);
+    ///
+    /// encoder.try_finish().unwrap();
+    /// assert_eq!(encoder.total_out(), encoder.as_inner_ref().len() as u64);
+    /// ```
+    pub fn total_out(&self) -> u64 {
+        self.writer.as_inner_ref().count()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this encoder (excluding the inner stream).
+    ///
+    /// The encoder buffers the input data until it reaches the block size (see [`EncodeOptions::block_size`]).
+    /// In the worst case, the buffer takes `6 * (block_size + size of the largest write)` bytes
+    /// (or `min(block_size, 65535)` bytes regardless of the size of writes if LZ77 compression is disabled),
+    /// in addition to the memory used by the LZ77 encoder (see [`Lz77Encode::memory_usage`](lz77::Lz77Encode::memory_usage)).
+    ///
+    /// If multi-threaded compression is enabled, the buffers of the chunks which are being compressed
+    /// are owned by the worker threads and not included in the returned value.
+    /// They take up to about `2 * threads * 7 * DEFAULT_CHUNK_SIZE` bytes in total
+    /// (see [`parallel`](crate::parallel)).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// let before = encoder.memory_usage();
+    /// encoder.write_all(&[0; 1024]).unwrap();
+    /// assert!(encoder.memory_usage() > before);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        #[cfg(not(feature = "no_std"))]
+        if let Some(parallel) = self.parallel.as_ref() {
+            return parallel.memory_usage();
+        }
+        self.block.block_buf.memory_usage()
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        self.writer.as_inner_ref().as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        self.writer.as_inner_mut().as_inner_mut()
+    }
+
+    /// Wraps this encoder with [`AutoFinish`], which finishes the encoding when it is dropped.
+    ///
+    /// This prevents a stream from being silently truncated because of a missing call of [`Encoder::finish`].
+    /// If finishing the encoding fails, the drop panics.
+    ///
+    /// To recover the inner stream (or to handle the finishing error),
+    /// call [`AutoFinish::into_inner`] and then [`Encoder::finish`] instead of dropping the wrapper.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut buf = Vec::new();
+    /// {
+    ///     let mut encoder = Encoder::new(&mut buf).auto_finish();
+    ///     encoder.write_all(b"Hello World!").unwrap();
+    /// } // The encoding is finished here
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).auto_finish();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.into_inner().finish().into_result().unwrap();
+    /// assert_eq!(encoded_data, buf);
+    /// ```
+    pub fn auto_finish(self) -> AutoFinish<Self> {
+        AutoFinish::new(self)
+    }
+
+    /// Unwraps the `Encoder`, returning the inner stream.
+    ///
+    /// Nothing is written to the inner stream by this method (see [`Encoder::abort`]).
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().into_inner()
+    }
+
+    /// Aborts the encoding, returning the inner stream.
+    ///
+    /// The buffered input data are discarded, and the final block are not written,
+    /// so the data written to the inner stream so far do not form a complete stream.
+    /// This is useful to reclaim the inner stream (e.g., a socket) when the encoding is cancelled midway.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let written = encoder.abort();
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// assert!(encoder.finish().into_result().unwrap().len() > written.len());
+    /// ```
+    pub fn abort(self) -> W {
+        self.into_inner()
+    }
+
+    /// Registers a callback which is invoked every time `interval` bytes of input data have been written.
+    ///
+    /// The arguments of the callback are the number of bytes written to this encoder so far
+    /// and the number of bytes of the DEFLATE stream written to the inner stream so far.
+    /// Note that the latter lags behind the former because the input data is buffered until a block is completed.
+    /// The callback is also invoked once when the encoding is finished.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let progress = Arc::new(Mutex::new(Vec::new()));
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// {
+    ///     let progress = progress.clone();
+    ///     encoder.set_progress_callback(1000, move |bytes_in, _bytes_out| {
+    ///         progress.lock().unwrap().push(bytes_in);
+    ///     });
+    /// }
+    /// for _ in 0..5 {
+    ///     encoder.write_all(&[0; 600]).unwrap();
+    /// }
+    /// encoder.finish().into_result().unwrap();
+    ///
+    /// assert_eq!(*progress.lock().unwrap(), [1200, 2400, 3000]);
+    /// ```
+    pub fn set_progress_callback<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        let mut progress = ProgressHook::new(interval, callback);
+        progress.notify(self.plain_size, self.writer.as_inner_ref().count());
+        self.progress = Some(progress);
+    }
+
+    /// Registers a callback which is invoked with the information of each block after it is written.
+    ///
+    /// This is useful to see how the compression performs on each part of the data
+    /// (e.g., to collect metrics about heterogeneous payloads).
+    /// The empty non-compressed blocks written by sync flushes (e.g., [`Encoder::message_boundary`]) are also reported.
+    ///
+    /// The callback is not invoked if the data is compressed on the worker threads (see [`EncodeOptions::threads`]).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::{BlockKind, EncodeOptions, Encoder};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let blocks = Arc::new(Mutex::new(Vec::new()));
+    /// let options = EncodeOptions::new().block_size(1000);
+    /// let mut encoder = Encoder::with_options(Vec::new(), options);
+    /// {
+    ///     let blocks = blocks.clone();
+    ///     encoder.set_block_callback(move |info| blocks.lock().unwrap().push(info.clone()));
+    /// }
+    /// for _ in 0..5 {
+    ///     encoder.write_all(&[b'a'; 500]).unwrap();
+    /// }
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let blocks = blocks.lock().unwrap();
+    /// assert_eq!(blocks.iter().map(|b| b.input_size).collect::<Vec<_>>(), [1000, 1000, 500]);
+    /// assert!(blocks.iter().all(|b| b.kind == BlockKind::Dynamic));
+    /// assert!(blocks[2].is_final);
+    /// let total_bits = blocks.iter().map(|b| b.output_bits).sum::<u64>();
+    /// assert_eq!((total_bits + 7) / 8, encoded_data.len() as u64);
+    /// ```
+    pub fn set_block_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&BlockInfo) + Send + Sync + 'static,
+    {
+        self.block.log = Some(Vec::new());
+        self.block_callback = Some(BlockCallback(Box::new(callback)));
+    }
+
+    /// Registers a check which is invoked to tell whether the encoding should be cancelled.
+    ///
+    /// The check is invoked only at the beginning of each write (including each call of `write_vectored`),
+    /// so the data passed to a single write are always compressed as a whole.
+    /// To cancel a large input promptly, write it in smaller pieces (e.g., by `write_all` for each chunk of it).
+    /// Once it returns `true`, writing to this encoder fails with an error
+    /// whose kind is [`ErrorKind::Cancelled`] (`io::ErrorKind::Other`).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let cancelled = Arc::new(AtomicBool::new(false));
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// {
+    ///     let cancelled = cancelled.clone();
+    ///     encoder.set_cancellation_check(move || cancelled.load(Ordering::Relaxed));
+    /// }
+    /// encoder.write_all(b"Hello ").unwrap();
+    ///
+    /// cancelled.store(true, Ordering::Relaxed);
+    /// assert!(encoder.write_all(b"World!").is_err());
+    /// ```
+    pub fn set_cancellation_check<F>(&mut self, is_cancelled: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.cancellation = Some(CancellationCheck::new(is_cancelled));
+    }
+
+    /// Changes the compression level of the subsequent data.
+    ///
+    /// Like `deflateParams` of zlib, the current block is ended (without byte alignment) if the level changes,
+    /// so the change takes effect immediately without breaking the stream.
+    /// Since this crate doesn't have speed/ratio tradeoff settings,
+    /// only switching to or from [`lz77::CompressionLevel::None`] (no compression) changes the output.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the encoder is finished or compresses in parallel (see [`EncodeOptions::threads`]),
+    /// or if compression is requested but the encoder has been made with [`EncodeOptions::no_compression`]
+    /// (i.e., it has no LZ77 encoder).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder};
+    /// use libflate::lz77::CompressionLevel;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.set_level(CompressionLevel::None).unwrap();
+    /// encoder.write_all(b" Hello World!").unwrap();
+    /// encoder.set_level(CompressionLevel::Fast).unwrap();
+    /// encoder.write_all(b" Hello World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoded_data = Vec::new();
+    /// Decoder::new(&encoded_data[..]).read_to_end(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data, b"Hello World! Hello World! Hello World!");
+    /// ```
+    pub fn set_level(&mut self, level: lz77::CompressionLevel) -> io::Result<()> {
+        self.check_params_changeable()?;
+        let compress = level != lz77::CompressionLevel::None;
+        let result = self
+            .block
+            .set_params(&mut self.writer, Some(compress), None);
+        self.report_blocks();
+        result
+    }
+
+    /// Changes the strategy to encode the subsequent blocks.
+    ///
+    /// Like [`Encoder::set_level`], the current block is ended if the strategy changes.
+    /// If the compression is disabled, the strategy is applied when it is enabled again.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the encoder is finished or compresses in parallel (see [`EncodeOptions::threads`]).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::deflate::Encoder;
+    /// use libflate::options::Strategy;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.set_strategy(Strategy::FixedHuffmanCodes).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// assert_eq!(encoder.finish().into_result().unwrap(),
+    ///            [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0]);
+    /// ```
+    pub fn set_strategy(&mut self, strategy: Strategy) -> io::Result<()> {
+        self.check_params_changeable()?;
+        let dynamic = strategy != Strategy::FixedHuffmanCodes;
+        let result = self.block.set_params(&mut self.writer, None, Some(dynamic));
+        self.report_blocks();
+        result
+    }
+
+    fn check_params_changeable(&self) -> io::Result<()> {
+        if self.finish_state.is_finished() {
+            return Err(util::finished_error());
+        }
+        #[cfg(not(feature = "no_std"))]
+        if self.parallel.is_some() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "The compression parameters of a parallel encoder cannot be changed",
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn notify_progress(&mut self) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress.notify(self.plain_size, self.writer.as_inner_ref().count());
+        }
+    }
+
+    /// Passes the information of the blocks written since the last call to the block callback.
+    fn report_blocks(&mut self) {
+        if let (Some(callback), Some(log)) = (self.block_callback.as_mut(), self.block.log.as_mut())
+        {
+            for info in log.drain(..) {
+                (callback.0)(&info);
+            }
+        }
+    }
+
+    /// Ends the current message, i.e., makes all the data written so far decodable (by a sync flush).
+    ///
+    /// Like the `Z_SYNC_FLUSH` of zlib, the current block is ended and then followed by an empty non-compressed block,
+    /// so that the output is byte-aligned.
+    /// If no data has been written since the last flush, this does nothing.
+    ///
+    /// See also [`EncodeOptions::sync_flush_interval`], which makes sync flushes automatically.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    /// use libflate::deflate::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.message_boundary().unwrap();
+    ///
+    /// // The message can be decoded before the stream is finished
+    /// let mut decoder = Decoder::new(&encoder.as_inner_ref()[..]);
+    /// let mut buf = [0; 12];
+    /// decoder.read_exact(&mut buf).unwrap();
+    /// assert_eq!(&buf, b"Hello World!");
+    /// ```
+    pub fn message_boundary(&mut self) -> io::Result<()> {
+        if self.sync_flush.unflushed_size == 0 {
+            return Ok(());
+        }
+        self.zlib_sync_flush()
+    }
+
+    /// Appends `buf` to the stream, making sync flushes at the flush points of [`EncodeOptions::sync_flush_interval`]
+    /// and [`EncodeOptions::line_flush`].
+    fn append_and_flush(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let size = self.sync_flush.writable_size(buf);
+            self.append(&buf[..size])?;
+            self.sync_flush.unflushed_size += size as u64;
+            let is_line_end = buf[size - 1] == b'\n';
+            buf = &buf[size..];
+            if self.sync_flush.is_flush_point(is_line_end) {
+                self.zlib_sync_flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, buf: &[u8]) -> io::Result<()> {
+        #[cfg(not(feature = "no_std"))]
+        if let Some(parallel) = self.parallel.as_mut() {
+            return parallel.write(self.writer.as_inner_mut(), buf);
+        }
+        let result = self.block.write(&mut self.writer, buf);
+        self.report_blocks();
+        result
+    }
+
+    pub(crate) fn zlib_sync_flush(&mut self) -> io::Result<()> {
+        if self.finish_state.is_finished() {
+            return util::retry_interrupted(|| self.writer.as_inner_mut().flush());
+        }
+        self.sync_flush.unflushed_size = 0;
+        #[cfg(not(feature = "no_std"))]
+        if let Some(parallel) = self.parallel.as_mut() {
+            return parallel.flush(self.writer.as_inner_mut());
+        }
+        let result = self.block.flush(&mut self.writer, false);
+        self.report_blocks();
+        result?;
+
+        let start = bit_position(&self.writer);
+        self.writer.write_bit(false)?;
+        self.writer.write_bits(2, BlockType::Raw as u16)?;
+        self.writer.flush()?;
+        self.writer.as_inner_mut().write_all(&[0, 0, 255, 255])?;
+        record_block(
+            &mut self.block.log,
+            &self.writer,
+            start,
+            BlockType::Raw,
+            None,
+            0,
+            false,
+        );
+        self.report_blocks();
+
+        util::retry_interrupted(|| self.writer.as_inner_mut().flush())
+    }
+}
+impl<W, E> io::Write for Encoder<W, E>
+where
+    W: io::Write,
+    E: lz77::Lz77Encode,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finish_state.is_finished() {
+            return Err(util::finished_error());
+        }
+        if let Some(cancellation) = self.cancellation.as_ref() {
+            cancellation.check()?;
+        }
+        self.append_and_flush(buf)?;
+        self.plain_size += buf.len() as u64;
+        self.notify_progress();
+        Ok(buf.len())
+    }
+
+    /// Writes all the data of `bufs`.
+    ///
+    /// The slices are appended to the current block as if they were a single contiguous buffer,
+    /// so the LZ77 encoder can find matches across their boundaries.
+    #[cfg(not(feature = "no_std"))]
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        if self.finish_state.is_finished() {
+            return Err(util::finished_error());
+        }
+        if let Some(cancellation) = self.cancellation.as_ref() {
+            cancellation.check()?;
+        }
+        let mut size = 0;
+        for buf in bufs {
+            if let Err(e) = self.append_and_flush(buf) {
+                // The preceding slices have been consumed, so the error is reported by the next call
+                if size == 0 {
+                    return Err(e);
+                }
+                break;
+            }
+            size += buf.len();
+        }
+        self.plain_size += size as u64;
+        self.notify_progress();
+        Ok(size)
     }
     fn flush(&mut self) -> io::Result<()> {
-        self.block.flush(&mut self.writer, false)?;
-        self.writer.as_inner_mut().flush()
+        if self.finish_state.is_finished() {
+            return util::retry_interrupted(|| self.writer.as_inner_mut().flush());
+        }
+        #[cfg(not(feature = "no_std"))]
+        if let Some(parallel) = self.parallel.as_mut() {
+            return parallel.flush(self.writer.as_inner_mut());
+        }
+        let result = self.block.flush(&mut self.writer, false);
+        self.report_blocks();
+        result?;
+        util::retry_interrupted(|| self.writer.as_inner_mut().flush())
     }
 }
 impl<W, E> Complete for Encoder<W, E>
@@ -271,53 +1426,368 @@ where
     }
 }
 
-#[derive(Debug)]
+/// The state of an [`Encoder`].
+///
+/// See [`Encoder::state`] for the details.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedEncoderState<E>"))]
+pub struct EncoderState<E = lz77::DefaultLz77Encoder> {
+    pending_bits: (u32, u8),
+    block: Block<E>,
+    sync_flush: SyncFlushPolicy,
+}
+
+impl<E> EncoderState<E>
+where
+    E: lz77::Lz77Encode,
+{
+    /// Returns `true` if no uncompressed data are buffered in the state.
+    ///
+    /// The block buffer of such a state can be reused by any encoder made with the same options
+    /// (see [`IdleState`]).
+    pub(crate) fn is_idle(&self) -> bool {
+        self.block.block_buf.len() == 0
+    }
+
+    /// Returns the part of this state which is specific to the stream.
+    pub(super) fn idle_state(&self) -> IdleState {
+        IdleState {
+            pending_bits: self.pending_bits,
+            sync_flush: self.sync_flush.clone(),
+        }
+    }
+
+    /// Replaces the part of this (idle) state which is specific to the stream with `idle`.
+    pub(super) fn restore_idle_state(&mut self, idle: &IdleState) {
+        self.pending_bits = idle.pending_bits;
+        self.sync_flush = idle.sync_flush.clone();
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.block.block_buf.memory_usage()
+    }
+}
+
+/// An [`EncoderState`] just deserialized, which is validated before use
+/// (an inconsistent state could make the encoder panic or write a corrupted stream).
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedEncoderState<E> {
+    pending_bits: (u32, u8),
+    block: Block<E>,
+    sync_flush: SyncFlushPolicy,
+}
+#[cfg(feature = "serde")]
+impl<E> TryFrom<UncheckedEncoderState<E>> for EncoderState<E> {
+    type Error = &'static str;
+    fn try_from(state: UncheckedEncoderState<E>) -> Result<Self, Self::Error> {
+        let (bits, end) = state.pending_bits;
+        if end >= 16 || bits >> end != 0 {
+            return Err("The pending bits of the encoder state are invalid");
+        }
+        state.sync_flush.validate()?;
+        state.block.validate()?;
+        Ok(EncoderState {
+            pending_bits: state.pending_bits,
+            block: state.block,
+            sync_flush: state.sync_flush,
+        })
+    }
+}
+
+/// The part of an [`EncoderState`] which is specific to the stream if no uncompressed data are buffered.
+#[derive(Debug, Clone)]
+pub(super) struct IdleState {
+    pending_bits: (u32, u8),
+    sync_flush: SyncFlushPolicy,
+}
+
+/// The state of [`EncodeOptions::sync_flush_interval`] and [`EncodeOptions::line_flush`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct SyncFlushPolicy {
+    interval: Option<u64>,
+
+    // The minimum size of the data flushed at a newline.
+    #[cfg_attr(feature = "serde", serde(default))]
+    line_flush: Option<u64>,
+
+    // The number of the bytes written since the last sync flush.
+    unflushed_size: u64,
+
+    // See `EncodeOptions::endless`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    endless: bool,
+}
+impl SyncFlushPolicy {
+    #[cfg(feature = "serde")]
+    fn validate(&self) -> Result<(), &'static str> {
+        if self
+            .interval
+            .is_some_and(|interval| self.unflushed_size >= interval)
+        {
+            return Err("The unflushed size of the encoder state exceeds the sync flush interval");
+        }
+        Ok(())
+    }
+
+    fn new(interval: Option<u64>, line_flush: Option<u64>, endless: bool) -> Self {
+        SyncFlushPolicy {
+            interval,
+            line_flush,
+            unflushed_size: 0,
+            endless,
+        }
+    }
+
+    /// Returns the number of the bytes of `buf` which can be written before the next flush point.
+    fn writable_size(&self, buf: &[u8]) -> usize {
+        let size = match self.interval {
+            None => buf.len(),
+            Some(interval) => cmp::min(buf.len() as u64, interval - self.unflushed_size) as usize,
+        };
+        if let Some(min_bytes) = self.line_flush {
+            // The newlines before `skip` end lines shorter than `min_bytes`
+            let skip = min_bytes.saturating_sub(self.unflushed_size + 1);
+            let skip = cmp::min(skip, size as u64) as usize;
+            if let Some(i) = buf[skip..size].iter().position(|&b| b == b'\n') {
+                return skip + i + 1;
+            }
+        }
+        size
+    }
+    /// Returns `true` if the data written so far (whose last byte is a newline if `is_line_end`) should be flushed.
+    fn is_flush_point(&self, is_line_end: bool) -> bool {
+        let is_line_flush_point = self
+            .line_flush
+            .is_some_and(|min_bytes| is_line_end && self.unflushed_size >= min_bytes);
+        self.interval == Some(self.unflushed_size) || is_line_flush_point
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Block<E> {
     block_type: BlockType,
     block_size: usize,
     block_buf: BlockBuf<E>,
+    params: Box<BlockParams<E>>,
+
+    // The blocks written but not reported yet (only if `Encoder::set_block_callback` has been called).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    log: Option<Vec<BlockInfo>>,
+}
+
+/// The parameters to remake the block buffer when they are changed by [`Encoder::set_level`] or [`Encoder::set_strategy`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct BlockParams<E> {
+    block_size: usize,
+    dynamic_huffman: bool,
+    dynamic: DynamicOptions,
+
+    // The LZ77 encoder kept while the compression is disabled.
+    lz77: Option<E>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    allocator: BufferAllocator,
+}
+
+/// The options which only apply to the blocks with dynamic Huffman codes.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct DynamicOptions {
+    adaptive_block_size: bool,
+    reuse_huffman_codes: bool,
+    huffman_tables: Option<HuffmanTables>,
+}
+impl<E> Block<E> {
+    /// Makes the (empty) block buffers allocated by `allocator` from now on.
+    #[cfg(feature = "allocator_api")]
+    fn set_allocator(&mut self, allocator: BufferAllocator) {
+        self.params.allocator = allocator;
+        match self.block_buf {
+            BlockBuf::Raw(ref mut b) => b.buf = allocator.new_buffer(),
+            BlockBuf::Fixed(ref mut b) => b.buf = allocator.new_buffer(),
+            BlockBuf::Dynamic(ref mut b) => b.buf = allocator.new_buffer(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.block_size == 0 {
+            return Err("The block size of the encoder state is zero");
+        }
+        match (&self.block_buf, self.block_type) {
+            (BlockBuf::Raw(b), BlockType::Raw) => {
+                if self.block_size > MAX_NON_COMPRESSED_BLOCK_SIZE || b.len() > self.block_size {
+                    return Err("The non-compressed block of the encoder state is too large");
+                }
+                Ok(())
+            }
+            (BlockBuf::Fixed(b), BlockType::Fixed) => b.validate(),
+            (BlockBuf::Dynamic(b), BlockType::Dynamic) => b.validate(),
+            _ => Err("The block type of the encoder state doesn't match its buffer"),
+        }
+    }
 }
 impl<E> Block<E>
 where
     E: lz77::Lz77Encode,
 {
     fn new(options: EncodeOptions<E>) -> Self {
+        let allocator = BufferAllocator::default();
+        let params = BlockParams {
+            block_size: options.block_size,
+            dynamic_huffman: options.dynamic_huffman,
+            dynamic: DynamicOptions {
+                adaptive_block_size: options.adaptive_block_size,
+                reuse_huffman_codes: options.reuse_huffman_codes,
+                huffman_tables: options.huffman_tables.clone(),
+            },
+            lz77: None,
+            allocator,
+        };
         Block {
             block_type: options.get_block_type(),
-            block_size: options.get_block_size(),
-            block_buf: BlockBuf::new(options.lz77, options.dynamic_huffman),
+            block_size: cmp::max(options.get_block_size(), 1),
+            block_buf: BlockBuf::new(
+                options.lz77,
+                options.dynamic_huffman,
+                &params.dynamic,
+                allocator,
+            ),
+            params: Box::new(params),
+            log: None,
         }
     }
-    fn write<W>(&mut self, writer: &mut bit::BitWriter<W>, buf: &[u8]) -> io::Result<()>
+
+    /// Switches the block type, after ending the current block if needed.
+    ///
+    /// `compress` and `dynamic_huffman` are left unchanged if `None`.
+    fn set_params<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<Counter<W>>,
+        compress: Option<bool>,
+        dynamic_huffman: Option<bool>,
+    ) -> io::Result<()>
     where
         W: io::Write,
     {
-        self.block_buf.append(buf);
-        while self.block_buf.len() >= self.block_size {
+        let is_raw = self.block_type == BlockType::Raw;
+        let compress = compress.unwrap_or(!is_raw);
+        if compress && is_raw && self.params.lz77.is_none() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "The encoder made by `no_compression()` has no LZ77 encoder to compress data",
+            )
+            .into());
+        }
+        if let Some(dynamic_huffman) = dynamic_huffman {
+            self.params.dynamic_huffman = dynamic_huffman;
+        }
+        let block_type = match (compress, self.params.dynamic_huffman) {
+            (false, _) => BlockType::Raw,
+            (true, false) => BlockType::Fixed,
+            (true, true) => BlockType::Dynamic,
+        };
+        if block_type == self.block_type {
+            return Ok(());
+        }
+
+        if self.block_buf.len() > 0 {
             self.flush(writer, false)?;
         }
+        let allocator = self.params.allocator;
+        let lz77 = match mem::replace(&mut self.block_buf, BlockBuf::Raw(RawBuf::new(allocator))) {
+            BlockBuf::Raw(_) => self.params.lz77.take(),
+            BlockBuf::Fixed(b) => Some(b.lz77),
+            BlockBuf::Dynamic(b) => Some(b.lz77),
+        };
+        if compress {
+            let dynamic_huffman = self.params.dynamic_huffman;
+            self.block_buf = BlockBuf::new(lz77, dynamic_huffman, &self.params.dynamic, allocator);
+            self.block_size = cmp::max(self.params.block_size, 1);
+        } else {
+            self.params.lz77 = lz77;
+            self.block_size = self
+                .params
+                .block_size
+                .clamp(1, MAX_NON_COMPRESSED_BLOCK_SIZE);
+        }
+        self.block_type = block_type;
         Ok(())
     }
-    fn flush<W>(&mut self, writer: &mut bit::BitWriter<W>, is_final: bool) -> io::Result<()>
+    fn write<W>(&mut self, writer: &mut bit::BitWriter<Counter<W>>, buf: &[u8]) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if let BlockBuf::Raw(ref mut b) = self.block_buf {
+            return b.write(writer, buf, self.block_size, &mut self.log);
+        }
+
+        self.block_buf.append(buf);
+        loop {
+            if let Some(block_end) = self.block_buf.find_block_end() {
+                let start = bit_position(writer);
+                let len = self.block_buf.len();
+                writer.write_bit(false)?;
+                let (block_type, codes) =
+                    self.block_buf
+                        .flush_until(writer, block_end, self.block_type)?;
+                let input_size = len - self.block_buf.len();
+                record_block(
+                    &mut self.log,
+                    writer,
+                    start,
+                    block_type,
+                    codes,
+                    input_size,
+                    false,
+                );
+            } else if self.block_buf.len() >= self.block_size {
+                self.flush(writer, false)?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+    fn flush<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<Counter<W>>,
+        is_final: bool,
+    ) -> io::Result<()>
     where
         W: io::Write,
     {
+        let start = bit_position(writer);
+        let len = self.block_buf.len();
         writer.write_bit(is_final)?;
-        writer.write_bits(2, self.block_type as u16)?;
-        self.block_buf.flush(writer)?;
+        let (block_type, codes) = self.block_buf.flush(writer, self.block_type)?;
+        let input_size = len - self.block_buf.len();
+        record_block(
+            &mut self.log,
+            writer,
+            start,
+            block_type,
+            codes,
+            input_size,
+            is_final,
+        );
         Ok(())
     }
-    fn finish<W>(mut self, writer: &mut bit::BitWriter<W>) -> io::Result<()>
+    fn finish<W>(&mut self, writer: &mut bit::BitWriter<Counter<W>>) -> io::Result<()>
     where
         W: io::Write,
     {
         self.flush(writer, true)?;
-        writer.flush()?;
-        Ok(())
+        writer.write_pending_bits()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum BlockBuf<E> {
     Raw(RawBuf),
     Fixed(CompressBuf<symbol::FixedHuffmanCodec, E>),
@@ -327,15 +1797,44 @@ impl<E> BlockBuf<E>
 where
     E: lz77::Lz77Encode,
 {
-    fn new(lz77: Option<E>, dynamic: bool) -> Self {
+    fn new(
+        lz77: Option<E>,
+        dynamic: bool,
+        options: &DynamicOptions,
+        allocator: BufferAllocator,
+    ) -> Self {
         if let Some(lz77) = lz77 {
             if dynamic {
-                BlockBuf::Dynamic(CompressBuf::new(symbol::DynamicHuffmanCodec, lz77))
+                let mut buf = CompressBuf::new(symbol::DynamicHuffmanCodec, lz77, allocator);
+                if options.adaptive_block_size {
+                    buf.splitter = Some(Box::new(BlockSplitter::new()));
+                }
+                if options.reuse_huffman_codes || options.huffman_tables.is_some() {
+                    buf.codes_cache = Some(Box::new(CodesCache {
+                        reuse_previous: options.reuse_huffman_codes,
+                        tables: options.huffman_tables.clone(),
+                        custom: None,
+                        previous: None,
+                    }));
+                }
+                BlockBuf::Dynamic(buf)
             } else {
-                BlockBuf::Fixed(CompressBuf::new(symbol::FixedHuffmanCodec, lz77))
+                BlockBuf::Fixed(CompressBuf::new(symbol::FixedHuffmanCodec, lz77, allocator))
             }
         } else {
-            BlockBuf::Raw(RawBuf::new())
+            BlockBuf::Raw(RawBuf::new(allocator))
+        }
+    }
+    /// Forgets the state carried over from the previous blocks, so that this (empty) buffer can be reused by another stream.
+    #[cfg(not(feature = "no_std"))]
+    fn reset_history(&mut self) {
+        if let BlockBuf::Dynamic(ref mut b) = *self {
+            if let Some(splitter) = b.splitter.as_mut() {
+                splitter.reset();
+            }
+            if let Some(cache) = b.codes_cache.as_mut() {
+                cache.previous = None;
+            }
         }
     }
     fn append(&mut self, buf: &[u8]) {
@@ -345,6 +1844,13 @@ where
             BlockBuf::Dynamic(ref mut b) => b.append(buf),
         }
     }
+    fn memory_usage(&self) -> usize {
+        match *self {
+            BlockBuf::Raw(ref b) => b.buf.capacity(),
+            BlockBuf::Fixed(ref b) => b.memory_usage(),
+            BlockBuf::Dynamic(ref b) => b.memory_usage(),
+        }
+    }
     fn len(&self) -> usize {
         match *self {
             BlockBuf::Raw(ref b) => b.len(),
@@ -352,25 +1858,129 @@ where
             BlockBuf::Dynamic(ref b) => b.len(),
         }
     }
-    fn flush<W>(&mut self, writer: &mut bit::BitWriter<W>) -> io::Result<()>
+    fn find_block_end(&mut self) -> Option<usize> {
+        match *self {
+            BlockBuf::Dynamic(ref mut b) => b.find_block_end(),
+            _ => None,
+        }
+    }
+    /// Writes the block type and the block made from the buffered data.
+    ///
+    /// Returns the block type actually used and the source of the Huffman codes.
+    fn flush<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<W>,
+        block_type: BlockType,
+    ) -> io::Result<(BlockType, Option<HuffmanCodesSource>)>
+    where
+        W: io::Write,
+    {
+        match *self {
+            BlockBuf::Raw(ref mut b) => {
+                writer.write_bits(2, block_type as u16)?;
+                b.flush(writer)?;
+                Ok((block_type, None))
+            }
+            BlockBuf::Fixed(ref mut b) => b.flush(writer, block_type),
+            BlockBuf::Dynamic(ref mut b) => b.flush(writer, block_type),
+        }
+    }
+    fn flush_until<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<W>,
+        block_end: usize,
+        block_type: BlockType,
+    ) -> io::Result<(BlockType, Option<HuffmanCodesSource>)>
+    where
+        W: io::Write,
+    {
+        match *self {
+            BlockBuf::Dynamic(ref mut b) => b.write_block(writer, block_end, block_type),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+type Buffer<T> = Vec<T, lz77::DynAllocator>;
+#[cfg(not(feature = "allocator_api"))]
+type Buffer<T> = Vec<T>;
+
+/// The allocator of the block buffers (see [`Encoder::with_allocator`]).
+#[derive(Clone, Copy)]
+struct BufferAllocator(#[cfg(feature = "allocator_api")] lz77::DynAllocator);
+impl BufferAllocator {
+    fn new_buffer<T>(self) -> Buffer<T> {
+        #[cfg(feature = "allocator_api")]
+        {
+            Vec::new_in(self.0)
+        }
+        #[cfg(not(feature = "allocator_api"))]
+        {
+            Vec::new()
+        }
+    }
+}
+impl Default for BufferAllocator {
+    #[cfg(all(feature = "allocator_api", feature = "no_std"))]
+    fn default() -> Self {
+        BufferAllocator(&alloc::alloc::Global)
+    }
+    #[cfg(all(feature = "allocator_api", not(feature = "no_std")))]
+    fn default() -> Self {
+        BufferAllocator(&std::alloc::Global)
+    }
+    #[cfg(not(feature = "allocator_api"))]
+    fn default() -> Self {
+        BufferAllocator()
+    }
+}
+impl core::fmt::Debug for BufferAllocator {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_tuple("BufferAllocator").finish_non_exhaustive()
+    }
+}
+
+/// (De)serializes a [`Buffer`] as a sequence, deserializing it into a buffer allocated by the global allocator.
+#[cfg(all(feature = "serde", feature = "allocator_api"))]
+mod buffer_serde {
+    use super::{Buffer, BufferAllocator};
+    #[cfg(feature = "no_std")]
+    use alloc::vec::Vec;
+
+    pub fn serialize<T, S>(buf: &Buffer<T>, serializer: S) -> Result<S::Ok, S::Error>
     where
-        W: io::Write,
+        T: serde::Serialize,
+        S: serde::Serializer,
     {
-        match *self {
-            BlockBuf::Raw(ref mut b) => b.flush(writer),
-            BlockBuf::Fixed(ref mut b) => b.flush(writer),
-            BlockBuf::Dynamic(ref mut b) => b.flush(writer),
-        }
+        serde::Serialize::serialize(&buf[..], serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Buffer<T>, D::Error>
+    where
+        T: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {
+        let mut buf = BufferAllocator::default().new_buffer();
+        buf.extend(<Vec<T> as serde::Deserialize>::deserialize(deserializer)?);
+        Ok(buf)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct RawBuf {
-    buf: Vec<u8>,
+    #[cfg_attr(
+        all(feature = "serde", feature = "allocator_api"),
+        serde(with = "buffer_serde")
+    )]
+    buf: Buffer<u8>,
 }
 impl RawBuf {
-    fn new() -> Self {
-        RawBuf { buf: Vec::new() }
+    fn new(allocator: BufferAllocator) -> Self {
+        RawBuf {
+            buf: allocator.new_buffer(),
+        }
     }
     fn append(&mut self, buf: &[u8]) {
         self.buf.extend_from_slice(buf);
@@ -378,42 +1988,168 @@ impl RawBuf {
     fn len(&self) -> usize {
         self.buf.len()
     }
+
+    /// Writes `buf` as non-final blocks of `block_size` bytes, buffering only the remainder.
+    ///
+    /// The full blocks in `buf` are copied to the output as is, so that the memory usage doesn't depend on the size of writes.
+    fn write<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<Counter<W>>,
+        mut buf: &[u8],
+        block_size: usize,
+        log: &mut Option<Vec<BlockInfo>>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        while !buf.is_empty() {
+            let size = cmp::min(block_size - self.buf.len(), buf.len());
+            let (data, rest) = buf.split_at(size);
+            let start = bit_position(writer);
+            if self.buf.is_empty() && size == block_size {
+                writer.write_bit(false)?;
+                writer.write_bits(2, BlockType::Raw as u16)?;
+                write_non_compressed_block(writer, data)?;
+                record_block(log, writer, start, BlockType::Raw, None, size, false);
+            } else {
+                self.buf.extend_from_slice(data);
+                if self.buf.len() == block_size {
+                    writer.write_bit(false)?;
+                    writer.write_bits(2, BlockType::Raw as u16)?;
+                    self.flush(writer)?;
+                    record_block(log, writer, start, BlockType::Raw, None, block_size, false);
+                }
+            }
+            buf = rest;
+        }
+        Ok(())
+    }
     fn flush<W>(&mut self, writer: &mut bit::BitWriter<W>) -> io::Result<()>
     where
         W: io::Write,
     {
         let size = cmp::min(self.buf.len(), MAX_NON_COMPRESSED_BLOCK_SIZE);
-        writer.flush()?;
-        writer
-            .as_inner_mut()
-            .write_all(&(size as u16).to_le_bytes())?;
-        writer
-            .as_inner_mut()
-            .write_all(&(!size as u16).to_le_bytes())?;
-        writer.as_inner_mut().write_all(&self.buf[..size])?;
+        write_non_compressed_block(writer, &self.buf[..size])?;
         self.buf.drain(0..size);
         Ok(())
     }
 }
 
-#[derive(Debug)]
+/// The callback of [`Encoder::set_block_callback`].
+struct BlockCallback(Box<dyn FnMut(&BlockInfo) + Send + Sync>);
+impl core::fmt::Debug for BlockCallback {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_tuple("BlockCallback").finish_non_exhaustive()
+    }
+}
+
+/// Returns the number of the bits written by `writer` so far (including the pending ones).
+fn bit_position<W>(writer: &bit::BitWriter<Counter<W>>) -> u64 {
+    writer.as_inner_ref().count() * 8 + u64::from(writer.pending_bits().1)
+}
+
+/// Appends the information of the block written since `start` (see [`bit_position`]) to `log` (if enabled).
+fn record_block<W>(
+    log: &mut Option<Vec<BlockInfo>>,
+    writer: &bit::BitWriter<Counter<W>>,
+    start: u64,
+    block_type: BlockType,
+    huffman_codes: Option<HuffmanCodesSource>,
+    input_size: usize,
+    is_final: bool,
+) {
+    if let Some(log) = log.as_mut() {
+        let kind = match block_type {
+            BlockType::Raw => BlockKind::Stored,
+            BlockType::Fixed => BlockKind::Fixed,
+            BlockType::Dynamic => BlockKind::Dynamic,
+        };
+        log.push(BlockInfo {
+            kind,
+            huffman_codes,
+            input_size,
+            output_bits: bit_position(writer) - start,
+            is_final,
+        });
+    }
+}
+
+/// Writes the LEN, NLEN and the data of a non-compressed block (`data` must not exceed 65535 bytes).
+fn write_non_compressed_block<W>(writer: &mut bit::BitWriter<W>, data: &[u8]) -> io::Result<()>
+where
+    W: io::Write,
+{
+    let size = data.len() as u16;
+    writer.flush()?;
+    writer.as_inner_mut().write_all(&size.to_le_bytes())?;
+    writer.as_inner_mut().write_all(&(!size).to_le_bytes())?;
+    writer.as_inner_mut().write_all(data)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct CompressBuf<H, E> {
     huffman: H,
     lz77: E,
-    buf: Vec<symbol::Symbol>,
+    #[cfg_attr(
+        all(feature = "serde", feature = "allocator_api"),
+        serde(with = "buffer_serde")
+    )]
+    buf: Buffer<symbol::Symbol>,
     original_size: usize,
+    splitter: Option<Box<BlockSplitter>>,
+    codes_cache: Option<Box<CodesCache>>,
+}
+impl<H, E> CompressBuf<H, E> {
+    #[cfg(feature = "serde")]
+    fn validate(&self) -> Result<(), &'static str> {
+        let mut size = 0;
+        for s in &self.buf {
+            match *s {
+                symbol::Symbol::EndOfBlock => {
+                    return Err(
+                        "The buffered symbols of the encoder state contain an end-of-block",
+                    );
+                }
+                symbol::Symbol::Code(lz77::Code::Literal(_)) => size += 1,
+                symbol::Symbol::Code(lz77::Code::Pointer {
+                    length,
+                    backward_distance,
+                }) => {
+                    if !(lz77::MIN_LENGTH..=lz77::MAX_LENGTH).contains(&length)
+                        || !(1..=lz77::MAX_DISTANCE).contains(&backward_distance)
+                    {
+                        return Err(
+                            "The buffered symbols of the encoder state contain an invalid pointer",
+                        );
+                    }
+                    size += usize::from(length);
+                }
+            }
+        }
+        if size > self.original_size {
+            return Err("The buffered symbols of the encoder state exceed its input size");
+        }
+        match self.splitter {
+            Some(ref splitter) => splitter.validate(self.buf.len()),
+            None => Ok(()),
+        }
+    }
 }
 impl<H, E> CompressBuf<H, E>
 where
     H: symbol::HuffmanCodec,
     E: lz77::Lz77Encode,
 {
-    fn new(huffman: H, lz77: E) -> Self {
+    fn new(huffman: H, lz77: E, allocator: BufferAllocator) -> Self {
         CompressBuf {
             huffman,
             lz77,
-            buf: Vec::new(),
+            buf: allocator.new_buffer(),
             original_size: 0,
+            splitter: None,
+            codes_cache: None,
         }
     }
     fn append(&mut self, buf: &[u8]) {
@@ -423,20 +2159,554 @@ where
     fn len(&self) -> usize {
         self.original_size
     }
-    fn flush<W>(&mut self, writer: &mut bit::BitWriter<W>) -> io::Result<()>
+    fn memory_usage(&self) -> usize {
+        self.buf.capacity() * mem::size_of::<symbol::Symbol>()
+            + self.lz77.memory_usage()
+            + self.splitter.as_ref().map_or(0, |s| s.memory_usage())
+    }
+    fn find_block_end(&mut self) -> Option<usize> {
+        let buf = &self.buf;
+        self.splitter.as_mut().and_then(|s| s.find_block_end(buf))
+    }
+    fn flush<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<W>,
+        block_type: BlockType,
+    ) -> io::Result<(BlockType, Option<HuffmanCodesSource>)>
     where
         W: io::Write,
     {
         self.lz77.flush(&mut self.buf);
-        self.buf.push(symbol::Symbol::EndOfBlock);
-        let symbol_encoder = self.huffman.build(&self.buf)?;
-        self.huffman.save(writer, &symbol_encoder)?;
-        for s in self.buf.drain(..) {
+        let written = self.write_block(writer, self.buf.len(), block_type)?;
+        if let Some(splitter) = self.splitter.as_mut() {
+            splitter.reset();
+        }
+        Ok(written)
+    }
+
+    /// Writes the block type and the block made from `self.buf[..block_end]`.
+    ///
+    /// Returns the block type actually used and the source of the Huffman codes.
+    fn write_block<W>(
+        &mut self,
+        writer: &mut bit::BitWriter<W>,
+        block_end: usize,
+        block_type: BlockType,
+    ) -> io::Result<(BlockType, Option<HuffmanCodesSource>)>
+    where
+        W: io::Write,
+    {
+        self.buf.insert(block_end, symbol::Symbol::EndOfBlock);
+        let symbols = &self.buf[..=block_end];
+        let mut block_type = block_type;
+        let mut header = None;
+        let cached_codes = match self.codes_cache {
+            Some(ref mut cache) => cache.take_codes(&self.huffman, symbols)?,
+            None => None,
+        };
+        let mut source = HuffmanCodesSource::Built;
+        let mut symbol_encoder = match cached_codes {
+            Some((codes, cached_source)) => {
+                header = Some(codes.header);
+                source = cached_source;
+                codes.encoder
+            }
+            _ => {
+                let encoder = self.huffman.build(symbols)?;
+                if self.splitter.is_some() || self.codes_cache.is_some() {
+                    header = Some(EncodedHeader::new(&self.huffman, &encoder)?);
+                }
+                encoder
+            }
+        };
+        if source == HuffmanCodesSource::Custom {
+            let built_encoder = self.huffman.build(symbols)?;
+            let built_header = EncodedHeader::new(&self.huffman, &built_encoder)?;
+            let custom_bits =
+                header.as_ref().map_or(0, |h| h.bit_count()) + code_bits(&symbol_encoder, symbols);
+            if built_header.bit_count() + code_bits(&built_encoder, symbols) < custom_bits {
+                symbol_encoder = built_encoder;
+                header = Some(built_header);
+                source = HuffmanCodesSource::Built;
+            }
+        }
+        if self.splitter.is_some() {
+            let fixed_encoder = symbol::HuffmanCodec::build(&symbol::FixedHuffmanCodec, symbols)?;
+            let header_bits = header.as_ref().map_or(0, |h| h.bit_count());
+            let dynamic_bits = header_bits + code_bits(&symbol_encoder, symbols);
+            if code_bits(&fixed_encoder, symbols) <= dynamic_bits {
+                symbol_encoder = fixed_encoder;
+                block_type = BlockType::Fixed;
+                header = None;
+            }
+        }
+
+        writer.write_bits(2, block_type as u16)?;
+        match header {
+            Some(ref header) => header.write_to(writer)?,
+            None if !matches!(block_type, BlockType::Fixed) => {
+                self.huffman.save(writer, &symbol_encoder)?
+            }
+            None => {}
+        }
+        for s in self.buf.drain(..=block_end) {
+            if let symbol::Symbol::Code(ref code) = s {
+                self.original_size -= match *code {
+                    lz77::Code::Literal(_) => 1,
+                    lz77::Code::Pointer { length, .. } => usize::from(length),
+                };
+            }
             symbol_encoder.encode(writer, &s)?;
         }
-        self.original_size = 0;
+        if let (Some(cache), Some(header)) = (self.codes_cache.as_mut(), header) {
+            cache.store(ReusableCodes {
+                encoder: symbol_encoder,
+                header,
+            });
+        }
+        let source = (block_type == BlockType::Dynamic).then_some(source);
+        Ok((block_type, source))
+    }
+}
+
+/// The cache of the dynamic Huffman codes for [`EncodeOptions::reuse_huffman_codes`]
+/// and [`EncodeOptions::huffman_tables`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct CodesCache {
+    reuse_previous: bool,
+    tables: Option<HuffmanTables>,
+    // The codes are not serialized, so they are just rebuilt after deserialization.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    custom: Option<ReusableCodes>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    previous: Option<ReusableCodes>,
+}
+impl CodesCache {
+    /// Returns the cached codes which can be used to encode `symbols` (if any).
+    fn take_codes<H>(
+        &mut self,
+        huffman: &H,
+        symbols: &[symbol::Symbol],
+    ) -> io::Result<Option<(ReusableCodes, HuffmanCodesSource)>>
+    where
+        H: symbol::HuffmanCodec,
+    {
+        if let Some(ref tables) = self.tables {
+            if self.custom.is_none() {
+                let encoder = tables.to_encoder()?;
+                let header = EncodedHeader::new(huffman, &encoder)?;
+                self.custom = Some(ReusableCodes { encoder, header });
+            }
+            if let Some(custom) = self.custom.as_ref().filter(|c| c.covers(symbols)) {
+                return Ok(Some((custom.clone(), HuffmanCodesSource::Custom)));
+            }
+        }
+        let previous = self.previous.take().filter(|c| c.is_reusable(symbols));
+        Ok(previous.map(|c| (c, HuffmanCodesSource::Reused)))
+    }
+    fn store(&mut self, codes: ReusableCodes) {
+        if self.reuse_previous {
+            self.previous = Some(codes);
+        }
+    }
+}
+
+/// The dynamic Huffman codes of the previous block and their encoded header.
+#[derive(Debug, Clone)]
+struct ReusableCodes {
+    encoder: symbol::Encoder,
+    header: EncodedHeader,
+}
+impl ReusableCodes {
+    /// Returns `true` if all of `symbols` have codes.
+    fn covers(&self, symbols: &[symbol::Symbol]) -> bool {
+        symbols.iter().all(|s| {
+            self.encoder.literal_code_bitwidth(s.code()) > 0
+                && s.distance().is_none_or(|(code, _, _)| {
+                    self.encoder.distance_code_bitwidth(u16::from(code)) > 0
+                })
+        })
+    }
+
+    /// Returns `true` if encoding `symbols` with these codes costs at most about 3% more than the lower bound
+    /// (the entropy of the symbols).
+    fn is_reusable(&self, symbols: &[symbol::Symbol]) -> bool {
+        let mut literal_counts = [0u64; 286];
+        let mut distance_counts = [0u64; 30];
+        for s in symbols {
+            literal_counts[s.code() as usize] += 1;
+            if let Some((code, _, _)) = s.distance() {
+                distance_counts[code as usize] += 1;
+            }
+        }
+
+        let mut cost = 0;
+        let mut lower_bound = 0;
+        for (counts, is_literal) in [(&literal_counts[..], true), (&distance_counts[..], false)] {
+            let total = counts.iter().sum::<u64>();
+            for (code, &count) in counts.iter().enumerate().filter(|x| *x.1 > 0) {
+                let symbol_width = if is_literal {
+                    self.encoder.literal_code_bitwidth(code as u16)
+                } else {
+                    self.encoder.distance_code_bitwidth(code as u16)
+                };
+                if symbol_width == 0 {
+                    return false;
+                }
+                cost += count * u64::from(symbol_width) * 256;
+                lower_bound += count * (log2_q8(total) - log2_q8(count));
+            }
+        }
+        cost <= lower_bound + lower_bound / 32
+    }
+}
+
+/// Huffman code lengths specified by [`EncodeOptions::huffman_tables`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedHuffmanTables"))]
+pub struct HuffmanTables {
+    literal: Vec<u8>,
+    distance: Vec<u8>,
+}
+
+/// [`HuffmanTables`] just deserialized, which are validated by [`HuffmanTables::new`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedHuffmanTables {
+    literal: Vec<u8>,
+    distance: Vec<u8>,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedHuffmanTables> for HuffmanTables {
+    type Error = &'static str;
+    fn try_from(tables: UncheckedHuffmanTables) -> Result<Self, Self::Error> {
+        HuffmanTables::new(&tables.literal, &tables.distance)
+            .map_err(|_| "The Huffman code lengths are invalid")
+    }
+}
+impl HuffmanTables {
+    /// Makes a new instance from the code lengths of the literal/length codes and the distance codes.
+    ///
+    /// `literal[i]` is the length of the code of the literal/length code `i`,
+    /// and `distance[i]` is the one of the distance code `i` (`0` means that the code is unused).
+    ///
+    /// The lengths must not exceed 15 bits, and must make complete prefix codes
+    /// (i.e., no code can be shortened).
+    /// The literal/length codes must contain the end-of-block code (`256`),
+    /// and unused distance codes (all `0`) or a single distance code of length `1` are also accepted.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::HuffmanTables;
+    ///
+    /// let mut literal = vec![9; 256];
+    /// literal.extend_from_slice(&[2; 2]);
+    /// assert!(HuffmanTables::new(&literal, &[]).is_ok());
+    /// assert!(HuffmanTables::new(&literal, &[1]).is_ok());
+    /// assert!(HuffmanTables::new(&literal, &[1, 1]).is_ok());
+    /// assert!(HuffmanTables::new(&literal, &[1, 2]).is_err()); // incomplete
+    ///
+    /// literal.push(2);
+    /// assert!(HuffmanTables::new(&literal, &[]).is_err()); // over-subscribed
+    /// ```
+    pub fn new(literal: &[u8], distance: &[u8]) -> io::Result<Self> {
+        if literal.len() < 257 || literal.len() > 286 {
+            return Err(Error::new(
+                ErrorKind::InvalidHuffmanCode,
+                "The number of literal/length codes must be from 257 to 286",
+            )
+            .with_value(literal.len() as u64)
+            .into());
+        }
+        if literal[256] == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidHuffmanCode,
+                "The end-of-block code must have a code",
+            )
+            .into());
+        }
+        if distance.len() > 30 {
+            return Err(Error::new(
+                ErrorKind::InvalidHuffmanCode,
+                "The number of distance codes must not exceed 30",
+            )
+            .with_value(distance.len() as u64)
+            .into());
+        }
+        check_complete_code_lengths(literal)?;
+        let distance_codes = distance.iter().filter(|&&w| w > 0).count();
+        if distance_codes > 1 || distance.iter().any(|&w| w > 1) {
+            check_complete_code_lengths(distance)?;
+        }
+        Ok(HuffmanTables {
+            literal: literal.to_vec(),
+            distance: distance.to_vec(),
+        })
+    }
+
+    /// Returns the code lengths of the literal/length codes.
+    pub fn literal_code_lengths(&self) -> &[u8] {
+        &self.literal
+    }
+
+    /// Returns the code lengths of the distance codes.
+    pub fn distance_code_lengths(&self) -> &[u8] {
+        &self.distance
+    }
+
+    fn to_encoder(&self) -> io::Result<symbol::Encoder> {
+        if self.distance.iter().all(|&w| w == 0) {
+            // Sets a dummy code because an empty distance table causes decoding error on Windows
+            // (see `symbol::DynamicHuffmanCodec::build`).
+            symbol::Encoder::from_bitwidthes(&self.literal, &[1])
+        } else {
+            symbol::Encoder::from_bitwidthes(&self.literal, &self.distance)
+        }
+    }
+}
+
+fn check_complete_code_lengths(lengths: &[u8]) -> io::Result<()> {
+    const MAX_BITWIDTH: u8 = 15;
+    if let Some(&width) = lengths.iter().find(|&&w| w > MAX_BITWIDTH) {
+        return Err(
+            Error::new(ErrorKind::InvalidHuffmanCode, "Too long Huffman code")
+                .with_value(width)
+                .with_expected(MAX_BITWIDTH)
+                .into(),
+        );
+    }
+    let kraft_sum = lengths
+        .iter()
+        .filter(|&&w| w > 0)
+        .map(|&w| 1u32 << (MAX_BITWIDTH - w))
+        .sum::<u32>();
+    if kraft_sum != 1 << MAX_BITWIDTH {
+        let message = if kraft_sum > 1 << MAX_BITWIDTH {
+            "Over-subscribed Huffman code lengths"
+        } else {
+            "Incomplete Huffman code lengths"
+        };
+        return Err(Error::new(ErrorKind::InvalidHuffmanCode, message).into());
+    }
+    Ok(())
+}
+
+/// The bits of an encoded dynamic Huffman code header, which can be written (again) at any bit position.
+#[derive(Debug, Clone)]
+struct EncodedHeader {
+    bytes: Vec<u8>,
+    pending_bits: (u32, u8),
+}
+impl EncodedHeader {
+    fn new<H: symbol::HuffmanCodec>(huffman: &H, encoder: &symbol::Encoder) -> io::Result<Self> {
+        let mut writer = bit::BitWriter::new(Vec::new());
+        huffman.save(&mut writer, encoder)?;
+        Ok(EncodedHeader {
+            pending_bits: writer.pending_bits(),
+            bytes: writer.into_inner(),
+        })
+    }
+    fn bit_count(&self) -> usize {
+        self.bytes.len() * 8 + usize::from(self.pending_bits.1)
+    }
+    fn write_to<W>(&self, writer: &mut bit::BitWriter<W>) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        for &b in &self.bytes {
+            writer.write_bits(8, u16::from(b))?;
+        }
+        let (bits, width) = self.pending_bits;
+        writer.write_bits(width, bits as u16)
+    }
+}
+
+/// Returns the total number of the bits of the Huffman codes of `symbols`.
+fn code_bits(encoder: &symbol::Encoder, symbols: &[symbol::Symbol]) -> usize {
+    symbols.iter().map(|s| encoder.code_bitwidth(s)).sum()
+}
+
+/// Decides where to end blocks in the adaptive block size mode (see [`EncodeOptions::adaptive_block_size`]).
+///
+/// The symbols are examined in segments of [`SEGMENT_SYMBOLS`].
+/// If encoding a segment with the statistics of the current block is estimated to cost much more than
+/// encoding it with its own statistics, the block is ended just before the segment.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct BlockSplitter {
+    block_counts: Vec<u64>,
+    block_symbols: usize,
+    segment_counts: Vec<u64>,
+    segment_start: usize,
+    checked: usize,
+}
+impl BlockSplitter {
+    #[cfg(feature = "serde")]
+    fn validate(&self, buffered_symbols: usize) -> Result<(), &'static str> {
+        if self.block_counts.len() != SPLITTER_ALPHABET_SIZE
+            || self.segment_counts.len() != SPLITTER_ALPHABET_SIZE
+            || self.segment_start > self.checked
+            || self.checked > buffered_symbols
+        {
+            return Err("The block splitter of the encoder state is inconsistent");
+        }
         Ok(())
     }
+
+    fn new() -> Self {
+        BlockSplitter {
+            block_counts: vec![0; SPLITTER_ALPHABET_SIZE],
+            block_symbols: 0,
+            segment_counts: vec![0; SPLITTER_ALPHABET_SIZE],
+            segment_start: 0,
+            checked: 0,
+        }
+    }
+    fn memory_usage(&self) -> usize {
+        (self.block_counts.capacity() + self.segment_counts.capacity()) * mem::size_of::<u64>()
+    }
+    fn reset(&mut self) {
+        self.block_counts.iter_mut().for_each(|c| *c = 0);
+        self.segment_counts.iter_mut().for_each(|c| *c = 0);
+        self.block_symbols = 0;
+        self.segment_start = 0;
+        self.checked = 0;
+    }
+
+    /// Returns the index of `symbols` at which the current block should end, if any.
+    ///
+    /// The caller has to remove the symbols before the returned index.
+    fn find_block_end(&mut self, symbols: &[symbol::Symbol]) -> Option<usize> {
+        while self.checked < symbols.len() {
+            let s = &symbols[self.checked];
+            self.checked += 1;
+            self.segment_counts[s.code() as usize] += 1;
+            if let Some((code, _, _)) = s.distance() {
+                self.segment_counts[286 + code as usize] += 1;
+            }
+            if self.checked - self.segment_start < SEGMENT_SYMBOLS {
+                continue;
+            }
+
+            if self.block_symbols >= MIN_ADAPTIVE_BLOCK_SYMBOLS && self.is_segment_different() {
+                // The segment becomes the beginning of the next block.
+                let block_end = self.segment_start;
+                mem::swap(&mut self.block_counts, &mut self.segment_counts);
+                self.segment_counts.iter_mut().for_each(|c| *c = 0);
+                self.checked -= block_end;
+                self.block_symbols = self.checked;
+                self.segment_start = self.checked;
+                return Some(block_end);
+            }
+            for (b, s) in self
+                .block_counts
+                .iter_mut()
+                .zip(self.segment_counts.iter_mut())
+            {
+                *b += mem::replace(s, 0);
+            }
+            self.block_symbols += self.checked - self.segment_start;
+            self.segment_start = self.checked;
+        }
+        None
+    }
+
+    fn is_segment_different(&self) -> bool {
+        // The costs are the entropies in the 24.8 fixed-point format.
+        // The counts of the block are smoothed so that unseen symbols don't have an infinite cost.
+        let block_total = self.block_counts.iter().sum::<u64>() + SPLITTER_ALPHABET_SIZE as u64;
+        let segment_total = self.segment_counts.iter().sum::<u64>();
+        let mut block_cost = 0;
+        let mut segment_cost = 0;
+        for (&b, &s) in self.block_counts.iter().zip(self.segment_counts.iter()) {
+            if s > 0 {
+                block_cost += s * (log2_q8(block_total) - log2_q8(b + 1));
+                segment_cost += s * (log2_q8(segment_total) - log2_q8(s));
+            }
+        }
+        block_cost > segment_cost + segment_cost / 16 + BLOCK_SPLIT_COST_BITS * 256
+    }
+}
+
+/// Returns `log2(x)` in the 24.8 fixed-point format, approximated by linear interpolation.
+fn log2_q8(x: u64) -> u64 {
+    debug_assert!(x > 0);
+    let n = u64::from(63 - x.leading_zeros());
+    let mantissa = if n >= 8 { x >> (n - 8) } else { x << (8 - n) };
+    n * 256 + (mantissa - 256)
+}
+
+/// Returns an upper bound of the size of the DEFLATE stream made from `input_len` bytes
+/// (the equivalent of `compressBound` of zlib).
+///
+/// The bound holds for [`Encoder`] with any compression level, strategy and number of threads,
+/// as long as the block size is at least 64 KiB (the default is [`DEFAULT_BLOCK_SIZE`])
+/// and the encoder is not flushed before it is finished.
+///
+/// Unlike zlib, this crate doesn't fall back to non-compressed blocks for incompressible data,
+/// so the bound is about 12.5% larger than `input_len` rather than the overhead of the non-compressed blocks.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::Write;
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::Write;
+/// use libflate::deflate::{self, Encoder};
+///
+/// let input = b"Hello World!";
+/// let mut arena = vec![0; deflate::max_compressed_len(input.len())];
+/// let mut encoder = Encoder::new(&mut arena[..]);
+/// encoder.write_all(input).unwrap();
+/// encoder.finish().into_result().unwrap();
+/// ```
+pub fn max_compressed_len(input_len: usize) -> usize {
+    // The encoder doesn't fall back to non-compressed blocks, so the data can be expanded by the Huffman codes
+    // (at most 9 bits per byte, the length of the longest fixed literal code).
+    // Each block has a header (at most about 290 bytes for dynamic Huffman codes) and an end-of-block symbol.
+    // With the parallel compression, a sync flush (5 bytes) ends each chunk, which may also split a block.
+    const MAX_BLOCK_OVERHEAD: usize = 320;
+    let blocks = input_len / (32 * 1024) + 2;
+    input_len
+        .saturating_add(input_len / 8 + 1)
+        .saturating_add(blocks.saturating_mul(MAX_BLOCK_OVERHEAD))
+}
+
+/// Compresses `input` into a DEFLATE stream with the default options.
+///
+/// The capacity of the returned vector is reserved in advance from the worst case of the compressed size,
+/// so the vector is never reallocated.
+///
+/// Unless the `no_std` feature is enabled, the buffers of the encoder are kept in a thread-local [`CoderPool`](crate::pool::CoderPool),
+/// and reused by the following calls on the same thread.
+/// This saves most of the allocations when compressing many small inputs.
+///
+/// # Examples
+/// ```
+/// use libflate::deflate;
+///
+/// let encoded_data = deflate::compress_to_vec(b"Hello World!").unwrap();
+/// assert_eq!(deflate::decompress_to_vec(&encoded_data).unwrap(), b"Hello World!");
+/// ```
+pub fn compress_to_vec(input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    util::try_reserve(&mut buf, max_compressed_len(input.len()))?;
+    #[cfg(not(feature = "no_std"))]
+    {
+        crate::pool::with_local_pool(|pool| {
+            let mut encoder = pool.deflate_encoder(buf);
+            encoder.write_all(input)?;
+            encoder.finish().into_result()
+        })
+    }
+    #[cfg(feature = "no_std")]
+    {
+        let mut encoder = Encoder::new(buf);
+        encoder.write_all(input)?;
+        encoder.finish().into_result()
+    }
 }
 
 #[cfg(test)]
@@ -444,9 +2714,9 @@ mod tests {
     use super::super::Decoder;
     use super::*;
     #[cfg(feature = "no_std")]
-    use core2::io::{Read as _, Write as _};
+    use core2::io::Read as _;
     #[cfg(not(feature = "no_std"))]
-    use std::io::{Read as _, Write as _};
+    use std::io::Read as _;
 
     #[test]
     fn test_issues_52() {
@@ -473,6 +2743,383 @@ mod tests {
         assert!(LIMIT_2 > compressed.len());
     }
 
+    #[test]
+    fn block_splitter_works() {
+        let literals = |range: u64, count: usize, seed: u64| {
+            let mut x = seed;
+            (0..count)
+                .map(|_| {
+                    x = x
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    symbol::Symbol::Code(lz77::Code::Literal(((x >> 33) % range) as u8))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Stationary statistics
+        let mut splitter = BlockSplitter::new();
+        assert_eq!(splitter.find_block_end(&literals(26, 100_000, 1)), None);
+
+        // The statistics change at the 5th segment
+        let mut symbols = literals(26, 4 * SEGMENT_SYMBOLS, 2);
+        symbols.extend(literals(256, 4 * SEGMENT_SYMBOLS, 3));
+        let mut splitter = BlockSplitter::new();
+        assert_eq!(splitter.find_block_end(&symbols), Some(4 * SEGMENT_SYMBOLS));
+        symbols.drain(..4 * SEGMENT_SYMBOLS);
+        assert_eq!(splitter.find_block_end(&symbols), None);
+    }
+
+    #[test]
+    fn adaptive_block_size_works() {
+        let mut x = 1u32;
+        let mut plain = Vec::new();
+        for i in 0..6 {
+            for _ in 0..50_000 {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                plain.push(if i % 2 == 0 {
+                    b"aaabbc"[x as usize % 6]
+                } else {
+                    x as u8
+                });
+            }
+        }
+
+        let encode = |options: EncodeOptions| {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            for chunk in plain.chunks(1000) {
+                encoder.write_all(chunk).unwrap();
+            }
+            encoder.finish().into_result().unwrap()
+        };
+        let fixed_size = encode(EncodeOptions::new());
+        let adaptive = encode(EncodeOptions::new().adaptive_block_size());
+        assert!(adaptive.len() < fixed_size.len());
+
+        let mut decoded = Vec::new();
+        Decoder::new(&adaptive[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+
+        let small = encode(
+            EncodeOptions::new()
+                .adaptive_block_size()
+                .block_size(10_000),
+        );
+        let mut decoded = Vec::new();
+        Decoder::new(&small[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    #[test]
+    fn reuse_huffman_codes_works() {
+        let mut plain = Vec::new();
+        for i in 0..20_000 {
+            plain.extend_from_slice(
+                format!(
+                    "2026-10-14T12:{:02}:{:02} INFO request {} done\n",
+                    i / 60 % 60,
+                    i % 60,
+                    i * 7
+                )
+                .as_bytes(),
+            );
+        }
+
+        let encode = |options: EncodeOptions| {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            for chunk in plain.chunks(1000) {
+                encoder.write_all(chunk).unwrap();
+            }
+            encoder.finish().into_result().unwrap()
+        };
+        let options = EncodeOptions::new().block_size(4096);
+        let rebuilt = encode(options.clone());
+        let reused = encode(options.reuse_huffman_codes());
+        assert!(reused.len() <= rebuilt.len() + rebuilt.len() / 32);
+
+        let mut decoded = Vec::new();
+        Decoder::new(&reused[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    #[test]
+    fn block_callback_works() {
+        use std::sync::{Arc, Mutex};
+
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let cases = [
+            (EncodeOptions::new().block_size(10_000), BlockKind::Dynamic),
+            (EncodeOptions::new().fixed_huffman_codes(), BlockKind::Fixed),
+            (EncodeOptions::new().no_compression(), BlockKind::Stored),
+        ];
+        for (options, kind) in cases {
+            let blocks = Arc::new(Mutex::new(Vec::new()));
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            {
+                let blocks = blocks.clone();
+                encoder.set_block_callback(move |info| blocks.lock().unwrap().push(info.clone()));
+            }
+            encoder.write_all(&plain[..30_000]).unwrap();
+            encoder.message_boundary().unwrap();
+            encoder.write_all(&plain[30_000..]).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let blocks = blocks.lock().unwrap();
+            let input_size = blocks.iter().map(|b| b.input_size).sum::<usize>();
+            assert_eq!(input_size, plain.len());
+            let output_bits = blocks.iter().map(|b| b.output_bits).sum::<u64>();
+            assert_eq!(output_bits.div_ceil(8), encoded.len() as u64);
+            assert_eq!(blocks.iter().filter(|b| b.is_final).count(), 1);
+            assert!(blocks.last().unwrap().is_final);
+
+            // The empty non-compressed block of the sync flush
+            assert!(blocks
+                .iter()
+                .any(|b| b.kind == BlockKind::Stored && b.input_size == 0));
+            assert!(blocks
+                .iter()
+                .filter(|b| b.input_size > 0)
+                .all(|b| b.kind == kind));
+            assert!(blocks
+                .iter()
+                .all(|b| b.huffman_codes.is_some() == (b.kind == BlockKind::Dynamic)));
+        }
+
+        let blocks = Arc::new(Mutex::new(Vec::new()));
+        let options = EncodeOptions::new().block_size(4096).reuse_huffman_codes();
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+        {
+            let blocks = blocks.clone();
+            encoder.set_block_callback(move |info| blocks.lock().unwrap().push(info.clone()));
+        }
+        for i in 0..5000 {
+            writeln!(
+                encoder,
+                "12:{:02}:{:02} INFO request {} done",
+                i / 60 % 60,
+                i % 60,
+                i * 7
+            )
+            .unwrap();
+        }
+        encoder.finish().into_result().unwrap();
+        let blocks = blocks.lock().unwrap();
+        assert_eq!(blocks[0].huffman_codes, Some(HuffmanCodesSource::Built));
+        assert!(blocks
+            .iter()
+            .any(|b| b.huffman_codes == Some(HuffmanCodesSource::Reused)));
+    }
+
+    #[test]
+    fn reusable_codes_require_all_used_symbols() {
+        let symbols = b"aaaabbc"
+            .iter()
+            .map(|&b| symbol::Symbol::Code(lz77::Code::Literal(b)))
+            .chain(Some(symbol::Symbol::EndOfBlock))
+            .collect::<Vec<_>>();
+        let huffman = symbol::DynamicHuffmanCodec;
+        let encoder = symbol::HuffmanCodec::build(&huffman, &symbols).unwrap();
+        let codes = ReusableCodes {
+            header: EncodedHeader::new(&huffman, &encoder).unwrap(),
+            encoder,
+        };
+        assert!(codes.is_reusable(&symbols));
+
+        let mut other = symbols.clone();
+        other.insert(0, symbol::Symbol::Code(lz77::Code::Literal(b'd')));
+        assert!(!codes.is_reusable(&other));
+
+        let other = [symbol::Symbol::Code(lz77::Code::Pointer {
+            length: 3,
+            backward_distance: 30000,
+        })];
+        assert!(!codes.is_reusable(&other));
+    }
+
+    #[test]
+    fn huffman_tables_work() {
+        // Literals, the end-of-block code and the length code `257` (3 bytes) with the distance code `0` (1 byte)
+        let mut literal = vec![9; 256];
+        literal.extend_from_slice(&[2; 2]);
+        let tables = HuffmanTables::new(&literal, &[1]).unwrap();
+        let options = EncodeOptions::new().huffman_tables(tables);
+
+        let encode = |plain: &[u8]| {
+            let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+            encoder.write_all(plain).unwrap();
+            encoder.finish().into_result().unwrap()
+        };
+        let decode = |encoded: &[u8]| {
+            let mut decoded = Vec::new();
+            Decoder::new(encoded).read_to_end(&mut decoded).unwrap();
+            decoded
+        };
+
+        // Distinct literals are encoded with the custom codes, whose header is smaller than the built ones
+        let plain = (0..50).map(|i| (i * 97 % 256) as u8).collect::<Vec<_>>();
+        let encoded = encode(&plain);
+        assert_eq!(decode(&encoded), plain);
+        assert!(encoded.len() < compress_to_vec(&plain).unwrap().len());
+
+        // "aaaa" is encoded with the codes built for the block, which make it smaller
+        let encoded = encode(b"aaaa");
+        assert_eq!(decode(&encoded), b"aaaa");
+        assert_eq!(encoded, compress_to_vec(b"aaaa").unwrap());
+
+        // The symbols without codes are encoded with the codes built for the block
+        let plain = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        assert_eq!(decode(&encode(&plain)), plain);
+
+        assert!(HuffmanTables::new(&literal[..255], &[1]).is_err());
+        assert!(HuffmanTables::new(&literal, &[16, 1]).is_err());
+        literal[256] = 0;
+        literal[0] = 2;
+        assert!(HuffmanTables::new(&literal, &[]).is_err());
+    }
+
+    #[test]
+    fn set_params_works() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut encoder = Encoder::new(Vec::new());
+        for (i, chunk) in plain.chunks(10_000).enumerate() {
+            match i % 4 {
+                0 => encoder.set_level(lz77::CompressionLevel::None).unwrap(),
+                1 => encoder.set_level(lz77::CompressionLevel::Best).unwrap(),
+                2 => encoder.set_strategy(Strategy::FixedHuffmanCodes).unwrap(),
+                _ => encoder.set_strategy(Strategy::Default).unwrap(),
+            }
+            encoder.write_all(chunk).unwrap();
+        }
+        let encoded = encoder.finish().into_result().unwrap();
+        assert!(encoded.len() < plain.len() / 2);
+
+        let mut decoded = Vec::new();
+        Decoder::new(&encoded[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+
+        // An encoder made by `no_compression()` can't start compressing
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().no_compression());
+        encoder.write_all(&plain[..100]).unwrap();
+        encoder.set_strategy(Strategy::FixedHuffmanCodes).unwrap();
+        assert!(encoder.set_level(lz77::CompressionLevel::Fast).is_err());
+        encoder.write_all(&plain[100..]).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        assert!(encoded.len() > plain.len());
+    }
+
+    #[test]
+    fn sync_flush_interval_works() {
+        fn decodable_size(encoded: &[u8]) -> usize {
+            let mut decoder = Decoder::new(encoded);
+            let mut buf = [0; 100];
+            let mut size = 0;
+            while let Ok(n @ 1..) = decoder.read(&mut buf) {
+                size += n;
+            }
+            size
+        }
+
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let options = EncodeOptions::new().sync_flush_interval(10_000);
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+        encoder.write_all(&plain[..25_000]).unwrap();
+        assert_eq!(decodable_size(encoder.as_inner_ref()), 20_000);
+        encoder.write_all(&plain[25_000..29_999]).unwrap();
+        assert_eq!(decodable_size(encoder.as_inner_ref()), 20_000);
+        encoder.write_all(&plain[29_999..30_000]).unwrap();
+        assert_eq!(decodable_size(encoder.as_inner_ref()), 30_000);
+
+        encoder.write_all(&plain[30_000..30_100]).unwrap();
+        encoder.message_boundary().unwrap();
+        assert_eq!(decodable_size(encoder.as_inner_ref()), 30_100);
+        let size = encoder.as_inner_ref().len();
+        encoder.message_boundary().unwrap();
+        assert_eq!(encoder.as_inner_ref().len(), size);
+
+        // The interval is counted from the last flush
+        encoder.write_all(&plain[30_100..40_000]).unwrap();
+        assert_eq!(decodable_size(encoder.as_inner_ref()), 30_100);
+        encoder.write_all(&plain[40_000..]).unwrap();
+        assert_eq!(decodable_size(encoder.as_inner_ref()), 90_100);
+
+        let encoded = encoder.finish().into_result().unwrap();
+        let mut decoded = Vec::new();
+        Decoder::new(&encoded[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    #[test]
+    fn line_flush_works() {
+        fn decoded(encoded: &[u8]) -> Vec<u8> {
+            let mut decoder = Decoder::new(encoded);
+            let mut decoded = Vec::new();
+            let mut buf = [0; 100];
+            while let Ok(n @ 1..) = decoder.read(&mut buf) {
+                decoded.extend_from_slice(&buf[..n]);
+            }
+            decoded
+        }
+
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().line_flush(0));
+        encoder.write_all(b"foo\nbar").unwrap();
+        assert_eq!(decoded(encoder.as_inner_ref()), b"foo\n");
+        encoder.write_all(b"\n").unwrap();
+        assert_eq!(decoded(encoder.as_inner_ref()), b"foo\nbar\n");
+        encoder.write_all(b"\n\n").unwrap();
+        assert_eq!(decoded(encoder.as_inner_ref()), b"foo\nbar\n\n\n");
+
+        // Short lines are flushed together
+        let options = EncodeOptions::new().line_flush(10);
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+        encoder.write_all(b"a\nbb\nccc\nddddddd\ne").unwrap();
+        assert_eq!(decoded(encoder.as_inner_ref()), b"a\nbb\nccc\nddddddd\n");
+        encoder.write_all(b"\nf\n").unwrap();
+        assert_eq!(decoded(encoder.as_inner_ref()), b"a\nbb\nccc\nddddddd\n");
+        encoder.write_all(&[b'g'; 10]).unwrap();
+        encoder.write_all(b"\nh").unwrap();
+        assert_eq!(
+            decoded(encoder.as_inner_ref()),
+            [&b"a\nbb\nccc\nddddddd\ne\nf\n"[..], &[b'g'; 10], b"\n"].concat()
+        );
+
+        // Combined with an interval, a long line is flushed at the interval too
+        let options = EncodeOptions::new().line_flush(0).sync_flush_interval(5);
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+        encoder.write_all(b"abcdefgh\nij").unwrap();
+        assert_eq!(decoded(encoder.as_inner_ref()), b"abcdefgh\n");
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(decoded(&encoded), b"abcdefgh\nij");
+    }
+
+    #[test]
+    fn no_compression_memory_usage_is_constant() {
+        let plain = (0..1_000_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for &block_size in &[100, 1000, DEFAULT_BLOCK_SIZE] {
+            let options = EncodeOptions::new().no_compression().block_size(block_size);
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain[..10]).unwrap();
+            encoder.write_all(&plain[10..]).unwrap();
+            assert!(encoder.memory_usage() <= cmp::min(block_size, 0xFFFF) * 2);
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoded = Vec::new();
+            Decoder::new(&encoded[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, plain);
+        }
+    }
+
     #[test]
     fn test_issue_27() {
         // See: https://github.com/sile/libflate/issues/27
@@ -482,7 +3129,7 @@ mod tests {
         let mut encoder = Encoder::new(Vec::new());
         for _ in 0..2 {
             for string in &writes {
-                encoder.write(string.as_bytes()).expect("Write failed");
+                encoder.write_all(string.as_bytes()).expect("Write failed");
             }
             encoder.flush().expect("Flush failed");
         }
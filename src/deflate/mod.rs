@@ -22,19 +22,40 @@
 //!
 //! assert_eq!(decoded_data, b"Hello World!");
 //! ```
+//!
+//! # Endless streams
+//!
+//! Some streaming protocols keep a single raw DEFLATE stream open during a session without ever ending it,
+//! and make a sync flush after each message.
+//! Such a stream is produced by [`EncodeOptions::endless`] (with [`Encoder::message_boundary`] after each message),
+//! and is decoded by [`DecodeOptions::endless`], which regards the end of the input at a flush point as the end of the stream.
+pub use self::decode::decompress_to_vec;
+pub use self::decode::decompress_to_vec_with_capacity;
+pub use self::decode::DecodeOptions;
 pub use self::decode::Decoder;
+pub use self::encode::compress_to_vec;
+pub use self::encode::max_compressed_len;
+pub use self::encode::BlockInfo;
+pub use self::encode::BlockKind;
 pub use self::encode::EncodeOptions;
 pub use self::encode::Encoder;
+pub use self::encode::EncoderState;
+pub use self::encode::HuffmanCodesSource;
+pub use self::encode::HuffmanTables;
 pub use self::encode::DEFAULT_BLOCK_SIZE;
+pub use self::encode::DEFAULT_BUFFER_SIZE;
+pub use self::pool::ContextPool;
 
 mod decode;
 mod encode;
+mod pool;
 pub(crate) mod symbol;
 
 #[cfg(test)]
 pub(crate) mod test_data;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum BlockType {
     Raw = 0b00,
     Fixed = 0b01,
@@ -67,4 +88,180 @@ mod tests {
 
         assert_eq!(buffer, plain);
     }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn progress_callback_works() {
+        use std::sync::{Arc, Mutex};
+
+        let mut seed = 1u32;
+        let plain = (0..100_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8 % 16
+            })
+            .collect::<Vec<_>>();
+        for threads in [1, 4] {
+            let calls = Arc::new(Mutex::new(Vec::new()));
+            let mut encoder = Encoder::with_options(
+                Vec::new(),
+                EncodeOptions::new().block_size(10_000).threads(threads),
+            );
+            {
+                let calls = calls.clone();
+                encoder.set_progress_callback(30_000, move |bytes_in, bytes_out| {
+                    calls.lock().unwrap().push((bytes_in, bytes_out));
+                });
+            }
+            for chunk in plain.chunks(1000) {
+                encoder.write_all(chunk).unwrap();
+            }
+            let encoded = encoder.finish().into_result().unwrap();
+            let calls = calls.lock().unwrap().clone();
+            let bytes_in = calls.iter().map(|x| x.0).collect::<Vec<_>>();
+            assert_eq!(bytes_in, [30_000, 60_000, 90_000, 100_000]);
+            assert_eq!(calls.last().unwrap().1, encoded.len() as u64);
+
+            let calls = Arc::new(Mutex::new(Vec::new()));
+            let interval = encoded.len() as u64 / 10;
+            let mut decoder = Decoder::new(&encoded[..]);
+            {
+                let calls = calls.clone();
+                decoder.set_progress_callback(interval, move |bytes_in, bytes_out| {
+                    calls.lock().unwrap().push((bytes_in, bytes_out));
+                });
+            }
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, plain);
+            let calls = calls.lock().unwrap().clone();
+            assert!(calls.len() > 2);
+            assert!(calls[..calls.len() - 1]
+                .windows(2)
+                .all(|w| w[0].0 + interval <= w[1].0));
+            assert_eq!(
+                *calls.last().unwrap(),
+                (encoded.len() as u64, plain.len() as u64)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn cancellation_works() {
+        use crate::error::{Error, ErrorKind};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let plain = vec![b'a'; 1_000_000];
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut decoder = Decoder::new(&encoded[..]);
+        {
+            let cancelled = cancelled.clone();
+            decoder.set_cancellation_check(move || cancelled.load(Ordering::Relaxed));
+        }
+        let mut buf = vec![0; 100_000];
+        decoder.read_exact(&mut buf).unwrap();
+
+        cancelled.store(true, Ordering::Relaxed);
+        let mut rest = Vec::new();
+        let error = decoder.read_to_end(&mut rest).unwrap_err();
+        assert_eq!(
+            Error::from_io_error(&error).map(|e| e.kind()),
+            Some(ErrorKind::Cancelled)
+        );
+        assert!(buf.len() + rest.len() < plain.len());
+    }
+
+    #[test]
+    fn interrupted_errors_are_retried() {
+        use crate::util::InterruptingIo;
+        use crate::{gzip, non_blocking, zlib};
+
+        fn read_all<R: Read>(mut reader: R) -> Vec<u8> {
+            let mut buf = Vec::new();
+            let mut chunk = [0; 1000];
+            loop {
+                // Unlike `read_to_end`, this does not retry by itself
+                match reader.read(&mut chunk).unwrap() {
+                    0 => return buf,
+                    size => buf.extend_from_slice(&chunk[..size]),
+                }
+            }
+        }
+
+        let plain = (0..10_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for options in [EncodeOptions::new(), EncodeOptions::new().no_compression()] {
+            let mut encoder = Encoder::with_options(InterruptingIo::new(Vec::new()), options);
+            encoder.write_all(&plain).unwrap();
+            encoder.flush().unwrap();
+            let encoded = encoder.finish().into_result().unwrap().into_inner();
+
+            let decoder = Decoder::new(InterruptingIo::new(&encoded[..]));
+            assert_eq!(read_all(decoder), plain);
+            let decoder = non_blocking::deflate::Decoder::new(InterruptingIo::new(&encoded[..]));
+            assert_eq!(read_all(decoder), plain);
+        }
+
+        let mut encoder = zlib::Encoder::new(InterruptingIo::new(Vec::new())).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap().into_inner();
+        let decoder = zlib::Decoder::new(InterruptingIo::new(&encoded[..])).unwrap();
+        assert_eq!(read_all(decoder), plain);
+
+        let mut encoder = gzip::Encoder::new(InterruptingIo::new(Vec::new())).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap().into_inner();
+        let decoder = gzip::Decoder::new(InterruptingIo::new(&encoded[..])).unwrap();
+        assert_eq!(read_all(decoder), plain);
+    }
+
+    #[test]
+    fn max_compressed_len_is_upper_bound() {
+        let mut seed = 1u32;
+        let random = (0..1_000_000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect::<Vec<_>>();
+        let high_literals = (0..1_000_000)
+            .map(|i| 0x90 + (i * 7 % 0x70) as u8)
+            .collect::<Vec<_>>();
+        // Short codes for the ASCII characters and very long ones for the others
+        let mut literal = vec![7; 127];
+        literal.extend_from_slice(&[14; 97]);
+        literal.extend_from_slice(&[15; 62]);
+        let mut distance = vec![4; 2];
+        distance.extend_from_slice(&[5; 28]);
+        let tables = HuffmanTables::new(&literal, &distance).unwrap();
+
+        let mut options = vec![
+            EncodeOptions::new(),
+            EncodeOptions::new().huffman_tables(tables),
+            EncodeOptions::new().fixed_huffman_codes(),
+            EncodeOptions::new().no_compression(),
+            EncodeOptions::new().block_size(64 * 1024),
+        ];
+        #[cfg(not(feature = "no_std"))]
+        options.push(EncodeOptions::new().threads(4));
+        for options in options {
+            for input in [&random[..], &high_literals[..]] {
+                for &len in &[0, 1, 100, 65_535, 65_536, 1_000_000] {
+                    let mut encoder = Encoder::with_options(Vec::new(), options.clone());
+                    encoder.write_all(&input[..len]).unwrap();
+                    let encoded = encoder.finish().into_result().unwrap();
+                    assert!(encoded.len() <= max_compressed_len(len));
+                }
+            }
+        }
+    }
 }
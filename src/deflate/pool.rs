@@ -0,0 +1,279 @@
+use super::encode::IdleState;
+use super::{EncodeOptions, Encoder, EncoderState};
+use crate::lz77;
+#[cfg(feature = "no_std")]
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "no_std")]
+use core2::io::{self, Write};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+/// A set of independent DEFLATE encoding contexts (e.g., one per multiplexed channel or connection).
+///
+/// Each context encodes its own DEFLATE stream, which is written to the output passed to each operation.
+/// A context is suspended between the operations, and the buffers of the suspended contexts
+/// which have no buffered data (e.g., just after [`ContextPool::flush`]) are shared:
+/// such a context only takes a few dozen bytes, and resuming it reuses the buffers of the pool.
+/// So the memory usage of the pool depends on the number of the contexts holding unflushed data,
+/// rather than the number of all the contexts.
+///
+/// All the contexts are encoded with the same options, and the multi-threaded compression is not supported
+/// (the [`EncodeOptions::threads`] setting is ignored).
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::Read;
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::Read;
+/// use libflate::deflate::{ContextPool, Decoder};
+///
+/// let mut pool = ContextPool::new();
+/// let mut outputs = [Vec::new(), Vec::new()];
+/// pool.open(0).unwrap();
+/// pool.open(1).unwrap();
+///
+/// pool.write(&0, b"Hello ", &mut outputs[0]).unwrap();
+/// pool.write(&1, b"Good", &mut outputs[1]).unwrap();
+/// pool.flush(&0, &mut outputs[0]).unwrap();
+/// pool.write(&1, b"bye!", &mut outputs[1]).unwrap();
+/// pool.write(&0, b"World!", &mut outputs[0]).unwrap();
+/// pool.finish(&0, &mut outputs[0]).unwrap();
+/// pool.finish(&1, &mut outputs[1]).unwrap();
+/// assert!(pool.is_empty());
+///
+/// let mut decoded = String::new();
+/// Decoder::new(&outputs[0][..]).read_to_string(&mut decoded).unwrap();
+/// assert_eq!(decoded, "Hello World!");
+/// decoded.clear();
+/// Decoder::new(&outputs[1][..]).read_to_string(&mut decoded).unwrap();
+/// assert_eq!(decoded, "Goodbye!");
+/// ```
+#[derive(Debug)]
+pub struct ContextPool<K, E = lz77::DefaultLz77Encoder> {
+    options: EncodeOptions<E>,
+    initial: IdleState,
+    contexts: BTreeMap<K, Context<E>>,
+
+    // The state of an idle context, whose buffers are reused by the next resumed idle context.
+    spare: Option<EncoderState<E>>,
+}
+impl<K> ContextPool<K, lz77::DefaultLz77Encoder>
+where
+    K: Ord,
+{
+    /// Makes a new pool whose contexts are encoded with the default options.
+    pub fn new() -> Self {
+        Self::with_options(EncodeOptions::default())
+    }
+}
+impl<K> Default for ContextPool<K, lz77::DefaultLz77Encoder>
+where
+    K: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K, E> ContextPool<K, E>
+where
+    K: Ord,
+    E: lz77::Lz77Encode + Clone,
+{
+    /// Makes a new pool whose contexts are encoded with the specified options.
+    pub fn with_options(options: EncodeOptions<E>) -> Self {
+        #[cfg(not(feature = "no_std"))]
+        let options = options.threads(1);
+        let spare = Encoder::with_options(Vec::new(), options.clone()).into_state();
+        ContextPool {
+            options,
+            initial: spare.idle_state(),
+            contexts: BTreeMap::new(),
+            spare: Some(spare),
+        }
+    }
+
+    /// Starts a new context identified by `key`.
+    ///
+    /// # Errors
+    ///
+    /// If a context identified by `key` already exists, this returns an `io::ErrorKind::InvalidInput` error.
+    pub fn open(&mut self, key: K) -> io::Result<()> {
+        if self.contexts.contains_key(&key) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The context already exists",
+            ));
+        }
+        let context = Context {
+            idle: self.initial.clone(),
+            busy: None,
+        };
+        self.contexts.insert(key, context);
+        Ok(())
+    }
+
+    /// Encodes `buf` in the context identified by `key`.
+    ///
+    /// The compressed data are written to `output`, which should be the stream written by the previous operations on the context.
+    /// Like [`Encoder`], some of the data may be kept in the context until it is flushed or finished.
+    ///
+    /// # Errors
+    ///
+    /// If there is no context identified by `key`, this returns an `io::ErrorKind::InvalidInput` error.
+    pub fn write<W: Write>(&mut self, key: &K, buf: &[u8], output: W) -> io::Result<()> {
+        self.with_encoder(key, output, |encoder| encoder.write_all(buf))
+    }
+
+    /// Writes all the data buffered in the context identified by `key` to `output`,
+    /// terminating them with a sync flush (see [`Encoder::message_boundary`]), and flushes `output`.
+    ///
+    /// After this, the context no longer holds its own buffers until data are written to it again.
+    pub fn flush<W: Write>(&mut self, key: &K, output: W) -> io::Result<()> {
+        self.with_encoder(key, output, |encoder| encoder.flush())
+    }
+
+    /// Finishes the DEFLATE stream of the context identified by `key`, and removes the context.
+    ///
+    /// The context is removed even if this fails.
+    pub fn finish<W: Write>(&mut self, key: &K, output: W) -> io::Result<()> {
+        let result = self.with_encoder(key, output, |encoder| encoder.try_finish());
+        self.contexts.remove(key);
+        result
+    }
+
+    /// Removes the context identified by `key` without finishing its stream.
+    ///
+    /// Returns `false` if there is no such context.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.contexts.remove(key).is_some()
+    }
+
+    /// Returns `true` if there is a context identified by `key`.
+    pub fn contains(&self, key: &K) -> bool {
+        self.contexts.contains_key(key)
+    }
+
+    /// Returns the number of the contexts.
+    pub fn len(&self) -> usize {
+        self.contexts.len()
+    }
+
+    /// Returns `true` if there are no contexts.
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+
+    /// Returns the approximate number of the bytes of the buffers held by the pool (including the ones of the contexts).
+    ///
+    /// The fixed size of each context is not included.
+    pub fn memory_usage(&self) -> usize {
+        let busy = self
+            .contexts
+            .values()
+            .filter_map(|context| context.busy.as_ref())
+            .map(|state| state.memory_usage())
+            .sum::<usize>();
+        busy + self.spare.as_ref().map_or(0, |state| state.memory_usage())
+    }
+
+    fn with_encoder<W, F>(&mut self, key: &K, output: W, f: F) -> io::Result<()>
+    where
+        W: Write,
+        F: FnOnce(&mut Encoder<W, E>) -> io::Result<()>,
+    {
+        let context = self.contexts.get_mut(key).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "The context does not exist")
+        })?;
+        let state = match context.busy.take() {
+            Some(state) => state,
+            None => {
+                let mut state = match self.spare.take() {
+                    Some(state) => state,
+                    None => Encoder::with_options(Vec::new(), self.options.clone()).into_state(),
+                };
+                state.restore_idle_state(&context.idle);
+                state
+            }
+        };
+
+        let mut encoder = Encoder::from_state(output, state);
+        let result = f(&mut encoder);
+        let state = encoder.into_state();
+        if state.is_idle() {
+            context.idle = state.idle_state();
+            if self.spare.is_none() {
+                self.spare = Some(state);
+            }
+        } else {
+            context.busy = Some(state);
+        }
+        result
+    }
+}
+
+#[derive(Debug)]
+struct Context<E> {
+    // Valid only if `busy` is `None`.
+    idle: IdleState,
+
+    // The state of the context which holds buffered data.
+    busy: Option<EncoderState<E>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deflate::Decoder;
+    #[cfg(feature = "no_std")]
+    use alloc::vec;
+    #[cfg(feature = "no_std")]
+    use core2::io::Read;
+    #[cfg(not(feature = "no_std"))]
+    use std::io::Read;
+
+    #[test]
+    fn context_pool_works() {
+        let plain = (0..30_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let mut pool = ContextPool::with_options(EncodeOptions::new().block_size(10_000));
+        let mut outputs = vec![Vec::new(); 30];
+        for key in 0..outputs.len() {
+            pool.open(key).unwrap();
+        }
+        assert!(pool.open(0).is_err());
+
+        for (i, chunk) in plain.chunks(1000).enumerate() {
+            for (key, output) in outputs.iter_mut().enumerate() {
+                pool.write(&key, chunk, &mut *output).unwrap();
+                if (i + key) % 10 == 0 {
+                    pool.flush(&key, &mut *output).unwrap();
+                }
+            }
+        }
+        // Only the buffers of the contexts holding unflushed data (and the spare ones) are kept
+        let busy = pool.contexts.values().filter(|c| c.busy.is_some()).count();
+        assert!(busy < outputs.len());
+        assert!(pool.memory_usage() > 0);
+        for (key, output) in outputs.iter_mut().enumerate() {
+            pool.flush(&key, &mut *output).unwrap();
+        }
+        assert!(pool.contexts.values().all(|c| c.busy.is_none()));
+
+        for (key, output) in outputs.iter_mut().enumerate() {
+            pool.finish(&key, &mut *output).unwrap();
+        }
+        assert!(pool.is_empty());
+        assert!(pool.write(&0, b"foo", Vec::new()).is_err());
+
+        for output in outputs {
+            let mut decoded = Vec::new();
+            Decoder::new(&output[..]).read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, plain);
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use crate::bit;
+use crate::error::{Error, ErrorKind};
 use crate::huffman;
 use crate::huffman::Builder;
 use crate::lz77;
 #[cfg(feature = "no_std")]
 use alloc::boxed::Box;
 #[cfg(feature = "no_std")]
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 #[cfg(feature = "no_std")]
 use core::{cmp, iter, ops::Range};
 #[cfg(feature = "no_std")]
@@ -59,6 +60,7 @@ const LENGTH_TABLE: [(u16, u8); 29] = [
 ];
 
 const MAX_DISTANCE_CODE_COUNT: usize = 30;
+const MAX_LITERAL_CODE_COUNT: usize = 286;
 
 const DISTANCE_TABLE: [(u16, u8); 30] = [
     (1, 0),
@@ -93,7 +95,8 @@ const DISTANCE_TABLE: [(u16, u8); 30] = [
     (24_577, 13),
 ];
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Symbol {
     EndOfBlock,
     Code(lz77::Code),
@@ -166,7 +169,7 @@ impl From<lz77::Code> for Symbol {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Encoder {
     literal: huffman::Encoder,
     distance: huffman::Encoder,
@@ -188,6 +191,33 @@ impl Encoder {
         }
         Ok(())
     }
+
+    /// Makes an encoder of the canonical Huffman codes determined by the code lengths.
+    pub fn from_bitwidthes(literal: &[u8], distance: &[u8]) -> io::Result<Self> {
+        Ok(Encoder {
+            literal: huffman::EncoderBuilder::from_bitwidthes(literal)?,
+            distance: huffman::EncoderBuilder::from_bitwidthes(distance)?,
+        })
+    }
+
+    /// Returns the length of the code of the literal/length `code` (`0` if it has no code).
+    pub fn literal_code_bitwidth(&self, code: u16) -> u8 {
+        self.literal.bitwidth(code)
+    }
+
+    /// Returns the length of the code of the distance `code` (`0` if it has no code).
+    pub fn distance_code_bitwidth(&self, code: u16) -> u8 {
+        self.distance.bitwidth(code)
+    }
+
+    /// Returns the number of the bits of the Huffman codes of `symbol` (the extra bits are not included).
+    pub fn code_bitwidth(&self, symbol: &Symbol) -> usize {
+        let mut width = usize::from(self.literal.lookup(symbol.code()).width);
+        if let Some((code, _, _)) = symbol.distance() {
+            width += usize::from(self.distance.lookup(u16::from(code)).width);
+        }
+        width
+    }
 }
 
 #[derive(Debug)]
@@ -196,6 +226,9 @@ pub struct Decoder {
     distance: huffman::Decoder,
 }
 impl Decoder {
+    pub fn memory_usage(&self) -> usize {
+        self.literal.memory_usage() + self.distance.memory_usage()
+    }
     #[inline(always)]
     pub fn decode_unchecked<R>(&self, reader: &mut bit::BitReader<R>) -> Symbol
     where
@@ -211,6 +244,31 @@ impl Decoder {
         }
         symbol
     }
+    /// Decodes the following symbols into `literals` while they are literals.
+    ///
+    /// Returns the number of the decoded literals and the first non-literal symbol,
+    /// which is `None` if `literals` has been filled or an error has been recorded in `reader`.
+    #[inline(always)]
+    pub fn decode_literals_unchecked<R>(
+        &self,
+        reader: &mut bit::BitReader<R>,
+        literals: &mut [u8],
+    ) -> (usize, Option<Symbol>)
+    where
+        R: io::Read,
+    {
+        for (i, literal) in literals.iter_mut().enumerate() {
+            let symbol = self.decode_unchecked(reader);
+            if reader.has_last_error() {
+                return (i, None);
+            }
+            match symbol {
+                Symbol::Code(lz77::Code::Literal(b)) => *literal = b,
+                _ => return (i, Some(symbol)),
+            }
+        }
+        (literals.len(), None)
+    }
     #[inline(always)]
     fn decode_literal_or_length<R>(&self, reader: &mut bit::BitReader<R>) -> Symbol
     where
@@ -221,11 +279,14 @@ impl Decoder {
             0..=255 => Symbol::Code(lz77::Code::Literal(decoded as u8)),
             256 => Symbol::EndOfBlock,
             286 | 287 => {
-                #[cfg(not(feature = "no_std"))]
-                let message = format!("The value {decoded} must not occur in compressed data");
-                #[cfg(feature = "no_std")]
-                let message = "The value(s) [286, 287] must not occur in compressed data";
-                reader.set_last_error(io::Error::new(io::ErrorKind::InvalidData, message));
+                reader.set_last_error(
+                    Error::new(
+                        ErrorKind::InvalidHuffmanCode,
+                        "The value must not occur in compressed data",
+                    )
+                    .with_value(decoded)
+                    .into(),
+                );
                 Symbol::EndOfBlock // dummy value
             }
             length_code => {
@@ -243,10 +304,22 @@ impl Decoder {
     where
         R: io::Read,
     {
-        let decoded = self.distance.decode_unchecked(reader) as usize;
-        let (base, extra_bits) = DISTANCE_TABLE[decoded];
-        let extra = reader.read_bits_unchecked(extra_bits);
-        base + extra
+        let decoded = self.distance.decode_unchecked(reader);
+        if let Some(&(base, extra_bits)) = DISTANCE_TABLE.get(decoded as usize) {
+            let extra = reader.read_bits_unchecked(extra_bits);
+            base + extra
+        } else {
+            // The reserved distance codes 30 and 31
+            reader.set_last_error(
+                Error::new(
+                    ErrorKind::InvalidHuffmanCode,
+                    "The value must not occur in compressed data",
+                )
+                .with_value(decoded)
+                .into(),
+            );
+            0 // dummy value
+        }
     }
 }
 
@@ -260,7 +333,8 @@ pub trait HuffmanCodec {
         R: io::Read;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FixedHuffmanCodec;
 impl HuffmanCodec for FixedHuffmanCodec {
     #[allow(unused_variables)]
@@ -298,7 +372,7 @@ impl HuffmanCodec for FixedHuffmanCodec {
     where
         R: io::Read,
     {
-        let mut literal_builder = huffman::DecoderBuilder::new(9, None, Some(END_OF_BLOCK));
+        let mut literal_builder = huffman::DecoderBuilder::new(9, None, Some(END_OF_BLOCK))?;
         for &(bitwidth, ref symbols, code_base) in &FIXED_LITERAL_OR_LENGTH_CODE_TABLE {
             for (code, symbol) in symbols
                 .clone()
@@ -310,7 +384,7 @@ impl HuffmanCodec for FixedHuffmanCodec {
         }
 
         let mut distance_builder =
-            huffman::DecoderBuilder::new(5, literal_builder.safely_peek_bitwidth(), None);
+            huffman::DecoderBuilder::new(5, literal_builder.safely_peek_bitwidth(), None)?;
         for i in 0..30 {
             distance_builder.set_mapping(i, huffman::Code::new(5, i))?;
         }
@@ -322,7 +396,8 @@ impl HuffmanCodec for FixedHuffmanCodec {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DynamicHuffmanCodec;
 impl HuffmanCodec for DynamicHuffmanCodec {
     fn build(&self, symbols: &[Symbol]) -> io::Result<Encoder> {
@@ -353,22 +428,37 @@ impl HuffmanCodec for DynamicHuffmanCodec {
     {
         let literal_code_count = cmp::max(257, codec.literal.used_max_symbol().unwrap_or(0) + 1);
         let distance_code_count = cmp::max(1, codec.distance.used_max_symbol().unwrap_or(0) + 1);
-        let codes = build_bitwidth_codes(codec, literal_code_count, distance_code_count);
+        let lengths = (0..literal_code_count)
+            .map(|x| codec.literal.lookup(x).width)
+            .chain((0..distance_code_count).map(|x| codec.distance.lookup(x).width))
+            .collect::<Vec<_>>();
 
-        let mut code_counts = [0; 19];
-        for x in &codes {
-            code_counts[x.0 as usize] += 1;
+        // Starts from the greedy run-length encoding (like zlib), and then refines it
+        // by the optimal encoding for the code lengths of the previous one until the header no longer shrinks.
+        let mut codes = build_bitwidth_codes(&lengths, usize::from(literal_code_count));
+        let mut header = BitwidthCodeHeader::new(&codes)?;
+        for _ in 0..MAX_BITWIDTH_CODE_REFINEMENTS {
+            let mut costs = [0; 19];
+            for (i, cost) in costs.iter_mut().enumerate() {
+                *cost = match header.encoder.bitwidth(i as u16) {
+                    0 => UNUSED_BITWIDTH_CODE_COST,
+                    width => usize::from(width),
+                };
+            }
+            let candidate = optimal_bitwidth_codes(&lengths, &costs);
+            let candidate_header = BitwidthCodeHeader::new(&candidate)?;
+            if candidate_header.bit_count(&candidate) >= header.bit_count(&codes) {
+                break;
+            }
+            codes = candidate;
+            header = candidate_header;
         }
-        let bitwidth_encoder = huffman::EncoderBuilder::from_frequencies(&code_counts, 7)?;
+        let BitwidthCodeHeader {
+            encoder: bitwidth_encoder,
+            code_counts,
+            code_count: bitwidth_code_count,
+        } = header;
 
-        let bitwidth_code_count = cmp::max(
-            4,
-            BITWIDTH_CODE_ORDER
-                .iter()
-                .rev()
-                .position(|&i| code_counts[i] != 0 && bitwidth_encoder.lookup(i as u16).width > 0)
-                .map_or(0, |trailing_zeros| 19 - trailing_zeros),
-        ) as u16;
         writer.write_bits(5, literal_code_count - 257)?;
         writer.write_bits(5, distance_code_count - 1)?;
         writer.write_bits(4, bitwidth_code_count - 4)?;
@@ -395,72 +485,118 @@ impl HuffmanCodec for DynamicHuffmanCodec {
     where
         R: io::Read,
     {
-        let literal_code_count = reader.read_bits(5)? + 257;
-        let distance_code_count = reader.read_bits(5)? + 1;
-        let bitwidth_code_count = reader.read_bits(4)? + 4;
-
-        if distance_code_count as usize > MAX_DISTANCE_CODE_COUNT {
-            #[cfg(not(feature = "no_std"))]
-            let message = format!(
-                "The value of HDIST is too big: max={MAX_DISTANCE_CODE_COUNT}, actual={distance_code_count}"
-            );
-            #[cfg(feature = "no_std")]
-            let message = "The value of HDIST is too big: max={MAX_DISTANCE_CODE_COUNT}";
-            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
-        }
+        load_dynamic_huffman_codes(reader, false)
+    }
+}
 
-        let mut bitwidth_code_bitwidthes = [0; 19];
-        for &i in BITWIDTH_CODE_ORDER
-            .iter()
-            .take(bitwidth_code_count as usize)
-        {
-            bitwidth_code_bitwidthes[i] = reader.read_bits(3)? as u8;
-        }
-        let bitwidth_decoder =
-            huffman::DecoderBuilder::from_bitwidthes(&bitwidth_code_bitwidthes, Some(1), None)?;
+/// The same as `DynamicHuffmanCodec` except that the loading (decoding) of a header
+/// which no conforming encoder produces is rejected as early as possible.
+#[derive(Debug, Clone)]
+pub struct StrictDynamicHuffmanCodec;
+impl HuffmanCodec for StrictDynamicHuffmanCodec {
+    fn build(&self, symbols: &[Symbol]) -> io::Result<Encoder> {
+        DynamicHuffmanCodec.build(symbols)
+    }
+    fn save<W>(&self, writer: &mut bit::BitWriter<W>, codec: &Encoder) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        DynamicHuffmanCodec.save(writer, codec)
+    }
+    fn load<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<Decoder>
+    where
+        R: io::Read,
+    {
+        load_dynamic_huffman_codes(reader, true)
+    }
+}
 
-        let mut literal_code_bitwidthes = Vec::with_capacity(literal_code_count as usize);
-        while literal_code_bitwidthes.len() < literal_code_count as usize {
-            let c = bitwidth_decoder.decode(reader)?;
-            let last = literal_code_bitwidthes.last().cloned();
-            literal_code_bitwidthes.extend(load_bitwidthes(reader, c, last)?);
-        }
+fn load_dynamic_huffman_codes<R>(
+    reader: &mut bit::BitReader<R>,
+    strict: bool,
+) -> io::Result<Decoder>
+where
+    R: io::Read,
+{
+    let literal_code_count = reader.read_bits(5)? + 257;
+    let distance_code_count = reader.read_bits(5)? + 1;
+    let bitwidth_code_count = reader.read_bits(4)? + 4;
 
-        let mut distance_code_bitwidthes = literal_code_bitwidthes
-            .drain(literal_code_count as usize..)
-            .collect::<Vec<_>>();
-        while distance_code_bitwidthes.len() < distance_code_count as usize {
-            let c = bitwidth_decoder.decode(reader)?;
-            let last = distance_code_bitwidthes
-                .last()
-                .cloned()
-                .or_else(|| literal_code_bitwidthes.last().cloned());
-            distance_code_bitwidthes.extend(load_bitwidthes(reader, c, last)?);
-        }
-        if distance_code_bitwidthes.len() > distance_code_count as usize {
-            #[cfg(not(feature = "no_std"))]
-            let message = format!(
-                "The length of `distance_code_bitwidthes` is too large: actual={}, expected={}",
-                distance_code_bitwidthes.len(),
-                distance_code_count
-            );
-            #[cfg(feature = "no_std")]
-            let message = "The length of `distance_code_bitwidthes` is too large";
-            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
-        }
-
-        let literal = huffman::DecoderBuilder::from_bitwidthes(
-            &literal_code_bitwidthes,
-            None,
-            Some(END_OF_BLOCK),
-        )?;
-        let distance = huffman::DecoderBuilder::from_bitwidthes(
-            &distance_code_bitwidthes,
-            Some(literal.safely_peek_bitwidth()),
-            None,
-        )?;
-        Ok(Decoder { literal, distance })
+    if strict && literal_code_count as usize > MAX_LITERAL_CODE_COUNT {
+        return Err(
+            Error::new(ErrorKind::LimitExceeded, "The value of HLIT is too big")
+                .with_value(literal_code_count)
+                .with_expected(MAX_LITERAL_CODE_COUNT as u64)
+                .into(),
+        );
+    }
+
+    if distance_code_count as usize > MAX_DISTANCE_CODE_COUNT {
+        return Err(
+            Error::new(ErrorKind::InvalidHeader, "The value of HDIST is too big")
+                .with_value(distance_code_count)
+                .with_expected(MAX_DISTANCE_CODE_COUNT as u64)
+                .into(),
+        );
+    }
+
+    let mut bitwidth_code_bitwidthes = [0; 19];
+    for &i in BITWIDTH_CODE_ORDER
+        .iter()
+        .take(bitwidth_code_count as usize)
+    {
+        bitwidth_code_bitwidthes[i] = reader.read_bits(3)? as u8;
+    }
+    let bitwidth_decoder =
+        huffman::DecoderBuilder::from_bitwidthes(&bitwidth_code_bitwidthes, Some(1), None)?;
+
+    let mut literal_code_bitwidthes = Vec::with_capacity(literal_code_count as usize);
+    while literal_code_bitwidthes.len() < literal_code_count as usize {
+        let c = bitwidth_decoder.decode(reader)?;
+        let last = literal_code_bitwidthes.last().cloned();
+        literal_code_bitwidthes.extend(load_bitwidthes(reader, c, last)?);
+    }
+
+    if strict && literal_code_bitwidthes[usize::from(END_OF_BLOCK)] == 0 {
+        return Err(Error::new(
+            ErrorKind::LimitExceeded,
+            "The end-of-block code has no Huffman code",
+        )
+        .into());
+    }
+
+    let mut distance_code_bitwidthes = literal_code_bitwidthes
+        .drain(literal_code_count as usize..)
+        .collect::<Vec<_>>();
+    while distance_code_bitwidthes.len() < distance_code_count as usize {
+        let c = bitwidth_decoder.decode(reader)?;
+        let last = distance_code_bitwidthes
+            .last()
+            .cloned()
+            .or_else(|| literal_code_bitwidthes.last().cloned());
+        distance_code_bitwidthes.extend(load_bitwidthes(reader, c, last)?);
+    }
+    if distance_code_bitwidthes.len() > distance_code_count as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidHuffmanCode,
+            "The length of `distance_code_bitwidthes` is too large",
+        )
+        .with_value(distance_code_bitwidthes.len() as u64)
+        .with_expected(distance_code_count)
+        .into());
     }
+
+    let literal = huffman::DecoderBuilder::from_bitwidthes(
+        &literal_code_bitwidthes,
+        None,
+        Some(END_OF_BLOCK),
+    )?;
+    let distance = huffman::DecoderBuilder::from_bitwidthes(
+        &distance_code_bitwidthes,
+        Some(literal.safely_peek_bitwidth()),
+        None,
+    )?;
+    Ok(Decoder { literal, distance })
 }
 
 fn load_bitwidthes<R>(
@@ -475,42 +611,82 @@ where
         0..=15 => Box::new(iter::once(code as u8)),
         16 => {
             let count = reader.read_bits(2)? + 3;
-            let last = last.ok_or_else(|| invalid_data_error!("No preceding value"))?;
-            Box::new(iter::repeat(last).take(count as usize))
+            let last = last
+                .ok_or_else(|| Error::new(ErrorKind::InvalidHuffmanCode, "No preceding value"))?;
+            Box::new((0..count).map(move |_| last))
         }
         17 => {
             let zeros = reader.read_bits(3)? + 3;
-            Box::new(iter::repeat(0).take(zeros as usize))
+            Box::new((0..zeros).map(|_| 0))
         }
         18 => {
             let zeros = reader.read_bits(7)? + 11;
-            Box::new(iter::repeat(0).take(zeros as usize))
+            Box::new((0..zeros).map(|_| 0))
         }
         _ => unreachable!(),
     })
 }
 
-fn build_bitwidth_codes(
-    codec: &Encoder,
-    literal_code_count: u16,
-    distance_code_count: u16,
-) -> Vec<(u8, u8, u8)> {
+/// The maximum number of the refinements of the run-length encoding of the code lengths in a dynamic header.
+const MAX_BITWIDTH_CODE_REFINEMENTS: usize = 3;
+
+/// The assumed cost (in bits) of a code length code which is not used by the current encoding.
+const UNUSED_BITWIDTH_CODE_COST: usize = 7;
+
+/// The Huffman codes of the code length codes in a dynamic header.
+struct BitwidthCodeHeader {
+    encoder: huffman::Encoder,
+    code_counts: [usize; 19],
+
+    // HCLEN + 4
+    code_count: u16,
+}
+impl BitwidthCodeHeader {
+    fn new(codes: &[(u8, u8, u8)]) -> io::Result<Self> {
+        let mut code_counts = [0; 19];
+        for x in codes {
+            code_counts[x.0 as usize] += 1;
+        }
+        let encoder = huffman::EncoderBuilder::from_frequencies(&code_counts, 7)?;
+        let code_count = cmp::max(
+            4,
+            BITWIDTH_CODE_ORDER
+                .iter()
+                .rev()
+                .position(|&i| code_counts[i] != 0 && encoder.lookup(i as u16).width > 0)
+                .map_or(0, |trailing_zeros| 19 - trailing_zeros),
+        ) as u16;
+        Ok(BitwidthCodeHeader {
+            encoder,
+            code_counts,
+            code_count,
+        })
+    }
+
+    /// Returns the number of the bits of the header (after HLIT and HDIST) which encodes `codes`.
+    fn bit_count(&self, codes: &[(u8, u8, u8)]) -> usize {
+        let codes_bits = codes
+            .iter()
+            .map(|&(code, bits, _)| usize::from(self.encoder.bitwidth(u16::from(code)) + bits))
+            .sum::<usize>();
+        4 + 3 * usize::from(self.code_count) + codes_bits
+    }
+}
+
+/// Run-length encodes `lengths` (the code lengths of the literal/length codes followed by the ones of the distance codes)
+/// greedily, without crossing the boundary of the two tables at `literal_code_count`.
+fn build_bitwidth_codes(lengths: &[u8], literal_code_count: usize) -> Vec<(u8, u8, u8)> {
     struct RunLength {
         value: u8,
         count: usize,
     }
 
     let mut run_lens: Vec<RunLength> = Vec::new();
-    for &(e, size) in &[
-        (&codec.literal, literal_code_count),
-        (&codec.distance, distance_code_count),
-    ] {
-        for (i, c) in (0..size).map(|x| e.lookup(x).width).enumerate() {
-            if i > 0 && run_lens.last().map_or(false, |s| s.value == c) {
-                run_lens.last_mut().unwrap().count += 1;
-            } else {
-                run_lens.push(RunLength { value: c, count: 1 })
-            }
+    for (i, &c) in lengths.iter().enumerate() {
+        if i != 0 && i != literal_code_count && matches!(run_lens.last(), Some(s) if s.value == c) {
+            run_lens.last_mut().unwrap().count += 1;
+        } else {
+            run_lens.push(RunLength { value: c, count: 1 })
         }
     }
 
@@ -545,3 +721,141 @@ fn build_bitwidth_codes(
     }
     codes
 }
+
+/// Run-length encodes `lengths` with the fewest bits, where `costs[i]` is the number of the bits of the code length code `i`.
+///
+/// Unlike [`build_bitwidth_codes`], runs may cross the boundary of the literal/length and distance tables,
+/// which RFC 1951 allows (they form a single sequence of code lengths).
+fn optimal_bitwidth_codes(lengths: &[u8], costs: &[usize; 19]) -> Vec<(u8, u8, u8)> {
+    // `runs[i]` is the number of the consecutive lengths which are equal to `lengths[i]` (up to 138)
+    let mut runs = vec![1; lengths.len()];
+    for i in (0..lengths.len().saturating_sub(1)).rev() {
+        if lengths[i] == lengths[i + 1] {
+            runs[i] = cmp::min(138, runs[i + 1] + 1);
+        }
+    }
+
+    // `best[i]` is the cost of encoding `lengths[i..]` and the first code of the encoding
+    let mut best = vec![(0, (0, 0, 0), 1); lengths.len() + 1];
+    for i in (0..lengths.len()).rev() {
+        let length = lengths[i];
+        let mut choice = (
+            costs[usize::from(length)] + best[i + 1].0,
+            (length, 0, 0),
+            1,
+        );
+        let mut consider = |code: u8, bits: u8, min: usize, max: usize| {
+            for run in min..=cmp::min(max, runs[i]) {
+                let cost = costs[usize::from(code)] + usize::from(bits) + best[i + run].0;
+                if cost < choice.0 {
+                    choice = (cost, (code, bits, (run - min) as u8), run);
+                }
+            }
+        };
+        if length == 0 {
+            consider(17, 3, 3, 10);
+            consider(18, 7, 11, 138);
+        }
+        if i > 0 && lengths[i - 1] == length {
+            consider(16, 2, 3, 6);
+        }
+        best[i] = choice;
+    }
+
+    let mut codes = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let (_, code, run) = best[i];
+        codes.push(code);
+        i += run;
+    }
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dynamic_header_works() {
+        let mut literal_counts = [0; 286];
+        for (i, count) in literal_counts.iter_mut().enumerate() {
+            *count = match i {
+                0..=31 => 0,
+                32..=126 => (i * 7) % 50 + 1,
+                256 => 1,
+                260..=270 => 3,
+                _ => 0,
+            };
+        }
+        let mut distance_counts = [0; 30];
+        distance_counts[3..10].copy_from_slice(&[5, 5, 5, 5, 5, 5, 1]);
+        let codec = Encoder {
+            literal: huffman::EncoderBuilder::from_frequencies(&literal_counts, 15).unwrap(),
+            distance: huffman::EncoderBuilder::from_frequencies(&distance_counts, 15).unwrap(),
+        };
+
+        let mut writer = bit::BitWriter::new(Vec::new());
+        DynamicHuffmanCodec.save(&mut writer, &codec).unwrap();
+        for symbol in (0..286).filter(|&s| codec.literal.bitwidth(s) > 0) {
+            codec.literal.encode(&mut writer, symbol).unwrap();
+        }
+        for symbol in (0..30).filter(|&s| codec.distance.bitwidth(s) > 0) {
+            codec.distance.encode(&mut writer, symbol).unwrap();
+        }
+        writer.flush().unwrap();
+        let encoded = writer.into_inner();
+
+        let mut reader = bit::BitReader::new(&encoded[..]);
+        let decoder = StrictDynamicHuffmanCodec.load(&mut reader).unwrap();
+        for symbol in (0..286).filter(|&s| codec.literal.bitwidth(s) > 0) {
+            assert_eq!(decoder.literal.decode(&mut reader).unwrap(), symbol);
+        }
+        for symbol in (0..30).filter(|&s| codec.distance.bitwidth(s) > 0) {
+            assert_eq!(decoder.distance.decode(&mut reader).unwrap(), symbol);
+        }
+    }
+
+    #[test]
+    fn optimal_bitwidth_codes_work() {
+        let lengths = [
+            &[0; 140][..],
+            &[8; 9],
+            &[7, 7, 0, 0, 0, 5, 5, 5, 5],
+            &[0; 5],
+            &[6; 4],
+        ]
+        .concat();
+        let costs = [4, 4, 4, 4, 4, 4, 4, 3, 3, 4, 4, 4, 4, 4, 4, 4, 2, 3, 5];
+        let codes = optimal_bitwidth_codes(&lengths, &costs);
+
+        // Decodes the codes
+        let mut decoded = Vec::new();
+        for &(code, bits, extra) in &codes {
+            let (value, count) = match code {
+                16 => (*decoded.last().unwrap(), 3 + extra),
+                17 => (0, 3 + extra),
+                18 => (0, 11 + extra),
+                _ => (code, 1),
+            };
+            assert_eq!(
+                bits,
+                [2, 3, 7]
+                    .get(code.wrapping_sub(16) as usize)
+                    .map_or(0, |&b| b)
+            );
+            decoded.extend(iter::repeat_n(value, usize::from(count)));
+        }
+        assert_eq!(decoded, lengths);
+
+        // No worse than the greedy encoding
+        let cost = |codes: &[(u8, u8, u8)]| {
+            codes
+                .iter()
+                .map(|&(code, bits, _)| costs[usize::from(code)] + usize::from(bits))
+                .sum::<usize>()
+        };
+        let greedy = build_bitwidth_codes(&lengths, lengths.len());
+        assert!(cost(&codes) < cost(&greedy));
+    }
+}
@@ -0,0 +1,258 @@
+//! Training of preset dictionaries.
+//!
+//! A preset dictionary is a block of data which is (conceptually) placed before each message,
+//! so that the LZ77 encoder can refer to it even in the first bytes of the message.
+//! It significantly improves the compression ratio of small messages which share common substrings
+//! (e.g., the field names of JSON messages of a protocol).
+//!
+//! Note that the encoders and decoders of this crate do not accept preset dictionaries yet,
+//! so the trained dictionary is intended to be used with an implementation which supports them
+//! (e.g., `deflateSetDictionary` and `inflateSetDictionary` of zlib).
+//!
+//! # Examples
+//! ```
+//! use libflate::dictionary;
+//!
+//! let samples = (0..100)
+//!     .map(|i| format!(r#"{{"id":{},"status":"ok","message":"hello"}}"#, i))
+//!     .collect::<Vec<_>>();
+//!
+//! let dict = dictionary::train(&samples, 1024);
+//! assert!(dict.len() <= 1024);
+//! assert!(dict.windows(8).any(|w| w == br#""status""#));
+//! ```
+use crate::lz77;
+#[cfg(feature = "no_std")]
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+#[cfg(not(feature = "no_std"))]
+use std::collections::BTreeMap;
+
+/// The length of the substrings whose frequencies are counted.
+const KGRAM_SIZE: usize = 6;
+
+/// The length of a segment (a substring of a sample) which is added to the dictionary at once.
+const SEGMENT_SIZE: usize = 64;
+
+/// The maximum number of passes over the samples to fill the dictionary.
+const MAX_PASSES: usize = 4;
+
+/// Builds a preset dictionary of at most `max_size` bytes from `samples`.
+///
+/// `max_size` is clamped to [`lz77::MAX_WINDOW_SIZE`] (32 KiB) because a DEFLATE stream can not refer beyond it.
+///
+/// The dictionary consists of the segments of the samples which contain the most frequent substrings
+/// (a substring is counted at most once per sample, so the content which is common to many messages is preferred).
+/// The segments are ordered by their value in ascending order,
+/// so the most valuable content is placed last, where it can be referred by the shortest distances.
+///
+/// Returns an empty dictionary if the samples have nothing in common.
+pub fn train<S>(samples: &[S], max_size: usize) -> Vec<u8>
+where
+    S: AsRef<[u8]>,
+{
+    let max_size = core::cmp::min(max_size, usize::from(lz77::MAX_WINDOW_SIZE));
+    let samples = samples
+        .iter()
+        .map(|s| s.as_ref())
+        .filter(|s| s.len() >= KGRAM_SIZE)
+        .collect::<Vec<_>>();
+    if max_size == 0 || samples.is_empty() {
+        return Vec::new();
+    }
+
+    // Assigns an ID to each distinct k-gram, and counts the samples in which it appears.
+    let mut ids = BTreeMap::new();
+    let mut frequencies = Vec::new();
+    let mut last_samples = Vec::new();
+    let kgram_ids = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            sample
+                .windows(KGRAM_SIZE)
+                .map(|kgram| {
+                    let id = *ids.entry(kgram).or_insert_with(|| {
+                        frequencies.push(0);
+                        last_samples.push(usize::MAX);
+                        frequencies.len() - 1
+                    });
+                    if last_samples[id] != i {
+                        last_samples[id] = i;
+                        frequencies[id] += 1;
+                    }
+                    id
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // A k-gram which appears in only one sample is not worth being in the dictionary.
+    if samples.len() > 1 {
+        for f in &mut frequencies {
+            if *f == 1 {
+                *f = 0;
+            }
+        }
+    }
+
+    let total_positions = kgram_ids.iter().map(|x| x.len()).sum::<usize>();
+    let epochs = core::cmp::max(1, max_size / SEGMENT_SIZE);
+    let epoch_size = core::cmp::max(1, total_positions / epochs);
+
+    let mut counts = vec![0u32; frequencies.len()];
+    let mut segments = Vec::new();
+    let mut dict_size = 0;
+    for _ in 0..MAX_PASSES {
+        let mut progressed = false;
+        let mut epoch_start = 0;
+        while epoch_start < total_positions && dict_size < max_size {
+            let epoch_end = core::cmp::min(epoch_start + epoch_size, total_positions);
+            let best = find_best_segment(
+                &kgram_ids,
+                &frequencies,
+                &mut counts,
+                epoch_start..epoch_end,
+            );
+            epoch_start = epoch_end;
+
+            let (score, sample, start, end) = match best {
+                Some(best) => best,
+                None => continue,
+            };
+            for &id in &kgram_ids[sample][start..end] {
+                frequencies[id] = 0;
+            }
+            let segment = &samples[sample][start..end + KGRAM_SIZE - 1];
+            dict_size += segment.len();
+            segments.push((score, segment));
+            progressed = true;
+        }
+        if !progressed || dict_size >= max_size {
+            break;
+        }
+    }
+
+    // The most valuable segments are placed last (and survive the truncation).
+    segments.sort_by_key(|&(score, _)| score);
+    let mut dict = segments
+        .into_iter()
+        .flat_map(|(_, segment)| segment.iter().copied())
+        .collect::<Vec<_>>();
+    if dict.len() > max_size {
+        dict.drain(..dict.len() - max_size);
+    }
+    dict
+}
+
+/// Finds the segment which has the highest score in the given range of (global) k-gram positions.
+///
+/// The score of a segment is the sum of the frequencies of the distinct k-grams in it.
+/// Returns `(score, sample index, start, end)`, where `start..end` is the range of the k-gram positions of the segment.
+fn find_best_segment(
+    kgram_ids: &[Vec<usize>],
+    frequencies: &[u32],
+    counts: &mut [u32],
+    range: core::ops::Range<usize>,
+) -> Option<(u64, usize, usize, usize)> {
+    let window = SEGMENT_SIZE - KGRAM_SIZE + 1;
+    let mut best: Option<(u64, usize, usize, usize)> = None;
+    let mut offset = 0;
+    for (sample, ids) in kgram_ids.iter().enumerate() {
+        let sample_range = offset..offset + ids.len();
+        offset += ids.len();
+        let start = core::cmp::max(range.start, sample_range.start) - sample_range.start;
+        let end = core::cmp::min(range.end, sample_range.end);
+        if end <= sample_range.start || start >= ids.len() {
+            continue;
+        }
+        let end = end - sample_range.start;
+
+        // Slides the window over the positions `start..end` (the window itself may extend beyond `end`).
+        let mut score = 0u64;
+        let mut head = start;
+        for tail in start..end {
+            while head < ids.len() && head < tail + window {
+                let id = ids[head];
+                if counts[id] == 0 {
+                    score += u64::from(frequencies[id]);
+                }
+                counts[id] += 1;
+                head += 1;
+            }
+            if score > 0 && best.is_none_or(|b| score > b.0) {
+                best = Some((score, sample, tail, head));
+            }
+            let id = ids[tail];
+            counts[id] -= 1;
+            if counts[id] == 0 {
+                score -= u64::from(frequencies[id]);
+            }
+        }
+        for &id in &ids[end..head] {
+            counts[id] -= 1;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deflate;
+    #[cfg(feature = "no_std")]
+    use alloc::{format, string::String};
+    #[cfg(feature = "no_std")]
+    use core2::io::Write;
+    #[cfg(not(feature = "no_std"))]
+    use std::io::Write;
+
+    fn samples() -> Vec<String> {
+        (0..200)
+            .map(|i| {
+                format!(
+                    r#"{{"request_id":{},"user":"user{}","status":"success","items":[{}]}}"#,
+                    i * 7919 % 1000,
+                    i % 13,
+                    i % 5
+                )
+            })
+            .collect()
+    }
+
+    fn compressed_size(prefix: &[u8], message: &[u8]) -> usize {
+        let mut encoder = deflate::Encoder::new(Vec::new());
+        encoder.write_all(prefix).unwrap();
+        encoder.write_all(message).unwrap();
+        encoder.finish().into_result().unwrap().len()
+    }
+
+    #[test]
+    fn train_works() {
+        let samples = samples();
+        let dict = train(&samples, 256);
+        assert!(!dict.is_empty());
+        assert!(dict.len() <= 256);
+        assert!(dict.windows(9).any(|w| w == br#""success""#));
+
+        // The dictionary reduces the (marginal) size of a new message.
+        let message = br#"{"request_id":12345,"user":"user99","status":"success","items":[3]}"#;
+        let with_dict = compressed_size(&dict, message) - compressed_size(&dict, b"");
+        let without_dict = compressed_size(b"", message);
+        assert!(with_dict < without_dict, "{} {}", with_dict, without_dict);
+    }
+
+    #[test]
+    fn size_is_limited() {
+        let samples = samples();
+        for &max_size in &[0, 1, 63, 64, 1000, 100_000] {
+            let dict = train(&samples, max_size);
+            assert!(dict.len() <= max_size, "{} {}", dict.len(), max_size);
+        }
+    }
+
+    #[test]
+    fn unrelated_samples_yield_empty_dictionary() {
+        assert!(train::<&[u8]>(&[], 1024).is_empty());
+        assert!(train(&[b"abcdefgh", b"ijklmnop"], 1024).is_empty());
+    }
+}
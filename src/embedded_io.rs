@@ -0,0 +1,400 @@
+//! Implementations that work with the blocking I/O traits of [embedded-io].
+//!
+//! [`Decoder`] implements `embedded_io::Read` and [`Encoder`] implements `embedded_io::Write`,
+//! so compressed data can be streamed over the I/O HAL of a `no_std` target without adapters.
+//!
+//! Internally, they are thin wrappers of the push-style coders in the [`push`] module
+//! (like the asynchronous coders of the `tokio` module).
+//!
+//! # NOTICE
+//!
+//! - This module is available only if the `embedded-io` feature is enabled.
+//!   It works with and without the `no_std` feature.
+//! - [`Encoder::finish`] must be called to write the trailer of a stream.
+//!
+//! # Examples
+//! ```
+//! use embedded_io::{Read, Write};
+//! use libflate::embedded_io::{Decoder, Encoder};
+//! use libflate::push::Format;
+//!
+//! // Encoding
+//! let mut encoder = Encoder::new(Vec::new(), Format::Gzip);
+//! encoder.write_all(b"Hello World!").unwrap();
+//! let encoded_data = encoder.finish().unwrap();
+//!
+//! // Decoding
+//! let mut decoder = Decoder::new(&encoded_data[..], Format::Gzip);
+//! let mut decoded_data = [0; 12];
+//! decoder.read_exact(&mut decoded_data).unwrap();
+//!
+//! assert_eq!(&decoded_data, b"Hello World!");
+//! ```
+//!
+//! [embedded-io]: https://docs.rs/embedded-io
+use crate::deflate;
+use crate::lz77;
+use crate::push::{self, Flush, Format};
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, vec};
+#[cfg(feature = "no_std")]
+use core::fmt;
+#[cfg(feature = "no_std")]
+use core2::io;
+#[cfg(not(feature = "no_std"))]
+use std::{fmt, io};
+
+/// The error type of the coders in this module.
+#[derive(Debug)]
+pub enum Error<E> {
+    /// An error reported by the inner stream.
+    Inner(E),
+
+    /// An error of the compression or decompression (e.g., the compressed data are malformed).
+    Stream(io::Error),
+}
+impl<E: embedded_io::Error> embedded_io::Error for Error<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::Inner(e) => e.kind(),
+            Error::Stream(e) => match e.kind() {
+                io::ErrorKind::InvalidData => embedded_io::ErrorKind::InvalidData,
+                io::ErrorKind::InvalidInput => embedded_io::ErrorKind::InvalidInput,
+                io::ErrorKind::WriteZero => embedded_io::ErrorKind::WriteZero,
+                _ => embedded_io::ErrorKind::Other,
+            },
+        }
+    }
+}
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Inner(e) => write!(f, "Inner stream error: {:?}", e),
+            Error::Stream(e) => write!(f, "{}", e),
+        }
+    }
+}
+#[cfg(not(feature = "no_std"))]
+impl<E: fmt::Debug> std::error::Error for Error<E> {}
+
+/// Decoder which reads compressed data from an `embedded_io::Read` and implements `embedded_io::Read`.
+#[derive(Debug)]
+pub struct Decoder<R> {
+    inner: R,
+    decoder: push::Decoder,
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    eof: bool,
+}
+impl<R> Decoder<R>
+where
+    R: embedded_io::Read,
+{
+    /// Makes a new decoder instance for the stream of `format`.
+    ///
+    /// The compressed data are read from `inner` in chunks of up to
+    /// [`deflate::DEFAULT_BUFFER_SIZE`] bytes.
+    pub fn new(inner: R, format: Format) -> Self {
+        Self::with_buffer_size(inner, format, deflate::DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Makes a new decoder instance whose input buffer is `size` bytes (at least `1`).
+    ///
+    /// A small buffer is suitable for memory-constrained targets.
+    pub fn with_buffer_size(inner: R, format: Format, size: usize) -> Self {
+        Decoder {
+            inner,
+            decoder: push::Decoder::new(format),
+            buf: vec![0; size.max(1)].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            eof: false,
+        }
+    }
+}
+impl<R> Decoder<R> {
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Decoder`, returning the underlying stream.
+    ///
+    /// Note that the bytes which have been read into the input buffer but not decoded yet will be lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+impl<R> embedded_io::ErrorType for Decoder<R>
+where
+    R: embedded_io::ErrorType,
+{
+    type Error = Error<R::Error>;
+}
+impl<R> embedded_io::Read for Decoder<R>
+where
+    R: embedded_io::Read,
+{
+    fn read(&mut self, out: &mut [u8]) -> Result<usize, Self::Error> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        while !self.decoder.is_finished() {
+            if self.start == self.end && !self.eof {
+                self.end = self.inner.read(&mut self.buf).map_err(Error::Inner)?;
+                self.start = 0;
+                self.eof = self.end == 0;
+            }
+
+            let progress = self
+                .decoder
+                .decode(&self.buf[self.start..self.end], out)
+                .map_err(Error::Stream)?;
+            self.start += progress.consumed;
+            if progress.produced > 0 {
+                return Ok(progress.produced);
+            }
+            if self.eof && self.start == self.end && !self.decoder.is_finished() {
+                return Err(Error::Stream(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Unexpected end of the compressed stream",
+                )));
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Encoder which writes compressed data to an `embedded_io::Write` and implements `embedded_io::Write`.
+#[derive(Debug)]
+pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
+    inner: W,
+    encoder: push::Encoder<E>,
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+}
+impl<W> Encoder<W, lz77::DefaultLz77Encoder>
+where
+    W: embedded_io::Write,
+{
+    /// Makes a new encoder instance for `format` with the default options.
+    pub fn new(inner: W, format: Format) -> Self {
+        Self::with_encoder(inner, push::Encoder::new(format))
+    }
+}
+impl<W, E> Encoder<W, E>
+where
+    W: embedded_io::Write,
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new encoder instance which compresses the data with `encoder`.
+    ///
+    /// The compressed data are staged in a buffer of the size specified by the options of `encoder`
+    /// (see [`deflate::EncodeOptions::buffer_size`]).
+    ///
+    /// # Examples
+    /// ```
+    /// use embedded_io::Write;
+    /// use libflate::embedded_io::Encoder;
+    /// use libflate::{push, zlib};
+    ///
+    /// let options = zlib::EncodeOptions::new().fixed_huffman_codes().buffer_size(64);
+    /// let mut encoder = Encoder::with_encoder(Vec::new(), push::Encoder::zlib(options).unwrap());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// assert_eq!(zlib::decompress_to_vec(&encoder.finish().unwrap()).unwrap(), b"Hello World!");
+    /// ```
+    pub fn with_encoder(inner: W, encoder: push::Encoder<E>) -> Self {
+        Encoder {
+            inner,
+            buf: vec![0; encoder.buffer_size().max(1)].into_boxed_slice(),
+            encoder,
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Writes the trailer of the stream, and returns the inner stream.
+    pub fn finish(mut self) -> Result<W, Error<W::Error>> {
+        if !self.encoder.is_finished() {
+            self.encode(&[], Flush::Finish)?;
+        }
+        self.drain()?;
+        self.inner.flush().map_err(Error::Inner)?;
+        Ok(self.inner)
+    }
+
+    /// Writes all the output of `self.encoder` to the inner stream.
+    fn drain(&mut self) -> Result<(), Error<W::Error>> {
+        loop {
+            self.inner
+                .write_all(&self.buf[self.start..self.end])
+                .map_err(Error::Inner)?;
+            let progress = self
+                .encoder
+                .encode(&[], &mut self.buf, Flush::None)
+                .map_err(Error::Stream)?;
+            self.start = 0;
+            self.end = progress.produced;
+            if progress.produced == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn encode(&mut self, buf: &[u8], flush: Flush) -> Result<usize, Error<W::Error>> {
+        loop {
+            self.drain()?;
+            let progress = self
+                .encoder
+                .encode(buf, &mut self.buf, flush)
+                .map_err(Error::Stream)?;
+            self.start = 0;
+            self.end = progress.produced;
+            if progress.consumed > 0 || progress.produced == 0 {
+                return Ok(progress.consumed);
+            }
+        }
+    }
+}
+impl<W, E> Encoder<W, E> {
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Encoder`, returning the underlying stream.
+    ///
+    /// Nothing is written to the inner stream by this method (see [`Encoder::finish`]).
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+impl<W, E> embedded_io::ErrorType for Encoder<W, E>
+where
+    W: embedded_io::ErrorType,
+{
+    type Error = Error<W::Error>;
+}
+impl<W, E> embedded_io::Write for Encoder<W, E>
+where
+    W: embedded_io::Write,
+    E: lz77::Lz77Encode,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if self.encoder.is_finished() {
+            #[cfg(not(feature = "no_std"))]
+            let e = io::Error::other("The encoder has already been finished");
+            #[cfg(feature = "no_std")]
+            let e = io::Error::new(
+                io::ErrorKind::Other,
+                "The encoder has already been finished",
+            );
+            return Err(Error::Stream(e));
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.encode(buf, Flush::None)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if !self.encoder.is_finished() {
+            self.encode(&[], Flush::Sync)?;
+        }
+        self.drain()?;
+        self.inner.flush().map_err(Error::Inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "no_std")]
+    use alloc::vec::Vec;
+    use embedded_io::{Read, Write};
+
+    fn round_trip(format: Format, plain: &[u8], buffer_size: usize) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new(), format);
+        for chunk in plain.chunks(1000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let encoded = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::with_buffer_size(&encoded[..], format, buffer_size);
+        let mut decoded = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let size = decoder.read(&mut buf).unwrap();
+            if size == 0 {
+                break;
+            }
+            decoded.extend_from_slice(&buf[..size]);
+        }
+        decoded
+    }
+
+    #[test]
+    fn round_trip_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 13) as u8 + (i / 777) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            assert_eq!(round_trip(format, &plain, 1), plain);
+            assert_eq!(round_trip(format, &plain, 4096), plain);
+        }
+    }
+
+    #[test]
+    fn flush_makes_data_decodable() {
+        let mut encoder = Encoder::new(Vec::new(), Format::Zlib);
+        encoder.write_all(b"Hello").unwrap();
+        encoder.flush().unwrap();
+
+        let mut decoder = Decoder::new(&encoder.as_inner_ref()[..], Format::Zlib);
+        let mut buf = [0; 5];
+        decoder.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hello");
+    }
+
+    #[test]
+    fn errors_work() {
+        let mut encoder = Encoder::new(Vec::new(), Format::Gzip);
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded = encoder.finish().unwrap();
+
+        let mut decoder = Decoder::new(&encoded[..encoded.len() - 1], Format::Gzip);
+        let mut buf = [0; 64];
+        let error = loop {
+            match decoder.read(&mut buf) {
+                Ok(size) => assert_ne!(size, 0),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(error, Error::Stream(_)));
+
+        // The output buffer is too small
+        let mut output = [0; 8];
+        let mut encoder = Encoder::new(&mut output[..], Format::Gzip);
+        let error = encoder.write_all(b"Hello World!").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Inner(embedded_io::SliceWriteError::Full)
+        ));
+        assert_eq!(
+            embedded_io::Error::kind(&error),
+            embedded_io::ErrorKind::WriteZero
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Structured errors reported by the decoders.
+//!
+//! The decoders of this crate return `io::Error` (to make them usable as `io::Read`),
+//! but the `InvalidData` and `UnexpectedEof` errors detected by themselves wrap an [`Error`]
+//! which describes the failure in a machine readable form.
+//!
+//! # Truncated input
+//!
+//! If the input ends before the end of the compressed stream (in the header, the body or the trailer),
+//! every decoder reports an `io::ErrorKind::UnexpectedEof` error,
+//! whereas `InvalidData` always means that the input is corrupted.
+//! The decoders of the [`non_blocking`](crate::non_blocking) module can also report it as `WouldBlock`
+//! to resume the decoding once more data has arrived
+//! (see [`set_truncation_as_would_block`](crate::non_blocking::deflate::Decoder::set_truncation_as_would_block)).
+//!
+//! # Examples
+//! ```
+//! # #[cfg(not(feature = "no_std"))]
+//! # {
+//! use libflate::error::{Error, ErrorKind};
+//! use libflate::zlib::Decoder;
+//!
+//! // The compression method is not DEFLATE(8)
+//! let data = [0x79, 0x18];
+//! let error = Decoder::new(&data[..]).unwrap_err();
+//!
+//! let error = Error::from_io_error(&error).unwrap();
+//! assert_eq!(error.kind(), ErrorKind::Unsupported);
+//! assert_eq!(error.value(), Some(9));
+//! assert_eq!(error.position(), Some(0));
+//! # }
+//! ```
+#[cfg(feature = "no_std")]
+use core::fmt;
+#[cfg(feature = "no_std")]
+use core2::io;
+#[cfg(not(feature = "no_std"))]
+use std::{fmt, io};
+
+/// The category of an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A field of a header (of a GZIP/ZLIB stream or of a DEFLATE block) has an invalid value.
+    InvalidHeader,
+
+    /// A checksum (e.g., CRC32 of a GZIP stream) does not match the one of the decoded data.
+    ChecksumMismatch,
+
+    /// A Huffman code (or the definition of Huffman codes) in a DEFLATE stream is invalid.
+    InvalidHuffmanCode,
+
+    /// The stream ended before it was expected to end.
+    UnexpectedEof,
+
+    /// The stream uses a feature which is not supported by this crate.
+    Unsupported,
+
+    /// The stream exceeds a limit configured by the decode options
+    /// (e.g., [`gzip::DecodeOptions`](crate::gzip::DecodeOptions)).
+    LimitExceeded,
+
+    /// The processing was cancelled by the check registered with `set_cancellation_check`
+    /// (e.g., [`deflate::Decoder::set_cancellation_check`](crate::deflate::Decoder::set_cancellation_check)).
+    Cancelled,
+
+    /// A buffer could not be allocated (or grown) because the allocator reported a failure.
+    OutOfMemory,
+
+    /// The decoded data exceed the size or the expansion ratio allowed by a [`guard::Guard`](crate::guard::Guard).
+    DecompressionBomb,
+
+    /// The password to decrypt the data is wrong (or the encryption header is corrupted).
+    InvalidPassword,
+}
+impl ErrorKind {
+    fn io_error_kind(self) -> io::ErrorKind {
+        match self {
+            ErrorKind::UnexpectedEof => io::ErrorKind::UnexpectedEof,
+            // Not `Interrupted`, which makes `read_to_end` and the like retry forever
+            ErrorKind::Cancelled => io::ErrorKind::Other,
+            #[cfg(not(feature = "no_std"))]
+            ErrorKind::OutOfMemory => io::ErrorKind::OutOfMemory,
+            #[cfg(feature = "no_std")]
+            ErrorKind::OutOfMemory => io::ErrorKind::Other,
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// An error detected by the decoders (or encoders) of this crate.
+///
+/// This is converted to an `io::Error` (whose kind is `UnexpectedEof` for [`ErrorKind::UnexpectedEof`],
+/// `Other` for [`ErrorKind::Cancelled`], `OutOfMemory` for [`ErrorKind::OutOfMemory`]
+/// and `InvalidData` for the others) when it is returned from the decoders.
+///
+/// Note that, if the `no_std` feature is enabled,
+/// the `io::Error` only keeps the message of the error because `core2::io::Error` can not hold a payload.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Error {
+    kind: ErrorKind,
+    message: &'static str,
+    value: Option<u64>,
+    expected: Option<u64>,
+    position: Option<u64>,
+}
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, message: &'static str) -> Self {
+        Error {
+            kind,
+            message,
+            value: None,
+            expected: None,
+            position: None,
+        }
+    }
+
+    pub(crate) fn with_value(mut self, value: impl Into<u64>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    pub(crate) fn with_expected(mut self, expected: impl Into<u64>) -> Self {
+        self.expected = Some(expected.into());
+        self
+    }
+
+    pub(crate) fn with_position(mut self, position: u64) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the human readable description of this error (without the offending values).
+    pub fn message(&self) -> &'static str {
+        self.message
+    }
+
+    /// Returns the offending value (e.g., the value of a header field or the computed checksum) if any.
+    pub fn value(&self) -> Option<u64> {
+        self.value
+    }
+
+    /// Returns the value that was expected (or the maximum allowed value) if it is known.
+    pub fn expected(&self) -> Option<u64> {
+        self.expected
+    }
+
+    /// Returns the byte offset, from the beginning of the stream, at which the error was detected if it is known.
+    pub fn position(&self) -> Option<u64> {
+        self.position
+    }
+
+    /// Returns the `Error` wrapped by the given `io::Error` if any.
+    #[cfg(not(feature = "no_std"))]
+    pub fn from_io_error(error: &io::Error) -> Option<&Self> {
+        error.get_ref().and_then(|e| e.downcast_ref())
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(value) = self.value {
+            write!(f, ": value={}", value)?;
+            if let Some(expected) = self.expected {
+                write!(f, ", expected={}", expected)?;
+            }
+        }
+        if let Some(position) = self.position {
+            write!(f, " (position={})", position)?;
+        }
+        Ok(())
+    }
+}
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for Error {}
+impl From<Error> for io::Error {
+    #[cfg(not(feature = "no_std"))]
+    fn from(e: Error) -> Self {
+        io::Error::new(e.kind.io_error_kind(), e)
+    }
+
+    #[cfg(feature = "no_std")]
+    fn from(e: Error) -> Self {
+        io::Error::new(e.kind.io_error_kind(), e.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_works() {
+        let e = Error::new(ErrorKind::ChecksumMismatch, "CRC32 mismatched")
+            .with_value(1u32)
+            .with_expected(2u32);
+        assert_eq!(e.to_string(), "CRC32 mismatched: value=1, expected=2");
+
+        let e = Error::new(ErrorKind::InvalidHeader, "Unexpected GZIP ID")
+            .with_value(0u16)
+            .with_position(0);
+        assert_eq!(e.to_string(), "Unexpected GZIP ID: value=0 (position=0)");
+    }
+
+    #[test]
+    fn conversion_to_io_error_works() {
+        let e: io::Error = Error::new(ErrorKind::UnexpectedEof, "Truncated").into();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        let e: io::Error = Error::new(ErrorKind::InvalidHuffmanCode, "Invalid").into();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        #[cfg(not(feature = "no_std"))]
+        assert_eq!(
+            Error::from_io_error(&e).map(|e| e.kind()),
+            Some(ErrorKind::InvalidHuffmanCode)
+        );
+    }
+
+    #[test]
+    fn truncated_streams_are_unexpected_eof() {
+        use crate::{any, deflate, gzip, non_blocking, zlib};
+        #[cfg(feature = "no_std")]
+        use core2::io::{Read, Write};
+        #[cfg(not(feature = "no_std"))]
+        use std::io::{Read, Write};
+
+        fn read_all<R: Read>(mut reader: R) -> io::Result<()> {
+            reader.read_to_end(&mut Vec::new()).map(|_| ())
+        }
+
+        let plain = (0..2000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let gzip_data = encoder.finish().into_result().unwrap();
+        let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let zlib_data = encoder.finish().into_result().unwrap();
+        let options = deflate::EncodeOptions::new().no_compression();
+        let mut encoder = deflate::Encoder::with_options(Vec::new(), options);
+        encoder.write_all(&plain).unwrap();
+        let stored_data = encoder.finish().into_result().unwrap();
+
+        for n in 0..gzip_data.len() {
+            let data = &gzip_data[..n];
+            let results = [
+                gzip::Decoder::new(data).and_then(read_all),
+                gzip::MultiDecoder::new(data).and_then(read_all),
+                any::Decoder::new(data).and_then(read_all),
+                read_all(non_blocking::gzip::Decoder::new(data)),
+            ];
+            for result in results {
+                assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+            }
+        }
+        for n in 0..zlib_data.len() {
+            let data = &zlib_data[..n];
+            let results = [
+                zlib::Decoder::new(data).and_then(read_all),
+                any::Decoder::new(data).and_then(read_all),
+                read_all(non_blocking::zlib::Decoder::new(data)),
+            ];
+            for result in results {
+                assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+            }
+            if n < zlib_data.len() - 4 {
+                // Excludes the ADLER-32 trailer
+                let result = read_all(deflate::Decoder::new(&data[2.min(n)..]));
+                assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+            }
+        }
+        for n in 0..stored_data.len() {
+            let data = &stored_data[..n];
+            let results = [
+                read_all(deflate::Decoder::new(data)),
+                read_all(non_blocking::deflate::Decoder::new(data)),
+            ];
+            for result in results {
+                assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+            }
+        }
+    }
+
+    #[test]
+    fn allocation_failure_is_reported() {
+        let mut buf = vec![0u8];
+        let e = crate::util::try_reserve(&mut buf, usize::MAX).unwrap_err();
+        #[cfg(not(feature = "no_std"))]
+        assert_eq!(e.kind(), io::ErrorKind::OutOfMemory);
+        #[cfg(not(feature = "no_std"))]
+        assert_eq!(
+            Error::from_io_error(&e).map(|e| e.kind()),
+            Some(ErrorKind::OutOfMemory)
+        );
+        assert_eq!(buf, [0]);
+    }
+}
@@ -93,6 +93,61 @@ impl<T, E> Finish<T, E> {
             Ok(&self.value)
         }
     }
+
+    /// Maps the value of this instance by applying `f`, leaving the error untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::Finish;
+    ///
+    /// let succeeded = Finish::new(vec![1, 2, 3], None as Option<()>);
+    /// assert_eq!(succeeded.map(|v| v.len()).into_result(), Ok(3));
+    ///
+    /// let failed = Finish::new(vec![1, 2, 3], Some("error"));
+    /// assert_eq!(failed.map(|v| v.len()).unwrap(), (3, Some("error")));
+    /// ```
+    pub fn map<U, F>(self, f: F) -> Finish<U, E>
+    where
+        F: FnOnce(T) -> U,
+    {
+        Finish::new(f(self.value), self.error)
+    }
+
+    /// Maps the error of this instance (if any) by applying `f`, leaving the value untouched.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::Finish;
+    ///
+    /// let failed = Finish::new("value", Some("error"));
+    /// assert_eq!(failed.map_err(|e| e.len()).unwrap(), ("value", Some(5)));
+    /// ```
+    pub fn map_err<U, F>(self, f: F) -> Finish<T, U>
+    where
+        F: FnOnce(E) -> U,
+    {
+        Finish::new(self.value, self.error.map(f))
+    }
+}
+impl<T, E> From<Finish<T, E>> for Result<T, (T, E)> {
+    /// Converts from `Finish<T, E>` to `Result<T, (T, E)>`, which keeps the value even if an error occurred.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::Finish;
+    ///
+    /// let succeeded: Result<_, _> = Finish::new("value", None as Option<()>).into();
+    /// assert_eq!(succeeded, Ok("value"));
+    ///
+    /// let failed: Result<_, _> = Finish::new("value", Some("error")).into();
+    /// assert_eq!(failed, Err(("value", "error")));
+    /// ```
+    fn from(finish: Finish<T, E>) -> Self {
+        match finish.error {
+            Some(e) => Err((finish.value, e)),
+            None => Ok(finish.value),
+        }
+    }
 }
 
 /// A wrapper struct that completes the processing of the underlying instance when drops.
@@ -0,0 +1,433 @@
+//! Types compatible with the low-level (backend) interface of the [flate2] crate.
+//!
+//! [`Compress`] and [`Decompress`] have the same methods as `flate2::Compress` and `flate2::Decompress`,
+//! so code written against that interface can be switched to this crate
+//! by replacing `flate2::` with `libflate::flate2_compat::` in the import paths.
+//!
+//! [flate2]: https://docs.rs/flate2
+//!
+//! # Examples
+//! ```
+//! use libflate::flate2_compat::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+//!
+//! // Encoding
+//! let mut compress = Compress::new(Compression::default(), true);
+//! let mut encoded_data = Vec::with_capacity(64);
+//! let status = compress.compress_vec(b"Hello World!", &mut encoded_data, FlushCompress::Finish).unwrap();
+//! assert_eq!(status, Status::StreamEnd);
+//!
+//! // Decoding
+//! let mut decompress = Decompress::new(true);
+//! let mut decoded_data = Vec::with_capacity(64);
+//! let status = decompress.decompress_vec(&encoded_data, &mut decoded_data, FlushDecompress::Finish).unwrap();
+//! assert_eq!(status, Status::StreamEnd);
+//! assert_eq!(decoded_data, b"Hello World!");
+//! ```
+use crate::deflate;
+use crate::push;
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+#[cfg(feature = "no_std")]
+use core::fmt;
+#[cfg(feature = "no_std")]
+use core2::io;
+#[cfg(not(feature = "no_std"))]
+use std::{fmt, io};
+
+/// Compression level.
+///
+/// Level `0` means no compression. Since this crate doesn't have speed/ratio tradeoff settings,
+/// all the other levels use the same (default) compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Compression(u32);
+impl Compression {
+    /// Makes a new compression level instance.
+    ///
+    /// `level` is expected to be in the range `0..=9`.
+    pub const fn new(level: u32) -> Self {
+        Compression(level)
+    }
+
+    /// No compression.
+    pub const fn none() -> Self {
+        Compression(0)
+    }
+
+    /// Optimize for speed.
+    pub const fn fast() -> Self {
+        Compression(1)
+    }
+
+    /// Optimize for size.
+    pub const fn best() -> Self {
+        Compression(9)
+    }
+
+    /// Returns the numeric value of the level.
+    pub const fn level(&self) -> u32 {
+        self.0
+    }
+}
+impl Default for Compression {
+    fn default() -> Self {
+        Compression(6)
+    }
+}
+
+/// Flush behavior of [`Compress::compress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlushCompress {
+    /// Encoded data may be buffered internally.
+    None,
+
+    /// All pending data is flushed and aligned to a byte boundary.
+    Sync,
+
+    /// Same as `Sync` in this crate.
+    Partial,
+
+    /// Same as `Sync` in this crate.
+    Full,
+
+    /// The stream is terminated.
+    Finish,
+}
+
+/// Flush behavior of [`Decompress::decompress`].
+///
+/// The decompressor always produces as much output as possible, so the value makes no difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlushDecompress {
+    /// No special behavior.
+    None,
+
+    /// No special behavior.
+    Sync,
+
+    /// No special behavior.
+    Finish,
+}
+
+/// The result of a successful (de)compression invocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// Some input has been consumed or some output has been produced.
+    Ok,
+
+    /// No progress was possible (e.g., because the output buffer is empty).
+    BufError,
+
+    /// The end of the stream has been reached and all output has been produced.
+    StreamEnd,
+}
+
+/// Error of [`Compress`].
+#[derive(Debug)]
+pub struct CompressError(io::Error);
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deflate compression error: {}", self.0)
+    }
+}
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for CompressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+impl From<CompressError> for io::Error {
+    fn from(f: CompressError) -> Self {
+        f.0
+    }
+}
+
+/// Error of [`Decompress`].
+#[derive(Debug)]
+pub struct DecompressError(io::Error);
+impl DecompressError {
+    /// Returns the Adler-32 of the required preset dictionary.
+    ///
+    /// Preset dictionaries are not supported by this crate, so this method always returns `None`.
+    pub fn needs_dictionary(&self) -> Option<u32> {
+        None
+    }
+}
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "deflate decompression error: {}", self.0)
+    }
+}
+#[cfg(not(feature = "no_std"))]
+impl std::error::Error for DecompressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+impl From<DecompressError> for io::Error {
+    fn from(f: DecompressError) -> Self {
+        f.0
+    }
+}
+
+/// Raw in-memory compression stream (the counterpart of `flate2::Compress`).
+#[derive(Debug)]
+pub struct Compress {
+    encoder: push::Encoder,
+    level: Compression,
+    zlib_header: bool,
+    total_in: u64,
+    total_out: u64,
+}
+impl Compress {
+    /// Makes a new compression stream.
+    ///
+    /// If `zlib_header` is `true`, a ZLIB stream is produced, otherwise a raw DEFLATE stream is produced.
+    pub fn new(level: Compression, zlib_header: bool) -> Self {
+        Compress {
+            encoder: Self::make_encoder(level, zlib_header),
+            level,
+            zlib_header,
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+
+    /// Returns the total number of input bytes which have been processed.
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    /// Returns the total number of output bytes which have been produced.
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    /// Resets the state of this stream so that a new stream can be compressed.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.level, self.zlib_header);
+    }
+
+    /// Compresses the data in `input` into `output`.
+    ///
+    /// The amount of the consumed input and the produced output can be known by
+    /// the differences of [`total_in`](Self::total_in) and [`total_out`](Self::total_out).
+    pub fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        flush: FlushCompress,
+    ) -> Result<Status, CompressError> {
+        let flush = match flush {
+            FlushCompress::None => push::Flush::None,
+            FlushCompress::Sync | FlushCompress::Partial | FlushCompress::Full => push::Flush::Sync,
+            FlushCompress::Finish => push::Flush::Finish,
+        };
+        let progress = self
+            .encoder
+            .encode(input, output, flush)
+            .map_err(CompressError)?;
+        self.total_in += progress.consumed as u64;
+        self.total_out += progress.produced as u64;
+        Ok(to_status(progress))
+    }
+
+    /// Compresses the data in `input` into the spare capacity of `output`.
+    ///
+    /// `output` is never reallocated.
+    pub fn compress_vec(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        flush: FlushCompress,
+    ) -> Result<Status, CompressError> {
+        let len = output.len();
+        let total_out = self.total_out;
+        output.resize(output.capacity(), 0);
+        let result = self.compress(input, &mut output[len..], flush);
+        output.truncate(len + (self.total_out - total_out) as usize);
+        result
+    }
+
+    fn make_encoder(level: Compression, zlib_header: bool) -> push::Encoder {
+        let no_compression = level.level() == 0;
+        if zlib_header {
+            let mut options = zlib::EncodeOptions::new();
+            if no_compression {
+                options = options.no_compression();
+            }
+            push::Encoder::zlib(options).expect("Writing to `Vec` never fails")
+        } else {
+            let mut options = deflate::EncodeOptions::new();
+            if no_compression {
+                options = options.no_compression();
+            }
+            push::Encoder::deflate(options)
+        }
+    }
+}
+
+/// Raw in-memory decompression stream (the counterpart of `flate2::Decompress`).
+#[derive(Debug)]
+pub struct Decompress {
+    decoder: push::Decoder,
+    total_in: u64,
+    total_out: u64,
+}
+impl Decompress {
+    /// Makes a new decompression stream.
+    ///
+    /// If `zlib_header` is `true`, a ZLIB stream is expected, otherwise a raw DEFLATE stream is expected.
+    pub fn new(zlib_header: bool) -> Self {
+        let format = if zlib_header {
+            push::Format::Zlib
+        } else {
+            push::Format::Deflate
+        };
+        Decompress {
+            decoder: push::Decoder::new(format),
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+
+    /// Returns the total number of input bytes which have been processed.
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    /// Returns the total number of output bytes which have been produced.
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    /// Resets the state of this stream so that a new stream can be decompressed.
+    pub fn reset(&mut self, zlib_header: bool) {
+        *self = Self::new(zlib_header);
+    }
+
+    /// Decompresses the data in `input` into `output`.
+    ///
+    /// The amount of the consumed input and the produced output can be known by
+    /// the differences of [`total_in`](Self::total_in) and [`total_out`](Self::total_out).
+    pub fn decompress(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        _flush: FlushDecompress,
+    ) -> Result<Status, DecompressError> {
+        let progress = self
+            .decoder
+            .decode(input, output)
+            .map_err(DecompressError)?;
+        self.total_in += progress.consumed as u64;
+        self.total_out += progress.produced as u64;
+        Ok(to_status(progress))
+    }
+
+    /// Decompresses the data in `input` into the spare capacity of `output`.
+    ///
+    /// `output` is never reallocated.
+    pub fn decompress_vec(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        flush: FlushDecompress,
+    ) -> Result<Status, DecompressError> {
+        let len = output.len();
+        let total_out = self.total_out;
+        output.resize(output.capacity(), 0);
+        let result = self.decompress(input, &mut output[len..], flush);
+        output.truncate(len + (self.total_out - total_out) as usize);
+        result
+    }
+}
+
+fn to_status(progress: push::Progress) -> Status {
+    if progress.status == push::Status::StreamEnd {
+        Status::StreamEnd
+    } else if progress.consumed == 0 && progress.produced == 0 {
+        Status::BufError
+    } else {
+        Status::Ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compress_all(compress: &mut Compress, mut input: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut output = Vec::new();
+        loop {
+            output.reserve(chunk_size);
+            let before = compress.total_in();
+            let status = compress
+                .compress_vec(input, &mut output, FlushCompress::Finish)
+                .unwrap();
+            input = &input[(compress.total_in() - before) as usize..];
+            if status == Status::StreamEnd {
+                return output;
+            }
+        }
+    }
+
+    fn decompress_all(decompress: &mut Decompress, input: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut output = Vec::new();
+        loop {
+            output.reserve(chunk_size);
+            let offset = decompress.total_in() as usize;
+            let status = decompress
+                .decompress_vec(&input[offset..], &mut output, FlushDecompress::None)
+                .unwrap();
+            if status == Status::StreamEnd {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_works() {
+        let plain = (0..50_000).map(|i| (i % 13) as u8).collect::<Vec<_>>();
+        for &zlib_header in &[true, false] {
+            for &level in &[Compression::none(), Compression::default()] {
+                let mut compress = Compress::new(level, zlib_header);
+                let encoded = compress_all(&mut compress, &plain, 100);
+                assert_eq!(compress.total_in(), plain.len() as u64);
+                assert_eq!(compress.total_out(), encoded.len() as u64);
+
+                let mut decompress = Decompress::new(zlib_header);
+                let decoded = decompress_all(&mut decompress, &encoded, 100);
+                assert_eq!(decoded, plain);
+                assert_eq!(decompress.total_in(), encoded.len() as u64);
+
+                compress.reset();
+                assert_eq!(compress.total_in(), 0);
+                assert_eq!(compress_all(&mut compress, &plain, 100000), encoded);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_output_is_buf_error() {
+        let mut compress = Compress::new(Compression::default(), true);
+        let status = compress
+            .compress(b"foo", &mut [], FlushCompress::Finish)
+            .unwrap();
+        assert_eq!(status, Status::Ok);
+        let status = compress
+            .compress(&[], &mut [], FlushCompress::Finish)
+            .unwrap();
+        assert_eq!(status, Status::BufError);
+    }
+
+    #[test]
+    fn corrupted_data_is_error() {
+        let mut decompress = Decompress::new(true);
+        let error = decompress
+            .decompress(&[0xFF; 8], &mut [0; 8], FlushDecompress::None)
+            .unwrap_err();
+        assert_eq!(error.needs_dictionary(), None);
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,239 @@
+//! A wrapper of decoders which protects against decompression bombs.
+//!
+//! A small compressed stream can expand to a huge amount of data (at most 1032 times as large with DEFLATE).
+//! [`Guard`] limits the size of the decoded data and the ratio of it to the size of the compressed data,
+//! and fails with an [`ErrorKind::DecompressionBomb`] error once either limit is exceeded.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::Read;
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::Read;
+//! use libflate::gzip;
+//! use libflate::guard::{Guard, Limits};
+//!
+//! let encoded_data = gzip::compress_to_vec(&[0; 1_000_000]).unwrap();
+//!
+//! let decoder = gzip::Decoder::new(&encoded_data[..]).unwrap();
+//! let mut guard = Guard::new(decoder, Limits::new().max_output_size(100_000));
+//! let mut buf = Vec::new();
+//! assert!(guard.read_to_end(&mut buf).is_err());
+//! assert!(buf.len() <= 100_000);
+//! ```
+use crate::codec::ByteCount;
+use crate::error::{Error, ErrorKind};
+#[cfg(feature = "no_std")]
+use core::cmp;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp,
+    io::{self, Read},
+};
+
+/// The decoded size up to which the expansion ratio is not checked.
+///
+/// The ratio of a short stream is meaningless (e.g., the header of a GZIP stream may be read after some data are decoded).
+const RATIO_CHECK_THRESHOLD: u64 = 64 * 1024;
+
+/// The limits enforced by [`Guard`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Limits {
+    max_output_size: u64,
+    max_ratio: u64,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_output_size: u64::MAX,
+            max_ratio: u64::MAX,
+        }
+    }
+}
+impl Limits {
+    /// Makes a default instance, which limits nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Specifies the maximum size of the decoded data.
+    ///
+    /// The default value is `u64::MAX`.
+    pub fn max_output_size(mut self, size: u64) -> Self {
+        self.max_output_size = size;
+        self
+    }
+
+    /// Specifies the maximum ratio of the size of the decoded data to the size of the compressed data.
+    ///
+    /// The ratio is checked only after 64 KiB of data have been decoded.
+    ///
+    /// The default value is `u64::MAX`.
+    pub fn max_ratio(mut self, ratio: u64) -> Self {
+        self.max_ratio = ratio;
+        self
+    }
+
+    /// Returns the maximum size of the decoded data.
+    pub fn get_max_output_size(&self) -> u64 {
+        self.max_output_size
+    }
+
+    /// Returns the maximum ratio of the size of the decoded data to the size of the compressed data.
+    pub fn get_max_ratio(&self) -> u64 {
+        self.max_ratio
+    }
+}
+
+/// A decoder wrapper which enforces [`Limits`].
+///
+/// The wrapped decoder can be any of the decoders of this crate, or anything else implementing [`ByteCount`].
+/// The data exceeding the maximum output size are never returned.
+#[derive(Debug)]
+pub struct Guard<D> {
+    decoder: D,
+    limits: Limits,
+    total_out: u64,
+    exceeded: bool,
+}
+impl<D> Guard<D>
+where
+    D: Read + ByteCount,
+{
+    /// Makes a new guard of `decoder`.
+    ///
+    /// The decoded data which have already been read from `decoder` are not counted.
+    pub fn new(decoder: D, limits: Limits) -> Self {
+        Guard {
+            decoder,
+            limits,
+            total_out: 0,
+            exceeded: false,
+        }
+    }
+
+    /// Returns the limits enforced by this guard.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Returns the immutable reference to the wrapped decoder.
+    pub fn as_inner_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns the mutable reference to the wrapped decoder.
+    ///
+    /// Note that the data read from the decoder directly are not counted.
+    pub fn as_inner_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Unwraps this `Guard`, returning the wrapped decoder.
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+
+    fn check_ratio(&self) -> io::Result<()> {
+        let total_in = self.decoder.total_in();
+        if self.total_out > RATIO_CHECK_THRESHOLD
+            && self.total_out > total_in.saturating_mul(self.limits.max_ratio)
+        {
+            Err(Error::new(
+                ErrorKind::DecompressionBomb,
+                "The expansion ratio of the decoded data exceeds the limit",
+            )
+            .with_value(self.total_out / cmp::max(total_in, 1))
+            .with_expected(self.limits.max_ratio)
+            .with_position(total_in)
+            .into())
+        } else {
+            Ok(())
+        }
+    }
+}
+impl<D> Read for Guard<D>
+where
+    D: Read + ByteCount,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.limits.max_output_size - self.total_out;
+        if !self.exceeded {
+            // One more byte than allowed is requested to detect the excess
+            let len = cmp::min(buf.len() as u64, remaining.saturating_add(1)) as usize;
+            let size = self.decoder.read(&mut buf[..len])? as u64;
+            self.exceeded = size > remaining;
+            let size = cmp::min(size, remaining);
+            self.total_out += size;
+            self.check_ratio()?;
+            if size > 0 || !self.exceeded {
+                // The error (if exceeded) is reported at the next call
+                return Ok(size as usize);
+            }
+        }
+        Err(Error::new(
+            ErrorKind::DecompressionBomb,
+            "The size of the decoded data exceeds the limit",
+        )
+        .with_expected(self.limits.max_output_size)
+        .with_position(self.decoder.total_in())
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deflate, zlib};
+    #[cfg(feature = "no_std")]
+    use alloc::vec::Vec;
+
+    #[test]
+    fn output_size_is_limited() {
+        let encoded = zlib::compress_to_vec(&[1; 100_000]).unwrap();
+        for &max in &[0, 99_999, 100_000] {
+            let decoder = zlib::Decoder::new(&encoded[..]).unwrap();
+            let mut guard = Guard::new(decoder, Limits::new().max_output_size(max));
+            let mut decoded = Vec::new();
+            let result = guard.read_to_end(&mut decoded);
+            if max < 100_000 {
+                let error = result.unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                #[cfg(not(feature = "no_std"))]
+                assert_eq!(
+                    Error::from_io_error(&error).map(|e| e.kind()),
+                    Some(ErrorKind::DecompressionBomb)
+                );
+                assert_eq!(decoded.len() as u64, max);
+                assert!(guard.read(&mut [0; 10]).is_err());
+            } else {
+                result.unwrap();
+                assert_eq!(decoded, [1; 100_000]);
+            }
+        }
+    }
+
+    #[test]
+    fn expansion_ratio_is_limited() {
+        let plain = (0..1_000_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let encoded = deflate::compress_to_vec(&plain).unwrap();
+        let ratio = plain.len() as u64 / encoded.len() as u64;
+        assert!(ratio > 2);
+
+        // The ratio of a part of the stream may be a little higher than the overall one
+        let limits = Limits::new().max_ratio(ratio * 2);
+        let mut guard = Guard::new(deflate::Decoder::new(&encoded[..]), limits.clone());
+        let mut decoded = Vec::new();
+        guard.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, plain);
+
+        let zeros = deflate::compress_to_vec(&[0; 1_000_000]).unwrap();
+        let mut guard = Guard::new(deflate::Decoder::new(&zeros[..]), limits);
+        let error = guard.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
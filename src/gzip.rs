@@ -24,17 +24,25 @@
 //! ```
 use crate::checksum;
 use crate::deflate;
-use crate::finish::{Complete, Finish};
+use crate::error::{Error, ErrorKind};
+use crate::finish::{AutoFinish, Complete, Finish};
 use crate::lz77;
+use crate::options;
+#[cfg(not(feature = "no_std"))]
+use crate::parallel;
+use crate::read;
+use crate::util::{self, FinishState};
 #[cfg(feature = "no_std")]
 use alloc::{ffi::CString, vec::Vec};
 #[cfg(feature = "no_std")]
 use core2::io;
 #[cfg(not(feature = "no_std"))]
-use std::{ffi::CString, io, time};
+use std::{ffi::CString, fs, io, path, time};
 
 const GZIP_ID: [u8; 2] = [31, 139];
 const COMPRESSION_METHOD_DEFLATE: u8 = 8;
+const MIN_HEADER_SIZE: u64 = 10;
+const TRAILER_SIZE: u64 = 8;
 
 const OS_FAT: u8 = 0;
 const OS_AMIGA: u8 = 1;
@@ -57,9 +65,11 @@ const F_HCRC: u8 = 0b00_0010;
 const F_EXTRA: u8 = 0b00_0100;
 const F_NAME: u8 = 0b00_1000;
 const F_COMMENT: u8 = 0b01_0000;
+const F_RESERVED: u8 = 0b1110_0000;
 
 /// Compression levels defined by the GZIP format.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompressionLevel {
     /// Compressor used fastest algorithm.
     Fastest,
@@ -102,6 +112,9 @@ pub(crate) struct Trailer {
     input_size: u32,
 }
 impl Trailer {
+    pub fn new(crc32: u32, input_size: u32) -> Self {
+        Trailer { crc32, input_size }
+    }
     pub fn crc32(&self) -> u32 {
         self.crc32
     }
@@ -116,7 +129,7 @@ impl Trailer {
         let input_size = u32::from_le_bytes(buf);
         Ok(Trailer { crc32, input_size })
     }
-    fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    pub fn write_to<W>(&self, mut writer: W) -> io::Result<()>
     where
         W: io::Write,
     {
@@ -166,6 +179,8 @@ impl HeaderBuilder {
             extra_field: None,
             filename: None,
             comment: None,
+            raw_bytes: None,
+            anomalies: Vec::new(),
         };
         HeaderBuilder { header }
     }
@@ -184,6 +199,70 @@ impl HeaderBuilder {
         self
     }
 
+    /// Sets the modification time from signed UNIX seconds (e.g., `filetime::FileTime::unix_seconds`).
+    ///
+    /// The MTIME field can only hold the times from 1970 to 2106,
+    /// so the other times are rejected with an [`ErrorKind::InvalidHeader`] error.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    ///
+    /// let header = HeaderBuilder::new().modification_unix_seconds(10).unwrap().finish();
+    /// assert_eq!(header.modification_time(), 10);
+    ///
+    /// assert!(HeaderBuilder::new().modification_unix_seconds(-1).is_err());
+    /// assert!(HeaderBuilder::new().modification_unix_seconds(1 << 32).is_err());
+    /// ```
+    pub fn modification_unix_seconds(&mut self, seconds: i64) -> io::Result<&mut Self> {
+        if seconds < 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidHeader,
+                "A modification time before 1970 can not be stored in the GZIP header",
+            )
+            .into());
+        }
+        if seconds > i64::from(u32::MAX) {
+            return Err(Error::new(
+                ErrorKind::InvalidHeader,
+                "A modification time after 2106 can not be stored in the GZIP header",
+            )
+            .with_value(seconds as u64)
+            .with_expected(u32::MAX)
+            .into());
+        }
+        Ok(self.modification_time(seconds as u32))
+    }
+
+    /// Sets the modification time from a `SystemTime`.
+    ///
+    /// The sub-second part is truncated.
+    /// As with [`HeaderBuilder::modification_unix_seconds`], the times before 1970 or after 2106 are rejected.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let time = UNIX_EPOCH + Duration::from_millis(10_500);
+    /// let header = HeaderBuilder::new().modification_system_time(time).unwrap().finish();
+    /// assert_eq!(header.modification_time(), 10);
+    ///
+    /// assert!(HeaderBuilder::new().modification_system_time(UNIX_EPOCH - Duration::from_secs(1)).is_err());
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn modification_system_time(&mut self, time: time::SystemTime) -> io::Result<&mut Self> {
+        match time.duration_since(time::UNIX_EPOCH) {
+            Ok(elapsed) => {
+                let seconds = elapsed.as_secs().min(i64::MAX as u64) as i64;
+                self.modification_unix_seconds(seconds)
+            }
+            // Less than one second before the epoch is truncated to the epoch as well
+            Err(e) if e.duration().as_secs() == 0 => Ok(self.modification_time(0)),
+            Err(_) => self.modification_unix_seconds(-1),
+        }
+    }
+
     /// Sets the OS type.
     ///
     /// ```
@@ -281,10 +360,120 @@ impl HeaderBuilder {
         self
     }
 
+    /// Sets the file name from a string, encoding it in ISO 8859-1 (Latin-1) as the GZIP format requires.
+    ///
+    /// A name which contains NUL or a character outside of Latin-1 is rejected
+    /// with an [`ErrorKind::InvalidHeader`] error (see [`HeaderBuilder::filename_str_lossy`] for the alternative).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    ///
+    /// let header = HeaderBuilder::new().filename_str("caf\u{e9}.txt").unwrap().finish();
+    /// assert_eq!(header.filename().unwrap().as_bytes(), b"caf\xe9.txt");
+    ///
+    /// assert!(HeaderBuilder::new().filename_str("\u{732b}.txt").is_err());
+    /// assert!(HeaderBuilder::new().filename_str("a\0b").is_err());
+    /// ```
+    pub fn filename_str(&mut self, filename: &str) -> io::Result<&mut Self> {
+        let filename =
+            latin1_cstring(filename, false).map_err(|c| unrepresentable_char_error("FNAME", c))?;
+        Ok(self.filename(filename))
+    }
+
+    /// Sets the file name from a string, replacing NUL and the characters outside of Latin-1 with `?`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    ///
+    /// let header = HeaderBuilder::new().filename_str_lossy("\u{732b}.txt").finish();
+    /// assert_eq!(header.filename().unwrap().as_bytes(), b"?.txt");
+    /// ```
+    pub fn filename_str_lossy(&mut self, filename: &str) -> &mut Self {
+        let filename = latin1_cstring(filename, true).unwrap_or_default();
+        self.filename(filename)
+    }
+
+    /// Sets the comment from a string, encoding it in ISO 8859-1 (Latin-1) as the GZIP format requires.
+    ///
+    /// A comment which contains NUL or a character outside of Latin-1 is rejected
+    /// with an [`ErrorKind::InvalidHeader`] error (see [`HeaderBuilder::comment_str_lossy`] for the alternative).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    ///
+    /// let header = HeaderBuilder::new().comment_str("\u{a9} 2024").unwrap().finish();
+    /// assert_eq!(header.comment().unwrap().as_bytes(), b"\xa9 2024");
+    ///
+    /// assert!(HeaderBuilder::new().comment_str("\u{2122}").is_err());
+    /// ```
+    pub fn comment_str(&mut self, comment: &str) -> io::Result<&mut Self> {
+        let comment = latin1_cstring(comment, false)
+            .map_err(|c| unrepresentable_char_error("FCOMMENT", c))?;
+        Ok(self.comment(comment))
+    }
+
+    /// Sets the comment from a string, replacing NUL and the characters outside of Latin-1 with `?`.
+    pub fn comment_str_lossy(&mut self, comment: &str) -> &mut Self {
+        let comment = latin1_cstring(comment, true).unwrap_or_default();
+        self.comment(comment)
+    }
+
     /// Returns the result header.
+    ///
+    /// Use [`HeaderBuilder::try_finish`] to detect the fields which can not be encoded
+    /// before the header is passed to an encoder.
     pub fn finish(&self) -> Header {
         self.header.clone()
     }
+
+    /// Returns the result header after validating it.
+    ///
+    /// The extra field is rejected with an [`ErrorKind::InvalidHeader`] error
+    /// if it exceeds 65535 bytes,
+    /// which the encoders would otherwise report only when the header is written.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::{HeaderBuilder, ExtraField, ExtraSubField};
+    ///
+    /// let subfield = ExtraSubField{id: [0, 1], data: vec![0; 70_000]};
+    /// let extra = ExtraField{subfields: vec![subfield]};
+    /// assert!(HeaderBuilder::new().extra_field(extra).try_finish().is_err());
+    /// ```
+    pub fn try_finish(&self) -> io::Result<Header> {
+        if let Some(ref extra) = self.header.extra_field {
+            extra.validate()?;
+        }
+        Ok(self.finish())
+    }
+}
+
+/// Encodes `s` in ISO 8859-1, returning the first character which can not be encoded (unless `lossy`).
+fn latin1_cstring(s: &str, lossy: bool) -> Result<CString, char> {
+    let mut bytes = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match u32::from(c) {
+            b @ 0x01..=0xFF => bytes.push(b as u8),
+            _ if lossy => bytes.push(b'?'),
+            _ => return Err(c),
+        }
+    }
+    Ok(CString::new(bytes).expect("never fails"))
+}
+
+fn unrepresentable_char_error(field: &str, c: char) -> io::Error {
+    let message = match (field, c) {
+        ("FNAME", '\0') => "The FNAME field of GZIP header can not contain NUL",
+        ("FNAME", _) => "The FNAME field of GZIP header can only contain ISO 8859-1 characters",
+        (_, '\0') => "The FCOMMENT field of GZIP header can not contain NUL",
+        _ => "The FCOMMENT field of GZIP header can only contain ISO 8859-1 characters",
+    };
+    Error::new(ErrorKind::InvalidHeader, message)
+        .with_value(u32::from(c))
+        .into()
 }
 impl Default for HeaderBuilder {
     fn default() -> Self {
@@ -294,6 +483,7 @@ impl Default for HeaderBuilder {
 
 /// GZIP Header.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     modification_time: u32,
     compression_level: CompressionLevel,
@@ -303,6 +493,13 @@ pub struct Header {
     extra_field: Option<ExtraField>,
     filename: Option<CString>,
     comment: Option<CString>,
+
+    // The bytes from which the header was decoded.
+    #[cfg_attr(feature = "serde", serde(default))]
+    raw_bytes: Option<Vec<u8>>,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    anomalies: Vec<HeaderAnomaly>,
 }
 impl Header {
     /// Returns the modification time (UNIX timestamp).
@@ -310,6 +507,30 @@ impl Header {
         self.modification_time
     }
 
+    /// Returns the modification time as a `SystemTime`.
+    ///
+    /// Returns `None` if the MTIME field is `0`, which means that no time stamp is available.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::HeaderBuilder;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let header = HeaderBuilder::new().modification_time(10).finish();
+    /// assert_eq!(header.modification_system_time(), Some(UNIX_EPOCH + Duration::from_secs(10)));
+    ///
+    /// let header = HeaderBuilder::new().modification_time(0).finish();
+    /// assert_eq!(header.modification_system_time(), None);
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn modification_system_time(&self) -> Option<time::SystemTime> {
+        if self.modification_time == 0 {
+            None
+        } else {
+            Some(time::UNIX_EPOCH + time::Duration::from_secs(u64::from(self.modification_time)))
+        }
+    }
+
     /// Returns the compression level.
     pub fn compression_level(&self) -> CompressionLevel {
         self.compression_level.clone()
@@ -345,6 +566,90 @@ impl Header {
         self.comment.as_ref()
     }
 
+    /// Returns the exact bytes from which this header was decoded.
+    ///
+    /// Unlike re-encoding the parsed fields, the bytes preserve everything in the original header
+    /// (e.g., the reserved flags, or the CRC-16 of a header written by a non-conforming encoder).
+    /// They can be written back verbatim with [`EncodeOptions::verbatim_header`].
+    ///
+    /// Returns `None` if the header was not decoded (e.g., made by [`HeaderBuilder`]).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::{Decoder, Encoder, HeaderBuilder};
+    ///
+    /// let mut encoded_data = Encoder::new(Vec::new()).unwrap().finish().into_result().unwrap();
+    /// encoded_data[3] |= 0x80; // Sets a reserved flag
+    ///
+    /// let decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// assert_eq!(decoder.header().raw_bytes(), Some(&encoded_data[..10]));
+    /// assert_eq!(HeaderBuilder::new().finish().raw_bytes(), None);
+    /// ```
+    pub fn raw_bytes(&self) -> Option<&[u8]> {
+        self.raw_bytes.as_deref()
+    }
+
+    /// Returns the anomalies found while decoding this header.
+    ///
+    /// Reserved flags are always reported (and otherwise ignored).
+    /// The other anomalies are tolerated only by [`DecodeOptions::lenient`], and are errors otherwise.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::{DecodeOptions, Decoder, HeaderAnomaly};
+    ///
+    /// // An empty stream whose header has the compression method 9 and a reserved flag
+    /// let encoded_data = [31, 139, 9, 0x80, 0, 0, 0, 0, 0, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    /// assert!(Decoder::new(&encoded_data[..]).is_err());
+    ///
+    /// let options = DecodeOptions::new().lenient(true);
+    /// let decoder = Decoder::with_options(&encoded_data[..], options).unwrap();
+    /// assert_eq!(
+    ///     decoder.header().anomalies(),
+    ///     [HeaderAnomaly::UnknownCompressionMethod(9), HeaderAnomaly::ReservedFlags(0x80)]
+    /// );
+    /// ```
+    pub fn anomalies(&self) -> &[HeaderAnomaly] {
+        &self.anomalies
+    }
+
+    /// Decodes a header from its exact bytes (e.g., the ones returned by [`Header::raw_bytes`] and stored elsewhere).
+    ///
+    /// # Errors
+    ///
+    /// If `bytes` is not a valid GZIP header, or has trailing bytes after the header,
+    /// this returns an error.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::{Header, HeaderBuilder};
+    ///
+    /// let bytes = [31, 139, 8, 8, 0, 0, 0, 0, 0, 3, b'a', 0];
+    /// let header = Header::from_raw_bytes(&bytes).unwrap();
+    /// assert_eq!(header.filename().unwrap().to_bytes(), b"a");
+    /// assert_eq!(header.raw_bytes(), Some(&bytes[..]));
+    ///
+    /// assert!(Header::from_raw_bytes(&bytes[..11]).is_err());
+    /// assert!(Header::from_raw_bytes(&[&bytes[..], &[0]].concat()).is_err());
+    /// ```
+    pub fn from_raw_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = bytes;
+        let header = Self::read_from(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidHeader,
+                "Unexpected trailing bytes after GZIP header",
+            )
+            .with_position((bytes.len() - reader.len()) as u64)
+            .into());
+        }
+        Ok(header)
+    }
+
+    pub(crate) fn set_compression_level(&mut self, level: CompressionLevel) {
+        self.compression_level = level;
+    }
+
     fn flags(&self) -> u8 {
         [
             (F_TEXT, self.is_text),
@@ -370,7 +675,7 @@ impl Header {
         crc.update(&buf);
         crc.value() as u16
     }
-    fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    pub(crate) fn write_to<W>(&self, mut writer: W) -> io::Result<()>
     where
         W: io::Write,
     {
@@ -392,66 +697,158 @@ impl Header {
         }
         Ok(())
     }
-    pub(crate) fn read_from<R>(mut reader: R) -> io::Result<Self>
+    /// Writes the raw bytes of this header if any, and its fields otherwise.
+    fn write_verbatim_to<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        match self.raw_bytes {
+            Some(ref bytes) => writer.write_all(bytes),
+            None => self.write_to(writer),
+        }
+    }
+    pub(crate) fn read_from<R>(reader: R) -> io::Result<Self>
+    where
+        R: io::Read,
+    {
+        Self::read_with_options(reader, &DecodeOptions::new())
+    }
+    pub(crate) fn read_with_options<R>(reader: R, options: &DecodeOptions) -> io::Result<Self>
     where
         R: io::Read,
     {
+        let mut reader = Recorder::new(reader);
         let mut this = HeaderBuilder::new().finish();
         let mut buf = [0; 2 + 1 + 1 + 4 + 1 + 1];
-        reader.read_exact(&mut buf)?;
+        io::Read::read_exact(&mut reader, &mut buf)?;
         let id = &buf[0..2];
         if id != GZIP_ID {
-            return Err(invalid_data_error!(
-                "Unexpected GZIP ID: value={:?}, \
-                 expected={:?}",
-                id,
-                GZIP_ID
-            ));
+            return Err(Error::new(ErrorKind::InvalidHeader, "Unexpected GZIP ID")
+                .with_value(u16::from_be_bytes([id[0], id[1]]))
+                .with_expected(u16::from_be_bytes(GZIP_ID))
+                .with_position(0)
+                .into());
         }
         let compression_method = buf[2];
-        if compression_method != COMPRESSION_METHOD_DEFLATE {
-            return Err(invalid_data_error!(
-                "Compression methods other than DEFLATE(8) are \
-                 unsupported: method={}",
-                compression_method
-            ));
+        if compression_method != COMPRESSION_METHOD_DEFLATE && options.lenient {
+            this.anomalies
+                .push(HeaderAnomaly::UnknownCompressionMethod(compression_method));
+        } else if compression_method != COMPRESSION_METHOD_DEFLATE {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Compression methods other than DEFLATE(8) are unsupported",
+            )
+            .with_value(compression_method)
+            .with_position(2)
+            .into());
         }
         let flags = buf[3];
+        if flags & F_RESERVED != 0 {
+            this.anomalies
+                .push(HeaderAnomaly::ReservedFlags(flags & F_RESERVED));
+        }
         this.modification_time = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
         this.compression_level = CompressionLevel::from_u8(buf[8]);
         this.os = Os::from_u8(buf[9]);
-        if flags & F_EXTRA != 0 {
-            this.extra_field = Some(ExtraField::read_from(&mut reader)?);
+        if flags & F_EXTRA != 0 && options.lenient {
+            match ExtraField::read_leniently(&mut reader, options.max_extra_field_len)? {
+                Ok(extra_field) => this.extra_field = Some(extra_field),
+                Err(xlen) => this
+                    .anomalies
+                    .push(HeaderAnomaly::MalformedExtraField { xlen }),
+            }
+        } else if flags & F_EXTRA != 0 {
+            this.extra_field = Some(ExtraField::read_from(
+                &mut reader,
+                options.max_extra_field_len,
+            )?);
         }
         if flags & F_NAME != 0 {
-            this.filename = Some(read_cstring(&mut reader)?);
+            this.filename = Some(read_cstring(
+                &mut reader,
+                options.max_filename_len,
+                "The FNAME field of GZIP header is too long",
+            )?);
         }
         if flags & F_COMMENT != 0 {
-            this.comment = Some(read_cstring(&mut reader)?);
+            this.comment = Some(read_cstring(
+                &mut reader,
+                options.max_comment_len,
+                "The FCOMMENT field of GZIP header is too long",
+            )?);
         }
         // Checksum verification is skipped during fuzzing
         // so that random data from fuzzer can reach actually interesting code.
         // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
         if flags & F_HCRC != 0 && cfg!(not(fuzzing)) {
+            let mut crc16 = checksum::Crc32::new();
+            crc16.update(&reader.buf);
+            let expected = crc16.value() as u16;
             let mut buf = [0; 2];
-            reader.read_exact(&mut buf)?;
+            io::Read::read_exact(&mut reader, &mut buf)?;
             let crc = u16::from_le_bytes(buf);
-            let expected = this.crc16();
             if crc != expected {
-                return Err(invalid_data_error!(
-                    "CRC16 of GZIP header mismatched: value={}, \
-                     expected={}",
-                    crc,
-                    expected
-                ));
+                return Err(Error::new(
+                    ErrorKind::ChecksumMismatch,
+                    "CRC16 of GZIP header mismatched",
+                )
+                .with_value(crc)
+                .with_expected(expected)
+                .into());
             }
             this.is_verified = true;
         }
+        this.raw_bytes = Some(reader.buf);
         Ok(this)
     }
 }
 
-fn read_cstring<R>(mut reader: R) -> io::Result<CString>
+/// An anomaly found while decoding a GZIP header (see [`Header::anomalies`]).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum HeaderAnomaly {
+    /// The CM field is not DEFLATE(8), and the body was decoded as DEFLATE anyway.
+    UnknownCompressionMethod(u8),
+
+    /// Some of the reserved bits of the FLG field (the contained ones) are set.
+    ReservedFlags(u8),
+
+    /// The FEXTRA field doesn't consist of valid subfields (e.g., a subfield exceeds XLEN),
+    /// and its XLEN bytes were skipped.
+    MalformedExtraField {
+        /// The length declared by the field.
+        xlen: u16,
+    },
+}
+
+/// A reader which keeps the bytes read from `inner`.
+struct Recorder<R> {
+    inner: R,
+    buf: Vec<u8>,
+}
+impl<R> Recorder<R> {
+    fn new(inner: R) -> Self {
+        Recorder {
+            inner,
+            buf: Vec::new(),
+        }
+    }
+}
+impl<R: io::Read> io::Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        util::try_reserve(&mut self.buf, size)?;
+        self.buf.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+}
+
+fn read_cstring<R>(
+    mut reader: R,
+    max_len: Option<usize>,
+    too_long_message: &'static str,
+) -> io::Result<CString>
 where
     R: io::Read,
 {
@@ -462,18 +859,26 @@ where
         if cbuf[0] == 0 {
             return Ok(CString::new(buf).unwrap());
         }
+        if let Some(max_len) = max_len.filter(|&max_len| buf.len() >= max_len) {
+            return Err(Error::new(ErrorKind::LimitExceeded, too_long_message)
+                .with_value(buf.len() as u64 + 1)
+                .with_expected(max_len as u64)
+                .into());
+        }
+        util::try_reserve(&mut buf, 1)?;
         buf.push(cbuf[0]);
     }
 }
 
 /// Extra field of a GZIP header.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExtraField {
     /// Data of the extra field.
     pub subfields: Vec<ExtraSubField>,
 }
 impl ExtraField {
-    fn read_from<R>(mut reader: R) -> io::Result<Self>
+    fn read_from<R>(mut reader: R, max_len: Option<usize>) -> io::Result<Self>
     where
         R: io::Read,
     {
@@ -481,20 +886,58 @@ impl ExtraField {
         let mut buf = [0; 2];
         reader.read_exact(&mut buf)?;
         let data_size = u16::from_le_bytes(buf) as usize;
+        if let Some(max_len) = max_len.filter(|&max_len| data_size > max_len) {
+            return Err(Error::new(
+                ErrorKind::LimitExceeded,
+                "The FEXTRA field of GZIP header is too long",
+            )
+            .with_value(data_size as u64)
+            .with_expected(max_len as u64)
+            .into());
+        }
         let mut reader = reader.take(data_size as u64);
         while reader.limit() > 0 {
             subfields.push(ExtraSubField::read_from(&mut reader)?);
         }
         Ok(ExtraField { subfields })
     }
-    fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    /// Reads the field like [`ExtraField::read_from`], except that the XLEN bytes are just skipped
+    /// if they are not valid subfields, in which case XLEN is returned as the inner error.
+    fn read_leniently<R>(mut reader: R, max_len: Option<usize>) -> io::Result<Result<Self, u16>>
     where
-        W: io::Write,
+        R: io::Read,
     {
+        let mut buf = [0; 2];
+        reader.read_exact(&mut buf)?;
+        let xlen = u16::from_le_bytes(buf);
+        let mut data = Vec::new();
+        util::try_reserve(&mut data, usize::from(xlen))?;
+        data.resize(usize::from(xlen), 0);
+        reader.read_exact(&mut data)?;
+
+        let field = [&buf[..], &data[..]].concat();
+        match Self::read_from(&field[..], max_len) {
+            Ok(field) => Ok(Ok(field)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(Err(xlen)),
+            Err(e) => Err(e),
+        }
+    }
+    /// Checks that this field can be written to a header, returning its length.
+    fn validate(&self) -> io::Result<usize> {
         let len = self.subfields.iter().map(|f| f.write_len()).sum::<usize>();
         if len > 0xFFFF {
-            return Err(invalid_data_error!("extra field too long: {}", len));
+            return Err(Error::new(ErrorKind::InvalidHeader, "extra field too long")
+                .with_value(len as u64)
+                .with_expected(0xFFFFu16)
+                .into());
         }
+        Ok(len)
+    }
+    fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let len = self.validate()?;
         writer.write_all(&(len as u16).to_le_bytes())?;
         for f in &self.subfields {
             f.write_to(&mut writer)?;
@@ -505,6 +948,7 @@ impl ExtraField {
 
 /// A sub field in the extra field of a GZIP header.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExtraSubField {
     /// ID of the field.
     pub id: [u8; 2],
@@ -526,6 +970,7 @@ impl ExtraSubField {
         let mut buf = [0; 2];
         reader.read_exact(&mut buf)?;
         let data_size = u16::from_le_bytes(buf) as usize;
+        util::try_reserve(&mut field.data, data_size)?;
         field.data.resize(data_size, 0);
         reader.read_exact(&mut field.data)?;
 
@@ -547,6 +992,7 @@ impl ExtraSubField {
 
 /// OS type.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Os {
     /// FAT filesystem (MS-DOS, OS/2, NT/Win32)
     Fat,
@@ -639,6 +1085,116 @@ impl Os {
     }
 }
 
+/// Options for a GZIP decoder.
+///
+/// By default, no limits are imposed on the lengths of the header fields
+/// (other than the ones the format itself imposes).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct DecodeOptions {
+    max_filename_len: Option<usize>,
+    max_comment_len: Option<usize>,
+    max_extra_field_len: Option<usize>,
+    lenient: bool,
+    options: deflate::DecodeOptions,
+}
+impl DecodeOptions {
+    /// Makes a default instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::{Decoder, DecodeOptions};
+    ///
+    /// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+    ///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+    ///                     163, 28, 41, 28, 12, 0, 0, 0];
+    ///
+    /// let options = DecodeOptions::new();
+    /// let decoder = Decoder::with_options(&encoded_data[..], options).unwrap();
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a hardened instance which is suitable for decoding untrusted input.
+    ///
+    /// The FNAME, FCOMMENT and FEXTRA fields are limited to 1024 bytes each,
+    /// and the DEFLATE stream is decoded with [`deflate::DecodeOptions::strict`].
+    ///
+    /// # Examples
+    /// ```
+    /// # #[cfg(not(feature = "no_std"))]
+    /// # {
+    /// use libflate::error::{Error, ErrorKind};
+    /// use libflate::gzip::{Decoder, DecodeOptions, EncodeOptions, Encoder, HeaderBuilder};
+    /// use std::ffi::CString;
+    ///
+    /// let header = HeaderBuilder::new()
+    ///     .filename(CString::new(vec![b'a'; 2000]).unwrap())
+    ///     .finish();
+    /// let encoder = Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// assert!(Decoder::new(&encoded_data[..]).is_ok());
+    ///
+    /// let error = Decoder::with_options(&encoded_data[..], DecodeOptions::strict()).unwrap_err();
+    /// assert_eq!(Error::from_io_error(&error).unwrap().kind(), ErrorKind::LimitExceeded);
+    /// # }
+    /// ```
+    pub fn strict() -> Self {
+        DecodeOptions {
+            max_filename_len: Some(1024),
+            max_comment_len: Some(1024),
+            max_extra_field_len: Some(1024),
+            lenient: false,
+            options: deflate::DecodeOptions::strict(),
+        }
+    }
+
+    /// Specifies the maximum length (excluding the terminating zero) of the FNAME field.
+    ///
+    /// A header with a longer filename is rejected with [`ErrorKind::LimitExceeded`].
+    pub fn max_filename_len(mut self, len: usize) -> Self {
+        self.max_filename_len = Some(len);
+        self
+    }
+
+    /// Specifies the maximum length (excluding the terminating zero) of the FCOMMENT field.
+    ///
+    /// A header with a longer comment is rejected with [`ErrorKind::LimitExceeded`].
+    pub fn max_comment_len(mut self, len: usize) -> Self {
+        self.max_comment_len = Some(len);
+        self
+    }
+
+    /// Specifies the maximum length (the value of XLEN) of the FEXTRA field.
+    ///
+    /// A header with a longer extra field is rejected with [`ErrorKind::LimitExceeded`].
+    pub fn max_extra_field_len(mut self, len: usize) -> Self {
+        self.max_extra_field_len = Some(len);
+        self
+    }
+
+    /// Specifies whether malformed headers are tolerated as far as the body can still be decoded.
+    ///
+    /// This is intended for recovering data from slightly damaged files.
+    /// If `true`, a header with an unknown compression method (the body is decoded as DEFLATE anyway)
+    /// or an FEXTRA field which doesn't consist of valid subfields (its XLEN bytes are skipped)
+    /// is accepted, and the anomalies are reported by [`Header::anomalies`].
+    /// The body and the trailer are still verified as usual.
+    ///
+    /// The default value is `false`.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Specifies the options of the underlying DEFLATE decoder.
+    pub fn deflate_options(mut self, options: deflate::DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
 /// Options for a GZIP encoder.
 #[derive(Debug)]
 pub struct EncodeOptions<E>
@@ -646,12 +1202,14 @@ where
     E: lz77::Lz77Encode,
 {
     header: Header,
+    verbatim_header: bool,
     options: deflate::EncodeOptions<E>,
 }
 impl Default for EncodeOptions<lz77::DefaultLz77Encoder> {
     fn default() -> Self {
         EncodeOptions {
             header: HeaderBuilder::new().finish(),
+            verbatim_header: false,
             options: Default::default(),
         }
     }
@@ -689,10 +1247,21 @@ where
         header.compression_level = From::from(lz77.compression_level());
         EncodeOptions {
             header,
+            verbatim_header: false,
             options: deflate::EncodeOptions::with_lz77(lz77),
         }
     }
 
+    /// Makes an instance which writes `header`, and whose DEFLATE stream is encoded with `options`.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn from_deflate_options(options: deflate::EncodeOptions<E>, header: Header) -> Self {
+        EncodeOptions {
+            header,
+            verbatim_header: false,
+            options,
+        }
+    }
+
     /// Disables LZ77 compression.
     ///
     /// # Example
@@ -721,6 +1290,31 @@ where
     /// ```
     pub fn header(mut self, header: Header) -> Self {
         self.header = header;
+        self.verbatim_header = false;
+        self
+    }
+
+    /// Sets the GZIP header which will be written to the output stream verbatim.
+    ///
+    /// Unlike [`EncodeOptions::header`], the exact bytes from which `header` was decoded
+    /// (see [`Header::raw_bytes`]) are written as they are, so the header is preserved bit-for-bit.
+    /// Note that this includes the XFL field, which may not reflect the compression level of the output stream.
+    /// If `header` was not decoded, this is the same as [`EncodeOptions::header`].
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::gzip::{Decoder, Encoder, EncodeOptions, Header};
+    ///
+    /// let raw_header = [31, 139, 8, 0xE0, 0, 0, 0, 0, 4, 255];
+    /// let header = Header::from_raw_bytes(&raw_header).unwrap();
+    /// let options = EncodeOptions::new().verbatim_header(header);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    /// assert_eq!(&encoded_data[..10], raw_header);
+    /// ```
+    pub fn verbatim_header(mut self, header: Header) -> Self {
+        self.header = header;
+        self.verbatim_header = true;
         self
     }
 
@@ -740,42 +1334,153 @@ where
         self
     }
 
-    /// Specifies to compress with fixed huffman codes.
+    /// Enables the adaptive block size mode.
+    ///
+    /// See [`deflate::EncodeOptions::adaptive_block_size`] for the details.
     ///
     /// # Example
     /// ```
     /// use libflate::gzip::{Encoder, EncodeOptions};
     ///
-    /// let options = EncodeOptions::new().fixed_huffman_codes();
+    /// let options = EncodeOptions::new().adaptive_block_size();
     /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
     /// ```
-    pub fn fixed_huffman_codes(mut self) -> Self {
-        self.options = self.options.fixed_huffman_codes();
+    pub fn adaptive_block_size(mut self) -> Self {
+        self.options = self.options.adaptive_block_size();
         self
     }
-}
 
-/// GZIP encoder.
-pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
-    header: Header,
-    crc32: checksum::Crc32,
-    input_size: u32,
-    writer: deflate::Encoder<W, E>,
-}
-impl<W> Encoder<W, lz77::DefaultLz77Encoder>
-where
-    W: io::Write,
-{
-    /// Makes a new encoder instance.
+    /// Enables the reuse of the dynamic Huffman codes of the previous block.
     ///
-    /// Encoded GZIP stream is written to `inner`.
+    /// See [`deflate::EncodeOptions::reuse_huffman_codes`] for the details.
+    pub fn reuse_huffman_codes(mut self) -> Self {
+        self.options = self.options.reuse_huffman_codes();
+        self
+    }
+
+    /// Specifies the Huffman code lengths used for all blocks.
     ///
-    /// # Examples
-    /// ```
-    /// #[cfg(feature = "no_std")]
-    /// use core2::io::Write;
-    /// #[cfg(not(feature = "no_std"))]
-    /// use std::io::Write;
+    /// See [`deflate::EncodeOptions::huffman_tables`] for the details.
+    pub fn huffman_tables(mut self, tables: deflate::HuffmanTables) -> Self {
+        self.options = self.options.huffman_tables(tables);
+        self
+    }
+
+    /// Makes the encoder emit a sync flush after every `bytes` bytes of uncompressed data.
+    ///
+    /// See [`deflate::EncodeOptions::sync_flush_interval`] for the details.
+    pub fn sync_flush_interval(mut self, bytes: u64) -> Self {
+        self.options = self.options.sync_flush_interval(bytes);
+        self
+    }
+
+    /// Makes the encoder emit a sync flush after each newline once at least `min_bytes` bytes have been written since the last flush.
+    ///
+    /// See [`deflate::EncodeOptions::line_flush`] for the details.
+    pub fn line_flush(mut self, min_bytes: u64) -> Self {
+        self.options = self.options.line_flush(min_bytes);
+        self
+    }
+
+    /// Specifies the size of the buffer in which the I/O adapters stage the data around the encoder.
+    ///
+    /// See [`deflate::EncodeOptions::buffer_size`] for the details.
+    ///
+    /// The default value is `deflate::DEFAULT_BUFFER_SIZE`.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.options = self.options.buffer_size(size);
+        self
+    }
+
+    /// Specifies to compress with fixed huffman codes.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::gzip::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().fixed_huffman_codes();
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    pub fn fixed_huffman_codes(mut self) -> Self {
+        self.options = self.options.fixed_huffman_codes();
+        self
+    }
+
+    /// Specifies the number of worker threads used to compress input data.
+    ///
+    /// See the [`parallel`] module for the details.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::gzip::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().threads(4);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options = self.options.threads(threads);
+        self
+    }
+
+    /// Specifies whether the encoder computes the checksum of the input data on a worker thread.
+    ///
+    /// See [`deflate::EncodeOptions::pipelined_checksum`] for the details.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().pipelined_checksum(true);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn pipelined_checksum(mut self, pipelined: bool) -> Self {
+        self.options = self.options.pipelined_checksum(pipelined);
+        self
+    }
+
+    pub(crate) fn get_buffer_size(&self) -> usize {
+        self.options.get_buffer_size()
+    }
+}
+
+/// The state of an [`Encoder`].
+///
+/// See [`Encoder::state`] for the details.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncoderState<E = lz77::DefaultLz77Encoder> {
+    header: Header,
+    crc32: u32,
+    input_size: u32,
+    deflate: deflate::EncoderState<E>,
+}
+
+/// GZIP encoder.
+#[derive(Debug)]
+pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
+    header: Header,
+    crc32: checksum::StreamChecksum<checksum::Crc32>,
+    input_size: u32,
+    writer: deflate::Encoder<W, E>,
+    finish_state: FinishState,
+    framing_size: u64,
+}
+impl<W> Encoder<W, lz77::DefaultLz77Encoder>
+where
+    W: io::Write,
+{
+    /// Makes a new encoder instance.
+    ///
+    /// Encoded GZIP stream is written to `inner`.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
     /// use libflate::gzip::Encoder;
     ///
     /// let mut encoder = Encoder::new(Vec::new()).unwrap();
@@ -813,15 +1518,40 @@ where
     ///              111, 32, 87, 111, 114, 108, 100, 33, 163, 28, 41, 28, 12, 0, 0, 0][..]);
     /// ```
     pub fn with_options(mut inner: W, options: EncodeOptions<E>) -> io::Result<Self> {
-        options.header.write_to(&mut inner)?;
+        let mut counter = util::Counter::new(&mut inner);
+        if options.verbatim_header {
+            options.header.write_verbatim_to(&mut counter)?;
+        } else {
+            options.header.write_to(&mut counter)?;
+        }
+        let header_size = counter.count();
+        let pipelined = options.options.get_pipelined_checksum();
         Ok(Encoder {
             header: options.header.clone(),
-            crc32: checksum::Crc32::new(),
+            crc32: checksum::StreamChecksum::with_pipelining(checksum::Crc32::new(), pipelined),
             input_size: 0,
+            #[cfg(not(feature = "no_std"))]
+            writer: deflate::Encoder::with_checksum(
+                inner,
+                options.options,
+                parallel::ChecksumKind::Crc32,
+            ),
+            #[cfg(feature = "no_std")]
             writer: deflate::Encoder::with_options(inner, options.options),
+            finish_state: FinishState::default(),
+            framing_size: header_size,
         })
     }
 
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn as_deflate_mut(&mut self) -> &mut deflate::Encoder<W, E> {
+        &mut self.writer
+    }
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn into_deflate(self) -> deflate::Encoder<W, E> {
+        self.writer
+    }
+
     /// Returns the header of the GZIP stream.
     ///
     /// # Examples
@@ -872,15 +1602,190 @@ where
     /// #[cfg(not(feature = "no_std"))]
     /// io::copy(&mut &plain[..], &mut encoder).unwrap();
     /// ```
-    pub fn finish(self) -> Finish<W, io::Error> {
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let result = self.try_finish();
+        Finish::new(self.into_inner(), result.err())
+    }
+
+    /// Writes the rest of the GZIP stream (including the trailer), and flushes the inner stream.
+    ///
+    /// Unlike [`Encoder::finish`], this does not consume the encoder.
+    /// This method is idempotent: the second and later calls return the result of the first call
+    /// without writing anything. Writing to the encoder after this call fails.
+    /// See [`deflate::Encoder::try_finish`] for the retry of the final flush.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    /// use libflate::gzip::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.try_finish().unwrap();
+    /// encoder.try_finish().unwrap();
+    /// let encoded_data = encoder.into_inner();
+    ///
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        if let Some(result) = self.finish_state.previous_result() {
+            return result;
+        }
+        if !self.finish_state.is_finished() {
+            let result = self.finish_stream();
+            self.finish_state.record_end(&result);
+            result?;
+        }
+        let result = util::retry_interrupted(|| self.writer.as_inner_mut().flush());
+        self.finish_state.record_flush(&result);
+        result
+    }
+
+    fn finish_stream(&mut self) -> io::Result<()> {
+        self.writer.write_end_of_stream()?;
         let trailer = Trailer {
-            crc32: self.crc32.value(),
+            crc32: match self.writer.parallel_checksum() {
+                Some(crc32) => crc32,
+                None => self.crc32.value()?,
+            },
             input_size: self.input_size,
         };
-        let mut inner = finish_try!(self.writer.finish());
-        match trailer.write_to(&mut inner).and_then(|_| inner.flush()) {
-            Ok(_) => Finish::new(inner, None),
-            Err(e) => Finish::new(inner, Some(e)),
+        let inner = self.writer.as_inner_mut();
+        trailer.write_to(inner)?;
+        self.framing_size += TRAILER_SIZE;
+        Ok(())
+    }
+
+    /// Returns the number of the (uncompressed) bytes written to this encoder.
+    pub fn total_in(&self) -> u64 {
+        self.writer.total_in()
+    }
+
+    /// Returns the number of the (compressed) bytes written to the inner stream, including the header and the trailer.
+    ///
+    /// For an encoder made by [`Encoder::from_state`], the bytes written before the suspension are not counted.
+    /// See [`deflate::Encoder::total_out`] for the details.
+    pub fn total_out(&self) -> u64 {
+        self.writer.total_out() + self.framing_size
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this encoder (excluding the inner stream).
+    ///
+    /// See [`deflate::Encoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.writer.memory_usage()
+    }
+
+    /// Registers a callback which is invoked every time `interval` bytes of input data have been written.
+    ///
+    /// Note that the bytes of the header and the trailer are not counted.
+    /// See [`deflate::Encoder::set_progress_callback`] for the details.
+    pub fn set_progress_callback<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        self.writer.set_progress_callback(interval, callback)
+    }
+
+    /// Registers a callback which is invoked with the information of each DEFLATE block after it is written.
+    ///
+    /// See [`deflate::Encoder::set_block_callback`] for the details.
+    pub fn set_block_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&deflate::BlockInfo) + Send + Sync + 'static,
+    {
+        self.writer.set_block_callback(callback)
+    }
+
+    /// Registers a check which is invoked periodically to tell whether the encoding should be cancelled.
+    ///
+    /// See [`deflate::Encoder::set_cancellation_check`] for the details.
+    pub fn set_cancellation_check<F>(&mut self, is_cancelled: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.writer.set_cancellation_check(is_cancelled)
+    }
+
+    /// Ends the current message, i.e., makes all the data written so far decodable (by a sync flush).
+    ///
+    /// See [`deflate::Encoder::message_boundary`] for the details.
+    pub fn message_boundary(&mut self) -> io::Result<()> {
+        self.writer.message_boundary()
+    }
+
+    /// Changes the compression level of the subsequent data.
+    ///
+    /// See [`deflate::Encoder::set_level`] for the details.
+    /// Note that the level recorded in the header is not changed.
+    pub fn set_level(&mut self, level: lz77::CompressionLevel) -> io::Result<()> {
+        self.writer.set_level(level)
+    }
+
+    /// Changes the strategy to encode the subsequent blocks.
+    ///
+    /// See [`deflate::Encoder::set_strategy`] for the details.
+    pub fn set_strategy(&mut self, strategy: options::Strategy) -> io::Result<()> {
+        self.writer.set_strategy(strategy)
+    }
+
+    /// Returns the current state of this encoder (including the running checksum).
+    ///
+    /// See [`deflate::Encoder::state`] for the details.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Read, Write};
+    /// use libflate::gzip::{Decoder, Encoder};
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello ").unwrap();
+    /// let state = encoder.state().unwrap();
+    /// let written = encoder.into_inner();
+    ///
+    /// let mut encoder = Encoder::from_state(written, state);
+    /// encoder.write_all(b"World!").unwrap();
+    /// let encoded_data = encoder.finish().into_result().unwrap();
+    ///
+    /// let mut decoded_data = Vec::new();
+    /// Decoder::new(&encoded_data[..]).unwrap().read_to_end(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data, b"Hello World!");
+    /// ```
+    pub fn state(&self) -> io::Result<EncoderState<E>>
+    where
+        E: Clone,
+    {
+        Ok(EncoderState {
+            header: self.header.clone(),
+            crc32: self
+                .crc32
+                .peek()
+                .ok_or_else(util::pipelined_checksum_state_error)?,
+            input_size: self.input_size,
+            deflate: self.writer.state()?,
+        })
+    }
+
+    /// Makes an encoder instance which resumes the encoding from `state`.
+    ///
+    /// The GZIP header is not written again.
+    pub fn from_state(inner: W, state: EncoderState<E>) -> Self {
+        Encoder {
+            header: state.header,
+            crc32: checksum::StreamChecksum::new(checksum::Crc32::from_value(state.crc32)),
+            input_size: state.input_size,
+            writer: deflate::Encoder::from_state(inner, state.deflate),
+            finish_state: FinishState::default(),
+            framing_size: 0,
         }
     }
 
@@ -894,10 +1799,73 @@ where
         self.writer.as_inner_mut()
     }
 
+    /// Wraps this encoder with [`AutoFinish`], which finishes the encoding when it is dropped.
+    ///
+    /// This prevents a stream from being silently truncated because of a missing call of [`Encoder::finish`].
+    /// If finishing the encoding fails, the drop panics.
+    ///
+    /// To recover the inner stream (or to handle the finishing error),
+    /// call [`AutoFinish::into_inner`] and then [`Encoder::finish`] instead of dropping the wrapper.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::gzip::Encoder;
+    ///
+    /// let mut buf = Vec::new();
+    /// {
+    ///     let mut encoder = Encoder::new(&mut buf).unwrap().auto_finish();
+    ///     encoder.write_all(b"Hello World!").unwrap();
+    /// } // The encoding is finished here
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap().auto_finish();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.into_inner().finish().into_result().unwrap();
+    /// assert_eq!(encoded_data, buf);
+    /// ```
+    pub fn auto_finish(self) -> AutoFinish<Self> {
+        AutoFinish::new(self)
+    }
+
     /// Unwraps the `Encoder`, returning the inner stream.
+    ///
+    /// Nothing is written to the inner stream by this method (see [`Encoder::abort`]).
     pub fn into_inner(self) -> W {
         self.writer.into_inner()
     }
+
+    /// Aborts the encoding, returning the inner stream.
+    ///
+    /// The buffered input data are discarded, and the final block and the trailer (CRC32 and size) are not written,
+    /// so the data written to the inner stream so far do not form a complete stream.
+    /// This is useful to reclaim the inner stream (e.g., a socket) when the encoding is cancelled midway.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::gzip::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let written = encoder.abort();
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// assert!(encoder.finish().into_result().unwrap().len() > written.len());
+    /// ```
+    pub fn abort(self) -> W {
+        self.into_inner()
+    }
+
+    pub(crate) fn sync_flush(&mut self) -> io::Result<()> {
+        self.writer.zlib_sync_flush()
+    }
 }
 impl<W, E> io::Write for Encoder<W, E>
 where
@@ -906,7 +1874,27 @@ where
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let written_size = self.writer.write(buf)?;
-        self.crc32.update(&buf[..written_size]);
+        if !self.writer.is_parallel() {
+            self.crc32.update(&buf[..written_size]);
+        }
+        self.input_size = self.input_size.wrapping_add(written_size as u32);
+        Ok(written_size)
+    }
+
+    /// Writes all the data of `bufs`.
+    ///
+    /// See [`deflate::Encoder`]'s implementation for the details.
+    #[cfg(not(feature = "no_std"))]
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let written_size = self.writer.write_vectored(bufs)?;
+        if !self.writer.is_parallel() {
+            let mut rest = written_size;
+            for buf in bufs {
+                let size = buf.len().min(rest);
+                self.crc32.update(&buf[..size]);
+                rest -= size;
+            }
+        }
         self.input_size = self.input_size.wrapping_add(written_size as u32);
         Ok(written_size)
     }
@@ -929,8 +1917,10 @@ where
 pub struct Decoder<R> {
     header: Header,
     reader: deflate::Decoder<R>,
-    crc32: checksum::Crc32,
+    crc32: checksum::StreamChecksum<checksum::Crc32>,
     eos: bool,
+    framing_size: u64,
+    header_size: u64,
 }
 impl<R> Decoder<R>
 where
@@ -958,9 +1948,45 @@ where
     ///
     /// assert_eq!(buf, b"Hello World!");
     /// ```
-    pub fn new(mut inner: R) -> io::Result<Self> {
-        let header = Header::read_from(&mut inner)?;
-        Ok(Self::with_header(inner, header))
+    pub fn new(inner: R) -> io::Result<Self> {
+        Self::with_options(inner, DecodeOptions::new())
+    }
+
+    /// Makes a new decoder instance with the specified options.
+    ///
+    /// `inner` is to be decoded GZIP stream.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::gzip::{Decoder, DecodeOptions};
+    ///
+    /// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+    ///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+    ///                     163, 28, 41, 28, 12, 0, 0, 0];
+    ///
+    /// let mut decoder = Decoder::with_options(&encoded_data[..], DecodeOptions::strict()).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn with_options(mut inner: R, options: DecodeOptions) -> io::Result<Self> {
+        let (header, header_size) = read_header(&mut inner, &options)?;
+        Ok(Self::with_header(
+            inner,
+            header,
+            header_size,
+            options.options,
+        ))
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn as_deflate_mut(&mut self) -> &mut deflate::Decoder<R> {
+        &mut self.reader
     }
 
     /// Returns the header of the GZIP stream.
@@ -980,6 +2006,46 @@ where
         &self.header
     }
 
+    /// Returns the number of the (compressed) bytes read from the inner stream, including the header and the trailer.
+    ///
+    /// See [`deflate::Decoder::total_in`] for the details.
+    pub fn total_in(&self) -> u64 {
+        self.reader.total_in() + self.framing_size
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        self.reader.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder (excluding the inner stream).
+    ///
+    /// See [`deflate::Decoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.reader.memory_usage()
+    }
+
+    /// Registers a callback which is invoked every time `interval` bytes of the DEFLATE stream have been read.
+    ///
+    /// Note that the bytes of the header and the trailer are not counted.
+    /// See [`deflate::Decoder::set_progress_callback`] for the details.
+    pub fn set_progress_callback<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        self.reader.set_progress_callback(interval, callback)
+    }
+
+    /// Registers a check which is invoked periodically to tell whether the decoding should be cancelled.
+    ///
+    /// See [`deflate::Decoder::set_cancellation_check`] for the details.
+    pub fn set_cancellation_check<F>(&mut self, is_cancelled: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.reader.set_cancellation_check(is_cancelled)
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
         self.reader.as_inner_ref()
@@ -992,6 +2058,8 @@ where
 
     /// Unwraps this `Decoder`, returning the underlying reader.
     ///
+    /// If the end of the stream has been reached, the reader is positioned just after the stream.
+    ///
     /// # Examples
     /// ```
     /// #[cfg(feature = "no_std")]
@@ -1018,20 +2086,97 @@ where
         self.reader.unread_decoded_data()
     }
 
-    fn with_header(inner: R, header: Header) -> Self {
+    /// Discards the next `n` decoded bytes without copying them.
+    ///
+    /// The data are decoded into the internal buffer of the decoder only, so no output buffer is allocated.
+    /// Returns the number of the discarded bytes, which is less than `n` only if the end of the stream is reached.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::gzip::Decoder;
+    ///
+    /// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+    ///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+    ///                     163, 28, 41, 28, 12, 0, 0, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// assert_eq!(decoder.skip(6).unwrap(), 6);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"World!");
+    /// ```
+    pub fn skip(&mut self, n: u64) -> io::Result<u64> {
+        util::skip(self, n)
+    }
+
+    /// Returns an iterator which yields the decoded data in chunks of `chunk_size` bytes.
+    ///
+    /// See [`read::Chunks`] for the details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::gzip::Decoder;
+    ///
+    /// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+    ///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+    ///                     163, 28, 41, 28, 12, 0, 0, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// let chunks = decoder.chunks(5).collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(chunks, [&b"Hello"[..], b" Worl", b"d!"]);
+    /// ```
+    pub fn chunks(&mut self, chunk_size: usize) -> read::Chunks<&mut Self> {
+        read::Chunks::new(self, chunk_size)
+    }
+
+    fn with_header(
+        inner: R,
+        header: Header,
+        header_size: u64,
+        options: deflate::DecodeOptions,
+    ) -> Self {
+        let pipelined = options.get_pipelined_checksum();
         Decoder {
             header,
-            reader: deflate::Decoder::new(inner),
-            crc32: checksum::Crc32::new(),
+            reader: deflate::Decoder::with_options(inner, options),
+            crc32: checksum::StreamChecksum::with_pipelining(checksum::Crc32::new(), pipelined),
             eos: false,
+            framing_size: header_size,
+            header_size,
         }
     }
 
-    fn reset(&mut self, header: Header) {
+    fn reset(&mut self, header: Header, header_size: u64) {
         self.header = header;
         self.reader.reset();
-        self.crc32 = checksum::Crc32::new();
+        self.crc32.reset();
         self.eos = false;
+        self.framing_size += header_size;
+        self.header_size = header_size;
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        self.eos = true;
+        let trailer = Trailer::read_from(self.reader.as_inner_mut())?;
+        self.framing_size += TRAILER_SIZE;
+        // checksum verification is skipped during fuzzing
+        // so that random data from fuzzer can reach actually interesting code
+        // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
+        let actual = self.crc32.value()?;
+        if cfg!(not(fuzzing)) && trailer.crc32 != actual {
+            Err(Error::new(ErrorKind::ChecksumMismatch, "CRC32 mismatched")
+                .with_value(actual)
+                .with_expected(trailer.crc32)
+                .into())
+        } else {
+            Ok(())
+        }
     }
 }
 impl<R> io::Read for Decoder<R>
@@ -1044,36 +2189,113 @@ where
         } else {
             let read_size = self.reader.read(buf)?;
             self.crc32.update(&buf[..read_size]);
-            if read_size == 0 {
-                if buf.is_empty() {
-                    return Ok(0);
-                }
-
-                self.eos = true;
-                let trailer = Trailer::read_from(self.reader.as_inner_mut())?;
-                // checksum verification is skipped during fuzzing
-                // so that random data from fuzzer can reach actually interesting code
-                // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
-                if cfg!(not(fuzzing)) && trailer.crc32 != self.crc32.value() {
-                    Err(invalid_data_error!(
-                        "CRC32 mismatched: value={}, expected={}",
-                        self.crc32.value(),
-                        trailer.crc32
-                    ))
-                } else {
-                    Ok(0)
-                }
-            } else {
-                Ok(read_size)
+            if read_size == 0 && !buf.is_empty() {
+                self.read_trailer()?;
             }
+            Ok(read_size)
         }
     }
+
+    /// Fills `bufs` in order.
+    ///
+    /// See [`deflate::Decoder`]'s implementation for the details.
+    #[cfg(not(feature = "no_std"))]
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        util::read_vectored(self, bufs, |this| this.reader.has_buffered_data())
+    }
+}
+
+impl<R> util::Rewind for Decoder<R>
+where
+    R: io::Read + io::Seek,
+{
+    fn position(&self) -> u64 {
+        self.total_out()
+    }
+    fn rewind(&mut self) -> io::Result<()> {
+        let read = self.total_in();
+        let start = util::stream_start(self.reader.as_inner_mut(), read)?;
+        self.reader.rewind_to(start + self.header_size)?;
+        self.crc32.reset();
+        self.eos = false;
+        self.framing_size = self.header_size;
+        Ok(())
+    }
+}
+
+/// Seeks in the decoded data.
+///
+/// Seeking backward rewinds the inner stream to the start of the DEFLATE stream (just after the GZIP header).
+/// See [`deflate::Decoder`]'s implementation for the details.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::{Cursor, Read, Seek, SeekFrom};
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::{Cursor, Read, Seek, SeekFrom};
+/// use libflate::gzip::Decoder;
+///
+/// let encoded_data = [31, 139, 8, 0, 123, 0, 0, 0, 0, 3, 1, 12, 0, 243, 255,
+///                     72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33,
+///                     163, 28, 41, 28, 12, 0, 0, 0];
+/// let mut decoder = Decoder::new(Cursor::new(&encoded_data[..])).unwrap();
+///
+/// let mut buf = [0; 5];
+/// decoder.seek(SeekFrom::Start(6)).unwrap();
+/// decoder.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"World");
+/// decoder.seek(SeekFrom::Start(0)).unwrap();
+/// decoder.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"Hello");
+/// ```
+impl<R> io::Seek for Decoder<R>
+where
+    R: io::Read + io::Seek,
+{
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        util::seek(self, pos)
+    }
+}
+
+/// Exposes the buffer of the decoded data, so that they can be processed without copying.
+///
+/// The CRC32 checksum is verified when [`BufRead::fill_buf`](io::BufRead::fill_buf)
+/// reaches the end of the stream.
+impl<R> io::BufRead for Decoder<R>
+where
+    R: io::Read,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.eos && self.reader.fill_buf()?.is_empty() {
+            self.read_trailer()?;
+        }
+        Ok(self.reader.unread_decoded_data())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let buffered = self.reader.unread_decoded_data();
+        let amt = core::cmp::min(amt, buffered.len());
+        self.crc32.update(&buffered[..amt]);
+        self.reader.consume(amt);
+    }
+}
+
+/// Reads a GZIP header, returning it with its size in bytes.
+fn read_header<R>(inner: &mut R, options: &DecodeOptions) -> io::Result<(Header, u64)>
+where
+    R: io::Read,
+{
+    let mut counter = util::Counter::new(inner);
+    let header = Header::read_with_options(&mut counter, options)?;
+    Ok((header, counter.count()))
 }
 
 /// A decoder that decodes all members in a GZIP stream.
 #[derive(Debug)]
 pub struct MultiDecoder<R> {
     decoder: Decoder<R>,
+    options: DecodeOptions,
     eos: bool,
 }
 impl<R> MultiDecoder<R>
@@ -1109,9 +2331,17 @@ where
     /// assert_eq!(buf, b"Hello World!");
     /// ```
     pub fn new(inner: R) -> io::Result<Self> {
-        let decoder = Decoder::new(inner)?;
+        Self::with_options(inner, DecodeOptions::new())
+    }
+
+    /// Makes a new decoder instance with the specified options.
+    ///
+    /// The options are applied to all members in the GZIP stream.
+    pub fn with_options(inner: R, options: DecodeOptions) -> io::Result<Self> {
+        let decoder = Decoder::with_options(inner, options.clone())?;
         Ok(MultiDecoder {
             decoder,
+            options,
             eos: false,
         })
     }
@@ -1133,6 +2363,23 @@ where
         self.decoder.header()
     }
 
+    /// Returns the number of the (compressed) bytes of all the members read from the inner stream.
+    pub fn total_in(&self) -> u64 {
+        self.decoder.total_in()
+    }
+
+    /// Returns the number of the decoded bytes of all the members read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        self.decoder.total_out()
+    }
+
+    /// Discards the next `n` decoded bytes (of the concatenated members) without copying them.
+    ///
+    /// See [`Decoder::skip`] for the details.
+    pub fn skip(&mut self, n: u64) -> io::Result<u64> {
+        util::skip(self, n)
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
         self.decoder.as_inner_ref()
@@ -1175,7 +2422,7 @@ where
 
         let read_size = self.decoder.read(buf)?;
         if read_size == 0 {
-            match Header::read_from(self.as_inner_mut()) {
+            match read_header(self.decoder.as_inner_mut(), &self.options) {
                 Err(e) => {
                     if e.kind() == io::ErrorKind::UnexpectedEof {
                         self.eos = true;
@@ -1184,8 +2431,8 @@ where
                         Err(e)
                     }
                 }
-                Ok(header) => {
-                    self.decoder.reset(header);
+                Ok((header, header_size)) => {
+                    self.decoder.reset(header, header_size);
                     self.read(buf)
                 }
             }
@@ -1193,6 +2440,201 @@ where
             Ok(read_size)
         }
     }
+
+    /// Fills `bufs` in order.
+    ///
+    /// See [`deflate::Decoder`]'s implementation for the details.
+    #[cfg(not(feature = "no_std"))]
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        util::read_vectored(self, bufs, |this| this.decoder.reader.has_buffered_data())
+    }
+}
+
+/// Exposes the buffer of the decoded data, so that they can be processed without copying.
+impl<R> io::BufRead for MultiDecoder<R>
+where
+    R: io::Read,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while !self.eos && self.decoder.fill_buf()?.is_empty() {
+            match read_header(self.decoder.as_inner_mut(), &self.options) {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => self.eos = true,
+                Err(e) => return Err(e),
+                Ok((header, header_size)) => self.decoder.reset(header, header_size),
+            }
+        }
+        Ok(self.decoder.unread_decoded_data())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.decoder.consume(amt)
+    }
+}
+
+/// Returns an upper bound of the size of the GZIP stream made from `input_len` bytes,
+/// including the trailer (8 bytes) and a header without optional fields (10 bytes).
+///
+/// If the header has optional fields (e.g., the filename), add their encoded sizes to the bound.
+/// See [`deflate::max_compressed_len`] for the other conditions.
+///
+/// # Examples
+/// ```
+/// use libflate::gzip;
+///
+/// let encoded_data = gzip::compress_to_vec(&[0; 1000]).unwrap();
+/// assert!(encoded_data.len() <= gzip::max_compressed_len(1000));
+/// ```
+pub fn max_compressed_len(input_len: usize) -> usize {
+    deflate::max_compressed_len(input_len).saturating_add((MIN_HEADER_SIZE + TRAILER_SIZE) as usize)
+}
+
+/// Compresses `input` into a GZIP stream with the default options (and the default header).
+///
+/// The capacity of the returned vector is reserved in advance from the worst case of the compressed size,
+/// so the vector is never reallocated.
+/// As with [`deflate::compress_to_vec`], the buffers of the encoder are reused across the calls on the same thread.
+///
+/// # Examples
+/// ```
+/// use libflate::gzip;
+///
+/// let encoded_data = gzip::compress_to_vec(b"Hello World!").unwrap();
+/// assert_eq!(gzip::decompress_to_vec(&encoded_data).unwrap(), b"Hello World!");
+/// ```
+pub fn compress_to_vec(input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    util::try_reserve(&mut buf, max_compressed_len(input.len()))?;
+    #[cfg(not(feature = "no_std"))]
+    {
+        crate::pool::with_local_pool(|pool| {
+            let mut encoder = pool.gzip_encoder(buf)?;
+            io::Write::write_all(&mut encoder, input)?;
+            encoder.finish().into_result()
+        })
+    }
+    #[cfg(feature = "no_std")]
+    {
+        let mut encoder = Encoder::new(buf)?;
+        io::Write::write_all(&mut encoder, input)?;
+        encoder.finish().into_result()
+    }
+}
+
+/// Decompresses the (first member of the) GZIP stream `input`.
+///
+/// The capacity of the returned vector is reserved in advance from the ISIZE field of the trailer
+/// (the size of the decompressed data modulo 2^32).
+/// Since the field may be corrupted (or forged), the capacity is limited
+/// by the maximum compression ratio of DEFLATE (1032:1).
+pub fn decompress_to_vec(input: &[u8]) -> io::Result<Vec<u8>> {
+    decompress_to_vec_with_capacity(input, trailer_capacity_hint(input))
+}
+
+/// Returns the decompressed size recorded in the ISIZE field of the last trailer in `input`,
+/// limited by the maximum compression ratio of DEFLATE.
+fn trailer_capacity_hint(input: &[u8]) -> usize {
+    if input.len() >= (MIN_HEADER_SIZE + TRAILER_SIZE) as usize {
+        let mut isize = [0; 4];
+        isize.copy_from_slice(&input[input.len() - 4..]);
+        isize_capacity_hint(isize, input.len())
+    } else {
+        0
+    }
+}
+
+/// Returns the decompressed size recorded in the ISIZE field `isize`,
+/// limited by the maximum compression ratio of DEFLATE for `input_len` bytes of input.
+fn isize_capacity_hint(isize: [u8; 4], input_len: usize) -> usize {
+    let isize = u32::from_le_bytes(isize) as usize;
+    core::cmp::min(isize, input_len.saturating_mul(util::MAX_EXPANSION_RATIO))
+}
+
+/// Decompresses the (first member of the) GZIP stream `input`,
+/// reserving `capacity` bytes for the decompressed data in advance.
+///
+/// The vector grows as usual if `capacity` is insufficient.
+/// As with [`deflate::decompress_to_vec_with_capacity`], the sliding window is reused across the calls on the same thread.
+pub fn decompress_to_vec_with_capacity(input: &[u8], capacity: usize) -> io::Result<Vec<u8>> {
+    #[cfg(not(feature = "no_std"))]
+    {
+        crate::pool::with_local_pool(|pool| util::read_to_vec(pool.gzip_decoder(input)?, capacity))
+    }
+    #[cfg(feature = "no_std")]
+    {
+        util::read_to_vec(Decoder::new(input)?, capacity)
+    }
+}
+
+/// Decompresses the GZIP file at `path`.
+///
+/// Unlike [`decompress_to_vec`], all the members in the file are decoded (as `gzip -d` does).
+/// The file is read in a streaming manner (through a [`MultiDecoder`]), so only the decoded data are held in memory.
+/// The vector for them is reserved in advance according to the ISIZE field of the last trailer.
+///
+/// This crate does not memory-map files
+/// since the mapping can not be made sound (the file may be modified by another process) without `unsafe`.
+///
+/// # Examples
+/// ```
+/// use libflate::gzip;
+///
+/// let path = std::env::temp_dir().join("libflate_decode_file_example.gz");
+/// std::fs::write(&path, gzip::compress_to_vec(b"Hello World!").unwrap()).unwrap();
+///
+/// assert_eq!(gzip::decode_file(&path).unwrap(), b"Hello World!");
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[cfg(not(feature = "no_std"))]
+pub fn decode_file<P: AsRef<path::Path>>(path: P) -> io::Result<Vec<u8>> {
+    use std::convert::TryFrom;
+    use std::io::{Read as _, Seek as _};
+
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut capacity = 0;
+    if len >= MIN_HEADER_SIZE + TRAILER_SIZE {
+        let mut isize = [0; 4];
+        file.seek(io::SeekFrom::Start(len - 4))?;
+        file.read_exact(&mut isize)?;
+        file.rewind()?;
+        capacity = isize_capacity_hint(isize, usize::try_from(len).unwrap_or(usize::MAX));
+    }
+    util::read_to_vec(MultiDecoder::new(io::BufReader::new(file))?, capacity)
+}
+
+/// Compresses the file at `src` into the GZIP file at `dst` with `options`.
+///
+/// The input is read in a streaming manner, so the file is not loaded into memory at once.
+/// If `dst` exists, it is truncated.
+///
+/// Returns the number of the bytes read from `src`.
+///
+/// # Examples
+/// ```
+/// use libflate::gzip::{self, EncodeOptions};
+///
+/// let src = std::env::temp_dir().join("libflate_encode_file_example.txt");
+/// let dst = std::env::temp_dir().join("libflate_encode_file_example.txt.gz");
+/// std::fs::write(&src, b"Hello World!").unwrap();
+///
+/// assert_eq!(gzip::encode_file(&src, &dst, EncodeOptions::new()).unwrap(), 12);
+/// assert_eq!(gzip::decode_file(&dst).unwrap(), b"Hello World!");
+/// # std::fs::remove_file(&src).unwrap();
+/// # std::fs::remove_file(&dst).unwrap();
+/// ```
+#[cfg(not(feature = "no_std"))]
+pub fn encode_file<P, Q, E>(src: P, dst: Q, options: EncodeOptions<E>) -> io::Result<u64>
+where
+    P: AsRef<path::Path>,
+    Q: AsRef<path::Path>,
+    E: lz77::Lz77Encode,
+{
+    let mut input = fs::File::open(src)?;
+    let output = io::BufWriter::new(fs::File::create(dst)?);
+    let mut encoder = Encoder::with_options(output, options)?;
+    let size = io::copy(&mut input, &mut encoder)?;
+    io::Write::flush(&mut encoder.finish().into_result()?)?;
+    Ok(size)
 }
 
 #[cfg(test)]
@@ -1200,9 +2642,9 @@ mod tests {
     use super::*;
     use crate::finish::AutoFinish;
     #[cfg(feature = "no_std")]
-    use core2::io::{Read, Write};
+    use core2::io::{BufRead, Read, Write};
     #[cfg(not(feature = "no_std"))]
-    use std::io::{self, Read, Write};
+    use std::io::{self, BufRead, Read, Write};
 
     fn decode(buf: &[u8]) -> io::Result<Vec<u8>> {
         let mut decoder = Decoder::new(buf).unwrap();
@@ -1246,15 +2688,8 @@ mod tests {
 
     #[test]
     fn multi_decode_works() {
-        #[cfg(feature = "no_std")]
-        use core::iter;
-        #[cfg(not(feature = "no_std"))]
-        use std::iter;
         let text = b"Hello World!";
-        let encoded: Vec<u8> = iter::repeat(encode(text).unwrap())
-            .take(2)
-            .flat_map(|b| b)
-            .collect();
+        let encoded = [encode(text).unwrap(), encode(text).unwrap()].concat();
         assert_eq!(decode(&encoded).unwrap(), b"Hello World!");
         assert_eq!(decode_multi(&encoded).unwrap(), b"Hello World!Hello World!");
     }
@@ -1286,7 +2721,7 @@ mod tests {
         let data = encode(b"Hello World").unwrap();
         let mut decoder = Decoder::new(&data[..]).unwrap();
         let mut buf = Vec::new();
-        decoder.read(&mut buf).unwrap();
+        assert_eq!(decoder.read(&mut buf).unwrap(), 0);
         decoder.read_to_end(&mut buf).unwrap();
         assert_eq!(buf, b"Hello World");
     }
@@ -1303,7 +2738,7 @@ mod tests {
         let mut buf = Vec::new();
         f.write_to(&mut buf).unwrap();
 
-        assert_eq!(ExtraField::read_from(&buf[..]).unwrap(), f);
+        assert_eq!(ExtraField::read_from(&buf[..], None).unwrap(), f);
     }
 
     #[test]
@@ -1334,4 +2769,614 @@ mod tests {
             assert_eq!(decoder.header().extra_field(), Some(&extra_field));
         }
     }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn checksum_mismatch_is_reported_as_structured_error() {
+        let mut data = encode(b"Hello World").unwrap();
+        let len = data.len();
+        data[len - 8] ^= 1; // Corrupts CRC32 of the trailer
+
+        let error = decode(&data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = crate::Error::from_io_error(&error).unwrap();
+        assert_eq!(error.kind(), crate::error::ErrorKind::ChecksumMismatch);
+        assert_ne!(error.value(), error.expected());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn header_field_limits_work() {
+        let header = HeaderBuilder::new()
+            .filename(CString::new("foo.txt").unwrap())
+            .comment(CString::new("bar").unwrap())
+            .extra_field(ExtraField {
+                subfields: vec![ExtraSubField {
+                    id: [0, 1],
+                    data: vec![2; 10],
+                }],
+            })
+            .finish();
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+        encoder.write_all(b"baz").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let limit_of = |options: DecodeOptions| {
+            Decoder::with_options(&encoded[..], options)
+                .err()
+                .map(|e| Error::from_io_error(&e).map(|e| (e.kind(), e.message())))
+        };
+        assert_eq!(limit_of(DecodeOptions::strict()), None);
+        assert_eq!(
+            limit_of(DecodeOptions::new().max_filename_len(7).max_comment_len(3)),
+            None
+        );
+        assert_eq!(
+            limit_of(DecodeOptions::new().max_filename_len(6)),
+            Some(Some((
+                ErrorKind::LimitExceeded,
+                "The FNAME field of GZIP header is too long"
+            )))
+        );
+        assert_eq!(
+            limit_of(DecodeOptions::new().max_comment_len(2)),
+            Some(Some((
+                ErrorKind::LimitExceeded,
+                "The FCOMMENT field of GZIP header is too long"
+            )))
+        );
+        assert_eq!(
+            limit_of(DecodeOptions::new().max_extra_field_len(13)),
+            Some(Some((
+                ErrorKind::LimitExceeded,
+                "The FEXTRA field of GZIP header is too long"
+            )))
+        );
+
+        // The limits are applied to all members
+        let header = HeaderBuilder::new()
+            .comment(CString::new("qux!").unwrap())
+            .finish();
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+        encoder.write_all(b"quux").unwrap();
+        let mut multi = encoded.clone();
+        multi.extend_from_slice(&encoder.finish().into_result().unwrap());
+
+        let mut decoder = MultiDecoder::with_options(&multi[..], DecodeOptions::new()).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"bazquux");
+
+        let mut decoder =
+            MultiDecoder::with_options(&multi[..], DecodeOptions::new().max_comment_len(3))
+                .unwrap();
+        let error = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(
+            Error::from_io_error(&error).map(|e| e.kind()),
+            Some(ErrorKind::LimitExceeded)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn encoder_state_can_be_serialized() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let options = EncodeOptions::new().block_size(10_000);
+        let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(&plain[..55_555]).unwrap();
+        let state = serde_json::to_string(&encoder.state().unwrap()).unwrap();
+        let written = encoder.into_inner();
+
+        let state: EncoderState = serde_json::from_str(&state).unwrap();
+        let mut encoder = Encoder::from_state(written, state);
+        encoder.write_all(&plain[55_555..]).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(decode(&encoded).unwrap(), plain);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn inconsistent_encoder_state_is_rejected() {
+        fn replace(value: &mut serde_json::Value, key: &str, new: &serde_json::Value) -> bool {
+            match value {
+                serde_json::Value::Object(map) => {
+                    if let Some(v) = map.get_mut(key) {
+                        *v = new.clone();
+                        return true;
+                    }
+                    map.values_mut().any(|v| replace(v, key, new))
+                }
+                serde_json::Value::Array(vec) => vec.iter_mut().any(|v| replace(v, key, new)),
+                _ => false,
+            }
+        }
+
+        // A small window makes the LZ77 encoder emit pointers before the block is finished
+        let lz77 = lz77::DefaultLz77Encoder::with_window_size(16);
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::with_lz77(lz77)).unwrap();
+        encoder.write_all(&b"Hello World! ".repeat(20)).unwrap();
+        let state = serde_json::to_value(encoder.state().unwrap()).unwrap();
+        assert!(serde_json::from_value::<EncoderState>(state.clone()).is_ok());
+
+        for (key, new) in [
+            ("pending_bits", serde_json::json!([0xFFFF, 3])),
+            ("block_size", serde_json::json!(0)),
+            ("window_size", serde_json::json!(u16::MAX)),
+            ("backward_distance", serde_json::json!(0)),
+        ] {
+            let mut corrupted = state.clone();
+            assert!(replace(&mut corrupted, key, &new), "{}", key);
+            assert!(
+                serde_json::from_value::<EncoderState>(corrupted).is_err(),
+                "{}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn vectored_write_error_after_prefix_is_deferred() {
+        // A writer which accepts up to 3000 bytes
+        struct Full(Vec<u8>);
+        impl Write for Full {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let size = buf.len().min(3000 - self.0.len());
+                if size == 0 {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+                }
+                self.0.extend_from_slice(&buf[..size]);
+                Ok(size)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let plain = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let options = EncodeOptions::new().no_compression().block_size(1000);
+        let mut encoder = Encoder::with_options(Full(Vec::new()), options).unwrap();
+        let bufs = plain.chunks(1000).map(io::IoSlice::new).collect::<Vec<_>>();
+        let size = encoder.write_vectored(&bufs).unwrap();
+        assert!(size > 0 && size < plain.len());
+        assert_eq!(size % 1000, 0);
+        assert!(encoder.write_vectored(&bufs[size / 1000..]).is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn vectored_io_works() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut encoder = Encoder::new(Vec::new()).unwrap();
+        let bufs = plain.chunks(1000).map(io::IoSlice::new).collect::<Vec<_>>();
+        assert_eq!(encoder.write_vectored(&bufs).unwrap(), plain.len());
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        let mut decoded = vec![0; plain.len() + 1];
+        let mut offset = 0;
+        loop {
+            let rest = &mut decoded[offset..];
+            let (head, tail) = rest.split_at_mut(rest.len().min(3));
+            let mut bufs = [io::IoSliceMut::new(head), io::IoSliceMut::new(tail)];
+            let size = decoder.read_vectored(&mut bufs).unwrap();
+            if size == 0 {
+                break;
+            }
+            offset += size;
+        }
+        assert_eq!(offset, plain.len());
+        assert_eq!(&decoded[..offset], &plain[..]);
+    }
+
+    #[test]
+    fn buf_read_works() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let encoded = encode(&plain).unwrap();
+
+        // Consume the buffered data bit by bit, mixed with `read`
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        loop {
+            let buf = decoder.fill_buf().unwrap();
+            if buf.is_empty() {
+                break;
+            }
+            let size = buf.len().min(1000);
+            decoded.extend_from_slice(&buf[..size]);
+            decoder.consume(size);
+
+            let mut buf = [0; 10];
+            let size = decoder.read(&mut buf).unwrap();
+            decoded.extend_from_slice(&buf[..size]);
+        }
+        assert_eq!(decoded, plain);
+
+        // The checksum is verified
+        let mut corrupted = encoded.clone();
+        let crc32_offset = corrupted.len() - 8;
+        corrupted[crc32_offset] ^= 1;
+        let mut decoder = Decoder::new(&corrupted[..]).unwrap();
+        loop {
+            match decoder.fill_buf() {
+                Ok([]) => panic!("The corruption is not detected"),
+                Ok(buf) => {
+                    let size = buf.len();
+                    decoder.consume(size);
+                }
+                Err(e) => {
+                    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                    break;
+                }
+            }
+        }
+
+        // Multiple members
+        let mut encoded_multi = encoded.clone();
+        encoded_multi.extend_from_slice(&encoded);
+        let mut decoder = MultiDecoder::new(&encoded_multi[..]).unwrap();
+        let mut decoded = Vec::new();
+        loop {
+            let buf = decoder.fill_buf().unwrap();
+            if buf.is_empty() {
+                break;
+            }
+            let size = buf.len();
+            decoded.extend_from_slice(buf);
+            decoder.consume(size);
+        }
+        assert_eq!(decoded.len(), plain.len() * 2);
+    }
+
+    #[test]
+    fn total_in_and_total_out_work() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let header = HeaderBuilder::new()
+            .filename(CString::new("foo").unwrap())
+            .finish();
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::new().header(header)).unwrap();
+        encoder.write_all(&plain).unwrap();
+        assert_eq!(encoder.total_in(), plain.len() as u64);
+        encoder.try_finish().unwrap();
+        assert_eq!(encoder.total_out(), encoder.as_inner_ref().len() as u64);
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        io::copy(&mut decoder, &mut io::sink()).unwrap();
+        assert_eq!(decoder.total_in(), encoded.len() as u64);
+        assert_eq!(decoder.total_out(), plain.len() as u64);
+
+        let mut encoded_multi = encoded.clone();
+        encoded_multi.extend_from_slice(&encoded);
+        let mut decoder = MultiDecoder::new(&encoded_multi[..]).unwrap();
+        io::copy(&mut decoder, &mut io::sink()).unwrap();
+        assert_eq!(decoder.total_in(), encoded_multi.len() as u64);
+        assert_eq!(decoder.total_out(), plain.len() as u64 * 2);
+    }
+
+    #[test]
+    fn to_vec_functions_work() {
+        let mut seed = 1u32;
+        let plain = (0..300_000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect::<Vec<_>>();
+
+        // The vector is not reallocated even for incompressible data
+        let encoded = compress_to_vec(&plain).unwrap();
+        assert_eq!(encoded.capacity(), max_compressed_len(plain.len()));
+
+        let decoded = decompress_to_vec(&encoded).unwrap();
+        assert_eq!(decoded, plain);
+        assert_eq!(decoded.capacity(), plain.len());
+
+        // A forged ISIZE doesn't make a huge allocation
+        let mut forged = encode(b"Hello World!").unwrap();
+        let isize_offset = forged.len() - 4;
+        forged[isize_offset..].copy_from_slice(&u32::MAX.to_le_bytes());
+        let decoded = decompress_to_vec(&forged).unwrap();
+        assert_eq!(decoded, b"Hello World!");
+        assert!(decoded.capacity() <= forged.len() * util::MAX_EXPANSION_RATIO);
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn file_helpers_work() {
+        let dir = std::env::temp_dir();
+        let src = dir.join(format!("libflate_file_helpers_{}.txt", std::process::id()));
+        let dst = dir.join(format!(
+            "libflate_file_helpers_{}.txt.gz",
+            std::process::id()
+        ));
+        let plain = (0..300_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        fs::write(&src, &plain).unwrap();
+
+        let size = encode_file(&src, &dst, EncodeOptions::new()).unwrap();
+        assert_eq!(size, plain.len() as u64);
+        let decoded = decode_file(&dst).unwrap();
+        assert_eq!(decoded, plain);
+        assert_eq!(decoded.capacity(), plain.len());
+
+        // A truncated file is an error
+        let encoded = fs::read(&dst).unwrap();
+        fs::write(&dst, &encoded[..encoded.len() / 2]).unwrap();
+        assert!(decode_file(&dst).is_err());
+        fs::write(&dst, &encoded).unwrap();
+
+        // All the members are decoded
+        let mut multi = fs::read(&dst).unwrap();
+        multi.extend_from_slice(&compress_to_vec(b"Hello World!").unwrap());
+        fs::write(&dst, &multi).unwrap();
+        let decoded = decode_file(&dst).unwrap();
+        assert_eq!(&decoded[..plain.len()], &plain[..]);
+        assert_eq!(&decoded[plain.len()..], b"Hello World!");
+
+        fs::remove_file(&src).unwrap();
+        fs::remove_file(&dst).unwrap();
+        assert!(decode_file(&dst).is_err());
+    }
+
+    #[test]
+    fn seek_works() {
+        #[cfg(feature = "no_std")]
+        use core2::io::{Cursor, Seek, SeekFrom};
+        #[cfg(not(feature = "no_std"))]
+        use std::io::{Cursor, Seek, SeekFrom};
+
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for options in [EncodeOptions::new(), EncodeOptions::new().no_compression()] {
+            let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+
+            // The GZIP stream starts in the middle of the inner stream
+            let mut data = b"prefix".to_vec();
+            data.extend_from_slice(&encoded);
+            let mut inner = Cursor::new(data);
+            inner.set_position(6);
+            let mut decoder = Decoder::new(inner).unwrap();
+
+            let mut buf = [0; 100];
+            for &(pos, expected) in &[
+                (SeekFrom::Start(50_000), 50_000),
+                (SeekFrom::Current(-20_000), 30_200),
+                (SeekFrom::End(-100), 99_900),
+                (SeekFrom::Start(10), 10),
+                (SeekFrom::Current(1000), 1210),
+            ] {
+                if let SeekFrom::Current(_) = pos {
+                    decoder.read_exact(&mut buf).unwrap();
+                }
+                let expected = expected as usize;
+                assert_eq!(decoder.seek(pos).unwrap(), expected as u64);
+                decoder.read_exact(&mut buf).unwrap();
+                assert_eq!(buf[..], plain[expected..][..100]);
+            }
+            assert_eq!(decoder.seek(SeekFrom::Start(200_000)).unwrap(), 100_000);
+            assert!(decoder.seek(SeekFrom::Current(-200_000)).is_err());
+
+            // The CRC32 is verified also after a rewind
+            decoder.seek(SeekFrom::Start(0)).unwrap();
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, plain);
+        }
+    }
+
+    #[test]
+    fn no_bytes_are_read_past_the_trailer() {
+        let mut encoded = compress_to_vec(b"Hello World!").unwrap();
+        encoded.extend_from_slice(b"trailing");
+
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, b"Hello World!");
+        assert_eq!(decoder.into_inner(), b"trailing");
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn modification_system_time_works() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let max = UNIX_EPOCH + Duration::from_secs(u64::from(u32::MAX));
+        let header = HeaderBuilder::new()
+            .modification_system_time(max)
+            .unwrap()
+            .finish();
+        assert_eq!(header.modification_time(), u32::MAX);
+        assert_eq!(header.modification_system_time(), Some(max));
+
+        let error = HeaderBuilder::new()
+            .modification_system_time(max + Duration::from_secs(1))
+            .unwrap_err();
+        assert_eq!(
+            Error::from_io_error(&error).unwrap().kind(),
+            ErrorKind::InvalidHeader
+        );
+
+        let header = HeaderBuilder::new()
+            .modification_system_time(UNIX_EPOCH - Duration::from_millis(500))
+            .unwrap()
+            .finish();
+        assert_eq!(header.modification_system_time(), None);
+    }
+
+    #[test]
+    fn string_header_fields_are_validated() {
+        let header = HeaderBuilder::new()
+            .filename_str("r\u{e9}sum\u{e9}.txt")
+            .unwrap()
+            .comment_str_lossy("\u{732b}\0!")
+            .finish();
+        assert_eq!(header.filename().unwrap().as_bytes(), b"r\xe9sum\xe9.txt");
+        assert_eq!(header.comment().unwrap().as_bytes(), b"??!");
+
+        let error = HeaderBuilder::new().filename_str("\u{100}").unwrap_err();
+        let error = Error::from_io_error(&error).unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidHeader);
+        assert_eq!(error.value(), Some(0x100));
+
+        let extra = ExtraField {
+            subfields: vec![ExtraSubField {
+                id: [1, 2],
+                data: vec![0; 0xFFFF - 4],
+            }],
+        };
+        let header = HeaderBuilder::new()
+            .extra_field(extra.clone())
+            .try_finish()
+            .unwrap();
+        let mut encoded = Vec::new();
+        header.write_to(&mut encoded).unwrap();
+        assert_eq!(
+            Header::read_from(&encoded[..]).unwrap().extra_field(),
+            Some(&extra)
+        );
+    }
+
+    #[test]
+    fn raw_header_works() {
+        let header = HeaderBuilder::new()
+            .modification_time(1)
+            .extra_field(ExtraField {
+                subfields: vec![ExtraSubField {
+                    id: [1, 2],
+                    data: vec![3; 10],
+                }],
+            })
+            .filename(CString::new("foo").unwrap())
+            .comment(CString::new("bar").unwrap())
+            .verify()
+            .finish();
+        let mut raw = Vec::new();
+        header.write_to(&mut raw).unwrap();
+        let header_size = raw.len();
+
+        // Sets a reserved flag, and fixes the CRC-16 of the header
+        raw[3] |= 0x20;
+        let mut crc = checksum::Crc32::new();
+        crc.update(&raw[..header_size - 2]);
+        let crc16 = (crc.value() as u16).to_le_bytes();
+        raw[header_size - 2..].copy_from_slice(&crc16);
+
+        let mut encoder = Encoder::with_options(Vec::new(), EncodeOptions::new()).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+        let header_len = Header::read_from(&encoded_data[..])
+            .unwrap()
+            .raw_bytes()
+            .unwrap()
+            .len();
+        let stream = [&raw[..], &encoded_data[header_len..]].concat();
+
+        let mut decoder = Decoder::new(&stream[..]).unwrap();
+        assert_eq!(decoder.header().raw_bytes(), Some(&raw[..]));
+        assert_eq!(decoder.header().filename(), header.filename());
+        assert!(decoder.header().is_verified());
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!");
+        let decoded_header = decoder.header().clone();
+
+        // Re-serializing the fields drops the reserved flag, but writing verbatim keeps it
+        let mut encoder = Encoder::with_options(
+            Vec::new(),
+            EncodeOptions::new().header(decoded_header.clone()),
+        )
+        .unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+        assert_ne!(&encoded_data[..header_size], &raw[..]);
+
+        let options = EncodeOptions::new().verbatim_header(decoded_header);
+        let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+        assert_eq!(&encoded_data[..header_size], &raw[..]);
+        assert_eq!(decode(&encoded_data).unwrap(), b"Hello World!");
+
+        assert_eq!(
+            Header::from_raw_bytes(&raw).unwrap().raw_bytes(),
+            Some(&raw[..])
+        );
+        assert!(Header::from_raw_bytes(&raw[..header_size - 1]).is_err());
+    }
+
+    #[test]
+    fn lenient_header_works() {
+        let header = HeaderBuilder::new()
+            .modification_time(1)
+            .filename(CString::new("foo").unwrap())
+            .finish();
+        let options = EncodeOptions::new().header(header);
+        let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+        assert!(Decoder::new(&encoded_data[..])
+            .unwrap()
+            .header()
+            .anomalies()
+            .is_empty());
+
+        // An unknown compression method, reserved flags and an extra field whose subfield exceeds XLEN
+        let mut broken = encoded_data[..10].to_vec();
+        broken[2] = 7;
+        broken[3] |= 0xC0 | F_EXTRA;
+        broken.extend_from_slice(&[6, 0, 1, 2, 100, 0, 0, 0]);
+        broken.extend_from_slice(&encoded_data[10..]);
+        assert!(Decoder::new(&broken[..]).is_err());
+        broken[2] = 8;
+        assert!(Decoder::new(&broken[..]).is_err());
+        broken[2] = 7;
+
+        let options = DecodeOptions::new().lenient(true);
+        let mut decoder = Decoder::with_options(&broken[..], options.clone()).unwrap();
+        assert_eq!(
+            decoder.header().anomalies(),
+            [
+                HeaderAnomaly::UnknownCompressionMethod(7),
+                HeaderAnomaly::ReservedFlags(0xC0),
+                HeaderAnomaly::MalformedExtraField { xlen: 6 },
+            ]
+        );
+        assert_eq!(decoder.header().extra_field(), None);
+        assert_eq!(decoder.header().filename().unwrap().to_bytes(), b"foo");
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!");
+
+        // A valid extra field is parsed as usual
+        let mut valid = broken.clone();
+        valid[14] = 2;
+        let decoder = Decoder::with_options(&valid[..], options.clone()).unwrap();
+        assert_eq!(decoder.header().anomalies().len(), 2);
+        assert_eq!(
+            decoder.header().extra_field().unwrap().subfields,
+            [ExtraSubField {
+                id: [1, 2],
+                data: vec![0, 0]
+            }]
+        );
+
+        // The limits still apply, and the body is still verified
+        let options = options.max_extra_field_len(4);
+        assert!(Decoder::with_options(&broken[..], options.clone()).is_err());
+        let len = broken.len();
+        broken[len - 5] ^= 1;
+        let mut decoder =
+            Decoder::with_options(&broken[..], DecodeOptions::new().lenient(true)).unwrap();
+        assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+    }
 }
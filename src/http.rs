@@ -0,0 +1,203 @@
+//! Decoders for the content codings of HTTP.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::Read;
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::Read;
+//! use libflate::http::DeflateDecoder;
+//!
+//! // `Content-Encoding: deflate` body sent by a server that conforms to the specification (ZLIB)
+//! let zlib_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+//!                  202, 73, 81, 4, 0, 28, 73, 4, 62];
+//!
+//! // and one that does not (raw DEFLATE)
+//! let deflate_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+//!
+//! for data in &[&zlib_data[..], &deflate_data[..]] {
+//!     let mut decoder = DeflateDecoder::new(*data).unwrap();
+//!     let mut buf = Vec::new();
+//!     decoder.read_to_end(&mut buf).unwrap();
+//!     assert_eq!(buf, b"Hello World!");
+//! }
+//! ```
+use crate::deflate;
+use crate::util::{self, PrefixedReader};
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, Read};
+
+/// Decoder for the `deflate` content coding of HTTP.
+///
+/// The specification ([RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-8.4.1.2)) says that
+/// the `deflate` coding is a ZLIB stream, but some servers send a raw DEFLATE stream instead.
+/// Like web browsers, this decoder first tries to parse the ZLIB header and,
+/// if it fails, decodes the (buffered) data from the beginning as raw DEFLATE.
+#[derive(Debug)]
+pub struct DeflateDecoder<R> {
+    inner: Inner<R>,
+}
+
+#[derive(Debug)]
+enum Inner<R> {
+    Zlib(zlib::Decoder<PrefixedReader<R>>),
+    Deflate(deflate::Decoder<PrefixedReader<R>>),
+}
+
+impl<R> DeflateDecoder<R>
+where
+    R: Read,
+{
+    /// Makes a new decoder instance.
+    ///
+    /// `inner` is to be decoded `deflate` coded body.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let prefix = util::read_prefix(&mut inner, 2)?;
+        let is_zlib = zlib::Header::read_from(&prefix[..]).is_ok();
+        let reader = PrefixedReader::new(prefix, inner);
+        let inner = if is_zlib {
+            Inner::Zlib(zlib::Decoder::new(reader)?)
+        } else {
+            Inner::Deflate(deflate::Decoder::new(reader))
+        };
+        Ok(DeflateDecoder { inner })
+    }
+
+    /// Returns `true` if the body is a ZLIB stream, `false` if it is a raw DEFLATE stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::http::DeflateDecoder;
+    ///
+    /// let deflate_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let decoder = DeflateDecoder::new(&deflate_data[..]).unwrap();
+    /// assert!(!decoder.is_zlib());
+    /// ```
+    pub fn is_zlib(&self) -> bool {
+        matches!(self.inner, Inner::Zlib(_))
+    }
+
+    /// Returns the number of the (compressed) bytes read from the inner stream.
+    ///
+    /// See [`zlib::Decoder::total_in`] and [`deflate::Decoder::total_in`] for the details.
+    pub fn total_in(&self) -> u64 {
+        match self.inner {
+            Inner::Zlib(ref x) => x.total_in(),
+            Inner::Deflate(ref x) => x.total_in(),
+        }
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        match self.inner {
+            Inner::Zlib(ref x) => x.total_out(),
+            Inner::Deflate(ref x) => x.total_out(),
+        }
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        match self.inner {
+            Inner::Zlib(ref x) => x.as_inner_ref().as_inner_ref(),
+            Inner::Deflate(ref x) => x.as_inner_ref().as_inner_ref(),
+        }
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        match self.inner {
+            Inner::Zlib(ref mut x) => x.as_inner_mut().as_inner_mut(),
+            Inner::Deflate(ref mut x) => x.as_inner_mut().as_inner_mut(),
+        }
+    }
+
+    /// Unwraps this `DeflateDecoder`, returning the underlying reader.
+    ///
+    /// Note that the buffered bytes which have not been decoded yet will be lost.
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            Inner::Zlib(x) => x.into_inner().into_inner(),
+            Inner::Deflate(x) => x.into_inner().into_inner(),
+        }
+    }
+}
+impl<R> Read for DeflateDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner {
+            Inner::Zlib(ref mut x) => x.read(buf),
+            Inner::Deflate(ref mut x) => x.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deflate::EncodeOptions;
+    #[cfg(feature = "no_std")]
+    use core2::io::Write;
+    #[cfg(not(feature = "no_std"))]
+    use std::io::Write;
+
+    fn decode_all(data: &[u8]) -> (bool, Vec<u8>) {
+        let mut decoder = DeflateDecoder::new(data).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        (decoder.is_zlib(), buf)
+    }
+
+    #[test]
+    fn zlib_and_raw_deflate_are_accepted() {
+        let text = (0..1000)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>();
+
+        let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(text.as_bytes()).unwrap();
+        let zlib_data = encoder.finish().into_result().unwrap();
+        assert_eq!(decode_all(&zlib_data), (true, text.clone().into_bytes()));
+
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().no_compression(),
+            EncodeOptions::new().fixed_huffman_codes(),
+        ] {
+            let mut encoder = deflate::Encoder::with_options(Vec::new(), options);
+            encoder.write_all(text.as_bytes()).unwrap();
+            let deflate_data = encoder.finish().into_result().unwrap();
+            assert_eq!(
+                decode_all(&deflate_data),
+                (false, text.clone().into_bytes())
+            );
+        }
+    }
+
+    #[test]
+    fn zlib_header_with_preset_dictionary_is_regarded_as_raw_deflate() {
+        // FDICT is set, so the ZLIB decoder of this crate never accepts this header.
+        let data = [0x78, 0xBB];
+        let decoder = DeflateDecoder::new(&data[..]).unwrap();
+        assert!(!decoder.is_zlib());
+    }
+    #[test]
+    fn total_in_and_total_out_work() {
+        let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let zlib_data = encoder.finish().into_result().unwrap();
+        let deflate_data = deflate::compress_to_vec(b"Hello World!").unwrap();
+
+        for data in [zlib_data, deflate_data] {
+            let mut decoder = DeflateDecoder::new(&data[..]).unwrap();
+            let mut buf = Vec::new();
+            decoder.read_to_end(&mut buf).unwrap();
+            assert_eq!(decoder.total_in(), data.len() as u64);
+            assert_eq!(decoder.total_out(), 12);
+        }
+    }
+}
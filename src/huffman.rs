@@ -1,26 +1,76 @@
-//! Length-limited Huffman Codes.
+//! Length-limited canonical Huffman codes.
+//!
+//! This module exposes the Huffman coding machinery used by the DEFLATE encoder and decoder,
+//! so that it can be reused to implement other entropy coded formats:
+//! - [`EncoderBuilder::from_frequencies`] builds length-limited codes from symbol frequencies,
+//! - [`Encoder::encode`] and [`Decoder::decode`] write and read symbols with the [`bit`] module,
+//! - [`Encoder::bitwidthes`] and [`DecoderBuilder::from_bitwidthes`] serialize and deserialize
+//!   the code lengths (which determine the canonical codes uniquely, like in DEFLATE).
+//!
+//! Like DEFLATE, the codes are written starting from the most significant bit of each code
+//! into a bit stream which is packed LSB-first.
+//!
+//! # Examples
+//! ```
+//! use libflate::bit::{BitReader, BitWriter};
+//! use libflate::huffman::{DecoderBuilder, EncoderBuilder};
+//!
+//! let message = [1u16, 0, 1, 2, 1, 0, 3, 1];
+//! let mut frequencies = [0; 4];
+//! for &symbol in &message {
+//!     frequencies[symbol as usize] += 1;
+//! }
+//!
+//! // Encoding
+//! let encoder = EncoderBuilder::from_frequencies(&frequencies, 15).unwrap();
+//! let bitwidthes = encoder.bitwidthes();
+//! assert_eq!(bitwidthes, [2, 1, 3, 3]);
+//!
+//! let mut writer = BitWriter::new(Vec::new());
+//! for &symbol in &message {
+//!     encoder.encode(&mut writer, symbol).unwrap();
+//! }
+//! writer.flush().unwrap();
+//! let encoded = writer.into_inner();
+//! assert_eq!(encoded.len(), 2);
+//!
+//! // Decoding
+//! let decoder = DecoderBuilder::from_bitwidthes(&bitwidthes, None, None).unwrap();
+//! let mut reader = BitReader::new(&encoded[..]);
+//! for &symbol in &message {
+//!     assert_eq!(decoder.decode(&mut reader).unwrap(), symbol);
+//! }
+//! ```
 use crate::bit;
+use crate::error::{Error, ErrorKind};
 #[cfg(feature = "no_std")]
 use alloc::vec;
 #[cfg(feature = "no_std")]
 use alloc::vec::Vec;
 #[cfg(feature = "no_std")]
-use core::cmp;
+use core::{cmp, mem};
 #[cfg(feature = "no_std")]
 use core2::io;
 #[cfg(not(feature = "no_std"))]
-use std::{cmp, io};
+use std::{cmp, io, mem};
 
-const MAX_BITWIDTH: u8 = 15;
+/// The maximum length of a code (in bits) supported by this module.
+pub const MAX_BITWIDTH: u8 = 15;
 
+/// A Huffman code.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Code {
+    /// The length of the code in bits (at most [`MAX_BITWIDTH`]).
     pub width: u8,
+
+    /// The bits of the code (the lowest `width` bits are used).
     pub bits: u16,
 }
 impl Code {
+    /// Makes a new `Code` instance.
+    ///
+    /// The code is validated when it is passed to [`Builder::set_mapping`].
     pub fn new(width: u8, bits: u16) -> Self {
-        debug_assert!(width <= MAX_BITWIDTH);
         Code { width, bits }
     }
     fn inverse_endian(&self) -> Self {
@@ -35,12 +85,25 @@ impl Code {
     }
 }
 
+/// A builder of a Huffman encoder or decoder.
 pub trait Builder: Sized {
+    /// The type of the instance built by this builder.
     type Instance;
+
+    /// Assigns `code` (whose bits are ordered from the most significant one) to `symbol`.
     fn set_mapping(&mut self, symbol: u16, code: Code) -> io::Result<()>;
+
+    /// Builds the instance.
     fn finish(self) -> Self::Instance;
+
+    /// Assigns the canonical Huffman codes determined by the code lengths to the symbols, and builds the instance.
+    ///
+    /// `bitwidthes[i]` is the length of the code of the symbol `i` (`0` means that the symbol is unused).
+    ///
+    /// If `bitwidthes` is empty, an `io::ErrorKind::InvalidInput` error is returned.
+    /// If the lengths are too long or define overlapping codes, an [`ErrorKind::InvalidHuffmanCode`] error is returned.
     fn restore_canonical_huffman_codes(mut self, bitwidthes: &[u8]) -> io::Result<Self::Instance> {
-        debug_assert!(!bitwidthes.is_empty());
+        check_bitwidthes(bitwidthes)?;
 
         let mut symbols = bitwidthes
             .iter()
@@ -62,6 +125,8 @@ pub trait Builder: Sized {
     }
 }
 
+/// A builder of [`Decoder`].
+#[derive(Debug)]
 pub struct DecoderBuilder {
     table: Vec<u16>,
     eob_symbol: Option<u16>,
@@ -69,31 +134,51 @@ pub struct DecoderBuilder {
     max_bitwidth: u8,
 }
 impl DecoderBuilder {
+    /// Makes a new builder for codes up to `max_bitwidth` bits.
+    ///
+    /// `safely_peek_bitwidth` is the number of the bits which can always be peeked before decoding a symbol,
+    /// i.e., the minimum length of the codes which may appear in the stream.
+    /// If `eob_symbol` is specified, the length of its code is used as `safely_peek_bitwidth` instead.
+    /// A larger value makes the decoding faster, but if it is larger than the code of the last symbol in the stream,
+    /// the decoder may try to read beyond the end of the stream.
+    /// If both are `None`, `1` is used.
+    ///
+    /// If `max_bitwidth` exceeds [`MAX_BITWIDTH`], an `io::ErrorKind::InvalidInput` error is returned.
     pub fn new(
         max_bitwidth: u8,
         safely_peek_bitwidth: Option<u8>,
         eob_symbol: Option<u16>,
-    ) -> Self {
-        debug_assert!(max_bitwidth <= MAX_BITWIDTH);
-        DecoderBuilder {
+    ) -> io::Result<Self> {
+        if max_bitwidth > MAX_BITWIDTH {
+            return Err(invalid_input_error("Too long maximum Huffman code length"));
+        }
+        Ok(DecoderBuilder {
             table: vec![u16::from(MAX_BITWIDTH) + 1; 1 << max_bitwidth],
             eob_symbol,
             safely_peek_bitwidth,
             max_bitwidth,
-        }
+        })
     }
+    /// Builds a decoder of the canonical Huffman codes determined by `bitwidthes`.
+    ///
+    /// See [`DecoderBuilder::new`] for the meaning of `safely_peek_bitwidth` and `eob_symbol`.
+    ///
+    /// If the lengths are too long or define overlapping codes, an [`ErrorKind::InvalidHuffmanCode`] error is returned.
+    /// If `bitwidthes` is empty, an `io::ErrorKind::InvalidInput` error is returned.
     pub fn from_bitwidthes(
         bitwidthes: &[u8],
         safely_peek_bitwidth: Option<u8>,
         eob_symbol: Option<u16>,
     ) -> io::Result<Decoder> {
+        check_bitwidthes(bitwidthes)?;
         let builder = Self::new(
             bitwidthes.iter().cloned().max().unwrap_or(0),
             safely_peek_bitwidth,
             eob_symbol,
-        );
+        )?;
         builder.restore_canonical_huffman_codes(bitwidthes)
     }
+    /// Returns the number of the bits which can always be peeked before decoding a symbol (if known).
     pub fn safely_peek_bitwidth(&self) -> Option<u8> {
         self.safely_peek_bitwidth
     }
@@ -101,7 +186,10 @@ impl DecoderBuilder {
 impl Builder for DecoderBuilder {
     type Instance = Decoder;
     fn set_mapping(&mut self, symbol: u16, code: Code) -> io::Result<()> {
-        debug_assert!(code.width <= self.max_bitwidth);
+        check_code(&code, self.max_bitwidth)?;
+        if symbol > u16::MAX >> 5 {
+            return Err(invalid_input_error("Too large Huffman coded symbol"));
+        }
         if Some(symbol) == self.eob_symbol {
             self.safely_peek_bitwidth = Some(code.width);
         }
@@ -114,14 +202,11 @@ impl Builder for DecoderBuilder {
         for padding in 0..(1 << (self.max_bitwidth - code.width)) {
             let i = ((padding << code.width) | code_be.bits) as usize;
             if self.table[i] != u16::from(MAX_BITWIDTH) + 1 {
-                #[cfg(not(feature = "no_std"))]
-                let message = format!(
-                    "Bit region conflict: i={}, old_value={}, new_value={}, symbol={}, code={:?}",
-                    i, self.table[i], value, symbol, code
+                return Err(
+                    Error::new(ErrorKind::InvalidHuffmanCode, "Bit region conflict")
+                        .with_value(symbol)
+                        .into(),
                 );
-                #[cfg(feature = "no_std")]
-                let message = "Bit region conflict";
-                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
             }
             self.table[i] = value;
         }
@@ -139,6 +224,9 @@ impl Builder for DecoderBuilder {
     }
 }
 
+/// Huffman decoder.
+///
+/// This decodes a symbol by looking up a table indexed by the next (at most [`MAX_BITWIDTH`]) bits.
 #[derive(Debug)]
 pub struct Decoder {
     table: Vec<u16>,
@@ -146,10 +234,19 @@ pub struct Decoder {
     max_bitwidth: u8,
 }
 impl Decoder {
+    /// Returns the number of bytes allocated on the heap by the decoding table.
+    pub fn memory_usage(&self) -> usize {
+        self.table.capacity() * mem::size_of::<u16>()
+    }
+
+    /// Returns the number of the bits which are peeked before decoding a symbol.
     pub fn safely_peek_bitwidth(&self) -> u8 {
         self.safely_peek_bitwidth
     }
 
+    /// Reads a code from `reader` and returns the decoded symbol.
+    ///
+    /// If the bits do not match any code, an [`ErrorKind::InvalidHuffmanCode`] error is returned.
     #[inline(always)]
     pub fn decode<R>(&self, reader: &mut bit::BitReader<R>) -> io::Result<u16>
     where
@@ -161,7 +258,7 @@ impl Decoder {
     }
 
     #[inline(always)]
-    pub fn decode_unchecked<R>(&self, reader: &mut bit::BitReader<R>) -> u16
+    pub(crate) fn decode_unchecked<R>(&self, reader: &mut bit::BitReader<R>) -> u16
     where
         R: io::Read,
     {
@@ -176,38 +273,78 @@ impl Decoder {
                 break;
             }
             if bitwidth > self.max_bitwidth {
-                reader.set_last_error(invalid_data_error!("Invalid huffman coded stream"));
+                reader.set_last_error(
+                    Error::new(
+                        ErrorKind::InvalidHuffmanCode,
+                        "Invalid huffman coded stream",
+                    )
+                    .into(),
+                );
                 break;
             }
             peek_bitwidth = bitwidth;
         }
-        reader.skip_bits(bitwidth);
+        reader.skip_bits_unchecked(bitwidth);
         value >> 5
     }
 }
 
+/// A builder of [`Encoder`].
 #[derive(Debug)]
 pub struct EncoderBuilder {
     table: Vec<Code>,
 }
 impl EncoderBuilder {
+    /// Makes a new builder for the symbols less than `symbol_count`.
     pub fn new(symbol_count: usize) -> Self {
         EncoderBuilder {
             table: vec![Code::new(0, 0); symbol_count],
         }
     }
+    /// Builds an encoder of the canonical Huffman codes determined by `bitwidthes`.
+    ///
+    /// If `bitwidthes` is empty, or if the lengths are too long or define overlapping codes,
+    /// an `io::ErrorKind::InvalidInput` error is returned.
     pub fn from_bitwidthes(bitwidthes: &[u8]) -> io::Result<Encoder> {
+        check_bitwidthes(bitwidthes).map_err(|e| {
+            if e.kind() == io::ErrorKind::InvalidInput {
+                e
+            } else {
+                invalid_input_error("Invalid Huffman code lengths")
+            }
+        })?;
         let symbol_count = bitwidthes
             .iter()
             .enumerate()
-            .filter(|e| *e.1 > 0)
-            .last()
+            .rfind(|e| *e.1 > 0)
             .map_or(0, |e| e.0)
             + 1;
         let builder = Self::new(symbol_count);
         builder.restore_canonical_huffman_codes(bitwidthes)
     }
+    /// Builds an encoder of the optimal Huffman codes for `symbol_frequencies`
+    /// whose lengths are limited to `max_bitwidth` bits (and [`MAX_BITWIDTH`]).
+    ///
+    /// The lengths are computed by the package-merge algorithm,
+    /// so the codes are optimal under the limit even for skewed distributions
+    /// (unlike heuristics which shorten the codes of an unlimited Huffman tree).
+    ///
+    /// `symbol_frequencies[i]` is the number of occurrences of the symbol `i`.
+    /// Note that `max_bitwidth` must be large enough to assign codes to all the used symbols
+    /// (i.e., the number of the used symbols must not exceed `2^max_bitwidth`),
+    /// otherwise an `io::ErrorKind::InvalidInput` error is returned
+    /// (as well as when `symbol_frequencies` is empty).
     pub fn from_frequencies(symbol_frequencies: &[usize], max_bitwidth: u8) -> io::Result<Encoder> {
+        if symbol_frequencies.is_empty() {
+            return Err(invalid_input_error("No Huffman coded symbols"));
+        }
+        let max_bitwidth = cmp::min(max_bitwidth, MAX_BITWIDTH);
+        let used_symbols = symbol_frequencies.iter().filter(|&&f| f > 0).count();
+        if used_symbols > 0 && (max_bitwidth == 0 || used_symbols > 1 << max_bitwidth) {
+            return Err(invalid_input_error(
+                "Too many symbols for the maximum Huffman code length",
+            ));
+        }
         let max_bitwidth = cmp::min(
             max_bitwidth,
             ordinary_huffman_codes::calc_optimal_max_bitwidth(symbol_frequencies),
@@ -219,8 +356,15 @@ impl EncoderBuilder {
 impl Builder for EncoderBuilder {
     type Instance = Encoder;
     fn set_mapping(&mut self, symbol: u16, code: Code) -> io::Result<()> {
-        debug_assert_eq!(self.table[symbol as usize], Code::new(0, 0));
-        self.table[symbol as usize] = code.inverse_endian();
+        check_code(&code, MAX_BITWIDTH)?;
+        let entry = self
+            .table
+            .get_mut(symbol as usize)
+            .ok_or_else(|| invalid_input_error("Huffman coded symbol out of range"))?;
+        if entry.width != 0 {
+            return Err(invalid_input_error("Huffman code is assigned twice"));
+        }
+        *entry = code.inverse_endian();
         Ok(())
     }
     fn finish(self) -> Self::Instance {
@@ -228,30 +372,45 @@ impl Builder for EncoderBuilder {
     }
 }
 
+/// Huffman encoder.
 #[derive(Debug, Clone)]
 pub struct Encoder {
     table: Vec<Code>,
 }
 impl Encoder {
+    /// Writes the code of `symbol` to `writer`.
+    ///
+    /// If `symbol` has no code, an `io::ErrorKind::InvalidInput` error is returned.
     #[inline(always)]
     pub fn encode<W>(&self, writer: &mut bit::BitWriter<W>, symbol: u16) -> io::Result<()>
     where
         W: io::Write,
     {
         let code = self.lookup(symbol);
-        debug_assert_ne!(code, Code::new(0, 0));
+        if code.width == 0 {
+            return Err(invalid_input_error("Huffman coded symbol has no code"));
+        }
         writer.write_bits(code.width, code.bits)
     }
+    /// Returns the code of `symbol`.
+    ///
+    /// The bits of the returned code are reversed (i.e., ordered from the least significant one),
+    /// so that they can be written to a [`bit::BitWriter`] as is.
+    /// If `symbol` has no code (or is out of the range of the table), a zero-length code is returned.
     #[inline(always)]
     pub fn lookup(&self, symbol: u16) -> Code {
-        debug_assert!(
-            symbol < self.table.len() as u16,
-            "symbol:{}, table:{}",
-            symbol,
-            self.table.len()
-        );
-        self.table[symbol as usize].clone()
+        self.table
+            .get(symbol as usize)
+            .cloned()
+            .unwrap_or(Code::new(0, 0))
+    }
+    /// Returns the length of the code of `symbol` (`0` if it has no code).
+    ///
+    /// Unlike [`Encoder::lookup`], `symbol` may be out of the range of the table.
+    pub fn bitwidth(&self, symbol: u16) -> u8 {
+        self.table.get(symbol as usize).map_or(0, |code| code.width)
     }
+    /// Returns the largest symbol which has a code.
     pub fn used_max_symbol(&self) -> Option<u16> {
         self.table
             .iter()
@@ -259,6 +418,54 @@ impl Encoder {
             .position(|x| x.width > 0)
             .map(|trailing_zeros| (self.table.len() - 1 - trailing_zeros) as u16)
     }
+
+    /// Returns the length of the code of each symbol (`0` for the symbols which have no code).
+    ///
+    /// The codes can be restored from the returned lengths by [`EncoderBuilder::from_bitwidthes`]
+    /// or [`DecoderBuilder::from_bitwidthes`].
+    pub fn bitwidthes(&self) -> Vec<u8> {
+        self.table.iter().map(|code| code.width).collect()
+    }
+}
+
+fn check_bitwidthes(bitwidthes: &[u8]) -> io::Result<()> {
+    if bitwidthes.is_empty() {
+        return Err(invalid_input_error("No Huffman code lengths"));
+    }
+    if let Some(&bitwidth) = bitwidthes.iter().find(|&&w| w > MAX_BITWIDTH) {
+        return Err(
+            Error::new(ErrorKind::InvalidHuffmanCode, "Too long Huffman code")
+                .with_value(bitwidth)
+                .with_expected(MAX_BITWIDTH)
+                .into(),
+        );
+    }
+
+    // The Kraft inequality (which also keeps the canonical codes from overflowing)
+    let kraft_sum = bitwidthes
+        .iter()
+        .filter(|&&w| w > 0)
+        .map(|&w| 1_u64 << (MAX_BITWIDTH - w))
+        .sum::<u64>();
+    if kraft_sum > 1 << MAX_BITWIDTH {
+        return Err(Error::new(
+            ErrorKind::InvalidHuffmanCode,
+            "Over-subscribed Huffman codes",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn check_code(code: &Code, max_bitwidth: u8) -> io::Result<()> {
+    if code.width > max_bitwidth || u32::from(code.bits) >> code.width != 0 {
+        return Err(invalid_input_error("Invalid Huffman code"));
+    }
+    Ok(())
+}
+
+fn invalid_input_error(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
 }
 
 #[allow(dead_code)]
@@ -289,95 +496,191 @@ mod length_limited_huffman_codes {
     use alloc::vec;
     #[cfg(feature = "no_std")]
     use alloc::vec::Vec;
-    use core::mem;
-
-    #[derive(Debug, Clone)]
-    struct Node {
-        symbols: Vec<u16>,
-        weight: usize,
-    }
-    impl Node {
-        pub fn empty() -> Self {
-            Node {
-                symbols: vec![],
-                weight: 0,
-            }
-        }
-        pub fn single(symbol: u16, weight: usize) -> Self {
-            Node {
-                symbols: vec![symbol],
-                weight,
-            }
-        }
-        pub fn merge(&mut self, other: Self) {
-            self.weight += other.weight;
-            self.symbols.extend(other.symbols);
-        }
-    }
 
+    /// Computes the optimal code lengths limited to `max_bitwidth` bits by the package-merge algorithm.
+    ///
+    /// Only the order of the leaves (symbols) and the packages is kept for each list,
+    /// so this takes `O(max_bitwidth * symbols)` time and memory.
+    ///
     /// Reference: [A Fast Algorithm for Optimal Length-Limited Huffman Codes][LenLimHuff.pdf]
     ///
     /// [LenLimHuff.pdf]: https://www.ics.uci.edu/~dan/pubs/LenLimHuff.pdf
     pub fn calc(max_bitwidth: u8, frequencies: &[usize]) -> Vec<u8> {
-        // NOTE: unoptimized implementation
-        let mut source = frequencies
+        let mut symbols = frequencies
             .iter()
             .enumerate()
             .filter(|&(_, &f)| f > 0)
-            .map(|(symbol, &weight)| Node::single(symbol as u16, weight))
+            .map(|(symbol, &weight)| (symbol, weight))
             .collect::<Vec<_>>();
-        source.sort_by_key(|o| o.weight);
+        symbols.sort_by_key(|&(_, weight)| weight);
 
-        let weighted =
-            (0..max_bitwidth - 1).fold(source.clone(), |w, _| merge(package(w), source.clone()));
+        // `is_leaf[i][j]` tells whether the `j`-th item of the list for the `i+1`-th deepest level is a symbol (or a package)
+        let mut is_leaf = Vec::with_capacity(usize::from(max_bitwidth));
+        is_leaf.push(vec![true; symbols.len()]);
+        let mut weights = symbols
+            .iter()
+            .map(|&(_, weight)| weight)
+            .collect::<Vec<_>>();
+        for _ in 1..max_bitwidth {
+            let packages = weights.chunks_exact(2).map(|pair| pair[0] + pair[1]);
+            let mut packages = packages.peekable();
+            let mut leaves = symbols.iter().map(|&(_, weight)| weight).peekable();
+            let mut next_weights = Vec::with_capacity(symbols.len() + weights.len() / 2);
+            let mut next_is_leaf = Vec::with_capacity(next_weights.capacity());
+            loop {
+                let leaf = match (packages.peek(), leaves.peek()) {
+                    (None, None) => break,
+                    (Some(p), Some(l)) => l <= p,
+                    (p, _) => p.is_none(),
+                };
+                let weight = if leaf { leaves.next() } else { packages.next() };
+                next_weights.push(weight.expect("never fails"));
+                next_is_leaf.push(leaf);
+            }
+            weights = next_weights;
+            is_leaf.push(next_is_leaf);
+        }
 
+        // The items of the top level list are packaged once more, and all the resulting packages are selected.
+        // The selected items of each list are its prefix, and each selected leaf adds one to the length of its symbol.
         let mut code_bitwidthes = vec![0; frequencies.len()];
-        for symbol in package(weighted)
-            .into_iter()
-            .flat_map(|n| n.symbols.into_iter())
-        {
-            code_bitwidthes[symbol as usize] += 1;
+        let mut selected = if symbols.len() < 2 {
+            symbols.len()
+        } else {
+            weights.len() / 2 * 2
+        };
+        for flags in is_leaf.iter().rev() {
+            let leaves = flags[..selected].iter().filter(|&&leaf| leaf).count();
+            for &(symbol, _) in &symbols[..leaves] {
+                code_bitwidthes[symbol] += 1;
+            }
+            selected = (selected - leaves) * 2;
         }
         code_bitwidthes
     }
-    fn merge(x: Vec<Node>, y: Vec<Node>) -> Vec<Node> {
-        let mut z = Vec::with_capacity(x.len() + y.len());
-        let mut x = x.into_iter().peekable();
-        let mut y = y.into_iter().peekable();
-        loop {
-            let x_weight = x.peek().map(|s| s.weight);
-            let y_weight = y.peek().map(|s| s.weight);
-            if x_weight.is_none() {
-                z.extend(y);
-                break;
-            } else if y_weight.is_none() {
-                z.extend(x);
-                break;
-            } else if x_weight < y_weight {
-                z.push(x.next().unwrap());
-            } else {
-                z.push(y.next().unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {}
+
+    #[test]
+    fn length_limited_codes_are_optimal() {
+        fn cost(frequencies: &[usize], bitwidthes: &[u8]) -> usize {
+            frequencies
+                .iter()
+                .zip(bitwidthes)
+                .map(|(&f, &w)| f * usize::from(w))
+                .sum()
+        }
+
+        // Finds the optimal cost by trying all the lengths which satisfy the Kraft inequality
+        fn brute_force(frequencies: &[usize], max_bitwidth: u8, bitwidthes: &mut Vec<u8>) -> usize {
+            if bitwidthes.len() == frequencies.len() {
+                let kraft = bitwidthes
+                    .iter()
+                    .map(|&w| 1 << (max_bitwidth - w))
+                    .sum::<usize>();
+                return if kraft <= 1 << max_bitwidth {
+                    cost(frequencies, bitwidthes)
+                } else {
+                    usize::MAX
+                };
             }
+            (1..=max_bitwidth)
+                .map(|w| {
+                    bitwidthes.push(w);
+                    let c = brute_force(frequencies, max_bitwidth, bitwidthes);
+                    bitwidthes.pop();
+                    c
+                })
+                .min()
+                .unwrap()
         }
-        z
-    }
-    fn package(mut nodes: Vec<Node>) -> Vec<Node> {
-        if nodes.len() >= 2 {
-            let new_len = nodes.len() / 2;
 
-            for i in 0..new_len {
-                nodes[i] = mem::replace(&mut nodes[i * 2], Node::empty());
-                let other = mem::replace(&mut nodes[i * 2 + 1], Node::empty());
-                nodes[i].merge(other);
+        let frequencies_list = [
+            vec![1, 1, 2, 4, 8, 16, 32],
+            vec![1, 2, 3, 5, 8, 13, 21],
+            vec![100, 1, 1, 1, 1, 1],
+            vec![7, 7, 7, 7, 7],
+            vec![1000, 999, 3, 2, 1, 1, 1],
+        ];
+        for frequencies in &frequencies_list {
+            for max_bitwidth in 3..=6 {
+                let encoder = EncoderBuilder::from_frequencies(frequencies, max_bitwidth).unwrap();
+                let bitwidthes = encoder.bitwidthes();
+                assert!(bitwidthes.iter().all(|&w| 0 < w && w <= max_bitwidth));
+                assert_eq!(
+                    cost(frequencies, &bitwidthes),
+                    brute_force(frequencies, max_bitwidth, &mut Vec::new()),
+                    "frequencies={:?}, max_bitwidth={}",
+                    frequencies,
+                    max_bitwidth
+                );
+                DecoderBuilder::from_bitwidthes(&bitwidthes, None, None).unwrap();
             }
-            nodes.truncate(new_len);
         }
-        nodes
+
+        // The symbols without occurrences have no codes, and a single symbol has a 1-bit code
+        let encoder = EncoderBuilder::from_frequencies(&[0, 5, 0, 3], 15).unwrap();
+        assert_eq!(encoder.bitwidthes(), [0, 1, 0, 1]);
+        let encoder = EncoderBuilder::from_frequencies(&[0, 0, 9], 15).unwrap();
+        assert_eq!(encoder.bitwidthes(), [0, 0, 1]);
     }
-}
 
-#[cfg(test)]
-mod tests {
     #[test]
-    fn it_works() {}
+    fn codes_are_canonical() {
+        let encoder = EncoderBuilder::from_bitwidthes(&[2, 1, 3, 3]).unwrap();
+        assert_eq!(encoder.lookup(1), Code::new(1, 0b0));
+        assert_eq!(encoder.lookup(0), Code::new(2, 0b01));
+        assert_eq!(encoder.lookup(2), Code::new(3, 0b011));
+        assert_eq!(encoder.lookup(3), Code::new(3, 0b111));
+        assert_eq!(encoder.used_max_symbol(), Some(3));
+    }
+
+    #[test]
+    fn invalid_bitwidthes_are_rejected() {
+        assert!(EncoderBuilder::from_bitwidthes(&[16, 1]).is_err());
+        assert!(DecoderBuilder::from_bitwidthes(&[16, 1], None, None).is_err());
+
+        // Over-subscribed
+        assert!(DecoderBuilder::from_bitwidthes(&[1, 1, 1], None, None).is_err());
+        let e = EncoderBuilder::from_bitwidthes(&[1, 1, 1]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        let e = EncoderBuilder::from_frequencies(&[1; 5], 2).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let e = EncoderBuilder::from_frequencies(&[1; 2], 0).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        EncoderBuilder::from_frequencies(&[1; 4], 2).unwrap();
+
+        let e = EncoderBuilder::from_frequencies(&[], 15).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let e = EncoderBuilder::from_bitwidthes(&[]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let e = DecoderBuilder::from_bitwidthes(&[], None, None).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        let e = DecoderBuilder::new(16, None, None).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let mut builder = DecoderBuilder::new(2, None, None).unwrap();
+        let e = builder.set_mapping(0, Code::new(3, 0)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let mut builder = EncoderBuilder::new(2);
+        let e = builder.set_mapping(2, Code::new(1, 0)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+
+        let encoder = EncoderBuilder::from_bitwidthes(&[1, 1]).unwrap();
+        let mut writer = bit::BitWriter::new(Vec::new());
+        for symbol in [2, 1000] {
+            let e = encoder.encode(&mut writer, symbol).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
 }
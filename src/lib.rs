@@ -1,49 +1,67 @@
 //! A Rust implementation of DEFLATE algorithm and related formats (ZLIB, GZIP).
+//!
+//! # Untrusted input
+//!
+//! The decoders never panic on malformed input.
+//! A corrupted or truncated stream is reported as an [`Error`]
+//! (converted into an `io::Error` of the kind `InvalidData` or `UnexpectedEof`).
+//! Note that this doesn't bound the size of the decoded data; see [`guard`] for that.
+//!
+//! # Reading position
+//!
+//! The decoders of the [`deflate`], [`zlib`] and [`gzip`] modules read their input byte by byte,
+//! and never read past the end of the stream (the trailer included).
+//! Once a decoder has reached the end of the stream, the inner reader (see e.g. [`gzip::Decoder::into_inner`])
+//! is positioned at the first byte following the stream, so that a container format can continue to parse it.
+//! This holds for any reader, buffered or not;
+//! the decoders of the [`bufread`] module give the same guarantee without the per-byte reads.
+//!
+//! [`gzip::MultiDecoder`] reads the header of the next member (if any) at the end of each member,
+//! and [`parallel::ReadAhead`] reads the input ahead by design.
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
 #![cfg_attr(feature = "no_std", no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
+pub use error::Error;
 pub use finish::Finish;
 
 #[cfg(feature = "no_std")]
 extern crate alloc;
 
-#[cfg(not(feature = "no_std"))]
-macro_rules! invalid_data_error {
-    ($fmt:expr) => { invalid_data_error!("{}", $fmt) };
-    ($fmt:expr, $($arg:tt)*) => {
-        ::std::io::Error::new(::std::io::ErrorKind::InvalidData, format!($fmt, $($arg)*))
-    }
-}
-
-#[cfg(feature = "no_std")]
-macro_rules! invalid_data_error {
-    ($fmt:expr) => {
-        invalid_data_error!($fmt, "")
-    };
-    ($fmt:expr, $($arg:tt)*) => {
-        ::core2::io::Error::new(::core2::io::ErrorKind::InvalidData, $fmt)
-    };
-}
-
-macro_rules! finish_try {
-    ($e:expr) => {
-        match $e.unwrap() {
-            (inner, None) => inner,
-            (inner, error) => return crate::finish::Finish::new(inner, error),
-        }
-    };
-}
-
+pub mod any;
+pub mod bit;
+pub mod bufread;
+pub mod checksum;
+pub mod codec;
+pub mod copy;
 pub mod deflate;
+pub mod dictionary;
+#[cfg(feature = "embedded-io")]
+pub mod embedded_io;
+pub mod error;
 pub mod finish;
+pub mod flate2_compat;
+pub mod guard;
 pub mod gzip;
+pub mod http;
+pub mod huffman;
 pub mod lz77;
 pub mod non_blocking;
+pub mod options;
+#[cfg(not(feature = "no_std"))]
+pub mod parallel;
+#[cfg(not(feature = "no_std"))]
+pub mod pool;
+pub mod predictor;
+pub mod push;
+pub mod read;
+#[cfg(all(feature = "tokio", not(feature = "no_std")))]
+pub mod tokio;
+pub mod transcode;
+pub mod write;
+pub mod zip;
 pub mod zlib;
 
-mod bit;
-mod checksum;
-mod huffman;
 mod util;
@@ -1,6 +1,8 @@
 use crate::deflate::symbol::{self, HuffmanCodec};
+use crate::error::{Error, ErrorKind};
 use crate::lz77;
-use crate::non_blocking::transaction::TransactionalBitReader;
+use crate::non_blocking::transaction::{self, TransactionalBitReader};
+use crate::util;
 #[cfg(feature = "no_std")]
 use core::cmp;
 #[cfg(feature = "no_std")]
@@ -18,6 +20,7 @@ pub struct Decoder<R> {
     eos: bool,
     bit_reader: TransactionalBitReader<R>,
     block_decoder: BlockDecoder,
+    decoded_size: u64,
 }
 impl<R: Read> Decoder<R> {
     /// Makes a new decoder instance.
@@ -45,9 +48,55 @@ impl<R: Read> Decoder<R> {
             eos: false,
             bit_reader: TransactionalBitReader::new(inner),
             block_decoder: BlockDecoder::new(),
+            decoded_size: 0,
         }
     }
 
+    /// Returns the number of the (compressed) bytes read from the inner stream.
+    ///
+    /// This may include a few bytes which have been read into the bit buffer but not decoded yet.
+    /// The bytes read before an interrupted operation (by `WouldBlock`) are counted only once.
+    pub fn total_in(&self) -> u64 {
+        self.bit_reader.total_in()
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::non_blocking::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(decoder.total_in(), 14);
+    /// assert_eq!(decoder.total_out(), 12);
+    /// ```
+    pub fn total_out(&self) -> u64 {
+        self.decoded_size
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder (excluding the inner stream).
+    ///
+    /// This includes the sliding window and the decoded data which have not been read yet (up to 128 KiB
+    /// plus the size of the buffer passed to `read()`), the Huffman tables of the current block (up to 128 KiB),
+    /// and the input data buffered to retry the operation interrupted by `WouldBlock`.
+    pub fn memory_usage(&self) -> usize {
+        let huffman = match self.state {
+            DecoderState::DecodeBlock(ref x) => x.memory_usage(),
+            _ => 0,
+        };
+        self.block_decoder.lz77_decoder.memory_usage()
+            + huffman
+            + self.bit_reader.buffer_memory_usage()
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
         self.bit_reader.as_inner_ref()
@@ -76,6 +125,42 @@ impl<R: Read> Decoder<R> {
         self.bit_reader.into_inner()
     }
 
+    /// Makes this decoder report the end of the inner stream in the middle of the DEFLATE stream
+    /// as an `ErrorKind::WouldBlock` error (instead of `ErrorKind::UnexpectedEof`) if `enabled` is `true`.
+    ///
+    /// Either way, the decoder keeps its state across such an error, so the decoding can be resumed
+    /// by retrying the read after more data has become available from the inner stream.
+    /// Enabling this is convenient if the inner stream is, e.g., a buffer which is filled as data arrives from the network
+    /// and the caller already waits for more data on `WouldBlock`.
+    /// Otherwise, an `UnexpectedEof` error tells that the data is truncated (rather than corrupted).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{self, Read};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{self, Read};
+    /// use libflate::non_blocking::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(io::Cursor::new(encoded_data[..5].to_vec()));
+    /// decoder.set_truncation_as_would_block(true);
+    /// let mut buf = Vec::new();
+    /// let e = decoder.read_to_end(&mut buf).unwrap_err();
+    /// assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+    ///
+    /// // More data arrives
+    /// let cursor = decoder.as_inner_mut();
+    /// let position = cursor.position();
+    /// cursor.get_mut().extend_from_slice(&encoded_data[5..]);
+    /// cursor.set_position(position);
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn set_truncation_as_would_block(&mut self, enabled: bool) {
+        self.bit_reader.set_eof_as_would_block(enabled);
+    }
+
     pub(crate) fn bit_reader_mut(&mut self) -> &mut TransactionalBitReader<R> {
         &mut self.bit_reader
     }
@@ -98,9 +183,11 @@ impl<R: Read> Read for Decoder<R> {
                         0b01 => DecoderState::LoadFixedHuffmanCode,
                         0b10 => DecoderState::LoadDynamicHuffmanCode,
                         0b11 => {
-                            return Err(invalid_data_error!(
-                                "btype 0x11 of DEFLATE is reserved(error) value"
-                            ));
+                            return Err(Error::new(
+                                ErrorKind::InvalidHeader,
+                                "btype 0x11 of DEFLATE is reserved(error) value",
+                            )
+                            .into());
                         }
                         _ => unreachable!(),
                     }
@@ -114,11 +201,13 @@ impl<R: Read> Read for Decoder<R> {
                         r.as_inner_mut().read_exact(&mut buf)?;
                         let nlen = u16::from_le_bytes(buf);
                         if !len != nlen {
-                            Err(invalid_data_error!(
-                                "LEN={} is not the one's complement of NLEN={}",
-                                len,
-                                nlen
-                            ))
+                            Err(Error::new(
+                                ErrorKind::InvalidHeader,
+                                "NLEN is not the one's complement of LEN",
+                            )
+                            .with_value(nlen)
+                            .with_expected(!len)
+                            .into())
                         } else {
                             Ok(len)
                         }
@@ -136,9 +225,15 @@ impl<R: Read> Read for Decoder<R> {
                 DecoderState::ReadNonCompressedBlock { ref mut len } => {
                     let buf_len = buf.len();
                     let buf = &mut buf[..cmp::min(buf_len, *len as usize)];
-                    read_size = self.bit_reader.as_inner_mut().read(buf)?;
+                    let would_block = self.bit_reader.eof_as_would_block();
+                    let reader = self.bit_reader.as_inner_mut();
+                    read_size = util::retry_interrupted(|| reader.read(buf))?;
+                    if read_size == 0 && !buf.is_empty() {
+                        return Err(transaction::truncated_stream_error(would_block));
+                    }
+                    self.bit_reader.add_total_in(read_size);
 
-                    self.block_decoder.extend(&buf[..read_size]);
+                    self.block_decoder.extend(&buf[..read_size])?;
                     *len -= read_size as u16;
                     break;
                 }
@@ -155,8 +250,9 @@ impl<R: Read> Read for Decoder<R> {
                     DecoderState::DecodeBlock(symbol_decoder)
                 }
                 DecoderState::DecodeBlock(ref mut symbol_decoder) => {
+                    let limit = cmp::max(buf.len(), 1);
                     self.block_decoder
-                        .decode(&mut self.bit_reader, symbol_decoder)?;
+                        .decode(&mut self.bit_reader, symbol_decoder, limit)?;
                     read_size = self.block_decoder.read(buf)?;
                     if read_size == 0 && !buf.is_empty() && !self.eos {
                         DecoderState::ReadBlockHeader
@@ -167,6 +263,7 @@ impl<R: Read> Read for Decoder<R> {
             };
             self.state = next;
         }
+        self.decoded_size += read_size as u64;
         Ok(read_size)
     }
 }
@@ -196,15 +293,22 @@ impl BlockDecoder {
     pub fn enter_new_block(&mut self) {
         self.eob = false;
     }
+    /// Decodes the symbols of the current block until `limit` bytes of decoded data are buffered
+    /// (it may be exceeded by a back-reference), the end of the block is reached or the inner stream would block.
     pub fn decode<R: Read>(
         &mut self,
         bit_reader: &mut TransactionalBitReader<R>,
         symbol_decoder: &mut symbol::Decoder,
+        limit: usize,
     ) -> io::Result<()> {
         if self.eob {
             return Ok(());
         }
-        while let Some(s) = self.decode_symbol(bit_reader, symbol_decoder)? {
+        while self.lz77_decoder.buffer().len() < limit {
+            let s = match self.decode_symbol(bit_reader, symbol_decoder)? {
+                None => break,
+                Some(s) => s,
+            };
             match s {
                 symbol::Symbol::Code(code) => {
                     self.lz77_decoder.decode(code)?;
@@ -218,8 +322,8 @@ impl BlockDecoder {
         Ok(())
     }
 
-    fn extend(&mut self, buf: &[u8]) {
-        self.lz77_decoder.extend_from_slice(buf);
+    fn extend(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.lz77_decoder.try_extend_from_slice(buf)
     }
 
     fn decode_symbol<R: Read>(
@@ -287,10 +391,7 @@ mod tests {
 
     #[test]
     fn non_blocking_io_for_large_text_works() {
-        let text: String = (0..10000)
-            .into_iter()
-            .map(|i| format!("test {}", i))
-            .collect();
+        let text: String = (0..10000).map(|i| format!("test {}", i)).collect();
 
         let mut encoder = crate::deflate::Encoder::new(Vec::new());
         encoder.write_all(text.as_bytes()).unwrap();
@@ -312,4 +413,51 @@ mod tests {
 
         assert_eq!(decoded_data, b"Hello World!");
     }
+
+    #[test]
+    fn truncation_as_would_block_works() {
+        let plain = (0..5000)
+            .map(|i| (i % 13 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for options in [EncodeOptions::new(), EncodeOptions::new().no_compression()] {
+            let mut encoder = Encoder::with_options(Vec::new(), options);
+            encoder.write_all(&plain).unwrap();
+            let encoded_data = encoder.finish().into_result().unwrap();
+
+            let truncated = &encoded_data[..encoded_data.len() / 2];
+            let e = Decoder::new(truncated)
+                .read_to_end(&mut Vec::new())
+                .unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+            // Feeds the data byte by byte
+            let mut decoder = Decoder::new(io::Cursor::new(Vec::new()));
+            decoder.set_truncation_as_would_block(true);
+            let mut decoded_data = Vec::new();
+            for &b in &encoded_data {
+                let cursor = decoder.as_inner_mut();
+                let position = cursor.position();
+                cursor.get_mut().push(b);
+                cursor.set_position(position);
+                match decoder.read_to_end(&mut decoded_data) {
+                    Ok(_) => {}
+                    Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+                }
+            }
+            assert_eq!(decoded_data, plain);
+        }
+    }
+
+    #[test]
+    fn would_block_in_the_middle_of_huffman_code_is_not_error() {
+        let data: Vec<u8> = (0..50_000).map(|i| (i % 251) as u8).collect();
+
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(&data).unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+
+        let decoder = Decoder::new(WouldBlockReader::new(&encoded_data[..]));
+        let decoded_data = nb_read_to_end(decoder).unwrap();
+        assert_eq!(decoded_data, data);
+    }
 }
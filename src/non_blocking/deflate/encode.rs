@@ -0,0 +1,177 @@
+use crate::deflate::{self, EncodeOptions};
+use crate::finish::Finish;
+use crate::lz77;
+use crate::non_blocking::encode::EncoderDriver;
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Write};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, Write};
+
+/// DEFLATE encoder which supports non-blocking I/O.
+///
+/// The encoded data which could not be written to the inner writer (because it returned `WouldBlock`)
+/// are kept in a buffer, and written before anything else at the next call.
+/// So `write`, `flush` and [`Encoder::try_finish`] can be retried after a `WouldBlock` error.
+///
+/// Note that `write` consumes the given data even if the encoded data could not be written yet.
+/// Such data are written by the subsequent calls (e.g., `flush`).
+#[derive(Debug)]
+pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
+    driver: EncoderDriver<deflate::Encoder<Vec<u8>, E>, W>,
+}
+impl<W> Encoder<W, lz77::DefaultLz77Encoder>
+where
+    W: Write,
+{
+    /// Makes a new encoder instance.
+    ///
+    /// Encoded DEFLATE stream is written to `inner`.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::non_blocking::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!".as_ref()).unwrap();
+    ///
+    /// assert_eq!(encoder.finish().into_result().unwrap(),
+    ///            [5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237,
+    ///            147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
+    /// ```
+    pub fn new(inner: W) -> Self {
+        Self::with_options(inner, EncodeOptions::default())
+    }
+}
+impl<W, E> Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new encoder instance with specified options.
+    ///
+    /// Encoded DEFLATE stream is written to `inner`.
+    pub fn with_options(inner: W, options: EncodeOptions<E>) -> Self {
+        let encoder = deflate::Encoder::with_options(Vec::new(), options);
+        Encoder {
+            driver: EncoderDriver::new(encoder, inner),
+        }
+    }
+
+    /// Writes the rest of the DEFLATE stream (including the one which has been kept because of `WouldBlock`),
+    /// and flushes the inner writer.
+    ///
+    /// If this returns a `WouldBlock` error, it can be retried once the inner writer becomes writable.
+    /// Writing to this encoder after this method returned `Ok(())` fails.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::non_blocking::deflate::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.try_finish().unwrap();
+    /// assert!(encoder.is_finished());
+    /// assert!(encoder.write_all(b"Hello World!").is_err());
+    /// ```
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        self.driver.finish()
+    }
+
+    /// Finishes the encoding, and returns the inner writer.
+    ///
+    /// Unlike [`Encoder::try_finish`], this can not be retried if the inner writer returns `WouldBlock`.
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let result = self.driver.finish();
+        Finish::new(self.driver.into_inner(), result.err())
+    }
+
+    /// Returns `true` if the whole DEFLATE stream has been written to the inner writer, `false` otherwise.
+    pub fn is_finished(&self) -> bool {
+        self.driver.is_finished()
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        self.driver.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        self.driver.as_inner_mut()
+    }
+
+    /// Unwraps the `Encoder`, returning the inner stream.
+    ///
+    /// The encoded data which have not been written to the inner stream yet are discarded.
+    pub fn into_inner(self) -> W {
+        self.driver.into_inner()
+    }
+}
+impl<W, E> Write for Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.driver.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.driver.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::WouldBlockWriter;
+
+    #[test]
+    fn would_block_is_retryable() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().no_compression(),
+            EncodeOptions::new().fixed_huffman_codes(),
+        ] {
+            let mut expected = deflate::Encoder::with_options(Vec::new(), options.clone());
+            expected.write_all(&plain).unwrap();
+            expected.flush().unwrap();
+            let expected = expected.finish().into_result().unwrap();
+
+            let mut encoder = Encoder::with_options(WouldBlockWriter::new(Vec::new()), options);
+            let mut offset = 0;
+            while offset < plain.len() {
+                match encoder.write(&plain[offset..]) {
+                    Ok(size) => offset += size,
+                    Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+                }
+            }
+            retry(|| encoder.flush());
+            retry(|| encoder.try_finish());
+            assert!(encoder.is_finished());
+            assert_eq!(encoder.into_inner().into_inner(), expected);
+        }
+    }
+
+    fn retry<F: FnMut() -> io::Result<()>>(mut f: F) {
+        loop {
+            match f() {
+                Ok(()) => return,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => panic!("{}", e),
+            }
+        }
+    }
+}
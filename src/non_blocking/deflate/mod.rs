@@ -1,4 +1,4 @@
-//! The decoder of the DEFLATE format and algorithm.
+//! The encoder and decoder of the DEFLATE format and algorithm.
 //!
 //! The DEFLATE is defined in [RFC-1951](https://tools.ietf.org/html/rfc1951).
 //!
@@ -24,5 +24,7 @@
 //! assert_eq!(decoded_data, b"Hello World!");
 //! ```
 pub use self::decode::Decoder;
+pub use self::encode::Encoder;
 
 mod decode;
+mod encode;
@@ -0,0 +1,164 @@
+use crate::finish::Finish;
+use crate::lz77;
+use crate::util;
+use crate::{deflate, gzip, zlib};
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+#[cfg(feature = "no_std")]
+use core::mem;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Write};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    io::{self, Write},
+    mem,
+};
+
+/// An encoder which writes its output to a `Vec<u8>`.
+pub trait BufferedEncoder: Write {
+    fn output_mut(&mut self) -> &mut Vec<u8>;
+    fn finish_output(self) -> Finish<Vec<u8>, io::Error>;
+}
+
+/// Drives a [`BufferedEncoder`], and forwards its output to a (possibly non-blocking) writer.
+///
+/// The output which could not be written because of a `WouldBlock` error is kept,
+/// and written before anything else at the next call.
+#[derive(Debug)]
+pub struct EncoderDriver<C, W> {
+    encoder: Option<C>,
+    pending: Vec<u8>,
+    offset: usize,
+    needs_flush: bool,
+    inner: W,
+}
+impl<C, W> EncoderDriver<C, W>
+where
+    C: BufferedEncoder,
+    W: Write,
+{
+    pub fn new(mut encoder: C, inner: W) -> Self {
+        // The header (if any) has been written by the constructor of `encoder`
+        let pending = mem::take(encoder.output_mut());
+        EncoderDriver {
+            encoder: Some(encoder),
+            pending,
+            offset: 0,
+            needs_flush: false,
+            inner,
+        }
+    }
+    pub fn as_inner_ref(&self) -> &W {
+        &self.inner
+    }
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+    pub fn is_finished(&self) -> bool {
+        self.encoder.is_none() && self.offset == self.pending.len()
+    }
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.drain()?;
+        let encoder = self.encoder.as_mut().ok_or_else(util::finished_error)?;
+        let size = encoder.write(buf)?;
+        self.needs_flush = true;
+        self.take_output();
+        match self.drain() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(size),
+            Err(e) => Err(e),
+            Ok(()) => Ok(size),
+        }
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
+        if self.needs_flush {
+            if let Some(encoder) = self.encoder.as_mut() {
+                encoder.flush()?;
+                self.needs_flush = false;
+                self.take_output();
+                self.drain()?;
+            }
+        }
+        util::retry_interrupted(|| self.inner.flush())
+    }
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.drain()?;
+        if let Some(encoder) = self.encoder.take() {
+            let (output, error) = encoder.finish_output().unwrap();
+            self.pending = output;
+            self.offset = 0;
+            if let Some(e) = error {
+                return Err(e);
+            }
+            self.drain()?;
+        }
+        util::retry_interrupted(|| self.inner.flush())
+    }
+
+    /// Writes the pending output to the inner writer.
+    fn drain(&mut self) -> io::Result<()> {
+        while self.offset < self.pending.len() {
+            match self.inner.write(&self.pending[self.offset..]) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                Ok(size) => self.offset += size,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the output of the encoder to `self.pending` (which must have been drained).
+    fn take_output(&mut self) {
+        if let Some(encoder) = self.encoder.as_mut() {
+            let output = encoder.output_mut();
+            mem::swap(output, &mut self.pending);
+            output.clear();
+            self.offset = 0;
+        }
+    }
+}
+
+impl<E> BufferedEncoder for deflate::Encoder<Vec<u8>, E>
+where
+    E: lz77::Lz77Encode,
+{
+    fn output_mut(&mut self) -> &mut Vec<u8> {
+        self.as_inner_mut()
+    }
+    fn finish_output(self) -> Finish<Vec<u8>, io::Error> {
+        deflate::Encoder::finish(self)
+    }
+}
+
+impl<E> BufferedEncoder for zlib::Encoder<Vec<u8>, E>
+where
+    E: lz77::Lz77Encode,
+{
+    fn output_mut(&mut self) -> &mut Vec<u8> {
+        self.as_inner_mut()
+    }
+    fn finish_output(self) -> Finish<Vec<u8>, io::Error> {
+        zlib::Encoder::finish(self)
+    }
+}
+
+impl<E> BufferedEncoder for gzip::Encoder<Vec<u8>, E>
+where
+    E: lz77::Lz77Encode,
+{
+    fn output_mut(&mut self) -> &mut Vec<u8> {
+        self.as_inner_mut()
+    }
+    fn finish_output(self) -> Finish<Vec<u8>, io::Error> {
+        gzip::Encoder::finish(self)
+    }
+}
@@ -24,12 +24,18 @@
 //! assert_eq!(decoded_data, b"Hello World!");
 //! ```
 use crate::checksum;
-use crate::gzip::{Header, Trailer};
+use crate::error::{Error, ErrorKind};
+use crate::finish::Finish;
+use crate::gzip::{self, Header, Trailer};
+use crate::lz77;
 use crate::non_blocking::deflate;
+use crate::non_blocking::encode::EncoderDriver;
 #[cfg(feature = "no_std")]
-use core2::io::{self, Read};
+use alloc::vec::Vec;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read, Write};
 #[cfg(not(feature = "no_std"))]
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 
 /// GZIP decoder which supports non-blocking I/O.
 #[derive(Debug)]
@@ -98,6 +104,33 @@ impl<R: Read> Decoder<R> {
         }
     }
 
+    /// Makes this decoder report the end of the inner stream in the middle of the GZIP stream
+    /// as an `ErrorKind::WouldBlock` error (instead of `ErrorKind::UnexpectedEof`) if `enabled` is `true`.
+    ///
+    /// See [`deflate::Decoder::set_truncation_as_would_block`] for the details.
+    pub fn set_truncation_as_would_block(&mut self, enabled: bool) {
+        self.reader.set_truncation_as_would_block(enabled);
+    }
+
+    /// Returns the number of the (compressed) bytes read from the inner stream, including the header and the trailer.
+    ///
+    /// See [`deflate::Decoder::total_in`] for the details.
+    pub fn total_in(&self) -> u64 {
+        self.reader.total_in()
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        self.reader.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder (excluding the inner stream).
+    ///
+    /// See [`deflate::Decoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.reader.memory_usage()
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
         self.reader.as_inner_ref()
@@ -148,11 +181,10 @@ impl<R: Read> Read for Decoder<R> {
                 // so that random data from fuzzer can reach actually interesting code
                 // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
                 if cfg!(not(fuzzing)) && trailer.crc32() != self.crc32.value() {
-                    Err(invalid_data_error!(
-                        "CRC32 mismatched: value={}, expected={}",
-                        self.crc32.value(),
-                        trailer.crc32()
-                    ))
+                    Err(Error::new(ErrorKind::ChecksumMismatch, "CRC32 mismatched")
+                        .with_value(self.crc32.value())
+                        .with_expected(trailer.crc32())
+                        .into())
                 } else {
                     Ok(0)
                 }
@@ -164,11 +196,112 @@ impl<R: Read> Read for Decoder<R> {
     }
 }
 
+/// GZIP encoder which supports non-blocking I/O.
+///
+/// See [`deflate::Encoder`] for the behavior against `WouldBlock` errors.
+#[derive(Debug)]
+pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
+    driver: EncoderDriver<gzip::Encoder<Vec<u8>, E>, W>,
+}
+impl<W> Encoder<W, lz77::DefaultLz77Encoder>
+where
+    W: Write,
+{
+    /// Makes a new encoder instance.
+    ///
+    /// Encoded GZIP stream is written to `inner`.
+    /// The header is written lazily (by the first call which writes to `inner`).
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::non_blocking::gzip::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.try_finish().unwrap();
+    /// assert!(encoder.is_finished());
+    ///
+    /// let encoded_data = encoder.into_inner();
+    /// assert_eq!(encoded_data[..2], [31, 139]);
+    /// ```
+    pub fn new(inner: W) -> io::Result<Self> {
+        Self::with_options(inner, gzip::EncodeOptions::default())
+    }
+}
+impl<W, E> Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new encoder instance with specified options.
+    ///
+    /// Encoded GZIP stream is written to `inner`.
+    pub fn with_options(inner: W, options: gzip::EncodeOptions<E>) -> io::Result<Self> {
+        let encoder = gzip::Encoder::with_options(Vec::new(), options)?;
+        Ok(Encoder {
+            driver: EncoderDriver::new(encoder, inner),
+        })
+    }
+
+    /// Writes the rest of the GZIP stream (including the trailer), and flushes the inner writer.
+    ///
+    /// If this returns a `WouldBlock` error, it can be retried once the inner writer becomes writable.
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        self.driver.finish()
+    }
+
+    /// Finishes the encoding, and returns the inner writer.
+    ///
+    /// Unlike [`Encoder::try_finish`], this can not be retried if the inner writer returns `WouldBlock`.
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let result = self.driver.finish();
+        Finish::new(self.driver.into_inner(), result.err())
+    }
+
+    /// Returns `true` if the whole GZIP stream has been written to the inner writer, `false` otherwise.
+    pub fn is_finished(&self) -> bool {
+        self.driver.is_finished()
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        self.driver.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        self.driver.as_inner_mut()
+    }
+
+    /// Unwraps the `Encoder`, returning the inner stream.
+    ///
+    /// The encoded data which have not been written to the inner stream yet are discarded.
+    pub fn into_inner(self) -> W {
+        self.driver.into_inner()
+    }
+}
+impl<W, E> Write for Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.driver.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.driver.flush()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::gzip::Encoder;
-    use crate::util::{nb_read_to_end, WouldBlockReader};
+    use crate::util::{nb_read_to_end, WouldBlockReader, WouldBlockWriter};
     #[cfg(feature = "no_std")]
     use core2::io::Write;
     #[cfg(not(feature = "no_std"))]
@@ -195,4 +328,22 @@ mod tests {
         // decode_all(encoded).unwrap();
         assert_eq!(decode_all(encoded).unwrap(), decoded.to_vec());
     }
+
+    #[test]
+    fn non_blocking_encode_works() {
+        let plain = b"Hello World! Hello GZIP!!";
+        let mut encoder = super::Encoder::new(WouldBlockWriter::new(Vec::new())).unwrap();
+        let mut offset = 0;
+        while offset < plain.len() {
+            match encoder.write(&plain[offset..]) {
+                Ok(size) => offset += size,
+                Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+            }
+        }
+        while let Err(e) = encoder.try_finish() {
+            assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        }
+        let encoded = encoder.into_inner().into_inner();
+        assert_eq!(decode_all(&encoded).unwrap(), plain);
+    }
 }
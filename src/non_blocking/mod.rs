@@ -8,6 +8,11 @@
 //! `Decoder`s and `Encoder`s in this module will also return `ErrorKind::WouldBlock`.
 //!
 //! If retrying the operation after the inner I/O become available, it will proceed successfully.
+//! The decoders keep the partially read headers and symbols, and the encoders keep the encoded data
+//! which could not be written yet, across such errors.
+//!
+//! Note that the decoders and encoders outside of this module (e.g., [`crate::gzip::Decoder`])
+//! may lose their state if the inner I/O returns `ErrorKind::WouldBlock`.
 //!
 //! # NOTICE
 //!
@@ -18,4 +23,5 @@ pub mod deflate;
 pub mod gzip;
 pub mod zlib;
 
+mod encode;
 mod transaction;
@@ -1,4 +1,6 @@
 use crate::bit;
+use crate::error::{Error, ErrorKind};
+use crate::util;
 #[cfg(feature = "no_std")]
 use alloc::vec::Vec;
 #[cfg(feature = "no_std")]
@@ -11,6 +13,25 @@ use std::{
     io::{self, Read},
 };
 
+/// Returns the error for the inner stream which ended before the end of the compressed stream.
+///
+/// Its kind is `WouldBlock` if `would_block` is `true` (see `Decoder::set_truncation_as_would_block`),
+/// and `UnexpectedEof` otherwise.
+pub fn truncated_stream_error(would_block: bool) -> io::Error {
+    if would_block {
+        io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "The inner stream ended in the middle of the compressed stream",
+        )
+    } else {
+        Error::new(
+            ErrorKind::UnexpectedEof,
+            "The inner stream ended in the middle of the compressed stream",
+        )
+        .into()
+    }
+}
+
 #[derive(Debug)]
 pub struct TransactionalBitReader<R> {
     inner: bit::BitReader<TransactionalReader<R>>,
@@ -52,6 +73,9 @@ impl<R: Read> TransactionalBitReader<R> {
     }
 }
 impl<R> TransactionalBitReader<R> {
+    pub fn buffer_memory_usage(&self) -> usize {
+        self.inner.as_inner_ref().buffer.capacity()
+    }
     pub fn as_inner_ref(&self) -> &R {
         &self.inner.as_inner_ref().inner
     }
@@ -61,14 +85,30 @@ impl<R> TransactionalBitReader<R> {
     pub fn into_inner(self) -> R {
         self.inner.into_inner().inner
     }
+    pub fn set_eof_as_would_block(&mut self, enabled: bool) {
+        self.inner.as_inner_mut().eof_as_would_block = enabled;
+    }
+    pub fn eof_as_would_block(&self) -> bool {
+        self.inner.as_inner_ref().eof_as_would_block
+    }
+    /// Returns the number of the bytes read from the inner stream (including the ones read via `as_inner_mut`).
+    pub fn total_in(&self) -> u64 {
+        self.inner.as_inner_ref().count
+    }
+    /// Adds `n` to [`TransactionalBitReader::total_in`] (for the bytes read via `as_inner_mut`).
+    pub fn add_total_in(&mut self, n: usize) {
+        self.inner.as_inner_mut().count += n as u64;
+    }
 }
 
 #[derive(Debug)]
 pub struct TransactionalReader<R> {
     inner: R,
     in_transaction: bool,
+    eof_as_would_block: bool,
     buffer: Vec<u8>,
     offset: usize,
+    count: u64,
 }
 impl<R> TransactionalReader<R> {
     pub fn new(inner: R) -> Self {
@@ -76,7 +116,9 @@ impl<R> TransactionalReader<R> {
             inner,
             buffer: Vec::new(),
             in_transaction: false,
+            eof_as_would_block: false,
             offset: 0,
+            count: 0,
         }
     }
     #[inline]
@@ -106,8 +148,13 @@ impl<R: Read> Read for TransactionalReader<R> {
             return Ok(size);
         }
 
-        let size = self.inner.read(buf)?;
+        let size = util::retry_interrupted(|| self.inner.read(buf))?;
+        self.count += size as u64;
+        if size == 0 && !buf.is_empty() && self.eof_as_would_block {
+            return Err(truncated_stream_error(true));
+        }
         if self.in_transaction {
+            util::try_reserve(&mut self.buffer, size)?;
             self.buffer.extend_from_slice(&buf[0..size]);
             self.offset += size;
         }
@@ -0,0 +1,288 @@
+//! Compression options shared by the DEFLATE, ZLIB and GZIP encoders.
+//!
+//! [`CompressionOptions`] can be converted into the `EncodeOptions` of each format,
+//! so that applications supporting multiple output formats can keep a single configuration.
+//!
+//! # Examples
+//! ```
+//! use libflate::options::{CompressionOptions, Strategy};
+//! use libflate::{deflate, gzip, zlib};
+//!
+//! let options = CompressionOptions::new()
+//!     .strategy(Strategy::FixedHuffmanCodes)
+//!     .block_size(64 * 1024)
+//!     .window_size(4096);
+//!
+//! let _ = deflate::Encoder::with_options(Vec::new(), options.clone().into());
+//! let _ = zlib::Encoder::with_options(Vec::new(), options.clone().into()).unwrap();
+//! let _ = gzip::Encoder::with_options(Vec::new(), gzip::EncodeOptions::from(options)).unwrap();
+//! ```
+use crate::deflate;
+use crate::gzip;
+use crate::lz77;
+use crate::zlib;
+
+/// The strategy to encode DEFLATE blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Strategy {
+    /// Huffman codes optimized for each block (dynamic Huffman codes).
+    #[default]
+    Default,
+
+    /// The fixed Huffman codes defined by the DEFLATE format.
+    ///
+    /// This avoids the overhead of the code definitions, which is beneficial for very small inputs.
+    FixedHuffmanCodes,
+
+    /// Huffman codes optimized for each block, with only LZ77 matches of [`FILTERED_MIN_LENGTH`] bytes or longer.
+    ///
+    /// This favors Huffman coding over string matching,
+    /// which is beneficial for data consisting of small values with somewhat random distribution,
+    /// such as the image data filtered by a PNG encoder.
+    ///
+    /// The minimum match length is applied when the encoder is made,
+    /// so changing to (or from) this strategy by `Encoder::set_strategy` only affects the Huffman codes.
+    Filtered,
+}
+
+/// The minimum length of the LZ77 matches used by [`Strategy::Filtered`].
+pub const FILTERED_MIN_LENGTH: u16 = 6;
+
+/// Compression options which are independent of the output format.
+///
+/// Since this crate doesn't have speed/ratio tradeoff settings,
+/// all the levels other than [`lz77::CompressionLevel::None`] (no compression) use the same compression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompressionOptions {
+    level: lz77::CompressionLevel,
+    strategy: Strategy,
+    block_size: usize,
+    window_size: u16,
+    #[cfg(not(feature = "no_std"))]
+    threads: usize,
+}
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        CompressionOptions {
+            level: lz77::CompressionLevel::Balance,
+            strategy: Strategy::Default,
+            block_size: deflate::DEFAULT_BLOCK_SIZE,
+            window_size: lz77::MAX_WINDOW_SIZE,
+            #[cfg(not(feature = "no_std"))]
+            threads: 1,
+        }
+    }
+}
+impl CompressionOptions {
+    /// Makes a default instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Specifies the compression level.
+    ///
+    /// The default value is [`lz77::CompressionLevel::Balance`].
+    pub fn level(mut self, level: lz77::CompressionLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Specifies the strategy to encode DEFLATE blocks.
+    ///
+    /// This is ignored if the level is [`lz77::CompressionLevel::None`].
+    ///
+    /// The default value is [`Strategy::Default`].
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Specifies the hint of the size of a DEFLATE block.
+    ///
+    /// The default value is [`deflate::DEFAULT_BLOCK_SIZE`].
+    pub fn block_size(mut self, size: usize) -> Self {
+        self.block_size = size;
+        self
+    }
+
+    /// Specifies the size of the LZ77 sliding window.
+    ///
+    /// The value is clamped to [`lz77::MAX_WINDOW_SIZE`], which is also the default value.
+    pub fn window_size(mut self, size: u16) -> Self {
+        self.window_size = core::cmp::min(size, lz77::MAX_WINDOW_SIZE);
+        self
+    }
+
+    /// Specifies the number of worker threads used to compress input data.
+    ///
+    /// See [`deflate::EncodeOptions::threads`] for the details.
+    ///
+    /// The default value is `1`.
+    #[cfg(not(feature = "no_std"))]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Returns the compression level.
+    pub fn get_level(&self) -> lz77::CompressionLevel {
+        self.level.clone()
+    }
+
+    /// Returns the strategy to encode DEFLATE blocks.
+    pub fn get_strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Returns the hint of the size of a DEFLATE block.
+    pub fn get_block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the size of the LZ77 sliding window.
+    pub fn get_window_size(&self) -> u16 {
+        self.window_size
+    }
+
+    /// Returns the number of worker threads used to compress input data.
+    #[cfg(not(feature = "no_std"))]
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    fn lz77(&self) -> lz77::DefaultLz77Encoder {
+        let builder = lz77::DefaultLz77EncoderBuilder::new().window_size(self.window_size);
+        if self.strategy == Strategy::Filtered {
+            builder.min_length(FILTERED_MIN_LENGTH).build()
+        } else {
+            builder.build()
+        }
+    }
+    fn is_no_compression(&self) -> bool {
+        self.level == lz77::CompressionLevel::None
+    }
+    fn is_fixed_huffman_codes(&self) -> bool {
+        self.strategy == Strategy::FixedHuffmanCodes
+    }
+}
+
+impl From<CompressionOptions> for deflate::EncodeOptions<lz77::DefaultLz77Encoder> {
+    fn from(f: CompressionOptions) -> Self {
+        let mut options = deflate::EncodeOptions::with_lz77(f.lz77()).block_size(f.block_size);
+        #[cfg(not(feature = "no_std"))]
+        {
+            options = options.threads(f.threads);
+        }
+        if f.is_no_compression() {
+            options = options.no_compression();
+        } else if f.is_fixed_huffman_codes() {
+            options = options.fixed_huffman_codes();
+        }
+        options
+    }
+}
+
+impl From<CompressionOptions> for zlib::EncodeOptions<lz77::DefaultLz77Encoder> {
+    fn from(f: CompressionOptions) -> Self {
+        let mut options = zlib::EncodeOptions::with_lz77(f.lz77()).block_size(f.block_size);
+        #[cfg(not(feature = "no_std"))]
+        {
+            options = options.threads(f.threads);
+        }
+        if f.is_no_compression() {
+            options = options.no_compression();
+        } else if f.is_fixed_huffman_codes() {
+            options = options.fixed_huffman_codes();
+        }
+        options
+    }
+}
+
+impl From<CompressionOptions> for gzip::EncodeOptions<lz77::DefaultLz77Encoder> {
+    fn from(f: CompressionOptions) -> Self {
+        let mut options = gzip::EncodeOptions::with_lz77(f.lz77()).block_size(f.block_size);
+        #[cfg(not(feature = "no_std"))]
+        {
+            options = options.threads(f.threads);
+        }
+        if f.is_no_compression() {
+            options = options.no_compression();
+        } else if f.is_fixed_huffman_codes() {
+            options = options.fixed_huffman_codes();
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::any;
+    use crate::push::Format;
+    #[cfg(feature = "no_std")]
+    use alloc::vec::Vec;
+    #[cfg(feature = "no_std")]
+    use core2::io::{Read, Write};
+    #[cfg(not(feature = "no_std"))]
+    use std::io::{Read, Write};
+
+    fn encode(format: Format, options: CompressionOptions, plain: &[u8]) -> Vec<u8> {
+        match format {
+            Format::Deflate => {
+                let mut encoder = deflate::Encoder::with_options(Vec::new(), options.into());
+                encoder.write_all(plain).unwrap();
+                encoder.finish().into_result().unwrap()
+            }
+            Format::Zlib => {
+                let mut encoder = zlib::Encoder::with_options(Vec::new(), options.into()).unwrap();
+                encoder.write_all(plain).unwrap();
+                encoder.finish().into_result().unwrap()
+            }
+            Format::Gzip => {
+                let mut encoder = gzip::Encoder::with_options(Vec::new(), options.into()).unwrap();
+                encoder.write_all(plain).unwrap();
+                encoder.finish().into_result().unwrap()
+            }
+        }
+    }
+
+    #[test]
+    fn conversions_work() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let options = [
+            CompressionOptions::new(),
+            CompressionOptions::new().level(lz77::CompressionLevel::None),
+            CompressionOptions::new()
+                .strategy(Strategy::FixedHuffmanCodes)
+                .block_size(1024)
+                .window_size(1024),
+            CompressionOptions::new().strategy(Strategy::Filtered),
+        ];
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let sizes = options
+                .iter()
+                .map(|options| {
+                    let encoded = encode(format, options.clone(), &plain);
+                    let mut decoder = any::Decoder::new(&encoded[..]).unwrap();
+                    assert_eq!(decoder.format(), format);
+                    let mut decoded = Vec::new();
+                    decoder.read_to_end(&mut decoded).unwrap();
+                    assert_eq!(decoded, plain);
+                    encoded.len()
+                })
+                .collect::<Vec<_>>();
+            assert!(sizes[0] < sizes[1]);
+            assert!(sizes[2] < sizes[1]);
+            assert!(sizes[3] < sizes[1]);
+        }
+    }
+
+    #[test]
+    fn zlib_header_reflects_options() {
+        let options = CompressionOptions::new().window_size(1024);
+        let encoded = encode(Format::Zlib, options, b"Hello World!");
+        let decoder = zlib::Decoder::new(&encoded[..]).unwrap();
+        assert_eq!(decoder.header().window_size(), zlib::Lz77WindowSize::KB1);
+    }
+}
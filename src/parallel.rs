@@ -0,0 +1,679 @@
+//! Multi-threaded compression and pipelined decompression.
+//!
+//! If `threads(n)` (where `n > 1`) is specified to the encode options of
+//! [`deflate`], [`zlib`](crate::zlib) or [`gzip`](crate::gzip),
+//! the encoder splits the input into chunks of [`DEFAULT_CHUNK_SIZE`] bytes and
+//! compresses them on a pool of `n` worker threads.
+//!
+//! Idle workers take the next chunk from the shared queue,
+//! compress it (LZ77 and Huffman coding) and compute its checksum.
+//! The compressed chunks are then written to the output stream in the original order,
+//! and the checksums of them are combined into the one of the whole stream.
+//!
+//! Each chunk is compressed independently (i.e., LZ77 does not refer to the preceding chunks),
+//! so the compression ratio is slightly worse than the one of the single-threaded encoders.
+//! Besides, each worker uses a [`DefaultLz77Encoder`](crate::lz77::DefaultLz77Encoder)
+//! which has the same window size as the LZ77 encoder specified to the options.
+//!
+//! # Examples
+//! ```
+//! use std::io::{Read, Write};
+//! use libflate::gzip::{Decoder, EncodeOptions, Encoder};
+//!
+//! let plain = (0..1_000_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+//!
+//! let options = EncodeOptions::new().threads(4);
+//! let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+//! encoder.write_all(&plain).unwrap();
+//! let encoded = encoder.finish().into_result().unwrap();
+//!
+//! let mut decoded = Vec::new();
+//! Decoder::new(&encoded[..]).unwrap().read_to_end(&mut decoded).unwrap();
+//! assert_eq!(decoded, plain);
+//! ```
+//!
+//! DEFLATE streams can not be decompressed in parallel,
+//! but the reading of the compressed input can be overlapped with the decompression
+//! by wrapping the input with [`ReadAhead`], which reads it on a background I/O thread.
+use crate::checksum;
+use crate::deflate;
+use crate::lz77;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Read, Write};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+/// The size of the chunks which are compressed by the worker threads.
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// The checksum which is computed for each chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChecksumKind {
+    None,
+    Crc32,
+    Adler32,
+}
+
+/// The number of the buffers which are in flight between a [`ChecksumWorker`] and its owner.
+const CHECKSUM_BUFFERS: usize = 4;
+
+/// The size of each buffer passed to a [`ChecksumWorker`].
+const CHECKSUM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The default size of each buffer filled by the I/O thread of [`ReadAhead`].
+pub const DEFAULT_READ_AHEAD_SIZE: usize = 256 * 1024;
+
+/// The number of the buffers of [`ReadAhead`] (one is filled while the other is consumed).
+const READ_AHEAD_BUFFERS: usize = 2;
+
+/// The DEFLATE settings used to compress each chunk.
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkOptions {
+    pub block_size: usize,
+    pub dynamic_huffman: bool,
+    pub adaptive_block_size: bool,
+    pub reuse_huffman_codes: bool,
+    pub huffman_tables: Option<deflate::HuffmanTables>,
+    pub window_size: Option<u16>,
+}
+impl ChunkOptions {
+    /// Compresses `data` into a byte-aligned sequence of DEFLATE blocks.
+    ///
+    /// Unless `is_last` is `true`, the sequence ends with an empty non-final stored block
+    /// (i.e., the one written by a sync flush) so that the next chunk can be appended as is.
+    fn compress(&self, data: &[u8], is_last: bool) -> io::Result<Vec<u8>> {
+        let options = match self.window_size {
+            None => deflate::EncodeOptions::new().no_compression(),
+            Some(size) => {
+                deflate::EncodeOptions::with_lz77(lz77::DefaultLz77Encoder::with_window_size(size))
+            }
+        };
+        let mut options = options.block_size(self.block_size);
+        if !self.dynamic_huffman {
+            options = options.fixed_huffman_codes();
+        }
+        if self.adaptive_block_size {
+            options = options.adaptive_block_size();
+        }
+        if self.reuse_huffman_codes {
+            options = options.reuse_huffman_codes();
+        }
+        if let Some(ref tables) = self.huffman_tables {
+            options = options.huffman_tables(tables.clone());
+        }
+
+        let mut encoder = deflate::Encoder::with_options(Vec::new(), options);
+        encoder.write_all(data)?;
+        if is_last {
+            encoder.finish().into_result()
+        } else {
+            encoder.zlib_sync_flush()?;
+            Ok(encoder.into_inner())
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    index: u64,
+    data: Vec<u8>,
+    is_last: bool,
+}
+
+#[derive(Debug)]
+struct Compressed {
+    index: u64,
+    data: Vec<u8>,
+    checksum: Checksum,
+    size: u64,
+}
+
+#[derive(Debug)]
+enum Checksum {
+    None,
+    Crc32(checksum::Crc32),
+    Adler32(checksum::Adler32),
+}
+impl Checksum {
+    fn new(kind: ChecksumKind) -> Self {
+        match kind {
+            ChecksumKind::None => Checksum::None,
+            ChecksumKind::Crc32 => Checksum::Crc32(checksum::Crc32::new()),
+            ChecksumKind::Adler32 => Checksum::Adler32(checksum::Adler32::new()),
+        }
+    }
+    fn update(&mut self, buf: &[u8]) {
+        match self {
+            Checksum::None => {}
+            Checksum::Crc32(x) => x.update(buf),
+            Checksum::Adler32(x) => x.update(buf),
+        }
+    }
+    fn combine(&mut self, other: &Checksum, other_size: u64) {
+        match (self, other) {
+            (Checksum::Crc32(x), Checksum::Crc32(y)) => x.combine(y),
+            (Checksum::Adler32(x), Checksum::Adler32(y)) => x.combine(y, other_size),
+            _ => {}
+        }
+    }
+    fn value(&self) -> Option<u32> {
+        match self {
+            Checksum::None => None,
+            Checksum::Crc32(x) => Some(x.value()),
+            Checksum::Adler32(x) => Some(x.value()),
+        }
+    }
+}
+
+/// The multi-threaded compression pipeline shared by the DEFLATE, ZLIB and GZIP encoders.
+///
+/// This does not own the output stream, and the compressed data is written to the stream
+/// passed to each method call.
+#[derive(Debug)]
+pub(crate) struct Encoder {
+    buf: Vec<u8>,
+    jobs: Option<mpsc::Sender<Job>>,
+    results: mpsc::Receiver<io::Result<Compressed>>,
+    workers: Vec<thread::JoinHandle<()>>,
+    max_in_flight: u64,
+    next_index: u64,
+    next_write_index: u64,
+    completed: BTreeMap<u64, Compressed>,
+    checksum: Checksum,
+    finished: bool,
+}
+impl Encoder {
+    pub fn new(threads: usize, options: ChunkOptions, checksum: ChecksumKind) -> Self {
+        let (job_tx, job_rx) = mpsc::channel::<Job>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let workers = (0..threads)
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                let result_tx = result_tx.clone();
+                let options = options.clone();
+                thread::spawn(move || loop {
+                    let job = match job_rx.lock().map(|rx| rx.recv()) {
+                        Ok(Ok(job)) => job,
+                        _ => break,
+                    };
+                    let result = catch_panic(|| {
+                        let data = options.compress(&job.data, job.is_last)?;
+                        let mut chunk_checksum = Checksum::new(checksum);
+                        chunk_checksum.update(&job.data);
+                        Ok(Compressed {
+                            index: job.index,
+                            data,
+                            checksum: chunk_checksum,
+                            size: job.data.len() as u64,
+                        })
+                    });
+                    if result_tx.send(result).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+        Encoder {
+            buf: Vec::with_capacity(DEFAULT_CHUNK_SIZE),
+            jobs: Some(job_tx),
+            results: result_rx,
+            workers,
+            max_in_flight: threads as u64 * 2,
+            next_index: 0,
+            next_write_index: 0,
+            completed: BTreeMap::new(),
+            checksum: Checksum::new(checksum),
+            finished: false,
+        }
+    }
+
+    /// Returns the checksum of the data written so far (or `None` if `ChecksumKind::None` is specified).
+    ///
+    /// Note that the data which have not been compressed yet are not included.
+    pub fn checksum(&self) -> Option<u32> {
+        self.checksum.value()
+    }
+
+    /// Returns the number of bytes allocated for the chunks owned by this encoder
+    /// (i.e., the chunk being filled and the compressed chunks waiting to be written).
+    pub fn memory_usage(&self) -> usize {
+        self.buf.capacity()
+            + self
+                .completed
+                .values()
+                .map(|c| c.data.capacity())
+                .sum::<usize>()
+    }
+
+    pub fn write<W>(&mut self, writer: &mut W, mut buf: &[u8]) -> io::Result<()>
+    where
+        W: Write,
+    {
+        while !buf.is_empty() {
+            let size = (DEFAULT_CHUNK_SIZE - self.buf.len()).min(buf.len());
+            self.buf.extend_from_slice(&buf[..size]);
+            buf = &buf[size..];
+            if self.buf.len() == DEFAULT_CHUNK_SIZE {
+                self.submit(writer, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compresses the buffered data (ending with a sync flush) and writes all pending chunks to `writer`.
+    pub fn flush<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        self.submit(writer, false)?;
+        self.drain(writer)?;
+        writer.flush()
+    }
+
+    /// Compresses the buffered data as the final chunk, and waits for all workers to stop.
+    ///
+    /// If this encoder has already been finished, this method does nothing.
+    pub fn finish<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        if self.finished {
+            return Ok(());
+        }
+        self.submit(writer, true)?;
+        self.drain(writer)?;
+        self.finished = true;
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        Ok(())
+    }
+
+    fn submit<W>(&mut self, writer: &mut W, is_last: bool) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let job = Job {
+            index: self.next_index,
+            data: mem::replace(&mut self.buf, Vec::with_capacity(DEFAULT_CHUNK_SIZE)),
+            is_last,
+        };
+        let sent = self
+            .jobs
+            .as_ref()
+            .is_some_and(|jobs| jobs.send(job).is_ok());
+        if !sent {
+            return Err(io::Error::other("The worker threads have been stopped"));
+        }
+        self.next_index += 1;
+        while self.next_index - self.next_write_index >= self.max_in_flight {
+            self.wait_one(writer)?;
+        }
+        Ok(())
+    }
+
+    fn drain<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        while self.next_write_index < self.next_index {
+            self.wait_one(writer)?;
+        }
+        Ok(())
+    }
+
+    fn wait_one<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let compressed = self
+            .results
+            .recv()
+            .map_err(|_| io::Error::other("A worker thread has panicked"))??;
+        self.completed.insert(compressed.index, compressed);
+        while let Some(compressed) = self.completed.remove(&self.next_write_index) {
+            writer.write_all(&compressed.data)?;
+            self.checksum.combine(&compressed.checksum, compressed.size);
+            self.next_write_index += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Calls `f`, converting a panic into an error.
+///
+/// A worker thread must send a result for each job even if it panics, or the owner waits for the result forever.
+fn catch_panic<T, F>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T>,
+{
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(io::Error::other("A worker thread has panicked")))
+}
+
+/// A worker thread which computes the checksum of a stream in parallel with the compression or decompression.
+///
+/// The data are copied into a ring of [`CHECKSUM_BUFFERS`] buffers, which are passed to the worker in turn.
+#[derive(Debug)]
+pub(crate) struct ChecksumWorker<C> {
+    pending: Vec<u8>,
+    allocated_buffers: usize,
+    data_tx: mpsc::SyncSender<Vec<u8>>,
+    free_rx: mpsc::Receiver<Vec<u8>>,
+    handle: thread::JoinHandle<C>,
+}
+impl<C> ChecksumWorker<C>
+where
+    C: checksum::Checksum32 + Send + 'static,
+{
+    /// Spawns a worker thread which updates `checksum` with the data passed to [`ChecksumWorker::update`].
+    pub fn new(mut checksum: C) -> Self {
+        let (data_tx, data_rx) = mpsc::sync_channel::<Vec<u8>>(CHECKSUM_BUFFERS);
+        let (free_tx, free_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            for mut buf in data_rx {
+                checksum.update(&buf);
+                buf.clear();
+                let _ = free_tx.send(buf);
+            }
+            checksum
+        });
+        ChecksumWorker {
+            pending: Vec::with_capacity(CHECKSUM_BUFFER_SIZE),
+            allocated_buffers: 1,
+            data_tx,
+            free_rx,
+            handle,
+        }
+    }
+
+    pub fn update(&mut self, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            let size = cmp::min(buf.len(), CHECKSUM_BUFFER_SIZE - self.pending.len());
+            self.pending.extend_from_slice(&buf[..size]);
+            buf = &buf[size..];
+            if self.pending.len() == CHECKSUM_BUFFER_SIZE {
+                self.send_pending();
+            }
+        }
+    }
+
+    /// Waits for the worker to process all the data, and returns the resulting checksum.
+    pub fn finish(mut self) -> io::Result<C> {
+        if !self.pending.is_empty() {
+            let pending = mem::take(&mut self.pending);
+            let _ = self.data_tx.send(pending);
+        }
+        drop(self.data_tx);
+        self.handle
+            .join()
+            .map_err(|_| io::Error::other("The checksum thread has panicked"))
+    }
+
+    fn send_pending(&mut self) {
+        let next = match self.free_rx.try_recv() {
+            Ok(buf) => buf,
+            Err(_) if self.allocated_buffers < CHECKSUM_BUFFERS => {
+                self.allocated_buffers += 1;
+                Vec::with_capacity(CHECKSUM_BUFFER_SIZE)
+            }
+            // If the worker has panicked, the error is reported by `finish()`
+            Err(_) => self.free_rx.recv().unwrap_or_default(),
+        };
+        let pending = mem::replace(&mut self.pending, next);
+        let _ = self.data_tx.send(pending);
+    }
+}
+
+/// A reader which reads the inner reader on a background I/O thread.
+///
+/// The inner reader is read into a pair of buffers:
+/// while the data of one buffer are consumed (e.g., decompressed by a decoder which wraps this reader),
+/// the I/O thread fills the other one.
+/// This overlaps the latency of slow storage or network with the decompression.
+///
+/// The I/O thread stops when the inner reader reaches EOF.
+/// The thread is detached rather than joined when this reader is dropped:
+/// if it is inside an `inner.read()` call at that time, it notices the drop only after the call returns,
+/// and then drops the inner reader and exits.
+/// So if the call blocks indefinitely (e.g., on a socket which never receives data),
+/// the thread and the inner reader are leaked.
+/// The data which have been read ahead are discarded on drop,
+/// so the inner reader is not given back.
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use libflate::gzip::{Decoder, Encoder};
+/// use libflate::parallel::ReadAhead;
+///
+/// let mut encoder = Encoder::new(Vec::new()).unwrap();
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let encoded = encoder.finish().into_result().unwrap();
+///
+/// let mut decoder = Decoder::new(ReadAhead::new(std::io::Cursor::new(encoded))).unwrap();
+/// let mut decoded = Vec::new();
+/// decoder.read_to_end(&mut decoded).unwrap();
+/// assert_eq!(decoded, b"Hello World!");
+/// ```
+#[derive(Debug)]
+pub struct ReadAhead<R> {
+    current: Vec<u8>,
+    offset: usize,
+    eos: bool,
+    data_rx: mpsc::Receiver<io::Result<Vec<u8>>>,
+    free_tx: mpsc::SyncSender<Vec<u8>>,
+    _inner: std::marker::PhantomData<fn() -> R>,
+}
+impl<R> ReadAhead<R>
+where
+    R: Read + Send + 'static,
+{
+    /// Makes a new `ReadAhead` instance which reads `inner` in chunks of [`DEFAULT_READ_AHEAD_SIZE`] bytes.
+    pub fn new(inner: R) -> Self {
+        Self::with_buffer_size(inner, DEFAULT_READ_AHEAD_SIZE)
+    }
+
+    /// Makes a new `ReadAhead` instance which reads `inner` in chunks of up to `size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is `0`.
+    pub fn with_buffer_size(mut inner: R, size: usize) -> Self {
+        assert!(size > 0, "The buffer size must be positive");
+        let (data_tx, data_rx) = mpsc::sync_channel(READ_AHEAD_BUFFERS);
+        let (free_tx, free_rx) = mpsc::sync_channel::<Vec<u8>>(READ_AHEAD_BUFFERS);
+        for _ in 0..READ_AHEAD_BUFFERS {
+            let _ = free_tx.send(Vec::with_capacity(size));
+        }
+        thread::spawn(move || {
+            let mut next = None;
+            loop {
+                let mut buf = match next.take().or_else(|| free_rx.recv().ok()) {
+                    Some(buf) => buf,
+                    None => return,
+                };
+                buf.resize(size, 0);
+                match inner.read(&mut buf) {
+                    Ok(read_size) => {
+                        buf.truncate(read_size);
+                        if data_tx.send(Ok(buf)).is_err() || read_size == 0 {
+                            return;
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => next = Some(buf),
+                    Err(e) => {
+                        if data_tx.send(Err(e)).is_err() {
+                            return;
+                        }
+                        next = Some(buf);
+                    }
+                }
+            }
+        });
+        ReadAhead {
+            current: Vec::new(),
+            offset: 0,
+            eos: false,
+            data_rx,
+            free_tx,
+            _inner: std::marker::PhantomData,
+        }
+    }
+}
+impl<R> Read for ReadAhead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let size = cmp::min(available.len(), buf.len());
+        buf[..size].copy_from_slice(&available[..size]);
+        self.consume(size);
+        Ok(size)
+    }
+}
+impl<R> BufRead for ReadAhead<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.offset == self.current.len() && !self.eos {
+            let consumed = mem::take(&mut self.current);
+            self.offset = 0;
+            if consumed.capacity() > 0 {
+                let _ = self.free_tx.send(consumed);
+            }
+            match self.data_rx.recv() {
+                Ok(Ok(data)) => {
+                    self.eos = data.is_empty();
+                    self.current = data;
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Err(io::Error::other("The read-ahead thread has panicked")),
+            }
+        }
+        Ok(&self.current[self.offset..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.offset = cmp::min(self.offset + amt, self.current.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gzip;
+    use crate::zlib;
+    use std::io::Read;
+
+    fn plain() -> Vec<u8> {
+        (0..DEFAULT_CHUNK_SIZE * 5 + 123)
+            .map(|i| (i % 251) as u8 ^ (i / 1000) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn gzip_works() {
+        let plain = plain();
+        let options = gzip::EncodeOptions::new().threads(3);
+        let mut encoder = gzip::Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut decoded = Vec::new();
+        gzip::Decoder::new(&encoded[..])
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    #[test]
+    fn zlib_works() {
+        let plain = plain();
+        for options in [
+            zlib::EncodeOptions::new().threads(4),
+            zlib::EncodeOptions::new().no_compression().threads(2),
+            zlib::EncodeOptions::new().fixed_huffman_codes().threads(2),
+        ] {
+            let mut encoder = zlib::Encoder::with_options(Vec::new(), options).unwrap();
+            for chunk in plain.chunks(100_000) {
+                encoder.write_all(chunk).unwrap();
+                encoder.flush().unwrap();
+            }
+            let encoded = encoder.finish().into_result().unwrap();
+
+            let mut decoded = Vec::new();
+            zlib::Decoder::new(&encoded[..])
+                .unwrap()
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, plain);
+        }
+    }
+
+    #[test]
+    fn empty_input_works() {
+        let options = deflate::EncodeOptions::new().threads(2);
+        let encoder = deflate::Encoder::with_options(Vec::new(), options);
+        let encoded = encoder.finish().into_result().unwrap();
+
+        let mut decoded = Vec::new();
+        deflate::Decoder::new(&encoded[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn panic_is_converted_into_error() {
+        assert_eq!(catch_panic(|| Ok(1)).unwrap(), 1);
+        let error = catch_panic::<(), _>(|| panic!("foo")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn read_ahead_works() {
+        let plain = plain();
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+
+        for size in [1, 1000, DEFAULT_READ_AHEAD_SIZE] {
+            let reader = ReadAhead::with_buffer_size(io::Cursor::new(encoded.clone()), size);
+            let mut decoded = Vec::new();
+            gzip::Decoder::new(reader)
+                .unwrap()
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, plain);
+        }
+    }
+
+    #[test]
+    fn read_ahead_reports_errors() {
+        struct Failing(usize);
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0 += 1;
+                match self.0 {
+                    1 => Err(io::ErrorKind::Interrupted.into()),
+                    2 => Err(io::Error::other("failed")),
+                    3 => {
+                        buf[0] = 1;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        let mut reader = ReadAhead::new(Failing(0));
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap_err().to_string(), "failed");
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+}
@@ -0,0 +1,615 @@
+//! A thread-safe pool of encoders and decoders for server workloads.
+//!
+//! Making a coder for each of many small payloads (e.g., the bodies of HTTP requests) is dominated by
+//! the allocation of its buffers: the sliding window of a decoder, and the LZ77 and Huffman buffers of an encoder.
+//! [`CoderPool`] hands out new coders which reuse the buffers of the ones returned to the pool,
+//! and reclaims the buffers when the handed out [`Pooled`] coders are dropped (or finished).
+//!
+//! # Examples
+//! ```
+//! use std::io::{Read, Write};
+//! use std::sync::Arc;
+//! use libflate::pool::CoderPool;
+//!
+//! let pool = Arc::new(CoderPool::new());
+//!
+//! let handles = (0..4)
+//!     .map(|i| {
+//!         let pool = pool.clone();
+//!         std::thread::spawn(move || {
+//!             let message = format!("Hello World! ({})", i);
+//!             let mut encoder = pool.gzip_encoder(Vec::new()).unwrap();
+//!             encoder.write_all(message.as_bytes()).unwrap();
+//!             let encoded_data = encoder.finish().into_result().unwrap();
+//!
+//!             let mut decoder = pool.gzip_decoder(&encoded_data[..]).unwrap();
+//!             let mut decoded_data = String::new();
+//!             decoder.read_to_string(&mut decoded_data).unwrap();
+//!             assert_eq!(decoded_data, message);
+//!         })
+//!     })
+//!     .collect::<Vec<_>>();
+//! for handle in handles {
+//!     handle.join().unwrap();
+//! }
+//! assert!(pool.idle_decoders() > 0);
+//! ```
+use crate::finish::Finish;
+use crate::{deflate, gzip, lz77, zlib};
+use std::io::{self, BufRead, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard};
+
+/// The default value of [`CoderPool::max_idle`].
+pub const DEFAULT_MAX_IDLE: usize = 64;
+
+/// The default value of [`CoderPool::max_buffer_size`].
+///
+/// This is large enough for the buffers of the coders which are written (or read) in chunks of moderate sizes.
+pub const DEFAULT_MAX_BUFFER_SIZE: usize = 2 * 1024 * 1024;
+
+/// A thread-safe pool of encoders and decoders, which reuses their buffers.
+///
+/// All the coders are made with the options of the pool.
+/// The encoders are single-threaded (the [`deflate::EncodeOptions::threads`] setting is ignored).
+///
+/// The buffers of an encoder are reused only if the encoder was finished,
+/// and its compression parameters were not changed (e.g., by [`deflate::Encoder::set_level`]).
+/// The output of an encoder made by the pool is the same as the one of a new encoder made with the same options.
+#[derive(Debug)]
+pub struct CoderPool<E = lz77::DefaultLz77Encoder> {
+    encode_options: deflate::EncodeOptions<E>,
+    decode_options: deflate::DecodeOptions,
+    max_idle: usize,
+    max_buffer_size: usize,
+    encoders: Mutex<Vec<deflate::EncoderState<E>>>,
+    decoders: Mutex<Vec<lz77::Lz77Decoder>>,
+}
+impl CoderPool<lz77::DefaultLz77Encoder> {
+    /// Makes a new pool whose coders are made with the default options.
+    pub fn new() -> Self {
+        Self::with_options(deflate::EncodeOptions::new(), deflate::DecodeOptions::new())
+    }
+}
+impl Default for CoderPool<lz77::DefaultLz77Encoder> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<E> CoderPool<E>
+where
+    E: lz77::Lz77Encode + Clone,
+{
+    /// Makes a new pool whose coders are made with the specified options.
+    pub fn with_options(
+        encode_options: deflate::EncodeOptions<E>,
+        decode_options: deflate::DecodeOptions,
+    ) -> Self {
+        CoderPool {
+            encode_options: encode_options.threads(1),
+            decode_options,
+            max_idle: DEFAULT_MAX_IDLE,
+            max_buffer_size: DEFAULT_MAX_BUFFER_SIZE,
+            encoders: Mutex::new(Vec::new()),
+            decoders: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Specifies the maximum number of the idle buffers of the encoders (and the decoders respectively) kept by the pool.
+    ///
+    /// The buffers returned to a full pool are released.
+    ///
+    /// The default value is [`DEFAULT_MAX_IDLE`].
+    pub fn max_idle(mut self, max_idle: usize) -> Self {
+        self.max_idle = max_idle;
+        self
+    }
+
+    /// Specifies the maximum size (in bytes) of the buffers of a coder kept by the pool.
+    ///
+    /// The buffers grow with the size of the data passed at once (e.g., a single `write_all` of a large input),
+    /// so the larger buffers of a coder returned to the pool are released instead of being kept
+    /// (see [`deflate::Encoder::memory_usage`] and [`deflate::Decoder::memory_usage`]).
+    ///
+    /// The default value is [`DEFAULT_MAX_BUFFER_SIZE`].
+    pub fn max_buffer_size(mut self, max_buffer_size: usize) -> Self {
+        self.max_buffer_size = max_buffer_size;
+        self
+    }
+
+    /// Returns the number of the idle buffers of the encoders kept by the pool.
+    pub fn idle_encoders(&self) -> usize {
+        lock(&self.encoders).len()
+    }
+
+    /// Returns the number of the idle buffers of the decoders kept by the pool.
+    pub fn idle_decoders(&self) -> usize {
+        lock(&self.decoders).len()
+    }
+
+    /// Releases all the idle buffers kept by the pool.
+    pub fn clear(&self) {
+        lock(&self.encoders).clear();
+        lock(&self.decoders).clear();
+    }
+
+    /// Makes a DEFLATE encoder.
+    pub fn deflate_encoder<W: Write>(&self, inner: W) -> Pooled<'_, deflate::Encoder<W, E>, E> {
+        let mut encoder = deflate::Encoder::with_options(inner, self.encode_options.clone());
+        self.reuse_encoder_buffers(&mut encoder);
+        Pooled::new(encoder, self)
+    }
+
+    /// Makes a ZLIB encoder.
+    ///
+    /// # Errors
+    ///
+    /// If writing the header to `inner` fails, this returns an error.
+    pub fn zlib_encoder<W: Write>(
+        &self,
+        inner: W,
+    ) -> io::Result<Pooled<'_, zlib::Encoder<W, E>, E>> {
+        let options = zlib::EncodeOptions::from_deflate_options(self.encode_options.clone());
+        let mut encoder = zlib::Encoder::with_options(inner, options)?;
+        self.reuse_encoder_buffers(encoder.as_deflate_mut());
+        Ok(Pooled::new(encoder, self))
+    }
+
+    /// Makes a GZIP encoder which writes the default header (see [`gzip::HeaderBuilder::new`]).
+    ///
+    /// # Errors
+    ///
+    /// If writing the header to `inner` fails, this returns an error.
+    pub fn gzip_encoder<W: Write>(
+        &self,
+        inner: W,
+    ) -> io::Result<Pooled<'_, gzip::Encoder<W, E>, E>> {
+        let mut header = gzip::HeaderBuilder::new().finish();
+        if let Some(lz77) = self.encode_options.get_lz77() {
+            header.set_compression_level(From::from(lz77.compression_level()));
+        }
+        self.gzip_encoder_with_header(inner, header)
+    }
+
+    /// Makes a GZIP encoder which writes `header`.
+    ///
+    /// # Errors
+    ///
+    /// If writing the header to `inner` fails, this returns an error.
+    pub fn gzip_encoder_with_header<W: Write>(
+        &self,
+        inner: W,
+        header: gzip::Header,
+    ) -> io::Result<Pooled<'_, gzip::Encoder<W, E>, E>> {
+        let options =
+            gzip::EncodeOptions::from_deflate_options(self.encode_options.clone(), header);
+        let mut encoder = gzip::Encoder::with_options(inner, options)?;
+        self.reuse_encoder_buffers(encoder.as_deflate_mut());
+        Ok(Pooled::new(encoder, self))
+    }
+
+    /// Makes a DEFLATE decoder.
+    pub fn deflate_decoder<R: Read>(&self, inner: R) -> Pooled<'_, deflate::Decoder<R>, E> {
+        let mut decoder = deflate::Decoder::with_options(inner, self.decode_options.clone());
+        self.reuse_decoder_buffer(&mut decoder);
+        Pooled::new(decoder, self)
+    }
+
+    /// Makes a ZLIB decoder.
+    ///
+    /// # Errors
+    ///
+    /// If reading the header from `inner` fails, this returns an error.
+    pub fn zlib_decoder<R: Read>(&self, inner: R) -> io::Result<Pooled<'_, zlib::Decoder<R>, E>> {
+        let mut decoder = zlib::Decoder::with_options(inner, self.decode_options.clone())?;
+        self.reuse_decoder_buffer(decoder.as_deflate_mut());
+        Ok(Pooled::new(decoder, self))
+    }
+
+    /// Makes a GZIP decoder.
+    ///
+    /// # Errors
+    ///
+    /// If reading the header from `inner` fails, this returns an error.
+    pub fn gzip_decoder<R: Read>(&self, inner: R) -> io::Result<Pooled<'_, gzip::Decoder<R>, E>> {
+        let options = gzip::DecodeOptions::new().deflate_options(self.decode_options.clone());
+        let mut decoder = gzip::Decoder::with_options(inner, options)?;
+        self.reuse_decoder_buffer(decoder.as_deflate_mut());
+        Ok(Pooled::new(decoder, self))
+    }
+
+    fn reuse_encoder_buffers<W: Write>(&self, encoder: &mut deflate::Encoder<W, E>) {
+        if let Some(state) = lock(&self.encoders).pop() {
+            encoder.reuse_buffers(state);
+        }
+    }
+    fn reuse_decoder_buffer<R: Read>(&self, decoder: &mut deflate::Decoder<R>) {
+        if let Some(buffer) = lock(&self.decoders).pop() {
+            decoder.replace_buffer(buffer);
+        }
+    }
+
+    fn put_encoder<W: Write>(&self, encoder: deflate::Encoder<W, E>) -> W {
+        let (inner, state) = encoder.into_parts();
+        if !state.is_idle() || state.memory_usage() > self.max_buffer_size {
+            return inner;
+        }
+        let mut encoders = lock(&self.encoders);
+        if encoders.len() < self.max_idle {
+            encoders.push(state);
+        }
+        inner
+    }
+    fn put_decoder<R: Read>(&self, decoder: &mut deflate::Decoder<R>) {
+        let buffer = decoder.replace_buffer(lz77::Lz77Decoder::new());
+        if buffer.memory_usage() > self.max_buffer_size {
+            return;
+        }
+        let mut decoders = lock(&self.decoders);
+        if decoders.len() < self.max_idle {
+            decoders.push(buffer);
+        }
+    }
+}
+
+thread_local! {
+    static LOCAL_POOL: CoderPool = CoderPool::new().max_idle(1);
+}
+
+/// Calls `f` with the pool local to the current thread.
+///
+/// The pool keeps the buffers of (at most) one encoder and one decoder,
+/// which are reused by the `compress_to_vec` and `decompress_to_vec` functions of each format.
+/// The buffers larger than [`DEFAULT_MAX_BUFFER_SIZE`] are released so that a single large call does not keep them for the lifetime of the thread.
+/// If the local pool has already been destroyed (i.e., while the thread is exiting), a new pool is used instead.
+pub(crate) fn with_local_pool<F, T>(f: F) -> T
+where
+    F: FnOnce(&CoderPool) -> T,
+{
+    let mut f = Some(f);
+    LOCAL_POOL
+        .try_with(|pool| (f.take().expect("Never fails"))(pool))
+        .unwrap_or_else(|_| (f.take().expect("Never fails"))(&CoderPool::new()))
+}
+
+/// Locks `mutex`, ignoring the poisoning (the pooled buffers are valid even if a panic occurred while holding the lock).
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A coder whose buffers can be returned to a [`CoderPool`].
+///
+/// This is implemented by the encoders and the decoders made by [`CoderPool`].
+pub trait Recycle<E> {
+    /// Returns the reusable buffers of this coder to `pool`, dropping the rest.
+    fn recycle(self, pool: &CoderPool<E>);
+}
+impl<W, E> Recycle<E> for deflate::Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode + Clone,
+{
+    fn recycle(self, pool: &CoderPool<E>) {
+        pool.put_encoder(self);
+    }
+}
+impl<W, E> Recycle<E> for zlib::Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode + Clone,
+{
+    fn recycle(self, pool: &CoderPool<E>) {
+        pool.put_encoder(self.into_deflate());
+    }
+}
+impl<W, E> Recycle<E> for gzip::Encoder<W, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode + Clone,
+{
+    fn recycle(self, pool: &CoderPool<E>) {
+        pool.put_encoder(self.into_deflate());
+    }
+}
+impl<R, E> Recycle<E> for deflate::Decoder<R>
+where
+    R: Read,
+    E: lz77::Lz77Encode + Clone,
+{
+    fn recycle(mut self, pool: &CoderPool<E>) {
+        pool.put_decoder(&mut self);
+    }
+}
+impl<R, E> Recycle<E> for zlib::Decoder<R>
+where
+    R: Read,
+    E: lz77::Lz77Encode + Clone,
+{
+    fn recycle(mut self, pool: &CoderPool<E>) {
+        pool.put_decoder(self.as_deflate_mut());
+    }
+}
+impl<R, E> Recycle<E> for gzip::Decoder<R>
+where
+    R: Read,
+    E: lz77::Lz77Encode + Clone,
+{
+    fn recycle(mut self, pool: &CoderPool<E>) {
+        pool.put_decoder(self.as_deflate_mut());
+    }
+}
+
+/// A coder made by a [`CoderPool`], whose buffers are returned to the pool when this is dropped.
+///
+/// This dereferences to the coder.
+/// Note that dropping a pooled encoder doesn't finish its stream: call [`Pooled::finish`] for that.
+#[derive(Debug)]
+pub struct Pooled<'a, C, E = lz77::DefaultLz77Encoder>
+where
+    C: Recycle<E>,
+{
+    coder: Option<C>,
+    pool: &'a CoderPool<E>,
+}
+impl<'a, C, E> Pooled<'a, C, E>
+where
+    C: Recycle<E>,
+{
+    fn new(coder: C, pool: &'a CoderPool<E>) -> Self {
+        Pooled {
+            coder: Some(coder),
+            pool,
+        }
+    }
+
+    /// Detaches the coder from the pool, so that its buffers are not returned to the pool.
+    pub fn detach(mut self) -> C {
+        self.coder.take().expect("Never fails")
+    }
+}
+impl<'a, W, E> Pooled<'a, deflate::Encoder<W, E>, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode + Clone,
+{
+    /// Finishes the stream (see [`deflate::Encoder::finish`]), and returns the inner stream.
+    ///
+    /// The buffers of the encoder are returned to the pool.
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let mut encoder = self.coder.take().expect("Never fails");
+        let result = encoder.try_finish();
+        let inner = self.pool.put_encoder(encoder);
+        Finish::new(inner, result.err())
+    }
+}
+impl<'a, W, E> Pooled<'a, zlib::Encoder<W, E>, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode + Clone,
+{
+    /// Finishes the stream (see [`zlib::Encoder::finish`]), and returns the inner stream.
+    ///
+    /// The buffers of the encoder are returned to the pool.
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let mut encoder = self.coder.take().expect("Never fails");
+        let result = encoder.try_finish();
+        let inner = self.pool.put_encoder(encoder.into_deflate());
+        Finish::new(inner, result.err())
+    }
+}
+impl<'a, W, E> Pooled<'a, gzip::Encoder<W, E>, E>
+where
+    W: Write,
+    E: lz77::Lz77Encode + Clone,
+{
+    /// Finishes the stream (see [`gzip::Encoder::finish`]), and returns the inner stream.
+    ///
+    /// The buffers of the encoder are returned to the pool.
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let mut encoder = self.coder.take().expect("Never fails");
+        let result = encoder.try_finish();
+        let inner = self.pool.put_encoder(encoder.into_deflate());
+        Finish::new(inner, result.err())
+    }
+}
+impl<'a, C, E> Drop for Pooled<'a, C, E>
+where
+    C: Recycle<E>,
+{
+    fn drop(&mut self) {
+        if let Some(coder) = self.coder.take() {
+            coder.recycle(self.pool);
+        }
+    }
+}
+impl<'a, C, E> Deref for Pooled<'a, C, E>
+where
+    C: Recycle<E>,
+{
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        self.coder.as_ref().expect("Never fails")
+    }
+}
+impl<'a, C, E> DerefMut for Pooled<'a, C, E>
+where
+    C: Recycle<E>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.coder.as_mut().expect("Never fails")
+    }
+}
+impl<'a, C, E> Write for Pooled<'a, C, E>
+where
+    C: Recycle<E> + Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.deref_mut().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.deref_mut().flush()
+    }
+}
+impl<'a, C, E> Read for Pooled<'a, C, E>
+where
+    C: Recycle<E> + Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.deref_mut().read(buf)
+    }
+}
+impl<'a, C, E> BufRead for Pooled<'a, C, E>
+where
+    C: Recycle<E> + BufRead,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.deref_mut().fill_buf()
+    }
+    fn consume(&mut self, amt: usize) {
+        self.deref_mut().consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(seed: usize) -> Vec<u8> {
+        (0..50_000)
+            .map(|i| ((i + seed) % 7 * i % 251) as u8)
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn coder_pool_works() {
+        let pool = CoderPool::new();
+        for i in 0..5 {
+            let plain = plain(i);
+            let mut encoder = pool.zlib_encoder(Vec::new()).unwrap();
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            assert_eq!(encoded, zlib::compress_to_vec(&plain).unwrap());
+            assert_eq!(pool.idle_encoders(), 1);
+
+            let mut decoder = pool.zlib_decoder(&encoded[..]).unwrap();
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, plain);
+            drop(decoder);
+            assert_eq!(pool.idle_decoders(), 1);
+
+            let mut encoder = pool.deflate_encoder(Vec::new());
+            encoder.write_all(&plain).unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            assert_eq!(encoded, deflate::compress_to_vec(&plain).unwrap());
+
+            let mut decoder = pool.deflate_decoder(&encoded[..]);
+            let mut decoded = Vec::new();
+            decoder.read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, plain);
+        }
+
+        // An unfinished encoder holds buffered data, which are not reused
+        let mut encoder = pool.deflate_encoder(Vec::new());
+        encoder.write_all(b"foo").unwrap();
+        drop(encoder);
+        let mut encoder = pool.deflate_encoder(Vec::new());
+        encoder.write_all(b"bar").unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(encoded, deflate::compress_to_vec(b"bar").unwrap());
+
+        // An encoder whose parameters have been changed is not reused
+        let mut encoder = pool.deflate_encoder(Vec::new());
+        encoder.set_level(lz77::CompressionLevel::None).unwrap();
+        encoder.finish().into_result().unwrap();
+        let mut encoder = pool.deflate_encoder(Vec::new());
+        encoder.write_all(&plain(0)).unwrap();
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(encoded, deflate::compress_to_vec(&plain(0)).unwrap());
+
+        let encoder = pool.gzip_encoder(Vec::new()).unwrap().detach();
+        encoder.finish().into_result().unwrap();
+        pool.clear();
+        assert_eq!((pool.idle_encoders(), pool.idle_decoders()), (0, 0));
+    }
+
+    #[test]
+    fn local_pool_works() {
+        std::thread::spawn(|| {
+            let plain = plain(0);
+            let encoded = gzip::compress_to_vec(&plain).unwrap();
+            assert_eq!(with_local_pool(|pool| pool.idle_encoders()), 1);
+            // The default header holds the current time, so the MTIME field may differ
+            let mut reencoded = gzip::compress_to_vec(&plain).unwrap();
+            reencoded[4..8].copy_from_slice(&encoded[4..8]);
+            assert_eq!(reencoded, encoded);
+            let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+            encoder.write_all(b"foo").unwrap();
+            let expected = encoder.finish().into_result().unwrap();
+            assert_eq!(zlib::compress_to_vec(b"foo").unwrap(), expected);
+            assert_eq!(with_local_pool(|pool| pool.idle_encoders()), 1);
+
+            assert_eq!(gzip::decompress_to_vec(&encoded).unwrap(), plain);
+            assert_eq!(with_local_pool(|pool| pool.idle_decoders()), 1);
+            assert_eq!(gzip::decompress_to_vec(&encoded).unwrap(), plain);
+            assert!(deflate::decompress_to_vec(&[0xFF]).is_err());
+            assert_eq!(with_local_pool(|pool| pool.idle_decoders()), 1);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn coder_pool_is_bounded() {
+        let pool = CoderPool::new().max_idle(2);
+        let decoders = (0..5)
+            .map(|_| pool.deflate_decoder(&[3, 0][..]))
+            .collect::<Vec<_>>();
+        drop(decoders);
+        assert_eq!(pool.idle_decoders(), 2);
+    }
+
+    #[test]
+    fn local_pool_releases_large_buffers() {
+        std::thread::spawn(|| {
+            let large = (0..8_000_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            let encoded = deflate::compress_to_vec(&large).unwrap();
+            assert_eq!(with_local_pool(|pool| pool.idle_encoders()), 0);
+
+            let small = deflate::compress_to_vec(&plain(0)).unwrap();
+            assert_eq!(with_local_pool(|pool| pool.idle_encoders()), 1);
+            assert_eq!(deflate::decompress_to_vec(&small).unwrap(), plain(0));
+            assert_eq!(deflate::decompress_to_vec(&encoded).unwrap(), large);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn large_buffers_are_not_pooled() {
+        let pool = CoderPool::new().max_buffer_size(128 * 1024);
+        let large = (0..1_000_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let mut encoder = pool.deflate_encoder(Vec::new());
+        encoder.write_all(&large).unwrap();
+        assert!(encoder.memory_usage() > 128 * 1024);
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(pool.idle_encoders(), 0);
+        let encoder = pool.deflate_encoder(Vec::new());
+        assert_eq!(encoder.memory_usage(), 0);
+        drop(encoder);
+
+        let mut decoder = pool.deflate_decoder(&encoded[..]);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, large);
+        drop(decoder);
+        assert_eq!(pool.idle_decoders(), 0);
+
+        let mut encoder = pool.deflate_encoder(Vec::new());
+        encoder.write_all(&plain(0)).unwrap();
+        encoder.finish().into_result().unwrap();
+        assert_eq!(pool.idle_encoders(), 1);
+    }
+}
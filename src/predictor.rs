@@ -0,0 +1,660 @@
+//! PNG and TIFF predictors, which are applied to image data before compression and reverted after decompression.
+//!
+//! A predictor replaces each sample with its difference from a prediction made from the neighbouring samples,
+//! which makes smooth image data much more compressible.
+//! This is the pairing used by PNG images and by the `FlateDecode` filter of PDF
+//! (whose `/Predictor` parameter is `2` for [`Predictor::Tiff`] and `10` to `15` for the PNG predictors),
+//! so [`Encoder`] is typically wrapped by a ZLIB encoder and [`Decoder`] wraps a ZLIB decoder.
+//!
+//! The data are processed row by row, where the size of a row is determined by [`Params`].
+//! The last row may be shorter than the others.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::{Read, Write};
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::{Read, Write};
+//! use libflate::predictor::{self, Params, Predictor};
+//! use libflate::zlib;
+//!
+//! // A 64x16 RGB image
+//! let params = Params::new(64, 3, 8);
+//! let image = (0..16 * 64 * 3).map(|i| (i % 192 + i / 192) as u8).collect::<Vec<_>>();
+//!
+//! // Encoding
+//! let encoder = zlib::Encoder::new(Vec::new()).unwrap();
+//! let mut encoder = predictor::Encoder::new(encoder, Predictor::PngAdaptive, params.clone()).unwrap();
+//! encoder.write_all(&image).unwrap();
+//! let encoder = encoder.finish().into_result().unwrap();
+//! let encoded_data = encoder.finish().into_result().unwrap();
+//!
+//! // Decoding
+//! let decoder = zlib::Decoder::new(&encoded_data[..]).unwrap();
+//! let mut decoder = predictor::Decoder::new(decoder, Predictor::PngAdaptive, params).unwrap();
+//! let mut decoded_data = Vec::new();
+//! decoder.read_to_end(&mut decoded_data).unwrap();
+//!
+//! assert_eq!(decoded_data, image);
+//! ```
+use crate::error::{Error, ErrorKind};
+use crate::finish::Finish;
+use crate::util;
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+#[cfg(feature = "no_std")]
+use core::{cmp, mem};
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp,
+    io::{self, Read, Write},
+    mem,
+};
+
+/// The layout of the image data processed by a predictor.
+///
+/// The names and the default values of the fields follow the decode parameters of the `FlateDecode` filter of PDF.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Params {
+    columns: usize,
+    colors: u8,
+    bits_per_component: u8,
+}
+impl Params {
+    /// Makes a new `Params` instance.
+    ///
+    /// - `columns` is the number of the pixels in a row,
+    /// - `colors` is the number of the components of a pixel (e.g., `3` for RGB),
+    /// - `bits_per_component` is the number of the bits of a component (`1`, `2`, `4`, `8` or `16`).
+    pub fn new(columns: usize, colors: u8, bits_per_component: u8) -> Self {
+        Params {
+            columns,
+            colors,
+            bits_per_component,
+        }
+    }
+
+    /// Returns the number of the pixels in a row.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the number of the components of a pixel.
+    pub fn colors(&self) -> u8 {
+        self.colors
+    }
+
+    /// Returns the number of the bits of a component.
+    pub fn bits_per_component(&self) -> u8 {
+        self.bits_per_component
+    }
+
+    /// Returns the number of the bytes of a row (excluding the filter type byte of the PNG predictors).
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::predictor::Params;
+    ///
+    /// assert_eq!(Params::new(10, 3, 8).row_size(), 30);
+    /// assert_eq!(Params::new(10, 1, 1).row_size(), 2);
+    /// ```
+    pub fn row_size(&self) -> usize {
+        self.columns
+            .saturating_mul(usize::from(self.colors) * usize::from(self.bits_per_component))
+            .div_ceil(8)
+    }
+
+    /// Returns the number of the bytes of a pixel, rounded up to one byte
+    /// (i.e., the distance between the bytes compared by the PNG predictors).
+    pub fn bytes_per_pixel(&self) -> usize {
+        cmp::max(
+            1,
+            usize::from(self.colors) * usize::from(self.bits_per_component) / 8,
+        )
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        if self.columns == 0 || self.colors == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The columns and the colors of a predictor must be positive",
+            ));
+        }
+        if ![1, 2, 4, 8, 16].contains(&self.bits_per_component) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "The bits per component of a predictor must be 1, 2, 4, 8 or 16",
+            )
+            .with_value(self.bits_per_component)
+            .into());
+        }
+        let bits_per_pixel = usize::from(self.colors) * usize::from(self.bits_per_component);
+        if self.columns.checked_mul(bits_per_pixel).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Too many columns of a predictor",
+            ));
+        }
+        Ok(())
+    }
+}
+impl Default for Params {
+    /// One 8-bit component per row (the defaults of PDF).
+    fn default() -> Self {
+        Self::new(1, 1, 8)
+    }
+}
+
+/// PNG filter types.
+///
+/// See [the PNG specification](https://www.w3.org/TR/png/#9Filter-types) for the details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PngFilter {
+    /// The bytes are not changed.
+    None = 0,
+
+    /// The difference from the byte of the previous pixel.
+    Sub = 1,
+
+    /// The difference from the byte of the previous row.
+    Up = 2,
+
+    /// The difference from the average of the bytes of the previous pixel and the previous row.
+    Average = 3,
+
+    /// The difference from the one of the previous pixel, the previous row,
+    /// and the previous pixel of the previous row which is the closest to their linear prediction.
+    Paeth = 4,
+}
+impl PngFilter {
+    /// Returns the filter type of `value`, which is the first byte of a filtered row.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(PngFilter::None),
+            1 => Some(PngFilter::Sub),
+            2 => Some(PngFilter::Up),
+            3 => Some(PngFilter::Average),
+            4 => Some(PngFilter::Paeth),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of this filter type.
+    pub fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    const ALL: [PngFilter; 5] = [
+        PngFilter::None,
+        PngFilter::Sub,
+        PngFilter::Up,
+        PngFilter::Average,
+        PngFilter::Paeth,
+    ];
+
+    /// Returns the prediction for a byte from `a` (the previous pixel), `b` (the previous row)
+    /// and `c` (the previous pixel of the previous row).
+    fn predict(self, a: u8, b: u8, c: u8) -> u8 {
+        match self {
+            PngFilter::None => 0,
+            PngFilter::Sub => a,
+            PngFilter::Up => b,
+            PngFilter::Average => ((u16::from(a) + u16::from(b)) / 2) as u8,
+            PngFilter::Paeth => {
+                let p = i16::from(a) + i16::from(b) - i16::from(c);
+                let (pa, pb, pc) = (
+                    (p - i16::from(a)).abs(),
+                    (p - i16::from(b)).abs(),
+                    (p - i16::from(c)).abs(),
+                );
+                if pa <= pb && pa <= pc {
+                    a
+                } else if pb <= pc {
+                    b
+                } else {
+                    c
+                }
+            }
+        }
+    }
+}
+
+/// A predictor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Predictor {
+    /// TIFF predictor 2 (horizontal differencing), which replaces each component
+    /// with its difference from the same component of the previous pixel.
+    Tiff,
+
+    /// PNG predictors, where every row is filtered with the specified filter type.
+    ///
+    /// Each row is prefixed with its filter type, so the decoding is the same for all the PNG predictors.
+    Png(PngFilter),
+
+    /// PNG predictors, where the filter type of each row is chosen by the heuristic recommended by the PNG specification
+    /// (the one minimizing the sum of the absolute values of the filtered bytes as signed integers).
+    PngAdaptive,
+}
+impl Predictor {
+    fn is_png(self) -> bool {
+        !matches!(self, Predictor::Tiff)
+    }
+}
+
+/// An encoder which applies a predictor to the data written to it, and writes the results to the inner stream.
+#[derive(Debug)]
+pub struct Encoder<W> {
+    inner: W,
+    predictor: Predictor,
+    params: Params,
+    row: Vec<u8>,
+    prev: Vec<u8>,
+    filtered: Vec<u8>,
+}
+impl<W: Write> Encoder<W> {
+    /// Makes a new encoder instance.
+    ///
+    /// # Errors
+    ///
+    /// If `params` is invalid (e.g., `bits_per_component` is `3`), this returns an error.
+    pub fn new(inner: W, predictor: Predictor, params: Params) -> io::Result<Self> {
+        params.validate()?;
+        let row_size = params.row_size();
+        let mut this = Encoder {
+            inner,
+            predictor,
+            params,
+            row: Vec::new(),
+            prev: Vec::new(),
+            filtered: Vec::new(),
+        };
+        util::try_reserve(&mut this.row, row_size)?;
+        util::try_reserve(&mut this.prev, row_size)?;
+        util::try_reserve(&mut this.filtered, row_size + 1)?;
+        Ok(this)
+    }
+
+    /// Processes the last (short) row if any, and returns the inner stream.
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let result = if self.row.is_empty() {
+            Ok(())
+        } else {
+            self.write_row()
+        };
+        Finish::new(self.inner, result.err())
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    fn write_row(&mut self) -> io::Result<()> {
+        let bpp = self.params.bytes_per_pixel();
+        self.prev.resize(self.row.len(), 0);
+        self.filtered.clear();
+        match self.predictor {
+            Predictor::Tiff => {
+                self.filtered.extend_from_slice(&self.row);
+                tiff_encode(&self.params, &mut self.filtered);
+            }
+            Predictor::Png(filter) => {
+                png_filter(filter, bpp, &self.prev, &self.row, &mut self.filtered);
+            }
+            Predictor::PngAdaptive => {
+                let mut best = (usize::MAX, PngFilter::None);
+                for &filter in &PngFilter::ALL {
+                    self.filtered.clear();
+                    png_filter(filter, bpp, &self.prev, &self.row, &mut self.filtered);
+                    let score = self.filtered[1..]
+                        .iter()
+                        .map(|&b| usize::from((b as i8).unsigned_abs()))
+                        .sum::<usize>();
+                    if score < best.0 {
+                        best = (score, filter);
+                    }
+                }
+                self.filtered.clear();
+                png_filter(best.1, bpp, &self.prev, &self.row, &mut self.filtered);
+            }
+        }
+        self.inner.write_all(&self.filtered)?;
+        mem::swap(&mut self.prev, &mut self.row);
+        self.row.clear();
+        Ok(())
+    }
+}
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = cmp::min(buf.len(), self.params.row_size() - self.row.len());
+        self.row.extend_from_slice(&buf[..size]);
+        if self.row.len() == self.params.row_size() {
+            if let Err(e) = self.write_row() {
+                // `buf[..size]` is not consumed, so it will be written again by the caller
+                self.row.truncate(self.row.len() - size);
+                return Err(e);
+            }
+        }
+        Ok(size)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A decoder which reverts a predictor applied to the data read from the inner stream.
+#[derive(Debug)]
+pub struct Decoder<R> {
+    inner: R,
+    predictor: Predictor,
+    params: Params,
+
+    // The current row (prefixed with the filter type for the PNG predictors), and the decoded previous one
+    row: Vec<u8>,
+    prev: Vec<u8>,
+    offset: usize,
+}
+impl<R: Read> Decoder<R> {
+    /// Makes a new decoder instance.
+    ///
+    /// For the PNG predictors, the filter type of each row is read from the stream,
+    /// so any `Predictor::Png*` value can be specified.
+    ///
+    /// # Errors
+    ///
+    /// If `params` is invalid (e.g., `bits_per_component` is `3`), this returns an error.
+    pub fn new(inner: R, predictor: Predictor, params: Params) -> io::Result<Self> {
+        params.validate()?;
+        let row_size = params.row_size() + usize::from(predictor.is_png());
+        let mut this = Decoder {
+            inner,
+            predictor,
+            params,
+            row: Vec::new(),
+            prev: Vec::new(),
+            offset: 0,
+        };
+        util::try_reserve(&mut this.row, row_size)?;
+        util::try_reserve(&mut this.prev, row_size)?;
+        Ok(this)
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Decoder`, returning the inner stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads and decodes the next row, returning `false` at the end of the stream.
+    fn read_row(&mut self) -> io::Result<bool> {
+        let start = usize::from(self.predictor.is_png());
+        let row_size = self.params.row_size() + start;
+        if start == 1 && self.row.len() > 1 {
+            // Keeps the decoded row without the filter type
+            self.prev.clear();
+            self.prev.extend_from_slice(&self.row[1..]);
+        } else {
+            mem::swap(&mut self.prev, &mut self.row);
+        }
+        self.row.resize(row_size, 0);
+        let (inner, row) = (&mut self.inner, &mut self.row);
+        let mut size = 0;
+        while size < row_size {
+            match util::retry_interrupted(|| inner.read(&mut row[size..]))? {
+                0 => break,
+                n => size += n,
+            }
+        }
+        self.row.truncate(size);
+        self.offset = start;
+        if size == 0 {
+            return Ok(false);
+        }
+        if size == start {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Missing PNG row data").into());
+        }
+
+        match self.predictor {
+            Predictor::Tiff => tiff_decode(&self.params, &mut self.row),
+            Predictor::Png(_) | Predictor::PngAdaptive => {
+                let filter = PngFilter::from_u8(self.row[0]).ok_or_else(|| {
+                    io::Error::from(
+                        Error::new(ErrorKind::InvalidHeader, "Unknown PNG filter type")
+                            .with_value(self.row[0]),
+                    )
+                })?;
+                self.prev.resize(size - 1, 0);
+                png_unfilter(
+                    filter,
+                    self.params.bytes_per_pixel(),
+                    &self.prev,
+                    &mut self.row[1..],
+                );
+            }
+        }
+        Ok(true)
+    }
+}
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.offset == self.row.len() && !self.read_row()? {
+            return Ok(0);
+        }
+        let size = cmp::min(buf.len(), self.row.len() - self.offset);
+        buf[..size].copy_from_slice(&self.row[self.offset..][..size]);
+        self.offset += size;
+        Ok(size)
+    }
+}
+
+/// Appends the filter type and the filtered bytes of `row` to `out`.
+fn png_filter(filter: PngFilter, bpp: usize, prev: &[u8], row: &[u8], out: &mut Vec<u8>) {
+    out.push(filter.to_u8());
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        out.push(row[i].wrapping_sub(filter.predict(a, prev[i], c)));
+    }
+}
+
+fn png_unfilter(filter: PngFilter, bpp: usize, prev: &[u8], row: &mut [u8]) {
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        row[i] = row[i].wrapping_add(filter.predict(a, prev[i], c));
+    }
+}
+
+fn tiff_encode(params: &Params, row: &mut [u8]) {
+    let colors = usize::from(params.colors);
+    let count = row.len() * 8 / usize::from(params.bits_per_component);
+    for i in (colors..count).rev() {
+        let x = get_sample(params, row, i).wrapping_sub(get_sample(params, row, i - colors));
+        set_sample(params, row, i, x);
+    }
+}
+
+fn tiff_decode(params: &Params, row: &mut [u8]) {
+    let colors = usize::from(params.colors);
+    let count = row.len() * 8 / usize::from(params.bits_per_component);
+    for i in colors..count {
+        let x = get_sample(params, row, i).wrapping_add(get_sample(params, row, i - colors));
+        set_sample(params, row, i, x);
+    }
+}
+
+/// Returns the `i`-th sample of `row` (whose samples are packed from the most significant bit).
+fn get_sample(params: &Params, row: &[u8], i: usize) -> u16 {
+    match params.bits_per_component {
+        16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]),
+        bits => {
+            let bit = i * usize::from(bits);
+            let shift = 8 - usize::from(bits) - bit % 8;
+            u16::from(row[bit / 8] >> shift) & ((1 << bits) - 1)
+        }
+    }
+}
+
+/// Sets the `i`-th sample of `row` to (the lower bits of) `x`.
+fn set_sample(params: &Params, row: &mut [u8], i: usize, x: u16) {
+    match params.bits_per_component {
+        16 => row[i * 2..][..2].copy_from_slice(&x.to_be_bytes()),
+        bits => {
+            let bit = i * usize::from(bits);
+            let shift = 8 - usize::from(bits) - bit % 8;
+            let mask = (((1u16 << bits) - 1) as u8) << shift;
+            row[bit / 8] = (row[bit / 8] & !mask) | (((x as u8) << shift) & mask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "no_std")]
+    use alloc::vec;
+
+    fn encode(predictor: Predictor, params: &Params, data: &[u8]) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new(), predictor, params.clone()).unwrap();
+        // Writes in small pieces to cross the row boundaries
+        for chunk in data.chunks(7) {
+            encoder.write_all(chunk).unwrap();
+        }
+        encoder.finish().into_result().unwrap()
+    }
+
+    fn decode(predictor: Predictor, params: &Params, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoder = Decoder::new(data, predictor, params.clone()).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    #[test]
+    fn png_predictors_work() {
+        // 3x2 RGB image
+        let params = Params::new(3, 3, 8);
+        let data = [
+            10, 20, 30, 11, 22, 33, 12, 24, 36, //
+            20, 20, 20, 25, 25, 25, 30, 30, 30,
+        ];
+        let encoded = encode(Predictor::Png(PngFilter::Sub), &params, &data);
+        assert_eq!(
+            encoded,
+            [
+                1, 10, 20, 30, 1, 2, 3, 1, 2, 3, //
+                1, 20, 20, 20, 5, 5, 5, 5, 5, 5
+            ]
+        );
+        let encoded = encode(Predictor::Png(PngFilter::Up), &params, &data);
+        assert_eq!(&encoded[10..], [2, 10, 0, 246, 14, 3, 248, 18, 6, 250]);
+
+        for &filter in &PngFilter::ALL {
+            let encoded = encode(Predictor::Png(filter), &params, &data);
+            assert_eq!(encoded.len(), data.len() + 2);
+            assert_eq!(encoded[0], filter.to_u8());
+            assert_eq!(
+                decode(Predictor::Png(PngFilter::None), &params, &encoded).unwrap(),
+                data
+            );
+        }
+
+        let image = (0..100 * 20 * 2)
+            .map(|i| ((i % 200) * (i / 200) / 7) as u8)
+            .collect::<Vec<_>>();
+        let params = Params::new(100, 2, 8);
+        for &len in &[image.len(), image.len() - 55] {
+            let encoded = encode(Predictor::PngAdaptive, &params, &image[..len]);
+            let decoded = decode(Predictor::PngAdaptive, &params, &encoded).unwrap();
+            assert_eq!(decoded, &image[..len]);
+        }
+
+        let mut encoded = encode(Predictor::PngAdaptive, &params, &image);
+        encoded.push(0);
+        assert!(decode(Predictor::PngAdaptive, &params, &encoded).is_err());
+        encoded[0] = 5;
+        assert!(decode(Predictor::PngAdaptive, &params, &encoded).is_err());
+    }
+
+    #[test]
+    fn tiff_predictor_works() {
+        let params = Params::new(3, 2, 8);
+        let data = [10, 20, 11, 22, 9, 24, 1, 2, 3, 4];
+        let encoded = encode(Predictor::Tiff, &params, &data);
+        assert_eq!(encoded, [10, 20, 1, 2, 254, 2, 1, 2, 2, 2]);
+        assert_eq!(decode(Predictor::Tiff, &params, &encoded).unwrap(), data);
+
+        let data = (0..200).map(|i| (i * 37 % 251) as u8).collect::<Vec<_>>();
+        for &bits in &[1, 2, 4, 8, 16] {
+            let params = Params::new(5, 3, bits);
+            let encoded = encode(Predictor::Tiff, &params, &data);
+            assert_eq!(encoded.len(), data.len());
+            assert_eq!(decode(Predictor::Tiff, &params, &encoded).unwrap(), data);
+        }
+
+        let params = Params::new(2, 1, 16);
+        let encoded = encode(Predictor::Tiff, &params, &[1, 0, 1, 5]);
+        assert_eq!(encoded, [1, 0, 0, 5]);
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let mut data = vec![];
+        for params in [
+            Params::new(0, 1, 8),
+            Params::new(1, 0, 8),
+            Params::new(1, 1, 3),
+            Params::new(usize::MAX, 3, 16),
+        ] {
+            assert!(Encoder::new(&mut data, Predictor::Tiff, params.clone()).is_err());
+            assert!(Decoder::new(&[][..], Predictor::Tiff, params).is_err());
+        }
+        let e =
+            Encoder::new(&mut data, Predictor::Tiff, Params::new(usize::MAX, 3, 16)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn failed_row_is_not_consumed() {
+        struct FailOnce(Vec<u8>, bool);
+        impl Write for FailOnce {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if !self.1 {
+                    self.1 = true;
+                    return Err(io::Error::new(io::ErrorKind::BrokenPipe, "failed"));
+                }
+                self.0.write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let params = Params::new(2, 1, 8);
+        let inner = FailOnce(Vec::new(), false);
+        let mut encoder = Encoder::new(inner, Predictor::Tiff, params.clone()).unwrap();
+        assert!(encoder.write(&[1, 3]).is_err());
+        encoder.write_all(&[1, 3, 2, 2]).unwrap();
+        let encoded = encoder.finish().into_result().unwrap().0;
+        assert_eq!(encoded, encode(Predictor::Tiff, &params, &[1, 3, 2, 2]));
+    }
+}
@@ -0,0 +1,1003 @@
+//! Push-style encoders and decoders.
+//!
+//! The types in this module do not own any I/O object.
+//! Instead, input bytes are pushed into them as slices and the results are written to caller-provided output slices.
+//! Because of this, they behave the same way regardless of whether the `no_std` feature is enabled.
+//!
+//! # Examples
+//! ```
+//! use libflate::push::{Decoder, Encoder, Flush, Format, Status};
+//!
+//! // Encoding
+//! let mut encoder = Encoder::new(Format::Zlib);
+//! let mut encoded_data = vec![0; 64];
+//! let progress = encoder.encode(b"Hello World!", &mut encoded_data, Flush::Finish).unwrap();
+//! assert_eq!(progress.consumed, 12);
+//! assert_eq!(progress.status, Status::StreamEnd);
+//! encoded_data.truncate(progress.produced);
+//!
+//! // Decoding
+//! let mut decoder = Decoder::new(Format::Zlib);
+//! let mut decoded_data = vec![0; 64];
+//! let progress = decoder.decode(&encoded_data, &mut decoded_data).unwrap();
+//! assert_eq!(progress.status, Status::StreamEnd);
+//! assert_eq!(&decoded_data[..progress.produced], b"Hello World!");
+//! ```
+use crate::deflate;
+use crate::gzip;
+use crate::lz77;
+use crate::non_blocking;
+use crate::util;
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+#[cfg(feature = "bytes")]
+use bytes::{Buf, BytesMut};
+#[cfg(feature = "no_std")]
+use core::{cmp, mem};
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp,
+    io::{self, Read, Write},
+    mem,
+};
+
+/// Compressed data formats supported by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Raw DEFLATE stream ([RFC-1951](https://tools.ietf.org/html/rfc1951)).
+    Deflate,
+
+    /// ZLIB stream ([RFC-1950](https://tools.ietf.org/html/rfc1950)).
+    Zlib,
+
+    /// GZIP stream ([RFC-1952](https://tools.ietf.org/html/rfc1952)).
+    Gzip,
+}
+
+/// Flush behavior of [`Encoder::encode`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flush {
+    /// Encoded data may be buffered internally (default).
+    #[default]
+    None,
+
+    /// All pending data is flushed and aligned to a byte boundary (`Z_SYNC_FLUSH`).
+    Sync,
+
+    /// The stream is terminated (the trailer of the format is written).
+    Finish,
+}
+
+/// The state of a push-style stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// The stream has not reached its end yet.
+    Ok,
+
+    /// The end of the stream has been reached and all output has been produced.
+    StreamEnd,
+}
+
+/// The result of an [`Encoder::encode`] or a [`Decoder::decode`] invocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// Number of bytes consumed from the input slice.
+    pub consumed: usize,
+
+    /// Number of bytes written to the output slice.
+    pub produced: usize,
+
+    /// The state of the stream after the invocation.
+    pub status: Status,
+}
+
+#[derive(Debug)]
+enum EncoderInner<E> {
+    Deflate(deflate::Encoder<Vec<u8>, E>),
+    Zlib(zlib::Encoder<Vec<u8>, E>),
+    Gzip(gzip::Encoder<Vec<u8>, E>),
+}
+impl<E> EncoderInner<E>
+where
+    E: lz77::Lz77Encode,
+{
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match *self {
+            EncoderInner::Deflate(ref mut x) => x.write_all(buf),
+            EncoderInner::Zlib(ref mut x) => x.write_all(buf),
+            EncoderInner::Gzip(ref mut x) => x.write_all(buf),
+        }
+    }
+    fn sync_flush(&mut self) -> io::Result<()> {
+        match *self {
+            EncoderInner::Deflate(ref mut x) => x.zlib_sync_flush(),
+            EncoderInner::Zlib(ref mut x) => x.sync_flush(),
+            EncoderInner::Gzip(ref mut x) => x.sync_flush(),
+        }
+    }
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            EncoderInner::Deflate(x) => x.finish().into_result(),
+            EncoderInner::Zlib(x) => x.finish().into_result(),
+            EncoderInner::Gzip(x) => x.finish().into_result(),
+        }
+    }
+    fn memory_usage(&self) -> usize {
+        match *self {
+            EncoderInner::Deflate(ref x) => x.memory_usage() + x.as_inner_ref().capacity(),
+            EncoderInner::Zlib(ref x) => x.memory_usage() + x.as_inner_ref().capacity(),
+            EncoderInner::Gzip(ref x) => x.memory_usage() + x.as_inner_ref().capacity(),
+        }
+    }
+    fn take_output(&mut self) -> Vec<u8> {
+        mem::take(match *self {
+            EncoderInner::Deflate(ref mut x) => x.as_inner_mut(),
+            EncoderInner::Zlib(ref mut x) => x.as_inner_mut(),
+            EncoderInner::Gzip(ref mut x) => x.as_inner_mut(),
+        })
+    }
+}
+
+/// Push-style encoder.
+#[derive(Debug)]
+pub struct Encoder<E = lz77::DefaultLz77Encoder> {
+    inner: Option<EncoderInner<E>>,
+    pending: Vec<u8>,
+    offset: usize,
+    synced: bool,
+    buffer_size: usize,
+    total_in: u64,
+    total_out: u64,
+}
+impl Encoder<lz77::DefaultLz77Encoder> {
+    /// Makes a new encoder instance for `format` with the default options.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::push::{Encoder, Flush, Format};
+    ///
+    /// let mut encoder = Encoder::new(Format::Deflate);
+    /// let mut buf = [0; 64];
+    /// let progress = encoder.encode(b"Hello World!", &mut buf, Flush::Finish).unwrap();
+    /// assert_eq!(&buf[..progress.produced],
+    ///            [5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237,
+    ///             147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
+    /// ```
+    pub fn new(format: Format) -> Self {
+        match format {
+            Format::Deflate => Self::deflate(deflate::EncodeOptions::new()),
+            Format::Zlib => {
+                Self::zlib(zlib::EncodeOptions::new()).expect("Writing to `Vec` never fails")
+            }
+            Format::Gzip => {
+                Self::gzip(gzip::EncodeOptions::new()).expect("Writing to `Vec` never fails")
+            }
+        }
+    }
+}
+impl<E> Encoder<E>
+where
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new encoder instance that produces a raw DEFLATE stream.
+    pub fn deflate(options: deflate::EncodeOptions<E>) -> Self {
+        let buffer_size = options.get_buffer_size();
+        let encoder = deflate::Encoder::with_options(Vec::new(), options);
+        Self::from_inner(EncoderInner::Deflate(encoder), buffer_size)
+    }
+
+    /// Makes a new encoder instance that produces a ZLIB stream.
+    pub fn zlib(options: zlib::EncodeOptions<E>) -> io::Result<Self> {
+        let buffer_size = options.get_buffer_size();
+        zlib::Encoder::with_options(Vec::new(), options)
+            .map(|x| Self::from_inner(EncoderInner::Zlib(x), buffer_size))
+    }
+
+    /// Makes a new encoder instance that produces a GZIP stream.
+    pub fn gzip(options: gzip::EncodeOptions<E>) -> io::Result<Self> {
+        let buffer_size = options.get_buffer_size();
+        gzip::Encoder::with_options(Vec::new(), options)
+            .map(|x| Self::from_inner(EncoderInner::Gzip(x), buffer_size))
+    }
+
+    /// Encodes `input` and writes the resulting bytes to `output`.
+    ///
+    /// Input is only consumed after all previously encoded bytes have been written out,
+    /// so the amount of internally buffered output is bounded.
+    /// Callers should keep calling this method (with the unconsumed part of the input)
+    /// until all input is consumed, and, in the case of `Flush::Finish`,
+    /// until [`Status::StreamEnd`] is returned.
+    ///
+    /// Repeating `Flush::Sync` without new input (e.g., to drain the output) does not emit another flush marker.
+    pub fn encode(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        flush: Flush,
+    ) -> io::Result<Progress> {
+        let mut produced = self.drain(output);
+        let mut consumed = 0;
+        if self.pending.is_empty() {
+            if let Some(mut inner) = self.inner.take() {
+                inner.write_all(input)?;
+                consumed = input.len();
+                if !input.is_empty() {
+                    self.synced = false;
+                }
+                match flush {
+                    Flush::None => {
+                        self.pending = inner.take_output();
+                        self.inner = Some(inner);
+                    }
+                    Flush::Sync if self.synced && input.is_empty() => {
+                        self.pending = inner.take_output();
+                        self.inner = Some(inner);
+                    }
+                    Flush::Sync => {
+                        inner.sync_flush()?;
+                        self.synced = true;
+                        self.pending = inner.take_output();
+                        self.inner = Some(inner);
+                    }
+                    Flush::Finish => {
+                        self.pending = inner.finish()?;
+                    }
+                }
+                self.offset = 0;
+                produced += self.drain(&mut output[produced..]);
+            }
+        }
+        let status = if self.inner.is_none() && self.pending.is_empty() {
+            Status::StreamEnd
+        } else {
+            Status::Ok
+        };
+        self.total_in += consumed as u64;
+        self.total_out += produced as u64;
+        Ok(Progress {
+            consumed,
+            produced,
+            status,
+        })
+    }
+
+    /// Encodes the whole `input` and appends the resulting bytes to `output`.
+    ///
+    /// Unlike [`Encoder::encode`], `output` is extended as needed,
+    /// so all of the input is consumed and no output remains in this encoder when this method returns.
+    /// The returned [`Progress::produced`] is the number of the bytes appended to `output`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::push::{Encoder, Flush, Format, Status};
+    ///
+    /// let mut encoder = Encoder::new(Format::Zlib);
+    /// let mut encoded_data = Vec::new();
+    /// encoder.encode_to_vec(b"Hello ", &mut encoded_data, Flush::None).unwrap();
+    /// let progress = encoder.encode_to_vec(b"World!", &mut encoded_data, Flush::Finish).unwrap();
+    /// assert_eq!(progress.status, Status::StreamEnd);
+    /// assert_eq!(encoded_data,
+    ///            [120, 156, 5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237, 147,
+    ///             38, 245, 63, 244, 230, 65, 181, 50, 215, 1, 28, 73, 4, 62]);
+    /// ```
+    pub fn encode_to_vec(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        flush: Flush,
+    ) -> io::Result<Progress> {
+        self.encode_to_buffer(input, output, flush)
+    }
+
+    /// Encodes the whole `input` and appends the resulting bytes to `output`.
+    ///
+    /// This is the same as [`Encoder::encode_to_vec`] except that the input is taken from
+    /// the chunks of a [`Buf`] (which is advanced accordingly) without flattening them,
+    /// and that the output is appended to a [`BytesMut`].
+    /// `flush` is applied after the last chunk has been encoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use bytes::{Buf, BytesMut};
+    /// use libflate::push::{Decoder, Encoder, Flush, Format, Status};
+    ///
+    /// let mut input = (&b"Hello "[..]).chain(&b"World!"[..]);
+    /// let mut encoder = Encoder::new(Format::Zlib);
+    /// let mut encoded_data = BytesMut::new();
+    /// let progress = encoder.encode_into(&mut input, &mut encoded_data, Flush::Finish).unwrap();
+    /// assert_eq!(progress.consumed, 12);
+    /// assert_eq!(progress.status, Status::StreamEnd);
+    /// assert!(!input.has_remaining());
+    ///
+    /// let mut decoder = Decoder::new(Format::Zlib);
+    /// let mut decoded_data = BytesMut::new();
+    /// decoder.decode_into(&mut encoded_data.freeze(), &mut decoded_data).unwrap();
+    /// assert_eq!(&decoded_data[..], b"Hello World!");
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn encode_into<B: Buf>(
+        &mut self,
+        input: &mut B,
+        output: &mut BytesMut,
+        flush: Flush,
+    ) -> io::Result<Progress> {
+        let start = output.len();
+        let mut consumed = 0;
+        loop {
+            let chunk = input.chunk();
+            let last = chunk.len() == input.remaining();
+            let progress =
+                self.encode_to_buffer(chunk, output, if last { flush } else { Flush::None })?;
+            input.advance(progress.consumed);
+            consumed += progress.consumed;
+            if last {
+                return Ok(Progress {
+                    consumed,
+                    produced: output.len() - start,
+                    status: progress.status,
+                });
+            }
+        }
+    }
+
+    fn encode_to_buffer<O: OutputBuffer>(
+        &mut self,
+        input: &[u8],
+        output: &mut O,
+        flush: Flush,
+    ) -> io::Result<Progress> {
+        let start = output.len();
+        let mut consumed = 0;
+        loop {
+            let len = output.len();
+            let progress = with_spare_capacity(output, |spare| {
+                self.encode(&input[consumed..], spare, flush)
+            })?;
+            consumed += progress.consumed;
+            if consumed == input.len() && progress.produced < output.capacity() - len {
+                return Ok(Progress {
+                    consumed,
+                    produced: output.len() - start,
+                    status: progress.status,
+                });
+            }
+        }
+    }
+
+    /// Returns `true` if the stream has been finished, `false` otherwise.
+    ///
+    /// Note that some output may remain to be retrieved even if this method returns `true`.
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    /// Returns the number of the (uncompressed) bytes consumed by this encoder.
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    /// Returns the number of the (compressed) bytes written to the output buffers.
+    ///
+    /// Unlike [`deflate::Encoder::total_out`], the output which has not been retrieved yet is not counted.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::push::{Encoder, Flush, Format};
+    ///
+    /// let mut encoder = Encoder::new(Format::Zlib);
+    /// let mut encoded_data = Vec::new();
+    /// encoder.encode_to_vec(b"Hello World!", &mut encoded_data, Flush::Finish).unwrap();
+    /// assert_eq!(encoder.total_in(), 12);
+    /// assert_eq!(encoder.total_out(), encoded_data.len() as u64);
+    /// ```
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this encoder.
+    ///
+    /// This includes the output which has not been retrieved yet.
+    /// See [`deflate::Encoder::memory_usage`] for the details of the rest.
+    pub fn memory_usage(&self) -> usize {
+        self.inner.as_ref().map_or(0, |x| x.memory_usage()) + self.pending.capacity()
+    }
+
+    /// Returns the size of the buffers of the I/O adapters (see [`deflate::EncodeOptions::buffer_size`]).
+    pub(crate) fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    fn from_inner(inner: EncoderInner<E>, buffer_size: usize) -> Self {
+        Encoder {
+            inner: Some(inner),
+            pending: Vec::new(),
+            offset: 0,
+            synced: false,
+            buffer_size,
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+    fn drain(&mut self, output: &mut [u8]) -> usize {
+        let size = cmp::min(output.len(), self.pending.len() - self.offset);
+        output[..size].copy_from_slice(&self.pending[self.offset..][..size]);
+        self.offset += size;
+        if self.offset == self.pending.len() {
+            self.pending.clear();
+            self.offset = 0;
+        }
+        size
+    }
+}
+
+/// The maximum number of the input bytes pushed into [`PushedInput`] at once by [`Decoder::decode`].
+const MAX_PUSH_SIZE: usize = 8 * 1024;
+
+/// A reader that returns `ErrorKind::WouldBlock` once the pushed bytes are exhausted.
+#[derive(Debug, Default)]
+struct PushedInput {
+    buf: Vec<u8>,
+    offset: usize,
+}
+impl PushedInput {
+    fn push(&mut self, input: &[u8]) -> io::Result<()> {
+        self.buf.clear();
+        self.offset = 0;
+        util::try_reserve(&mut self.buf, input.len())?;
+        self.buf.extend_from_slice(input);
+        Ok(())
+    }
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.offset
+    }
+}
+impl Read for PushedInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining() == 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "Would block"));
+        }
+        let size = cmp::min(buf.len(), self.remaining());
+        buf[..size].copy_from_slice(&self.buf[self.offset..][..size]);
+        self.offset += size;
+        Ok(size)
+    }
+}
+
+#[derive(Debug)]
+enum DecoderInner {
+    Deflate(non_blocking::deflate::Decoder<PushedInput>),
+    Zlib(non_blocking::zlib::Decoder<PushedInput>),
+    Gzip(non_blocking::gzip::Decoder<PushedInput>),
+}
+impl DecoderInner {
+    fn memory_usage(&self) -> usize {
+        match *self {
+            DecoderInner::Deflate(ref x) => x.memory_usage() + x.as_inner_ref().buf.capacity(),
+            DecoderInner::Zlib(ref x) => x.memory_usage() + x.as_inner_ref().buf.capacity(),
+            DecoderInner::Gzip(ref x) => x.memory_usage() + x.as_inner_ref().buf.capacity(),
+        }
+    }
+    fn total_in(&self) -> u64 {
+        match *self {
+            DecoderInner::Deflate(ref x) => x.total_in(),
+            DecoderInner::Zlib(ref x) => x.total_in(),
+            DecoderInner::Gzip(ref x) => x.total_in(),
+        }
+    }
+    fn total_out(&self) -> u64 {
+        match *self {
+            DecoderInner::Deflate(ref x) => x.total_out(),
+            DecoderInner::Zlib(ref x) => x.total_out(),
+            DecoderInner::Gzip(ref x) => x.total_out(),
+        }
+    }
+    fn input_mut(&mut self) -> &mut PushedInput {
+        match *self {
+            DecoderInner::Deflate(ref mut x) => x.as_inner_mut(),
+            DecoderInner::Zlib(ref mut x) => x.as_inner_mut(),
+            DecoderInner::Gzip(ref mut x) => x.as_inner_mut(),
+        }
+    }
+}
+impl Read for DecoderInner {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            DecoderInner::Deflate(ref mut x) => x.read(buf),
+            DecoderInner::Zlib(ref mut x) => x.read(buf),
+            DecoderInner::Gzip(ref mut x) => x.read(buf),
+        }
+    }
+}
+
+/// Push-style decoder.
+#[derive(Debug)]
+pub struct Decoder {
+    inner: DecoderInner,
+    eos: bool,
+}
+impl Decoder {
+    /// Makes a new decoder instance for `format`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::push::{Decoder, Format, Status};
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(Format::Deflate);
+    /// let mut buf = [0; 64];
+    /// let progress = decoder.decode(&encoded_data, &mut buf).unwrap();
+    /// assert_eq!(progress.status, Status::StreamEnd);
+    /// assert_eq!(&buf[..progress.produced], b"Hello World!");
+    /// ```
+    pub fn new(format: Format) -> Self {
+        let input = PushedInput::default();
+        let inner = match format {
+            Format::Deflate => DecoderInner::Deflate(non_blocking::deflate::Decoder::new(input)),
+            Format::Zlib => DecoderInner::Zlib(non_blocking::zlib::Decoder::new(input)),
+            Format::Gzip => DecoderInner::Gzip(non_blocking::gzip::Decoder::new(input)),
+        };
+        Decoder { inner, eos: false }
+    }
+
+    /// Decodes `input` and writes the resulting bytes to `output`.
+    ///
+    /// Decoding stops when `output` is full, when `input` is exhausted or when the end of the stream is reached.
+    /// In the last case, bytes following the stream are not consumed.
+    pub fn decode(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<Progress> {
+        // The input is pushed in chunks, so that the bytes which are not going to be consumed are not copied
+        let mut consumed = 0;
+        let mut produced = 0;
+        loop {
+            let chunk = &input[consumed..][..cmp::min(MAX_PUSH_SIZE, input.len() - consumed)];
+            self.inner.input_mut().push(chunk)?;
+            let mut would_block = false;
+            while !self.eos && produced < output.len() {
+                match self.inner.read(&mut output[produced..]) {
+                    Ok(0) => self.eos = true,
+                    Ok(size) => produced += size,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        would_block = true;
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            consumed += chunk.len() - self.inner.input_mut().remaining();
+            self.inner.input_mut().push(&[])?;
+            if !would_block || consumed == input.len() {
+                break;
+            }
+        }
+        let status = if self.eos {
+            Status::StreamEnd
+        } else {
+            Status::Ok
+        };
+        Ok(Progress {
+            consumed,
+            produced,
+            status,
+        })
+    }
+
+    /// Decodes `input` and appends the resulting bytes to `output`.
+    ///
+    /// Unlike [`Decoder::decode`], `output` is extended as needed,
+    /// so decoding stops only when `input` is exhausted or when the end of the stream is reached.
+    /// The returned [`Progress::produced`] is the number of the bytes appended to `output`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::push::{Decoder, Format, Status};
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(Format::Deflate);
+    /// let mut decoded_data = Vec::new();
+    /// decoder.decode_to_vec(&encoded_data[..5], &mut decoded_data).unwrap();
+    /// let progress = decoder.decode_to_vec(&encoded_data[5..], &mut decoded_data).unwrap();
+    /// assert_eq!(progress.status, Status::StreamEnd);
+    /// assert_eq!(decoded_data, b"Hello World!");
+    /// ```
+    pub fn decode_to_vec(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<Progress> {
+        self.decode_to_buffer(input, output)
+    }
+
+    /// Decodes the chunks of `input` and appends the resulting bytes to `output`.
+    ///
+    /// This is the same as [`Decoder::decode_to_vec`] except that the input is taken from
+    /// the chunks of a [`Buf`] without flattening them, and that the output is appended to a [`BytesMut`].
+    /// `input` is advanced by the number of the consumed bytes,
+    /// so the bytes following the stream remain in it when the end of the stream is reached.
+    ///
+    /// # Examples
+    /// ```
+    /// use bytes::{Buf, BytesMut};
+    /// use libflate::push::{Decoder, Format, Status};
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut input = (&encoded_data[..5]).chain(&encoded_data[5..]).chain(&b"trailing"[..]);
+    /// let mut decoder = Decoder::new(Format::Deflate);
+    /// let mut decoded_data = BytesMut::new();
+    /// let progress = decoder.decode_into(&mut input, &mut decoded_data).unwrap();
+    /// assert_eq!(progress.status, Status::StreamEnd);
+    /// assert_eq!(&decoded_data[..], b"Hello World!");
+    /// assert_eq!(input.chunk(), b"trailing");
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn decode_into<B: Buf>(
+        &mut self,
+        input: &mut B,
+        output: &mut BytesMut,
+    ) -> io::Result<Progress> {
+        let start = output.len();
+        let mut consumed = 0;
+        loop {
+            let progress = self.decode_to_buffer(input.chunk(), output)?;
+            input.advance(progress.consumed);
+            consumed += progress.consumed;
+            if progress.status == Status::StreamEnd || !input.has_remaining() {
+                return Ok(Progress {
+                    consumed,
+                    produced: output.len() - start,
+                    status: progress.status,
+                });
+            }
+        }
+    }
+
+    fn decode_to_buffer<O: OutputBuffer>(
+        &mut self,
+        input: &[u8],
+        output: &mut O,
+    ) -> io::Result<Progress> {
+        let start = output.len();
+        let mut consumed = 0;
+        loop {
+            let len = output.len();
+            let progress =
+                with_spare_capacity(output, |spare| self.decode(&input[consumed..], spare))?;
+            consumed += progress.consumed;
+            if progress.status == Status::StreamEnd || progress.produced < output.capacity() - len {
+                return Ok(Progress {
+                    consumed,
+                    produced: output.len() - start,
+                    status: progress.status,
+                });
+            }
+        }
+    }
+
+    /// Returns `true` if the end of the stream has been reached, `false` otherwise.
+    pub fn is_finished(&self) -> bool {
+        self.eos
+    }
+
+    /// Returns the number of the (compressed) bytes consumed by this decoder.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::push::{Decoder, Format};
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(Format::Deflate);
+    /// let mut decoded_data = Vec::new();
+    /// decoder.decode_to_vec(&encoded_data, &mut decoded_data).unwrap();
+    /// assert_eq!(decoder.total_in(), 14);
+    /// assert_eq!(decoder.total_out(), 12);
+    /// ```
+    pub fn total_in(&self) -> u64 {
+        self.inner.total_in()
+    }
+
+    /// Returns the number of the decoded bytes written to the output buffers.
+    pub fn total_out(&self) -> u64 {
+        self.inner.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder.
+    ///
+    /// See [`non_blocking::deflate::Decoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.inner.memory_usage()
+    }
+}
+
+/// A growable output buffer of the `*_to_vec` (and `*_into`) methods.
+trait OutputBuffer: AsMut<[u8]> {
+    fn len(&self) -> usize;
+    fn capacity(&self) -> usize;
+    fn try_reserve(&mut self, additional: usize) -> io::Result<()>;
+    fn resize(&mut self, len: usize);
+    fn truncate(&mut self, len: usize);
+}
+impl OutputBuffer for Vec<u8> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+    fn try_reserve(&mut self, additional: usize) -> io::Result<()> {
+        util::try_reserve(self, additional)
+    }
+    fn resize(&mut self, len: usize) {
+        self.resize(len, 0);
+    }
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len);
+    }
+}
+#[cfg(feature = "bytes")]
+impl OutputBuffer for BytesMut {
+    fn len(&self) -> usize {
+        self.len()
+    }
+    fn capacity(&self) -> usize {
+        self.capacity()
+    }
+    fn try_reserve(&mut self, additional: usize) -> io::Result<()> {
+        // `BytesMut` has no fallible reservation
+        self.reserve(additional);
+        Ok(())
+    }
+    fn resize(&mut self, len: usize) {
+        self.resize(len, 0);
+    }
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len);
+    }
+}
+
+/// Calls `f` with the spare capacity of `output` (reserving some if there is none),
+/// and then extends `output` by the number of the bytes produced by `f`.
+fn with_spare_capacity<O, F>(output: &mut O, f: F) -> io::Result<Progress>
+where
+    O: OutputBuffer,
+    F: FnOnce(&mut [u8]) -> io::Result<Progress>,
+{
+    const MIN_SPARE_CAPACITY: usize = 4096;
+    if output.capacity() - output.len() < MIN_SPARE_CAPACITY {
+        let additional = cmp::max(MIN_SPARE_CAPACITY, output.len());
+        output.try_reserve(additional)?;
+    }
+    let len = output.len();
+    let capacity = output.capacity();
+    output.resize(capacity);
+    let result = f(&mut output.as_mut()[len..]);
+    output.truncate(len + result.as_ref().map_or(0, |p| p.produced));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_chunked(format: Format, input: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut encoder = Encoder::new(format);
+        let mut encoded = Vec::new();
+        let mut buf = vec![0; chunk_size];
+        let mut input = input;
+        loop {
+            let flush = if input.len() <= chunk_size {
+                Flush::Finish
+            } else {
+                Flush::None
+            };
+            let chunk = &input[..cmp::min(chunk_size, input.len())];
+            let progress = encoder.encode(chunk, &mut buf, flush).unwrap();
+            encoded.extend_from_slice(&buf[..progress.produced]);
+            input = &input[progress.consumed..];
+            if progress.status == Status::StreamEnd {
+                return encoded;
+            }
+        }
+    }
+
+    fn decode_chunked(format: Format, input: &[u8], chunk_size: usize) -> (Vec<u8>, usize) {
+        let mut decoder = Decoder::new(format);
+        let mut decoded = Vec::new();
+        let mut buf = vec![0; chunk_size];
+        let mut offset = 0;
+        loop {
+            let end = cmp::min(offset + chunk_size, input.len());
+            let progress = decoder.decode(&input[offset..end], &mut buf).unwrap();
+            decoded.extend_from_slice(&buf[..progress.produced]);
+            offset += progress.consumed;
+            if progress.status == Status::StreamEnd {
+                return (decoded, offset);
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_round_trip_works() {
+        let plain = (0..20_000)
+            .map(|i| (i % 251) as u8 ^ (i / 300) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            for &chunk_size in &[1, 7, 1024] {
+                let encoded = encode_chunked(format, &plain, chunk_size);
+                let (decoded, consumed) = decode_chunked(format, &encoded, chunk_size);
+                assert_eq!(decoded, plain);
+                assert_eq!(consumed, encoded.len());
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_not_consumed() {
+        let mut encoded = encode_chunked(Format::Zlib, b"Hello World!", 64);
+        let stream_len = encoded.len();
+        encoded.extend_from_slice(b"trailing");
+
+        let mut decoder = Decoder::new(Format::Zlib);
+        let mut buf = [0; 64];
+        let progress = decoder.decode(&encoded, &mut buf).unwrap();
+        assert_eq!(progress.consumed, stream_len);
+        assert_eq!(progress.status, Status::StreamEnd);
+        assert_eq!(&buf[..progress.produced], b"Hello World!");
+    }
+
+    #[test]
+    fn sync_flush_makes_data_decodable() {
+        let mut encoder = Encoder::new(Format::Deflate);
+        let mut decoder = Decoder::new(Format::Deflate);
+        let mut encoded = [0; 64];
+        let mut decoded = [0; 64];
+
+        let progress = encoder.encode(b"foo", &mut encoded, Flush::Sync).unwrap();
+        assert_eq!(progress.status, Status::Ok);
+        assert_eq!(
+            &encoded[progress.produced - 4..progress.produced],
+            [0, 0, 255, 255]
+        );
+
+        let d = decoder
+            .decode(&encoded[..progress.produced], &mut decoded)
+            .unwrap();
+        assert_eq!(d.status, Status::Ok);
+        assert_eq!(&decoded[..d.produced], b"foo");
+    }
+
+    #[test]
+    fn repeated_sync_flush_without_input_is_no_op() {
+        let mut encoder = Encoder::new(Format::Deflate);
+        let mut encoded = [0; 64];
+
+        let progress = encoder.encode(b"foo", &mut encoded, Flush::Sync).unwrap();
+        assert_ne!(progress.produced, 0);
+        let progress = encoder.encode(&[], &mut encoded, Flush::Sync).unwrap();
+        assert_eq!(progress.produced, 0);
+
+        let progress = encoder.encode(b"bar", &mut encoded, Flush::Sync).unwrap();
+        assert_ne!(progress.produced, 0);
+    }
+
+    #[test]
+    fn decoding_memory_is_bounded_by_output() {
+        let plain = vec![0; 10_000_000];
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let encoded = encode_chunked(format, &plain, 1024);
+            let mut decoder = Decoder::new(format);
+            let mut buf = [0; 64];
+            let mut offset = 0;
+            let mut decoded_size = 0;
+            let mut max_memory_usage = 0;
+            loop {
+                let progress = decoder.decode(&encoded[offset..], &mut buf).unwrap();
+                offset += progress.consumed;
+                decoded_size += progress.produced;
+                max_memory_usage = cmp::max(max_memory_usage, decoder.memory_usage());
+                if progress.status == Status::StreamEnd {
+                    break;
+                }
+            }
+            assert_eq!(decoded_size, plain.len());
+            assert!(max_memory_usage < 1024 * 1024, "{}", max_memory_usage);
+        }
+    }
+
+    #[test]
+    fn to_vec_variants_work() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let mut encoder = Encoder::new(format);
+            let mut encoded = Vec::new();
+            for chunk in plain.chunks(30_000) {
+                let progress = encoder
+                    .encode_to_vec(chunk, &mut encoded, Flush::None)
+                    .unwrap();
+                assert_eq!(progress.consumed, chunk.len());
+                assert_eq!(progress.status, Status::Ok);
+            }
+            let progress = encoder
+                .encode_to_vec(&[], &mut encoded, Flush::Finish)
+                .unwrap();
+            assert_eq!(progress.status, Status::StreamEnd);
+            assert_eq!(decode_chunked(format, &encoded, 1024).0, plain);
+
+            encoded.extend_from_slice(b"trailing");
+            let mut decoder = Decoder::new(format);
+            let mut decoded = Vec::new();
+            let mut consumed = 0;
+            for chunk in encoded.chunks(100) {
+                let progress = decoder.decode_to_vec(chunk, &mut decoded).unwrap();
+                consumed += progress.consumed;
+                if progress.status == Status::StreamEnd {
+                    break;
+                }
+            }
+            assert_eq!(decoded, plain);
+            assert_eq!(&encoded[consumed..], b"trailing");
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes_variants_work() {
+        use bytes::{Bytes, BytesMut};
+
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let mut input = Bytes::from(plain[..10].to_vec())
+                .chain(Bytes::from(plain[10..60_000].to_vec()))
+                .chain(&plain[60_000..]);
+            let mut encoder = Encoder::new(format);
+            let mut encoded = BytesMut::new();
+            let progress = encoder
+                .encode_into(&mut input, &mut encoded, Flush::Finish)
+                .unwrap();
+            assert_eq!(progress.consumed, plain.len());
+            assert_eq!(progress.status, Status::StreamEnd);
+            assert!(!input.has_remaining());
+            assert_eq!(decode_chunked(format, &encoded, 1024).0, plain);
+
+            let split = encoded.len() / 3;
+            let mut input = Bytes::copy_from_slice(&encoded[..split])
+                .chain(Bytes::copy_from_slice(&encoded[split..]))
+                .chain(&b"trailing"[..]);
+            let mut decoder = Decoder::new(format);
+            let mut decoded = BytesMut::new();
+            let progress = decoder.decode_into(&mut input, &mut decoded).unwrap();
+            assert_eq!(progress.consumed, encoded.len());
+            assert_eq!(progress.status, Status::StreamEnd);
+            assert_eq!(decoded, plain);
+            assert_eq!(input.copy_to_bytes(input.remaining()), &b"trailing"[..]);
+        }
+    }
+    #[test]
+    fn total_in_and_total_out_work() {
+        let plain = (0..20_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let mut encoder = Encoder::new(format);
+            let mut encoded = Vec::new();
+            for chunk in plain.chunks(1000) {
+                encoder
+                    .encode_to_vec(chunk, &mut encoded, Flush::None)
+                    .unwrap();
+            }
+            encoder
+                .encode_to_vec(&[], &mut encoded, Flush::Finish)
+                .unwrap();
+            assert_eq!(encoder.total_in(), plain.len() as u64);
+            assert_eq!(encoder.total_out(), encoded.len() as u64);
+
+            let mut decoder = Decoder::new(format);
+            let mut decoded = Vec::new();
+            for chunk in encoded.chunks(7) {
+                decoder.decode_to_vec(chunk, &mut decoded).unwrap();
+            }
+            assert!(decoder.is_finished());
+            assert_eq!(decoded, plain);
+            assert_eq!(decoder.total_in(), encoded.len() as u64);
+            assert_eq!(decoder.total_out(), plain.len() as u64);
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! Encoders which wrap a reader and produce compressed bytes on demand (pull model).
+//!
+//! The encoders of the [`deflate`], [`zlib`] and [`gzip`] modules implement `Write`
+//! and push the compressed bytes to an inner writer.
+//! [`Encoder`] works in the opposite direction: it implements `Read`,
+//! and reads (and compresses) the data from an inner reader only when the compressed bytes are requested.
+//! This is convenient for APIs which take a reader, such as `io::copy` or the body of an HTTP request.
+//!
+//! [`Chunks`] splits the data of a reader (typically a decoder) into pieces of a bounded size,
+//! so that they can be processed by a `for` loop.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::Read;
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::Read;
+//! use libflate::gzip::Decoder;
+//! use libflate::push::Format;
+//! use libflate::read::Encoder;
+//!
+//! // Encoding
+//! let mut encoder = Encoder::new(&b"Hello World!"[..], Format::Gzip);
+//! let mut encoded_data = Vec::new();
+//! encoder.read_to_end(&mut encoded_data).unwrap();
+//!
+//! // Decoding
+//! let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+//! let mut decoded_data = Vec::new();
+//! decoder.read_to_end(&mut decoded_data).unwrap();
+//!
+//! assert_eq!(decoded_data, b"Hello World!");
+//! ```
+use crate::deflate;
+use crate::gzip;
+use crate::lz77;
+use crate::push::{self, Flush, Format, Status};
+use crate::util;
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, Read};
+
+/// Encoder which reads uncompressed data from the inner reader and implements `Read` for the compressed data.
+#[derive(Debug)]
+pub struct Encoder<R, E = lz77::DefaultLz77Encoder> {
+    inner: R,
+    encoder: push::Encoder<E>,
+    buf: Vec<u8>,
+    offset: usize,
+    eof: bool,
+}
+impl<R> Encoder<R, lz77::DefaultLz77Encoder>
+where
+    R: Read,
+{
+    /// Makes a new encoder instance for `format` with the default options.
+    ///
+    /// `inner` is the reader from which the data to be compressed is read.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::push::Format;
+    /// use libflate::read::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(&b"Hello World!"[..], Format::Deflate);
+    /// let mut buf = Vec::new();
+    /// encoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf,
+    ///            [5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237,
+    ///             147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
+    /// ```
+    pub fn new(inner: R, format: Format) -> Self {
+        Self::from_push(inner, push::Encoder::new(format))
+    }
+}
+impl<R, E> Encoder<R, E>
+where
+    R: Read,
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new encoder instance that produces a raw DEFLATE stream.
+    pub fn deflate(inner: R, options: deflate::EncodeOptions<E>) -> Self {
+        Self::from_push(inner, push::Encoder::deflate(options))
+    }
+
+    /// Makes a new encoder instance that produces a ZLIB stream.
+    pub fn zlib(inner: R, options: zlib::EncodeOptions<E>) -> io::Result<Self> {
+        push::Encoder::zlib(options).map(|encoder| Self::from_push(inner, encoder))
+    }
+
+    /// Makes a new encoder instance that produces a GZIP stream.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::gzip::{Decoder, EncodeOptions, HeaderBuilder};
+    /// use libflate::read::Encoder;
+    ///
+    /// let header = HeaderBuilder::new().modification_time(123).finish();
+    /// let options = EncodeOptions::new().header(header);
+    /// let mut encoder = Encoder::gzip(&b"Hello World!"[..], options).unwrap();
+    /// let mut encoded_data = Vec::new();
+    /// encoder.read_to_end(&mut encoded_data).unwrap();
+    ///
+    /// let decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// assert_eq!(decoder.header().modification_time(), 123);
+    /// ```
+    pub fn gzip(inner: R, options: gzip::EncodeOptions<E>) -> io::Result<Self> {
+        push::Encoder::gzip(options).map(|encoder| Self::from_push(inner, encoder))
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Encoder`, returning the underlying reader.
+    ///
+    /// Note that the data which have been read from the reader but not compressed yet will be lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the number of the (uncompressed) bytes read from the inner stream and compressed by this encoder.
+    pub fn total_in(&self) -> u64 {
+        self.encoder.total_in()
+    }
+
+    /// Returns the number of the (compressed) bytes read from this encoder.
+    pub fn total_out(&self) -> u64 {
+        self.encoder.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this encoder (excluding the inner stream).
+    ///
+    /// This includes the input buffer (see [`deflate::EncodeOptions::buffer_size`]).
+    /// See [`push::Encoder::memory_usage`] for the details of the rest.
+    pub fn memory_usage(&self) -> usize {
+        self.encoder.memory_usage() + self.buf.capacity()
+    }
+
+    fn from_push(inner: R, encoder: push::Encoder<E>) -> Self {
+        Encoder {
+            inner,
+            encoder,
+            buf: Vec::new(),
+            offset: 0,
+            eof: false,
+        }
+    }
+}
+impl<R, E> Read for Encoder<R, E>
+where
+    R: Read,
+    E: lz77::Lz77Encode,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.offset == self.buf.len() && !self.eof {
+                self.buf.resize(self.encoder.buffer_size(), 0);
+                self.offset = 0;
+                let size = match util::retry_interrupted(|| self.inner.read(&mut self.buf)) {
+                    Ok(size) => size,
+                    Err(e) => {
+                        self.buf.clear();
+                        return Err(e);
+                    }
+                };
+                self.buf.truncate(size);
+                self.eof = size == 0;
+            }
+
+            let flush = if self.eof { Flush::Finish } else { Flush::None };
+            let progress = self.encoder.encode(&self.buf[self.offset..], buf, flush)?;
+            self.offset += progress.consumed;
+            if progress.produced > 0 {
+                return Ok(progress.produced);
+            }
+            if progress.status == Status::StreamEnd {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+/// Iterator which reads the data of the inner reader in chunks of a fixed size.
+///
+/// Every chunk but the last one has exactly the requested size; the last one may be shorter.
+/// The iteration ends at the end of the data or after the first error.
+///
+/// This is usually made by the `chunks()` method of the decoders, such as [`deflate::Decoder::chunks`].
+///
+/// # Examples
+/// ```
+/// use libflate::read::Chunks;
+/// use libflate::zlib::Decoder;
+///
+/// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+/// let decoder = Decoder::new(&encoded_data[..]).unwrap();
+/// let chunks = Chunks::new(decoder, 5).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(chunks, [&b"Hello"[..], b" Worl", b"d!"]);
+/// ```
+#[derive(Debug)]
+pub struct Chunks<R> {
+    inner: R,
+    chunk_size: usize,
+    done: bool,
+}
+impl<R> Chunks<R>
+where
+    R: Read,
+{
+    /// Makes a new `Chunks` instance which reads `inner` in chunks of `chunk_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`.
+    pub fn new(inner: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "The chunk size must be positive");
+        Chunks {
+            inner,
+            chunk_size,
+            done: false,
+        }
+    }
+
+    /// Reads the next chunk into `buf`, reusing its allocation.
+    ///
+    /// `buf` is cleared first, and then holds the chunk if `Some(Ok(()))` is returned.
+    /// On an error, it holds the bytes read before the error.
+    /// `None` is returned (and `buf` is left empty) at the end of the iteration.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut chunks = decoder.chunks(8);
+    /// let mut buf = Vec::new();
+    /// let mut sizes = Vec::new();
+    /// while let Some(result) = chunks.next_into(&mut buf) {
+    ///     result.unwrap();
+    ///     sizes.push(buf.len());
+    /// }
+    /// assert_eq!(sizes, [8, 4]);
+    /// ```
+    pub fn next_into(&mut self, buf: &mut Vec<u8>) -> Option<io::Result<()>> {
+        buf.clear();
+        if self.done {
+            return None;
+        }
+        if let Err(e) = util::try_reserve(buf, self.chunk_size) {
+            self.done = true;
+            return Some(Err(e));
+        }
+        buf.resize(self.chunk_size, 0);
+        let mut filled = 0;
+        while filled < buf.len() {
+            match util::retry_interrupted(|| self.inner.read(&mut buf[filled..])) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(size) => filled += size,
+                Err(e) => {
+                    self.done = true;
+                    buf.truncate(filled);
+                    return Some(Err(e));
+                }
+            }
+        }
+        buf.truncate(filled);
+        if filled == 0 {
+            None
+        } else {
+            Some(Ok(()))
+        }
+    }
+
+    /// Returns the immutable reference to the inner reader.
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner reader.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Chunks`, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+impl<R> Iterator for Chunks<R>
+where
+    R: Read,
+{
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = Vec::new();
+        self.next_into(&mut buf).map(|result| result.map(|()| buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::any;
+
+    fn read_chunked<R: Read>(mut reader: R, chunk_size: usize) -> Vec<u8> {
+        let mut buf = vec![0; chunk_size];
+        let mut output = Vec::new();
+        loop {
+            let size = reader.read(&mut buf).unwrap();
+            if size == 0 {
+                return output;
+            }
+            output.extend_from_slice(&buf[..size]);
+        }
+    }
+
+    #[test]
+    fn encode_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            for &chunk_size in &[1, 100, 100_000] {
+                let encoder = Encoder::new(&plain[..], format);
+                let encoded = read_chunked(encoder, chunk_size);
+
+                let mut decoder = any::Decoder::new(&encoded[..]).unwrap();
+                assert_eq!(decoder.format(), format);
+                let mut decoded = Vec::new();
+                decoder.read_to_end(&mut decoded).unwrap();
+                assert_eq!(decoded, plain);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_input_works() {
+        let encoder = Encoder::zlib(&[][..], zlib::EncodeOptions::new()).unwrap();
+        let encoded = read_chunked(encoder, 3);
+
+        let mut decoder = zlib::Decoder::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn encoder_reads_lazily() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let options = deflate::EncodeOptions::new().block_size(1024);
+        let mut encoder = Encoder::deflate(&plain[..], options);
+        let mut buf = [0; 1];
+        assert_eq!(encoder.read(&mut buf).unwrap(), 1);
+        assert!(!encoder.as_inner_ref().is_empty());
+    }
+
+    #[test]
+    fn buffer_size_works() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for &size in &[1, 100, 64 * 1024] {
+            let options = deflate::EncodeOptions::new()
+                .block_size(1024)
+                .buffer_size(size);
+            let mut encoder = Encoder::deflate(&plain[..], options);
+            let mut buf = [0; 1];
+            assert_eq!(encoder.read(&mut buf).unwrap(), 1);
+            let consumed = plain.len() - encoder.as_inner_ref().len();
+            assert_eq!(consumed % size, 0);
+            assert!(encoder.memory_usage() >= size);
+
+            let mut encoded = buf.to_vec();
+            encoded.extend(read_chunked(encoder, 1000));
+            let decoded = read_chunked(deflate::Decoder::new(&encoded[..]), 1000);
+            assert_eq!(decoded, plain);
+        }
+    }
+
+    #[test]
+    fn chunks_work() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let encoded = read_chunked(Encoder::new(&plain[..], Format::Gzip), 1000);
+        for &chunk_size in &[1, 999, 50_000, 100_000, 200_000] {
+            let mut decoder = gzip::Decoder::new(&encoded[..]).unwrap();
+            let chunks = decoder.chunks(chunk_size).collect::<Vec<_>>();
+            assert_eq!(chunks.len(), plain.len().div_ceil(chunk_size));
+            let mut decoded = Vec::new();
+            for chunk in chunks {
+                let chunk = chunk.unwrap();
+                assert!(chunk.len() == chunk_size || decoded.len() + chunk.len() == plain.len());
+                decoded.extend_from_slice(&chunk);
+            }
+            assert_eq!(decoded, plain);
+        }
+
+        let mut chunks = Chunks::new(&[][..], 10);
+        assert!(chunks.next().is_none());
+
+        let broken = &encoded[..encoded.len() / 2];
+        let decoder = gzip::Decoder::new(broken).unwrap();
+        let mut chunks = Chunks::new(decoder, 1000);
+        assert!(chunks.by_ref().any(|chunk| chunk.is_err()));
+        assert!(chunks.next().is_none());
+    }
+    #[test]
+    fn total_in_and_total_out_work() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let mut encoder = Encoder::new(&plain[..], format);
+            let encoded = read_chunked(&mut encoder, 100);
+            assert_eq!(encoder.total_in(), plain.len() as u64);
+            assert_eq!(encoder.total_out(), encoded.len() as u64);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! [`tokio_util::codec`] integration.
+//!
+//! [`Codec`] compresses each frame given to its `Encoder` implementation and
+//! yields the decompressed data of a compressed byte stream as frames from its `Decoder` implementation.
+//!
+//! # Examples
+//! ```
+//! use bytes::{Bytes, BytesMut};
+//! use libflate::push::Format;
+//! use libflate::tokio::codec::Codec;
+//! use tokio_util::codec::{Decoder, Encoder};
+//!
+//! // Encoding
+//! let mut codec = Codec::new(Format::Gzip);
+//! let mut encoded = BytesMut::new();
+//! codec.encode(Bytes::from_static(b"Hello World!"), &mut encoded).unwrap();
+//! codec.encode(Bytes::new(), &mut encoded).unwrap(); // Finishes the stream
+//!
+//! // Decoding
+//! let mut codec = Codec::new(Format::Gzip);
+//! let mut decoded = Vec::new();
+//! while let Some(frame) = codec.decode_eof(&mut encoded).unwrap() {
+//!     decoded.extend_from_slice(&frame);
+//! }
+//! assert_eq!(decoded, b"Hello World!");
+//! ```
+use crate::deflate;
+use crate::lz77;
+use crate::push;
+use ::bytes::{Buf, Bytes, BytesMut};
+use ::tokio_util::codec;
+use std::io;
+
+/// A codec which compresses frames in the `Encoder` direction and
+/// decompresses a byte stream into frames in the `Decoder` direction.
+///
+/// # Encoding
+///
+/// Each non-empty frame is compressed and then sync-flushed,
+/// so the peer can decode it as soon as it is received.
+/// An empty frame finishes the compressed stream (i.e., writes the trailer).
+///
+/// # Decoding
+///
+/// Each produced frame holds a chunk of decompressed data.
+/// The bytes following the end of the compressed stream are left in the source buffer.
+#[derive(Debug)]
+pub struct Codec<E = lz77::DefaultLz77Encoder> {
+    encoder: push::Encoder<E>,
+    decoder: push::Decoder,
+    // The decoded bytes of the frames are split off from this buffer, so its allocation is reused
+    // once the caller drops the frames
+    decode_buf: BytesMut,
+    encode_buf: Vec<u8>,
+}
+impl Codec<lz77::DefaultLz77Encoder> {
+    /// Makes a new codec instance for the given format.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::push::Format;
+    /// use libflate::tokio::codec::Codec;
+    ///
+    /// let codec = Codec::new(Format::Zlib);
+    /// ```
+    pub fn new(format: push::Format) -> Self {
+        Self::with_encoder(format, push::Encoder::new(format))
+    }
+}
+impl<E> Codec<E>
+where
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new codec instance which uses `encoder` to compress frames.
+    ///
+    /// `format` specifies the format of the stream to be decoded.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::EncodeOptions;
+    /// use libflate::push::{Encoder, Format};
+    /// use libflate::tokio::codec::Codec;
+    ///
+    /// let encoder = Encoder::deflate(EncodeOptions::new().fixed_huffman_codes());
+    /// let codec = Codec::with_encoder(Format::Deflate, encoder);
+    /// ```
+    pub fn with_encoder(format: push::Format, encoder: push::Encoder<E>) -> Self {
+        Codec {
+            encoder,
+            decoder: push::Decoder::new(format),
+            decode_buf: BytesMut::new(),
+            encode_buf: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the stream being decoded has reached its end, otherwise `false`.
+    pub fn is_decoding_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Returns `true` if the stream being encoded has been finished, otherwise `false`.
+    pub fn is_encoding_finished(&self) -> bool {
+        self.encoder.is_finished()
+    }
+}
+impl<E> codec::Decoder for Codec<E>
+where
+    E: lz77::Lz77Encode,
+{
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        let buf = &mut self.decode_buf;
+        buf.resize(deflate::DEFAULT_BUFFER_SIZE, 0);
+        let mut size = 0;
+        while size < buf.len() && !self.decoder.is_finished() {
+            let progress = self.decoder.decode(src, &mut buf[size..])?;
+            src.advance(progress.consumed);
+            size += progress.produced;
+            if progress.consumed == 0 && progress.produced == 0 {
+                break;
+            }
+        }
+        if size == 0 {
+            return Ok(None);
+        }
+        Ok(Some(buf.split_to(size).freeze()))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if self.decoder.is_finished() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Unexpected end of the compressed stream",
+            )),
+        }
+    }
+}
+impl<E> codec::Encoder<Bytes> for Codec<E>
+where
+    E: lz77::Lz77Encode,
+{
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        if self.encoder.is_finished() {
+            return Err(io::Error::other("The encoder has already been finished"));
+        }
+        let flush = if item.is_empty() {
+            push::Flush::Finish
+        } else {
+            push::Flush::Sync
+        };
+
+        // No output is pending here, so the first call consumes the whole item and applies `flush`.
+        let buf = &mut self.encode_buf;
+        buf.resize(self.encoder.buffer_size(), 0);
+        let mut progress = self.encoder.encode(&item, buf, flush)?;
+        dst.extend_from_slice(&buf[..progress.produced]);
+        while progress.produced == buf.len() {
+            progress = self.encoder.encode(&[], buf, push::Flush::None)?;
+            dst.extend_from_slice(&buf[..progress.produced]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn frames_are_decodable_immediately() {
+        let mut encoder = Codec::new(push::Format::Zlib);
+        let mut decoder = Codec::new(push::Format::Zlib);
+        let mut wire = BytesMut::new();
+
+        encoder
+            .encode(Bytes::from_static(b"foo"), &mut wire)
+            .unwrap();
+        assert_eq!(decoder.decode(&mut wire).unwrap().unwrap(), &b"foo"[..]);
+        assert_eq!(decoder.decode(&mut wire).unwrap(), None);
+
+        encoder
+            .encode(Bytes::from_static(b"bar"), &mut wire)
+            .unwrap();
+        encoder.encode(Bytes::new(), &mut wire).unwrap();
+        assert!(encoder.is_encoding_finished());
+        wire.extend_from_slice(b"trailing");
+
+        assert_eq!(decoder.decode_eof(&mut wire).unwrap().unwrap(), &b"bar"[..]);
+        assert_eq!(decoder.decode_eof(&mut wire).unwrap(), None);
+        assert!(decoder.is_decoding_finished());
+        assert_eq!(wire, &b"trailing"[..]);
+    }
+
+    #[test]
+    fn large_frame_works() {
+        let plain = (0..100_000).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+        let mut codec = Codec::new(push::Format::Gzip);
+        let mut wire = BytesMut::new();
+        codec.encode(Bytes::from(plain.clone()), &mut wire).unwrap();
+        codec.encode(Bytes::new(), &mut wire).unwrap();
+
+        let mut codec = Codec::new(push::Format::Gzip);
+        let mut decoded = Vec::new();
+        while let Some(frame) = codec.decode_eof(&mut wire).unwrap() {
+            assert!(frame.len() <= deflate::DEFAULT_BUFFER_SIZE);
+            decoded.extend_from_slice(&frame);
+        }
+        assert_eq!(decoded, plain);
+    }
+
+    #[test]
+    fn truncated_stream_is_error() {
+        let mut codec = Codec::new(push::Format::Deflate);
+        let mut wire = BytesMut::new();
+        codec
+            .encode(Bytes::from_static(b"Hello World!"), &mut wire)
+            .unwrap();
+
+        let mut codec = Codec::new(push::Format::Deflate);
+        assert_eq!(
+            codec.decode_eof(&mut wire).unwrap().unwrap(),
+            &b"Hello World!"[..]
+        );
+        let error = codec.decode_eof(&mut wire).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
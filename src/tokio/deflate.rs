@@ -0,0 +1,228 @@
+//! The asynchronous encoder and decoder of the DEFLATE format.
+//!
+//! # Examples
+//! ```
+//! use libflate::tokio::deflate::{Decoder, Encoder};
+//! use tokio::io::{AsyncReadExt, AsyncWriteExt};
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! // Encoding
+//! let mut encoder = Encoder::new(Vec::new());
+//! encoder.write_all(b"Hello World!").await.unwrap();
+//! encoder.shutdown().await.unwrap();
+//! let encoded_data = encoder.into_inner();
+//!
+//! // Decoding
+//! let mut decoder = Decoder::new(&encoded_data[..]);
+//! let mut decoded_data = Vec::new();
+//! decoder.read_to_end(&mut decoded_data).await.unwrap();
+//!
+//! assert_eq!(decoded_data, b"Hello World!");
+//! # });
+//! ```
+use super::{AsyncDecoder, AsyncEncoder};
+use crate::deflate::EncodeOptions;
+use crate::lz77;
+use crate::push;
+use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// DEFLATE decoder which implements `tokio::io::AsyncRead`.
+#[derive(Debug)]
+pub struct Decoder<R> {
+    inner: AsyncDecoder<R>,
+}
+impl<R> Decoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Makes a new decoder instance.
+    ///
+    /// `inner` is to be decoded DEFLATE stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::tokio::deflate::Decoder;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(&encoded_data[..]);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).await.unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// # });
+    /// ```
+    pub fn new(inner: R) -> Self {
+        Decoder {
+            inner: AsyncDecoder::new(inner, push::Format::Deflate),
+        }
+    }
+
+    /// Makes a new decoder instance whose input buffer is `size` bytes (at least `1`).
+    ///
+    /// The compressed data are read from `inner` in chunks of up to `size` bytes.
+    /// The default size is [`deflate::DEFAULT_BUFFER_SIZE`](crate::deflate::DEFAULT_BUFFER_SIZE).
+    pub fn with_buffer_size(inner: R, size: usize) -> Self {
+        Decoder {
+            inner: AsyncDecoder::with_buffer_size(inner, push::Format::Deflate, size),
+        }
+    }
+}
+impl<R> Decoder<R> {
+    /// Returns the number of the (compressed) bytes read from the inner stream.
+    pub fn total_in(&self) -> u64 {
+        self.inner.total_in()
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        self.inner.total_out()
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        self.inner.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        self.inner.as_inner_mut()
+    }
+
+    /// Unwraps this `Decoder`, returning the underlying reader.
+    ///
+    /// Note that any leftover data in the internal buffer will be lost.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+impl<R> AsyncRead for Decoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+/// DEFLATE encoder which implements `tokio::io::AsyncWrite`.
+///
+/// `AsyncWriteExt::shutdown` must be called to terminate the stream.
+#[derive(Debug)]
+pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
+    inner: AsyncEncoder<W, E>,
+}
+impl<W> Encoder<W, lz77::DefaultLz77Encoder>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Makes a new encoder instance.
+    ///
+    /// Encoded DEFLATE stream is written to `inner`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::tokio::deflate::Encoder;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let mut encoder = Encoder::new(Vec::new());
+    /// encoder.write_all(b"Hello World!").await.unwrap();
+    /// encoder.shutdown().await.unwrap();
+    ///
+    /// assert_eq!(encoder.into_inner(),
+    ///            [5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128, 237,
+    ///            147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1]);
+    /// # });
+    /// ```
+    pub fn new(inner: W) -> Self {
+        Self::with_options(inner, EncodeOptions::default())
+    }
+}
+impl<W, E> Encoder<W, E>
+where
+    W: AsyncWrite + Unpin,
+    E: lz77::Lz77Encode,
+{
+    /// Makes a new encoder instance with specified options.
+    ///
+    /// Encoded DEFLATE stream is written to `inner`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::deflate::EncodeOptions;
+    /// use libflate::tokio::deflate::Encoder;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let options = EncodeOptions::new().no_compression();
+    /// let mut encoder = Encoder::with_options(Vec::new(), options);
+    /// encoder.write_all(b"Hello World!").await.unwrap();
+    /// encoder.shutdown().await.unwrap();
+    ///
+    /// assert_eq!(encoder.into_inner(),
+    ///            [1, 12, 0, 243, 255, 72, 101, 108, 108, 111, 32, 87, 111,
+    ///             114, 108, 100, 33]);
+    /// # });
+    /// ```
+    pub fn with_options(inner: W, options: EncodeOptions<E>) -> Self {
+        Encoder {
+            inner: AsyncEncoder::new(inner, push::Encoder::deflate(options)),
+        }
+    }
+
+    /// Returns the number of the (uncompressed) bytes written to this encoder.
+    pub fn total_in(&self) -> u64 {
+        self.inner.total_in()
+    }
+
+    /// Returns the number of the (compressed) bytes written to the inner stream.
+    pub fn total_out(&self) -> u64 {
+        self.inner.total_out()
+    }
+}
+impl<W, E> Encoder<W, E> {
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        self.inner.as_inner_ref()
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        self.inner.as_inner_mut()
+    }
+
+    /// Unwraps the `Encoder`, returning the inner stream.
+    ///
+    /// Note that the stream is incomplete unless `shutdown` has been completed before this call.
+    pub fn into_inner(self) -> W {
+        self.inner.into_inner()
+    }
+}
+impl<W, E> AsyncWrite for Encoder<W, E>
+where
+    W: AsyncWrite + Unpin,
+    E: lz77::Lz77Encode + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
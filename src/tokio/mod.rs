@@ -0,0 +1,324 @@
+//! Implementations that work with [tokio]'s asynchronous I/O traits.
+//!
+//! The `Decoder`s in this module implement `tokio::io::AsyncRead` and
+//! the `Encoder`s implement `tokio::io::AsyncWrite`,
+//! so they can be placed directly in tokio based I/O pipelines.
+//!
+//! Internally, they are thin wrappers of the push-style coders in the [`push`] module.
+//!
+//! # NOTICE
+//!
+//! - This module is available only if the `tokio` feature is enabled (and the `no_std` feature is disabled).
+//! - The inner streams are required to be `Unpin`. Use `Box::pin` to wrap `!Unpin` streams.
+//! - `AsyncWrite::poll_shutdown` must be called to write the trailer of a stream.
+//!
+//! If the `tokio-util` feature is enabled, the [`codec`] and [`stream`] modules are also available.
+//!
+//! [tokio]: https://tokio.rs/
+#[cfg(feature = "tokio-util")]
+pub mod codec;
+pub mod deflate;
+pub mod gzip;
+#[cfg(feature = "tokio-util")]
+pub mod stream;
+pub mod zlib;
+
+use crate::lz77;
+use crate::push;
+use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+#[derive(Debug)]
+struct AsyncDecoder<R> {
+    inner: R,
+    decoder: push::Decoder,
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    eof: bool,
+}
+impl<R> AsyncDecoder<R> {
+    fn new(inner: R, format: push::Format) -> Self {
+        Self::with_buffer_size(inner, format, crate::deflate::DEFAULT_BUFFER_SIZE)
+    }
+    fn with_buffer_size(inner: R, format: push::Format, size: usize) -> Self {
+        AsyncDecoder {
+            inner,
+            decoder: push::Decoder::new(format),
+            buf: vec![0; size.max(1)].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            eof: false,
+        }
+    }
+    fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+    fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+    fn into_inner(self) -> R {
+        self.inner
+    }
+    fn total_in(&self) -> u64 {
+        self.decoder.total_in()
+    }
+    fn total_out(&self) -> u64 {
+        self.decoder.total_out()
+    }
+}
+impl<R> AsyncRead for AsyncDecoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while out.remaining() > 0 && !this.decoder.is_finished() {
+            if this.start == this.end && !this.eof {
+                let mut buf = ReadBuf::new(&mut this.buf);
+                ready!(Pin::new(&mut this.inner).poll_read(cx, &mut buf))?;
+                this.start = 0;
+                this.end = buf.filled().len();
+                this.eof = this.end == 0;
+            }
+
+            let progress = this
+                .decoder
+                .decode(&this.buf[this.start..this.end], out.initialize_unfilled())?;
+            this.start += progress.consumed;
+            out.advance(progress.produced);
+            if progress.produced > 0 {
+                break;
+            }
+            if this.eof && this.start == this.end && !this.decoder.is_finished() {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Unexpected end of the compressed stream",
+                )));
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[derive(Debug)]
+struct AsyncEncoder<W, E> {
+    inner: W,
+    encoder: push::Encoder<E>,
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    flushing: bool,
+}
+impl<W, E> AsyncEncoder<W, E>
+where
+    W: AsyncWrite + Unpin,
+    E: lz77::Lz77Encode,
+{
+    fn new(inner: W, encoder: push::Encoder<E>) -> Self {
+        AsyncEncoder {
+            inner,
+            buf: vec![0; encoder.buffer_size()].into_boxed_slice(),
+            encoder,
+            start: 0,
+            end: 0,
+            flushing: false,
+        }
+    }
+
+    /// Writes all the output of `self.encoder` to the inner stream.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            while self.start < self.end {
+                let size = ready!(
+                    Pin::new(&mut self.inner).poll_write(cx, &self.buf[self.start..self.end])
+                )?;
+                if size == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "Failed to write the compressed data",
+                    )));
+                }
+                self.start += size;
+            }
+            let progress = self.encoder.encode(&[], &mut self.buf, push::Flush::None)?;
+            self.start = 0;
+            self.end = progress.produced;
+            if progress.produced == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+
+    fn total_in(&self) -> u64 {
+        self.encoder.total_in()
+    }
+
+    /// Returns the number of the compressed bytes written to the inner stream
+    /// (excluding the ones buffered in `self.buf`).
+    fn total_out(&self) -> u64 {
+        self.encoder.total_out() - (self.end - self.start) as u64
+    }
+
+    fn poll_write_with(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        flush: push::Flush,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_drain(cx))?;
+        let progress = self.encoder.encode(buf, &mut self.buf, flush)?;
+        self.start = 0;
+        self.end = progress.produced;
+        Poll::Ready(Ok(progress.consumed))
+    }
+}
+impl<W, E> AsyncEncoder<W, E> {
+    fn as_inner_ref(&self) -> &W {
+        &self.inner
+    }
+    fn as_inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+    fn into_inner(self) -> W {
+        self.inner
+    }
+}
+impl<W, E> AsyncWrite for AsyncEncoder<W, E>
+where
+    W: AsyncWrite + Unpin,
+    E: lz77::Lz77Encode + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.encoder.is_finished() {
+            return Poll::Ready(Err(io::Error::other(
+                "The encoder has already been shut down",
+            )));
+        }
+        this.poll_write_with(cx, buf, push::Flush::None)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.flushing {
+            ready!(this.poll_write_with(cx, &[], push::Flush::Sync))?;
+            this.flushing = true;
+        }
+        ready!(this.poll_drain(cx))?;
+        this.flushing = false;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.encoder.is_finished() {
+            ready!(this.poll_write_with(cx, &[], push::Flush::Finish))?;
+        }
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn round_trip(format: push::Format, plain: &[u8]) -> Vec<u8> {
+        let mut encoder = AsyncEncoder::new(Vec::new(), push::Encoder::new(format));
+        for chunk in plain.chunks(1000) {
+            encoder.write_all(chunk).await.unwrap();
+        }
+        encoder.shutdown().await.unwrap();
+        let encoded = encoder.into_inner();
+
+        let mut decoder = AsyncDecoder::new(&encoded[..], format);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).await.unwrap();
+        decoded
+    }
+
+    #[tokio::test]
+    async fn round_trip_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 13) as u8 + (i / 777) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[
+            push::Format::Deflate,
+            push::Format::Zlib,
+            push::Format::Gzip,
+        ] {
+            assert_eq!(round_trip(format, &plain).await, plain);
+        }
+    }
+
+    #[tokio::test]
+    async fn small_buffers_work() {
+        let plain = (0..10_000).map(|i| (i % 13) as u8).collect::<Vec<_>>();
+        let options = crate::zlib::EncodeOptions::new().buffer_size(3);
+        let mut encoder = AsyncEncoder::new(Vec::new(), push::Encoder::zlib(options).unwrap());
+        assert_eq!(encoder.buf.len(), 3);
+        encoder.write_all(&plain).await.unwrap();
+        encoder.shutdown().await.unwrap();
+        let encoded = encoder.into_inner();
+
+        let mut decoder = AsyncDecoder::with_buffer_size(&encoded[..], push::Format::Zlib, 0);
+        assert_eq!(decoder.buf.len(), 1);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).await.unwrap();
+        assert_eq!(decoded, plain);
+    }
+
+    #[tokio::test]
+    async fn flush_makes_data_decodable() {
+        let mut encoder = AsyncEncoder::new(Vec::new(), push::Encoder::new(push::Format::Zlib));
+        encoder.write_all(b"Hello").await.unwrap();
+        encoder.flush().await.unwrap();
+
+        let mut decoder = AsyncDecoder::new(&encoder.as_inner_ref()[..], push::Format::Zlib);
+        let mut buf = [0; 5];
+        decoder.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"Hello");
+    }
+
+    #[tokio::test]
+    async fn truncated_stream_is_error() {
+        let mut encoder = AsyncEncoder::new(Vec::new(), push::Encoder::new(push::Format::Gzip));
+        encoder.write_all(b"Hello World!").await.unwrap();
+        encoder.shutdown().await.unwrap();
+        let encoded = encoder.into_inner();
+
+        let mut decoder = AsyncDecoder::new(&encoded[..encoded.len() - 1], push::Format::Gzip);
+        let mut decoded = Vec::new();
+        let error = decoder.read_to_end(&mut decoded).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+    #[tokio::test]
+    async fn total_in_and_total_out_work() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        for chunk in plain.chunks(1000) {
+            encoder.write_all(chunk).await.unwrap();
+        }
+        encoder.shutdown().await.unwrap();
+        assert_eq!(encoder.total_in(), plain.len() as u64);
+        assert_eq!(encoder.total_out(), encoder.as_inner_ref().len() as u64);
+        let encoded = encoder.into_inner();
+
+        let mut decoder = gzip::Decoder::with_buffer_size(&encoded[..], 100);
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).await.unwrap();
+        assert_eq!(decoded, plain);
+        assert_eq!(decoder.total_in(), encoded.len() as u64);
+        assert_eq!(decoder.total_out(), plain.len() as u64);
+    }
+}
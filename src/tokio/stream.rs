@@ -0,0 +1,222 @@
+//! [`Stream`] adapters.
+//!
+//! # Examples
+//! ```
+//! use bytes::Bytes;
+//! use futures_core::Stream;
+//! use libflate::push::Format;
+//! use libflate::tokio::stream::Decoder;
+//! use std::future::poll_fn;
+//! use std::io;
+//! use std::pin::Pin;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+//! let chunks = encoded_data
+//!     .chunks(5)
+//!     .map(|chunk| Ok::<_, io::Error>(Bytes::copy_from_slice(chunk)))
+//!     .collect::<Vec<_>>();
+//!
+//! # struct Iter<I>(I);
+//! # impl<I: Iterator + Unpin> Stream for Iter<I> {
+//! #     type Item = I::Item;
+//! #     fn poll_next(mut self: Pin<&mut Self>, _: &mut std::task::Context<'_>)
+//! #         -> std::task::Poll<Option<I::Item>> { std::task::Poll::Ready(self.0.next()) }
+//! # }
+//! let mut decoder = Decoder::new(Iter(chunks.into_iter()), Format::Deflate);
+//! let mut decoded_data = Vec::new();
+//! while let Some(chunk) = poll_fn(|cx| Pin::new(&mut decoder).poll_next(cx)).await {
+//!     decoded_data.extend_from_slice(&chunk.unwrap());
+//! }
+//! assert_eq!(decoded_data, b"Hello World!");
+//! # });
+//! ```
+use crate::deflate;
+use crate::push;
+use ::bytes::{Buf, Bytes};
+use ::futures_core::Stream;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// A stream adapter which decompresses a stream of compressed chunks.
+///
+/// The inner stream yields `Result<Bytes, E>`, as HTTP body streams typically do.
+/// Items of the adapter are chunks of decompressed data
+/// (at most [`deflate::DEFAULT_BUFFER_SIZE`] bytes each by default, see [`Decoder::with_buffer_size`]).
+///
+/// The adapter ends when the compressed stream ends, and the data following it is discarded.
+/// If the inner stream ends before that, `ErrorKind::UnexpectedEof` error is yielded.
+#[derive(Debug)]
+pub struct Decoder<S> {
+    inner: S,
+    decoder: push::Decoder,
+    chunk: Bytes,
+    buffer_size: usize,
+    eos: bool,
+}
+impl<S> Decoder<S> {
+    /// Makes a new decoder instance.
+    ///
+    /// `inner` is to be decoded stream of `format`.
+    pub fn new(inner: S, format: push::Format) -> Self {
+        Self::with_buffer_size(inner, format, deflate::DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Makes a new decoder instance which yields chunks of up to `size` bytes (at least `1`).
+    pub fn with_buffer_size(inner: S, format: push::Format, size: usize) -> Self {
+        Decoder {
+            inner,
+            decoder: push::Decoder::new(format),
+            chunk: Bytes::new(),
+            buffer_size: size.max(1),
+            eos: false,
+        }
+    }
+
+    /// Returns the number of the (compressed) bytes consumed from the chunks of the inner stream.
+    pub fn total_in(&self) -> u64 {
+        self.decoder.total_in()
+    }
+
+    /// Returns the number of the decoded bytes yielded by this decoder.
+    pub fn total_out(&self) -> u64 {
+        self.decoder.total_out()
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Decoder`, returning the inner stream.
+    ///
+    /// Note that the unconsumed part of the current chunk will be lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+impl<S, E> Stream for Decoder<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<io::Error>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut buf = vec![0; this.buffer_size];
+        loop {
+            if this.eos || this.decoder.is_finished() {
+                return Poll::Ready(None);
+            }
+            if this.chunk.is_empty() {
+                match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                    Some(Ok(chunk)) => this.chunk = chunk,
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                    None => {
+                        this.eos = true;
+                        return Poll::Ready(Some(Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "Unexpected end of the compressed stream",
+                        ))));
+                    }
+                }
+            }
+
+            let progress = match this.decoder.decode(&this.chunk, &mut buf) {
+                Ok(progress) => progress,
+                Err(e) => {
+                    this.eos = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            };
+            this.chunk.advance(progress.consumed);
+            if progress.produced > 0 {
+                buf.truncate(progress.produced);
+                return Poll::Ready(Some(Ok(Bytes::from(buf))));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gzip;
+    use std::future::poll_fn;
+    use std::io::Write;
+
+    struct Iter<I>(I);
+    impl<I: Iterator + Unpin> Stream for Iter<I> {
+        type Item = I::Item;
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<I::Item>> {
+            Poll::Ready(self.0.next())
+        }
+    }
+
+    fn chunks(data: &[u8], size: usize) -> Iter<std::vec::IntoIter<io::Result<Bytes>>> {
+        Iter(
+            data.chunks(size)
+                .map(|c| Ok(Bytes::copy_from_slice(c)))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )
+    }
+
+    async fn collect<S: Stream<Item = io::Result<Bytes>> + Unpin>(
+        mut stream: S,
+    ) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            buf.extend_from_slice(&chunk?);
+        }
+        Ok(buf)
+    }
+
+    fn gzip_encode(plain: &[u8]) -> Vec<u8> {
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(plain).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    #[tokio::test]
+    async fn decoding_works() {
+        let plain = (0..50_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let encoded = gzip_encode(&plain);
+        for &size in &[1, 100, 100_000] {
+            let decoder = Decoder::new(chunks(&encoded, size), push::Format::Gzip);
+            assert_eq!(collect(decoder).await.unwrap(), plain);
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_stream_is_error() {
+        let encoded = gzip_encode(b"Hello World!");
+        let decoder = Decoder::new(chunks(&encoded[..encoded.len() - 3], 4), push::Format::Gzip);
+        let error = collect(decoder).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn inner_error_is_propagated() {
+        let items = vec![Err(io::Error::other("oops"))];
+        let decoder = Decoder::new(Iter(items.into_iter()), push::Format::Zlib);
+        let error = collect(decoder).await.unwrap_err();
+        assert_eq!(error.to_string(), "oops");
+    }
+    #[tokio::test]
+    async fn total_in_and_total_out_work() {
+        let plain = (0..50_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let encoded = gzip_encode(&plain);
+        let mut decoder = Decoder::new(chunks(&encoded, 100), push::Format::Gzip);
+        assert_eq!(collect(&mut decoder).await.unwrap(), plain);
+        assert_eq!(decoder.total_in(), encoded.len() as u64);
+        assert_eq!(decoder.total_out(), plain.len() as u64);
+    }
+}
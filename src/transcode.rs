@@ -0,0 +1,429 @@
+//! Conversion of compressed streams in a single streaming pass.
+//!
+//! Both the GZIP and ZLIB formats wrap a DEFLATE stream, so converting one to the other only requires
+//! replacing the header and the trailer around the (identical) compressed body.
+//! [`gzip_to_zlib`] and [`zlib_to_gzip`] copy the body as is,
+//! and decode it only to compute (and verify) the checksums.
+//!
+//! [`recompress_gzip`] and [`recompress_zlib`] instead re-encode the body with other compression options,
+//! keeping the metadata of the container ([`recompress_gzip_verbatim`] keeps the GZIP header bit-for-bit).
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::{Read, Write};
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::{Read, Write};
+//! use libflate::{gzip, transcode, zlib};
+//!
+//! let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+//! encoder.write_all(b"Hello World!").unwrap();
+//! let gzip_data = encoder.finish().into_result().unwrap();
+//!
+//! let mut zlib_data = Vec::new();
+//! transcode::gzip_to_zlib(&gzip_data[..], &mut zlib_data).unwrap();
+//!
+//! let mut decoder = zlib::Decoder::new(&zlib_data[..]).unwrap();
+//! let mut buf = Vec::new();
+//! decoder.read_to_end(&mut buf).unwrap();
+//! assert_eq!(buf, b"Hello World!");
+//! ```
+use crate::checksum;
+use crate::copy::Copier;
+use crate::deflate;
+use crate::error::{Error, ErrorKind};
+use crate::gzip;
+use crate::options::CompressionOptions;
+use crate::zlib;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, Read, Write};
+
+/// Converts the (first member of the) GZIP stream read from `reader` to a ZLIB stream written to `writer`.
+///
+/// The CRC32 of the GZIP stream is verified, and the header of the GZIP stream is returned.
+/// The ZLIB header declares the maximum window size (32 KiB),
+/// and its compression level is derived from that of the GZIP header.
+///
+/// Bytes following the GZIP member are not read.
+pub fn gzip_to_zlib<R, W>(mut reader: R, mut writer: W) -> io::Result<gzip::Header>
+where
+    R: Read,
+    W: Write,
+{
+    let header = gzip::Header::read_from(&mut reader)?;
+    let compression_level = match header.compression_level() {
+        gzip::CompressionLevel::Fastest => zlib::CompressionLevel::Fastest,
+        gzip::CompressionLevel::Slowest => zlib::CompressionLevel::Slowest,
+        gzip::CompressionLevel::Unknown => zlib::CompressionLevel::Default,
+    };
+    zlib::Header::new(zlib::Lz77WindowSize::KB32, compression_level).write_to(&mut writer)?;
+
+    let mut crc32 = checksum::Crc32::new();
+    let mut adler32 = checksum::Adler32::new();
+    let mut reader = copy_deflate_body(reader, &mut writer, |decoded| {
+        crc32.update(decoded);
+        adler32.update(decoded);
+    })?;
+
+    let trailer = gzip::Trailer::read_from(&mut reader)?;
+    if cfg!(not(fuzzing)) && trailer.crc32() != crc32.value() {
+        return Err(Error::new(ErrorKind::ChecksumMismatch, "CRC32 mismatched")
+            .with_value(crc32.value())
+            .with_expected(trailer.crc32())
+            .into());
+    }
+    writer.write_all(&adler32.value().to_be_bytes())?;
+    writer.flush()?;
+    Ok(header)
+}
+
+/// Converts the ZLIB stream read from `reader` to a GZIP stream (with `header`) written to `writer`.
+///
+/// The ADLER32 of the ZLIB stream is verified, and the header of the ZLIB stream is returned.
+///
+/// Bytes following the ZLIB stream are not read.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::{Read, Write};
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::{Read, Write};
+/// use libflate::{gzip, transcode, zlib};
+///
+/// let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+/// encoder.write_all(b"Hello World!").unwrap();
+/// let zlib_data = encoder.finish().into_result().unwrap();
+///
+/// let header = gzip::HeaderBuilder::new().modification_time(123).finish();
+/// let mut gzip_data = Vec::new();
+/// transcode::zlib_to_gzip(&zlib_data[..], &mut gzip_data, &header).unwrap();
+///
+/// let mut decoder = gzip::Decoder::new(&gzip_data[..]).unwrap();
+/// assert_eq!(decoder.header().modification_time(), 123);
+/// let mut buf = Vec::new();
+/// decoder.read_to_end(&mut buf).unwrap();
+/// assert_eq!(buf, b"Hello World!");
+/// ```
+pub fn zlib_to_gzip<R, W>(
+    mut reader: R,
+    mut writer: W,
+    header: &gzip::Header,
+) -> io::Result<zlib::Header>
+where
+    R: Read,
+    W: Write,
+{
+    let zlib_header = zlib::Header::read_from(&mut reader)?;
+    header.write_to(&mut writer)?;
+
+    let mut crc32 = checksum::Crc32::new();
+    let mut adler32 = checksum::Adler32::new();
+    let mut input_size = 0u32;
+    let mut reader = copy_deflate_body(reader, &mut writer, |decoded| {
+        crc32.update(decoded);
+        adler32.update(decoded);
+        input_size = input_size.wrapping_add(decoded.len() as u32);
+    })?;
+
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    let expected = u32::from_be_bytes(buf);
+    if cfg!(not(fuzzing)) && expected != adler32.value() {
+        return Err(
+            Error::new(ErrorKind::ChecksumMismatch, "Adler32 checksum mismatched")
+                .with_value(adler32.value())
+                .with_expected(expected)
+                .into(),
+        );
+    }
+    gzip::Trailer::new(crc32.value(), input_size).write_to(&mut writer)?;
+    writer.flush()?;
+    Ok(zlib_header)
+}
+
+/// Re-encodes the (first member of the) GZIP stream read from `reader` with `options`,
+/// and writes the resulting GZIP stream to `writer`.
+///
+/// The header of the input stream (the modification time, the file name, the comment, the extra field, etc)
+/// is written as is, except for the compression level which reflects `options`.
+/// The stream is processed in a single pass, so the memory usage doesn't depend on the size of the stream.
+///
+/// The CRC32 of the input stream is verified, and its header is returned.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::{Read, Write};
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::{Read, Write};
+/// use libflate::lz77::CompressionLevel;
+/// use libflate::options::CompressionOptions;
+/// use libflate::{gzip, transcode};
+///
+/// let header = gzip::HeaderBuilder::new().filename_str("hello.txt").unwrap().finish();
+/// let options = gzip::EncodeOptions::new().no_compression().header(header);
+/// let mut encoder = gzip::Encoder::with_options(Vec::new(), options).unwrap();
+/// encoder.write_all(&b"Hello World!".repeat(100)).unwrap();
+/// let stored_data = encoder.finish().into_result().unwrap();
+///
+/// let options = CompressionOptions::new().level(CompressionLevel::Best);
+/// let mut recompressed_data = Vec::new();
+/// transcode::recompress_gzip(&stored_data[..], &mut recompressed_data, options).unwrap();
+/// assert!(recompressed_data.len() < stored_data.len());
+///
+/// let mut decoder = gzip::Decoder::new(&recompressed_data[..]).unwrap();
+/// assert_eq!(decoder.header().filename().unwrap().to_str(), Ok("hello.txt"));
+/// let mut buf = Vec::new();
+/// decoder.read_to_end(&mut buf).unwrap();
+/// assert_eq!(buf, b"Hello World!".repeat(100));
+/// ```
+pub fn recompress_gzip<R, W>(
+    reader: R,
+    writer: W,
+    options: CompressionOptions,
+) -> io::Result<gzip::Header>
+where
+    R: Read,
+    W: Write,
+{
+    let mut decoder = gzip::Decoder::new(reader)?;
+    let mut header = decoder.header().clone();
+    header.set_compression_level(options.get_level().into());
+    let options = gzip::EncodeOptions::from(options).header(header);
+    let mut encoder = gzip::Encoder::with_options(writer, options)?;
+    Copier::new().copy(&mut decoder, &mut encoder)?;
+    encoder.finish().into_result()?.flush()?;
+    Ok(decoder.header().clone())
+}
+
+/// Same as [`recompress_gzip`], except that the header of the input stream is written bit-for-bit
+/// (see [`gzip::EncodeOptions::verbatim_header`]).
+///
+/// This is useful for forensic or archival tools, which must preserve the header
+/// (including the XFL field, which reflects the compression level of the input stream).
+///
+/// # Examples
+/// ```
+/// use libflate::options::CompressionOptions;
+/// use libflate::{gzip, transcode};
+///
+/// let mut stored_data = vec![31, 139, 8, 0xE2, 0, 0, 0, 0, 0, 255, 0x87, 0x6F]; // FLG has reserved and FHCRC bits
+/// stored_data.extend_from_slice(&[1, 0, 0, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0]);
+/// let header = gzip::Header::from_raw_bytes(&stored_data[..12]).unwrap();
+///
+/// let mut recompressed_data = Vec::new();
+/// transcode::recompress_gzip_verbatim(&stored_data[..], &mut recompressed_data, CompressionOptions::new())
+///     .unwrap();
+/// assert_eq!(&recompressed_data[..12], header.raw_bytes().unwrap());
+/// assert!(gzip::Decoder::new(&recompressed_data[..]).is_ok());
+/// ```
+pub fn recompress_gzip_verbatim<R, W>(
+    reader: R,
+    writer: W,
+    options: CompressionOptions,
+) -> io::Result<gzip::Header>
+where
+    R: Read,
+    W: Write,
+{
+    let mut decoder = gzip::Decoder::new(reader)?;
+    let options = gzip::EncodeOptions::from(options).verbatim_header(decoder.header().clone());
+    let mut encoder = gzip::Encoder::with_options(writer, options)?;
+    Copier::new().copy(&mut decoder, &mut encoder)?;
+    encoder.finish().into_result()?.flush()?;
+    Ok(decoder.header().clone())
+}
+
+/// Re-encodes the ZLIB stream read from `reader` with `options`,
+/// and writes the resulting ZLIB stream to `writer`.
+///
+/// The header of the output stream reflects `options`.
+/// The stream is processed in a single pass, so the memory usage doesn't depend on the size of the stream.
+///
+/// The ADLER32 of the input stream is verified, and its header is returned.
+pub fn recompress_zlib<R, W>(
+    reader: R,
+    writer: W,
+    options: CompressionOptions,
+) -> io::Result<zlib::Header>
+where
+    R: Read,
+    W: Write,
+{
+    let mut decoder = zlib::Decoder::new(reader)?;
+    let mut encoder = zlib::Encoder::with_options(writer, options.into())?;
+    Copier::new().copy(&mut decoder, &mut encoder)?;
+    encoder.finish().into_result()?.flush()?;
+    Ok(decoder.header().clone())
+}
+
+/// Copies the DEFLATE stream from `reader` to `writer`, passing the decoded data to `f`.
+///
+/// The DEFLATE decoder never reads beyond the end of the stream, so the returned reader
+/// is positioned just after it.
+fn copy_deflate_body<R, W, F>(reader: R, writer: W, mut f: F) -> io::Result<R>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(&[u8]),
+{
+    let mut decoder = deflate::Decoder::new(TeeReader { reader, writer });
+    let mut buf = [0; 8 * 1024];
+    loop {
+        match decoder.read(&mut buf)? {
+            0 => break,
+            size => f(&buf[..size]),
+        }
+    }
+    Ok(decoder.into_inner().reader)
+}
+
+/// A reader which writes the bytes read from `reader` to `writer`.
+#[derive(Debug)]
+struct TeeReader<R, W> {
+    reader: R,
+    writer: W,
+}
+impl<R, W> Read for TeeReader<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.reader.read(buf)?;
+        self.writer.write_all(&buf[..size])?;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deflate::EncodeOptions;
+    use crate::lz77;
+    #[cfg(feature = "no_std")]
+    use alloc::{vec, vec::Vec};
+
+    fn plain_data() -> Vec<u8> {
+        (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn gzip_to_zlib_works() {
+        let plain = plain_data();
+        for options in [
+            gzip::EncodeOptions::new(),
+            gzip::EncodeOptions::new().no_compression(),
+            gzip::EncodeOptions::new().fixed_huffman_codes(),
+        ] {
+            let mut encoder = gzip::Encoder::with_options(Vec::new(), options).unwrap();
+            encoder.write_all(&plain).unwrap();
+            let gzip_data = encoder.finish().into_result().unwrap();
+
+            let mut zlib_data = Vec::new();
+            gzip_to_zlib(&gzip_data[..], &mut zlib_data).unwrap();
+            // The DEFLATE body is kept as is
+            assert_eq!(
+                zlib_data[2..zlib_data.len() - 4],
+                gzip_data[10..gzip_data.len() - 8]
+            );
+
+            let mut decoder = zlib::Decoder::new(&zlib_data[..]).unwrap();
+            let mut buf = Vec::new();
+            decoder.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, plain);
+        }
+    }
+
+    #[test]
+    fn zlib_to_gzip_works() {
+        let plain = plain_data();
+        let options = zlib::EncodeOptions::new().block_size(1024);
+        let mut encoder = zlib::Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let mut zlib_data = encoder.finish().into_result().unwrap();
+        zlib_data.extend_from_slice(b"trailing");
+
+        let header = gzip::HeaderBuilder::new().finish();
+        let mut gzip_data = Vec::new();
+        let mut reader = &zlib_data[..];
+        zlib_to_gzip(&mut reader, &mut gzip_data, &header).unwrap();
+        assert_eq!(reader, b"trailing");
+
+        let mut decoder = gzip::Decoder::new(&gzip_data[..]).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, plain);
+    }
+
+    #[test]
+    fn recompression_works() {
+        let plain = plain_data();
+        let header = gzip::HeaderBuilder::new()
+            .modification_time(123)
+            .comment_str("foo")
+            .unwrap()
+            .extra_field(gzip::ExtraField {
+                subfields: vec![gzip::ExtraSubField {
+                    id: *b"ab",
+                    data: b"bar".to_vec(),
+                }],
+            })
+            .finish();
+        let options = gzip::EncodeOptions::new().no_compression().header(header);
+        let mut encoder = gzip::Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let gzip_data = encoder.finish().into_result().unwrap();
+
+        let mut recompressed = Vec::new();
+        let options = CompressionOptions::new().level(lz77::CompressionLevel::Fast);
+        let header = recompress_gzip(&gzip_data[..], &mut recompressed, options).unwrap();
+        assert_eq!(header.modification_time(), 123);
+        assert!(recompressed.len() < gzip_data.len());
+
+        let mut decoder = gzip::Decoder::new(&recompressed[..]).unwrap();
+        assert_eq!(decoder.header().modification_time(), 123);
+        assert_eq!(decoder.header().comment(), header.comment());
+        assert_eq!(decoder.header().extra_field(), header.extra_field());
+        assert_eq!(
+            decoder.header().compression_level(),
+            gzip::CompressionLevel::Fastest
+        );
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, plain);
+
+        let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let zlib_data = encoder.finish().into_result().unwrap();
+
+        let mut recompressed = Vec::new();
+        let options = CompressionOptions::new().level(lz77::CompressionLevel::None);
+        recompress_zlib(&zlib_data[..], &mut recompressed, options).unwrap();
+        assert!(recompressed.len() > zlib_data.len());
+
+        let mut decoder = zlib::Decoder::new(&recompressed[..]).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, plain);
+    }
+
+    #[test]
+    fn corrupted_checksum_is_rejected() {
+        let mut encoder = deflate::Encoder::with_options(Vec::new(), EncodeOptions::new());
+        encoder.write_all(b"Hello World!").unwrap();
+        let body = encoder.finish().into_result().unwrap();
+
+        let mut zlib_data = vec![0x78, 0x9c];
+        zlib_data.extend_from_slice(&body);
+        zlib_data.extend_from_slice(&[0, 0, 0, 0]);
+        let header = gzip::HeaderBuilder::new().finish();
+        let error = zlib_to_gzip(&zlib_data[..], Vec::new(), &header).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,7 +1,394 @@
-#[cfg(all(test, feature = "no_std"))]
-use core2::io::{self, Read};
-#[cfg(all(test, not(feature = "no_std")))]
-use std::io::{self, Read};
+use crate::error::{Error, ErrorKind};
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, vec, vec::Vec};
+#[cfg(feature = "no_std")]
+use core::{cmp, fmt};
+#[cfg(feature = "no_std")]
+use core2::io::{self, BufRead, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp, fmt,
+    io::{self, BufRead, Read, Write},
+};
+
+/// Reserves the capacity for `additional` more bytes, returning an [`ErrorKind::OutOfMemory`] error on failure.
+pub(crate) fn try_reserve(buf: &mut Vec<u8>, additional: usize) -> io::Result<()> {
+    buf.try_reserve(additional).map_err(|_| {
+        Error::new(ErrorKind::OutOfMemory, "Failed to allocate a buffer")
+            .with_value(additional as u64)
+            .into()
+    })
+}
+
+/// The maximum ratio of the decompressed size to the compressed size of a DEFLATE stream
+/// (a match of 258 bytes can be encoded in 2 bits).
+pub(crate) const MAX_EXPANSION_RATIO: usize = 1032;
+
+/// Returns the capacity reserved for the decompressed data when their size is not known.
+pub(crate) fn decompressed_capacity_hint(compressed_len: usize) -> usize {
+    compressed_len.saturating_mul(4)
+}
+
+/// Reads all the data from `reader` into a vector whose capacity is reserved in advance.
+pub(crate) fn read_to_vec<R: Read>(mut reader: R, capacity: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    try_reserve(&mut buf, capacity)?;
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// The error returned when an encoder is used after it has been finished.
+pub(crate) fn finished_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "The encoder has already been finished",
+    )
+}
+
+pub(crate) fn pipelined_checksum_state_error() -> io::Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "The state of an encoder with a pipelined checksum can not be taken",
+    )
+    .into()
+}
+
+/// Remembers the progress of finishing an encoder, so that `try_finish` can be called repeatedly.
+///
+/// The end of the stream is written only once, and any error while writing it is permanent.
+/// Only the final flush of the inner stream is retried if it fails with `Interrupted` or `WouldBlock`.
+#[derive(Debug, Default)]
+pub(crate) struct FinishState {
+    end_written: bool,
+    result: Option<Result<(), io::ErrorKind>>,
+}
+impl FinishState {
+    pub fn is_finished(&self) -> bool {
+        self.end_written || self.result.is_some()
+    }
+
+    /// Returns the result of the previous attempt if it is final.
+    pub fn previous_result(&self) -> Option<io::Result<()>> {
+        self.result.map(|result| {
+            result.map_err(|kind| {
+                io::Error::new(kind, "The previous attempt to finish the encoder failed")
+            })
+        })
+    }
+
+    /// Records the result of writing the end of the stream.
+    pub fn record_end(&mut self, result: &io::Result<()>) {
+        match result {
+            Ok(()) => self.end_written = true,
+            Err(e) => self.result = Some(Err(e.kind())),
+        }
+    }
+
+    /// Records the result of the final flush of the inner stream.
+    pub fn record_flush(&mut self, result: &io::Result<()>) {
+        match result {
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+                ) => {}
+            _ => self.result = Some(result.as_ref().map(|_| ()).map_err(|e| e.kind())),
+        }
+    }
+}
+
+/// Calls `reader.fill_buf()`, retrying on `Interrupted` errors.
+pub(crate) fn fill_buf<R: BufRead>(reader: &mut R) -> io::Result<&[u8]> {
+    // The buffer can't be returned from within the retry loop (the borrow checker rejects it),
+    // so it is fetched again once the reader has succeeded (which is cheap for a filled buffer).
+    retry_interrupted(|| reader.fill_buf().map(|_| ()))?;
+    reader.fill_buf()
+}
+
+/// Calls `f` until it returns anything other than an `Interrupted` error.
+pub(crate) fn retry_interrupted<T, F>(mut f: F) -> io::Result<T>
+where
+    F: FnMut() -> io::Result<T>,
+{
+    loop {
+        match f() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
+
+/// Reads up to `size` bytes from `reader` (fewer only if the end of the stream is reached).
+pub(crate) fn read_prefix<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; size];
+    let mut offset = 0;
+    while offset < size {
+        match retry_interrupted(|| reader.read(&mut buf[offset..]))? {
+            0 => break,
+            n => offset += n,
+        }
+    }
+    buf.truncate(offset);
+    Ok(buf)
+}
+
+/// Discards up to `n` bytes of `reader` (fewer only if the end of the stream is reached) through its internal buffer.
+///
+/// Returns the number of the discarded bytes.
+pub(crate) fn skip<R: BufRead + ?Sized>(reader: &mut R, n: u64) -> io::Result<u64> {
+    let mut skipped = 0;
+    while skipped < n {
+        let available = retry_interrupted(|| reader.fill_buf().map(|buf| buf.len()))?;
+        if available == 0 {
+            break;
+        }
+        let amt = cmp::min(available as u64, n - skipped) as usize;
+        reader.consume(amt);
+        skipped += amt as u64;
+    }
+    Ok(skipped)
+}
+
+/// A decoder which can be rewound to the start of its stream (see [`seek`]).
+pub(crate) trait Rewind: BufRead {
+    /// Returns the number of the decoded bytes read so far.
+    fn position(&self) -> u64;
+
+    /// Rewinds the decoder and its inner stream to the start of the (compressed) stream.
+    fn rewind(&mut self) -> io::Result<()>;
+}
+
+/// Seeks `decoder` to `pos` in the decoded data, as `Seek::seek` of a decoder.
+///
+/// Seeking forward decodes and discards the data, and seeking backward rewinds the decoder and then seeks forward.
+/// The position is clamped to the end of the stream.
+pub(crate) fn seek<D: Rewind>(decoder: &mut D, pos: io::SeekFrom) -> io::Result<u64> {
+    let target = match pos {
+        io::SeekFrom::Start(n) => Some(n),
+        io::SeekFrom::Current(n) => decoder.position().checked_add_signed(n),
+        io::SeekFrom::End(n) => {
+            skip(decoder, u64::MAX)?;
+            decoder.position().checked_add_signed(n)
+        }
+    };
+    let target = target.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })?;
+    if target < decoder.position() {
+        decoder.rewind()?;
+    }
+    let n = target - decoder.position();
+    skip(decoder, n)?;
+    Ok(decoder.position())
+}
+
+/// Returns the position of the start of a stream of which `read` bytes have been read from `inner`.
+pub(crate) fn stream_start<S: io::Seek>(inner: &mut S, read: u64) -> io::Result<u64> {
+    // `Seek::stream_position` is not available in `core2`
+    #[allow(clippy::seek_from_current)]
+    let position = inner.seek(io::SeekFrom::Current(0))?;
+    position.checked_sub(read).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The inner stream has been moved before the start of the compressed stream",
+        )
+    })
+}
+
+/// Reads into `bufs` in order, as `Read::read_vectored` of a decoder.
+///
+/// The first non-empty buffer is passed to `reader.read`, and the following ones only while
+/// `has_buffered_data` tells that decoded data are available without reading the inner stream
+/// (so an I/O error never discards the bytes already copied to the preceding buffers).
+#[cfg(not(feature = "no_std"))]
+pub(crate) fn read_vectored<R, F>(
+    reader: &mut R,
+    bufs: &mut [io::IoSliceMut],
+    has_buffered_data: F,
+) -> io::Result<usize>
+where
+    R: Read,
+    F: Fn(&R) -> bool,
+{
+    let mut total = 0;
+    for buf in bufs.iter_mut().filter(|buf| !buf.is_empty()) {
+        if total > 0 && !has_buffered_data(reader) {
+            break;
+        }
+        let size = reader.read(buf)?;
+        total += size;
+        if size < buf.len() {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+/// A reader which yields the bytes of `prefix` before those of `inner`.
+#[derive(Debug)]
+pub(crate) struct PrefixedReader<R> {
+    prefix: Vec<u8>,
+    offset: usize,
+    inner: R,
+}
+impl<R> PrefixedReader<R> {
+    pub fn new(prefix: Vec<u8>, inner: R) -> Self {
+        PrefixedReader {
+            prefix,
+            offset: 0,
+            inner,
+        }
+    }
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+impl<R: Read> Read for PrefixedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset < self.prefix.len() {
+            let size = cmp::min(buf.len(), self.prefix.len() - self.offset);
+            buf[..size].copy_from_slice(&self.prefix[self.offset..][..size]);
+            self.offset += size;
+            Ok(size)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+/// A reader (or writer) which counts the number of bytes read from (or written to) `inner`.
+#[derive(Debug)]
+pub(crate) struct Counter<T> {
+    inner: T,
+    count: u64,
+}
+impl<T> Counter<T> {
+    pub fn new(inner: T) -> Self {
+        Counter { inner, count: 0 }
+    }
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    /// Adds `n` to the count (for the bytes consumed without using `Read`).
+    pub fn add(&mut self, n: usize) {
+        self.count += n as u64;
+    }
+    pub fn reset_count(&mut self) {
+        self.count = 0;
+    }
+    pub fn as_inner_ref(&self) -> &T {
+        &self.inner
+    }
+    pub fn as_inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+impl<T: Read> Read for Counter<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.add(size);
+        Ok(size)
+    }
+}
+impl<T: Write> Write for Counter<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = self.inner.write(buf)?;
+        self.add(size);
+        Ok(size)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A progress callback which is invoked every `interval` input bytes.
+pub(crate) struct ProgressHook {
+    interval: u64,
+    next: u64,
+    finished: bool,
+    callback: Box<dyn FnMut(u64, u64) + Send + Sync>,
+}
+impl ProgressHook {
+    pub fn new<F>(interval: u64, callback: F) -> Self
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        ProgressHook {
+            interval,
+            next: interval,
+            finished: false,
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Invokes the callback if `interval` bytes have been processed since the last invocation.
+    pub fn notify(&mut self, bytes_in: u64, bytes_out: u64) {
+        if bytes_in >= self.next && !self.finished {
+            (self.callback)(bytes_in, bytes_out);
+            self.next = bytes_in.saturating_add(self.interval.max(1));
+        }
+    }
+
+    /// Makes the callback be invoked again as if no bytes have been processed.
+    pub fn restart(&mut self) {
+        self.next = self.interval;
+        self.finished = false;
+    }
+
+    /// Invokes the callback with the final counts (only once).
+    pub fn finish(&mut self, bytes_in: u64, bytes_out: u64) {
+        if !self.finished {
+            (self.callback)(bytes_in, bytes_out);
+            self.finished = true;
+        }
+    }
+}
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgressHook")
+            .field("interval", &self.interval)
+            .field("next", &self.next)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A check which tells whether the processing should be cancelled.
+pub(crate) struct CancellationCheck(Box<dyn Fn() -> bool + Send + Sync>);
+impl CancellationCheck {
+    pub fn new<F>(is_cancelled: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        CancellationCheck(Box::new(is_cancelled))
+    }
+
+    /// Returns an `ErrorKind::Cancelled` error if the processing should be cancelled.
+    pub fn check(&self) -> io::Result<()> {
+        if (self.0)() {
+            Err(Error::new(ErrorKind::Cancelled, "The processing was cancelled").into())
+        } else {
+            Ok(())
+        }
+    }
+}
+impl fmt::Debug for CancellationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CancellationCheck").finish_non_exhaustive()
+    }
+}
 
 #[cfg(test)]
 pub struct WouldBlockReader<R> {
@@ -37,6 +424,91 @@ impl<R: Read> Read for WouldBlockReader<R> {
     }
 }
 
+/// A writer which fails with a `WouldBlock` error every other call.
+#[cfg(test)]
+pub struct WouldBlockWriter<W> {
+    inner: W,
+    do_block: bool,
+}
+#[cfg(test)]
+impl<W: Write> WouldBlockWriter<W> {
+    pub fn new(inner: W) -> Self {
+        WouldBlockWriter {
+            inner,
+            do_block: false,
+        }
+    }
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+#[cfg(test)]
+impl<W: Write> Write for WouldBlockWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.do_block = !self.do_block;
+        if self.do_block {
+            Err(io::Error::new(io::ErrorKind::WouldBlock, "Would block"))
+        } else {
+            let len = cmp::min(buf.len(), 100);
+            self.inner.write(&buf[..len])
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.do_block = !self.do_block;
+        if self.do_block {
+            Err(io::Error::new(io::ErrorKind::WouldBlock, "Would block"))
+        } else {
+            self.inner.flush()
+        }
+    }
+}
+
+/// A reader (or writer) which fails with an `Interrupted` error every other call.
+#[cfg(test)]
+pub struct InterruptingIo<T> {
+    inner: T,
+    interrupt: bool,
+}
+#[cfg(test)]
+impl<T> InterruptingIo<T> {
+    pub fn new(inner: T) -> Self {
+        InterruptingIo {
+            inner,
+            interrupt: false,
+        }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    fn interrupt(&mut self) -> io::Result<()> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            Err(io::Error::new(io::ErrorKind::Interrupted, "Interrupted"))
+        } else {
+            Ok(())
+        }
+    }
+}
+#[cfg(test)]
+impl<T: Read> Read for InterruptingIo<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt()?;
+        let len = cmp::min(buf.len(), 7);
+        self.inner.read(&mut buf[..len])
+    }
+}
+#[cfg(test)]
+impl<T: Write> Write for InterruptingIo<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.interrupt()?;
+        self.inner.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.interrupt()?;
+        self.inner.flush()
+    }
+}
+
 #[cfg(test)]
 pub fn nb_read_to_end<R: Read>(mut reader: R) -> io::Result<Vec<u8>> {
     let mut buf = vec![0; 1024];
@@ -0,0 +1,334 @@
+//! Decoders which accept compressed bytes via `Write` and forward the decoded bytes to an inner writer (push model).
+//!
+//! The decoders of the [`deflate`], [`zlib`](crate::zlib) and [`gzip`](crate::gzip) modules
+//! implement `Read` and pull the compressed bytes from an inner reader.
+//! [`Decoder`] works in the opposite direction, which is convenient when the compressed data arrive
+//! as a sequence of events (e.g., the frames received by a server).
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::Write;
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::Write;
+//! use libflate::gzip::Encoder;
+//! use libflate::push::Format;
+//! use libflate::write::Decoder;
+//!
+//! // Encoding
+//! let mut encoder = Encoder::new(Vec::new()).unwrap();
+//! encoder.write_all(b"Hello World!").unwrap();
+//! let encoded_data = encoder.finish().into_result().unwrap();
+//!
+//! // Decoding
+//! let mut decoder = Decoder::new(Vec::new(), Format::Gzip);
+//! for chunk in encoded_data.chunks(5) {
+//!     decoder.write_all(chunk).unwrap();
+//! }
+//! let decoded_data = decoder.finish().into_result().unwrap();
+//!
+//! assert_eq!(decoded_data, b"Hello World!");
+//! ```
+use crate::deflate;
+use crate::error::{Error, ErrorKind};
+use crate::finish::Finish;
+use crate::push::{self, Format, Status};
+#[cfg(feature = "no_std")]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "no_std")]
+use core::cmp;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Write};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp,
+    io::{self, Write},
+};
+
+/// Decoder which accepts compressed data via `Write` and writes the decoded data to the inner writer.
+#[derive(Debug)]
+pub struct Decoder<W> {
+    inner: W,
+    decoder: push::Decoder,
+    buf: Vec<u8>,
+}
+impl<W> Decoder<W>
+where
+    W: Write,
+{
+    /// Makes a new decoder instance for `format`.
+    ///
+    /// `inner` is the writer to which the decoded data are written.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::push::Format;
+    /// use libflate::write::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::new(Vec::new(), Format::Deflate);
+    /// decoder.write_all(&encoded_data).unwrap();
+    /// assert_eq!(decoder.finish().into_result().unwrap(), b"Hello World!");
+    /// ```
+    pub fn new(inner: W, format: Format) -> Self {
+        Self::with_buffer_size(inner, format, deflate::DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Makes a new decoder instance whose output buffer is `size` bytes (at least `1`).
+    ///
+    /// The decoded data are written to `inner` in chunks of up to `size` bytes.
+    /// The default size is [`deflate::DEFAULT_BUFFER_SIZE`].
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::push::Format;
+    /// use libflate::write::Decoder;
+    ///
+    /// let encoded_data = [243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0];
+    /// let mut decoder = Decoder::with_buffer_size(Vec::new(), Format::Deflate, 4);
+    /// decoder.write_all(&encoded_data).unwrap();
+    /// assert_eq!(decoder.finish().into_result().unwrap(), b"Hello World!");
+    /// ```
+    pub fn with_buffer_size(inner: W, format: Format, size: usize) -> Self {
+        Decoder {
+            inner,
+            decoder: push::Decoder::new(format),
+            buf: vec![0; cmp::max(size, 1)],
+        }
+    }
+
+    /// Returns `true` if the end of the compressed stream has been reached, `false` otherwise.
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Decoder`, returning the underlying writer.
+    ///
+    /// Unlike [`Decoder::finish`], this method does not check whether the stream has been terminated properly.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Returns the number of the (compressed) bytes written to this decoder and consumed.
+    pub fn total_in(&self) -> u64 {
+        self.decoder.total_in()
+    }
+
+    /// Returns the number of the decoded bytes written to the inner stream.
+    pub fn total_out(&self) -> u64 {
+        self.decoder.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder (excluding the inner stream).
+    ///
+    /// This includes the output buffer (see [`Decoder::with_buffer_size`]).
+    /// See [`push::Decoder::memory_usage`] for the details of the rest.
+    pub fn memory_usage(&self) -> usize {
+        self.decoder.memory_usage() + self.buf.capacity()
+    }
+
+    /// Finishes the decoding, returning the underlying writer.
+    ///
+    /// The trailer of the stream (if any) is validated while the data are written,
+    /// so this method only checks that the end of the stream has been reached
+    /// and flushes the inner writer.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{self, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{self, Write};
+    /// use libflate::push::Format;
+    /// use libflate::write::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    ///
+    /// // The ADLER32 trailer is missing
+    /// let mut decoder = Decoder::new(Vec::new(), Format::Zlib);
+    /// decoder.write_all(&encoded_data[..16]).unwrap();
+    /// let error = decoder.finish().into_result().unwrap_err();
+    /// assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    /// ```
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        if let Err(e) = self.decode(&[]) {
+            return Finish::new(self.inner, Some(e));
+        }
+        if !self.decoder.is_finished() {
+            let e = Error::new(
+                ErrorKind::UnexpectedEof,
+                "The compressed stream ended before its end was reached",
+            );
+            return Finish::new(self.inner, Some(e.into()));
+        }
+        match self.inner.flush() {
+            Ok(()) => Finish::new(self.inner, None),
+            Err(e) => Finish::new(self.inner, Some(e)),
+        }
+    }
+
+    fn decode(&mut self, input: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+        loop {
+            let progress = self.decoder.decode(&input[consumed..], &mut self.buf)?;
+            consumed += progress.consumed;
+            self.inner.write_all(&self.buf[..progress.produced])?;
+            if progress.status == Status::StreamEnd || progress.produced < self.buf.len() {
+                return Ok(consumed);
+            }
+        }
+    }
+}
+impl<W> Write for Decoder<W>
+where
+    W: Write,
+{
+    /// Decodes `buf` and writes the decoded data to the inner writer.
+    ///
+    /// Bytes following the end of the compressed stream are not consumed,
+    /// so `write_all` fails if they are written.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.decode(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deflate, gzip, zlib};
+
+    fn encode(format: Format, plain: &[u8]) -> Vec<u8> {
+        match format {
+            Format::Deflate => {
+                let mut encoder = deflate::Encoder::new(Vec::new());
+                encoder.write_all(plain).unwrap();
+                encoder.finish().into_result().unwrap()
+            }
+            Format::Zlib => {
+                let mut encoder = zlib::Encoder::new(Vec::new()).unwrap();
+                encoder.write_all(plain).unwrap();
+                encoder.finish().into_result().unwrap()
+            }
+            Format::Gzip => {
+                let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+                encoder.write_all(plain).unwrap();
+                encoder.finish().into_result().unwrap()
+            }
+        }
+    }
+
+    #[test]
+    fn decode_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let encoded = encode(format, &plain);
+            for &chunk_size in &[1, 100, encoded.len()] {
+                let mut decoder = Decoder::new(Vec::new(), format);
+                for chunk in encoded.chunks(chunk_size) {
+                    decoder.write_all(chunk).unwrap();
+                }
+                assert!(decoder.is_finished());
+                assert_eq!(decoder.finish().into_result().unwrap(), plain);
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_size_works() {
+        struct ChunkRecorder(Vec<usize>);
+        impl Write for ChunkRecorder {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let encoded = encode(Format::Zlib, &plain);
+        for &size in &[0, 100, 64 * 1024] {
+            let mut decoder =
+                Decoder::with_buffer_size(ChunkRecorder(Vec::new()), Format::Zlib, size);
+            decoder.write_all(&encoded).unwrap();
+            let chunks = decoder.finish().into_result().unwrap().0;
+            assert_eq!(chunks.iter().sum::<usize>(), plain.len());
+            assert_eq!(chunks.iter().max(), Some(&cmp::max(size, 1)));
+        }
+    }
+
+    #[test]
+    fn truncated_stream_is_rejected() {
+        let encoded = encode(Format::Gzip, b"Hello World!");
+        let mut decoder = Decoder::new(Vec::new(), Format::Gzip);
+        decoder.write_all(&encoded[..encoded.len() - 1]).unwrap();
+        assert!(!decoder.is_finished());
+
+        let (decoded, error) = decoder.finish().unwrap();
+        assert_eq!(decoded, b"Hello World!");
+        assert_eq!(error.unwrap().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn corrupted_trailer_is_rejected() {
+        let mut encoded = encode(Format::Gzip, b"Hello World!");
+        let last = encoded.len() - 5;
+        encoded[last] ^= 1; // CRC32
+        let mut decoder = Decoder::new(Vec::new(), Format::Gzip);
+        let error = decoder.write_all(&encoded).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn trailing_bytes_are_not_consumed() {
+        let mut encoded = encode(Format::Zlib, b"Hello World!");
+        let len = encoded.len();
+        encoded.extend_from_slice(b"garbage");
+
+        let mut decoder = Decoder::new(Vec::new(), Format::Zlib);
+        assert_eq!(decoder.write(&encoded).unwrap(), len);
+        assert_eq!(decoder.write(b"garbage").unwrap(), 0);
+        assert_eq!(decoder.finish().into_result().unwrap(), b"Hello World!");
+    }
+    #[test]
+    fn total_in_and_total_out_work() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        for &format in &[Format::Deflate, Format::Zlib, Format::Gzip] {
+            let encoded = encode(format, &plain);
+            let mut decoder = Decoder::new(Vec::new(), format);
+            for chunk in encoded.chunks(100) {
+                decoder.write_all(chunk).unwrap();
+            }
+            assert_eq!(decoder.total_in(), encoded.len() as u64);
+            assert_eq!(decoder.total_out(), plain.len() as u64);
+        }
+    }
+}
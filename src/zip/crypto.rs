@@ -0,0 +1,190 @@
+//! The traditional PKWARE encryption (a.k.a. ZipCrypto).
+//!
+//! See the section 6.1 of APPNOTE.TXT.
+//! The cipher is known to be weak (it can be broken by a known-plaintext attack with a dozen bytes),
+//! so it should only be used to exchange archives with legacy tools.
+use crate::checksum;
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read, Write};
+#[cfg(not(feature = "no_std"))]
+use std::io::{self, Read, Write};
+
+/// The size of the encryption header preceding the encrypted data of an entry.
+pub(super) const ENCRYPTION_HEADER_SIZE: usize = 12;
+
+/// The maximum number of the bytes encrypted at once by [`Encrypt`].
+const MAX_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Updates `crc` (without the pre- and post-conditioning of CRC-32) with the byte `b`.
+fn crc32_update(crc: u32, b: u8) -> u32 {
+    let mut crc32 = checksum::Crc32::from_value(!crc);
+    crc32.update(&[b]);
+    !crc32.value()
+}
+
+/// The internal state of the cipher.
+#[derive(Clone)]
+pub(super) struct Keys([u32; 3]);
+impl Keys {
+    pub fn new(password: &[u8]) -> Self {
+        let mut keys = Keys([0x1234_5678, 0x2345_6789, 0x3456_7890]);
+        for &b in password {
+            keys.update(b);
+        }
+        keys
+    }
+
+    fn update(&mut self, plain: u8) {
+        let k = &mut self.0;
+        k[0] = crc32_update(k[0], plain);
+        k[1] = k[1]
+            .wrapping_add(k[0] & 0xFF)
+            .wrapping_mul(134_775_813)
+            .wrapping_add(1);
+        k[2] = crc32_update(k[2], (k[1] >> 24) as u8);
+    }
+
+    fn stream_byte(&self) -> u8 {
+        let temp = (self.0[2] | 2) as u16;
+        (temp.wrapping_mul(temp ^ 1) >> 8) as u8
+    }
+
+    pub fn decrypt(&mut self, buf: &mut [u8]) {
+        for b in buf {
+            *b ^= self.stream_byte();
+            self.update(*b);
+        }
+    }
+
+    pub fn encrypt(&mut self, buf: &mut [u8]) {
+        for b in buf {
+            let plain = *b;
+            *b ^= self.stream_byte();
+            self.update(plain);
+        }
+    }
+}
+impl core::fmt::Debug for Keys {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "Keys(_)")
+    }
+}
+
+/// A reader which decrypts the data read from the inner reader if `keys` is set.
+#[derive(Debug)]
+pub(super) struct Decrypt<R> {
+    inner: R,
+    keys: Option<Keys>,
+}
+impl<R: Read> Decrypt<R> {
+    pub fn new(inner: R, keys: Option<Keys>) -> Self {
+        Decrypt { inner, keys }
+    }
+}
+impl<R: Read> Read for Decrypt<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        if let Some(keys) = self.keys.as_mut() {
+            keys.decrypt(&mut buf[..size]);
+        }
+        Ok(size)
+    }
+}
+
+/// A writer which encrypts the data written to the inner writer if `keys` is set.
+#[derive(Debug)]
+pub(super) struct Encrypt<W> {
+    inner: W,
+    keys: Option<Keys>,
+    buf: Vec<u8>,
+}
+impl<W: Write> Encrypt<W> {
+    pub fn new(inner: W) -> Self {
+        Encrypt {
+            inner,
+            keys: None,
+            buf: Vec::new(),
+        }
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    pub fn set_keys(&mut self, keys: Option<Keys>) {
+        self.keys = keys;
+    }
+
+    pub fn as_inner_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+impl<W: Write> Write for Encrypt<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let keys = match self.keys.as_mut() {
+            None => return self.inner.write(buf),
+            Some(keys) => keys,
+        };
+        // The keys depend on the plain data, so the encrypted data must be written entirely
+        let buf = &buf[..core::cmp::min(buf.len(), MAX_CHUNK_SIZE)];
+        self.buf.clear();
+        self.buf.extend_from_slice(buf);
+        keys.encrypt(&mut self.buf);
+        self.inner.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Makes a random encryption header whose last byte is `check_byte`.
+#[cfg(not(feature = "no_std"))]
+pub(super) fn encryption_header(check_byte: u8) -> [u8; ENCRYPTION_HEADER_SIZE] {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    // `RandomState` is seeded randomly by the OS (and made distinct for each instance)
+    let state = RandomState::new();
+    let mut header = [0; ENCRYPTION_HEADER_SIZE];
+    for (i, chunk) in header.chunks_mut(8).enumerate() {
+        let mut hasher = state.build_hasher();
+        hasher.write_usize(i);
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes()[..chunk.len()]);
+    }
+    header[ENCRYPTION_HEADER_SIZE - 1] = check_byte;
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_update_works() {
+        let mut crc = !0;
+        for &b in b"abcde" {
+            crc = crc32_update(crc, b);
+        }
+        assert_eq!(!crc, 0x8587_D865);
+    }
+
+    #[test]
+    fn keys_work() {
+        let plain = b"Hello World!";
+        let mut encrypted = *plain;
+        Keys::new(b"password").encrypt(&mut encrypted);
+        assert_ne!(&encrypted, plain);
+
+        let mut decrypted = encrypted;
+        Keys::new(b"password").decrypt(&mut decrypted);
+        assert_eq!(&decrypted, plain);
+
+        let mut decrypted = encrypted;
+        Keys::new(b"Password").decrypt(&mut decrypted);
+        assert_ne!(&decrypted, plain);
+    }
+}
@@ -0,0 +1,291 @@
+//! The reader and writer of the ZIP archive format.
+//!
+//! The ZIP format is defined in [APPNOTE.TXT](https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT).
+//! The entries compressed with DEFLATE (and the stored ones) are supported,
+//! as well as the Zip64 extensions for the archives larger than 4 GiB or with more than 65535 entries.
+//!
+//! The entries encrypted with the traditional PKWARE encryption (a.k.a. ZipCrypto) can be read
+//! via [`Archive::by_index_decrypt`] (and written with [`EntryOptions::password`]).
+//! Note that the encryption is weak: it can be broken by a known-plaintext attack within hours,
+//! so it only serves the compatibility with legacy tools, and doesn't protect the data.
+//!
+//! The archives read from non-seekable streams (e.g., pipes and sockets) can be read entry by entry
+//! with [`StreamReader`], which parses the local headers sequentially instead of the central directory.
+//!
+//! # Examples
+//! ```
+//! #[cfg(feature = "no_std")]
+//! use core2::io::{Cursor, Read, Write};
+//! #[cfg(not(feature = "no_std"))]
+//! use std::io::{Cursor, Read, Write};
+//! use libflate::zip::{Archive, EntryOptions, Writer};
+//!
+//! // Writing
+//! let mut writer = Writer::new(Vec::new());
+//! writer.start_entry("hello.txt", EntryOptions::new()).unwrap();
+//! writer.write_all(b"Hello World!").unwrap();
+//! let archive_data = writer.finish().into_result().unwrap();
+//!
+//! // Reading
+//! let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+//! assert_eq!(archive.len(), 1);
+//! assert_eq!(archive.entries()[0].name(), b"hello.txt");
+//!
+//! let mut data = Vec::new();
+//! archive.by_index(0).unwrap().read_to_end(&mut data).unwrap();
+//! assert_eq!(data, b"Hello World!");
+//! ```
+pub use self::read::{Archive, EntryReader};
+pub use self::stream::{StreamEntry, StreamReader};
+pub use self::write::{EntryOptions, Writer};
+
+mod crypto;
+mod read;
+mod stream;
+mod write;
+
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4B50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4B50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4B50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4B50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4B50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x0706_4B50;
+
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: usize = 56;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE: usize = 20;
+
+/// The header ID of the Zip64 extended information extra field.
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+
+const FLAG_ENCRYPTED: u16 = 1 << 0;
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_STRONG_ENCRYPTION: u16 = 1 << 6;
+const FLAG_UTF8: u16 = 1 << 11;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// Returns the data of the first field whose header ID is `id` in `extra_field`.
+///
+/// The extra field consists of (header ID, data size, data) tuples.
+fn find_extra_field(extra_field: &[u8], id: u16) -> Option<&[u8]> {
+    let mut rest = extra_field;
+    while rest.len() >= 4 {
+        let field_id = u16::from_le_bytes([rest[0], rest[1]]);
+        let size = usize::from(u16::from_le_bytes([rest[2], rest[3]]));
+        let data = rest.get(4..4 + size)?;
+        if field_id == id {
+            return Some(data);
+        }
+        rest = &rest[4 + size..];
+    }
+    None
+}
+
+/// The compression method of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompressionMethod {
+    /// The data is stored as is.
+    Stored,
+
+    /// The data is compressed with DEFLATE.
+    Deflated,
+
+    /// The other (unsupported) methods.
+    Unknown(u16),
+}
+impl CompressionMethod {
+    fn from_u16(x: u16) -> Self {
+        match x {
+            METHOD_STORED => CompressionMethod::Stored,
+            METHOD_DEFLATED => CompressionMethod::Deflated,
+            x => CompressionMethod::Unknown(x),
+        }
+    }
+}
+
+/// The MS-DOS date and time, in which the modification times of the entries are recorded.
+///
+/// The resolution of the time is two seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DosDateTime {
+    date: u16,
+    time: u16,
+}
+impl DosDateTime {
+    /// Makes a new instance.
+    ///
+    /// The year is clamped to the range from 1980 to 2107, and the second is rounded down to an even number.
+    /// The other values are not validated.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::zip::DosDateTime;
+    ///
+    /// let time = DosDateTime::new(2024, 12, 31, 23, 59, 59);
+    /// assert_eq!(time.year(), 2024);
+    /// assert_eq!(time.second(), 58);
+    /// ```
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Self {
+        let year = year.clamp(1980, 2107) - 1980;
+        let date = (year << 9) | (u16::from(month & 0b1111) << 5) | u16::from(day & 0b1_1111);
+        let time = (u16::from(hour & 0b1_1111) << 11)
+            | (u16::from(minute & 0b11_1111) << 5)
+            | u16::from((second / 2) & 0b1_1111);
+        DosDateTime { date, time }
+    }
+
+    /// Makes a new instance from the raw MS-DOS `date` and `time` fields.
+    pub fn from_raw(date: u16, time: u16) -> Self {
+        DosDateTime { date, time }
+    }
+
+    /// Returns the raw MS-DOS date field.
+    pub fn raw_date(&self) -> u16 {
+        self.date
+    }
+
+    /// Returns the raw MS-DOS time field.
+    pub fn raw_time(&self) -> u16 {
+        self.time
+    }
+
+    /// Returns the year (1980 to 2107).
+    pub fn year(&self) -> u16 {
+        1980 + (self.date >> 9)
+    }
+
+    /// Returns the month (1 to 12 if valid).
+    pub fn month(&self) -> u8 {
+        ((self.date >> 5) & 0b1111) as u8
+    }
+
+    /// Returns the day of the month (1 to 31 if valid).
+    pub fn day(&self) -> u8 {
+        (self.date & 0b1_1111) as u8
+    }
+
+    /// Returns the hour (0 to 23 if valid).
+    pub fn hour(&self) -> u8 {
+        (self.time >> 11) as u8
+    }
+
+    /// Returns the minute (0 to 59 if valid).
+    pub fn minute(&self) -> u8 {
+        ((self.time >> 5) & 0b11_1111) as u8
+    }
+
+    /// Returns the second (an even number from 0 to 58 if valid).
+    pub fn second(&self) -> u8 {
+        ((self.time & 0b1_1111) * 2) as u8
+    }
+}
+
+/// The metadata of an entry, recorded in the central directory of an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    name: Vec<u8>,
+    extra_field: Vec<u8>,
+    comment: Vec<u8>,
+    version_made_by: u16,
+    flags: u16,
+    compression_method: CompressionMethod,
+    modification_time: DosDateTime,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    external_attributes: u32,
+    local_header_offset: u64,
+}
+impl Entry {
+    /// Returns the name of the entry.
+    ///
+    /// The name is encoded in UTF-8 if [`Entry::is_utf8`] returns `true`, otherwise (usually) in CP437.
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    /// Returns the extra field of the entry in the central directory.
+    pub fn extra_field(&self) -> &[u8] {
+        &self.extra_field
+    }
+
+    /// Returns the comment of the entry.
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// Returns `true` if the name and the comment are encoded in UTF-8.
+    pub fn is_utf8(&self) -> bool {
+        self.flags & FLAG_UTF8 != 0
+    }
+
+    /// Returns `true` if the entry is a directory (i.e., the name ends with `/`).
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with(b"/")
+    }
+
+    /// Returns `true` if the data of the entry is encrypted.
+    ///
+    /// The entries encrypted with the traditional PKWARE encryption can be read via [`Archive::by_index_decrypt`].
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// Returns the byte which the last byte of the (decrypted) encryption header should match.
+    fn encryption_check_byte(&self) -> u8 {
+        if self.flags & FLAG_DATA_DESCRIPTOR != 0 {
+            (self.modification_time.raw_time() >> 8) as u8
+        } else {
+            (self.crc32 >> 24) as u8
+        }
+    }
+
+    /// Returns the compression method of the entry.
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.compression_method
+    }
+
+    /// Returns the last modification time of the entry.
+    pub fn modification_time(&self) -> DosDateTime {
+        self.modification_time
+    }
+
+    /// Returns the CRC-32 checksum of the uncompressed data.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Returns the size of the compressed data.
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// Returns the size of the uncompressed data.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Returns the external file attributes of the entry (e.g., the Unix mode in the upper 16 bits).
+    pub fn external_attributes(&self) -> u32 {
+        self.external_attributes
+    }
+
+    /// Returns the Unix file mode of the entry, if the archive was made on Unix.
+    pub fn unix_mode(&self) -> Option<u32> {
+        if self.version_made_by >> 8 == 3 {
+            Some(self.external_attributes >> 16)
+        } else {
+            None
+        }
+    }
+}
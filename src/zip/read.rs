@@ -0,0 +1,732 @@
+use super::crypto::{self, Decrypt};
+use super::{
+    find_extra_field, CompressionMethod, DosDateTime, Entry, CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+    CENTRAL_DIRECTORY_HEADER_SIZE, END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+    END_OF_CENTRAL_DIRECTORY_SIZE, FLAG_STRONG_ENCRYPTION, LOCAL_FILE_HEADER_SIGNATURE,
+    LOCAL_FILE_HEADER_SIZE, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE, ZIP64_EXTRA_FIELD_ID,
+};
+use crate::checksum;
+use crate::deflate;
+use crate::error::{Error, ErrorKind};
+use crate::util;
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "no_std")]
+use core::cmp;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read, Seek, SeekFrom, Take};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp,
+    io::{self, Read, Seek, SeekFrom, Take},
+};
+
+/// The maximum length of the archive comment.
+const MAX_COMMENT_LEN: usize = 0xFFFF;
+
+/// A ZIP archive reader.
+///
+/// The central directory is read when the reader is made,
+/// and then the data of each entry can be read via [`Archive::by_index`].
+#[derive(Debug)]
+pub struct Archive<R> {
+    inner: R,
+    entries: Vec<Entry>,
+    comment: Vec<u8>,
+}
+impl<R> Archive<R>
+where
+    R: Read + Seek,
+{
+    /// Makes a new archive reader, reading the central directory from `inner`.
+    ///
+    /// The Zip64 end of central directory record and extended information extra fields are recognized.
+    /// Multi-disk archives and encrypted central directories are unsupported.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let (eocd, comment) = read_end_of_central_directory(&mut inner)?;
+        let archive_len = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(eocd.offset))?;
+        // `eocd.size` is not trusted beyond the end of the archive
+        let available = cmp::min(eocd.size, archive_len.saturating_sub(eocd.offset));
+        let mut buf = Vec::new();
+        util::try_reserve(&mut buf, available as usize)?;
+        (&mut inner).take(eocd.size).read_to_end(&mut buf)?;
+        if buf.len() as u64 != eocd.size {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "The central directory of the ZIP archive is truncated",
+            )
+            .with_value(buf.len() as u64)
+            .with_expected(eocd.size)
+            .with_position(eocd.offset)
+            .into());
+        }
+
+        // `entry_count` is not trusted until the headers are actually read
+        let mut entries = Vec::with_capacity(cmp::min(
+            eocd.entry_count,
+            (buf.len() / CENTRAL_DIRECTORY_HEADER_SIZE) as u64,
+        ) as usize);
+        let mut fields = Fields::new(&buf, eocd.offset);
+        for _ in 0..eocd.entry_count {
+            entries.push(read_central_directory_header(&mut fields)?);
+        }
+        Ok(Archive {
+            inner,
+            entries,
+            comment,
+        })
+    }
+
+    /// Returns the number of the entries in this archive.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if this archive has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the metadata of the entries in the order of the central directory.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the index of the (first) entry named `name`.
+    pub fn index_of(&self, name: &[u8]) -> Option<usize> {
+        self.entries.iter().position(|e| e.name() == name)
+    }
+
+    /// Returns the comment of this archive.
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// Returns a reader of the (uncompressed) data of the `index`-th entry.
+    ///
+    /// The CRC-32 checksum and the size of the data are verified when the end of the data is reached.
+    /// Encrypted entries can't be read with this method (see [`Archive::by_index_decrypt`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn by_index(&mut self, index: usize) -> io::Result<EntryReader<'_, R>> {
+        self.open(index, None)
+    }
+
+    /// Returns a reader of the (uncompressed) data of the `index`-th entry,
+    /// decrypting the data with `password` if the entry is encrypted with the traditional PKWARE encryption.
+    ///
+    /// The password is checked against a byte of the encryption header, so a wrong password is detected
+    /// with the probability of 255/256 by this method, and otherwise by the CRC-32 checksum of the data.
+    /// Note that the encryption is weak (see the [module documentation](super)).
+    ///
+    /// # Errors
+    ///
+    /// If the password is wrong, this returns an error of the kind [`ErrorKind::InvalidPassword`].
+    /// The entries encrypted with the other methods (e.g., AES) are unsupported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::{Cursor, Read, Write};
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::{Cursor, Read, Write};
+    /// use libflate::zip::{Archive, EntryOptions, Writer};
+    ///
+    /// # #[cfg(not(feature = "no_std"))]
+    /// # {
+    /// let mut writer = Writer::new(Vec::new());
+    /// let options = EntryOptions::new().password(b"secret".to_vec());
+    /// writer.start_entry("hello.txt", options).unwrap();
+    /// writer.write_all(b"Hello World!").unwrap();
+    /// let archive_data = writer.finish().into_result().unwrap();
+    ///
+    /// let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+    /// assert!(archive.entries()[0].is_encrypted());
+    /// assert!(archive.by_index(0).is_err());
+    /// let result = archive
+    ///     .by_index_decrypt(0, b"public")
+    ///     .and_then(|mut reader| reader.read_to_end(&mut Vec::new()));
+    /// assert!(result.is_err());
+    ///
+    /// let mut data = Vec::new();
+    /// archive
+    ///     .by_index_decrypt(0, b"secret")
+    ///     .unwrap()
+    ///     .read_to_end(&mut data)
+    ///     .unwrap();
+    /// assert_eq!(data, b"Hello World!");
+    /// # }
+    /// ```
+    pub fn by_index_decrypt(
+        &mut self,
+        index: usize,
+        password: &[u8],
+    ) -> io::Result<EntryReader<'_, R>> {
+        self.open(index, Some(password))
+    }
+
+    fn open(&mut self, index: usize, password: Option<&[u8]>) -> io::Result<EntryReader<'_, R>> {
+        let entry = &self.entries[index];
+        let keys = match password {
+            _ if !entry.is_encrypted() => None,
+            Some(password) if entry.flags & FLAG_STRONG_ENCRYPTION == 0 => {
+                Some(crypto::Keys::new(password))
+            }
+            Some(_) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "ZIP entries encrypted with the strong encryption are unsupported",
+                )
+                .with_position(entry.local_header_offset)
+                .into())
+            }
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Encrypted ZIP entries can't be read without a password",
+                )
+                .with_position(entry.local_header_offset)
+                .into())
+            }
+        };
+
+        self.inner
+            .seek(SeekFrom::Start(entry.local_header_offset))?;
+        let mut buf = [0; LOCAL_FILE_HEADER_SIZE];
+        self.inner.read_exact(&mut buf)?;
+        let mut fields = Fields::new(&buf, entry.local_header_offset);
+        fields.signature(LOCAL_FILE_HEADER_SIGNATURE)?;
+        fields.skip(22)?;
+        let name_len = fields.u16()?;
+        let extra_field_len = fields.u16()?;
+        // The sizes recorded in the local header may be zeros (if a data descriptor follows the data),
+        // so those in the central directory are used instead.
+        self.inner.seek(SeekFrom::Current(
+            i64::from(name_len) + i64::from(extra_field_len),
+        ))?;
+
+        let data_offset = entry.local_header_offset
+            + LOCAL_FILE_HEADER_SIZE as u64
+            + u64::from(name_len)
+            + u64::from(extra_field_len);
+        let data = match keys {
+            None => Decrypt::new((&mut self.inner).take(entry.compressed_size), None),
+            Some(keys) => {
+                let data_size = entry
+                    .compressed_size
+                    .checked_sub(crypto::ENCRYPTION_HEADER_SIZE as u64)
+                    .ok_or_else(|| {
+                        io::Error::from(
+                            Error::new(
+                                ErrorKind::UnexpectedEof,
+                                "The encryption header of the ZIP entry is truncated",
+                            )
+                            .with_value(entry.compressed_size)
+                            .with_position(data_offset),
+                        )
+                    })?;
+                let mut header = [0; crypto::ENCRYPTION_HEADER_SIZE];
+                self.inner.read_exact(&mut header)?;
+                let mut keys = keys;
+                keys.decrypt(&mut header);
+                let check_byte = header[crypto::ENCRYPTION_HEADER_SIZE - 1];
+                if check_byte != entry.encryption_check_byte() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidPassword,
+                        "The password of the ZIP entry is wrong",
+                    )
+                    .with_value(check_byte)
+                    .with_expected(entry.encryption_check_byte())
+                    .with_position(data_offset)
+                    .into());
+                }
+                Decrypt::new((&mut self.inner).take(data_size), Some(keys))
+            }
+        };
+        let data = match entry.compression_method {
+            CompressionMethod::Stored => EntryData::Stored(data),
+            CompressionMethod::Deflated => {
+                EntryData::Deflated(Box::new(deflate::Decoder::new(data)))
+            }
+            CompressionMethod::Unknown(method) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Compression methods other than DEFLATE(8) and STORED(0) are unsupported",
+                )
+                .with_value(method)
+                .with_position(entry.local_header_offset)
+                .into())
+            }
+        };
+        Ok(EntryReader {
+            entry,
+            data,
+            crc32: checksum::Crc32::new(),
+            size: 0,
+            eos: false,
+        })
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the mutable reference to the inner stream.
+    pub fn as_inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Archive`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[derive(Debug)]
+enum EntryData<'a, R> {
+    Stored(Decrypt<Take<&'a mut R>>),
+    Deflated(Box<deflate::Decoder<Decrypt<Take<&'a mut R>>>>),
+}
+
+/// A reader of the data of an entry, made by [`Archive::by_index`].
+#[derive(Debug)]
+pub struct EntryReader<'a, R> {
+    entry: &'a Entry,
+    data: EntryData<'a, R>,
+    crc32: checksum::Crc32,
+    size: u64,
+    eos: bool,
+}
+impl<R> EntryReader<'_, R> {
+    /// Returns the metadata of the entry.
+    pub fn entry(&self) -> &Entry {
+        self.entry
+    }
+
+    fn verify(&self) -> io::Result<()> {
+        if self.size != self.entry.uncompressed_size {
+            let kind = if self.size < self.entry.uncompressed_size {
+                ErrorKind::UnexpectedEof
+            } else {
+                ErrorKind::InvalidHeader
+            };
+            return Err(
+                Error::new(kind, "The size of the ZIP entry data mismatched")
+                    .with_value(self.size)
+                    .with_expected(self.entry.uncompressed_size)
+                    .into(),
+            );
+        }
+        let crc32 = self.crc32.value();
+        if crc32 != self.entry.crc32 {
+            return Err(Error::new(
+                ErrorKind::ChecksumMismatch,
+                "CRC32 of the ZIP entry data mismatched",
+            )
+            .with_value(crc32)
+            .with_expected(self.entry.crc32)
+            .into());
+        }
+        Ok(())
+    }
+}
+impl<R> Read for EntryReader<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.eos || buf.is_empty() {
+            return Ok(0);
+        }
+        let size = match self.data {
+            EntryData::Stored(ref mut r) => r.read(buf)?,
+            EntryData::Deflated(ref mut r) => r.read(buf)?,
+        };
+        self.crc32.update(&buf[..size]);
+        self.size += size as u64;
+        if size == 0 {
+            self.eos = true;
+            self.verify()?;
+        }
+        Ok(size)
+    }
+}
+
+struct EndOfCentralDirectory {
+    entry_count: u64,
+    size: u64,
+    offset: u64,
+}
+
+fn read_end_of_central_directory<R>(inner: &mut R) -> io::Result<(EndOfCentralDirectory, Vec<u8>)>
+where
+    R: Read + Seek,
+{
+    // The record is at the end of the archive, followed by the comment of variable length
+    let archive_len = inner.seek(SeekFrom::End(0))?;
+    let tail_len = cmp::min(
+        archive_len,
+        (END_OF_CENTRAL_DIRECTORY_SIZE + MAX_COMMENT_LEN) as u64,
+    );
+    let tail_offset = archive_len - tail_len;
+    inner.seek(SeekFrom::Start(tail_offset))?;
+    let mut tail = Vec::new();
+    inner.take(tail_len).read_to_end(&mut tail)?;
+
+    let signature = END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes();
+    let position = (0..(tail.len() + 1).saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+        .rev()
+        .find(|&i| {
+            tail[i..].starts_with(&signature)
+                && END_OF_CENTRAL_DIRECTORY_SIZE
+                    + usize::from(u16::from_le_bytes([tail[i + 20], tail[i + 21]]))
+                    <= tail.len() - i
+        })
+        .ok_or_else(|| {
+            io::Error::from(Error::new(
+                ErrorKind::InvalidHeader,
+                "No end of central directory record is found in the ZIP archive",
+            ))
+        })?;
+
+    let mut fields = Fields::new(&tail[position..], tail_offset + position as u64);
+    fields.skip(4)?;
+    let disk = fields.u16()?;
+    let central_directory_disk = fields.u16()?;
+    let disk_entry_count = fields.u16()?;
+    let entry_count = fields.u16()?;
+    if disk != 0 || central_directory_disk != 0 || disk_entry_count != entry_count {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Multi-disk ZIP archives are unsupported",
+        )
+        .with_value(disk)
+        .with_position(fields.position())
+        .into());
+    }
+    let size = fields.u32()?;
+    let offset = fields.u32()?;
+    let comment_len = fields.u16()?;
+    let comment = fields.bytes(usize::from(comment_len))?.to_vec();
+    if size == u32::MAX || offset == u32::MAX || entry_count == u16::MAX {
+        // The actual values are in the Zip64 record, located by the locator just before this record
+        let eocd = read_zip64_end_of_central_directory(inner, tail_offset + position as u64)?;
+        return Ok((eocd, comment));
+    }
+    let eocd = EndOfCentralDirectory {
+        entry_count: u64::from(entry_count),
+        size: u64::from(size),
+        offset: u64::from(offset),
+    };
+    Ok((eocd, comment))
+}
+
+fn read_zip64_end_of_central_directory<R>(
+    inner: &mut R,
+    eocd_offset: u64,
+) -> io::Result<EndOfCentralDirectory>
+where
+    R: Read + Seek,
+{
+    let locator_offset = eocd_offset
+        .checked_sub(ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE as u64)
+        .ok_or_else(|| {
+            io::Error::from(
+                Error::new(
+                    ErrorKind::InvalidHeader,
+                    "No Zip64 end of central directory locator is found in the ZIP archive",
+                )
+                .with_position(eocd_offset),
+            )
+        })?;
+    let mut buf = [0; ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIZE];
+    inner.seek(SeekFrom::Start(locator_offset))?;
+    inner.read_exact(&mut buf)?;
+    let mut fields = Fields::new(&buf, locator_offset);
+    fields.signature(ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE)?;
+    let disk = fields.u32()?;
+    let record_offset = fields.u64()?;
+    let disk_count = fields.u32()?;
+    if disk != 0 || disk_count > 1 {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Multi-disk ZIP archives are unsupported",
+        )
+        .with_value(disk_count)
+        .with_position(locator_offset)
+        .into());
+    }
+
+    let mut buf = [0; ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE];
+    inner.seek(SeekFrom::Start(record_offset))?;
+    inner.read_exact(&mut buf)?;
+    let mut fields = Fields::new(&buf, record_offset);
+    fields.signature(ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE)?;
+    fields.skip(12)?; // The size of the record and the versions
+    let disk = fields.u32()?;
+    let central_directory_disk = fields.u32()?;
+    let disk_entry_count = fields.u64()?;
+    let entry_count = fields.u64()?;
+    if disk != 0 || central_directory_disk != 0 || disk_entry_count != entry_count {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Multi-disk ZIP archives are unsupported",
+        )
+        .with_value(disk)
+        .with_position(fields.position())
+        .into());
+    }
+    let size = fields.u64()?;
+    let offset = fields.u64()?;
+    Ok(EndOfCentralDirectory {
+        entry_count,
+        size,
+        offset,
+    })
+}
+
+fn read_central_directory_header(fields: &mut Fields) -> io::Result<Entry> {
+    fields.signature(CENTRAL_DIRECTORY_HEADER_SIGNATURE)?;
+    let version_made_by = fields.u16()?;
+    let _version_needed = fields.u16()?;
+    let flags = fields.u16()?;
+    let compression_method = CompressionMethod::from_u16(fields.u16()?);
+    let time = fields.u16()?;
+    let date = fields.u16()?;
+    let crc32 = fields.u32()?;
+    let compressed_size = fields.u32()?;
+    let uncompressed_size = fields.u32()?;
+    let name_len = fields.u16()?;
+    let extra_field_len = fields.u16()?;
+    let comment_len = fields.u16()?;
+    let _disk_start = fields.u16()?;
+    let _internal_attributes = fields.u16()?;
+    let external_attributes = fields.u32()?;
+    let local_header_offset = fields.u32()?;
+    let name = fields.bytes(usize::from(name_len))?.to_vec();
+    let extra_field_position = fields.position();
+    let extra_field = fields.bytes(usize::from(extra_field_len))?.to_vec();
+    let comment = fields.bytes(usize::from(comment_len))?.to_vec();
+
+    // The fields set to `u32::MAX` are stored in the Zip64 extended information extra field (in this order)
+    let mut uncompressed_size = u64::from(uncompressed_size);
+    let mut compressed_size = u64::from(compressed_size);
+    let mut local_header_offset = u64::from(local_header_offset);
+    let max = u64::from(u32::MAX);
+    if uncompressed_size == max || compressed_size == max || local_header_offset == max {
+        let zip64 = find_extra_field(&extra_field, ZIP64_EXTRA_FIELD_ID).ok_or_else(|| {
+            io::Error::from(
+                Error::new(
+                    ErrorKind::InvalidHeader,
+                    "The Zip64 extended information extra field is missing",
+                )
+                .with_position(extra_field_position),
+            )
+        })?;
+        let mut zip64 = Fields::new(zip64, extra_field_position);
+        for value in [
+            &mut uncompressed_size,
+            &mut compressed_size,
+            &mut local_header_offset,
+        ] {
+            if *value == max {
+                *value = zip64.u64()?;
+            }
+        }
+    }
+    Ok(Entry {
+        name,
+        extra_field,
+        comment,
+        version_made_by,
+        flags,
+        compression_method,
+        modification_time: DosDateTime::from_raw(date, time),
+        crc32,
+        compressed_size,
+        uncompressed_size,
+        external_attributes,
+        local_header_offset,
+    })
+}
+
+/// A cursor over the little-endian fields of a record.
+pub(super) struct Fields<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    base: u64,
+}
+impl<'a> Fields<'a> {
+    /// `base` is the position of `buf` in the archive (used for error reporting).
+    pub(super) fn new(buf: &'a [u8], base: u64) -> Self {
+        Fields {
+            buf,
+            offset: 0,
+            base,
+        }
+    }
+    pub(super) fn position(&self) -> u64 {
+        self.base + self.offset as u64
+    }
+    pub(super) fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .buf
+            .get(self.offset..self.offset.saturating_add(len))
+            .ok_or_else(|| {
+                io::Error::from(
+                    Error::new(ErrorKind::UnexpectedEof, "The ZIP record is truncated")
+                        .with_position(self.position()),
+                )
+            })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+    pub(super) fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+    pub(super) fn u16(&mut self) -> io::Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+    pub(super) fn u32(&mut self) -> io::Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    pub(super) fn u64(&mut self) -> io::Result<u64> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+    pub(super) fn signature(&mut self, expected: u32) -> io::Result<()> {
+        let position = self.position();
+        let signature = self.u32()?;
+        if signature != expected {
+            return Err(
+                Error::new(ErrorKind::InvalidHeader, "Unexpected ZIP signature")
+                    .with_value(signature)
+                    .with_expected(expected)
+                    .with_position(position)
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "no_std")]
+    use core2::io::Cursor;
+    #[cfg(not(feature = "no_std"))]
+    use std::io::Cursor;
+
+    /// An archive made by Python's `zipfile` module, which has a DEFLATE entry "hello.txt" and a directory "dir/".
+    const ARCHIVE: [u8; 214] = [
+        80, 75, 3, 4, 20, 0, 0, 0, 8, 0, 131, 24, 34, 88, 163, 28, 41, 28, 14, 0, 0, 0, 12, 0, 0,
+        0, 9, 0, 0, 0, 104, 101, 108, 108, 111, 46, 116, 120, 116, 243, 72, 205, 201, 201, 87, 8,
+        207, 47, 202, 73, 81, 4, 0, 80, 75, 3, 4, 20, 0, 0, 0, 0, 0, 131, 24, 34, 88, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 100, 105, 114, 47, 80, 75, 1, 2, 20, 3, 20, 0, 0, 0, 8,
+        0, 131, 24, 34, 88, 163, 28, 41, 28, 14, 0, 0, 0, 12, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 128, 1, 0, 0, 0, 0, 104, 101, 108, 108, 111, 46, 116, 120, 116, 80, 75, 1, 2, 20,
+        3, 20, 0, 0, 0, 0, 0, 131, 24, 34, 88, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 128, 1, 53, 0, 0, 0, 100, 105, 114, 47, 80, 75, 5, 6, 0, 0, 0, 0, 2,
+        0, 2, 0, 105, 0, 0, 0, 87, 0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn read_works() {
+        let mut archive = Archive::new(Cursor::new(&ARCHIVE[..])).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert!(archive.comment().is_empty());
+
+        let entry = &archive.entries()[0];
+        assert_eq!(entry.name(), b"hello.txt");
+        assert_eq!(entry.compression_method(), CompressionMethod::Deflated);
+        assert_eq!(entry.uncompressed_size(), 12);
+        assert_eq!(entry.unix_mode(), Some(0o600));
+        assert!(!entry.is_dir());
+        let time = entry.modification_time();
+        assert_eq!((time.year(), time.month(), time.day()), (2024, 1, 2));
+        assert_eq!((time.hour(), time.minute(), time.second()), (3, 4, 6));
+
+        let entry = &archive.entries()[1];
+        assert_eq!(entry.name(), b"dir/");
+        assert_eq!(entry.compression_method(), CompressionMethod::Stored);
+        assert!(entry.is_dir());
+
+        let mut data = Vec::new();
+        let index = archive.index_of(b"hello.txt").unwrap();
+        archive
+            .by_index(index)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"Hello World!");
+
+        data.clear();
+        archive.by_index(1).unwrap().read_to_end(&mut data).unwrap();
+        assert!(data.is_empty());
+        assert_eq!(archive.index_of(b"foo"), None);
+    }
+
+    #[test]
+    fn archive_comment_works() {
+        // The archive comment is followed by garbage which looks like an end of central directory record,
+        // but its comment length doesn't fit.
+        let mut archive_data = ARCHIVE.to_vec();
+        let comment = b"comment PK\x05\x06";
+        archive_data[ARCHIVE.len() - 2..].copy_from_slice(&(comment.len() as u16).to_le_bytes());
+        archive_data.extend_from_slice(comment);
+
+        let archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        assert_eq!(archive.comment(), comment);
+        assert_eq!(archive.len(), 2);
+    }
+
+    #[test]
+    fn corrupted_data_is_rejected() {
+        // The CRC-32 field in the central directory
+        let mut archive_data = ARCHIVE;
+        archive_data[87 + 16] ^= 1;
+        let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        let error = archive
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // The uncompressed size field in the central directory
+        let mut archive_data = ARCHIVE;
+        archive_data[87 + 24] += 1;
+        let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        let error = archive
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // The size of the central directory beyond the end of the archive
+        let mut archive_data = ARCHIVE;
+        archive_data[ARCHIVE.len() - 10..][..4].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let error = Archive::new(Cursor::new(&archive_data[..])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Truncated archives
+        for len in 0..ARCHIVE.len() {
+            assert!(Archive::new(Cursor::new(&ARCHIVE[..len])).is_err());
+        }
+    }
+}
@@ -0,0 +1,672 @@
+use super::read::Fields;
+use super::{
+    find_extra_field, CompressionMethod, DosDateTime, Entry, CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+    DATA_DESCRIPTOR_SIGNATURE, END_OF_CENTRAL_DIRECTORY_SIGNATURE, FLAG_DATA_DESCRIPTOR,
+    LOCAL_FILE_HEADER_SIGNATURE, LOCAL_FILE_HEADER_SIZE, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+    ZIP64_EXTRA_FIELD_ID,
+};
+use crate::checksum;
+use crate::deflate;
+use crate::error::{Error, ErrorKind};
+use crate::util;
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "no_std")]
+use core::cmp;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Read};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp,
+    io::{self, Read},
+};
+
+/// The minimum number of the bytes read from the inner reader at once.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A ZIP archive reader which reads the entries sequentially from a non-seekable stream.
+///
+/// Unlike [`Archive`](super::Archive), the central directory is not read:
+/// each entry is parsed from its local header (and its data descriptor) as it is encountered,
+/// and the iteration ends at the central directory.
+/// So the metadata only recorded in the central directory (e.g., the comment and the Unix mode of an entry)
+/// are unavailable, and the entries deleted from the central directory (by some archive tools) are still yielded.
+///
+/// The data of a stored entry followed by a data descriptor (such as the ones written by [`Writer`](super::Writer))
+/// have no recorded size, so the end of the data is found by looking for the data descriptor
+/// whose CRC-32 checksum and sizes match the data read so far.
+/// Encrypted entries are unsupported.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::{Read, Write};
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::{Read, Write};
+/// use libflate::zip::{CompressionMethod, EntryOptions, StreamReader, Writer};
+///
+/// let mut writer = Writer::new(Vec::new());
+/// writer.start_entry("hello.txt", EntryOptions::new()).unwrap();
+/// writer.write_all(b"Hello World!").unwrap();
+/// let options = EntryOptions::new().compression_method(CompressionMethod::Stored);
+/// writer.start_entry("stored.txt", options).unwrap();
+/// writer.write_all(b"Hello Stream!").unwrap();
+/// let archive_data = writer.finish().into_result().unwrap();
+///
+/// let mut reader = StreamReader::new(&archive_data[..]);
+/// let mut entries = Vec::new();
+/// while let Some(mut entry) = reader.next_entry().unwrap() {
+///     let mut data = String::new();
+///     entry.read_to_string(&mut data).unwrap();
+///     entries.push((entry.entry().name().to_vec(), data));
+/// }
+/// assert_eq!(
+///     entries,
+///     [
+///         (b"hello.txt".to_vec(), "Hello World!".to_owned()),
+///         (b"stored.txt".to_vec(), "Hello Stream!".to_owned()),
+///     ]
+/// );
+/// ```
+#[derive(Debug)]
+pub struct StreamReader<R> {
+    source: Option<Source<R>>,
+    entry: Option<Entry>,
+    data: Data,
+    data_position: u64,
+    zip64: bool,
+    crc32: checksum::Crc32,
+    size: u64,
+    finished: bool,
+}
+impl<R> StreamReader<R>
+where
+    R: Read,
+{
+    /// Makes a new reader which reads an archive from `inner`.
+    pub fn new(inner: R) -> Self {
+        StreamReader {
+            source: Some(Source::Raw(Buffered::new(inner))),
+            entry: None,
+            data: Data::End,
+            data_position: 0,
+            zip64: false,
+            crc32: checksum::Crc32::new(),
+            size: 0,
+            finished: false,
+        }
+    }
+
+    /// Reads the local header of the next entry, and returns a reader of its (uncompressed) data.
+    ///
+    /// The unread data of the previous entry are skipped (and verified).
+    /// This returns `Ok(None)` once the central directory (or the end of central directory record) is reached.
+    ///
+    /// The CRC-32 checksum and the sizes of the returned entry may be zeros until the end of its data is reached,
+    /// if they are recorded in the data descriptor following the data.
+    pub fn next_entry(&mut self) -> io::Result<Option<StreamEntry<'_, R>>> {
+        if self.data != Data::End {
+            let mut buf = [0; 1024];
+            while self.read_data(&mut buf)? != 0 {}
+        }
+        self.entry = None;
+        if self.finished {
+            return Ok(None);
+        }
+
+        let raw = self.raw();
+        let position = raw.position();
+        let signature = raw.fill(4)?;
+        if signature.len() < 4 {
+            return Err(truncated_error(position));
+        }
+        let signature =
+            u32::from_le_bytes([signature[0], signature[1], signature[2], signature[3]]);
+        match signature {
+            LOCAL_FILE_HEADER_SIGNATURE => {}
+            CENTRAL_DIRECTORY_HEADER_SIGNATURE
+            | ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE
+            | END_OF_CENTRAL_DIRECTORY_SIGNATURE => {
+                self.finished = true;
+                return Ok(None);
+            }
+            _ => {
+                return Err(
+                    Error::new(ErrorKind::InvalidHeader, "Unexpected ZIP signature")
+                        .with_value(signature)
+                        .with_expected(LOCAL_FILE_HEADER_SIGNATURE)
+                        .with_position(position)
+                        .into(),
+                )
+            }
+        }
+
+        let mut fields = Fields::new(raw.take(LOCAL_FILE_HEADER_SIZE)?, position);
+        fields.skip(6)?; // The signature and the version needed to extract
+        let flags = fields.u16()?;
+        let compression_method = CompressionMethod::from_u16(fields.u16()?);
+        let time = fields.u16()?;
+        let date = fields.u16()?;
+        let crc32 = fields.u32()?;
+        let compressed_size = fields.u32()?;
+        let uncompressed_size = fields.u32()?;
+        let name_len = fields.u16()?;
+        let extra_field_len = fields.u16()?;
+        let name = raw.take(usize::from(name_len))?.to_vec();
+        let extra_field_position = raw.position();
+        let extra_field = raw.take(usize::from(extra_field_len))?.to_vec();
+
+        // Unlike the central directory, the local Zip64 extra field has both sizes
+        let zip64 = find_extra_field(&extra_field, ZIP64_EXTRA_FIELD_ID);
+        let mut uncompressed_size = u64::from(uncompressed_size);
+        let mut compressed_size = u64::from(compressed_size);
+        let max = u64::from(u32::MAX);
+        if uncompressed_size == max || compressed_size == max {
+            let zip64 = zip64.ok_or_else(|| {
+                io::Error::from(
+                    Error::new(
+                        ErrorKind::InvalidHeader,
+                        "The Zip64 extended information extra field is missing",
+                    )
+                    .with_position(extra_field_position),
+                )
+            })?;
+            let mut zip64 = Fields::new(zip64, extra_field_position);
+            uncompressed_size = zip64.u64()?;
+            compressed_size = zip64.u64()?;
+        }
+
+        let entry = Entry {
+            name,
+            extra_field: extra_field.clone(),
+            comment: Vec::new(),
+            version_made_by: 0,
+            flags,
+            compression_method,
+            modification_time: DosDateTime::from_raw(date, time),
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            external_attributes: 0,
+            local_header_offset: position,
+        };
+        if entry.is_encrypted() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Encrypted ZIP entries can't be read from a stream",
+            )
+            .with_position(position)
+            .into());
+        }
+        let data = match compression_method {
+            CompressionMethod::Stored
+                if flags & FLAG_DATA_DESCRIPTOR != 0 && compressed_size == 0 =>
+            {
+                Data::Scan
+            }
+            CompressionMethod::Stored => Data::Stored(compressed_size),
+            CompressionMethod::Deflated => Data::Deflated,
+            CompressionMethod::Unknown(method) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Compression methods other than DEFLATE(8) and STORED(0) are unsupported",
+                )
+                .with_value(method)
+                .with_position(position)
+                .into())
+            }
+        };
+        let data_position = raw.position();
+        if data == Data::Deflated {
+            if let Some(Source::Raw(r)) = self.source.take_if(|s| matches!(s, Source::Raw(_))) {
+                self.source = Some(Source::Deflate(Box::new(deflate::Decoder::new(r))));
+            }
+        }
+        self.entry = Some(entry);
+        self.data = data;
+        self.data_position = data_position;
+        self.zip64 = zip64.is_some();
+        self.crc32 = checksum::Crc32::new();
+        self.size = 0;
+        Ok(Some(StreamEntry { reader: self }))
+    }
+
+    fn raw(&mut self) -> &mut Buffered<R> {
+        match self.source.as_mut().expect("Never fails") {
+            Source::Raw(r) => r,
+            Source::Deflate(r) => r.as_inner_mut(),
+        }
+    }
+
+    fn read_data(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let size = match self.data {
+            Data::End => return Ok(0),
+            Data::Stored(0) => 0,
+            Data::Stored(remaining) => {
+                let raw = self.raw();
+                let position = raw.position();
+                let limit = cmp::min(remaining, buf.len() as u64) as usize;
+                let size = raw.read(&mut buf[..limit])?;
+                if size == 0 {
+                    return Err(truncated_error(position));
+                }
+                self.data = Data::Stored(remaining - size as u64);
+                size
+            }
+            Data::Scan => self.read_until_data_descriptor(buf)?,
+            Data::Deflated => match self.source.as_mut().expect("Never fails") {
+                Source::Deflate(r) => r.read(buf)?,
+                Source::Raw(_) => unreachable!(),
+            },
+        };
+        self.crc32.update(&buf[..size]);
+        self.size += size as u64;
+        if size == 0 {
+            self.finish_data()?;
+        }
+        Ok(size)
+    }
+
+    fn read_until_data_descriptor(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let descriptor_len = if self.zip64 { 24 } else { 16 };
+        let crc32 = self.crc32.value();
+        let size = self.size;
+        let zip64 = self.zip64;
+        let raw = self.raw();
+        let position = raw.position();
+        let available = raw.fill(descriptor_len)?;
+        if available.len() < descriptor_len {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "No data descriptor is found after the stored ZIP entry",
+            )
+            .with_position(position)
+            .into());
+        }
+        if is_data_descriptor_of(&available[..descriptor_len], crc32, size, zip64) {
+            raw.consume(descriptor_len);
+            self.data = Data::End;
+            let entry = self.entry.as_mut().expect("Never fails");
+            entry.crc32 = crc32;
+            entry.compressed_size = size;
+            entry.uncompressed_size = size;
+            return Ok(0);
+        }
+
+        // The bytes before the next candidate of the signature ("PK") are the data
+        let n = available[1..]
+            .iter()
+            .position(|&b| b == b'P')
+            .map_or(available.len(), |i| i + 1);
+        let n = cmp::min(n, buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        raw.consume(n);
+        Ok(n)
+    }
+
+    fn finish_data(&mut self) -> io::Result<()> {
+        let scanned = self.data == Data::End;
+        self.data = Data::End;
+        let compressed_size = match self.source.take_if(|s| matches!(s, Source::Deflate(_))) {
+            Some(Source::Deflate(r)) => {
+                let raw = r.into_inner();
+                let compressed_size = raw.position() - self.data_position;
+                self.source = Some(Source::Raw(raw));
+                Some(compressed_size)
+            }
+            _ => None,
+        };
+
+        let zip64 = self.zip64;
+        let position = self.raw().position();
+        let descriptor = if scanned {
+            None
+        } else if self.entry.as_ref().expect("Never fails").flags & FLAG_DATA_DESCRIPTOR != 0 {
+            Some(read_data_descriptor(self.raw(), zip64)?)
+        } else {
+            None
+        };
+        let entry = self.entry.as_mut().expect("Never fails");
+        if let Some((crc32, descriptor_compressed_size, uncompressed_size)) = descriptor {
+            entry.crc32 = crc32;
+            entry.compressed_size = descriptor_compressed_size;
+            entry.uncompressed_size = uncompressed_size;
+        } else if let Some(compressed_size) = compressed_size {
+            if entry.compressed_size == 0 {
+                entry.compressed_size = compressed_size;
+            }
+        }
+
+        if self.size != entry.uncompressed_size {
+            let kind = if self.size < entry.uncompressed_size {
+                ErrorKind::UnexpectedEof
+            } else {
+                ErrorKind::InvalidHeader
+            };
+            return Err(
+                Error::new(kind, "The size of the ZIP entry data mismatched")
+                    .with_value(self.size)
+                    .with_expected(entry.uncompressed_size)
+                    .with_position(position)
+                    .into(),
+            );
+        }
+        let crc32 = self.crc32.value();
+        if crc32 != entry.crc32 {
+            return Err(Error::new(
+                ErrorKind::ChecksumMismatch,
+                "CRC32 of the ZIP entry data mismatched",
+            )
+            .with_value(crc32)
+            .with_expected(entry.crc32)
+            .with_position(position)
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// A reader of the data of an entry, made by [`StreamReader::next_entry`].
+///
+/// The CRC-32 checksum and the size of the data are verified when the end of the data is reached.
+#[derive(Debug)]
+pub struct StreamEntry<'a, R> {
+    reader: &'a mut StreamReader<R>,
+}
+impl<R> StreamEntry<'_, R> {
+    /// Returns the metadata of the entry, read from its local header (and its data descriptor, if the end is reached).
+    pub fn entry(&self) -> &Entry {
+        self.reader.entry.as_ref().expect("Never fails")
+    }
+}
+impl<R> Read for StreamEntry<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read_data(buf)
+    }
+}
+
+#[derive(Debug)]
+enum Source<R> {
+    Raw(Buffered<R>),
+    Deflate(Box<deflate::Decoder<Buffered<R>>>),
+}
+
+/// The way to read the data of the current entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Data {
+    /// There are no (more) data.
+    End,
+
+    /// The data are stored, and the number of the remaining bytes is known.
+    Stored(u64),
+
+    /// The data are stored, and followed by a data descriptor which records the size.
+    Scan,
+
+    /// The data are compressed with DEFLATE (whose end is self-delimited).
+    Deflated,
+}
+
+fn truncated_error(position: u64) -> io::Error {
+    Error::new(ErrorKind::UnexpectedEof, "The ZIP stream is truncated")
+        .with_position(position)
+        .into()
+}
+
+fn is_data_descriptor_of(bytes: &[u8], crc32: u32, size: u64, zip64: bool) -> bool {
+    let u32_at =
+        |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+    let u64_at = |i: usize| u64::from(u32_at(i)) | (u64::from(u32_at(i + 4)) << 32);
+    let (compressed_size, uncompressed_size) = if zip64 {
+        (u64_at(8), u64_at(16))
+    } else {
+        (u64::from(u32_at(8)), u64::from(u32_at(12)))
+    };
+    u32_at(0) == DATA_DESCRIPTOR_SIGNATURE
+        && u32_at(4) == crc32
+        && compressed_size == size
+        && uncompressed_size == size
+}
+
+/// Reads a data descriptor (whose signature is optional), and returns the CRC-32 checksum and the sizes in it.
+fn read_data_descriptor<R: Read>(
+    raw: &mut Buffered<R>,
+    zip64: bool,
+) -> io::Result<(u32, u64, u64)> {
+    let signature = DATA_DESCRIPTOR_SIGNATURE.to_le_bytes();
+    if raw.fill(signature.len())?.starts_with(&signature) {
+        raw.consume(signature.len());
+    }
+    let position = raw.position();
+    let mut fields = Fields::new(raw.take(if zip64 { 20 } else { 12 })?, position);
+    let crc32 = fields.u32()?;
+    if zip64 {
+        Ok((crc32, fields.u64()?, fields.u64()?))
+    } else {
+        Ok((crc32, u64::from(fields.u32()?), u64::from(fields.u32()?)))
+    }
+}
+
+/// A reader which buffers the data read from the inner reader, so that they can be peeked at.
+#[derive(Debug)]
+struct Buffered<R> {
+    inner: R,
+    buf: Vec<u8>,
+    offset: usize,
+    position: u64,
+}
+impl<R> Buffered<R>
+where
+    R: Read,
+{
+    fn new(inner: R) -> Self {
+        Buffered {
+            inner,
+            buf: Vec::new(),
+            offset: 0,
+            position: 0,
+        }
+    }
+
+    /// Returns the position of the next unread byte in the stream.
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the buffered data after reading from the inner reader
+    /// until `size` bytes are buffered (or the end of the stream is reached).
+    fn fill(&mut self, size: usize) -> io::Result<&[u8]> {
+        while self.buf.len() - self.offset < size {
+            if self.offset > 0 {
+                self.buf.drain(..self.offset);
+                self.offset = 0;
+            }
+            let len = self.buf.len();
+            self.buf.resize(len + cmp::max(size - len, BUFFER_SIZE), 0);
+            let result = util::retry_interrupted(|| self.inner.read(&mut self.buf[len..]));
+            let read_size = *result.as_ref().unwrap_or(&0);
+            self.buf.truncate(len + read_size);
+            if result? == 0 {
+                break;
+            }
+        }
+        Ok(&self.buf[self.offset..])
+    }
+
+    fn consume(&mut self, size: usize) {
+        self.offset += size;
+        self.position += size as u64;
+    }
+
+    /// Consumes and returns `size` bytes.
+    fn take(&mut self, size: usize) -> io::Result<&[u8]> {
+        let position = self.position;
+        if self.fill(size)?.len() < size {
+            return Err(truncated_error(position));
+        }
+        let offset = self.offset;
+        self.consume(size);
+        Ok(&self.buf[offset..offset + size])
+    }
+}
+impl<R> Read for Buffered<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill(1)?;
+        let size = cmp::min(available.len(), buf.len());
+        buf[..size].copy_from_slice(&available[..size]);
+        self.consume(size);
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::{EntryOptions, Writer};
+    #[cfg(feature = "no_std")]
+    use core2::io::Write;
+    #[cfg(not(feature = "no_std"))]
+    use std::io::Write;
+
+    fn make_archive(entries: &[(&str, EntryOptions, &[u8])]) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new());
+        for (name, options, data) in entries {
+            writer.start_entry(name, options.clone()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().into_result().unwrap()
+    }
+
+    fn read_all(archive: &[u8]) -> io::Result<Vec<(Entry, Vec<u8>)>> {
+        let mut reader = StreamReader::new(archive);
+        let mut entries = Vec::new();
+        while let Some(mut entry) = reader.next_entry()? {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            entries.push((entry.entry().clone(), data));
+        }
+        Ok(entries)
+    }
+
+    #[test]
+    fn stream_read_works() {
+        let stored = EntryOptions::new().compression_method(CompressionMethod::Stored);
+        // Contains a fake data descriptor which doesn't match the data
+        let tricky = b"PK\x07\x08PK\x07\x08\x00\x00\x00\x00\x04\x00\x00\x00\x04\x00\x00\x00PKPK";
+        let long = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let archive = make_archive(&[
+            ("hello.txt", EntryOptions::new(), b"Hello World!"),
+            ("dir/", stored.clone(), b""),
+            ("tricky.bin", stored.clone(), tricky),
+            ("long.bin", stored, &long),
+            ("long.deflate", EntryOptions::new(), &long),
+            ("empty.txt", EntryOptions::new(), b""),
+        ]);
+
+        let entries = read_all(&archive).unwrap();
+        let names = entries.iter().map(|(e, _)| e.name()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                &b"hello.txt"[..],
+                b"dir/",
+                b"tricky.bin",
+                b"long.bin",
+                b"long.deflate",
+                b"empty.txt"
+            ]
+        );
+        assert_eq!(entries[0].1, b"Hello World!");
+        assert!(entries[1].0.is_dir());
+        assert_eq!(entries[2].1, &tricky[..]);
+        assert_eq!(entries[3].1, long);
+        assert_eq!(entries[4].1, long);
+        assert!(entries[5].1.is_empty());
+
+        let archive_entries = crate::zip::Archive::new(io::Cursor::new(&archive[..]))
+            .unwrap()
+            .entries()
+            .to_vec();
+        for ((entry, _), expected) in entries.iter().zip(archive_entries.iter()) {
+            assert_eq!(entry.crc32(), expected.crc32());
+            assert_eq!(entry.compressed_size(), expected.compressed_size());
+            assert_eq!(entry.uncompressed_size(), expected.uncompressed_size());
+            assert_eq!(entry.local_header_offset, expected.local_header_offset);
+        }
+    }
+
+    #[test]
+    fn skipping_entries_works() {
+        let stored = EntryOptions::new().compression_method(CompressionMethod::Stored);
+        let archive = make_archive(&[
+            ("a.txt", EntryOptions::new(), b"Hello World!"),
+            ("b.txt", stored, b"Hello Stream!"),
+            ("c.txt", EntryOptions::new(), b"Hello ZIP!"),
+        ]);
+
+        let mut reader = StreamReader::new(&archive[..]);
+        let mut buf = [0; 5];
+        reader.next_entry().unwrap().unwrap();
+        let mut entry = reader.next_entry().unwrap().unwrap();
+        entry.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hello");
+
+        let mut entry = reader.next_entry().unwrap().unwrap();
+        assert_eq!(entry.entry().name(), b"c.txt");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"Hello ZIP!");
+        assert!(reader.next_entry().unwrap().is_none());
+        assert!(reader.next_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn stream_read_zip64_works() {
+        let large = EntryOptions::new().large_file(true);
+        let archive = make_archive(&[
+            ("large.txt", large.clone(), b"Hello World!"),
+            (
+                "large.bin",
+                large.compression_method(CompressionMethod::Stored),
+                b"Hello Zip64!",
+            ),
+        ]);
+
+        let entries = read_all(&archive).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].1, b"Hello World!");
+        assert_eq!(entries[1].1, b"Hello Zip64!");
+        assert_eq!(entries[1].0.uncompressed_size(), 12);
+    }
+
+    #[test]
+    fn stream_read_errors_work() {
+        let archive = make_archive(&[("hello.txt", EntryOptions::new(), b"Hello World!")]);
+
+        // Truncated
+        for len in [2, 10, 40, 50, 60] {
+            let error = read_all(&archive[..len]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "len={len}");
+        }
+
+        // Corrupted CRC-32 in the data descriptor
+        let mut corrupted = archive.clone();
+        let offset = (0..archive.len())
+            .find(|&i| archive[i..].starts_with(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes()))
+            .unwrap();
+        corrupted[offset + 4] ^= 1;
+        assert!(read_all(&corrupted).is_err());
+
+        // Unknown signature
+        assert!(read_all(b"ABCDEFGH").is_err());
+    }
+}
@@ -0,0 +1,792 @@
+use super::crypto::Encrypt;
+#[cfg(not(feature = "no_std"))]
+use super::{crypto, FLAG_ENCRYPTED};
+use super::{
+    CompressionMethod, DosDateTime, Entry, CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+    DATA_DESCRIPTOR_SIGNATURE, END_OF_CENTRAL_DIRECTORY_SIGNATURE, FLAG_DATA_DESCRIPTOR, FLAG_UTF8,
+    LOCAL_FILE_HEADER_SIGNATURE, METHOD_DEFLATED, METHOD_STORED,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE, ZIP64_EXTRA_FIELD_ID,
+};
+use crate::checksum;
+use crate::deflate;
+use crate::error::{Error, ErrorKind};
+use crate::finish::Finish;
+use crate::options::CompressionOptions;
+use crate::util::Counter;
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+#[cfg(feature = "no_std")]
+use core::cmp;
+#[cfg(feature = "no_std")]
+use core2::io::{self, Write};
+#[cfg(not(feature = "no_std"))]
+use std::{
+    cmp,
+    io::{self, Write},
+};
+
+/// "Version 2.0" (DEFLATE and directories), which is needed to extract the entries.
+const VERSION_NEEDED: u16 = 20;
+
+/// "Version 4.5" (Zip64), which is needed to extract the entries recorded in the Zip64 format.
+const VERSION_NEEDED_ZIP64: u16 = 45;
+
+/// The upper byte of the "version made by" field for Unix.
+const UNIX_HOST: u16 = 3 << 8;
+
+/// The MS-DOS directory attribute.
+const DOS_DIRECTORY_ATTRIBUTE: u32 = 0x10;
+
+/// Options for an entry added by [`Writer`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryOptions {
+    compression_method: CompressionMethod,
+    compression: CompressionOptions,
+    modification_time: DosDateTime,
+    comment: Vec<u8>,
+    unix_mode: Option<u32>,
+    large_file: bool,
+    #[cfg(not(feature = "no_std"))]
+    password: Option<Vec<u8>>,
+}
+impl Default for EntryOptions {
+    fn default() -> Self {
+        EntryOptions {
+            compression_method: CompressionMethod::Deflated,
+            compression: CompressionOptions::new(),
+            modification_time: DosDateTime::new(1980, 1, 1, 0, 0, 0),
+            comment: Vec::new(),
+            unix_mode: None,
+            large_file: false,
+            #[cfg(not(feature = "no_std"))]
+            password: None,
+        }
+    }
+}
+impl EntryOptions {
+    /// Makes a default instance.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::zip::{CompressionMethod, EntryOptions};
+    ///
+    /// let options = EntryOptions::new();
+    /// assert_eq!(options.get_compression_method(), CompressionMethod::Deflated);
+    /// assert_eq!(options.get_modification_time().year(), 1980);
+    /// assert_eq!(options.get_unix_mode(), None);
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Specifies the compression method of the entry.
+    ///
+    /// The methods other than [`CompressionMethod::Deflated`] (the default) and [`CompressionMethod::Stored`] are unsupported.
+    pub fn compression_method(mut self, method: CompressionMethod) -> Self {
+        self.compression_method = method;
+        self
+    }
+
+    /// Specifies the options of the DEFLATE encoder used for the entry.
+    pub fn compression_options(mut self, options: CompressionOptions) -> Self {
+        self.compression = options;
+        self
+    }
+
+    /// Specifies the last modification time of the entry.
+    ///
+    /// The default value is 1980-01-01 00:00:00 (the earliest time representable in ZIP archives).
+    pub fn modification_time(mut self, time: DosDateTime) -> Self {
+        self.modification_time = time;
+        self
+    }
+
+    /// Specifies the comment of the entry.
+    pub fn comment(mut self, comment: Vec<u8>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Specifies the Unix file mode (e.g., `0o100644`) of the entry.
+    pub fn unix_mode(mut self, mode: u32) -> Self {
+        self.unix_mode = Some(mode);
+        self
+    }
+
+    /// Specifies whether the entry may be 4 GiB or larger.
+    ///
+    /// If `true`, the sizes of the entry are recorded in the Zip64 format,
+    /// which is not supported by some old extraction tools.
+    /// Since the sizes are unknown when the local header of the entry is written,
+    /// the data of an entry exceeding the limit without this option causes an error.
+    ///
+    /// The default value is `false`.
+    /// (The archive itself may exceed 4 GiB regardless of this option.)
+    pub fn large_file(mut self, large_file: bool) -> Self {
+        self.large_file = large_file;
+        self
+    }
+
+    /// Specifies the password to encrypt the entry with the traditional PKWARE encryption (a.k.a. ZipCrypto).
+    ///
+    /// **The encryption is weak**: it can be broken by a known-plaintext attack within hours,
+    /// so it should only be used to exchange archives with legacy tools which require it.
+    ///
+    /// The 12-byte encryption header, which precedes the data, is made from the random seed of
+    /// [`RandomState`](std::collections::hash_map::RandomState),
+    /// so this is unavailable if the `no_std` feature is enabled.
+    #[cfg(not(feature = "no_std"))]
+    pub fn password(mut self, password: Vec<u8>) -> Self {
+        self.password = Some(password);
+        self
+    }
+
+    /// Returns the compression method of the entry.
+    pub fn get_compression_method(&self) -> CompressionMethod {
+        self.compression_method
+    }
+
+    /// Returns the options of the DEFLATE encoder used for the entry.
+    pub fn get_compression_options(&self) -> &CompressionOptions {
+        &self.compression
+    }
+
+    /// Returns the last modification time of the entry.
+    pub fn get_modification_time(&self) -> DosDateTime {
+        self.modification_time
+    }
+
+    /// Returns the comment of the entry.
+    pub fn get_comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// Returns the Unix file mode of the entry.
+    pub fn get_unix_mode(&self) -> Option<u32> {
+        self.unix_mode
+    }
+
+    /// Returns whether the entry may be 4 GiB or larger.
+    pub fn get_large_file(&self) -> bool {
+        self.large_file
+    }
+
+    /// Returns the password to encrypt the entry.
+    #[cfg(not(feature = "no_std"))]
+    pub fn get_password(&self) -> Option<&[u8]> {
+        self.password.as_deref()
+    }
+}
+
+#[derive(Debug)]
+enum Sink<W> {
+    Direct(Counter<Encrypt<W>>),
+    Deflate(Box<deflate::Encoder<Counter<Encrypt<W>>>>),
+}
+impl<W: Write> Sink<W> {
+    fn counter(&mut self) -> &mut Counter<Encrypt<W>> {
+        match self {
+            Sink::Direct(w) => w,
+            Sink::Deflate(w) => w.as_inner_mut(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CurrentEntry {
+    entry: Entry,
+    crc32: checksum::Crc32,
+    data_offset: u64,
+    zip64: bool,
+}
+
+/// A streaming ZIP archive writer.
+///
+/// The entries are written one after another: data written via `Write` belongs to the entry most recently
+/// started by [`Writer::start_entry`].
+/// The inner stream doesn't have to be seekable, because the CRC-32 checksum and the sizes of each entry
+/// are written in a data descriptor following the data.
+/// The central directory is written by [`Writer::finish`].
+///
+/// If the archive is 4 GiB or larger, or has 65535 or more entries,
+/// the Zip64 end of central directory record is written in addition to the ordinary one.
+/// Entries of 4 GiB or larger need [`EntryOptions::large_file`].
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::Write;
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::Write;
+/// use libflate::zip::{CompressionMethod, EntryOptions, Writer};
+///
+/// let mut writer = Writer::new(Vec::new());
+/// writer.start_entry("hello.txt", EntryOptions::new()).unwrap();
+/// writer.write_all(b"Hello World!").unwrap();
+///
+/// let options = EntryOptions::new().compression_method(CompressionMethod::Stored);
+/// writer.start_entry("stored.txt", options).unwrap();
+/// writer.write_all(b"Hello World!").unwrap();
+///
+/// let archive_data = writer.finish().into_result().unwrap();
+/// assert_eq!(&archive_data[..4], b"PK\x03\x04");
+/// ```
+#[derive(Debug)]
+pub struct Writer<W> {
+    sink: Option<Sink<W>>,
+    current: Option<CurrentEntry>,
+    entries: Vec<Entry>,
+    comment: Vec<u8>,
+}
+impl<W> Writer<W>
+where
+    W: Write,
+{
+    /// Makes a new writer which writes an archive to `inner`.
+    pub fn new(inner: W) -> Self {
+        Writer {
+            sink: Some(Sink::Direct(Counter::new(Encrypt::new(inner)))),
+            current: None,
+            entries: Vec::new(),
+            comment: Vec::new(),
+        }
+    }
+
+    /// Sets the comment of the archive.
+    pub fn set_comment(&mut self, comment: Vec<u8>) {
+        self.comment = comment;
+    }
+
+    /// Finishes the current entry (if any), and starts a new entry named `name`.
+    ///
+    /// The data of the entry are written to this writer via `Write` until the next entry is started.
+    pub fn start_entry(&mut self, name: &str, options: EntryOptions) -> io::Result<()> {
+        self.finish_entry()?;
+        self.start_entry_with_attributes(name, options, 0)
+    }
+
+    /// Finishes the current entry (if any), and adds a directory entry named `name`.
+    ///
+    /// A trailing `/` is appended to `name` if it is missing. The compression method in `options` is ignored.
+    pub fn add_directory(&mut self, name: &str, options: EntryOptions) -> io::Result<()> {
+        self.finish_entry()?;
+        let mut name = String::from(name);
+        if !name.ends_with('/') {
+            name.push('/');
+        }
+        let options = options.compression_method(CompressionMethod::Stored);
+        self.start_entry_with_attributes(&name, options, DOS_DIRECTORY_ATTRIBUTE)?;
+        self.finish_entry()
+    }
+
+    /// Returns the number of the entries written so far (including the current one).
+    pub fn len(&self) -> usize {
+        self.entries.len() + usize::from(self.current.is_some())
+    }
+
+    /// Returns `true` if no entries have been started.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the immutable reference to the inner stream.
+    pub fn as_inner_ref(&self) -> &W {
+        match self.sink.as_ref().expect("Never fails") {
+            Sink::Direct(w) => w.as_inner_ref().as_inner_ref(),
+            Sink::Deflate(w) => w.as_inner_ref().as_inner_ref().as_inner_ref(),
+        }
+    }
+
+    /// Finishes the current entry, writes the central directory, and returns the inner stream.
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let result = self.try_finish();
+        let inner = match self.sink.take().expect("Never fails") {
+            Sink::Direct(w) => w.into_inner().into_inner(),
+            Sink::Deflate(w) => w.finish().unwrap().0.into_inner().into_inner(),
+        };
+        Finish::new(inner, result.err())
+    }
+
+    fn try_finish(&mut self) -> io::Result<()> {
+        self.finish_entry()?;
+        let sink = self.sink.as_mut().expect("Never fails").counter();
+        let offset = sink.count();
+        for entry in &self.entries {
+            write_central_directory_header(sink, entry)?;
+        }
+        let size = sink.count() - offset;
+        let entry_count = self.entries.len() as u64;
+        if offset >= u64::from(u32::MAX)
+            || size >= u64::from(u32::MAX)
+            || entry_count >= u64::from(u16::MAX)
+        {
+            let record_offset = sink.count();
+            let record_size = (ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE - 12) as u64;
+            sink.write_all(&ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+            sink.write_all(&record_size.to_le_bytes())?;
+            sink.write_all(&VERSION_NEEDED_ZIP64.to_le_bytes())?; // The version made by
+            sink.write_all(&VERSION_NEEDED_ZIP64.to_le_bytes())?;
+            sink.write_all(&[0; 8])?; // The disk numbers
+            sink.write_all(&entry_count.to_le_bytes())?;
+            sink.write_all(&entry_count.to_le_bytes())?;
+            sink.write_all(&size.to_le_bytes())?;
+            sink.write_all(&offset.to_le_bytes())?;
+
+            sink.write_all(&ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE.to_le_bytes())?;
+            sink.write_all(&0u32.to_le_bytes())?; // The disk number
+            sink.write_all(&record_offset.to_le_bytes())?;
+            sink.write_all(&1u32.to_le_bytes())?; // The number of the disks
+        }
+
+        // The fields which don't fit are set to their maximum values, indicating that the Zip64 record is used
+        let entry_count = cmp::min(entry_count, u64::from(u16::MAX)) as u16;
+        sink.write_all(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+        sink.write_all(&[0; 4])?; // The disk numbers
+        sink.write_all(&entry_count.to_le_bytes())?;
+        sink.write_all(&entry_count.to_le_bytes())?;
+        sink.write_all(&saturate_u32(size).to_le_bytes())?;
+        sink.write_all(&saturate_u32(offset).to_le_bytes())?;
+        sink.write_all(&(self.comment.len() as u16).to_le_bytes())?;
+        sink.write_all(&self.comment)?;
+        sink.flush()
+    }
+
+    fn start_entry_with_attributes(
+        &mut self,
+        name: &str,
+        options: EntryOptions,
+        dos_attributes: u32,
+    ) -> io::Result<()> {
+        if name.len() > usize::from(u16::MAX) || options.comment.len() > usize::from(u16::MAX) {
+            return Err(Error::new(
+                ErrorKind::LimitExceeded,
+                "The name or the comment of the ZIP entry is too long",
+            )
+            .with_expected(u64::from(u16::MAX))
+            .into());
+        }
+        let method = match options.compression_method {
+            CompressionMethod::Stored => METHOD_STORED,
+            CompressionMethod::Deflated => METHOD_DEFLATED,
+            CompressionMethod::Unknown(method) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Compression methods other than DEFLATE(8) and STORED(0) are unsupported",
+                )
+                .with_value(method)
+                .into())
+            }
+        };
+
+        let sink = self.sink.as_mut().expect("Never fails").counter();
+        let local_header_offset = sink.count();
+        let (version_made_by, external_attributes) = match options.unix_mode {
+            Some(mode) => (UNIX_HOST | VERSION_NEEDED, (mode << 16) | dos_attributes),
+            None => (VERSION_NEEDED, dos_attributes),
+        };
+        #[cfg_attr(feature = "no_std", allow(unused_mut))]
+        let mut flags = FLAG_DATA_DESCRIPTOR | FLAG_UTF8;
+        #[cfg(not(feature = "no_std"))]
+        if options.password.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+        let entry = Entry {
+            name: name.as_bytes().to_vec(),
+            extra_field: Vec::new(),
+            comment: options.comment,
+            version_made_by,
+            flags,
+            compression_method: options.compression_method,
+            modification_time: options.modification_time,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            external_attributes,
+            local_header_offset,
+        };
+
+        // The CRC-32 checksum and the sizes are written in the data descriptor.
+        // For a large file, the Zip64 extra field (with zero sizes) indicates that the sizes are 64-bit.
+        let zip64 = options.large_file;
+        let version_needed = if zip64 {
+            VERSION_NEEDED_ZIP64
+        } else {
+            VERSION_NEEDED
+        };
+        sink.write_all(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes())?;
+        sink.write_all(&version_needed.to_le_bytes())?;
+        sink.write_all(&entry.flags.to_le_bytes())?;
+        sink.write_all(&method.to_le_bytes())?;
+        sink.write_all(&entry.modification_time.raw_time().to_le_bytes())?;
+        sink.write_all(&entry.modification_time.raw_date().to_le_bytes())?;
+        sink.write_all(&[0; 4])?; // The CRC-32 checksum
+        if zip64 {
+            sink.write_all(&[0xFF; 8])?;
+        } else {
+            sink.write_all(&[0; 8])?;
+        }
+        sink.write_all(&(entry.name.len() as u16).to_le_bytes())?;
+        sink.write_all(&(if zip64 { 20u16 } else { 0 }).to_le_bytes())?;
+        sink.write_all(&entry.name)?;
+        if zip64 {
+            sink.write_all(&ZIP64_EXTRA_FIELD_ID.to_le_bytes())?;
+            sink.write_all(&16u16.to_le_bytes())?;
+            sink.write_all(&[0; 16])?;
+        }
+        let data_offset = sink.count();
+
+        // The encryption header is followed by the encrypted (compressed) data
+        #[cfg(not(feature = "no_std"))]
+        if let Some(password) = options.password.as_ref() {
+            let header = crypto::encryption_header(entry.encryption_check_byte());
+            sink.as_inner_mut()
+                .set_keys(Some(crypto::Keys::new(password)));
+            sink.write_all(&header)?;
+        }
+
+        if method == METHOD_DEFLATED {
+            if let Some(Sink::Direct(w)) = self.sink.take_if(|s| matches!(s, Sink::Direct(_))) {
+                let encoder = deflate::Encoder::with_options(w, options.compression.into());
+                self.sink = Some(Sink::Deflate(Box::new(encoder)));
+            }
+        }
+        self.current = Some(CurrentEntry {
+            entry,
+            crc32: checksum::Crc32::new(),
+            data_offset,
+            zip64,
+        });
+        Ok(())
+    }
+
+    fn finish_entry(&mut self) -> io::Result<()> {
+        let current = match self.current.take() {
+            None => return Ok(()),
+            Some(current) => current,
+        };
+        if let Some(Sink::Deflate(encoder)) = self.sink.take_if(|s| matches!(s, Sink::Deflate(_))) {
+            let (w, error) = encoder.finish().unwrap();
+            self.sink = Some(Sink::Direct(w));
+            if let Some(e) = error {
+                return Err(e);
+            }
+        }
+
+        let mut entry = current.entry;
+        let uncompressed_size = entry.uncompressed_size;
+        let sink = self.sink.as_mut().expect("Never fails").counter();
+        let compressed_size = sink.count() - current.data_offset;
+        #[cfg(not(feature = "no_std"))]
+        sink.as_inner_mut().set_keys(None);
+        entry.crc32 = current.crc32.value();
+        entry.compressed_size = compressed_size;
+        sink.write_all(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes())?;
+        sink.write_all(&entry.crc32.to_le_bytes())?;
+        if current.zip64 {
+            sink.write_all(&compressed_size.to_le_bytes())?;
+            sink.write_all(&uncompressed_size.to_le_bytes())?;
+        } else {
+            let too_large = "The ZIP entry is too large (see `EntryOptions::large_file`)";
+            sink.write_all(&check_u32(compressed_size, too_large)?.to_le_bytes())?;
+            sink.write_all(&check_u32(uncompressed_size, too_large)?.to_le_bytes())?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+impl<W> Write for Writer<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let current = self.current.as_mut().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "No ZIP entry has been started")
+        })?;
+        let size = match self.sink.as_mut().expect("Never fails") {
+            Sink::Direct(w) => w.write(buf)?,
+            Sink::Deflate(w) => w.write(buf)?,
+        };
+        current.crc32.update(&buf[..size]);
+        current.entry.uncompressed_size += size as u64;
+        Ok(size)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self.sink.as_mut().expect("Never fails") {
+            Sink::Direct(w) => w.flush(),
+            Sink::Deflate(w) => w.flush(),
+        }
+    }
+}
+
+fn check_u32(value: u64, message: &'static str) -> io::Result<u32> {
+    if value >= u64::from(u32::MAX) {
+        Err(Error::new(ErrorKind::LimitExceeded, message)
+            .with_value(value)
+            .with_expected(u64::from(u32::MAX) - 1)
+            .into())
+    } else {
+        Ok(value as u32)
+    }
+}
+
+fn saturate_u32(value: u64) -> u32 {
+    cmp::min(value, u64::from(u32::MAX)) as u32
+}
+
+fn write_central_directory_header<W: Write>(writer: &mut W, entry: &Entry) -> io::Result<()> {
+    let method = match entry.compression_method {
+        CompressionMethod::Stored => METHOD_STORED,
+        CompressionMethod::Deflated => METHOD_DEFLATED,
+        CompressionMethod::Unknown(method) => method,
+    };
+
+    // The values which don't fit in the 32-bit fields are stored in the Zip64 extra field (in this order)
+    let mut zip64 = Vec::new();
+    for &value in &[
+        entry.uncompressed_size,
+        entry.compressed_size,
+        entry.local_header_offset,
+    ] {
+        if value >= u64::from(u32::MAX) {
+            zip64.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let (version_needed, zip64_header_len) = if zip64.is_empty() {
+        (VERSION_NEEDED, 0)
+    } else {
+        (VERSION_NEEDED_ZIP64, 4)
+    };
+    let extra_field_len = zip64_header_len + zip64.len() + entry.extra_field.len();
+
+    writer.write_all(&CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes())?;
+    writer.write_all(&entry.version_made_by.to_le_bytes())?;
+    writer.write_all(&version_needed.to_le_bytes())?;
+    writer.write_all(&entry.flags.to_le_bytes())?;
+    writer.write_all(&method.to_le_bytes())?;
+    writer.write_all(&entry.modification_time.raw_time().to_le_bytes())?;
+    writer.write_all(&entry.modification_time.raw_date().to_le_bytes())?;
+    writer.write_all(&entry.crc32.to_le_bytes())?;
+    writer.write_all(&saturate_u32(entry.compressed_size).to_le_bytes())?;
+    writer.write_all(&saturate_u32(entry.uncompressed_size).to_le_bytes())?;
+    writer.write_all(&(entry.name.len() as u16).to_le_bytes())?;
+    writer.write_all(&(extra_field_len as u16).to_le_bytes())?;
+    writer.write_all(&(entry.comment.len() as u16).to_le_bytes())?;
+    writer.write_all(&[0; 4])?; // The disk number and the internal attributes
+    writer.write_all(&entry.external_attributes.to_le_bytes())?;
+    writer.write_all(&saturate_u32(entry.local_header_offset).to_le_bytes())?;
+    writer.write_all(&entry.name)?;
+    if !zip64.is_empty() {
+        writer.write_all(&ZIP64_EXTRA_FIELD_ID.to_le_bytes())?;
+        writer.write_all(&(zip64.len() as u16).to_le_bytes())?;
+        writer.write_all(&zip64)?;
+    }
+    writer.write_all(&entry.extra_field)?;
+    writer.write_all(&entry.comment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::Archive;
+    #[cfg(feature = "no_std")]
+    use core2::io::{Cursor, Read};
+    #[cfg(not(feature = "no_std"))]
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn round_trip_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let time = DosDateTime::new(2024, 2, 29, 23, 59, 58);
+
+        let mut writer = Writer::new(Vec::new());
+        writer.set_comment(b"archive comment".to_vec());
+        writer
+            .start_entry(
+                "a/deflated.bin",
+                EntryOptions::new().modification_time(time),
+            )
+            .unwrap();
+        writer.write_all(&plain).unwrap();
+        writer
+            .add_directory("a/empty", EntryOptions::new())
+            .unwrap();
+        let options = EntryOptions::new()
+            .compression_method(CompressionMethod::Stored)
+            .comment(b"entry comment".to_vec())
+            .unix_mode(0o100644);
+        writer.start_entry("stored.txt", options).unwrap();
+        writer.write_all(b"Hello World!").unwrap();
+        assert_eq!(writer.len(), 3);
+        let archive_data = writer.finish().into_result().unwrap();
+
+        let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        assert_eq!(archive.comment(), b"archive comment");
+        assert_eq!(archive.len(), 3);
+
+        let entry = &archive.entries()[0];
+        assert_eq!(entry.name(), b"a/deflated.bin");
+        assert!(entry.is_utf8());
+        assert_eq!(entry.modification_time(), time);
+        assert_eq!(entry.uncompressed_size(), plain.len() as u64);
+        assert!(entry.compressed_size() < entry.uncompressed_size());
+
+        let entry = &archive.entries()[1];
+        assert_eq!(entry.name(), b"a/empty/");
+        assert!(entry.is_dir());
+
+        let entry = &archive.entries()[2];
+        assert_eq!(entry.compression_method(), CompressionMethod::Stored);
+        assert_eq!(entry.comment(), b"entry comment");
+        assert_eq!(entry.unix_mode(), Some(0o100644));
+
+        let mut data = Vec::new();
+        archive.by_index(0).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, plain);
+        data.clear();
+        archive.by_index(2).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"Hello World!");
+    }
+
+    #[test]
+    fn empty_archive_works() {
+        let archive_data = Writer::new(Vec::new()).finish().into_result().unwrap();
+        assert_eq!(archive_data.len(), 22);
+        assert!(Archive::new(Cursor::new(&archive_data[..]))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn zip64_works() {
+        // A large file entry has the 64-bit sizes in its data descriptor
+        let mut writer = Writer::new(Vec::new());
+        let options = EntryOptions::new().large_file(true);
+        writer.start_entry("large.txt", options.clone()).unwrap();
+        writer.write_all(b"Hello World!").unwrap();
+        writer
+            .start_entry(
+                "stored.txt",
+                options.compression_method(CompressionMethod::Stored),
+            )
+            .unwrap();
+        writer.write_all(b"Hello World!").unwrap();
+        let archive_data = writer.finish().into_result().unwrap();
+
+        let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        for i in 0..2 {
+            let mut data = Vec::new();
+            archive.by_index(i).unwrap().read_to_end(&mut data).unwrap();
+            assert_eq!(data, b"Hello World!");
+        }
+
+        // The sizes and the offsets not fitting in 32 bits are recorded in the Zip64 extra fields
+        let mut writer = Writer::new(Vec::new());
+        writer.start_entry("foo", EntryOptions::new()).unwrap();
+        writer.finish_entry().unwrap();
+        let mut entry = writer.entries[0].clone();
+        entry.uncompressed_size = 5 << 32;
+        entry.local_header_offset = u64::from(u32::MAX);
+        writer.entries.push(entry.clone());
+        entry.compressed_size = 6 << 32;
+        entry.extra_field = vec![0xAB, 0xCD, 1, 0, 0xEF];
+        writer.entries.push(entry);
+        let archive_data = writer.finish().into_result().unwrap();
+
+        let archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        let entries = archive.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].extra_field(), b"");
+        assert_eq!(entries[1].uncompressed_size(), 5 << 32);
+        assert_eq!(entries[1].compressed_size(), entries[0].compressed_size());
+        assert_eq!(entries[1].local_header_offset, u64::from(u32::MAX));
+        assert_eq!(entries[2].compressed_size(), 6 << 32);
+        assert_eq!(entries[2].extra_field().len(), 4 + 24 + 5);
+        assert!(entries[2]
+            .extra_field()
+            .ends_with(&[0xAB, 0xCD, 1, 0, 0xEF]));
+    }
+
+    #[test]
+    fn many_entries_work() {
+        let count = usize::from(u16::MAX) + 1;
+        let mut writer = Writer::new(Vec::new());
+        let options = EntryOptions::new().compression_method(CompressionMethod::Stored);
+        for _ in 0..count {
+            writer.start_entry("a", options.clone()).unwrap();
+        }
+        writer.start_entry("b", options).unwrap();
+        writer.write_all(b"Hello World!").unwrap();
+        let archive_data = writer.finish().into_result().unwrap();
+
+        let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        assert_eq!(archive.len(), count + 1);
+        let mut data = Vec::new();
+        archive
+            .by_index(count)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"Hello World!");
+    }
+
+    #[test]
+    fn encryption_works() {
+        let plain = (0..100_000)
+            .map(|i| (i % 7 * i % 251) as u8)
+            .collect::<Vec<_>>();
+        let mut writer = Writer::new(Vec::new());
+        let options = EntryOptions::new().password(b"secret".to_vec());
+        writer.start_entry("deflated.bin", options.clone()).unwrap();
+        writer.write_all(&plain).unwrap();
+        let options = options.compression_method(CompressionMethod::Stored);
+        writer.start_entry("stored.bin", options).unwrap();
+        writer.write_all(&plain).unwrap();
+        writer
+            .start_entry("plain.bin", EntryOptions::new())
+            .unwrap();
+        writer.write_all(&plain).unwrap();
+        let archive_data = writer.finish().into_result().unwrap();
+        assert!(!archive_data.windows(100).any(|w| w == &plain[..100]));
+
+        let mut archive = Archive::new(Cursor::new(&archive_data[..])).unwrap();
+        for i in 0..3 {
+            assert_eq!(archive.entries()[i].is_encrypted(), i < 2);
+            let mut data = Vec::new();
+            archive
+                .by_index_decrypt(i, b"secret")
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert_eq!(data, plain);
+        }
+        assert_eq!(
+            archive.entries()[1].compressed_size(),
+            plain.len() as u64 + 12
+        );
+
+        let error = archive.by_index(0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // The check byte in the encryption header matches a wrong password with the probability of 1/256
+        let result = archive
+            .by_index_decrypt(1, b"wrong")
+            .and_then(|mut r| r.read_to_end(&mut Vec::new()));
+        let error = result.unwrap_err();
+        let kind = crate::error::Error::from_io_error(&error).map(|e| e.kind());
+        assert!(matches!(
+            kind,
+            Some(ErrorKind::InvalidPassword) | Some(ErrorKind::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn writing_without_entry_fails() {
+        let mut writer = Writer::new(Vec::new());
+        assert!(writer.write_all(b"foo").is_err());
+        let options = EntryOptions::new().compression_method(CompressionMethod::Unknown(14));
+        assert!(writer.start_entry("foo", options).is_err());
+    }
+}
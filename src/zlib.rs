@@ -24,17 +24,28 @@
 //! ```
 use crate::checksum;
 use crate::deflate;
-use crate::finish::{Complete, Finish};
+use crate::error::{Error, ErrorKind};
+use crate::finish::{AutoFinish, Complete, Finish};
 use crate::lz77;
+use crate::options;
+#[cfg(not(feature = "no_std"))]
+use crate::parallel;
+use crate::read;
+use crate::util::{self, FinishState};
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
 #[cfg(feature = "no_std")]
 use core2::io;
 #[cfg(not(feature = "no_std"))]
 use std::io;
 
 const COMPRESSION_METHOD_DEFLATE: u8 = 8;
+const HEADER_SIZE: u64 = 2;
+const TRAILER_SIZE: u64 = 4;
 
 /// Compression levels defined by the ZLIB format.
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompressionLevel {
     /// Compressor used fastest algorithm.
     Fastest = 0,
@@ -49,7 +60,7 @@ pub enum CompressionLevel {
     Slowest = 3,
 }
 impl CompressionLevel {
-    fn from_u2(level: u8) -> Self {
+    pub(crate) fn from_u2(level: u8) -> Self {
         match level {
             0 => CompressionLevel::Fastest,
             1 => CompressionLevel::Fast,
@@ -75,6 +86,7 @@ impl From<lz77::CompressionLevel> for CompressionLevel {
 
 /// LZ77 Window sizes defined by the ZLIB format.
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 // TODO: Use `#[allow(clippy::upper_case_acronyms)]` instead once it gets available on the stable branch
 #[allow(clippy::all)]
 pub enum Lz77WindowSize {
@@ -103,7 +115,7 @@ pub enum Lz77WindowSize {
     KB32 = 7,
 }
 impl Lz77WindowSize {
-    fn from_u4(compression_info: u8) -> Option<Self> {
+    pub(crate) fn from_u4(compression_info: u8) -> Option<Self> {
         match compression_info {
             0 => Some(Lz77WindowSize::B256),
             1 => Some(Lz77WindowSize::B512),
@@ -188,6 +200,7 @@ impl Lz77WindowSize {
 ///
 /// [zlib]: https://www.zlib.net/
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FlushMode {
     /// `Z_NO_FLUSH` (default).
     ///
@@ -202,6 +215,7 @@ pub enum FlushMode {
 
 /// ZLIB header.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     window_size: Lz77WindowSize,
     compression_level: CompressionLevel,
@@ -215,6 +229,12 @@ impl Header {
     pub fn compression_level(&self) -> CompressionLevel {
         self.compression_level.clone()
     }
+    pub(crate) fn new(window_size: Lz77WindowSize, compression_level: CompressionLevel) -> Self {
+        Header {
+            window_size,
+            compression_level,
+        }
+    }
     fn from_lz77<E>(lz77: &E) -> Self
     where
         E: lz77::Lz77Encode,
@@ -233,36 +253,44 @@ impl Header {
         let (cmf, flg) = (buf[0], buf[1]);
         let check = (u16::from(cmf) << 8) + u16::from(flg);
         if check % 31 != 0 {
-            return Err(invalid_data_error!(
-                "Inconsistent ZLIB check bits: `CMF({}) * 256 + \
-                 FLG({})` must be a multiple of 31",
-                cmf,
-                flg
-            ));
+            return Err(Error::new(
+                ErrorKind::InvalidHeader,
+                "Inconsistent ZLIB check bits: `CMF * 256 + FLG` must be a multiple of 31",
+            )
+            .with_value(check)
+            .with_position(1)
+            .into());
         }
 
         let compression_method = cmf & 0b1111;
         let compression_info = cmf >> 4;
         if compression_method != COMPRESSION_METHOD_DEFLATE {
-            return Err(invalid_data_error!(
-                "Compression methods other than DEFLATE(8) are \
-                 unsupported: method={}",
-                compression_method
-            ));
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Compression methods other than DEFLATE(8) are unsupported",
+            )
+            .with_value(compression_method)
+            .with_position(0)
+            .into());
         }
         let window_size = Lz77WindowSize::from_u4(compression_info).ok_or_else(|| {
-            invalid_data_error!("CINFO above 7 are not allowed: value={}", compression_info)
+            Error::new(ErrorKind::InvalidHeader, "CINFO above 7 are not allowed")
+                .with_value(compression_info)
+                .with_expected(7u8)
+                .with_position(0)
         })?;
 
         let dict_flag = (flg & 0b10_0000) != 0;
         if dict_flag {
             let mut buf = [0; 4];
             reader.read_exact(&mut buf)?;
-            return Err(invalid_data_error!(
-                "Preset dictionaries are not supported: \
-                 dictionary_id=0x{:X}",
-                u32::from_be_bytes(buf)
-            ));
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Preset dictionaries are not supported",
+            )
+            .with_value(u32::from_be_bytes(buf))
+            .with_position(2)
+            .into());
         }
         let compression_level = CompressionLevel::from_u2(flg >> 6);
         Ok(Header {
@@ -270,7 +298,7 @@ impl Header {
             compression_level,
         })
     }
-    fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    pub(crate) fn write_to<W>(&self, mut writer: W) -> io::Result<()>
     where
         W: io::Write,
     {
@@ -290,8 +318,9 @@ impl Header {
 pub struct Decoder<R> {
     header: Header,
     reader: deflate::Decoder<R>,
-    adler32: checksum::Adler32,
+    adler32: checksum::StreamChecksum<checksum::Adler32>,
     eos: bool,
+    framing_size: u64,
 }
 impl<R> Decoder<R>
 where
@@ -318,16 +347,61 @@ where
     ///
     /// assert_eq!(buf, b"Hello World!");
     /// ```
-    pub fn new(mut inner: R) -> io::Result<Self> {
+    pub fn new(inner: R) -> io::Result<Self> {
+        Self::with_options(inner, deflate::DecodeOptions::new())
+    }
+
+    /// Makes a new decoder instance with the specified options.
+    ///
+    /// The ZLIB header has no variable length fields, so `options` mostly affects the DEFLATE stream.
+    /// If the window size declared by the header (CINFO) exceeds [`deflate::DecodeOptions::window_size`],
+    /// an [`ErrorKind::LimitExceeded`] error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// use libflate::deflate::DecodeOptions;
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    ///
+    /// let mut decoder = Decoder::with_options(&encoded_data[..], DecodeOptions::strict()).unwrap();
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, b"Hello World!");
+    /// ```
+    pub fn with_options(mut inner: R, options: deflate::DecodeOptions) -> io::Result<Self> {
         let header = Header::read_from(&mut inner)?;
+        if header.window_size.to_u16() > options.get_window_size() {
+            return Err(Error::new(
+                ErrorKind::LimitExceeded,
+                "The LZ77 window size of the ZLIB stream exceeds the configured one",
+            )
+            .with_value(header.window_size.to_u16())
+            .with_expected(options.get_window_size())
+            .with_position(0)
+            .into());
+        }
+        let pipelined = options.get_pipelined_checksum();
         Ok(Decoder {
             header,
-            reader: deflate::Decoder::new(inner),
-            adler32: checksum::Adler32::new(),
+            reader: deflate::Decoder::with_options(inner, options),
+            adler32: checksum::StreamChecksum::with_pipelining(checksum::Adler32::new(), pipelined),
             eos: false,
+            framing_size: HEADER_SIZE,
         })
     }
 
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn as_deflate_mut(&mut self) -> &mut deflate::Decoder<R> {
+        &mut self.reader
+    }
+
     /// Returns the header of the ZLIB stream.
     ///
     /// # Examples
@@ -345,6 +419,46 @@ where
         &self.header
     }
 
+    /// Returns the number of the (compressed) bytes read from the inner stream, including the header and the trailer.
+    ///
+    /// See [`deflate::Decoder::total_in`] for the details.
+    pub fn total_in(&self) -> u64 {
+        self.reader.total_in() + self.framing_size
+    }
+
+    /// Returns the number of the decoded bytes read from this decoder.
+    pub fn total_out(&self) -> u64 {
+        self.reader.total_out()
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this decoder (excluding the inner stream).
+    ///
+    /// See [`deflate::Decoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.reader.memory_usage()
+    }
+
+    /// Registers a callback which is invoked every time `interval` bytes of the DEFLATE stream have been read.
+    ///
+    /// Note that the bytes of the header and the trailer are not counted.
+    /// See [`deflate::Decoder::set_progress_callback`] for the details.
+    pub fn set_progress_callback<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        self.reader.set_progress_callback(interval, callback)
+    }
+
+    /// Registers a check which is invoked periodically to tell whether the decoding should be cancelled.
+    ///
+    /// See [`deflate::Decoder::set_cancellation_check`] for the details.
+    pub fn set_cancellation_check<F>(&mut self, is_cancelled: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.reader.set_cancellation_check(is_cancelled)
+    }
+
     /// Returns the immutable reference to the inner stream.
     pub fn as_inner_ref(&self) -> &R {
         self.reader.as_inner_ref()
@@ -357,6 +471,8 @@ where
 
     /// Unwraps this `Decoder`, returning the underlying reader.
     ///
+    /// If the end of the stream has been reached, the reader is positioned just after the stream.
+    ///
     /// # Examples
     /// ```
     /// #[cfg(not(feature = "no_std"))]
@@ -381,6 +497,114 @@ where
     pub fn unread_decoded_data(&self) -> &[u8] {
         self.reader.unread_decoded_data()
     }
+
+    /// Decodes exactly `buf.len()` bytes into `buf` (e.g., the image data of PNG, whose size is known).
+    ///
+    /// The ADLER32 trailer is not read by this method, but by the next read which reaches the end of the stream.
+    /// See [`deflate::Decoder::decode_exact`] for the details.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// let mut buf = [0; 12];
+    /// decoder.decode_exact(&mut buf).unwrap();
+    /// assert_eq!(&buf, b"Hello World!");
+    ///
+    /// // Verifies the trailer
+    /// assert_eq!(decoder.read(&mut [0; 1]).unwrap(), 0);
+    /// ```
+    pub fn decode_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if self.eos && !buf.is_empty() {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "The ZLIB stream ended before the requested size of data were decoded",
+            )
+            .with_value(0u64)
+            .with_expected(buf.len() as u64)
+            .into());
+        }
+        self.reader.decode_exact(buf)?;
+        self.adler32.update(buf);
+        Ok(())
+    }
+
+    /// Discards the next `n` decoded bytes without copying them.
+    ///
+    /// The data are decoded into the internal buffer of the decoder only, so no output buffer is allocated.
+    /// Returns the number of the discarded bytes, which is less than `n` only if the end of the stream is reached.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Read;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Read;
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// assert_eq!(decoder.skip(6).unwrap(), 6);
+    /// let mut buf = Vec::new();
+    /// decoder.read_to_end(&mut buf).unwrap();
+    /// assert_eq!(buf, b"World!");
+    /// ```
+    pub fn skip(&mut self, n: u64) -> io::Result<u64> {
+        util::skip(self, n)
+    }
+
+    /// Returns an iterator which yields the decoded data in chunks of `chunk_size` bytes.
+    ///
+    /// See [`read::Chunks`] for the details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::zlib::Decoder;
+    ///
+    /// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+    ///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+    /// let mut decoder = Decoder::new(&encoded_data[..]).unwrap();
+    /// let chunks = decoder.chunks(5).collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(chunks, [&b"Hello"[..], b" Worl", b"d!"]);
+    /// ```
+    pub fn chunks(&mut self, chunk_size: usize) -> read::Chunks<&mut Self> {
+        read::Chunks::new(self, chunk_size)
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        self.eos = true;
+        let mut buf = [0; 4];
+        self.reader.as_inner_mut().read_exact(&mut buf)?;
+        self.framing_size += TRAILER_SIZE;
+        let adler32 = u32::from_be_bytes(buf);
+
+        // checksum verification is skipped during fuzzing
+        // so that random data from fuzzer can reach actually interesting code
+        // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
+        let actual = self.adler32.value()?;
+        if cfg!(not(fuzzing)) && adler32 != actual {
+            Err(
+                Error::new(ErrorKind::ChecksumMismatch, "Adler32 checksum mismatched")
+                    .with_value(actual)
+                    .with_expected(adler32)
+                    .into(),
+            )
+        } else {
+            Ok(())
+        }
+    }
 }
 impl<R> io::Read for Decoder<R>
 where
@@ -392,33 +616,98 @@ where
         } else {
             let read_size = self.reader.read(buf)?;
             if read_size == 0 {
-                if buf.is_empty() {
-                    return Ok(0);
-                }
-
-                self.eos = true;
-                let mut buf = [0; 4];
-                self.reader.as_inner_mut().read_exact(&mut buf)?;
-                let adler32 = u32::from_be_bytes(buf);
-
-                // checksum verification is skipped during fuzzing
-                // so that random data from fuzzer can reach actually interesting code
-                // Compilation flag 'fuzzing' is automatically set by all 3 Rust fuzzers.
-                if cfg!(not(fuzzing)) && adler32 != self.adler32.value() {
-                    Err(invalid_data_error!(
-                        "Adler32 checksum mismatched: value={}, expected={}",
-                        self.adler32.value(),
-                        adler32
-                    ))
-                } else {
-                    Ok(0)
+                if !buf.is_empty() {
+                    self.read_trailer()?;
                 }
+                Ok(0)
             } else {
                 self.adler32.update(&buf[..read_size]);
                 Ok(read_size)
             }
         }
     }
+
+    /// Fills `bufs` in order.
+    ///
+    /// See [`deflate::Decoder`]'s implementation for the details.
+    #[cfg(not(feature = "no_std"))]
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        util::read_vectored(self, bufs, |this| this.reader.has_buffered_data())
+    }
+}
+
+impl<R> util::Rewind for Decoder<R>
+where
+    R: io::Read + io::Seek,
+{
+    fn position(&self) -> u64 {
+        self.total_out()
+    }
+    fn rewind(&mut self) -> io::Result<()> {
+        let read = self.total_in();
+        let start = util::stream_start(self.reader.as_inner_mut(), read)?;
+        self.reader.rewind_to(start + HEADER_SIZE)?;
+        self.adler32.reset();
+        self.eos = false;
+        self.framing_size = HEADER_SIZE;
+        Ok(())
+    }
+}
+
+/// Seeks in the decoded data.
+///
+/// Seeking backward rewinds the inner stream to the start of the DEFLATE stream (just after the ZLIB header).
+/// See [`deflate::Decoder`]'s implementation for the details.
+///
+/// # Examples
+/// ```
+/// #[cfg(feature = "no_std")]
+/// use core2::io::{Cursor, Read, Seek, SeekFrom};
+/// #[cfg(not(feature = "no_std"))]
+/// use std::io::{Cursor, Read, Seek, SeekFrom};
+/// use libflate::zlib::Decoder;
+///
+/// let encoded_data = [120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47,
+///                     202, 73, 81, 4, 0, 28, 73, 4, 62];
+/// let mut decoder = Decoder::new(Cursor::new(&encoded_data[..])).unwrap();
+///
+/// let mut buf = Vec::new();
+/// decoder.read_to_end(&mut buf).unwrap();
+/// assert_eq!(decoder.seek(SeekFrom::Current(-6)).unwrap(), 6);
+/// buf.clear();
+/// decoder.read_to_end(&mut buf).unwrap();
+/// assert_eq!(buf, b"World!");
+/// ```
+impl<R> io::Seek for Decoder<R>
+where
+    R: io::Read + io::Seek,
+{
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        util::seek(self, pos)
+    }
+}
+
+/// Exposes the buffer of the decoded data, so that they can be processed without copying.
+///
+/// The ADLER32 checksum is verified when [`BufRead::fill_buf`](io::BufRead::fill_buf)
+/// reaches the end of the stream.
+impl<R> io::BufRead for Decoder<R>
+where
+    R: io::Read,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.eos && self.reader.fill_buf()?.is_empty() {
+            self.read_trailer()?;
+        }
+        Ok(self.reader.unread_decoded_data())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let buffered = self.reader.unread_decoded_data();
+        let amt = core::cmp::min(amt, buffered.len());
+        self.adler32.update(&buffered[..amt]);
+        self.reader.consume(amt);
+    }
 }
 
 /// Options for a ZLIB encoder.
@@ -453,6 +742,51 @@ impl EncodeOptions<lz77::DefaultLz77Encoder> {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Makes an instance suited to compress the image data of a PNG image (the contents of its IDAT chunks).
+    ///
+    /// `scanline_size` is the size of a filtered scanline in bytes (including the leading filter type byte),
+    /// and `height` is the number of the scanlines.
+    ///
+    /// The window size is the smallest one covering the whole image data (up to 32 KiB),
+    /// which saves the memory of the encoder and the decoders of small images,
+    /// and the strategy is [`options::Strategy::Filtered`].
+    ///
+    /// If `rows_per_flush` is `Some(n)`, a sync flush is made after every `n` scanlines,
+    /// so that the compressed data of each group of scanlines can be emitted (e.g., as an IDAT chunk)
+    /// as soon as the group is written.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::zlib::{Encoder, EncodeOptions, Lz77WindowSize};
+    ///
+    /// // A 16x16 RGB image
+    /// let scanline_size = 1 + 16 * 3;
+    /// let options = EncodeOptions::png(scanline_size, 16, Some(4));
+    /// let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// assert_eq!(encoder.header().window_size(), Lz77WindowSize::KB1);
+    ///
+    /// for _ in 0..16 {
+    ///     encoder.write_all(&[0; 1 + 16 * 3]).unwrap();
+    /// }
+    /// let idat = encoder.finish().into_result().unwrap();
+    /// ```
+    pub fn png(scanline_size: usize, height: usize, rows_per_flush: Option<usize>) -> Self {
+        let data_size = scanline_size.saturating_mul(height);
+        let window_size = core::cmp::min(data_size, lz77::MAX_WINDOW_SIZE as usize) as u16;
+        let options = options::CompressionOptions::new()
+            .strategy(options::Strategy::Filtered)
+            .window_size(core::cmp::max(window_size, Lz77WindowSize::B256.to_u16()));
+        let options = Self::from(options);
+        match rows_per_flush {
+            Some(rows) => options.sync_flush_interval(scanline_size.saturating_mul(rows) as u64),
+            None => options,
+        }
+    }
 }
 impl<E> EncodeOptions<E>
 where
@@ -476,6 +810,20 @@ where
         }
     }
 
+    /// Makes an instance whose DEFLATE stream is encoded with `options`.
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn from_deflate_options(options: deflate::EncodeOptions<E>) -> Self {
+        let header = match options.get_lz77() {
+            Some(lz77) => Header::from_lz77(lz77),
+            None => Header::new(Lz77WindowSize::KB32, CompressionLevel::Fastest),
+        };
+        EncodeOptions {
+            header,
+            options,
+            flush_mode: FlushMode::None,
+        }
+    }
+
     /// Disables LZ77 compression.
     ///
     /// # Example
@@ -508,6 +856,64 @@ where
         self
     }
 
+    /// Enables the adaptive block size mode.
+    ///
+    /// See [`deflate::EncodeOptions::adaptive_block_size`] for the details.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::zlib::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().adaptive_block_size();
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    pub fn adaptive_block_size(mut self) -> Self {
+        self.options = self.options.adaptive_block_size();
+        self
+    }
+
+    /// Enables the reuse of the dynamic Huffman codes of the previous block.
+    ///
+    /// See [`deflate::EncodeOptions::reuse_huffman_codes`] for the details.
+    pub fn reuse_huffman_codes(mut self) -> Self {
+        self.options = self.options.reuse_huffman_codes();
+        self
+    }
+
+    /// Specifies the Huffman code lengths used for all blocks.
+    ///
+    /// See [`deflate::EncodeOptions::huffman_tables`] for the details.
+    pub fn huffman_tables(mut self, tables: deflate::HuffmanTables) -> Self {
+        self.options = self.options.huffman_tables(tables);
+        self
+    }
+
+    /// Makes the encoder emit a sync flush after every `bytes` bytes of uncompressed data.
+    ///
+    /// See [`deflate::EncodeOptions::sync_flush_interval`] for the details.
+    pub fn sync_flush_interval(mut self, bytes: u64) -> Self {
+        self.options = self.options.sync_flush_interval(bytes);
+        self
+    }
+
+    /// Makes the encoder emit a sync flush after each newline once at least `min_bytes` bytes have been written since the last flush.
+    ///
+    /// See [`deflate::EncodeOptions::line_flush`] for the details.
+    pub fn line_flush(mut self, min_bytes: u64) -> Self {
+        self.options = self.options.line_flush(min_bytes);
+        self
+    }
+
+    /// Specifies the size of the buffer in which the I/O adapters stage the data around the encoder.
+    ///
+    /// See [`deflate::EncodeOptions::buffer_size`] for the details.
+    ///
+    /// The default value is `deflate::DEFAULT_BUFFER_SIZE`.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.options = self.options.buffer_size(size);
+        self
+    }
+
     /// Specifies to compress with fixed huffman codes.
     ///
     /// # Example
@@ -522,11 +928,61 @@ where
         self
     }
 
+    /// Specifies the number of worker threads used to compress input data.
+    ///
+    /// See the [`parallel`] module for the details.
+    ///
+    /// # Example
+    /// ```
+    /// use libflate::zlib::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().threads(4);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options = self.options.threads(threads);
+        self
+    }
+
+    /// Specifies whether the encoder computes the checksum of the input data on a worker thread.
+    ///
+    /// See [`deflate::EncodeOptions::pipelined_checksum`] for the details.
+    ///
+    /// # Examples
+    /// ```
+    /// use libflate::zlib::{Encoder, EncodeOptions};
+    ///
+    /// let options = EncodeOptions::new().pipelined_checksum(true);
+    /// let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+    /// ```
+    #[cfg(not(feature = "no_std"))]
+    pub fn pipelined_checksum(mut self, pipelined: bool) -> Self {
+        self.options = self.options.pipelined_checksum(pipelined);
+        self
+    }
+
     /// Specifies flush mode.
     pub fn flush_mode(mut self, mode: FlushMode) -> Self {
         self.flush_mode = mode;
         self
     }
+
+    pub(crate) fn get_buffer_size(&self) -> usize {
+        self.options.get_buffer_size()
+    }
+}
+
+/// The state of an [`Encoder`].
+///
+/// See [`Encoder::state`] for the details.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncoderState<E = lz77::DefaultLz77Encoder> {
+    header: Header,
+    flush_mode: FlushMode,
+    adler32: u32,
+    deflate: deflate::EncoderState<E>,
 }
 
 /// ZLIB encoder.
@@ -535,7 +991,9 @@ pub struct Encoder<W, E = lz77::DefaultLz77Encoder> {
     header: Header,
     flush_mode: FlushMode,
     writer: deflate::Encoder<W, E>,
-    adler32: checksum::Adler32,
+    adler32: checksum::StreamChecksum<checksum::Adler32>,
+    finish_state: FinishState,
+    framing_size: u64,
 }
 impl<W> Encoder<W, lz77::DefaultLz77Encoder>
 where
@@ -591,14 +1049,33 @@ where
     /// ```
     pub fn with_options(mut inner: W, options: EncodeOptions<E>) -> io::Result<Self> {
         options.header.write_to(&mut inner)?;
+        let pipelined = options.options.get_pipelined_checksum();
         Ok(Encoder {
             header: options.header,
             flush_mode: options.flush_mode,
+            #[cfg(not(feature = "no_std"))]
+            writer: deflate::Encoder::with_checksum(
+                inner,
+                options.options,
+                parallel::ChecksumKind::Adler32,
+            ),
+            #[cfg(feature = "no_std")]
             writer: deflate::Encoder::with_options(inner, options.options),
-            adler32: checksum::Adler32::new(),
+            adler32: checksum::StreamChecksum::with_pipelining(checksum::Adler32::new(), pipelined),
+            finish_state: FinishState::default(),
+            framing_size: HEADER_SIZE,
         })
     }
 
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn as_deflate_mut(&mut self) -> &mut deflate::Encoder<W, E> {
+        &mut self.writer
+    }
+    #[cfg(not(feature = "no_std"))]
+    pub(crate) fn into_deflate(self) -> deflate::Encoder<W, E> {
+        self.writer
+    }
+
     /// Returns the header of the ZLIB stream.
     ///
     /// # Examples
@@ -648,14 +1125,163 @@ where
     /// let mut encoder = AutoFinishUnchecked::new(Encoder::new(&mut buf).unwrap());
     /// encoder.write_all(plain.as_ref()).unwrap();
     /// ```
-    pub fn finish(self) -> Finish<W, io::Error> {
-        let mut inner = finish_try!(self.writer.finish());
-        match inner
-            .write_all(&self.adler32.value().to_be_bytes())
-            .and_then(|_| inner.flush())
-        {
-            Ok(_) => Finish::new(inner, None),
-            Err(e) => Finish::new(inner, Some(e)),
+    pub fn finish(mut self) -> Finish<W, io::Error> {
+        let result = self.try_finish();
+        Finish::new(self.into_inner(), result.err())
+    }
+
+    /// Writes the rest of the ZLIB stream (including the ADLER32 trailer), and flushes the inner stream.
+    ///
+    /// Unlike [`Encoder::finish`], this does not consume the encoder.
+    /// This method is idempotent: the second and later calls return the result of the first call
+    /// without writing anything. Writing to the encoder after this call fails.
+    /// See [`deflate::Encoder::try_finish`] for the retry of the final flush.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// use libflate::zlib::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// encoder.try_finish().unwrap();
+    /// encoder.try_finish().unwrap();
+    ///
+    /// assert_eq!(encoder.into_inner(),
+    ///            [120, 156, 5, 192, 49, 13, 0, 0, 8, 3, 65, 43, 224, 6, 7, 24, 128,
+    ///             237, 147, 38, 245, 63, 244, 230, 65, 181, 50, 215, 1, 28, 73, 4, 62]);
+    /// ```
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        if let Some(result) = self.finish_state.previous_result() {
+            return result;
+        }
+        if !self.finish_state.is_finished() {
+            let result = self.finish_stream();
+            self.finish_state.record_end(&result);
+            result?;
+        }
+        let result = util::retry_interrupted(|| self.writer.as_inner_mut().flush());
+        self.finish_state.record_flush(&result);
+        result
+    }
+
+    fn finish_stream(&mut self) -> io::Result<()> {
+        self.writer.write_end_of_stream()?;
+        let adler32 = match self.writer.parallel_checksum() {
+            Some(adler32) => adler32,
+            None => self.adler32.value()?,
+        };
+        let inner = self.writer.as_inner_mut();
+        inner.write_all(&adler32.to_be_bytes())?;
+        self.framing_size += TRAILER_SIZE;
+        Ok(())
+    }
+
+    /// Returns the number of the (uncompressed) bytes written to this encoder.
+    pub fn total_in(&self) -> u64 {
+        self.writer.total_in()
+    }
+
+    /// Returns the number of the (compressed) bytes written to the inner stream, including the header and the trailer.
+    ///
+    /// For an encoder made by [`Encoder::from_state`], the bytes written before the suspension are not counted.
+    /// See [`deflate::Encoder::total_out`] for the details.
+    pub fn total_out(&self) -> u64 {
+        self.writer.total_out() + self.framing_size
+    }
+
+    /// Returns the number of bytes currently allocated on the heap by this encoder (excluding the inner stream).
+    ///
+    /// See [`deflate::Encoder::memory_usage`] for the details.
+    pub fn memory_usage(&self) -> usize {
+        self.writer.memory_usage()
+    }
+
+    /// Registers a callback which is invoked every time `interval` bytes of input data have been written.
+    ///
+    /// Note that the bytes of the header and the trailer are not counted.
+    /// See [`deflate::Encoder::set_progress_callback`] for the details.
+    pub fn set_progress_callback<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(u64, u64) + Send + Sync + 'static,
+    {
+        self.writer.set_progress_callback(interval, callback)
+    }
+
+    /// Registers a callback which is invoked with the information of each DEFLATE block after it is written.
+    ///
+    /// See [`deflate::Encoder::set_block_callback`] for the details.
+    pub fn set_block_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&deflate::BlockInfo) + Send + Sync + 'static,
+    {
+        self.writer.set_block_callback(callback)
+    }
+
+    /// Registers a check which is invoked periodically to tell whether the encoding should be cancelled.
+    ///
+    /// See [`deflate::Encoder::set_cancellation_check`] for the details.
+    pub fn set_cancellation_check<F>(&mut self, is_cancelled: F)
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.writer.set_cancellation_check(is_cancelled)
+    }
+
+    /// Ends the current message, i.e., makes all the data written so far decodable (by a sync flush).
+    ///
+    /// See [`deflate::Encoder::message_boundary`] for the details.
+    pub fn message_boundary(&mut self) -> io::Result<()> {
+        self.writer.message_boundary()
+    }
+
+    /// Changes the compression level of the subsequent data.
+    ///
+    /// See [`deflate::Encoder::set_level`] for the details.
+    /// Note that the level recorded in the header is not changed.
+    pub fn set_level(&mut self, level: lz77::CompressionLevel) -> io::Result<()> {
+        self.writer.set_level(level)
+    }
+
+    /// Changes the strategy to encode the subsequent blocks.
+    ///
+    /// See [`deflate::Encoder::set_strategy`] for the details.
+    pub fn set_strategy(&mut self, strategy: options::Strategy) -> io::Result<()> {
+        self.writer.set_strategy(strategy)
+    }
+
+    /// Returns the current state of this encoder (including the running checksum).
+    ///
+    /// See [`deflate::Encoder::state`] for the details.
+    pub fn state(&self) -> io::Result<EncoderState<E>>
+    where
+        E: Clone,
+    {
+        Ok(EncoderState {
+            header: self.header.clone(),
+            flush_mode: self.flush_mode,
+            adler32: self
+                .adler32
+                .peek()
+                .ok_or_else(util::pipelined_checksum_state_error)?,
+            deflate: self.writer.state()?,
+        })
+    }
+
+    /// Makes an encoder instance which resumes the encoding from `state`.
+    ///
+    /// The ZLIB header is not written again.
+    pub fn from_state(inner: W, state: EncoderState<E>) -> Self {
+        Encoder {
+            header: state.header,
+            flush_mode: state.flush_mode,
+            writer: deflate::Encoder::from_state(inner, state.deflate),
+            adler32: checksum::StreamChecksum::new(checksum::Adler32::from_value(state.adler32)),
+            finish_state: FinishState::default(),
+            framing_size: 0,
         }
     }
 
@@ -669,10 +1295,73 @@ where
         self.writer.as_inner_mut()
     }
 
+    /// Wraps this encoder with [`AutoFinish`], which finishes the encoding when it is dropped.
+    ///
+    /// This prevents a stream from being silently truncated because of a missing call of [`Encoder::finish`].
+    /// If finishing the encoding fails, the drop panics.
+    ///
+    /// To recover the inner stream (or to handle the finishing error),
+    /// call [`AutoFinish::into_inner`] and then [`Encoder::finish`] instead of dropping the wrapper.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::zlib::Encoder;
+    ///
+    /// let mut buf = Vec::new();
+    /// {
+    ///     let mut encoder = Encoder::new(&mut buf).unwrap().auto_finish();
+    ///     encoder.write_all(b"Hello World!").unwrap();
+    /// } // The encoding is finished here
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap().auto_finish();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let encoded_data = encoder.into_inner().finish().into_result().unwrap();
+    /// assert_eq!(encoded_data, buf);
+    /// ```
+    pub fn auto_finish(self) -> AutoFinish<Self> {
+        AutoFinish::new(self)
+    }
+
     /// Unwraps the `Encoder`, returning the inner stream.
+    ///
+    /// Nothing is written to the inner stream by this method (see [`Encoder::abort`]).
     pub fn into_inner(self) -> W {
         self.writer.into_inner()
     }
+
+    /// Aborts the encoding, returning the inner stream.
+    ///
+    /// The buffered input data are discarded, and the final block and the ADLER32 trailer are not written,
+    /// so the data written to the inner stream so far do not form a complete stream.
+    /// This is useful to reclaim the inner stream (e.g., a socket) when the encoding is cancelled midway.
+    ///
+    /// # Examples
+    /// ```
+    /// #[cfg(feature = "no_std")]
+    /// use core2::io::Write;
+    /// #[cfg(not(feature = "no_std"))]
+    /// use std::io::Write;
+    /// use libflate::zlib::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// let written = encoder.abort();
+    ///
+    /// let mut encoder = Encoder::new(Vec::new()).unwrap();
+    /// encoder.write_all(b"Hello World!").unwrap();
+    /// assert!(encoder.finish().into_result().unwrap().len() > written.len());
+    /// ```
+    pub fn abort(self) -> W {
+        self.into_inner()
+    }
+
+    pub(crate) fn sync_flush(&mut self) -> io::Result<()> {
+        self.writer.zlib_sync_flush()
+    }
 }
 impl<W, E> io::Write for Encoder<W, E>
 where
@@ -681,7 +1370,26 @@ where
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let written_size = self.writer.write(buf)?;
-        self.adler32.update(&buf[..written_size]);
+        if !self.writer.is_parallel() {
+            self.adler32.update(&buf[..written_size]);
+        }
+        Ok(written_size)
+    }
+
+    /// Writes all the data of `bufs`.
+    ///
+    /// See [`deflate::Encoder`]'s implementation for the details.
+    #[cfg(not(feature = "no_std"))]
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let written_size = self.writer.write_vectored(bufs)?;
+        if !self.writer.is_parallel() {
+            let mut rest = written_size;
+            for buf in bufs {
+                let size = buf.len().min(rest);
+                self.adler32.update(&buf[..size]);
+                rest -= size;
+            }
+        }
         Ok(written_size)
     }
     fn flush(&mut self) -> io::Result<()> {
@@ -701,6 +1409,77 @@ where
     }
 }
 
+/// Returns an upper bound of the size of the ZLIB stream made from `input_len` bytes,
+/// including the header (2 bytes) and the trailer (4 bytes).
+///
+/// See [`deflate::max_compressed_len`] for the conditions.
+///
+/// # Examples
+/// ```
+/// use libflate::zlib;
+///
+/// let encoded_data = zlib::compress_to_vec(&[0; 1000]).unwrap();
+/// assert!(encoded_data.len() <= zlib::max_compressed_len(1000));
+/// ```
+pub fn max_compressed_len(input_len: usize) -> usize {
+    deflate::max_compressed_len(input_len).saturating_add((HEADER_SIZE + TRAILER_SIZE) as usize)
+}
+
+/// Compresses `input` into a ZLIB stream with the default options.
+///
+/// The capacity of the returned vector is reserved in advance from the worst case of the compressed size,
+/// so the vector is never reallocated.
+/// As with [`deflate::compress_to_vec`], the buffers of the encoder are reused across the calls on the same thread.
+///
+/// # Examples
+/// ```
+/// use libflate::zlib;
+///
+/// let encoded_data = zlib::compress_to_vec(b"Hello World!").unwrap();
+/// assert_eq!(zlib::decompress_to_vec(&encoded_data).unwrap(), b"Hello World!");
+/// ```
+pub fn compress_to_vec(input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    util::try_reserve(&mut buf, max_compressed_len(input.len()))?;
+    #[cfg(not(feature = "no_std"))]
+    {
+        crate::pool::with_local_pool(|pool| {
+            let mut encoder = pool.zlib_encoder(buf)?;
+            io::Write::write_all(&mut encoder, input)?;
+            encoder.finish().into_result()
+        })
+    }
+    #[cfg(feature = "no_std")]
+    {
+        let mut encoder = Encoder::new(buf)?;
+        io::Write::write_all(&mut encoder, input)?;
+        encoder.finish().into_result()
+    }
+}
+
+/// Decompresses the ZLIB stream `input`.
+///
+/// The capacity of the returned vector is reserved in advance from the size of `input`.
+/// Use [`decompress_to_vec_with_capacity`] if the size of the decompressed data is (roughly) known.
+pub fn decompress_to_vec(input: &[u8]) -> io::Result<Vec<u8>> {
+    decompress_to_vec_with_capacity(input, util::decompressed_capacity_hint(input.len()))
+}
+
+/// Decompresses the ZLIB stream `input`, reserving `capacity` bytes for the decompressed data in advance.
+///
+/// The vector grows as usual if `capacity` is insufficient.
+/// As with [`deflate::decompress_to_vec_with_capacity`], the sliding window is reused across the calls on the same thread.
+pub fn decompress_to_vec_with_capacity(input: &[u8], capacity: usize) -> io::Result<Vec<u8>> {
+    #[cfg(not(feature = "no_std"))]
+    {
+        crate::pool::with_local_pool(|pool| util::read_to_vec(pool.zlib_decoder(input)?, capacity))
+    }
+    #[cfg(feature = "no_std")]
+    {
+        util::read_to_vec(Decoder::new(input)?, capacity)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -869,7 +1648,7 @@ mod tests {
         let mut encoder = Encoder::new(Vec::new()).unwrap();
         for _ in 0..2 {
             for string in &writes {
-                encoder.write(string.as_bytes()).expect("Write failed");
+                encoder.write_all(string.as_bytes()).expect("Write failed");
             }
             encoder.flush().expect("Flush failed");
         }
@@ -898,7 +1677,7 @@ mod tests {
                 .unwrap();
         for _ in 0..2 {
             for string in &writes {
-                encoder.write(string.as_bytes()).expect("Write failed");
+                encoder.write_all(string.as_bytes()).expect("Write failed");
             }
             encoder.flush().expect("Flush failed");
         }
@@ -931,11 +1710,75 @@ mod tests {
         let data = default_encode(b"Hello World").unwrap();
         let mut decoder = Decoder::new(&data[..]).unwrap();
         let mut buf = Vec::new();
-        decoder.read(&mut buf).unwrap();
+        assert_eq!(decoder.read(&mut buf).unwrap(), 0);
         decoder.read_to_end(&mut buf).unwrap();
         assert_eq!(buf, b"Hello World");
     }
 
+    #[test]
+    fn png_options_work() {
+        // Sub-filtered scanlines of a gradient image
+        let (scanline_size, height) = (1 + 300 * 3, 200);
+        let image = (0..height)
+            .flat_map(|y| {
+                let row = (0..scanline_size - 1).map(move |x| ((x * 7 + y * 13) % 5) as u8);
+                Some(1).into_iter().chain(row)
+            })
+            .collect::<Vec<_>>();
+
+        let options = EncodeOptions::png(scanline_size, height, Some(50));
+        let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        assert_eq!(encoder.header().window_size(), Lz77WindowSize::KB32);
+        for scanline in image.chunks(scanline_size) {
+            encoder.write_all(scanline).unwrap();
+        }
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(
+            encoded
+                .windows(4)
+                .filter(|x| *x == [0, 0, 0xFF, 0xFF])
+                .count(),
+            4
+        );
+        assert_eq!(decode_all(&encoded).unwrap(), image);
+
+        let options = EncodeOptions::png(scanline_size, 2, None);
+        let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        assert_eq!(encoder.header().window_size(), Lz77WindowSize::KB2);
+        let options = EncodeOptions::png(0, 0, None);
+        let encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        assert_eq!(encoder.header().window_size(), Lz77WindowSize::B256);
+    }
+
+    #[test]
+    fn window_size_exceeding_options_is_rejected() {
+        use crate::error::Error;
+
+        // CINFO=7 (32 KiB)
+        let encoded_data = [
+            120, 156, 243, 72, 205, 201, 201, 87, 8, 207, 47, 202, 73, 81, 4, 0, 28, 73, 4, 62,
+        ];
+        let options = deflate::DecodeOptions::new().window_size(2048);
+        let error = Decoder::with_options(&encoded_data[..], options.clone()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        #[cfg(not(feature = "no_std"))]
+        assert_eq!(
+            Error::from_io_error(&error).map(|e| (e.kind(), e.value())),
+            Some((ErrorKind::LimitExceeded, Some(32768)))
+        );
+
+        // CINFO=3 (2 KiB)
+        let lz77 = lz77::DefaultLz77Encoder::with_window_size(2048);
+        let mut encoder =
+            Encoder::with_options(Vec::new(), EncodeOptions::with_lz77(lz77)).unwrap();
+        encoder.write_all(b"Hello World!").unwrap();
+        let encoded_data = encoder.finish().into_result().unwrap();
+        let mut decoder = Decoder::with_options(&encoded_data[..], options).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"Hello World!");
+    }
+
     #[test]
     fn issue71() {
         let encoded_data = [
@@ -955,4 +1798,122 @@ mod tests {
         ];
         assert_eq!(buf, decoded_data);
     }
+
+    #[test]
+    fn try_finish_works() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut options = vec![EncodeOptions::new()];
+        #[cfg(not(feature = "no_std"))]
+        options.push(EncodeOptions::new().threads(4));
+        for options in options {
+            let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+            encoder.write_all(&plain).unwrap();
+            encoder.try_finish().unwrap();
+            encoder.try_finish().unwrap();
+            assert!(encoder.write_all(b"more").is_err());
+            encoder.flush().unwrap();
+            let encoded = encoder.finish().into_result().unwrap();
+            assert_eq!(decode_all(&encoded).unwrap(), plain);
+        }
+
+        // The failure of the first attempt is reported again
+        let mut buf = [0; 10];
+        let mut encoder = Encoder::new(&mut buf[..]).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let error = encoder.try_finish().unwrap_err();
+        assert_eq!(encoder.try_finish().unwrap_err().kind(), error.kind());
+
+        // A transient failure of the final flush is retried by the next attempt
+        struct WouldBlockOnFlush(Vec<u8>, bool);
+        impl io::Write for WouldBlockOnFlush {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                if !self.1 {
+                    self.1 = true;
+                    return Err(io::Error::new(io::ErrorKind::WouldBlock, "would block"));
+                }
+                Ok(())
+            }
+        }
+        let mut encoder = Encoder::new(WouldBlockOnFlush(Vec::new(), false)).unwrap();
+        encoder.write_all(&plain).unwrap();
+        let error = encoder.try_finish().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        assert!(encoder.write_all(b"more").is_err());
+        encoder.try_finish().unwrap();
+        encoder.try_finish().unwrap();
+        let encoded = encoder.into_inner().0;
+        assert_eq!(decode_all(&encoded).unwrap(), plain);
+    }
+
+    #[test]
+    fn abort_works() {
+        let plain = (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut options = vec![EncodeOptions::new()];
+        #[cfg(not(feature = "no_std"))]
+        options.push(EncodeOptions::new().threads(4));
+        for options in options {
+            let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+            encoder.write_all(&plain).unwrap();
+            let aborted = encoder.abort();
+            let error = decode_all(&aborted).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn pipelined_checksum_works() {
+        let plain = (0..1_000_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let mut encoder = Encoder::new(Vec::new()).unwrap();
+        for chunk in plain.chunks(10_000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let expected = encoder.finish().into_result().unwrap();
+
+        let options = EncodeOptions::new().pipelined_checksum(true);
+        let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        for chunk in plain.chunks(10_000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let encoded = encoder.finish().into_result().unwrap();
+        assert_eq!(encoded, expected);
+
+        let options = deflate::DecodeOptions::new().pipelined_checksum(true);
+        let mut decoder = Decoder::with_options(&encoded[..], options.clone()).unwrap();
+        let mut buf = Vec::new();
+        decoder.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, plain);
+
+        let mut corrupted = encoded;
+        *corrupted.last_mut().unwrap() ^= 1;
+        let mut decoder = Decoder::with_options(&corrupted[..], options).unwrap();
+        let error = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(
+            Error::from_io_error(&error).unwrap().kind(),
+            ErrorKind::ChecksumMismatch
+        );
+
+        // The state can not be taken while the checksum is computed on the worker thread
+        let options = EncodeOptions::new().pipelined_checksum(true);
+        let mut encoder = Encoder::with_options(Vec::new(), options).unwrap();
+        encoder.write_all(&plain[..1000]).unwrap();
+        assert!(encoder.state().is_err());
+    }
+
+    #[test]
+    fn no_bytes_are_read_past_the_trailer() {
+        let mut encoded = default_encode(b"Hello World!").unwrap();
+        encoded.extend_from_slice(b"trailing");
+
+        let mut decoder = Decoder::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, b"Hello World!");
+        assert_eq!(decoder.into_inner(), b"trailing");
+    }
 }